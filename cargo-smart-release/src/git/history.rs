@@ -45,7 +45,7 @@ pub fn collect(repo: &git::Repository) -> anyhow::Result<Option<commit::History>
         .sorting(git::traverse::commit::Sorting::ByCommitTimeNewestFirst)
         .all()?
     {
-        let commit_id = commit_id?;
+        let commit_id = commit_id?.id;
         let (message, tree_id, parent_tree_id, commit_time) = {
             let (message, tree_id, commit_time, parent_commit_id) = {
                 let object = commit_id.object()?;
@@ -211,11 +211,11 @@ fn add_item_if_package_changed<'a>(
     match filter {
         Filter::None => history.push(item),
         Filter::Fast(comp) => {
-            let current = git::objs::TreeRefIter::from_bytes(&data_by_tree_id[&item.tree_id])
+            let current = git::objs::TreeRefIter::from_bytes(&data_by_tree_id[&item.tree_id], ctx.repo.object_hash())
                 .filter_map(Result::ok)
                 .find(|e| e.filename == comp);
             let parent = item.parent_tree_id.and_then(|parent| {
-                git::objs::TreeRefIter::from_bytes(&data_by_tree_id[&parent])
+                git::objs::TreeRefIter::from_bytes(&data_by_tree_id[&parent], ctx.repo.object_hash())
                     .filter_map(Result::ok)
                     .find(|e| e.filename == comp)
             });
@@ -21,7 +21,7 @@ pub mod name;
 mod state;
 
 mod match_group;
-pub use match_group::{Attributes, Ignore, Match, Pattern};
+pub use match_group::{Attributes, Ignore, Match, Pattern, Value};
 
 ///
 pub mod parse;
@@ -50,10 +50,12 @@ impl Pattern for Ignore {
     }
 }
 
-/// A value of an attribute pattern, which is either a macro definition or
+/// A value of an attribute pattern, which is either a macro definition or a set of attribute assignments.
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
 pub enum Value {
+    /// The attributes assigned by the macro of the same name as the pattern.
     MacroAttributes(Vec<Assignment>),
+    /// The attributes assigned to paths matching the pattern.
     Assignments(Vec<Assignment>),
 }
 
@@ -45,6 +45,80 @@ pub fn decode(data: &[u8]) -> Result<(Vec, &[u8]), decode::Error> {
     ))
 }
 
+/// Encoding and conversion from and to uncompressed words.
+mod encode {
+    use std::convert::TryFrom;
+
+    use super::{access, Vec};
+
+    impl Vec {
+        /// Compress the uncompressed `words`, with bit `n` of word `w` representing bit `w * 64 + n` of the bitmap.
+        pub fn from_words(words: &[u64]) -> Self {
+            let num_bits = u32::try_from(words.len() * 64).expect("no more than u32::MAX bits");
+            let mut bits = std::vec::Vec::with_capacity(words.len() / 2 + 1);
+            let mut rlw;
+            let mut words = words.iter().copied().peekable();
+            loop {
+                rlw = bits.len();
+                bits.push(0);
+                let run_bit = words.peek().map_or(false, |w| *w == u64::MAX);
+                let clean_word = if run_bit { u64::MAX } else { 0 };
+                let mut running_len = 0;
+                while running_len < access::RLW_LARGEST_RUNNING_COUNT && words.next_if_eq(&clean_word).is_some() {
+                    running_len += 1;
+                }
+                let mut literal_words = 0;
+                while literal_words < access::RLW_LARGEST_LITERAL_COUNT {
+                    match words.next_if(|w| *w != 0 && *w != u64::MAX) {
+                        Some(word) => bits.push(word),
+                        None => break,
+                    }
+                    literal_words += 1;
+                }
+                bits[rlw] = u64::from(run_bit) | running_len << 1 | literal_words << (1 + access::RLW_RUNNING_BITS);
+                if words.peek().is_none() {
+                    break;
+                }
+            }
+            Vec {
+                num_bits,
+                bits,
+                rlw: rlw as u64,
+            }
+        }
+
+        /// Decompress this instance into words, with bit `n` of word `w` representing bit `w * 64 + n` of the bitmap.
+        ///
+        /// Note that the amount of returned words may be less than what's needed to hold [`num_bits()`][Vec::num_bits()] bits
+        /// if the trailing words are all zero.
+        pub fn to_words(&self) -> std::vec::Vec<u64> {
+            let mut out = std::vec::Vec::with_capacity(self.num_bits() / 64 + 1);
+            let mut iter = self.bits.iter();
+            while let Some(word) = iter.next() {
+                let clean_word = if access::rlw_runbit_is_set(word) { u64::MAX } else { 0 };
+                out.extend(std::iter::repeat(clean_word).take(access::rlw_running_len(word) as usize));
+                out.extend(iter.by_ref().take(access::rlw_literal_words(word) as usize).copied());
+            }
+            out
+        }
+
+        /// Write this instance in its on-disk representation to `out` and return the amount of written bytes.
+        pub fn write_to(&self, mut out: impl std::io::Write) -> std::io::Result<usize> {
+            out.write_all(&self.num_bits.to_be_bytes())?;
+            out.write_all(
+                &u32::try_from(self.bits.len())
+                    .expect("no more than u32::MAX words")
+                    .to_be_bytes(),
+            )?;
+            for word in &self.bits {
+                out.write_all(&word.to_be_bytes())?;
+            }
+            out.write_all(&u32::try_from(self.rlw).expect("rlw fits into u32").to_be_bytes())?;
+            Ok(4 + 4 + self.bits.len() * std::mem::size_of::<u64>() + 4)
+        }
+    }
+}
+
 mod access {
     use std::convert::{TryFrom, TryInto};
 
@@ -96,22 +170,23 @@ mod access {
     }
 
     #[inline]
-    fn rlw_running_len(w: &u64) -> u64 {
+    pub(super) fn rlw_running_len(w: &u64) -> u64 {
         (w >> 1) & RLW_LARGEST_RUNNING_COUNT
     }
 
     #[inline]
-    fn rlw_literal_words(w: &u64) -> u64 {
+    pub(super) fn rlw_literal_words(w: &u64) -> u64 {
         w >> (1 + RLW_RUNNING_BITS)
     }
 
     #[inline]
-    fn rlw_runbit_is_set(w: &u64) -> bool {
+    pub(super) fn rlw_runbit_is_set(w: &u64) -> bool {
         w & 1 == 1
     }
 
-    const RLW_RUNNING_BITS: u64 = 4 * 8;
-    const RLW_LARGEST_RUNNING_COUNT: u64 = (1 << RLW_RUNNING_BITS) - 1;
+    pub(super) const RLW_RUNNING_BITS: u64 = 4 * 8;
+    pub(super) const RLW_LARGEST_RUNNING_COUNT: u64 = (1 << RLW_RUNNING_BITS) - 1;
+    pub(super) const RLW_LARGEST_LITERAL_COUNT: u64 = (1 << (64 - 1 - RLW_RUNNING_BITS)) - 1;
 }
 
 /// A growable collection of u64 that are seen as stream of individual bits.
@@ -0,0 +1,175 @@
+//! Changed-path Bloom filters, which allow to learn quickly whether a commit may have changed a path compared to its first parent.
+//!
+//! They are stored in the `BIDX` and `BDAT` chunks of a commit-graph file and are compatible with the ones written by
+//! `git commit-graph write --changed-paths`.
+use std::collections::BTreeSet;
+
+use bstr::{BStr, ByteSlice};
+
+/// The largest amount of changed paths, including their leading directories, for which a filter is computed.
+///
+/// Commits with more changes get a filter which claims to contain every path.
+pub const MAX_CHANGED_PATHS: usize = 512;
+
+const SEED0: u32 = 0x293a_e76f;
+const SEED1: u32 = 0x7e64_6e2c;
+const BITS_PER_WORD: u64 = 8;
+
+/// The settings used to compute Bloom filters, as stored in the header of the `BDAT` chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Settings {
+    /// The version of the murmur3 hash function, with 1 being the one git has been using until its implementation was fixed
+    /// to use unsigned bytes in version 2.
+    pub hash_version: u32,
+    /// The amount of bits set for each key in a filter.
+    pub num_hashes: u32,
+    /// The amount of bits in a filter per changed path.
+    pub bits_per_entry: u32,
+}
+
+impl Default for Settings {
+    /// The settings `git` uses by default.
+    fn default() -> Self {
+        Settings {
+            hash_version: 1,
+            num_hashes: 7,
+            bits_per_entry: 10,
+        }
+    }
+}
+
+impl Settings {
+    /// Return true if filters with these settings can be computed and queried by us.
+    pub fn is_supported(&self) -> bool {
+        matches!(self.hash_version, 1 | 2) && self.num_hashes > 0
+    }
+}
+
+/// The hashes of a path, which are used to set and test bits of a [`Filter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Key {
+    hashes: Vec<u32>,
+}
+
+impl Key {
+    /// Compute the key for `path` using the given `settings`.
+    pub fn new(path: &BStr, settings: &Settings) -> Self {
+        let hash0 = murmur3(SEED0, path, settings.hash_version);
+        let hash1 = murmur3(SEED1, path, settings.hash_version);
+        Key {
+            hashes: (0..settings.num_hashes)
+                .map(|i| hash0.wrapping_add(i.wrapping_mul(hash1)))
+                .collect(),
+        }
+    }
+
+    fn bits(&self, filter_len: usize) -> impl Iterator<Item = (usize, u8)> + '_ {
+        let num_bits = filter_len as u64 * BITS_PER_WORD;
+        self.hashes.iter().map(move |hash| {
+            let bit = u64::from(*hash) % num_bits;
+            ((bit / BITS_PER_WORD) as usize, 1 << (bit % BITS_PER_WORD))
+        })
+    }
+}
+
+/// A Bloom filter of the paths a commit changed compared to its first parent.
+#[derive(Debug, Clone, Copy)]
+pub struct Filter<'a> {
+    pub(crate) data: &'a [u8],
+    pub(crate) settings: Settings,
+}
+
+impl<'a> Filter<'a> {
+    /// The raw bytes of the filter, as stored in the `BDAT` chunk.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// The settings that were used to compute this filter.
+    pub fn settings(&self) -> Settings {
+        self.settings
+    }
+
+    /// Return `false` if `key` is definitely not contained in this filter, or `true` if it might be.
+    pub fn may_contain(&self, key: &Key) -> bool {
+        self.data.is_empty()
+            || key
+                .bits(self.data.len())
+                .all(|(byte, mask)| self.data[byte] & mask != 0)
+    }
+
+    /// Return `false` if `path` was definitely not changed, or `true` if it might have been changed.
+    ///
+    /// `path` is a slash-separated path relative to the root of the repository, which may be a file or a directory.
+    /// Just like `git`, all of its leading directories are tested as well to reduce the rate of false positives.
+    pub fn may_contain_path(&self, path: &BStr) -> bool {
+        let path = path.trim_end_with(|c| c == '/').as_bstr();
+        leading_paths(path).all(|path| self.may_contain(&Key::new(path, &self.settings)))
+    }
+}
+
+/// Compute the data of a filter for the `changed_paths` of a commit compared to its first parent, or to the empty tree if it has no parents,
+/// using `settings`.
+///
+/// The paths are typically obtained from a recursive diff without rename tracking, and all of their leading directories are added
+/// automatically. If there are more than [`MAX_CHANGED_PATHS`], a filter that contains all paths is returned instead.
+pub fn compute_filter<'a>(changed_paths: impl IntoIterator<Item = &'a BStr>, settings: &Settings) -> Vec<u8> {
+    let mut paths = BTreeSet::new();
+    for path in changed_paths {
+        paths.extend(leading_paths(path));
+        if paths.len() > MAX_CHANGED_PATHS {
+            return vec![0xff];
+        }
+    }
+    let num_bits = paths.len() as u64 * u64::from(settings.bits_per_entry);
+    let len = ((num_bits + BITS_PER_WORD - 1) / BITS_PER_WORD).max(1) as usize;
+    let mut data = vec![0; len];
+    for path in paths {
+        for (byte, mask) in Key::new(path, settings).bits(len) {
+            data[byte] |= mask;
+        }
+    }
+    data
+}
+
+/// Return `path` followed by all of its leading directories.
+fn leading_paths(path: &BStr) -> impl Iterator<Item = &BStr> {
+    std::iter::successors(Some(path), |path| {
+        path.rfind_byte(b'/').map(|pos| path[..pos].as_bstr())
+    })
+}
+
+/// The 32 bit murmur3 hash of `data` with `seed`, using signed bytes in `version` 1 just like `git` did initially.
+fn murmur3(seed: u32, data: &[u8], version: u32) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+    let byte = |b: u8| {
+        if version == 1 {
+            b as i8 as u32
+        } else {
+            u32::from(b)
+        }
+    };
+    let scramble = |k: u32| k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+
+    let mut hash = seed;
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        let k = byte(block[0]) | byte(block[1]) << 8 | byte(block[2]) << 16 | byte(block[3]) << 24;
+        hash ^= scramble(k);
+        hash = hash.rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        let k = tail.iter().enumerate().fold(0, |k, (idx, b)| k ^ byte(*b) << (idx * 8));
+        hash ^= scramble(k);
+    }
+
+    hash ^= data.len() as u32;
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^= hash >> 16;
+    hash
+}
@@ -4,7 +4,10 @@ use std::{
     path::Path,
 };
 
-use crate::file::{self, commit::Commit, File, COMMIT_DATA_ENTRY_SIZE_SANS_HASH};
+use crate::{
+    bloom,
+    file::{self, commit::Commit, File, COMMIT_DATA_ENTRY_SIZE_SANS_HASH, GENERATION_DATA_OVERFLOW_MASK},
+};
 
 /// Access
 impl File {
@@ -13,6 +16,66 @@ impl File {
         self.base_graph_count
     }
 
+    /// The settings of the changed-path Bloom filters stored in this file, or `None` if there are none that we can use.
+    pub fn bloom_filter_settings(&self) -> Option<bloom::Settings> {
+        self.bloom_filters.as_ref().map(|filters| filters.settings)
+    }
+
+    /// Returns the changed-path Bloom filter of the commit at the given lexigraphical position, or `None` if this file has no
+    /// filters or if none was computed for the commit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is out of bounds.
+    pub fn bloom_filter_at(&self, pos: file::Position) -> Option<bloom::Filter<'_>> {
+        assert!(
+            pos.0 < self.num_commits(),
+            "expected lexigraphical position less than {}, got {}",
+            self.num_commits(),
+            pos.0
+        );
+        let filters = self.bloom_filters.as_ref()?;
+        let end_at = |pos: usize| {
+            let offset = filters.index_offset + pos * 4;
+            u32::from_be_bytes(self.data[offset..][..4].try_into().unwrap()) as usize
+        };
+        let pos = pos.0 as usize;
+        let start = if pos == 0 { 0 } else { end_at(pos - 1) };
+        let end = end_at(pos);
+        let data = self.data.get(filters.data_range.clone())?.get(start..end)?;
+        (!data.is_empty()).then_some(bloom::Filter {
+            data,
+            settings: filters.settings,
+        })
+    }
+
+    /// Returns `true` if this file contains corrected commit dates, which are used as generation numbers v2.
+    pub fn has_corrected_commit_dates(&self) -> bool {
+        self.generation_data.is_some()
+    }
+
+    /// Returns the offset of the corrected commit date of the commit at the given lexigraphical position to its commit date,
+    /// or `None` if this file has no corrected commit dates.
+    pub(crate) fn corrected_commit_date_offset_at(&self, pos: file::Position) -> Option<u64> {
+        let generation_data = self.generation_data.as_ref()?;
+        let read = |offset: usize, len: usize| self.data.get(offset..offset + len);
+        let offset = u32::from_be_bytes(
+            read(generation_data.offset + pos.0 as usize * 4, 4)?
+                .try_into()
+                .unwrap(),
+        );
+        if offset & GENERATION_DATA_OVERFLOW_MASK == 0 {
+            Some(offset.into())
+        } else {
+            let overflow_range = generation_data.overflow_range.as_ref()?;
+            let overflow_offset = overflow_range.start + (offset ^ GENERATION_DATA_OVERFLOW_MASK) as usize * 8;
+            if overflow_offset + 8 > overflow_range.end {
+                return None;
+            }
+            Some(u64::from_be_bytes(read(overflow_offset, 8)?.try_into().unwrap()))
+        }
+    }
+
     /// Returns the commit data for the commit located at the given lexigraphical position.
     ///
     /// `pos` must range from 0 to self.num_commits().
@@ -6,6 +6,7 @@ use std::{
 };
 
 use crate::{
+    bloom,
     file::{self, File, EXTENDED_EDGES_MASK, LAST_EXTENDED_EDGE_MASK, NO_PARENT},
     graph,
 };
@@ -71,6 +72,16 @@ impl<'a> Commit<'a> {
         self.generation
     }
 
+    /// Returns the corrected commit date of this commit, or `None` if the owning [File] doesn't contain them.
+    ///
+    /// It is the smallest timestamp that is not older than the commit's own timestamp and that is larger than the corrected
+    /// commit dates of all of its parents, and is used as generation number v2 by `git`.
+    pub fn corrected_commit_date(&self) -> Option<u64> {
+        self.file
+            .corrected_commit_date_offset_at(self.pos)
+            .map(|offset| self.commit_timestamp + offset)
+    }
+
     /// Returns an iterator over the parent positions for lookup in the owning [Graph][crate::Graph].
     pub fn iter_parents(&'a self) -> impl Iterator<Item = Result<graph::Position, Error>> + 'a {
         // I didn't find a combinator approach that a) was as strict as ParentIterator, b) supported
@@ -101,6 +112,11 @@ impl<'a> Commit<'a> {
     pub fn root_tree_id(&self) -> &git_hash::oid {
         self.root_tree_id
     }
+
+    /// Return the changed-path Bloom filter of this commit, if one was computed for it.
+    pub fn bloom_filter(&self) -> Option<bloom::Filter<'a>> {
+        self.file.bloom_filter_at(self.pos)
+    }
 }
 
 impl<'a> Debug for Commit<'a> {
@@ -6,9 +6,14 @@ use std::{
 use bstr::ByteSlice;
 use memmap2::Mmap;
 
-use crate::file::{
-    ChunkId, File, BASE_GRAPHS_LIST_CHUNK_ID, COMMIT_DATA_CHUNK_ID, COMMIT_DATA_ENTRY_SIZE_SANS_HASH,
-    EXTENDED_EDGES_LIST_CHUNK_ID, FAN_LEN, HEADER_LEN, OID_FAN_CHUNK_ID, OID_LOOKUP_CHUNK_ID, SIGNATURE,
+use crate::{
+    bloom,
+    file::{
+        BloomFilters, ChunkId, File, GenerationData, BASE_GRAPHS_LIST_CHUNK_ID, BLOOM_FILTER_DATA_CHUNK_ID,
+        BLOOM_FILTER_DATA_HEADER_LEN, BLOOM_FILTER_INDEX_CHUNK_ID, COMMIT_DATA_CHUNK_ID,
+        COMMIT_DATA_ENTRY_SIZE_SANS_HASH, EXTENDED_EDGES_LIST_CHUNK_ID, FAN_LEN, GENERATION_DATA_CHUNK_ID,
+        GENERATION_DATA_OVERFLOW_CHUNK_ID, HEADER_LEN, OID_FAN_CHUNK_ID, OID_LOOKUP_CHUNK_ID, SIGNATURE,
+    },
 };
 
 /// The error used in [`File::at()`].
@@ -229,13 +234,71 @@ impl TryFrom<&Path> for File {
                 chunk2_commits: commit_data_count,
             });
         }
+
+        let generation_data = chunks
+            .validated_usize_offset_by_id(GENERATION_DATA_CHUNK_ID, |chunk_range| {
+                let expected_size = fan[255] as usize * 4;
+                if chunk_range.len() != expected_size {
+                    return Err(Error::InvalidChunkSize {
+                        id: GENERATION_DATA_CHUNK_ID,
+                        msg: format!("expected chunk length {expected_size}, got {}", chunk_range.len()),
+                    });
+                }
+                Ok(chunk_range.start)
+            })
+            .ok()
+            .transpose()?
+            .map(|offset| GenerationData {
+                offset,
+                overflow_range: chunks.usize_offset_by_id(GENERATION_DATA_OVERFLOW_CHUNK_ID).ok(),
+            });
+
+        let bloom_filters = match (
+            chunks.usize_offset_by_id(BLOOM_FILTER_INDEX_CHUNK_ID).ok(),
+            chunks.usize_offset_by_id(BLOOM_FILTER_DATA_CHUNK_ID).ok(),
+        ) {
+            (Some(index_range), Some(data_range)) => {
+                let expected_size = fan[255] as usize * 4;
+                if index_range.len() != expected_size {
+                    return Err(Error::InvalidChunkSize {
+                        id: BLOOM_FILTER_INDEX_CHUNK_ID,
+                        msg: format!("expected chunk length {expected_size}, got {}", index_range.len()),
+                    });
+                }
+                if data_range.len() < BLOOM_FILTER_DATA_HEADER_LEN {
+                    return Err(Error::InvalidChunkSize {
+                        id: BLOOM_FILTER_DATA_CHUNK_ID,
+                        msg: format!(
+                            "chunk length {} is too small for a header of {BLOOM_FILTER_DATA_HEADER_LEN} bytes",
+                            data_range.len()
+                        ),
+                    });
+                }
+                let header = &data[data_range.start..][..BLOOM_FILTER_DATA_HEADER_LEN];
+                let settings = bloom::Settings {
+                    hash_version: read_u32(&header[..4]),
+                    num_hashes: read_u32(&header[4..8]),
+                    bits_per_entry: read_u32(&header[8..]),
+                };
+                // Like git, ignore filters we don't understand instead of failing.
+                settings.is_supported().then_some(BloomFilters {
+                    index_offset: index_range.start,
+                    data_range: data_range.start + BLOOM_FILTER_DATA_HEADER_LEN..data_range.end,
+                    settings,
+                })
+            }
+            _ => None,
+        };
+
         Ok(File {
             base_graph_count,
             base_graphs_list_offset,
+            bloom_filters,
             commit_data_offset,
             data,
             extra_edges_list_range,
             fan,
+            generation_data,
             oid_lookup_offset,
             path: path.to_owned(),
             hash_len: object_hash.len_in_bytes(),
@@ -244,6 +307,10 @@ impl TryFrom<&Path> for File {
     }
 }
 
+fn read_u32(b: &[u8]) -> u32 {
+    u32::from_be_bytes(b.try_into().unwrap())
+}
+
 // Copied from git-odb/pack/index/init.rs
 fn read_fan(d: &[u8]) -> ([u32; FAN_LEN], usize) {
     let mut fan = [0; FAN_LEN];
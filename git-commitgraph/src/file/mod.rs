@@ -14,6 +14,7 @@ mod access;
 pub mod commit;
 mod init;
 pub mod verify;
+pub mod write;
 
 const COMMIT_DATA_ENTRY_SIZE_SANS_HASH: usize = 16;
 const FAN_LEN: usize = 256;
@@ -23,16 +24,22 @@ const SIGNATURE: &[u8] = b"CGPH";
 
 type ChunkId = git_chunk::Id;
 const BASE_GRAPHS_LIST_CHUNK_ID: ChunkId = *b"BASE";
+const BLOOM_FILTER_DATA_CHUNK_ID: ChunkId = *b"BDAT";
+const BLOOM_FILTER_INDEX_CHUNK_ID: ChunkId = *b"BIDX";
 const COMMIT_DATA_CHUNK_ID: ChunkId = *b"CDAT";
 const EXTENDED_EDGES_LIST_CHUNK_ID: ChunkId = *b"EDGE";
+const GENERATION_DATA_CHUNK_ID: ChunkId = *b"GDA2";
+const GENERATION_DATA_OVERFLOW_CHUNK_ID: ChunkId = *b"GDO2";
 const OID_FAN_CHUNK_ID: ChunkId = *b"OIDF";
 const OID_LOOKUP_CHUNK_ID: ChunkId = *b"OIDL";
 
 // Note that git's commit-graph-format.txt as of v2.28.0 gives an incorrect value 0x0700_0000 for
 // NO_PARENT. Fixed in https://github.com/git/git/commit/4d515253afcef985e94400adbfed7044959f9121 .
 const NO_PARENT: u32 = 0x7000_0000;
+const BLOOM_FILTER_DATA_HEADER_LEN: usize = 12;
 const EXTENDED_EDGES_MASK: u32 = 0x8000_0000;
 const LAST_EXTENDED_EDGE_MASK: u32 = 0x8000_0000;
+const GENERATION_DATA_OVERFLOW_MASK: u32 = 0x8000_0000;
 
 /// A single commit-graph file.
 ///
@@ -41,16 +48,35 @@ const LAST_EXTENDED_EDGE_MASK: u32 = 0x8000_0000;
 pub struct File {
     base_graph_count: u8,
     base_graphs_list_offset: Option<usize>,
+    bloom_filters: Option<BloomFilters>,
     commit_data_offset: usize,
     data: Mmap,
     extra_edges_list_range: Option<Range<usize>>,
     fan: [u32; FAN_LEN],
+    generation_data: Option<GenerationData>,
     oid_lookup_offset: usize,
     path: PathBuf,
     hash_len: usize,
     object_hash: git_hash::Kind,
 }
 
+/// The location of changed-path Bloom filters within a [`File`].
+struct BloomFilters {
+    /// The offset of the `BIDX` chunk with the end offset of each commit's filter.
+    index_offset: usize,
+    /// The range of the filters in the `BDAT` chunk, without its header.
+    data_range: Range<usize>,
+    settings: crate::bloom::Settings,
+}
+
+/// The location of corrected commit dates within a [`File`].
+struct GenerationData {
+    /// The offset of the `GDA2` chunk with the offset of each commit's corrected commit date to its commit date.
+    offset: usize,
+    /// The range of the `GDO2` chunk with offsets too large to fit into 31 bits.
+    overflow_range: Option<Range<usize>>,
+}
+
 /// The position of a given commit within a graph file, starting at 0.
 ///
 /// Commits within a graph file are sorted in lexicographical order by OID; a commit's lexigraphical position
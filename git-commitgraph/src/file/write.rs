@@ -0,0 +1,380 @@
+//! Auxiliary types used to write commit-graph files.
+use std::{
+    convert::TryInto,
+    io::Write,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+use git_features::progress::Progress;
+
+use crate::{
+    bloom,
+    file::{
+        File, BASE_GRAPHS_LIST_CHUNK_ID, BLOOM_FILTER_DATA_CHUNK_ID, BLOOM_FILTER_DATA_HEADER_LEN,
+        BLOOM_FILTER_INDEX_CHUNK_ID, COMMIT_DATA_CHUNK_ID, COMMIT_DATA_ENTRY_SIZE_SANS_HASH,
+        EXTENDED_EDGES_LIST_CHUNK_ID, EXTENDED_EDGES_MASK, FAN_LEN, HEADER_LEN, LAST_EXTENDED_EDGE_MASK, NO_PARENT,
+        OID_FAN_CHUNK_ID, OID_LOOKUP_CHUNK_ID, SIGNATURE,
+    },
+    graph, Graph, GENERATION_NUMBER_MAX, MAX_COMMITS,
+};
+
+mod error {
+    /// The error returned by [`File::write_to()`][crate::File::write_to()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error(transparent)]
+        Io(#[from] std::io::Error),
+        #[error("Interrupted")]
+        Interrupted,
+        #[error("Parent {parent} of commit {id} is neither part of the commits to write nor of the base graph")]
+        MissingParent {
+            id: git_hash::ObjectId,
+            parent: git_hash::ObjectId,
+        },
+        #[error(
+            "Commit-graph would contain {0} commits altogether, but only {} commits are allowed",
+            crate::MAX_COMMITS
+        )]
+        TooManyCommits(u64),
+        #[error("A commit-graph file can refer to at most 255 base graphs, got {0}")]
+        TooManyBaseGraphs(usize),
+    }
+}
+pub use error::Error;
+
+/// A commit with all information needed to write it into a commit-graph file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Commit {
+    /// The id of the commit itself.
+    pub id: git_hash::ObjectId,
+    /// The id of the commit's root tree.
+    pub tree: git_hash::ObjectId,
+    /// The ids of all parents, in order.
+    pub parents: Vec<git_hash::ObjectId>,
+    /// The time at which the commit was committed, in seconds since the unix epoch.
+    pub committer_timestamp: u64,
+    /// The data of the changed-path Bloom filter as computed by [`bloom::compute_filter()`] with the
+    /// [default settings][bloom::Settings::default()], or `None` if no filter was computed for this commit.
+    pub bloom_filter: Option<Vec<u8>>,
+}
+
+/// The result of [`File::write_to()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    /// The checksum over the written data, which is also the trailer of the file.
+    pub checksum: git_hash::ObjectId,
+    /// The amount of commits written into the file, excluding those already present in the base graph.
+    pub num_commits: u32,
+}
+
+/// The progress ids used in [`File::write_to()`].
+///
+/// Use this information to selectively extract the progress of interest in case the parent application has custom visualization.
+#[derive(Debug, Copy, Clone)]
+pub enum ProgressId {
+    /// Counts the commits whose generation number was computed.
+    ComputeGenerations,
+    /// The amount of bytes written as part of the commit-graph file.
+    BytesWritten,
+}
+
+impl From<ProgressId> for git_features::progress::Id {
+    fn from(v: ProgressId) -> Self {
+        match v {
+            ProgressId::ComputeGenerations => *b"CGCG",
+            ProgressId::BytesWritten => *b"CGBW",
+        }
+    }
+}
+
+/// Writing
+impl File {
+    /// Write all `commits` into a new commit-graph file to `out`, sorted by id and without duplicates.
+    ///
+    /// If `base` is set, the file will be written as layer of a split commit-graph on top of it, hence commits that are already
+    /// contained in `base` are skipped and parents may point to commits in `base`. Otherwise all parents of `commits` must
+    /// be contained in `commits` as well.
+    ///
+    /// Generation numbers are computed as topological levels, the first version of generation numbers understood by all git versions.
+    /// If any of the `commits` has a [Bloom filter][Commit::bloom_filter], changed-path Bloom filters are written as well.
+    /// Progress is sent to `progress` and interruptions checked via `should_interrupt`.
+    pub fn write_to(
+        mut commits: Vec<Commit>,
+        base: Option<&Graph>,
+        out: impl std::io::Write,
+        mut progress: impl Progress,
+        should_interrupt: &AtomicBool,
+        object_hash: git_hash::Kind,
+    ) -> Result<Outcome, Error> {
+        let out = git_features::hash::Write::new(out, object_hash);
+        commits.sort_by_key(|c| c.id);
+        commits.dedup_by(|a, b| a.id == b.id);
+        if let Some(base) = base {
+            commits.retain(|c| base.lookup(c.id).is_none());
+        }
+
+        let num_base_commits = base.map_or(0, Graph::num_commits);
+        let total_commits = u64::from(num_base_commits) + commits.len() as u64;
+        if total_commits > u64::from(MAX_COMMITS) {
+            return Err(Error::TooManyCommits(total_commits));
+        }
+        let base_graph_checksums: Vec<_> = base
+            .map(|base| base.files.iter().map(|f| f.checksum().to_owned()).collect())
+            .unwrap_or_default();
+        let num_base_graphs: u8 = base_graph_checksums
+            .len()
+            .try_into()
+            .map_err(|_| Error::TooManyBaseGraphs(base_graph_checksums.len()))?;
+
+        let parents = Parents::from_commits(&commits, base)?;
+        let generations = {
+            let start = Instant::now();
+            let mut progress =
+                progress.add_child_with_id("Computing generations", ProgressId::ComputeGenerations.into());
+            progress.init(Some(commits.len()), git_features::progress::count("commits"));
+            let generations = compute_generations(&parents, base, num_base_commits, &mut progress, should_interrupt)?;
+            progress.show_throughput(start);
+            generations
+        };
+
+        let mut cf = git_chunk::file::Index::for_writing();
+        cf.plan_chunk(OID_FAN_CHUNK_ID, (FAN_LEN * 4) as u64);
+        cf.plan_chunk(OID_LOOKUP_CHUNK_ID, (commits.len() * object_hash.len_in_bytes()) as u64);
+        cf.plan_chunk(
+            COMMIT_DATA_CHUNK_ID,
+            (commits.len() * (object_hash.len_in_bytes() + COMMIT_DATA_ENTRY_SIZE_SANS_HASH)) as u64,
+        );
+        let num_extra_edges = parents.num_extra_edges();
+        if num_extra_edges != 0 {
+            cf.plan_chunk(EXTENDED_EDGES_LIST_CHUNK_ID, (num_extra_edges * 4) as u64);
+        }
+        let bloom_filter_data_len: Option<usize> = commits.iter().any(|c| c.bloom_filter.is_some()).then(|| {
+            commits
+                .iter()
+                .filter_map(|c| c.bloom_filter.as_ref())
+                .map(Vec::len)
+                .sum()
+        });
+        if let Some(data_len) = bloom_filter_data_len {
+            cf.plan_chunk(BLOOM_FILTER_INDEX_CHUNK_ID, (commits.len() * 4) as u64);
+            cf.plan_chunk(
+                BLOOM_FILTER_DATA_CHUNK_ID,
+                (BLOOM_FILTER_DATA_HEADER_LEN + data_len) as u64,
+            );
+        }
+        if num_base_graphs != 0 {
+            cf.plan_chunk(
+                BASE_GRAPHS_LIST_CHUNK_ID,
+                (base_graph_checksums.len() * object_hash.len_in_bytes()) as u64,
+            );
+        }
+
+        let mut write_progress = progress.add_child_with_id("Writing commit-graph", ProgressId::BytesWritten.into());
+        let write_start = Instant::now();
+        write_progress.init(
+            Some(cf.planned_storage_size() as usize + HEADER_LEN),
+            git_features::progress::bytes(),
+        );
+        let mut out = git_features::progress::Write {
+            inner: out,
+            progress: write_progress,
+        };
+
+        out.write_all(SIGNATURE)?;
+        out.write_all(&[1 /* version */, object_hash as u8])?;
+        out.write_all(&[
+            cf.num_chunks().try_into().expect("BUG: wrote more than 256 chunks"),
+            num_base_graphs,
+        ])?;
+
+        let mut chunk_write = cf.into_write(&mut out, HEADER_LEN)?;
+        while let Some(chunk_to_write) = chunk_write.next_chunk() {
+            match chunk_to_write {
+                OID_FAN_CHUNK_ID => {
+                    let mut fan = [0u32; FAN_LEN];
+                    for commit in &commits {
+                        fan[usize::from(commit.id.first_byte())] += 1;
+                    }
+                    let mut count = 0;
+                    for entry in fan {
+                        count += entry;
+                        chunk_write.write_all(&count.to_be_bytes())?;
+                    }
+                }
+                OID_LOOKUP_CHUNK_ID => {
+                    for commit in &commits {
+                        chunk_write.write_all(commit.id.as_slice())?;
+                    }
+                }
+                COMMIT_DATA_CHUNK_ID => {
+                    let mut extra_edges_offset = 0;
+                    for ((commit, parents), generation) in
+                        commits.iter().zip(parents.iter()).zip(generations.iter().copied())
+                    {
+                        chunk_write.write_all(commit.tree.as_slice())?;
+                        let (parent1, parent2) = match parents {
+                            [] => (NO_PARENT, NO_PARENT),
+                            [p1] => (*p1, NO_PARENT),
+                            [p1, p2] => (*p1, *p2),
+                            [p1, rest @ ..] => {
+                                let parent2 = extra_edges_offset | EXTENDED_EDGES_MASK;
+                                extra_edges_offset += rest.len() as u32;
+                                (*p1, parent2)
+                            }
+                        };
+                        chunk_write.write_all(&parent1.to_be_bytes())?;
+                        chunk_write.write_all(&parent2.to_be_bytes())?;
+                        let timestamp = commit.committer_timestamp.min(0x0003_ffff_ffff);
+                        let generation_and_timestamp_high = (generation << 2) | (timestamp >> 32) as u32;
+                        chunk_write.write_all(&generation_and_timestamp_high.to_be_bytes())?;
+                        chunk_write.write_all(&(timestamp as u32).to_be_bytes())?;
+                    }
+                }
+                EXTENDED_EDGES_LIST_CHUNK_ID => {
+                    for parents in parents.iter().filter(|p| p.len() > 2) {
+                        let (last, rest) = parents[1..].split_last().expect("more than two parents");
+                        for parent in rest {
+                            chunk_write.write_all(&parent.to_be_bytes())?;
+                        }
+                        chunk_write.write_all(&(last | LAST_EXTENDED_EDGE_MASK).to_be_bytes())?;
+                    }
+                }
+                BLOOM_FILTER_INDEX_CHUNK_ID => {
+                    let mut end = 0u32;
+                    for commit in &commits {
+                        end += commit.bloom_filter.as_ref().map_or(0, Vec::len) as u32;
+                        chunk_write.write_all(&end.to_be_bytes())?;
+                    }
+                }
+                BLOOM_FILTER_DATA_CHUNK_ID => {
+                    let settings = bloom::Settings::default();
+                    for value in [settings.hash_version, settings.num_hashes, settings.bits_per_entry] {
+                        chunk_write.write_all(&value.to_be_bytes())?;
+                    }
+                    for filter in commits.iter().filter_map(|c| c.bloom_filter.as_ref()) {
+                        chunk_write.write_all(filter)?;
+                    }
+                }
+                BASE_GRAPHS_LIST_CHUNK_ID => {
+                    for checksum in &base_graph_checksums {
+                        chunk_write.write_all(checksum.as_slice())?;
+                    }
+                }
+                unknown => unreachable!("BUG: forgot to implement chunk {:?}", std::str::from_utf8(&unknown)),
+            }
+            if should_interrupt.load(Ordering::Relaxed) {
+                return Err(Error::Interrupted);
+            }
+        }
+
+        let checksum = out.inner.hash.digest();
+        out.inner.inner.write_all(checksum.as_slice())?;
+        out.progress.show_throughput(write_start);
+
+        Ok(Outcome {
+            checksum,
+            num_commits: commits.len() as u32,
+        })
+    }
+}
+
+/// The graph positions of the parents of all commits to write, stored back to back.
+struct Parents {
+    positions: Vec<u32>,
+    /// The end of each commit's parent positions in `positions`.
+    ends: Vec<usize>,
+}
+
+impl Parents {
+    fn from_commits(commits: &[Commit], base: Option<&Graph>) -> Result<Self, Error> {
+        let num_base_commits = base.map_or(0, Graph::num_commits);
+        let mut positions = Vec::with_capacity(commits.len() * 2);
+        let mut ends = Vec::with_capacity(commits.len());
+        for commit in commits {
+            for parent in &commit.parents {
+                let pos = match commits.binary_search_by(|c| c.id.cmp(parent)) {
+                    Ok(idx) => num_base_commits + idx as u32,
+                    Err(_) => {
+                        base.and_then(|base| base.lookup(parent))
+                            .map(|pos| pos.0)
+                            .ok_or(Error::MissingParent {
+                                id: commit.id,
+                                parent: *parent,
+                            })?
+                    }
+                };
+                positions.push(pos);
+            }
+            ends.push(positions.len());
+        }
+        Ok(Parents { positions, ends })
+    }
+
+    fn get(&self, idx: usize) -> &[u32] {
+        let start = if idx == 0 { 0 } else { self.ends[idx - 1] };
+        &self.positions[start..self.ends[idx]]
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &[u32]> {
+        (0..self.ends.len()).map(move |idx| self.get(idx))
+    }
+
+    fn num_extra_edges(&self) -> usize {
+        self.iter().filter(|p| p.len() > 2).map(|p| p.len() - 1).sum()
+    }
+}
+
+/// Compute the topological level of each commit, which is one more than the highest level of its parents,
+/// without recursion to support arbitrarily long histories.
+fn compute_generations(
+    parents: &Parents,
+    base: Option<&Graph>,
+    num_base_commits: u32,
+    progress: &mut impl Progress,
+    should_interrupt: &AtomicBool,
+) -> Result<Vec<u32>, Error> {
+    let mut generations = vec![0u32; parents.ends.len()];
+    let mut stack = Vec::new();
+    for idx in 0..generations.len() {
+        if generations[idx] != 0 {
+            continue;
+        }
+        stack.push(idx);
+        while let Some(&current) = stack.last() {
+            if generations[current] != 0 {
+                stack.pop();
+                continue;
+            }
+            let mut max_parent_generation = 0;
+            let mut has_unknown_parents = false;
+            for &parent in parents.get(current) {
+                let generation = match parent.checked_sub(num_base_commits) {
+                    Some(parent_idx) => {
+                        let generation = generations[parent_idx as usize];
+                        if generation == 0 {
+                            stack.push(parent_idx as usize);
+                            has_unknown_parents = true;
+                        }
+                        generation
+                    }
+                    None => base
+                        .expect("positions below the amount of base commits only exist with base")
+                        .commit_at(graph::Position(parent))
+                        .generation(),
+                };
+                max_parent_generation = max_parent_generation.max(generation);
+            }
+            if !has_unknown_parents {
+                generations[current] = (max_parent_generation + 1).min(GENERATION_NUMBER_MAX);
+                stack.pop();
+                progress.inc();
+                if should_interrupt.load(Ordering::Relaxed) {
+                    return Err(Error::Interrupted);
+                }
+            }
+        }
+    }
+    Ok(generations)
+}
@@ -48,6 +48,32 @@ impl Graph {
     pub fn num_commits(&self) -> u32 {
         self.files.iter().map(|f| f.num_commits()).sum()
     }
+
+    /// Returns `true` if all files of this graph contain corrected commit dates, which are then used as generation numbers.
+    ///
+    /// Like `git`, we only use them if all files have them, as a mix of generation numbers v1 and v2 can't be compared.
+    pub fn has_corrected_commit_dates(&self) -> bool {
+        self.files.iter().all(File::has_corrected_commit_dates)
+    }
+
+    /// Returns the generation number of `commit`, which is its [corrected commit date][Commit::corrected_commit_date()] if
+    /// [all files have them][Graph::has_corrected_commit_dates()], or its [topological level][Commit::generation()] otherwise.
+    ///
+    /// Either way, the generation number of a commit is larger than the ones of all of its parents.
+    pub fn generation_of(&self, commit: &Commit<'_>) -> u64 {
+        if self.has_corrected_commit_dates() {
+            commit
+                .corrected_commit_date()
+                .expect("all files have corrected commit dates")
+        } else {
+            commit.generation().into()
+        }
+    }
+
+    /// Returns the settings of the changed-path Bloom filters of the first file that has usable filters, or `None` if there are none.
+    pub fn bloom_filter_settings(&self) -> Option<crate::bloom::Settings> {
+        self.files.iter().find_map(File::bloom_filter_settings)
+    }
 }
 
 /// Access fundamentals
@@ -2,6 +2,9 @@
 mod access;
 mod init;
 pub mod verify;
+pub mod write;
+
+pub use init::Error;
 
 use std::fmt;
 
@@ -13,7 +16,7 @@ use crate::file::File;
 /// may come from one or more `objects/info/commit-graphs/graph-*.graph` files. These files are
 /// generated via `git commit-graph write ...` commands.
 pub struct Graph {
-    files: Vec<File>,
+    pub(crate) files: Vec<File>,
 }
 
 /// A generalized position for use in [`Graph`].
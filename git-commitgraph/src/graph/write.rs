@@ -0,0 +1,290 @@
+//! Auxiliary types used to write commit graphs.
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::AtomicBool,
+};
+
+use git_features::progress::Progress;
+
+use crate::{
+    bloom,
+    file::{self, File},
+    graph, Graph,
+};
+
+/// The way commits are written in [`Graph::write_to_info_dir()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Write all commits, including those of an existing graph, into a single `commit-graph` file and remove all layers
+    /// of a split commit-graph, similar to `git commit-graph write`.
+    Single,
+    /// Write new commits into a new layer of a split commit-graph, similar to `git commit-graph write --split`.
+    ///
+    /// Existing layers are merged into the new layer from the top while they contain no more than `size_multiple` times
+    /// the amount of commits merged so far, or while the commits merged so far exceed `max_commits`.
+    /// Git uses a `size_multiple` of 2 by default.
+    Split {
+        /// The factor by which a layer must be larger than the layers above it to not be merged with them.
+        size_multiple: u32,
+        /// If set, layers are merged for as long as the new layer has more commits than this.
+        max_commits: Option<u32>,
+    },
+    /// Write new commits into a new layer of a split commit-graph without merging it with existing layers,
+    /// similar to `git commit-graph write --split=no-merge`.
+    SplitNoMerge,
+    /// Merge all layers of an existing commit-graph along with new commits into a single layer of a split commit-graph,
+    /// similar to `git commit-graph write --split=replace`.
+    SplitReplace,
+}
+
+/// Options for use in [`Graph::write_to_info_dir()`].
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// How to write the commit-graph.
+    pub mode: Mode,
+    /// The kind of hash to use for commits, which must match the one of an existing graph.
+    pub object_hash: git_hash::Kind,
+}
+
+/// The result of [`Graph::write_to_info_dir()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// The path to the commit-graph file that was written.
+    pub path: PathBuf,
+    /// The checksum of the file at `path`.
+    pub checksum: git_hash::ObjectId,
+    /// The amount of commits in the file at `path`, including those that were merged from previously existing layers.
+    pub num_commits: u32,
+    /// The amount of files the commit-graph consists of after writing, with 1 for a non-split commit-graph.
+    pub num_layers: usize,
+}
+
+mod error {
+    use crate::{file, graph};
+
+    /// The error returned by [`Graph::write_to_info_dir()`][crate::Graph::write_to_info_dir()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("Could not open existing commit-graph")]
+        Open(#[from] graph::Error),
+        #[error(transparent)]
+        Commit(#[from] file::commit::Error),
+        #[error(transparent)]
+        WriteFile(#[from] file::write::Error),
+        #[error(transparent)]
+        Io(#[from] std::io::Error),
+        #[error(transparent)]
+        LockAcquire(#[from] git_lock::acquire::Error),
+        #[error("The existing commit-graph uses hash {actual:?}, but {expected:?} was expected")]
+        ObjectHashMismatch {
+            actual: git_hash::Kind,
+            expected: git_hash::Kind,
+        },
+    }
+}
+pub use error::Error;
+
+/// Writing
+impl Graph {
+    /// Write `commits` into the commit-graph stored in `info_dir`, typically `.git/objects/info`, using the way of writing
+    /// as configured in `options`.
+    ///
+    /// Commits that are already part of the existing commit-graph are skipped, but all parents of `commits` must either be
+    /// contained in `commits` or in the existing commit-graph.
+    /// Files of layers that were merged or replaced are removed on a best-effort basis.
+    ///
+    /// Returns `None` if there was nothing to write as no new commits were given and no existing files were to be merged.
+    /// Progress is sent to `progress` and interruptions checked via `should_interrupt`.
+    pub fn write_to_info_dir(
+        info_dir: impl AsRef<Path>,
+        commits: Vec<file::write::Commit>,
+        mut progress: impl Progress,
+        should_interrupt: &AtomicBool,
+        Options { mode, object_hash }: Options,
+    ) -> Result<Option<Outcome>, Error> {
+        let info_dir = info_dir.as_ref();
+        let single_file_path = info_dir.join("commit-graph");
+        let graphs_dir = info_dir.join("commit-graphs");
+        let chain_file_path = graphs_dir.join("commit-graph-chain");
+
+        let existing = if single_file_path.is_file() {
+            Some(Graph::from_file(&single_file_path)?)
+        } else if chain_file_path.is_file() {
+            Some(Graph::from_commit_graphs_dir(&graphs_dir)?)
+        } else {
+            None
+        };
+        if let Some(actual) = existing
+            .as_ref()
+            .and_then(|g| g.files.first())
+            .map(File::object_hash)
+            .filter(|actual| *actual != object_hash)
+        {
+            return Err(Error::ObjectHashMismatch {
+                actual,
+                expected: object_hash,
+            });
+        }
+
+        let mut commits = commits;
+        if let Some(existing) = &existing {
+            commits.retain(|c| existing.lookup(c.id).is_none());
+        }
+        let num_layers_before = existing.as_ref().map_or(0, |g| g.files.len());
+        let existing_is_single_file = existing
+            .as_ref()
+            .map_or(false, |g| g.files[0].path() == single_file_path);
+        let num_layers_to_keep = match mode {
+            Mode::Single | Mode::SplitReplace => 0,
+            Mode::SplitNoMerge => num_layers_before,
+            Mode::Split {
+                size_multiple,
+                max_commits,
+            } => {
+                let files = existing.as_ref().map(|g| g.files.as_slice()).unwrap_or_default();
+                let mut num_commits = commits.len() as u64;
+                let mut num_layers_to_keep = files.len();
+                while let Some(layer) = num_layers_to_keep.checked_sub(1).map(|idx| &files[idx]) {
+                    let layer_commits = u64::from(layer.num_commits());
+                    if layer_commits <= u64::from(size_multiple) * num_commits
+                        || max_commits.map_or(false, |max| num_commits > u64::from(max))
+                    {
+                        num_commits += layer_commits;
+                        num_layers_to_keep -= 1;
+                    } else {
+                        break;
+                    }
+                }
+                num_layers_to_keep
+            }
+        };
+
+        let nothing_changes = commits.is_empty()
+            && match mode {
+                Mode::Single => existing.is_none() || existing_is_single_file,
+                Mode::Split { .. } | Mode::SplitNoMerge | Mode::SplitReplace => num_layers_to_keep == num_layers_before,
+            };
+        if nothing_changes {
+            return Ok(None);
+        }
+
+        let (base, merged_paths) = match existing {
+            Some(existing) => {
+                let num_base_commits: u32 = existing.files[..num_layers_to_keep].iter().map(File::num_commits).sum();
+                for pos in num_base_commits..existing.num_commits() {
+                    let commit = existing.commit_at(graph::Position(pos));
+                    commits.push(file::write::Commit {
+                        id: commit.id().to_owned(),
+                        tree: commit.root_tree_id().to_owned(),
+                        parents: commit
+                            .iter_parents()
+                            .map(|parent| parent.map(|pos| existing.id_at(pos).to_owned()))
+                            .collect::<Result<_, _>>()?,
+                        committer_timestamp: commit.committer_timestamp(),
+                        bloom_filter: commit
+                            .bloom_filter()
+                            .filter(|filter| filter.settings() == bloom::Settings::default())
+                            .map(|filter| filter.data().to_owned()),
+                    });
+                }
+                let mut files = existing.files;
+                let merged_paths: Vec<_> = files
+                    .drain(num_layers_to_keep..)
+                    .map(|file| file.path().to_owned())
+                    .collect();
+                let base = (!files.is_empty()).then_some(Graph { files });
+                (base, merged_paths)
+            }
+            None => (None, Vec::new()),
+        };
+
+        let outcome = match mode {
+            Mode::Single => {
+                let mut out = std::io::BufWriter::new(git_lock::File::acquire_to_update_resource(
+                    &single_file_path,
+                    git_lock::acquire::Fail::Immediately,
+                    None,
+                )?);
+                let file::write::Outcome { checksum, num_commits } =
+                    File::write_to(commits, None, &mut out, &mut progress, should_interrupt, object_hash)?;
+                out.into_inner()
+                    .map_err(|err| err.into_error())?
+                    .commit()
+                    .map_err(|err| err.error)?;
+                Outcome {
+                    path: single_file_path,
+                    checksum,
+                    num_commits,
+                    num_layers: 1,
+                }
+            }
+            Mode::Split { .. } | Mode::SplitNoMerge | Mode::SplitReplace => {
+                std::fs::create_dir_all(&graphs_dir)?;
+                let mut out = std::io::BufWriter::new(git_tempfile::new(
+                    &graphs_dir,
+                    git_tempfile::ContainingDirectory::Exists,
+                    git_tempfile::AutoRemove::Tempfile,
+                )?);
+                let file::write::Outcome { checksum, num_commits } = File::write_to(
+                    commits,
+                    base.as_ref(),
+                    &mut out,
+                    &mut progress,
+                    should_interrupt,
+                    object_hash,
+                )?;
+                out.flush()?;
+                let path = graphs_dir.join(format!("graph-{}.graph", checksum.to_hex()));
+                out.into_inner()
+                    .map_err(|err| err.into_error())?
+                    .persist(&path)
+                    .map_err(|err| err.error)?;
+
+                let mut base_checksums = Vec::new();
+                if let Some(base) = base {
+                    for file in &base.files {
+                        base_checksums.push(file.checksum().to_owned());
+                    }
+                    let previous_single_file = base.files[0].path().to_owned();
+                    drop(base);
+                    if previous_single_file == single_file_path {
+                        std::fs::rename(
+                            &single_file_path,
+                            graphs_dir.join(format!("graph-{}.graph", base_checksums[0].to_hex())),
+                        )?;
+                    }
+                }
+
+                let mut chain = git_lock::File::acquire_to_update_resource(
+                    &chain_file_path,
+                    git_lock::acquire::Fail::Immediately,
+                    None,
+                )?;
+                for checksum in base_checksums.iter().chain(Some(&checksum)) {
+                    writeln!(chain, "{}", checksum.to_hex())?;
+                }
+                chain.commit().map_err(|err| err.error)?;
+                Outcome {
+                    path,
+                    checksum,
+                    num_commits,
+                    num_layers: base_checksums.len() + 1,
+                }
+            }
+        };
+
+        for path in merged_paths {
+            if path != outcome.path {
+                std::fs::remove_file(path).ok();
+            }
+        }
+        if let Mode::Single = mode {
+            if chain_file_path.is_file() {
+                std::fs::remove_file(&chain_file_path).ok();
+            }
+        }
+        Ok(Some(outcome))
+    }
+}
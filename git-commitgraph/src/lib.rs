@@ -7,6 +7,8 @@
 //! As generating the full commit graph from scratch can take some time, git may write new commits
 //! to separate [files][file::File] instead of overwriting the original file.
 //! Eventually, git will merge these files together as the number of files grows.
+//!
+//! Commit graphs may also contain [changed-path Bloom filters][bloom] to quickly rule out commits which didn't change a given path.
 //! ## Feature Flags
 #![cfg_attr(
     feature = "document-features",
@@ -15,6 +17,7 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 #![deny(missing_docs, rust_2018_idioms, unsafe_code)]
 
+pub mod bloom;
 pub mod file;
 pub mod graph;
 
@@ -1,4 +1,4 @@
-use git_commitgraph::Graph;
+use git_commitgraph::{bloom, Graph};
 
 use crate::{check_common, inspect_refs, make_readonly_repo};
 
@@ -69,3 +69,82 @@ fn two_parents() -> crate::Result {
 
     Ok(())
 }
+
+#[test]
+fn changed_path_filters() -> crate::Result {
+    let dir = git_testtools::scripted_fixture_writable("changed_paths.sh")?;
+    let status = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir.path())
+        .args([
+            "commit-graph",
+            "write",
+            "--no-progress",
+            "--reachable",
+            "--changed-paths",
+        ])
+        .env_remove("GIT_DIR")
+        .status()?;
+    assert!(status.success());
+
+    let refs = inspect_refs(
+        dir.path(),
+        &["root", "change-a", "change-dir-b", "add-sub-dir-d", "remove-dir-u"],
+    );
+    let cg = Graph::from_info_dir(dir.path().join(".git").join("objects").join("info"))?;
+    check_common(&cg, &refs);
+    assert_eq!(cg.bloom_filter_settings(), Some(bloom::Settings::default()));
+
+    let may_contain = |name: &str, path: &str| {
+        cg.commit_at(refs[name].pos())
+            .bloom_filter()
+            .expect("all commits have filters")
+            .may_contain_path(path.into())
+    };
+    for path in ["a", "dir", "dir/b", "dir/ü.txt"] {
+        assert!(may_contain("root", path), "{path} was added in the root commit");
+    }
+    assert!(may_contain("change-a", "a"));
+    assert!(may_contain("change-dir-b", "dir/b"));
+    assert!(may_contain("change-dir-b", "dir/"), "trailing slashes are ignored");
+    assert!(may_contain("add-sub-dir-d", "sub/dir"));
+    assert!(may_contain("remove-dir-u", "dir/ü.txt"), "deletions count as change");
+
+    assert!(!may_contain("change-a", "dir"));
+    assert!(!may_contain("change-a", "dir/b"));
+    assert!(!may_contain("change-dir-b", "a"));
+    assert!(
+        !may_contain("change-dir-b", "dir/ü.txt"),
+        "a sibling of a changed file wasn't changed"
+    );
+    assert!(!may_contain("add-sub-dir-d", "sub/other"));
+    assert!(!may_contain("remove-dir-u", "dir/b"));
+    Ok(())
+}
+
+#[test]
+fn corrected_commit_dates() -> crate::Result {
+    let repo_dir = make_readonly_repo("corrected_commit_dates.sh");
+    let refs = inspect_refs(&repo_dir, &["future", "past", "present"]);
+    let cg = Graph::from_info_dir(repo_dir.join(".git").join("objects").join("info"))?;
+    check_common(&cg, &refs);
+    assert!(cg.has_corrected_commit_dates());
+
+    let future = cg.commit_at(refs["future"].pos());
+    let past = cg.commit_at(refs["past"].pos());
+    let present = cg.commit_at(refs["present"].pos());
+    assert_eq!(future.corrected_commit_date(), Some(future.committer_timestamp()));
+    assert_eq!(
+        past.corrected_commit_date(),
+        Some(future.committer_timestamp() + 1),
+        "the commit is older than its parent, so its corrected date is larger than the parent's, and it's read from the overflow chunk"
+    );
+    assert_eq!(
+        present.corrected_commit_date(),
+        Some(present.committer_timestamp()),
+        "the commit is newer than its parent"
+    );
+    assert_eq!(present.generation(), 3, "topological levels are still available");
+    assert_eq!(cg.generation_of(&present), present.committer_timestamp());
+    Ok(())
+}
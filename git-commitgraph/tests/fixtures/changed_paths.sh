@@ -0,0 +1,28 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+
+mkdir dir
+echo a > a
+echo b > dir/b
+echo c > "dir/ü.txt"
+git add . && git commit -q -m "root"
+git tag root
+
+echo a2 > a
+git commit -q -am "change a"
+git tag change-a
+
+echo b2 > dir/b
+git commit -q -am "change dir/b"
+git tag change-dir-b
+
+mkdir -p sub/dir
+echo d > sub/dir/d
+git add . && git commit -q -m "add sub/dir/d"
+git tag add-sub-dir-d
+
+git rm -q "dir/ü.txt"
+git commit -q -m "remove dir/ü.txt"
+git tag remove-dir-u
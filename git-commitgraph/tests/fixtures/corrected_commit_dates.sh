@@ -0,0 +1,15 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+
+git checkout -q -b main
+GIT_COMMITTER_DATE="2090-01-01 00:00:00 +0000" git commit -q --allow-empty -m future
+git tag future
+# the offset of the corrected commit date to the commit date doesn't fit into 31 bits anymore
+GIT_COMMITTER_DATE="2000-01-01 00:00:00 +0000" git commit -q --allow-empty -m past
+git tag past
+GIT_COMMITTER_DATE="2090-01-01 00:00:10 +0000" git commit -q --allow-empty -m present
+git tag present
+
+git -c commitGraph.generationVersion=2 commit-graph write --no-progress --reachable
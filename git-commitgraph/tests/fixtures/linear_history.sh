@@ -0,0 +1,9 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+
+for i in $(seq 1 12); do
+  git commit -q --allow-empty -m "commit$i"
+  git tag "commit$i"
+done
@@ -0,0 +1,309 @@
+use std::{
+    io::{BufRead, Cursor},
+    path::Path,
+    process::Command,
+    sync::atomic::AtomicBool,
+};
+
+use git_commitgraph::{
+    bloom,
+    file::{write::Commit, File},
+    graph::write::{Mode, Options},
+    Graph,
+};
+use git_features::progress;
+use git_testtools::scripted_fixture_writable;
+
+/// Obtain all commits reachable from `revs` as seen by git, in the format needed for writing them.
+fn commits_of(repo_dir: &Path, revs: &[&str]) -> Vec<Commit> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo_dir)
+        .args(["log", "--format=%H %T %ct %P"])
+        .args(revs)
+        .arg("--")
+        .env_remove("GIT_DIR")
+        .output()
+        .expect("failed to execute `git log`");
+    assert!(output.status.success(), "git log failed");
+    Cursor::new(output.stdout)
+        .lines()
+        .map(|line| {
+            let line = line.expect("valid output");
+            let mut parts = line.trim_end().split(' ');
+            let mut id = || git_hash::ObjectId::from_hex(parts.next().expect("id").as_bytes()).expect("valid hex");
+            let (id, tree) = (id(), id());
+            let committer_timestamp = parts.next().expect("time").parse().expect("valid timestamp");
+            let parents = parts
+                .map(|p| git_hash::ObjectId::from_hex(p.as_bytes()).expect("valid hex"))
+                .collect();
+            Commit {
+                id,
+                tree,
+                parents,
+                committer_timestamp,
+                bloom_filter: None,
+            }
+        })
+        .collect()
+}
+
+/// Return the paths changed by `commit` compared to its first parent, or all of its paths if it is a root commit.
+fn changed_paths_of(repo_dir: &Path, commit: &git_hash::oid) -> Vec<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo_dir)
+        .args(["diff-tree", "-r", "-z", "--name-only", "--no-commit-id", "--root"])
+        .arg(commit.to_string())
+        .env_remove("GIT_DIR")
+        .output()
+        .expect("failed to execute `git diff-tree`");
+    assert!(output.status.success(), "git diff-tree failed");
+    output
+        .stdout
+        .split(|b| *b == 0)
+        .filter(|path| !path.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+fn git_verifies(repo_dir: &Path) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(repo_dir)
+        .args(["commit-graph", "verify", "--no-progress"])
+        .env_remove("GIT_DIR")
+        .status()
+        .expect("git can be executed")
+        .success()
+}
+
+fn write(info_dir: &Path, commits: Vec<Commit>, mode: Mode) -> Option<git_commitgraph::graph::write::Outcome> {
+    Graph::write_to_info_dir(
+        info_dir,
+        commits,
+        progress::Discard,
+        &AtomicBool::default(),
+        Options {
+            mode,
+            object_hash: git_hash::Kind::Sha1,
+        },
+    )
+    .expect("writing succeeds")
+}
+
+/// Return the amount of commits in each layer of the verified commit-graph in `info_dir`, from the base layer upwards.
+fn layer_sizes(info_dir: &Path) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+    let graph = Graph::from_info_dir(info_dir)?;
+    graph.verify_integrity(|_| Ok::<_, std::convert::Infallible>(()))?;
+
+    let single_file = info_dir.join("commit-graph");
+    let paths = if single_file.is_file() {
+        vec![single_file]
+    } else {
+        let graphs_dir = info_dir.join("commit-graphs");
+        std::fs::read_to_string(graphs_dir.join("commit-graph-chain"))?
+            .lines()
+            .map(|hex| graphs_dir.join(format!("graph-{hex}.graph")))
+            .collect()
+    };
+    let sizes = paths
+        .into_iter()
+        .map(|path| File::at(path).map(|file| file.num_commits()))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(sizes.iter().sum::<u32>(), graph.num_commits());
+    Ok(sizes)
+}
+
+#[test]
+fn single_file_is_identical_to_the_one_written_by_git() -> crate::Result {
+    let dir = scripted_fixture_writable("octopus_merges.sh")?;
+    let info_dir = dir.path().join(".git").join("objects").join("info");
+    std::fs::remove_file(info_dir.join("commit-graph"))?;
+
+    let outcome = write(&info_dir, commits_of(dir.path(), &["--all"]), Mode::Single).expect("written");
+    assert_eq!(outcome.num_commits, 7);
+    assert_eq!(outcome.num_layers, 1);
+    assert_eq!(outcome.path, info_dir.join("commit-graph"));
+    assert!(git_verifies(dir.path()));
+    let ours = std::fs::read(&outcome.path)?;
+
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir.path())
+        .args([
+            "-c",
+            "commitGraph.generationVersion=1",
+            "commit-graph",
+            "write",
+            "--no-progress",
+            "--reachable",
+        ])
+        .env_remove("GIT_DIR")
+        .status()?;
+    assert!(status.success());
+    assert_eq!(
+        ours,
+        std::fs::read(&outcome.path)?,
+        "including extra edges of octopus merges, our file is byte-for-byte the same"
+    );
+
+    assert!(
+        write(&info_dir, commits_of(dir.path(), &["--all"]), Mode::Single).is_none(),
+        "nothing is written if all commits are known already"
+    );
+    Ok(())
+}
+
+#[test]
+fn changed_path_filters_are_identical_to_the_ones_written_by_git() -> crate::Result {
+    let dir = scripted_fixture_writable("changed_paths.sh")?;
+    let info_dir = dir.path().join(".git").join("objects").join("info");
+
+    let mut commits = commits_of(dir.path(), &["--all"]);
+    for commit in &mut commits {
+        let paths = changed_paths_of(dir.path(), &commit.id);
+        commit.bloom_filter = Some(bloom::compute_filter(
+            paths.iter().map(|path| path.as_slice().into()),
+            &bloom::Settings::default(),
+        ));
+    }
+    let outcome = write(&info_dir, commits, Mode::Single).expect("written");
+    assert!(git_verifies(dir.path()));
+    let ours = std::fs::read(&outcome.path)?;
+
+    let graph = Graph::from_info_dir(&info_dir)?;
+    assert_eq!(graph.bloom_filter_settings(), Some(bloom::Settings::default()));
+
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir.path())
+        .args([
+            "-c",
+            "commitGraph.generationVersion=1",
+            "commit-graph",
+            "write",
+            "--no-progress",
+            "--reachable",
+            "--changed-paths",
+        ])
+        .env_remove("GIT_DIR")
+        .status()?;
+    assert!(status.success());
+    assert_eq!(
+        ours,
+        std::fs::read(&outcome.path)?,
+        "the BIDX and BDAT chunks are byte-for-byte the same, even for non-ASCII paths"
+    );
+
+    std::fs::remove_file(&outcome.path)?;
+    let mut commits = commits_of(dir.path(), &["change-a"]);
+    commits[0].bloom_filter = Some(bloom::compute_filter(None, &bloom::Settings::default()));
+    write(&info_dir, commits, Mode::Single).expect("written");
+    let file = File::at(info_dir.join("commit-graph"))?;
+    let unknown = file
+        .iter_commits()
+        .filter(|commit| commit.bloom_filter().is_none())
+        .count();
+    assert_eq!(unknown, 1, "commits without filter are marked as such");
+    assert!(git_verifies(dir.path()));
+    Ok(())
+}
+
+#[test]
+fn split_layers_are_merged_according_to_their_size() -> crate::Result {
+    let dir = scripted_fixture_writable("linear_history.sh")?;
+    let info_dir = dir.path().join(".git").join("objects").join("info");
+    let graphs_dir = info_dir.join("commit-graphs");
+
+    write(&info_dir, commits_of(dir.path(), &["commit8"]), Mode::Single).expect("written");
+    assert_eq!(layer_sizes(&info_dir)?, [8]);
+
+    let split = Mode::Split {
+        size_multiple: 2,
+        max_commits: None,
+    };
+    let outcome = write(&info_dir, commits_of(dir.path(), &["commit9"]), split).expect("written");
+    assert_eq!(outcome.num_commits, 1, "the new layer contains only the new commit");
+    assert_eq!(outcome.num_layers, 2);
+    assert!(
+        !info_dir.join("commit-graph").exists(),
+        "the previous file became the base layer"
+    );
+    assert_eq!(layer_sizes(&info_dir)?, [8, 1]);
+    assert!(git_verifies(dir.path()));
+
+    let outcome = write(&info_dir, commits_of(dir.path(), &["commit10"]), split).expect("written");
+    assert_eq!(
+        layer_sizes(&info_dir)?,
+        [8, 2],
+        "the small top layer was merged, but the base layer is more than twice as large"
+    );
+    assert_eq!(outcome.num_commits, 2);
+    assert_eq!(
+        std::fs::read_dir(&graphs_dir)?.count(),
+        3,
+        "the merged layer was removed, leaving only the chain and two graph files"
+    );
+    assert!(git_verifies(dir.path()));
+
+    write(&info_dir, commits_of(dir.path(), &["commit11"]), Mode::SplitNoMerge).expect("written");
+    assert_eq!(layer_sizes(&info_dir)?, [8, 2, 1]);
+    assert!(git_verifies(dir.path()));
+
+    assert!(
+        write(&info_dir, commits_of(dir.path(), &["commit11"]), split).is_none(),
+        "without new commits, nothing is merged"
+    );
+
+    let outcome = write(
+        &info_dir,
+        commits_of(dir.path(), &["commit12"]),
+        Mode::Split {
+            size_multiple: 2,
+            max_commits: Some(1),
+        },
+    )
+    .expect("written");
+    assert_eq!(
+        layer_sizes(&info_dir)?,
+        [12],
+        "the amount of commits exceeds the maximum, so all layers are merged"
+    );
+    assert_eq!(outcome.num_layers, 1);
+    assert!(git_verifies(dir.path()));
+
+    write(&info_dir, Vec::new(), Mode::SplitNoMerge);
+    let outcome = write(&info_dir, Vec::new(), Mode::Single).expect("the split graph is turned into a single file");
+    assert_eq!(outcome.num_commits, 12);
+    assert!(!graphs_dir.join("commit-graph-chain").exists());
+    assert_eq!(layer_sizes(&info_dir)?, [12]);
+    assert!(git_verifies(dir.path()));
+
+    let outcome = write(&info_dir, Vec::new(), Mode::SplitReplace).expect("the single file is turned into a layer");
+    assert_eq!(outcome.num_layers, 1);
+    assert!(!info_dir.join("commit-graph").exists());
+    assert!(git_verifies(dir.path()));
+    Ok(())
+}
+
+#[test]
+fn parents_must_be_known() {
+    let id = |hex: &str| git_hash::ObjectId::from_hex(hex.as_bytes()).expect("valid hex");
+    let err = File::write_to(
+        vec![Commit {
+            id: id("1111111111111111111111111111111111111111"),
+            tree: id("4b825dc642cb6eb9a060e54bf8d69288fbee4904"),
+            parents: vec![id("2222222222222222222222222222222222222222")],
+            committer_timestamp: 0,
+            bloom_filter: None,
+        }],
+        None,
+        Vec::new(),
+        progress::Discard,
+        &AtomicBool::default(),
+        git_hash::Kind::Sha1,
+    )
+    .unwrap_err();
+    assert!(matches!(err, git_commitgraph::file::write::Error::MissingParent { .. }));
+}
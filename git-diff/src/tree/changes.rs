@@ -20,9 +20,48 @@ pub enum Error {
     Cancelled,
     #[error(transparent)]
     EntriesDecode(#[from] git_object::decode::Error),
+    #[error(transparent)]
+    Rewrites(#[from] tree::rewrites::Error),
 }
 
 impl<'a> tree::Changes<'a> {
+    /// Like [`needed_to_obtain()`][Self::needed_to_obtain()], but hold back additions and deletions of blobs and symbolic links
+    /// until all changes are known to find renames and, if configured, copies among them as configured by `rewrites`.
+    ///
+    /// * `find_tree` works like `locate` in [`needed_to_obtain()`][Self::needed_to_obtain()], and is also used to traverse
+    ///   `self` if copies should be found in the entire source tree.
+    /// * `find_blob` is a function `f(object_id, &mut buffer) -> Result<BlobRef, E>` to obtain the data of blobs to determine
+    ///   their similarity.
+    /// * `delegate` receives all changes that aren't held back through [`Visit::visit()`][tree::Visit::visit()], and all held back
+    ///   additions and deletions through [`visit_tracked()`][tree::rewrites::Visit::visit_tracked()] once the traversal is complete,
+    ///   with their source set if they turned out to be renames or copies.
+    pub fn needed_to_obtain_with_rewrites<FindTreeFn, FindBlobFn, R, StateMut, E1, E2>(
+        self,
+        other: git_object::TreeRefIter<'_>,
+        state: StateMut,
+        mut find_tree: FindTreeFn,
+        find_blob: FindBlobFn,
+        rewrites: tree::rewrites::Rewrites,
+        delegate: &mut R,
+    ) -> Result<(), Error>
+    where
+        FindTreeFn: for<'b> FnMut(&oid, &'b mut Vec<u8>) -> Result<git_object::TreeRefIter<'b>, E1>,
+        E1: std::error::Error + Send + Sync + 'static,
+        FindBlobFn: for<'b> FnMut(&oid, &'b mut Vec<u8>) -> Result<git_object::BlobRef<'b>, E2>,
+        E2: std::error::Error + Send + Sync + 'static,
+        R: tree::rewrites::Visit,
+        StateMut: BorrowMut<tree::State>,
+    {
+        let src_tree = self.0.clone().unwrap_or_default();
+        let mut tracking = tree::rewrites::Delegate::new(delegate, rewrites);
+        self.needed_to_obtain(other, state, &mut find_tree, &mut tracking)?;
+        let tree::rewrites::Delegate { inner, mut tracker, .. } = tracking;
+        match tracker.emit(|dest| inner.visit_tracked(dest), find_blob, src_tree, find_tree)? {
+            tree::visit::Action::Continue => Ok(()),
+            tree::visit::Action::Cancel => Err(Error::Cancelled),
+        }
+    }
+
     /// Calculate the changes that would need to be applied to `self` to get `other`.
     ///
     /// * The `state` maybe owned or mutably borrowed to allow reuses allocated data structures through multiple runs.
@@ -38,8 +77,8 @@ impl<'a> tree::Changes<'a> {
     /// * Tree entries are expected to be ordered using [`tree-entry-comparison`][git_cmp_c] (the same [in Rust][git_cmp_rs])
     /// * it does a breadth first iteration as buffer space only fits two trees, the current one on the one we compare with.
     /// * does not do rename tracking but attempts to reduce allocations to zero (so performance is mostly determined
-    ///   by the delegate implementation which should be as specific as possible. Rename tracking can be computed on top of the changes
-    ///   received by the `delegate`.
+    ///   by the delegate implementation which should be as specific as possible. Rename tracking is performed by
+    ///   [`needed_to_obtain_with_rewrites()`][Self::needed_to_obtain_with_rewrites()] on top of the changes seen here.
     /// * cycle checking is not performed, but can be performed in the delegate which can return [`tree::visit::Action::Cancel`] to stop the traversal.
     /// * [std::mem::ManuallyDrop] is used because `Peekable` is needed. When using it as wrapper around our no-drop iterators, all of the sudden
     ///   borrowcheck complains as Drop is present (even though it's not)
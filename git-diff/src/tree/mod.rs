@@ -36,6 +36,9 @@ where
 ///
 pub mod changes;
 
+///
+pub mod rewrites;
+
 ///
 pub mod visit;
 #[doc(inline)]
@@ -0,0 +1,576 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    hash::Hasher,
+    ops::Range,
+};
+
+use git_hash::{oid, ObjectId};
+use git_object::{
+    bstr::{BStr, BString, ByteSlice, ByteVec},
+    tree::EntryMode,
+    TreeRefIter,
+};
+
+use crate::tree::{
+    self,
+    visit::{Action, Change},
+};
+
+/// A structure to capture how to perform rename and copy tracking
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rewrites {
+    /// If `Some(…)`, do also find copies. `None` is the default which does not try to detect copies at all.
+    ///
+    /// Note that this is an even more expensive operation than detecting renames as files.
+    pub copies: Option<Copies>,
+    /// The percentage of similarity needed for files to be considered renamed, defaulting to `Some(0.5)`.
+    /// This field is similar to `git diff -M50%`.
+    ///
+    /// If `None`, files are only considered equal if their content matches 100%.
+    /// Note that values greater than 1.0 have no different effect than 1.0.
+    pub percentage: Option<f32>,
+    /// The amount of files to consider for fuzzy rename or copy tracking. Defaults to 1000, meaning that only 1000*1000
+    /// combinations can be tested for fuzzy matches, i.e. the ones that try to find matches by comparing similarity.
+    /// If 0, there is no limit.
+    ///
+    /// If the limit would not be enough to test the entire set of combinations, the algorithm will trade in precision and not
+    /// run the fuzzy version of identity tests at all. That way results are never partial.
+    pub limit: usize,
+}
+
+/// The default settings for rewrites according to the git configuration defaults.
+impl Default for Rewrites {
+    fn default() -> Self {
+        Rewrites {
+            copies: None,
+            percentage: Some(0.5),
+            limit: 1000,
+        }
+    }
+}
+
+/// From where to source copies
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CopySource {
+    /// Find copies from the set of modified files only.
+    FromSetOfModifiedFiles,
+    /// Find copies from the set of changed files, as well as all files known to the source (i.e. previous state) of the tree.
+    ///
+    /// This can be an expensive operation as it scales exponentially with the total amount of files in the tree.
+    FromSetOfModifiedFilesAndSourceTree,
+}
+
+/// How to determine copied files.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Copies {
+    /// The set of files to search when finding the source of copies.
+    pub source: CopySource,
+    /// Equivalent to [`Rewrites::percentage`], but used for copy tracking.
+    ///
+    /// Useful to have similarity-based rename tracking and cheaper copy tracking, which also is the default
+    /// as only identity plays a role.
+    pub percentage: Option<f32>,
+}
+
+impl Default for Copies {
+    fn default() -> Self {
+        Copies {
+            source: CopySource::FromSetOfModifiedFiles,
+            percentage: Some(0.5),
+        }
+    }
+}
+
+/// The error returned by [`Tracker::emit()`].
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("The blob {oid} could not be found for similarity checking")]
+    FindExistingBlob {
+        oid: ObjectId,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
+    #[error("The tree {oid} could not be found while obtaining all entries of the source tree to find copies")]
+    FindExistingTree {
+        oid: ObjectId,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
+    #[error(transparent)]
+    EntriesDecode(#[from] git_object::decode::Error),
+}
+
+/// A [`Visit`][tree::Visit] implementation which also receives the changes that were held back to find rewrites among them,
+/// for use with [`Changes::needed_to_obtain_with_rewrites()`][tree::Changes::needed_to_obtain_with_rewrites()].
+pub trait Visit: tree::Visit {
+    /// Receive an addition or deletion that was held back until all changes were known, along with its full path.
+    /// If it turned out to be the destination of a rename or copy, `destination.source` is set.
+    fn visit_tracked(&mut self, destination: Destination<'_>) -> Action;
+}
+
+/// A rewrite destination along with its source, if a source was found.
+#[derive(Debug, Clone)]
+pub struct Destination<'a> {
+    /// The change as it was originally provided, with an `Addition` or `Deletion` variant.
+    pub change: Change,
+    /// The location of the change.
+    pub location: &'a BStr,
+    /// The source of the change if it is a rewrite.
+    pub source: Option<Source<'a>>,
+}
+
+/// The source of a rewrite.
+#[derive(Debug, Clone, Copy)]
+pub struct Source<'a> {
+    /// The mode of the entry in the source tree.
+    pub mode: EntryMode,
+    /// The object id of the entry in the source tree.
+    pub id: ObjectId,
+    /// The location of the entry in the source tree.
+    pub location: &'a BStr,
+    /// If `true`, this is a copy, otherwise it's a rename.
+    pub copy: bool,
+}
+
+/// A set of tracked items allows to figure out their relations by figuring out their similarity.
+pub struct Tracker {
+    /// The tracked items thus far, which will be used to determine renames/copied later.
+    items: Vec<Item>,
+    /// A place to store all paths in to reduce amount of allocations.
+    path_backing: Vec<u8>,
+    /// How to track copies and/or rewrites.
+    rewrites: Rewrites,
+}
+
+/// What an item is used for when figuring out rewrites.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Kind {
+    /// A possible rename destination, or copy destination if copies are tracked.
+    Addition,
+    /// A possible rename source, or copy source if copies are tracked.
+    Deletion,
+    /// A possible copy source, which was already emitted as modification.
+    Modification,
+    /// A possible copy source from the source tree, which never changed.
+    Unchanged,
+}
+
+struct Item {
+    /// The full path to the entry, pointing into the path backing.
+    location: Range<usize>,
+    kind: Kind,
+    /// The mode of the entry in its respective tree. For modifications, it's the previous mode.
+    mode: EntryMode,
+    /// The object id of the entry in its respective tree. For modifications, it's the previous id.
+    id: ObjectId,
+    /// The original change as provided by the diff, only set for additions and deletions.
+    change: Option<Change>,
+    /// For destinations, the index of the item serving as source along with the copy flag, if a match was found.
+    source: Option<(usize, bool)>,
+    /// If `true`, the item was used as rename source and thus shouldn't be emitted anymore.
+    consumed: bool,
+}
+
+impl Tracker {
+    /// Create a new instance with the given `rewrites` configuration.
+    pub fn new(rewrites: Rewrites) -> Self {
+        Tracker {
+            items: vec![],
+            path_backing: vec![],
+            rewrites,
+        }
+    }
+
+    /// Hold back `change` at `location` if it may be part of a rewrite, or return it if it should be emitted right away
+    /// as the information isn't needed for what we have to track.
+    pub fn try_push_change(&mut self, change: Change, location: &BStr) -> Option<Change> {
+        let (kind, mode, id) = match &change {
+            Change::Addition { entry_mode, oid } => (Kind::Addition, *entry_mode, *oid),
+            Change::Deletion { entry_mode, oid } => (Kind::Deletion, *entry_mode, *oid),
+            Change::Modification {
+                previous_entry_mode,
+                previous_oid,
+                entry_mode,
+                ..
+            } => {
+                if self.rewrites.copies.is_some() && previous_entry_mode.is_blob() && entry_mode.is_blob() {
+                    self.push_item(location, Kind::Modification, *previous_entry_mode, *previous_oid, None);
+                }
+                return Some(change);
+            }
+        };
+        if !is_trackable(mode) {
+            return Some(change);
+        }
+        self.push_item(location, kind, mode, id, Some(change));
+        None
+    }
+
+    /// Find renames and copies among the tracked items and call `cb` for each addition or deletion in the order they were pushed,
+    /// turning them into rewrites if a source could be found.
+    ///
+    /// * `find_blob` is a function `f(object_id, &mut buffer) -> Result<BlobRef, E>` to obtain the data of sources and destinations
+    ///   to determine their similarity.
+    /// * `src_tree` is the tree the changes were computed from, which is only traversed with `find_tree` if copies should be found
+    ///   in the entire source tree. `find_tree` works like the one passed to [`needed_to_obtain()`][tree::Changes::needed_to_obtain()].
+    pub fn emit<FindTreeFn, FindBlobFn, E1, E2>(
+        &mut self,
+        mut cb: impl FnMut(Destination<'_>) -> Action,
+        mut find_blob: FindBlobFn,
+        src_tree: TreeRefIter<'_>,
+        find_tree: FindTreeFn,
+    ) -> Result<Action, Error>
+    where
+        FindBlobFn: for<'b> FnMut(&oid, &'b mut Vec<u8>) -> Result<git_object::BlobRef<'b>, E1>,
+        E1: std::error::Error + Send + Sync + 'static,
+        FindTreeFn: for<'b> FnMut(&oid, &'b mut Vec<u8>) -> Result<TreeRefIter<'b>, E2>,
+        E2: std::error::Error + Send + Sync + 'static,
+    {
+        let mut signatures = HashMap::new();
+        let mut buf = Vec::new();
+        self.match_pairs(&[Kind::Deletion], self.rewrites.percentage, false, |idx, items| {
+            signature_for(&mut signatures, idx, items, &mut find_blob, &mut buf)
+        })?;
+
+        if let Some(copies) = self.rewrites.copies {
+            if copies.source == CopySource::FromSetOfModifiedFilesAndSourceTree {
+                self.push_unchanged_entries(src_tree, find_tree)?;
+            }
+            self.match_pairs(
+                &[Kind::Deletion, Kind::Modification, Kind::Unchanged],
+                copies.percentage,
+                true,
+                |idx, items| signature_for(&mut signatures, idx, items, &mut find_blob, &mut buf),
+            )?;
+        }
+
+        for item in &self.items {
+            let change = match (&item.change, item.kind) {
+                (Some(change), Kind::Addition) => change.clone(),
+                (Some(change), Kind::Deletion) if !item.consumed => change.clone(),
+                _ => continue,
+            };
+            let source = item.source.map(|(src_idx, copy)| {
+                let src = &self.items[src_idx];
+                Source {
+                    mode: src.mode,
+                    id: src.id,
+                    location: self.path_backing[src.location.clone()].as_bstr(),
+                    copy,
+                }
+            });
+            if cb(Destination {
+                change,
+                location: self.path_backing[item.location.clone()].as_bstr(),
+                source,
+            }) == Action::Cancel
+            {
+                return Ok(Action::Cancel);
+            }
+        }
+        Ok(Action::Continue)
+    }
+}
+
+impl Tracker {
+    fn push_item(&mut self, location: &BStr, kind: Kind, mode: EntryMode, id: ObjectId, change: Option<Change>) {
+        let start = self.path_backing.len();
+        self.path_backing.extend_from_slice(location);
+        self.items.push(Item {
+            location: start..self.path_backing.len(),
+            kind,
+            mode,
+            id,
+            change,
+            source: None,
+            consumed: false,
+        });
+    }
+
+    /// Track all trackable entries of `tree` and its subtrees as possible copy sources, breadth first.
+    fn push_unchanged_entries<FindTreeFn, E>(
+        &mut self,
+        tree: TreeRefIter<'_>,
+        mut find_tree: FindTreeFn,
+    ) -> Result<(), Error>
+    where
+        FindTreeFn: for<'b> FnMut(&oid, &'b mut Vec<u8>) -> Result<TreeRefIter<'b>, E>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let mut trees = VecDeque::new();
+        self.push_tree_entries(tree, BString::default(), &mut trees)?;
+        let mut buf = Vec::new();
+        while let Some((location, oid)) = trees.pop_front() {
+            let tree = find_tree(&oid, &mut buf).map_err(|err| Error::FindExistingTree {
+                oid,
+                source: err.into(),
+            })?;
+            self.push_tree_entries(tree, location, &mut trees)?;
+        }
+        Ok(())
+    }
+
+    fn push_tree_entries(
+        &mut self,
+        tree: TreeRefIter<'_>,
+        prefix: BString,
+        trees: &mut VecDeque<(BString, ObjectId)>,
+    ) -> Result<(), Error> {
+        for entry in tree {
+            let entry = entry?;
+            let mut location = prefix.clone();
+            if !location.is_empty() {
+                location.push(b'/');
+            }
+            location.push_str(entry.filename);
+            if entry.mode.is_tree() {
+                trees.push_back((location, entry.oid.to_owned()));
+            } else if is_trackable(entry.mode) {
+                self.push_item(
+                    location.as_ref(),
+                    Kind::Unchanged,
+                    entry.mode,
+                    entry.oid.to_owned(),
+                    None,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Match all unmatched additions with items of `source_kinds`, first by identity, then by similarity if `percentage` is set.
+    /// If `copy` is `false`, sources can only be used once, otherwise even sources of renames may be used.
+    fn match_pairs(
+        &mut self,
+        source_kinds: &[Kind],
+        percentage: Option<f32>,
+        copy: bool,
+        mut signature: impl FnMut(usize, &[Item]) -> Result<Signature, Error>,
+    ) -> Result<(), Error> {
+        let is_source = |item: &Item| source_kinds.contains(&item.kind) && (copy || !item.consumed);
+        let mut sources_by_id = HashMap::<_, Vec<usize>>::new();
+        for (idx, item) in self.items.iter().enumerate().filter(|(_, item)| is_source(item)) {
+            sources_by_id.entry(item.id).or_default().push(idx);
+        }
+        let destinations: Vec<_> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, item)| (item.kind == Kind::Addition && item.source.is_none()).then_some(idx))
+            .collect();
+
+        for &dst_idx in &destinations {
+            let candidates = match sources_by_id.get_mut(&self.items[dst_idx].id) {
+                Some(candidates) if !candidates.is_empty() => candidates,
+                _ => continue,
+            };
+            let dst_name = file_name(&self.path_backing[self.items[dst_idx].location.clone()]);
+            let pos = candidates
+                .iter()
+                .position(|&src_idx| file_name(&self.path_backing[self.items[src_idx].location.clone()]) == dst_name)
+                .unwrap_or(0);
+            let src_idx = if copy { candidates[pos] } else { candidates.remove(pos) };
+            self.set_source(dst_idx, src_idx, copy);
+        }
+
+        let percentage = match percentage {
+            Some(percentage) if percentage < 1.0 => percentage,
+            _ => return Ok(()),
+        };
+        let destinations: Vec<_> = destinations
+            .into_iter()
+            .filter(|&idx| self.items[idx].source.is_none() && self.items[idx].mode.is_blob())
+            .collect();
+        let sources: Vec<_> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, item)| (is_source(item) && item.mode.is_blob()).then_some(idx))
+            .collect();
+        if destinations.is_empty()
+            || sources.is_empty()
+            || (self.rewrites.limit != 0
+                && destinations.len().saturating_mul(sources.len())
+                    > self.rewrites.limit.saturating_mul(self.rewrites.limit))
+        {
+            return Ok(());
+        }
+
+        let mut candidates = Vec::new();
+        for &dst_idx in &destinations {
+            let dst = signature(dst_idx, &self.items)?;
+            if dst.size == 0 {
+                continue;
+            }
+            for &src_idx in &sources {
+                let src = signature(src_idx, &self.items)?;
+                if src.size == 0 || (src.size.min(dst.size) as f32 / src.size.max(dst.size) as f32) < percentage {
+                    continue;
+                }
+                let similarity = src.similarity(&dst);
+                if similarity >= percentage {
+                    candidates.push((similarity, dst_idx, src_idx));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        for (_, dst_idx, src_idx) in candidates {
+            if self.items[dst_idx].source.is_some() || (!copy && self.items[src_idx].consumed) {
+                continue;
+            }
+            self.set_source(dst_idx, src_idx, copy);
+        }
+        Ok(())
+    }
+
+    fn set_source(&mut self, dst_idx: usize, src_idx: usize, copy: bool) {
+        self.items[dst_idx].source = Some((src_idx, copy));
+        if !copy {
+            self.items[src_idx].consumed = true;
+        }
+    }
+}
+
+fn is_trackable(mode: EntryMode) -> bool {
+    matches!(mode, EntryMode::Blob | EntryMode::BlobExecutable | EntryMode::Link)
+}
+
+fn file_name(path: &[u8]) -> &[u8] {
+    path.rfind_byte(b'/').map_or(path, |pos| &path[pos + 1..])
+}
+
+fn signature_for<FindBlobFn, E>(
+    cache: &mut HashMap<usize, Signature>,
+    idx: usize,
+    items: &[Item],
+    find_blob: &mut FindBlobFn,
+    buf: &mut Vec<u8>,
+) -> Result<Signature, Error>
+where
+    FindBlobFn: for<'b> FnMut(&oid, &'b mut Vec<u8>) -> Result<git_object::BlobRef<'b>, E>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    if let Some(signature) = cache.get(&idx) {
+        return Ok(signature.clone());
+    }
+    let oid = items[idx].id;
+    let blob = find_blob(&oid, buf).map_err(|err| Error::FindExistingBlob {
+        oid,
+        source: err.into(),
+    })?;
+    let signature = Signature::from_data(blob.data);
+    cache.insert(idx, signature.clone());
+    Ok(signature)
+}
+
+/// A representation of the contents of a blob suitable for quickly estimating its similarity to another blob,
+/// similar to what `git` does.
+#[derive(Clone)]
+struct Signature {
+    /// The size of the blob in bytes.
+    size: usize,
+    /// The amount of bytes per span hash, with spans ending at newlines or after 64 bytes.
+    spans: std::rc::Rc<HashMap<u64, usize>>,
+}
+
+impl Signature {
+    fn from_data(data: &[u8]) -> Self {
+        let mut spans = HashMap::<u64, usize>::new();
+        let mut hash_span = |span: &[u8]| {
+            let mut hasher = DefaultHasher::new();
+            hasher.write(span);
+            *spans.entry(hasher.finish()).or_default() += span.len();
+        };
+        let mut start = 0;
+        for (pos, byte) in data.iter().enumerate() {
+            if *byte == b'\n' || pos + 1 - start == 64 {
+                hash_span(&data[start..=pos]);
+                start = pos + 1;
+            }
+        }
+        if start < data.len() {
+            hash_span(&data[start..]);
+        }
+        Signature {
+            size: data.len(),
+            spans: spans.into(),
+        }
+    }
+
+    /// Return a value between 0.0 and 1.0 to indicate how similar `self` is to `other`, with 1.0 meaning they are equal.
+    fn similarity(&self, other: &Signature) -> f32 {
+        let max_size = self.size.max(other.size);
+        if max_size == 0 {
+            return 1.0;
+        }
+        let copied: usize = self
+            .spans
+            .iter()
+            .filter_map(|(hash, count)| other.spans.get(hash).map(|other_count| (*count).min(*other_count)))
+            .sum();
+        copied as f32 / max_size as f32
+    }
+}
+
+/// A delegate keeping track of full paths to feed changes into a [`Tracker`], passing everything else on to `inner`.
+pub(crate) struct Delegate<'a, R> {
+    pub inner: &'a mut R,
+    pub tracker: Tracker,
+    path_deque: VecDeque<BString>,
+    path: BString,
+}
+
+impl<'a, R> Delegate<'a, R> {
+    pub fn new(inner: &'a mut R, rewrites: Rewrites) -> Self {
+        Delegate {
+            inner,
+            tracker: Tracker::new(rewrites),
+            path_deque: Default::default(),
+            path: Default::default(),
+        }
+    }
+
+    fn pop_element(&mut self) {
+        if let Some(pos) = self.path.rfind_byte(b'/') {
+            self.path.resize(pos, 0);
+        } else {
+            self.path.clear();
+        }
+    }
+
+    fn push_element(&mut self, name: &BStr) {
+        if !self.path.is_empty() {
+            self.path.push(b'/');
+        }
+        self.path.push_str(name);
+    }
+}
+
+impl<R: Visit> tree::Visit for Delegate<'_, R> {
+    fn pop_front_tracked_path_and_set_current(&mut self) {
+        self.path = self.path_deque.pop_front().expect("every parent is set only once");
+        self.inner.pop_front_tracked_path_and_set_current();
+    }
+
+    fn push_back_tracked_path_component(&mut self, component: &BStr) {
+        self.push_element(component);
+        self.path_deque.push_back(self.path.clone());
+        self.inner.push_back_tracked_path_component(component);
+    }
+
+    fn push_path_component(&mut self, component: &BStr) {
+        self.push_element(component);
+        self.inner.push_path_component(component);
+    }
+
+    fn pop_path_component(&mut self) {
+        self.pop_element();
+        self.inner.pop_path_component();
+    }
+
+    fn visit(&mut self, change: Change) -> Action {
+        match self.tracker.try_push_change(change, self.path.as_ref()) {
+            Some(change) => self.inner.visit(change),
+            None => Action::Continue,
+        }
+    }
+}
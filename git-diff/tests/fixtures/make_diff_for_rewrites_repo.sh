@@ -0,0 +1,28 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+git config core.autocrlf false
+
+git checkout -q -b main
+
+mkdir dir
+echo a > a
+seq 10 > dir/s
+git add .
+git commit -qm base
+git tag base
+
+git mv a a-renamed
+git commit -qm 'a renamed'
+git tag identity
+
+git mv dir/s dir/s-renamed
+echo 11 >> dir/s-renamed
+git commit -qam 'dir/s renamed and modified'
+git tag similar
+
+cp dir/s-renamed s-copy
+git add .
+git commit -qm 'dir/s-renamed copied'
+git tag copy
@@ -612,4 +612,170 @@ mod changes {
             Ok(())
         }
     }
+
+    mod to_obtain_tree_with_rewrites {
+        use git_diff::tree::{
+            rewrites::{Copies, CopySource, Destination, Rewrites},
+            visit::{Action, Change},
+            Recorder, Visit,
+        };
+        use git_object::{
+            bstr::{BStr, BString, ByteSlice},
+            TreeRefIter,
+        };
+        use git_odb::FindExt;
+
+        /// Records all changes that weren't held back, along with the held back ones and their sources, if any.
+        #[derive(Default)]
+        struct Delegate {
+            changes: Recorder,
+            tracked: Vec<(BString, Option<(BString, bool)>)>,
+        }
+
+        impl Visit for Delegate {
+            fn pop_front_tracked_path_and_set_current(&mut self) {
+                self.changes.pop_front_tracked_path_and_set_current()
+            }
+
+            fn push_back_tracked_path_component(&mut self, component: &BStr) {
+                self.changes.push_back_tracked_path_component(component)
+            }
+
+            fn push_path_component(&mut self, component: &BStr) {
+                self.changes.push_path_component(component)
+            }
+
+            fn pop_path_component(&mut self) {
+                self.changes.pop_path_component()
+            }
+
+            fn visit(&mut self, change: Change) -> Action {
+                self.changes.visit(change)
+            }
+        }
+
+        impl git_diff::tree::rewrites::Visit for Delegate {
+            fn visit_tracked(&mut self, destination: Destination<'_>) -> Action {
+                self.tracked.push((
+                    destination.location.to_owned(),
+                    destination
+                        .source
+                        .map(|source| (source.location.to_owned(), source.copy)),
+                ));
+                Action::Continue
+            }
+        }
+
+        fn db() -> crate::Result<git_odb::Handle> {
+            git_odb::at(
+                git_testtools::scripted_fixture_read_only_standalone("make_diff_for_rewrites_repo.sh")?
+                    .join(".git")
+                    .join("objects"),
+            )
+            .map_err(Into::into)
+        }
+
+        fn tree_of_tag<'a>(db: &git_odb::Handle, tag: &str, buf: &'a mut Vec<u8>) -> crate::Result<TreeRefIter<'a>> {
+            let commit_id = git_hash::ObjectId::from_hex(
+                std::fs::read(
+                    db.store_ref()
+                        .path()
+                        .parent()
+                        .unwrap()
+                        .join("refs")
+                        .join("tags")
+                        .join(tag),
+                )?
+                .as_bstr()
+                .trim(),
+            )?;
+            let tree_id = db.find_commit(commit_id, buf)?.tree();
+            Ok(db.find_tree_iter(tree_id, buf)?)
+        }
+
+        fn diff(db: &git_odb::Handle, from: &str, to: &str, rewrites: Rewrites) -> crate::Result<Delegate> {
+            let (mut lhs_buf, mut rhs_buf) = (Vec::new(), Vec::new());
+            let mut delegate = Delegate::default();
+            git_diff::tree::Changes::from(tree_of_tag(db, from, &mut lhs_buf)?).needed_to_obtain_with_rewrites(
+                tree_of_tag(db, to, &mut rhs_buf)?,
+                git_diff::tree::State::default(),
+                |oid, buf| db.find_tree_iter(oid, buf),
+                |oid, buf| db.find_blob(oid, buf),
+                rewrites,
+                &mut delegate,
+            )?;
+            Ok(delegate)
+        }
+
+        fn rename(source: &str, destination: &str) -> (BString, Option<(BString, bool)>) {
+            (destination.into(), Some((source.into(), false)))
+        }
+
+        #[test]
+        fn identity_renames_are_tracked_by_default() -> crate::Result {
+            let db = db()?;
+            let actual = diff(&db, "base", "identity", Rewrites::default())?;
+            assert!(actual.changes.records.is_empty(), "the rename is the only change");
+            assert_eq!(actual.tracked, vec![rename("a", "a-renamed")]);
+            Ok(())
+        }
+
+        #[test]
+        fn similar_renames_need_enough_similarity() -> crate::Result {
+            let db = db()?;
+            let actual = diff(&db, "identity", "similar", Rewrites::default())?;
+            assert_eq!(
+                actual.changes.records.len(),
+                1,
+                "the modification of the parent directory is emitted right away"
+            );
+            assert_eq!(actual.tracked, vec![rename("dir/s", "dir/s-renamed")]);
+
+            for percentage in [None, Some(0.95)] {
+                let actual = diff(
+                    &db,
+                    "identity",
+                    "similar",
+                    Rewrites {
+                        percentage,
+                        ..Default::default()
+                    },
+                )?;
+                assert_eq!(
+                    actual.tracked,
+                    vec![("dir/s".into(), None), ("dir/s-renamed".into(), None)],
+                    "deletions and additions are emitted in order if they aren't similar enough"
+                );
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn copies_from_unchanged_files_require_the_source_tree() -> crate::Result {
+            let db = db()?;
+            for (source, expected) in [
+                (CopySource::FromSetOfModifiedFiles, None),
+                (
+                    CopySource::FromSetOfModifiedFilesAndSourceTree,
+                    Some(("dir/s-renamed".into(), true)),
+                ),
+            ] {
+                let actual = diff(
+                    &db,
+                    "similar",
+                    "copy",
+                    Rewrites {
+                        copies: Some(Copies {
+                            source,
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    },
+                )?;
+                assert!(actual.changes.records.is_empty());
+                assert_eq!(actual.tracked, vec![("s-copy".into(), expected)]);
+            }
+            Ok(())
+        }
+    }
 }
@@ -3,7 +3,13 @@
 //! With the `fast-sha1` feature, the `Sha1` hash type will use a more elaborate implementation utilizing hardware support
 //! in case it is available. Otherwise the `rustsha1` feature should be set. `fast-sha1` will take precedence.
 //! Otherwise, a minimal yet performant implementation is used instead for a decent trade-off between compile times and run-time performance.
-#[cfg(all(feature = "rustsha1", not(feature = "fast-sha1")))]
+//!
+//! With the `hardened-sha1` feature, which takes precedence over all other Sha1 features, a Sha1 implementation with collision
+//! detection is used. It produces a mitigated hash for data that is part of a collision attack, which is reported as
+//! [`Error::CollisionAttack`] by [`Hasher::try_digest()`].
+//!
+//! The `Sha256` hash type is available with either of these features, and is always implemented in pure Rust.
+#[cfg(all(feature = "rustsha1", not(feature = "fast-sha1"), not(feature = "hardened-sha1")))]
 mod _impl {
     use super::Sha1Digest;
 
@@ -20,6 +26,10 @@ mod _impl {
         pub fn digest(self) -> Sha1Digest {
             self.0.digest().bytes()
         }
+        /// Finalize the hash and produce a digest, which never fails as collisions aren't detected.
+        pub fn try_digest(self) -> Result<Sha1Digest, super::Error> {
+            Ok(self.digest())
+        }
     }
 }
 
@@ -27,7 +37,7 @@ mod _impl {
 #[cfg(any(feature = "fast-sha1", feature = "rustsha1"))]
 pub type Sha1Digest = [u8; 20];
 
-#[cfg(feature = "fast-sha1")]
+#[cfg(all(feature = "fast-sha1", not(feature = "hardened-sha1")))]
 mod _impl {
     use sha1::Digest;
 
@@ -46,12 +56,164 @@ mod _impl {
         pub fn digest(self) -> Sha1Digest {
             self.0.finalize().into()
         }
+        /// Finalize the hash and produce a digest, which never fails as collisions aren't detected.
+        pub fn try_digest(self) -> Result<Sha1Digest, super::Error> {
+            Ok(self.digest())
+        }
+    }
+}
+
+#[cfg(feature = "hardened-sha1")]
+mod _impl {
+    use super::Sha1Digest;
+
+    /// A implementation of the Sha1 hash with collision detection, which can be used once.
+    #[derive(Default, Clone)]
+    pub struct Sha1(sha1collisiondetection::Sha1CD);
+
+    impl Sha1 {
+        /// Digest the given `bytes`.
+        pub fn update(&mut self, bytes: &[u8]) {
+            self.0.update(bytes)
+        }
+        /// Finalize the hash and produce a digest.
+        ///
+        /// If a collision attack was detected, the digest is computed such that it doesn't match the one the attacker
+        /// would have wanted, use [`try_digest()`][Sha1::try_digest()] to learn about the collision.
+        pub fn digest(mut self) -> Sha1Digest {
+            let mut digest = sha1collisiondetection::Output::default();
+            self.0.finalize_into_dirty_cd(&mut digest).ok();
+            digest.into()
+        }
+        /// Finalize the hash and produce a digest, or fail with [`Error::CollisionAttack`][super::Error::CollisionAttack]
+        /// if the hashed data is part of a collision attack.
+        pub fn try_digest(mut self) -> Result<Sha1Digest, super::Error> {
+            let mut digest = sha1collisiondetection::Output::default();
+            match self.0.finalize_into_dirty_cd(&mut digest) {
+                Ok(()) => Ok(digest.into()),
+                Err(_collision) => Err(super::Error::CollisionAttack {
+                    digest: git_hash::ObjectId::from(Sha1Digest::from(digest)),
+                }),
+            }
+        }
     }
 }
 
 #[cfg(any(feature = "rustsha1", feature = "fast-sha1"))]
 pub use _impl::Sha1;
 
+/// The error returned by [`Hasher::try_digest()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The hashed data was detected to be part of a Sha1 collision attack, like the one known as SHAttered.
+    CollisionAttack {
+        /// The mitigated digest, which differs from the one the attacker intended to produce.
+        digest: git_hash::ObjectId,
+    },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::CollisionAttack { digest } => {
+                write!(f, "Detected SHA1 collision attack, mitigated digest is {digest}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        std::io::Error::new(std::io::ErrorKind::Other, err)
+    }
+}
+
+/// A 32 bytes digest produced by a [`Sha256`] hash implementation.
+#[cfg(any(feature = "fast-sha1", feature = "rustsha1"))]
+pub type Sha256Digest = [u8; 32];
+
+#[cfg(any(feature = "fast-sha1", feature = "rustsha1"))]
+mod _impl_sha256 {
+    use sha2::Digest;
+
+    use super::Sha256Digest;
+
+    /// A implementation of the Sha256 hash, which can be used once.
+    #[derive(Default, Clone)]
+    pub struct Sha256(sha2::Sha256);
+
+    impl Sha256 {
+        /// Digest the given `bytes`.
+        pub fn update(&mut self, bytes: &[u8]) {
+            self.0.update(bytes)
+        }
+        /// Finalize the hash and produce a digest.
+        pub fn digest(self) -> Sha256Digest {
+            self.0.finalize().into()
+        }
+        /// Finalize the hash and produce a digest, which never fails as no collision attacks are known.
+        pub fn try_digest(self) -> Result<Sha256Digest, super::Error> {
+            Ok(self.digest())
+        }
+    }
+}
+
+#[cfg(any(feature = "rustsha1", feature = "fast-sha1"))]
+pub use _impl_sha256::Sha256;
+
+/// A hash implementation for any of the supported [kinds of hashes][git_hash::Kind], as obtained by [`hasher()`].
+#[cfg(any(feature = "rustsha1", feature = "fast-sha1"))]
+#[derive(Clone)]
+pub enum Hasher {
+    /// The Sha1 implementation.
+    Sha1(Sha1),
+    /// The Sha256 implementation.
+    Sha256(Sha256),
+}
+
+#[cfg(any(feature = "rustsha1", feature = "fast-sha1"))]
+impl Hasher {
+    /// Digest the given `bytes`.
+    pub fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Sha1(h) => h.update(bytes),
+            Hasher::Sha256(h) => h.update(bytes),
+        }
+    }
+
+    /// Finalize the hash and produce an object id of the kind this hasher was created with.
+    ///
+    /// Note that with the `hardened-sha1` feature, data which is part of a collision attack will produce
+    /// a mitigated id silently. Use [`try_digest()`][Hasher::try_digest()] where this should be an error.
+    pub fn digest(self) -> git_hash::ObjectId {
+        match self {
+            Hasher::Sha1(h) => h.digest().into(),
+            Hasher::Sha256(h) => h.digest().into(),
+        }
+    }
+
+    /// Finalize the hash and produce an object id of the kind this hasher was created with, or fail if
+    /// a collision attack was detected.
+    ///
+    /// Collisions are only detected with the `hardened-sha1` feature enabled, otherwise this method never fails.
+    pub fn try_digest(self) -> Result<git_hash::ObjectId, Error> {
+        Ok(match self {
+            Hasher::Sha1(h) => h.try_digest()?.into(),
+            Hasher::Sha256(h) => h.try_digest()?.into(),
+        })
+    }
+
+    /// Return the kind of hash this instance produces.
+    pub fn kind(&self) -> git_hash::Kind {
+        match self {
+            Hasher::Sha1(_) => git_hash::Kind::Sha1,
+            Hasher::Sha256(_) => git_hash::Kind::Sha256,
+        }
+    }
+}
+
 /// Compute a CRC32 hash from the given `bytes`, returning the CRC32 hash.
 ///
 /// When calling this function for the first time, `previous_value` should be `0`. Otherwise it
@@ -76,9 +238,10 @@ pub fn crc32(bytes: &[u8]) -> u32 {
 
 /// Produce a hasher suitable for the given kind of hash.
 #[cfg(any(feature = "rustsha1", feature = "fast-sha1"))]
-pub fn hasher(kind: git_hash::Kind) -> Sha1 {
+pub fn hasher(kind: git_hash::Kind) -> Hasher {
     match kind {
-        git_hash::Kind::Sha1 => Sha1::default(),
+        git_hash::Kind::Sha1 => Hasher::Sha1(Sha1::default()),
+        git_hash::Kind::Sha256 => Hasher::Sha256(Sha256::default()),
     }
 }
 
@@ -93,6 +256,7 @@ pub fn hasher(kind: git_hash::Kind) -> Sha1 {
 /// * Only available with the `git-object` feature enabled due to usage of the [`git_hash::Kind`] enum and the
 ///   [`git_hash::ObjectId`] return value.
 /// * [Interrupts][crate::interrupt] are supported.
+/// * Detected collision attacks are returned as [`std::io::Error`] wrapping [`Error::CollisionAttack`].
 #[cfg(all(feature = "progress", any(feature = "rustsha1", feature = "fast-sha1")))]
 pub fn bytes_of_file(
     path: impl AsRef<std::path::Path>,
@@ -139,19 +303,19 @@ pub fn bytes(
         }
     }
 
-    let id = git_hash::ObjectId::from(hasher.digest());
+    let id = hasher.try_digest()?;
     progress.show_throughput(start);
     Ok(id)
 }
 
 #[cfg(any(feature = "rustsha1", feature = "fast-sha1"))]
 mod write {
-    use crate::hash::Sha1;
+    use crate::hash::Hasher;
 
     /// A utility to automatically generate a hash while writing into an inner writer.
     pub struct Write<T> {
         /// The hash implementation.
-        pub hash: Sha1,
+        pub hash: Hasher,
         /// The inner writer.
         pub inner: T,
     }
@@ -177,11 +341,9 @@ mod write {
     {
         /// Create a new hash writer which hashes all bytes written to `inner` with a hash of `kind`.
         pub fn new(inner: T, object_hash: git_hash::Kind) -> Self {
-            match object_hash {
-                git_hash::Kind::Sha1 => Write {
-                    inner,
-                    hash: Sha1::default(),
-                },
+            Write {
+                inner,
+                hash: crate::hash::hasher(object_hash),
             }
         }
     }
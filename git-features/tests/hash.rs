@@ -1,13 +1,57 @@
 use git_features::hash::Sha1;
 
-#[cfg(not(feature = "fast-sha1"))]
+#[cfg(not(any(feature = "fast-sha1", feature = "hardened-sha1")))]
 #[test]
 fn size_of_sha1() {
     assert_eq!(std::mem::size_of::<Sha1>(), 96)
 }
 
-#[cfg(feature = "fast-sha1")]
+#[cfg(all(feature = "fast-sha1", not(feature = "hardened-sha1")))]
 #[test]
 fn size_of_sha1() {
     assert_eq!(std::mem::size_of::<Sha1>(), 104)
 }
+
+#[test]
+fn hasher_produces_ids_of_the_requested_kind() {
+    let id = git_features::hash::hasher(git_hash::Kind::Sha256).digest();
+    assert_eq!(id.kind(), git_hash::Kind::Sha256);
+    assert_eq!(
+        id.to_string(),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        "the hash of empty input"
+    );
+    assert_eq!(
+        git_features::hash::hasher(git_hash::Kind::Sha1).digest().to_string(),
+        "da39a3ee5e6b4b0d3255bfef95601890afd80709"
+    );
+}
+
+#[test]
+fn try_digest_succeeds_for_regular_input() {
+    let mut hasher = git_features::hash::hasher(git_hash::Kind::Sha1);
+    hasher.update(b"hello");
+    assert_eq!(
+        hasher.try_digest().expect("no collision").to_string(),
+        "aaf4c61ddcc5e8a2dabede0f3b482cd9aea9434d"
+    );
+}
+
+#[cfg(feature = "hardened-sha1")]
+#[test]
+fn collision_attacks_are_detected() {
+    // One half of the chosen-prefix collision published as SHA-1 is a Shambles.
+    let data = std::fs::read("tests/fixtures/sha-mbles-1.bin").unwrap();
+    let mut hasher = git_features::hash::hasher(git_hash::Kind::Sha1);
+    hasher.update(&data);
+    let mitigated_id = hasher.clone().digest();
+    assert_eq!(
+        mitigated_id.to_string(),
+        "4f3d9be4a472c4dae83c6314aa6c36a064c1fd14",
+        "the digest doesn't match the colliding one, 8ac60ba76f1999a1ab70223f225aefdc78d4ddc0"
+    );
+    assert_eq!(
+        hasher.try_digest().unwrap_err(),
+        git_features::hash::Error::CollisionAttack { digest: mitigated_id }
+    );
+}
@@ -9,8 +9,28 @@ mod from_hex_len {
         assert_eq!(Kind::from_hex_len(40), Some(Kind::Sha1));
     }
 
+    #[test]
+    fn some_sha256() {
+        assert_eq!(Kind::from_hex_len(41), Some(Kind::Sha256));
+        assert_eq!(Kind::from_hex_len(64), Some(Kind::Sha256));
+    }
+
     #[test]
     fn none_if_there_is_no_fit() {
         assert_eq!(Kind::from_hex_len(65), None);
     }
 }
+
+mod from_str {
+    use std::str::FromStr;
+
+    use git_hash::Kind;
+
+    #[test]
+    fn round_trips_through_display() {
+        for kind in [Kind::Sha1, Kind::Sha256] {
+            assert_eq!(Kind::from_str(&kind.to_string()).expect("valid"), kind);
+        }
+        assert_eq!(Kind::from_str("sha256").expect("valid"), Kind::Sha256);
+    }
+}
@@ -108,12 +108,20 @@ mod try_from {
 
     #[test]
     fn id_to_long() {
-        let input = "abcdefabcdefabcdefabcdefabcdefabcdefabcd123123123123123123";
-        let expected = Error::TooLong { hex_len: 58 };
+        let input = "abcdefabcdefabcdefabcdefabcdefabcdefabcd1231231231231231231231231";
+        let expected = Error::TooLong { hex_len: 65 };
         let actual = Prefix::try_from(input).unwrap_err();
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn id_longer_than_sha1_is_a_sha256_prefix() {
+        let input = "abcdefabcdefabcdefabcdefabcdefabcdefabcd123123123123123123";
+        let prefix = Prefix::try_from(input).expect("valid");
+        assert_eq!(prefix.hex_len(), 58);
+        assert_eq!(prefix.as_oid().kind(), git_hash::Kind::Sha256);
+    }
+
     #[test]
     fn invalid_chars() {
         let input = "abcdfOsd";
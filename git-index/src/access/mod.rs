@@ -20,6 +20,13 @@ impl State {
         self.object_hash
     }
 
+    /// Return the time at which the state was created, which is the modification time of the index file it was read from.
+    ///
+    /// Entries modified at or after this time are [racy][entry::Stat::is_racy()] and need their content to be checked.
+    pub fn timestamp(&self) -> filetime::FileTime {
+        self.timestamp
+    }
+
     /// Return our entries
     pub fn entries(&self) -> &[Entry] {
         &self.entries
@@ -204,6 +211,12 @@ impl State {
     pub fn tree(&self) -> Option<&extension::Tree> {
         self.tree.as_ref()
     }
+    /// Replace the `tree` extension with `tree`, or remove it if `None`.
+    ///
+    /// Use this to store the tree extension [obtained when writing trees][State::write_tree()].
+    pub fn set_tree(&mut self, tree: Option<extension::Tree>) {
+        self.tree = tree;
+    }
     /// Access the `link` extension.
     pub fn link(&self) -> Option<&extension::Link> {
         self.link.as_ref()
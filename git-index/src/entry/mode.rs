@@ -21,4 +21,17 @@ impl Mode {
     pub fn is_sparse(&self) -> bool {
         *self == Self::DIR
     }
+
+    /// Return the equivalent mode of an entry in a tree, or `None` if the mode is invalid.
+    pub fn to_tree_entry_mode(&self) -> Option<git_object::tree::EntryMode> {
+        use git_object::tree::EntryMode;
+        Some(match *self {
+            Self::DIR => EntryMode::Tree,
+            Self::FILE => EntryMode::Blob,
+            Self::FILE_EXECUTABLE => EntryMode::BlobExecutable,
+            Self::SYMLINK => EntryMode::Link,
+            Self::COMMIT => EntryMode::Commit,
+            _ => return None,
+        })
+    }
 }
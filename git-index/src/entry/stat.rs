@@ -0,0 +1,116 @@
+use std::{convert::TryInto, time::SystemTimeError};
+
+use filetime::FileTime;
+
+use crate::entry::{Stat, Time};
+
+/// Configure how [`Stat`] information is compared, similar to what `git` does with `core.trustCTime` and `core.checkStat`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Options {
+    /// If true, a difference in ctime doesn't cause a stat mismatch. Git's `core.trustCTime`.
+    pub trust_ctime: bool,
+    /// If true, check the inode number, owner and group along with the times and the size, otherwise only times and size are compared.
+    /// Git's `core.checkStat`.
+    pub check_stat: bool,
+    /// If true, nanoseconds are taken into consideration when comparing times, otherwise only seconds are compared.
+    ///
+    /// This is off by default like it is in `git`, which has to be compiled with `USE_NSEC` to enable it.
+    pub use_nsec: bool,
+    /// If true, the device number is compared as well, which is only meaningful if [`check_stat`][Options::check_stat] is set.
+    ///
+    /// This is off by default like it is in `git`, which has to be compiled with `USE_STDEV` to enable it.
+    pub use_stdev: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            trust_ctime: true,
+            check_stat: true,
+            use_nsec: false,
+            use_stdev: false,
+        }
+    }
+}
+
+impl Stat {
+    /// Create a new instance from the metadata of a file on disk, as obtained from `std::fs::symlink_metadata()`.
+    ///
+    /// Note that fields which cannot be obtained on the current platform will be zero.
+    pub fn from_fs(meta: &std::fs::Metadata) -> Result<Stat, SystemTimeError> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            Ok(Stat {
+                mtime: Time {
+                    secs: meta.mtime() as u32,
+                    nsecs: meta.mtime_nsec() as u32,
+                },
+                ctime: Time {
+                    secs: meta.ctime() as u32,
+                    nsecs: meta.ctime_nsec() as u32,
+                },
+                dev: meta.dev() as u32,
+                ino: meta.ino() as u32,
+                uid: meta.uid(),
+                gid: meta.gid(),
+                size: meta.size() as u32,
+            })
+        }
+        #[cfg(not(unix))]
+        {
+            fn time_since_epoch(time: std::io::Result<std::time::SystemTime>) -> Result<Time, SystemTimeError> {
+                Ok(match time {
+                    Ok(time) => {
+                        let duration = time.duration_since(std::time::UNIX_EPOCH)?;
+                        Time {
+                            secs: duration.as_secs().try_into().unwrap_or(u32::MAX),
+                            nsecs: duration.subsec_nanos(),
+                        }
+                    }
+                    Err(_) => Time::default(),
+                })
+            }
+            Ok(Stat {
+                mtime: time_since_epoch(meta.modified())?,
+                ctime: time_since_epoch(meta.created())?,
+                dev: 0,
+                ino: 0,
+                uid: 0,
+                gid: 0,
+                size: meta.len() as u32,
+            })
+        }
+    }
+
+    /// Return true if `other`, typically obtained from the file on disk, matches this instance, typically obtained from an index entry,
+    /// according to the comparison `options`.
+    ///
+    /// Note that a match doesn't necessarily mean the content is unchanged if the entry [is racy][Stat::is_racy()].
+    pub fn matches(&self, other: &Stat, options: Options) -> bool {
+        let time_matches = |lhs: Time, rhs: Time| lhs.secs == rhs.secs && (!options.use_nsec || lhs.nsecs == rhs.nsecs);
+        if !time_matches(self.mtime, other.mtime) || (options.trust_ctime && !time_matches(self.ctime, other.ctime)) {
+            return false;
+        }
+        if options.check_stat
+            && (self.ino != other.ino
+                || self.uid != other.uid
+                || self.gid != other.gid
+                || (options.use_stdev && self.dev != other.dev))
+        {
+            return false;
+        }
+        self.size == other.size
+    }
+
+    /// Return true if this stat information belongs to an entry of an index with the given `timestamp` and was modified at or after
+    /// that time.
+    ///
+    /// Racy entries may have changed without changing their stat information after the index was written, and thus need to have their
+    /// content checked to know if they really are unchanged.
+    pub fn is_racy(&self, timestamp: FileTime, options: Options) -> bool {
+        let index_secs: u32 = timestamp.unix_seconds().try_into().unwrap_or(0);
+        self.mtime.secs > index_secs
+            || (self.mtime.secs == index_secs && (!options.use_nsec || self.mtime.nsecs >= timestamp.nanoseconds()))
+    }
+}
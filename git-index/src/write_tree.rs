@@ -0,0 +1,162 @@
+use bstr::{BString, ByteSlice};
+use git_object::tree::EntryMode;
+
+use crate::{entry, extension, Entry, State};
+
+/// The error returned by [`State::write_tree()`].
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error<E: std::error::Error + Send + Sync + 'static> {
+    #[error("The entry at '{path}' is unmerged and can't be written into a tree")]
+    Unmerged { path: BString },
+    #[error("The entry at '{path}' has an invalid mode: {mode:?}")]
+    InvalidMode { path: BString, mode: entry::Mode },
+    #[error("Could not write a tree object")]
+    WriteTree(#[source] E),
+}
+
+/// The outcome of [`State::write_tree()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// The tree extension matching the written trees, whose root is the top-level tree of the index.
+    ///
+    /// It can be used to replace the [tree extension][State::tree()] of the index.
+    pub tree: extension::Tree,
+    /// The amount of trees that had to be written.
+    pub num_trees_written: usize,
+    /// The amount of trees, including all of their subtrees, that could be reused from the tree extension of the index.
+    pub num_trees_reused: usize,
+}
+
+/// Creating trees
+impl State {
+    /// Produce a tree from all entries in this index, recursively, calling `write` for each tree object that needs to be written and
+    /// which returns its id.
+    ///
+    /// Valid entries of the [tree extension][State::tree()] are used in place of the trees they represent, which avoids writing these
+    /// entirely. Note that we trust the extension to be up-to-date with the index entries as long as it isn't invalidated, just
+    /// like `git` does.
+    ///
+    /// Entries which are [only intended to be added][entry::Flags::INTENT_TO_ADD] are not written into the tree.
+    /// Unmerged entries cause an error.
+    pub fn write_tree<E>(
+        &self,
+        mut write: impl FnMut(&git_object::Tree) -> Result<git_hash::ObjectId, E>,
+    ) -> Result<Outcome, Error<E>>
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        if let Some(entry) = self.entries.iter().find(|entry| entry.stage() != 0) {
+            return Err(Error::Unmerged {
+                path: entry.path(self).to_owned(),
+            });
+        }
+        let mut counts = Counts::default();
+        let tree = self.write_tree_recursive(
+            &self.entries,
+            0,
+            Default::default(),
+            self.tree.as_ref(),
+            &mut write,
+            &mut counts,
+        )?;
+        Ok(Outcome {
+            tree,
+            num_trees_written: counts.written,
+            num_trees_reused: counts.reused,
+        })
+    }
+
+    fn write_tree_recursive<E>(
+        &self,
+        entries: &[Entry],
+        prefix_len: usize,
+        name: smallvec::SmallVec<[u8; 23]>,
+        cached: Option<&extension::Tree>,
+        write: &mut impl FnMut(&git_object::Tree) -> Result<git_hash::ObjectId, E>,
+        counts: &mut Counts,
+    ) -> Result<extension::Tree, Error<E>>
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        if let Some(cached) = cached.filter(|tree| tree.num_entries == Some(entries.len() as u32)) {
+            counts.reused += count_trees(cached);
+            return Ok(cached.clone());
+        }
+
+        let mut tree = git_object::Tree::empty();
+        let mut children = Vec::new();
+        let mut idx = 0;
+        while idx < entries.len() {
+            let entry = &entries[idx];
+            let path = entry.path(self);
+            let rest = &path[prefix_len..];
+            match rest
+                .find_byte(b'/')
+                .filter(|pos| !(entry.mode.is_sparse() && *pos + 1 == rest.len()))
+            {
+                Some(pos) => {
+                    let dir_name = &rest[..pos];
+                    let child_prefix_len = prefix_len + pos + 1;
+                    let dir_prefix = &path[..child_prefix_len];
+                    let end = idx
+                        + entries[idx..]
+                            .iter()
+                            .position(|entry| !entry.path(self).starts_with(dir_prefix))
+                            .unwrap_or(entries.len() - idx);
+                    let child = self.write_tree_recursive(
+                        &entries[idx..end],
+                        child_prefix_len,
+                        smallvec::SmallVec::from_slice(dir_name),
+                        cached.and_then(|cached| cached.children.iter().find(|tree| tree.name.as_slice() == dir_name)),
+                        write,
+                        counts,
+                    )?;
+                    if child.id != git_hash::ObjectId::empty_tree(self.object_hash) {
+                        tree.entries.push(git_object::tree::Entry {
+                            mode: EntryMode::Tree,
+                            filename: dir_name.into(),
+                            oid: child.id,
+                        });
+                    }
+                    children.push(child);
+                    idx = end;
+                }
+                None => {
+                    idx += 1;
+                    if entry.flags.contains(entry::Flags::INTENT_TO_ADD) {
+                        continue;
+                    }
+                    let mode = entry.mode.to_tree_entry_mode().ok_or_else(|| Error::InvalidMode {
+                        path: path.to_owned(),
+                        mode: entry.mode,
+                    })?;
+                    tree.entries.push(git_object::tree::Entry {
+                        mode,
+                        filename: rest.trim_end_with(|c| c == '/').into(),
+                        oid: entry.id,
+                    });
+                }
+            }
+        }
+
+        let id = write(&tree).map_err(Error::WriteTree)?;
+        counts.written += 1;
+        Ok(extension::Tree {
+            name,
+            id,
+            num_entries: Some(entries.len() as u32),
+            children,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Counts {
+    written: usize,
+    reused: usize,
+}
+
+fn count_trees(tree: &extension::Tree) -> usize {
+    1 + tree.children.iter().map(count_trees).sum::<usize>()
+}
@@ -15,3 +15,78 @@ mod time {
         }
     }
 }
+
+mod stat {
+    use filetime::FileTime;
+    use git_index::entry::{self, stat, Stat};
+
+    fn sample() -> Stat {
+        Stat {
+            mtime: entry::Time { secs: 100, nsecs: 5 },
+            ctime: entry::Time { secs: 90, nsecs: 7 },
+            dev: 1,
+            ino: 2,
+            uid: 3,
+            gid: 4,
+            size: 42,
+        }
+    }
+
+    #[test]
+    fn matches_honors_options() {
+        let opts = stat::Options::default();
+        let index = sample();
+        assert!(index.matches(&index, opts));
+
+        let mut other = index;
+        other.mtime.nsecs = 6;
+        other.dev = 10;
+        assert!(
+            index.matches(&other, opts),
+            "nanoseconds and devices are ignored by default"
+        );
+        assert!(!index.matches(&other, stat::Options { use_nsec: true, ..opts }));
+
+        let mut other = index;
+        other.ctime.secs += 1;
+        assert!(!index.matches(&other, opts));
+        assert!(index.matches(
+            &other,
+            stat::Options {
+                trust_ctime: false,
+                ..opts
+            }
+        ));
+
+        let mut other = index;
+        other.ino += 1;
+        assert!(!index.matches(&other, opts));
+        assert!(index.matches(
+            &other,
+            stat::Options {
+                check_stat: false,
+                ..opts
+            }
+        ));
+
+        let mut other = index;
+        other.size += 1;
+        assert!(!index.matches(&other, opts));
+    }
+
+    #[test]
+    fn is_racy_if_modified_at_or_after_the_index_timestamp() {
+        let opts = stat::Options::default();
+        let stat = sample();
+        assert!(stat.is_racy(FileTime::from_unix_time(100, 0), opts));
+        assert!(stat.is_racy(FileTime::from_unix_time(99, 0), opts));
+        assert!(!stat.is_racy(FileTime::from_unix_time(101, 0), opts));
+        assert!(
+            !stat.is_racy(
+                FileTime::from_unix_time(100, 6),
+                stat::Options { use_nsec: true, ..opts }
+            ),
+            "with nanoseconds, the index can be written after the modification in the same second"
+        );
+    }
+}
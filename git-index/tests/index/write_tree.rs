@@ -0,0 +1,79 @@
+use std::convert::Infallible;
+
+use git_repository::objs::WriteTo;
+
+use crate::index::Fixture;
+
+fn write_tree(state: &git_index::State) -> (git_index::write_tree::Outcome, Vec<git_hash::ObjectId>) {
+    let mut written = Vec::new();
+    let outcome = state
+        .write_tree(|tree| -> Result<_, Infallible> {
+            let id = tree_id(tree);
+            written.push(id);
+            Ok(id)
+        })
+        .expect("infallible");
+    (outcome, written)
+}
+
+fn tree_id(tree: &git_repository::objs::Tree) -> git_hash::ObjectId {
+    let mut buf = Vec::new();
+    tree.write_to(&mut buf).expect("write to memory");
+    let mut hasher = git_features::hash::hasher(git_hash::Kind::Sha1);
+    hasher.update(&git_repository::objs::encode::loose_header(
+        git_repository::objs::Kind::Tree,
+        buf.len(),
+    ));
+    hasher.update(&buf);
+    hasher.digest()
+}
+
+#[test]
+fn valid_tree_extension_is_reused_entirely() {
+    let file = Fixture::Generated("v2_more_files").open();
+    let expected = file.tree().expect("tree extension written by git commit").clone();
+
+    let (outcome, written) = write_tree(&file);
+    assert!(written.is_empty(), "nothing needs to be written");
+    assert_eq!(outcome.num_trees_written, 0);
+    assert_eq!(outcome.num_trees_reused, 2, "the root tree and 'd'");
+    assert_eq!(outcome.tree, expected);
+}
+
+#[test]
+fn trees_are_written_if_the_tree_extension_is_missing_or_invalid() {
+    let mut file = Fixture::Generated("v2_more_files").open();
+    let expected = file.tree().expect("tree extension written by git commit").clone();
+
+    file.set_tree(None);
+    let (outcome, written) = write_tree(&file);
+    assert_eq!(outcome.num_trees_written, 2);
+    assert_eq!(outcome.num_trees_reused, 0);
+    assert_eq!(
+        written,
+        [expected.children[0].id, expected.id],
+        "subtrees are written first"
+    );
+    assert_eq!(
+        outcome.tree, expected,
+        "the produced extension is the same as the one written by git"
+    );
+
+    let mut invalidated_root = expected.clone();
+    invalidated_root.num_entries = None;
+    file.set_tree(Some(invalidated_root));
+    let (outcome, written) = write_tree(&file);
+    assert_eq!(outcome.num_trees_written, 1, "only the root tree needs to be written");
+    assert_eq!(outcome.num_trees_reused, 1);
+    assert_eq!(written, [expected.id]);
+    assert_eq!(outcome.tree, expected);
+}
+
+#[test]
+fn unmerged_entries_cause_an_error() {
+    let file = Fixture::Loose("conflicting-file").open();
+    let err = file
+        .write_tree(|_tree| -> Result<_, Infallible> { unreachable!("no tree is written") })
+        .unwrap_err();
+    assert!(matches!(err, git_index::write_tree::Error::Unmerged { .. }));
+}
@@ -3,9 +3,13 @@
 use crate::{BlobRef, CommitRef, CommitRefIter, Data, Kind, ObjectRef, TagRef, TagRefIter, TreeRef, TreeRefIter};
 
 impl<'a> Data<'a> {
-    /// Constructs a new data object from `kind` and `data`.
-    pub fn new(kind: Kind, data: &'a [u8]) -> Data<'a> {
-        Data { kind, data }
+    /// Constructs a new data object from `kind` and `data`, with object ids contained within it being of kind `object_hash`.
+    pub fn new(kind: Kind, data: &'a [u8], object_hash: git_hash::Kind) -> Data<'a> {
+        Data {
+            kind,
+            data,
+            object_hash,
+        }
     }
     /// Decodes the data in the backing slice into a [`ObjectRef`], allowing to access all of its data
     /// conveniently. The cost of parsing an object is negligible.
@@ -14,7 +18,7 @@ impl<'a> Data<'a> {
     /// using [`crate::ObjectRef::into_owned()`].
     pub fn decode(&self) -> Result<ObjectRef<'a>, crate::decode::Error> {
         Ok(match self.kind {
-            Kind::Tree => ObjectRef::Tree(TreeRef::from_bytes(self.data)?),
+            Kind::Tree => ObjectRef::Tree(TreeRef::from_bytes(self.data, self.object_hash)?),
             Kind::Blob => ObjectRef::Blob(BlobRef { data: self.data }),
             Kind::Commit => ObjectRef::Commit(CommitRef::from_bytes(self.data)?),
             Kind::Tag => ObjectRef::Tag(TagRef::from_bytes(self.data)?),
@@ -25,7 +29,7 @@ impl<'a> Data<'a> {
     /// `None` if this is not a tree object.
     pub fn try_into_tree_iter(self) -> Option<TreeRefIter<'a>> {
         match self.kind {
-            Kind::Tree => Some(TreeRefIter::from_bytes(self.data)),
+            Kind::Tree => Some(TreeRefIter::from_bytes(self.data, self.object_hash)),
             _ => None,
         }
     }
@@ -61,6 +65,11 @@ pub mod verify {
             desired: git_hash::ObjectId,
             actual: git_hash::ObjectId,
         },
+        #[error("Object expected to have id {desired}, but its data is part of a SHA1 collision attack")]
+        CollisionAttack {
+            desired: git_hash::ObjectId,
+            source: git_features::hash::Error,
+        },
     }
 
     impl crate::Data<'_> {
@@ -73,7 +82,10 @@ pub mod verify {
             hasher.update(&crate::encode::loose_header(self.kind, self.data.len()));
             hasher.update(self.data);
 
-            let actual_id = git_hash::ObjectId::from(hasher.digest());
+            let actual_id = hasher.try_digest().map_err(|source| Error::CollisionAttack {
+                desired: desired.into(),
+                source,
+            })?;
             if desired != actual_id {
                 return Err(Error::ChecksumMismatch {
                     desired: desired.into(),
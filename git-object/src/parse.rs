@@ -1,7 +1,7 @@
 use bstr::{BStr, BString, ByteVec};
 use nom::{
     bytes::complete::{is_not, tag, take_until, take_while_m_n},
-    combinator::{peek, recognize},
+    combinator::{peek, recognize, verify},
     error::{context, ContextError, ParseError},
     multi::many1_count,
     sequence::{preceded, terminated, tuple},
@@ -65,11 +65,18 @@ fn is_hex_digit_lc(b: u8) -> bool {
     matches!(b, b'0'..=b'9' | b'a'..=b'f')
 }
 
+fn is_hash_len(hex: &[u8]) -> bool {
+    git_hash::Kind::from_hex_len(hex.len()).map_or(false, |kind| kind.len_in_hex() == hex.len())
+}
+
 pub fn hex_hash<'a, E: ParseError<&'a [u8]>>(i: &'a [u8]) -> IResult<&'a [u8], &'a BStr, E> {
-    take_while_m_n(
-        git_hash::Kind::shortest().len_in_hex(),
-        git_hash::Kind::longest().len_in_hex(),
-        is_hex_digit_lc,
+    verify(
+        take_while_m_n(
+            git_hash::Kind::shortest().len_in_hex(),
+            git_hash::Kind::longest().len_in_hex(),
+            is_hex_digit_lc,
+        ),
+        is_hash_len,
     )(i)
     .map(|(i, hex)| (i, hex.as_bstr()))
 }
@@ -5,13 +5,17 @@ mod iter {
 
     #[test]
     fn empty() {
-        assert_eq!(TreeRefIter::from_bytes(&[]).count(), 0, "empty trees are definitely ok");
+        assert_eq!(
+            TreeRefIter::from_bytes(&[], git_hash::Kind::Sha1).count(),
+            0,
+            "empty trees are definitely ok"
+        );
     }
 
     #[test]
     fn error_handling() {
         let data = fixture_bytes("tree", "everything.tree");
-        let iter = TreeRefIter::from_bytes(&data[..data.len() / 2]);
+        let iter = TreeRefIter::from_bytes(&data[..data.len() / 2], git_hash::Kind::Sha1);
         let entries = iter.collect::<Vec<_>>();
         assert!(
             entries.last().expect("at least one token").is_err(),
@@ -22,7 +26,8 @@ mod iter {
     #[test]
     fn everything() -> crate::Result {
         assert_eq!(
-            TreeRefIter::from_bytes(&fixture_bytes("tree", "everything.tree")).collect::<Result<Vec<_>, _>>()?,
+            TreeRefIter::from_bytes(&fixture_bytes("tree", "everything.tree"), git_hash::Kind::Sha1)
+                .collect::<Result<Vec<_>, _>>()?,
             vec![
                 EntryRef {
                     mode: tree::EntryMode::BlobExecutable,
@@ -53,6 +58,27 @@ mod iter {
         );
         Ok(())
     }
+
+    #[test]
+    fn sha256() -> crate::Result {
+        assert_eq!(
+            TreeRefIter::from_bytes(&fixture_bytes("tree", "sha256.tree"), git_hash::Kind::Sha256)
+                .collect::<Result<Vec<_>, _>>()?,
+            vec![
+                EntryRef {
+                    mode: tree::EntryMode::Blob,
+                    filename: b"file".as_bstr(),
+                    oid: &hex_to_id("96c18f0297e38d01f4b2dacddea4259aea6b2961eb0822bd2c0c3f6029030045")
+                },
+                EntryRef {
+                    mode: tree::EntryMode::Tree,
+                    filename: b"subdir".as_bstr(),
+                    oid: &hex_to_id("385836050393216259097506b92f0f0a246688879a5932159a604a26e5665bdf")
+                },
+            ]
+        );
+        Ok(())
+    }
 }
 
 mod from_bytes {
@@ -63,7 +89,7 @@ mod from_bytes {
     #[test]
     fn empty() -> crate::Result {
         assert_eq!(
-            TreeRef::from_bytes(&[])?,
+            TreeRef::from_bytes(&[], git_hash::Kind::Sha1)?,
             TreeRef { entries: vec![] },
             "empty trees are valid despite usually rare in the wild"
         );
@@ -73,7 +99,7 @@ mod from_bytes {
     #[test]
     fn everything() -> crate::Result {
         assert_eq!(
-            TreeRef::from_bytes(&fixture_bytes("tree", "everything.tree"))?,
+            TreeRef::from_bytes(&fixture_bytes("tree", "everything.tree"), git_hash::Kind::Sha1)?,
             TreeRef {
                 entries: vec![
                     EntryRef {
@@ -110,7 +136,7 @@ mod from_bytes {
     #[test]
     fn maybe_special() -> crate::Result {
         assert_eq!(
-            TreeRef::from_bytes(&fixture_bytes("tree", "maybe-special.tree"))?
+            TreeRef::from_bytes(&fixture_bytes("tree", "maybe-special.tree"), git_hash::Kind::Sha1)?
                 .entries
                 .len(),
             160
@@ -121,7 +147,7 @@ mod from_bytes {
     #[test]
     fn definitely_special() -> crate::Result {
         assert_eq!(
-            TreeRef::from_bytes(&fixture_bytes("tree", "definitely-special.tree"))?
+            TreeRef::from_bytes(&fixture_bytes("tree", "definitely-special.tree"), git_hash::Kind::Sha1)?
                 .entries
                 .len(),
             19
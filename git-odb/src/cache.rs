@@ -207,7 +207,7 @@ mod impls {
         ) -> Result<Option<(Data<'a>, Option<git_pack::data::entry::Location>)>, Self::Error> {
             if let Some(mut obj_cache) = self.object_cache.as_ref().map(|rc| rc.borrow_mut()) {
                 if let Some(kind) = obj_cache.get(&id.as_ref().to_owned(), buffer) {
-                    return Ok(Some((Data::new(kind, buffer), None)));
+                    return Ok(Some((Data::new(kind, buffer, id.as_ref().kind()), None)));
                 }
             }
             let possibly_obj = self.inner.try_find_cached(id.as_ref(), buffer, pack_cache)?;
@@ -230,5 +230,9 @@ mod impls {
         fn entry_by_location(&self, location: &Location) -> Option<git_pack::find::Entry> {
             self.inner.entry_by_location(location)
         }
+
+        fn bitmap(&self, pack_id: u32) -> Option<git_pack::find::Bitmap> {
+            self.inner.bitmap(pack_id)
+        }
     }
 }
@@ -158,6 +158,7 @@ where
                                 git_object::Data {
                                     kind: r.kind,
                                     data: buffer.as_slice(),
+                                    object_hash: id.kind(),
                                 },
                                 Some(git_pack::data::entry::Location {
                                     pack_id: pack.id,
@@ -257,6 +258,7 @@ where
                                         git_object::Data {
                                             kind: r.kind,
                                             data: buffer.as_slice(),
+                                            object_hash: id.kind(),
                                         },
                                         Some(git_pack::data::entry::Location {
                                             pack_id: pack.id,
@@ -496,6 +498,21 @@ where
             );
         }
     }
+
+    fn bitmap(&self, pack_id: u32) -> Option<git_pack::find::Bitmap> {
+        let pack_id = PackId::from_intrinsic_pack_id(pack_id);
+        let index = self
+            .snapshot
+            .borrow()
+            .indices
+            .iter()
+            .find_map(|index| index.single_index(pack_id).cloned())?;
+        let bitmap = git_pack::bitmap::File::at(index.path().with_extension("bitmap"), index.object_hash()).ok()?;
+        (bitmap.pack_checksum() == index.pack_checksum().as_ref()).then(|| git_pack::find::Bitmap {
+            bitmap: bitmap.into(),
+            index,
+        })
+    }
 }
 
 impl<S> Find for super::Handle<S>
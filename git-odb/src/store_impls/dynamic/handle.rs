@@ -112,6 +112,14 @@ pub(crate) mod index_lookup {
             })
         }
 
+        /// Return the index of the pack with `pack_id` if it's a single-pack index.
+        pub(crate) fn single_index(&self, pack_id: types::PackId) -> Option<&Arc<git_pack::index::File>> {
+            match &self.file {
+                handle::SingleOrMultiIndex::Single { index, .. } if self.id == pack_id.index => Some(index),
+                _ => None,
+            }
+        }
+
         /// Return true if the given object id exists in this index
         pub(crate) fn contains(&self, object_id: &oid) -> bool {
             match &self.file {
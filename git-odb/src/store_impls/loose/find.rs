@@ -257,6 +257,10 @@ impl Store {
             buf.copy_within(decompressed_start + header_size.., 0);
         }
         buf.resize(size, 0);
-        Ok(git_object::Data { kind, data: buf })
+        Ok(git_object::Data {
+            kind,
+            data: buf,
+            object_hash: self.object_hash,
+        })
     }
 }
@@ -38,6 +38,11 @@ impl Store {
     pub fn object_hash(&self) -> git_hash::Kind {
         self.object_hash
     }
+
+    /// Return the path at which the object with `id` is stored if it exists in this database.
+    pub fn object_path(&self, id: impl AsRef<git_hash::oid>) -> PathBuf {
+        hash_path(id.as_ref(), self.path.clone())
+    }
 }
 
 fn hash_path(id: &git_hash::oid, mut root: PathBuf) -> PathBuf {
@@ -54,6 +59,8 @@ pub mod find;
 ///
 pub mod iter;
 ///
+pub mod prune;
+///
 pub mod verify;
 
 /// The type for an iterator over `Result<git_hash::ObjectId, Error>)`
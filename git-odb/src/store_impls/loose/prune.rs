@@ -0,0 +1,144 @@
+use std::{
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Instant, UNIX_EPOCH},
+};
+
+use git_features::progress::Progress;
+
+use crate::loose::Store;
+
+/// The error returned by [`prune()`][Store::prune()].
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("Could not iterate loose objects")]
+    Iteration(#[from] crate::loose::iter::Error),
+    #[error("Could not obtain the modification time of or delete the loose object at '{}'", path.display())]
+    Io {
+        source: io::Error,
+        path: std::path::PathBuf,
+    },
+    #[error("Interrupted")]
+    Interrupted,
+}
+
+/// Options for use in [`prune()`][Store::prune()].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// Only objects whose file was last modified before this time in seconds since unix epoch are deleted, which protects
+    /// objects that were written recently and are about to be referenced.
+    /// If `None`, objects never expire and nothing is deleted.
+    pub expire: Option<u32>,
+    /// If true, no object is deleted, but the ids of those that would have been deleted are returned.
+    pub dry_run: bool,
+}
+
+/// The outcome of [`prune()`][Store::prune()].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outcome {
+    /// The ids of all objects that were deleted, or that would have been deleted in dry-run mode, in no particular order.
+    pub pruned: Vec<git_hash::ObjectId>,
+}
+
+/// The progress ids used in [`prune()`][Store::prune()].
+///
+/// Use this information to selectively extract the progress of interest in case the parent application has custom visualization.
+#[derive(Debug, Copy, Clone)]
+pub enum ProgressId {
+    /// The amount of loose objects that have been checked for reachability.
+    LooseObjects,
+}
+
+impl From<ProgressId> for git_features::progress::Id {
+    fn from(v: ProgressId) -> Self {
+        match v {
+            ProgressId::LooseObjects => *b"PRLO",
+        }
+    }
+}
+
+/// Object deletion
+impl Store {
+    /// Delete the object `id` and return `true` if it existed, or `false` if there was nothing to delete.
+    ///
+    /// The directory the object was stored in is removed as well if it is empty afterwards.
+    pub fn remove(&self, id: impl AsRef<git_hash::oid>) -> io::Result<bool> {
+        let path = self.object_path(id);
+        match std::fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err),
+        }
+        if let Some(dir) = path.parent() {
+            // This fails if the directory still contains objects, or if it was just removed by someone else, both of which is fine.
+            std::fs::remove_dir(dir).ok();
+        }
+        Ok(true)
+    }
+
+    /// Delete all objects for which `is_reachable(id)` returns `false` and whose files were last modified before the expiry date
+    /// in `options`, or just return their ids if `options.dry_run` is set.
+    ///
+    /// It's up to the caller to determine reachability, which typically includes all objects reachable from references,
+    /// their reference logs, the index and other worktrees.
+    pub fn prune(
+        &self,
+        mut is_reachable: impl FnMut(&git_hash::oid) -> bool,
+        mut progress: impl Progress,
+        should_interrupt: &AtomicBool,
+        Options { expire, dry_run }: Options,
+    ) -> Result<Outcome, Error> {
+        let mut pruned = Vec::new();
+        let expire = match expire {
+            Some(expire) => expire,
+            None => return Ok(Outcome { pruned }),
+        };
+
+        let start = Instant::now();
+        let mut progress = progress.add_child_with_id("Pruning", ProgressId::LooseObjects.into());
+        progress.init(None, git_features::progress::count("loose objects"));
+        for id in self.iter() {
+            let id = id?;
+            progress.inc();
+            if should_interrupt.load(Ordering::SeqCst) {
+                return Err(Error::Interrupted);
+            }
+            if is_reachable(&id) {
+                continue;
+            }
+            let path = self.object_path(id);
+            let mtime = match std::fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                Ok(mtime) => mtime,
+                // It was deleted by someone else in the meantime.
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(source) => return Err(Error::Io { source, path }),
+            };
+            let seconds_since_epoch = mtime
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default();
+            if seconds_since_epoch >= expire as u64 {
+                continue;
+            }
+            pruned.push(id);
+        }
+        progress.show_throughput(start);
+
+        if !dry_run {
+            // Deletion happens only after iteration to not interfere with the traversal of the directory tree.
+            let mut removed = Vec::with_capacity(pruned.len());
+            for id in pruned {
+                if self.remove(id).map_err(|source| Error::Io {
+                    source,
+                    path: self.object_path(id),
+                })? {
+                    removed.push(id);
+                }
+            }
+            pruned = removed;
+        }
+        Ok(Outcome { pruned })
+    }
+}
@@ -5,7 +5,7 @@ use std::{
 
 use git_features::progress::Progress;
 
-use crate::{loose::Store, Write};
+use crate::loose::Store;
 
 ///
 pub mod integrity {
@@ -25,6 +25,12 @@ pub mod integrity {
             actual: git_hash::ObjectId,
             expected: git_hash::ObjectId,
         },
+        #[error("{kind} object {id} is part of a SHA1 collision attack")]
+        CollisionAttack {
+            kind: git_object::Kind,
+            id: git_hash::ObjectId,
+            source: git_features::hash::Error,
+        },
         #[error("Objects were deleted during iteration - try again")]
         Retry,
         #[error("Interrupted")]
@@ -65,7 +71,6 @@ impl Store {
         should_interrupt: &AtomicBool,
     ) -> Result<integrity::Statistics, integrity::Error> {
         let mut buf = Vec::new();
-        let sink = crate::sink(self.object_hash);
 
         let mut num_objects = 0;
         let start = Instant::now();
@@ -76,14 +81,20 @@ impl Store {
                 .try_find(id, &mut buf)
                 .map_err(|_| integrity::Error::Retry)?
                 .ok_or(integrity::Error::Retry)?;
-            let actual_id = sink.write_buf(object.kind, object.data).expect("sink never fails");
-            if actual_id != id {
-                return Err(integrity::Error::ObjectHashMismatch {
+            object.verify_checksum(id).map_err(|err| match err {
+                git_object::data::verify::Error::ChecksumMismatch { actual, .. } => {
+                    integrity::Error::ObjectHashMismatch {
+                        kind: object.kind,
+                        actual,
+                        expected: id,
+                    }
+                }
+                git_object::data::verify::Error::CollisionAttack { source, .. } => integrity::Error::CollisionAttack {
                     kind: object.kind,
-                    actual: actual_id,
-                    expected: id,
-                });
-            }
+                    id,
+                    source,
+                },
+            })?;
             object.decode().map_err(|err| integrity::Error::ObjectDecode {
                 source: err,
                 kind: object.kind,
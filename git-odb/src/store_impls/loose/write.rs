@@ -24,6 +24,8 @@ pub enum Error {
         source: tempfile::PersistError,
         target: PathBuf,
     },
+    #[error("Refusing to write an object which is part of a SHA1 collision attack")]
+    CollisionAttack(#[from] hash::Error),
 }
 
 impl crate::traits::Write for Store {
@@ -114,7 +116,7 @@ impl Store {
         &self,
         hash::Write { hash, inner: file }: hash::Write<CompressedTempfile>,
     ) -> Result<git_hash::ObjectId, Error> {
-        let id = git_hash::ObjectId::from(hash.digest());
+        let id = hash.try_digest()?;
         let object_path = loose::hash_path(&id, self.path.clone());
         let object_dir = object_path
             .parent()
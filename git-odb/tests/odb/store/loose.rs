@@ -72,6 +72,99 @@ mod write {
     }
 }
 
+mod prune {
+    use std::sync::atomic::AtomicBool;
+
+    use git_features::progress;
+    use git_odb::{loose, Write};
+
+    use crate::store::loose::{locate_oid, object_ids};
+
+    fn db_with_all_objects_modified_at(dir: &std::path::Path, seconds_since_epoch: i64) -> crate::Result<loose::Store> {
+        let db = loose::Store::at(dir, git_hash::Kind::Sha1);
+        let mut buf = Vec::new();
+        for oid in object_ids() {
+            let obj = locate_oid(oid, &mut buf);
+            db.write_buf(obj.kind, obj.data)?;
+            filetime::set_file_mtime(
+                db.object_path(oid),
+                filetime::FileTime::from_unix_time(seconds_since_epoch, 0),
+            )?;
+        }
+        Ok(db)
+    }
+
+    #[test]
+    fn only_unreachable_and_expired_objects_are_deleted() -> crate::Result {
+        let dir = tempfile::tempdir()?;
+        let db = db_with_all_objects_modified_at(dir.path(), 1000)?;
+        let ids = object_ids();
+        let (reachable, unreachable) = ids.split_at(3);
+        let recent = unreachable[0];
+        filetime::set_file_mtime(db.object_path(recent), filetime::FileTime::from_unix_time(3000, 0))?;
+        let is_reachable = |id: &git_hash::oid| reachable.iter().any(|reachable| reachable.as_ref() == id);
+
+        let mut outcome = db.prune(
+            is_reachable,
+            progress::Discard,
+            &AtomicBool::new(false),
+            loose::prune::Options {
+                expire: Some(2000),
+                dry_run: true,
+            },
+        )?;
+        outcome.pruned.sort();
+        assert_eq!(
+            outcome.pruned,
+            &unreachable[1..],
+            "recent and reachable objects are kept"
+        );
+        assert_eq!(db.iter().count(), ids.len(), "nothing is deleted in dry-run mode");
+
+        let mut outcome = db.prune(
+            is_reachable,
+            progress::Discard,
+            &AtomicBool::new(false),
+            loose::prune::Options {
+                expire: Some(2000),
+                dry_run: false,
+            },
+        )?;
+        outcome.pruned.sort();
+        assert_eq!(outcome.pruned, &unreachable[1..]);
+        let mut remaining = db.iter().collect::<Result<Vec<_>, _>>()?;
+        remaining.sort();
+        assert_eq!(remaining, [reachable, &[recent]].concat());
+
+        let outcome = db.prune(
+            |_| false,
+            progress::Discard,
+            &AtomicBool::new(false),
+            loose::prune::Options {
+                expire: None,
+                dry_run: false,
+            },
+        )?;
+        assert!(outcome.pruned.is_empty(), "objects never expire without expiry date");
+        assert_eq!(db.iter().count(), reachable.len() + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn remove_deletes_empty_fan_out_directories() -> crate::Result {
+        let dir = tempfile::tempdir()?;
+        let db = db_with_all_objects_modified_at(dir.path(), 1000)?;
+        let id = object_ids()[0];
+        let fan_out_dir = db.object_path(id).parent().expect("present").to_owned();
+
+        assert!(db.remove(id)?);
+        assert!(!db.contains(id));
+        assert!(!fan_out_dir.exists(), "the directory only contained this object");
+        assert!(!db.remove(id)?, "the object doesn't exist anymore");
+        Ok(())
+    }
+}
+
 mod contains {
     use crate::store::loose::ldb;
 
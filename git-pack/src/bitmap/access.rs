@@ -0,0 +1,174 @@
+use std::path::Path;
+
+use crate::bitmap::{Bitmap, File, Version};
+
+/// Access to bits
+impl Bitmap {
+    /// Create a bitmap from its EWAH compressed representation.
+    pub fn from_ewah(bitmap: &git_bitmap::ewah::Vec) -> Self {
+        Bitmap {
+            words: bitmap.to_words(),
+        }
+    }
+
+    /// Compress this bitmap into its EWAH representation.
+    pub fn to_ewah(&self) -> git_bitmap::ewah::Vec {
+        git_bitmap::ewah::Vec::from_words(&self.words)
+    }
+
+    /// Return true if the bit at `position` is set.
+    pub fn contains(&self, position: usize) -> bool {
+        self.words
+            .get(position / 64)
+            .map_or(false, |word| word & (1 << (position % 64)) != 0)
+    }
+
+    /// Set the bit at `position` and return true if it wasn't set before.
+    pub fn insert(&mut self, position: usize) -> bool {
+        let word_index = position / 64;
+        if word_index >= self.words.len() {
+            self.words.resize(word_index + 1, 0);
+        }
+        let word = &mut self.words[word_index];
+        let mask = 1 << (position % 64);
+        let inserted = *word & mask == 0;
+        *word |= mask;
+        inserted
+    }
+
+    /// Set all bits that are set in `other` as well.
+    pub fn union_with(&mut self, other: &Bitmap) {
+        if other.words.len() > self.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        for (word, other) in self.words.iter_mut().zip(other.words.iter()) {
+            *word |= other;
+        }
+    }
+
+    /// Flip all bits that are set in `other`.
+    pub fn xor_with(&mut self, other: &Bitmap) {
+        if other.words.len() > self.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        for (word, other) in self.words.iter_mut().zip(other.words.iter()) {
+            *word ^= other;
+        }
+    }
+
+    /// Return the amount of set bits.
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Return true if no bit is set.
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|word| *word == 0)
+    }
+
+    /// Iterate over the positions of all set bits in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(word_index, word)| {
+            let word = *word;
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| word_index * 64 + bit)
+        })
+    }
+}
+
+impl PartialEq for Bitmap {
+    fn eq(&self, other: &Self) -> bool {
+        fn significant_words(words: &[u64]) -> &[u64] {
+            let len = words.iter().rposition(|word| *word != 0).map_or(0, |pos| pos + 1);
+            &words[..len]
+        }
+        significant_words(&self.words) == significant_words(&other.words)
+    }
+}
+
+impl Eq for Bitmap {}
+
+/// Basic file information
+impl File {
+    /// The version of the bitmap file
+    pub fn version(&self) -> Version {
+        self.version
+    }
+    /// The path of the opened bitmap file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// The kind of hash we assume
+    pub fn object_hash(&self) -> git_hash::Kind {
+        self.object_hash
+    }
+    /// The checksum of the pack whose objects are referred to by this bitmap.
+    pub fn pack_checksum(&self) -> &git_hash::oid {
+        &self.pack_checksum
+    }
+    /// The amount of commits for which a bitmap is stored.
+    pub fn num_commits(&self) -> u32 {
+        self.entries.len() as u32
+    }
+    /// Return true if the file contains a name hash for each object.
+    pub fn has_name_hash_cache(&self) -> bool {
+        self.name_hash_cache.is_some()
+    }
+}
+
+/// Bitmap access
+impl File {
+    /// Return the bitmap of all objects of `kind` in the pack.
+    pub fn objects_of_kind(&self, kind: git_object::Kind) -> Bitmap {
+        use git_object::Kind::*;
+        Bitmap::from_ewah(match kind {
+            Commit => &self.commits,
+            Tree => &self.trees,
+            Blob => &self.blobs,
+            Tag => &self.tags,
+        })
+    }
+
+    /// Iterate over the positions of all commits in the pack index for which a bitmap is stored, in the order of the file.
+    pub fn iter_commits(&self) -> impl Iterator<Item = crate::index::EntryIndex> + '_ {
+        self.entries.iter().map(|e| e.index_position)
+    }
+
+    /// Return the bitmap of all objects reachable from the commit at `index_position` in the pack index, or `None` if there is no
+    /// bitmap for it.
+    pub fn lookup(&self, index_position: crate::index::EntryIndex) -> Option<Bitmap> {
+        let entry_index = self
+            .entries_by_index_position
+            .binary_search_by_key(&index_position, |idx| self.entries[*idx as usize].index_position)
+            .ok()
+            .map(|idx| self.entries_by_index_position[idx] as usize)?;
+
+        let mut xor_chain = vec![entry_index];
+        let mut current = entry_index;
+        while self.entries[current].xor_offset != 0 {
+            current -= self.entries[current].xor_offset as usize;
+            xor_chain.push(current);
+        }
+        let mut bitmap = Bitmap::default();
+        for entry_index in xor_chain.into_iter().rev() {
+            bitmap.xor_with(&self.entry_bitmap(entry_index));
+        }
+        Some(bitmap)
+    }
+
+    /// Return the name hash of the object at `index_position` in the pack index, if there is a name hash cache.
+    ///
+    /// See [`crate::data::output::count::name_hash()`] for how it's computed.
+    pub fn name_hash(&self, index_position: crate::index::EntryIndex) -> Option<u32> {
+        let cache = self.name_hash_cache.as_ref()?;
+        let start = cache.start + index_position as usize * 4;
+        (start + 4 <= cache.end).then(|| crate::read_u32(&self.data[start..start + 4]))
+    }
+
+    fn entry_bitmap(&self, entry_index: usize) -> Bitmap {
+        let (bitmap, _) = git_bitmap::ewah::decode(&self.data[self.entries[entry_index].bitmap_offset..])
+            .expect("bitmap bounds were validated when opening the file");
+        Bitmap::from_ewah(&bitmap)
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    convert::{TryFrom, TryInto},
+    path::Path,
+};
+
+use crate::bitmap::{Entry, File, Version, FLAG_HASH_CACHE, FLAG_LOOKUP_TABLE};
+
+mod error {
+    /// The error returned by [File::at()][super::File::at()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("Could not open bitmap file at '{path}'")]
+        Io {
+            source: std::io::Error,
+            path: std::path::PathBuf,
+        },
+        #[error("{message}")]
+        Corrupt { message: &'static str },
+        #[error("Unsupported bitmap version: {version}")]
+        UnsupportedVersion { version: u16 },
+        #[error("Could not decode a type bitmap")]
+        Ewah(#[from] git_bitmap::ewah::decode::Error),
+    }
+}
+
+pub use error::Error;
+
+/// Initialization
+impl File {
+    /// Open the bitmap file at the given `path`, expecting hashes of kind `object_hash`.
+    pub fn at(path: impl AsRef<Path>, object_hash: git_hash::Kind) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = crate::mmap::read_only(path).map_err(|source| Error::Io {
+            source,
+            path: path.to_owned(),
+        })?;
+        // The header is followed by the pack checksum, and the trailer is a checksum of the whole file.
+        let hash_len = object_hash.len_in_bytes();
+        if data.len() < Self::HEADER_LEN + 2 * hash_len {
+            return Err(Error::Corrupt {
+                message: "bitmap file is truncated and too short",
+            });
+        }
+
+        let (signature, rest) = data.split_at(4);
+        if signature != Self::SIGNATURE {
+            return Err(Error::Corrupt {
+                message: "Invalid signature",
+            });
+        }
+        let (version, rest) = rest.split_at(2);
+        let version = match u16::from_be_bytes(version.try_into().expect("two bytes")) {
+            1 => Version::V1,
+            version => return Err(Error::UnsupportedVersion { version }),
+        };
+        let (flags, rest) = rest.split_at(2);
+        let flags = u16::from_be_bytes(flags.try_into().expect("two bytes"));
+        let (num_entries, rest) = rest.split_at(4);
+        let num_entries = crate::read_u32(num_entries);
+        let (pack_checksum, rest) = rest.split_at(hash_len);
+        let pack_checksum = git_hash::ObjectId::from(pack_checksum);
+
+        let body = &rest[..rest.len() - hash_len];
+        let (commits, body) = git_bitmap::ewah::decode(body)?;
+        let (trees, body) = git_bitmap::ewah::decode(body)?;
+        let (blobs, body) = git_bitmap::ewah::decode(body)?;
+        let (tags, mut body) = git_bitmap::ewah::decode(body)?;
+
+        let mut entries = Vec::with_capacity(num_entries as usize);
+        for entry_index in 0..num_entries as usize {
+            if body.len() < 4 + 1 + 1 {
+                return Err(Error::Corrupt {
+                    message: "eof while reading bitmap entry",
+                });
+            }
+            let index_position = crate::read_u32(&body[..4]);
+            let xor_offset = body[4];
+            if xor_offset as usize > entry_index {
+                return Err(Error::Corrupt {
+                    message: "bitmap entry is XORed with an entry that doesn't exist",
+                });
+            }
+            body = &body[4 + 1 + 1 /*flags*/..];
+            let bitmap_offset = data.len() - hash_len - body.len();
+            body = skip_ewah(body).ok_or(Error::Corrupt {
+                message: "eof while reading bitmap of entry",
+            })?;
+            entries.push(Entry {
+                index_position,
+                xor_offset,
+                bitmap_offset,
+            });
+        }
+
+        let name_hash_cache = if flags & FLAG_HASH_CACHE != 0 {
+            let lookup_table_len = if flags & FLAG_LOOKUP_TABLE != 0 {
+                entries.len() * (4 + 8 + 4)
+            } else {
+                0
+            };
+            let cache_len = body
+                .len()
+                .checked_sub(lookup_table_len)
+                .filter(|len| len % 4 == 0)
+                .ok_or(Error::Corrupt {
+                    message: "name hash cache has an invalid size",
+                })?;
+            let start = data.len() - hash_len - body.len();
+            Some(start..start + cache_len)
+        } else {
+            None
+        };
+
+        let mut entries_by_index_position: Vec<u32> =
+            (0..u32::try_from(entries.len()).expect("we read no more than u32::MAX entries")).collect();
+        entries_by_index_position.sort_by_key(|idx| entries[*idx as usize].index_position);
+
+        Ok(File {
+            data,
+            path: path.to_owned(),
+            version,
+            object_hash,
+            pack_checksum,
+            commits,
+            trees,
+            blobs,
+            tags,
+            entries,
+            entries_by_index_position,
+            name_hash_cache,
+        })
+    }
+}
+
+/// Skip over the EWAH bitmap at the beginning of `data` and return the remaining bytes, or `None` if it is truncated.
+fn skip_ewah(data: &[u8]) -> Option<&[u8]> {
+    const U32: usize = std::mem::size_of::<u32>();
+    let num_words = crate::read_u32(data.get(U32..2 * U32)?) as usize;
+    let len = 2 * U32 + num_words.checked_mul(std::mem::size_of::<u64>())? + U32;
+    data.get(len..)
+}
@@ -0,0 +1,72 @@
+use std::{ops::Range, path::PathBuf};
+
+use memmap2::Mmap;
+
+/// Known bitmap file versions
+#[derive(PartialEq, Eq, Ord, PartialOrd, Debug, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+pub enum Version {
+    V1 = 1,
+}
+
+impl Default for Version {
+    fn default() -> Self {
+        Version::V1
+    }
+}
+
+/// An uncompressed set of bits, one for each object in a pack, with bit `n` referring to the `n`th object in pack order,
+/// i.e. the object with the `n`th smallest pack offset.
+#[derive(Debug, Clone, Default)]
+pub struct Bitmap {
+    words: Vec<u64>,
+}
+
+/// A reachability bitmap file, typically stored next to its pack with the `.bitmap` extension, which stores for a selection
+/// of commits all objects of the pack that are reachable from them.
+///
+/// It's used to avoid traversing commits and trees when all objects reachable from a commit are needed.
+pub struct File {
+    data: Mmap,
+    path: PathBuf,
+    version: Version,
+    object_hash: git_hash::Kind,
+    pack_checksum: git_hash::ObjectId,
+    commits: git_bitmap::ewah::Vec,
+    trees: git_bitmap::ewah::Vec,
+    blobs: git_bitmap::ewah::Vec,
+    tags: git_bitmap::ewah::Vec,
+    /// All entries in the order in which they appear in the file.
+    entries: Vec<Entry>,
+    /// Indices into `entries`, sorted by the index position of their commit.
+    entries_by_index_position: Vec<u32>,
+    /// The range of bytes holding one name hash per object, in pack index order.
+    name_hash_cache: Option<Range<usize>>,
+}
+
+/// A commit for which a bitmap is stored.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Copy)]
+struct Entry {
+    /// The position of the commit in the pack index, i.e. the position in the list of all objects sorted by id.
+    index_position: crate::index::EntryIndex,
+    /// If not 0, the stored bitmap must be XORed with the bitmap of the entry this many entries before this one.
+    xor_offset: u8,
+    /// The offset at which the EWAH compressed bitmap starts.
+    bitmap_offset: usize,
+}
+
+/// The header flag indicating that the pack is closed under reachability.
+const FLAG_FULL_DAG: u16 = 0x1;
+/// The header flag indicating that the file contains one name hash per object.
+const FLAG_HASH_CACHE: u16 = 0x4;
+/// The header flag indicating a lookup table between the name hash cache and the trailer.
+const FLAG_LOOKUP_TABLE: u16 = 0x10;
+
+mod access;
+
+///
+pub mod init;
+
+///
+pub mod write;
@@ -0,0 +1,408 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    convert::TryFrom,
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+use git_features::progress::{self, Progress};
+use git_hash::{oid, ObjectId};
+use git_object::{bstr::BString, tree::EntryMode, CommitRefIter};
+
+use crate::{
+    bitmap::{Bitmap, File, FLAG_FULL_DAG, FLAG_HASH_CACHE},
+    index::EntryIndex,
+    FindExt,
+};
+
+mod error {
+    use git_hash::ObjectId;
+
+    /// The error returned by [bitmap::File::write_from_index()][crate::bitmap::File::write_from_index()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error<E>
+    where
+        E: std::error::Error + 'static,
+    {
+        #[error(transparent)]
+        Io(#[from] std::io::Error),
+        #[error("Interrupted")]
+        Interrupted,
+        #[error(transparent)]
+        FindExisting(#[from] crate::find::existing::Error<E>),
+        #[error(transparent)]
+        FindExistingIter(#[from] crate::find::existing_iter::Error<E>),
+        #[error(transparent)]
+        Decode(#[from] git_object::decode::Error),
+        #[error("The object {id} is reachable from a commit in the pack, but isn't contained in the pack itself")]
+        NotInPack { id: ObjectId },
+    }
+}
+pub use error::Error;
+
+/// The amount of preceding bitmaps to try to XOR a bitmap with to make it compress better.
+const MAX_XOR_OFFSET_SEARCH: usize = 10;
+
+/// Options for use in [`bitmap::File::write_from_index()`][crate::bitmap::File::write_from_index()].
+#[derive(Debug, Copy, Clone)]
+pub struct Options {
+    /// The kind of hash to use for the checksum of the bitmap file.
+    pub object_hash: git_hash::Kind,
+}
+
+/// The result of [`bitmap::File::write_from_index()`][crate::bitmap::File::write_from_index()].
+pub struct Outcome<P> {
+    /// The calculated checksum of the written bitmap file.
+    pub checksum: ObjectId,
+    /// The amount of commits for which a bitmap was written.
+    pub num_commits: u32,
+    /// The input progress
+    pub progress: P,
+}
+
+/// The progress ids used in [`write_from_index()`][crate::bitmap::File::write_from_index()].
+///
+/// Use this information to selectively extract the progress of interest in case the parent application has custom visualization.
+#[derive(Debug, Copy, Clone)]
+pub enum ProgressId {
+    /// The amount of objects in the pack whose kind was determined.
+    ObjectKinds,
+    /// The amount of selected commits for which a bitmap was computed.
+    CommitBitmaps,
+    /// The amount of bytes written as part of the bitmap file.
+    BytesWritten,
+}
+
+impl From<ProgressId> for git_features::progress::Id {
+    fn from(v: ProgressId) -> Self {
+        match v {
+            ProgressId::ObjectKinds => *b"BWOK",
+            ProgressId::CommitBitmaps => *b"BWCB",
+            ProgressId::BytesWritten => *b"BWBW",
+        }
+    }
+}
+
+impl File {
+    pub(crate) const SIGNATURE: &'static [u8] = b"BITM";
+    pub(crate) const HEADER_LEN: usize = 4 /*signature*/ +
+        2 /*version*/ +
+        2 /*flags*/ +
+        4 /*num entries*/;
+
+    /// Write a bitmap file for the pack described by `index` to `out`, using `db` to access the objects of the pack.
+    ///
+    /// Bitmaps are written for a selection of commits, which becomes sparser for older commits, along with all commits in `tips`,
+    /// typically the commits that references point to, as long as they are contained in the pack.
+    /// The pack must contain all objects reachable from any of its commits.
+    ///
+    /// The name hash cache is computed from the paths at which objects are first encountered while traversing the trees of
+    /// all commits, and objects that aren't reachable from any commit have a name hash of 0.
+    ///
+    /// Progress is sent to `progress` and interruptions checked via `should_interrupt`.
+    pub fn write_from_index<Find, P>(
+        index: &crate::index::File,
+        db: Find,
+        tips: impl IntoIterator<Item = ObjectId>,
+        out: impl io::Write,
+        mut progress: P,
+        should_interrupt: &AtomicBool,
+        Options { object_hash }: Options,
+    ) -> Result<Outcome<P>, Error<Find::Error>>
+    where
+        Find: crate::Find,
+        P: Progress,
+    {
+        let num_objects = index.num_objects();
+        let positions = Positions::new(index);
+        let mut buf = Vec::new();
+
+        let mut kinds = [
+            Bitmap::default(),
+            Bitmap::default(),
+            Bitmap::default(),
+            Bitmap::default(),
+        ];
+        let mut commits_with_time = Vec::new();
+        {
+            let start = Instant::now();
+            let mut progress = progress.add_child_with_id("reading object kinds", ProgressId::ObjectKinds.into());
+            progress.init(Some(num_objects as usize), progress::count("objects"));
+            for index_position in 0..num_objects {
+                if should_interrupt.load(Ordering::Relaxed) {
+                    return Err(Error::Interrupted);
+                }
+                let (obj, _location) = db.find(index.oid_at_index(index_position), &mut buf)?;
+                if obj.kind == git_object::Kind::Commit {
+                    let time = CommitRefIter::from_bytes(obj.data).committer()?.time;
+                    commits_with_time.push((time.seconds_since_unix_epoch, index_position));
+                }
+                kinds[kind_slot(obj.kind)].insert(positions.pack[index_position as usize] as usize);
+                progress.inc();
+            }
+            progress.show_throughput(start);
+        }
+
+        let tips: BTreeSet<_> = tips
+            .into_iter()
+            .filter_map(|id| index.lookup(id))
+            .filter(|index_position| {
+                kinds[kind_slot(git_object::Kind::Commit)].contains(positions.pack[*index_position as usize] as usize)
+            })
+            .collect();
+        let selected = select_commits(commits_with_time, &tips);
+
+        let mut name_hashes = vec![None; num_objects as usize];
+        let mut bitmaps = Vec::<(EntryIndex, Bitmap)>::with_capacity(selected.len());
+        {
+            let start = Instant::now();
+            let mut progress = progress.add_child_with_id("computing bitmaps", ProgressId::CommitBitmaps.into());
+            progress.init(Some(selected.len()), progress::count("commits"));
+            let mut bitmap_by_index_position = HashMap::<EntryIndex, usize>::new();
+            let mut traversal = Traversal::default();
+            for index_position in selected {
+                if should_interrupt.load(Ordering::Relaxed) {
+                    return Err(Error::Interrupted);
+                }
+                let bitmap = traversal.reachable(
+                    index.oid_at_index(index_position).to_owned(),
+                    &db,
+                    &positions,
+                    |index_position| {
+                        bitmap_by_index_position
+                            .get(&index_position)
+                            .map(|idx| &bitmaps[*idx].1)
+                    },
+                    &mut name_hashes,
+                    &mut buf,
+                )?;
+                bitmap_by_index_position.insert(index_position, bitmaps.len());
+                bitmaps.push((index_position, bitmap));
+                progress.inc();
+            }
+            progress.show_throughput(start);
+        }
+
+        let mut write_progress = progress.add_child_with_id("writing bitmap file", ProgressId::BytesWritten.into());
+        write_progress.init(None, progress::bytes());
+        let start = Instant::now();
+        let mut out = crate::index::util::Count::new(io::BufWriter::with_capacity(
+            8 * 4096,
+            git_features::hash::Write::new(out, object_hash),
+        ));
+        use io::Write;
+        out.write_all(Self::SIGNATURE)?;
+        out.write_all(&(crate::bitmap::Version::V1 as u16).to_be_bytes())?;
+        out.write_all(&(FLAG_FULL_DAG | FLAG_HASH_CACHE).to_be_bytes())?;
+        out.write_all(
+            &u32::try_from(bitmaps.len())
+                .expect("no more commits than objects")
+                .to_be_bytes(),
+        )?;
+        out.write_all(index.pack_checksum().as_slice())?;
+        for kind in &kinds {
+            kind.to_ewah().write_to(&mut out)?;
+        }
+        for (idx, (index_position, bitmap)) in bitmaps.iter().enumerate() {
+            let (xor_offset, bitmap) = xor_compressed(bitmap, &bitmaps[idx.saturating_sub(MAX_XOR_OFFSET_SEARCH)..idx]);
+            out.write_all(&index_position.to_be_bytes())?;
+            out.write_all(&[xor_offset, 0 /*flags*/])?;
+            bitmap.write_to(&mut out)?;
+        }
+        for name_hash in name_hashes {
+            out.write_all(&name_hash.unwrap_or(0).to_be_bytes())?;
+        }
+
+        let bytes_written_without_trailer = out.bytes;
+        let mut out = out.inner.into_inner().map_err(io::Error::from)?;
+        let checksum = out.hash.digest();
+        out.inner.write_all(checksum.as_slice())?;
+        out.inner.flush()?;
+
+        write_progress.inc_by(bytes_written_without_trailer as usize + checksum.as_slice().len());
+        write_progress.show_throughput(start);
+
+        Ok(Outcome {
+            checksum,
+            num_commits: bitmaps.len() as u32,
+            progress,
+        })
+    }
+}
+
+/// The positions of the type bitmaps in the file, which is `commits`, `trees`, `blobs` and `tags`.
+fn kind_slot(kind: git_object::Kind) -> usize {
+    use git_object::Kind::*;
+    match kind {
+        Commit => 0,
+        Tree => 1,
+        Blob => 2,
+        Tag => 3,
+    }
+}
+
+/// Select the commits to write bitmaps for, returned as positions in the pack index, in the order in which they should be
+/// computed and written: oldest first, so newer commits can reuse the bitmaps of their ancestors.
+///
+/// Like git, we select every one of the most recent commits, and then increasingly fewer of the older ones, in addition to `tips`.
+fn select_commits(mut commits_with_time: Vec<(u32, EntryIndex)>, tips: &BTreeSet<EntryIndex>) -> Vec<EntryIndex> {
+    const MIN_COMMITS: usize = 100;
+    const MAX_COMMITS: usize = 5000;
+    const MUST_REGION: usize = 100;
+    const MIN_REGION: usize = 20000;
+
+    fn next_commit_index(idx: usize) -> usize {
+        if idx <= MUST_REGION {
+            0
+        } else if idx <= MIN_REGION {
+            (idx - MUST_REGION).min(MIN_COMMITS)
+        } else {
+            (idx - MIN_REGION).clamp(MIN_COMMITS, MAX_COMMITS)
+        }
+    }
+
+    commits_with_time.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    let mut selected: Vec<_> = commits_with_time
+        .iter()
+        .filter(|(_, index_position)| tips.contains(index_position))
+        .copied()
+        .collect();
+    let mut idx = 0;
+    while idx < commits_with_time.len() {
+        selected.push(commits_with_time[idx]);
+        idx += next_commit_index(idx) + 1;
+    }
+    selected.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+    selected.dedup();
+    selected.into_iter().map(|(_, index_position)| index_position).collect()
+}
+
+/// Return the xor-offset to use for `bitmap` along with what to store for it, which is either `bitmap` itself if the offset is 0,
+/// or `bitmap` XORed with the bitmap that many positions before it, whichever of the `previous` bitmaps compresses best.
+fn xor_compressed(bitmap: &Bitmap, previous: &[(EntryIndex, Bitmap)]) -> (u8, git_bitmap::ewah::Vec) {
+    let mut best = (0, bitmap.to_ewah());
+    let mut best_size = ewah_size(&best.1);
+    for (distance, (_, base)) in previous.iter().rev().enumerate() {
+        let mut xored = bitmap.clone();
+        xored.xor_with(base);
+        let xored = xored.to_ewah();
+        let size = ewah_size(&xored);
+        if size < best_size {
+            best_size = size;
+            best = (distance as u8 + 1, xored);
+        }
+    }
+    best
+}
+
+fn ewah_size(bitmap: &git_bitmap::ewah::Vec) -> usize {
+    bitmap.write_to(io::sink()).expect("writing to sink never fails")
+}
+
+/// A mapping of positions in the pack index to positions in the pack.
+struct Positions<'a> {
+    index: &'a crate::index::File,
+    /// The position of each object in the pack, indexed by its position in the pack index.
+    pack: Vec<u32>,
+}
+
+impl<'a> Positions<'a> {
+    fn new(index: &'a crate::index::File) -> Self {
+        let mut index_positions_in_pack_order: Vec<EntryIndex> = (0..index.num_objects()).collect();
+        index_positions_in_pack_order.sort_by_key(|index_position| index.pack_offset_at_index(*index_position));
+        let mut pack = vec![0; index_positions_in_pack_order.len()];
+        for (pack_position, index_position) in index_positions_in_pack_order.into_iter().enumerate() {
+            pack[index_position as usize] = pack_position as u32;
+        }
+        Positions { index, pack }
+    }
+
+    fn lookup<E: std::error::Error>(&self, id: &oid) -> Result<(EntryIndex, usize), Error<E>> {
+        let index_position = self
+            .index
+            .lookup(id)
+            .ok_or_else(|| Error::NotInPack { id: id.to_owned() })?;
+        Ok((index_position, self.pack[index_position as usize] as usize))
+    }
+}
+
+/// State to traverse all objects reachable from a commit.
+#[derive(Default)]
+struct Traversal {
+    commits: Vec<ObjectId>,
+    trees: Vec<(ObjectId, BString)>,
+    entries: Vec<(EntryMode, ObjectId, BString)>,
+}
+
+impl Traversal {
+    /// Return a bitmap of all objects reachable from `commit`, using `bitmap_of(index_position)` to obtain the bitmaps of
+    /// commits that were computed already.
+    /// Store the name hash of each newly encountered tree and blob in `name_hashes`.
+    fn reachable<'b, Find: crate::Find>(
+        &mut self,
+        commit: ObjectId,
+        db: &Find,
+        positions: &Positions<'_>,
+        bitmap_of: impl Fn(EntryIndex) -> Option<&'b Bitmap>,
+        name_hashes: &mut [Option<u32>],
+        buf: &mut Vec<u8>,
+    ) -> Result<Bitmap, Error<Find::Error>> {
+        let mut bitmap = Bitmap::default();
+        self.commits.clear();
+        self.commits.push(commit);
+        while let Some(id) = self.commits.pop() {
+            let (index_position, pack_position) = positions.lookup(&id)?;
+            if bitmap.contains(pack_position) {
+                continue;
+            }
+            if let Some(ancestor_bitmap) = bitmap_of(index_position) {
+                bitmap.union_with(ancestor_bitmap);
+                continue;
+            }
+            bitmap.insert(pack_position);
+            let mut commit = db.find_commit_iter(id, buf)?.0;
+            self.trees.push((commit.tree_id()?, BString::default()));
+            self.commits.extend(commit.parent_ids());
+
+            while let Some((tree_id, path)) = self.trees.pop() {
+                let (index_position, pack_position) = positions.lookup(&tree_id)?;
+                if !bitmap.insert(pack_position) {
+                    continue;
+                }
+                set_name_hash(name_hashes, index_position, &path);
+                self.entries.clear();
+                for entry in db.find_tree_iter(tree_id, buf)?.0 {
+                    let entry = entry?;
+                    let mut entry_path = path.clone();
+                    if !entry_path.is_empty() {
+                        entry_path.push(b'/');
+                    }
+                    entry_path.extend_from_slice(entry.filename);
+                    self.entries.push((entry.mode, entry.oid.to_owned(), entry_path));
+                }
+                for (mode, id, path) in self.entries.drain(..) {
+                    match mode {
+                        EntryMode::Commit => {}
+                        EntryMode::Tree => self.trees.push((id, path)),
+                        EntryMode::Blob | EntryMode::BlobExecutable | EntryMode::Link => {
+                            let (index_position, pack_position) = positions.lookup(&id)?;
+                            if bitmap.insert(pack_position) {
+                                set_name_hash(name_hashes, index_position, &path);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(bitmap)
+    }
+}
+
+fn set_name_hash(name_hashes: &mut [Option<u32>], index_position: EntryIndex, path: &BString) {
+    let name_hash = &mut name_hashes[index_position as usize];
+    if name_hash.is_none() {
+        *name_hash = Some(crate::data::output::count::name_hash(path.as_ref()));
+    }
+}
@@ -68,3 +68,233 @@ pub fn apply(base: &[u8], mut target: &mut [u8], data: &[u8]) {
     assert_eq!(i, data.len());
     assert_eq!(target.len(), 0);
 }
+
+/// The size of the blocks of the base object which are indexed, and thus the minimal size of a match.
+const BLOCK_SIZE: usize = 16;
+/// The maximum amount of bytes to copy with a single instruction, which older git versions can't exceed.
+const MAX_COPY_SIZE: usize = 0x10000;
+/// The maximum amount of bytes to insert with a single instruction.
+const MAX_INSERT_SIZE: usize = 0x7f;
+/// Marks an empty slot in the block table of an [`Index`].
+const EMPTY: u32 = u32::MAX;
+/// The multiplier of our rolling hash.
+const HASH_MULTIPLIER: u32 = 0x0100_0193;
+/// The factor of the byte leaving the rolling hash window, `HASH_MULTIPLIER^(BLOCK_SIZE - 1)`.
+const HASH_OUTGOING_FACTOR: u32 = {
+    let mut factor = 1u32;
+    let mut n = 1;
+    while n < BLOCK_SIZE {
+        factor = factor.wrapping_mul(HASH_MULTIPLIER);
+        n += 1;
+    }
+    factor
+};
+
+/// An index over all blocks of a base object to quickly find matches of blocks of target objects, allowing
+/// to produce delta instructions for any amount of target objects.
+pub struct Index {
+    /// Maps a block hash to the offset of the first block in the base object with this hash, or `EMPTY`.
+    table: Vec<u32>,
+    /// The amount of bits to shift a block hash to the right to obtain its slot in `table`.
+    shift: u32,
+}
+
+impl Index {
+    /// Create a new index over the blocks of `base`.
+    pub fn new(base: &[u8]) -> Self {
+        let num_blocks = if base.len() < EMPTY as usize {
+            base.len() / BLOCK_SIZE
+        } else {
+            0 // offsets of copy instructions can't be represented
+        };
+        let bits = (num_blocks.max(1) * 2).next_power_of_two().trailing_zeros().min(30);
+        let mut index = Index {
+            table: vec![EMPTY; 1 << bits],
+            shift: 32 - bits,
+        };
+        for ofs in (0..num_blocks).map(|block| block * BLOCK_SIZE) {
+            let slot = index.slot(hash(&base[ofs..][..BLOCK_SIZE]));
+            if index.table[slot] == EMPTY {
+                index.table[slot] = ofs as u32;
+            }
+        }
+        index
+    }
+
+    /// Produce delta instructions, including the header, which turn `base`, the object this index was created from, into `target`.
+    /// Return `None` if the instructions would be larger than `max_size` bytes.
+    pub fn create_delta(&self, base: &[u8], target: &[u8], max_size: usize) -> Option<Vec<u8>> {
+        let mut out = Vec::with_capacity((target.len() / 4).min(max_size) + 2 * 10);
+        encode_header_size(&mut out, base.len());
+        encode_header_size(&mut out, target.len());
+
+        let mut insert_start = 0;
+        let mut pos = 0;
+        let mut block_hash = if target.len() >= BLOCK_SIZE {
+            hash(&target[..BLOCK_SIZE])
+        } else {
+            0
+        };
+        while pos + BLOCK_SIZE <= target.len() {
+            let base_ofs = self.table[self.slot(block_hash)];
+            if base_ofs != EMPTY && base[base_ofs as usize..][..BLOCK_SIZE] == target[pos..][..BLOCK_SIZE] {
+                let base_ofs = base_ofs as usize;
+                let forward = common_prefix_len(&base[base_ofs + BLOCK_SIZE..], &target[pos + BLOCK_SIZE..]);
+                let backward = base[..base_ofs]
+                    .iter()
+                    .rev()
+                    .zip(target[insert_start..pos].iter().rev())
+                    .take_while(|(lhs, rhs)| lhs == rhs)
+                    .count();
+                let start = pos - backward;
+                let len = backward + BLOCK_SIZE + forward;
+
+                encode_inserts(&mut out, &target[insert_start..start]);
+                encode_copies(&mut out, base_ofs - backward, len);
+                if out.len() > max_size {
+                    return None;
+                }
+                pos = start + len;
+                insert_start = pos;
+                if pos + BLOCK_SIZE <= target.len() {
+                    block_hash = hash(&target[pos..][..BLOCK_SIZE]);
+                }
+                continue;
+            }
+            if pos + BLOCK_SIZE < target.len() {
+                block_hash = roll(block_hash, target[pos], target[pos + BLOCK_SIZE]);
+            }
+            pos += 1;
+            if out.len() + (pos - insert_start) > max_size {
+                return None;
+            }
+        }
+        encode_inserts(&mut out, &target[insert_start..]);
+        (out.len() <= max_size).then_some(out)
+    }
+
+    fn slot(&self, block_hash: u32) -> usize {
+        (block_hash.wrapping_mul(0x9e37_79b1) >> self.shift) as usize
+    }
+}
+
+fn hash(block: &[u8]) -> u32 {
+    block.iter().fold(0u32, |hash, b| {
+        hash.wrapping_mul(HASH_MULTIPLIER).wrapping_add(*b as u32)
+    })
+}
+
+fn roll(hash: u32, outgoing: u8, incoming: u8) -> u32 {
+    hash.wrapping_sub((outgoing as u32).wrapping_mul(HASH_OUTGOING_FACTOR))
+        .wrapping_mul(HASH_MULTIPLIER)
+        .wrapping_add(incoming as u32)
+}
+
+fn common_prefix_len(lhs: &[u8], rhs: &[u8]) -> usize {
+    lhs.iter().zip(rhs).take_while(|(lhs, rhs)| lhs == rhs).count()
+}
+
+/// The inverse of [`decode_header_size()`].
+fn encode_header_size(out: &mut Vec<u8>, mut size: usize) {
+    loop {
+        let byte = (size & 0x7f) as u8;
+        size >>= 7;
+        if size == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn encode_inserts(out: &mut Vec<u8>, data: &[u8]) {
+    for chunk in data.chunks(MAX_INSERT_SIZE) {
+        out.push(chunk.len() as u8);
+        out.extend_from_slice(chunk);
+    }
+}
+
+fn encode_copies(out: &mut Vec<u8>, mut ofs: usize, mut len: usize) {
+    while len != 0 {
+        let size = len.min(MAX_COPY_SIZE);
+        let cmd_pos = out.len();
+        let mut cmd = 0b1000_0000;
+        out.push(cmd);
+        for (bit, byte) in (ofs as u32).to_le_bytes().iter().enumerate() {
+            if *byte != 0 {
+                cmd |= 1 << bit;
+                out.push(*byte);
+            }
+        }
+        for (bit, byte) in (size as u32).to_le_bytes().iter().take(3).enumerate() {
+            if *byte != 0 {
+                cmd |= 0b0001_0000 << bit;
+                out.push(*byte);
+            }
+        }
+        out[cmd_pos] = cmd;
+        ofs += size;
+        len -= size;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(base: &[u8], target: &[u8]) -> usize {
+        let delta = Index::new(base)
+            .create_delta(base, target, usize::MAX)
+            .expect("no size limit");
+        let (base_size, base_size_len) = decode_header_size(&delta);
+        let (result_size, result_size_len) = decode_header_size(&delta[base_size_len..]);
+        assert_eq!(base_size as usize, base.len());
+        assert_eq!(result_size as usize, target.len());
+        let mut out = vec![0; target.len()];
+        apply(base, &mut out, &delta[base_size_len + result_size_len..]);
+        assert_eq!(out, target, "applying the delta to the base yields the target");
+        delta.len()
+    }
+
+    fn data(len: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn deltas_reproduce_their_target() {
+        let base = data(100_000, 1);
+        assert_eq!(round_trip(&[], &[]), 2);
+        assert_eq!(round_trip(&[], b"hello"), 2 + 1 + 5, "a single insert instruction");
+        assert_eq!(
+            round_trip(&base, &base),
+            6 + 2 + 4,
+            "a copy of the maximum size, followed by the remainder"
+        );
+        round_trip(&base, &base[..15]);
+        round_trip(&base[..15], &base);
+
+        let mut target = base.clone();
+        target[50_000] ^= 0xff;
+        target.splice(1000..1000, data(300, 2));
+        target.truncate(90_000);
+        target.extend_from_slice(&base[10..200]);
+        assert!(
+            round_trip(&base, &target) < 400,
+            "most of the target is copied from the base"
+        );
+    }
+
+    #[test]
+    fn deltas_exceeding_the_maximum_size_are_not_produced() {
+        let base = data(1000, 1);
+        let target = data(1000, 2);
+        assert!(Index::new(&base).create_delta(&base, &target, 500).is_none());
+        assert!(Index::new(&base).create_delta(&base, &base, 500).is_some());
+    }
+}
@@ -37,6 +37,8 @@ pub struct FromEntriesIter<I, W> {
     /// It stores the pack offsets at which objects begin.
     /// Additionally we store if an object was invalid, and if so we will not write it nor will we allow delta objects to it.
     pack_offsets_and_validity: Vec<(u64, bool)>,
+    /// If set, the information needed to write a pack index for each written entry.
+    index_entries: Option<Vec<crate::index::write::Entry>>,
     /// If we are done, no additional writes will occur
     is_done: bool,
 }
@@ -76,10 +78,27 @@ where
             pack_offsets_and_validity: Vec::with_capacity(num_entries as usize),
             written: 0,
             header_info: Some((version, num_entries)),
+            index_entries: None,
             is_done: false,
         }
     }
 
+    /// Record the object id, pack offset and CRC32 of each written entry to allow writing a pack index along with the pack
+    /// without having to decode it, and obtain them with [`take_index_entries()`][Self::take_index_entries()] once done.
+    pub fn with_index_entries(mut self) -> Self {
+        self.index_entries = Some(Vec::with_capacity(self.pack_offsets_and_validity.capacity()));
+        self
+    }
+
+    /// Take the information needed to write a pack index for all entries written thus far, or `None` if
+    /// [`with_index_entries()`][Self::with_index_entries()] wasn't called or they were taken already.
+    ///
+    /// The entries are complete once we are done writing, see [`crate::index::File::write_entries_to_stream()`] to write
+    /// an index with them.
+    pub fn take_index_entries(&mut self) -> Option<Vec<crate::index::write::Entry>> {
+        self.index_entries.take()
+    }
+
     /// Consume this instance and return the `output` implementation.
     ///
     /// _Note_ that the `input` iterator can be moved out of this instance beforehand.
@@ -107,7 +126,8 @@ where
                         self.pack_offsets_and_validity.push((0, false));
                         continue;
                     };
-                    self.pack_offsets_and_validity.push((self.written, true));
+                    let pack_offset = self.written;
+                    self.pack_offsets_and_validity.push((pack_offset, true));
                     let header = entry.to_entry_header(self.entry_version, |index| {
                         let (base_offset, is_valid_object) = self.pack_offsets_and_validity[index];
                         if !is_valid_object {
@@ -115,17 +135,34 @@ where
                         }
                         self.written - base_offset
                     });
-                    self.written += header.write_to(entry.decompressed_size as u64, &mut self.output)? as u64;
-                    self.written += std::io::copy(&mut &*entry.compressed_data, &mut self.output)?;
+                    match self.index_entries.as_mut() {
+                        Some(index_entries) => {
+                            let mut header_buf = [0u8; 12 + git_hash::Kind::longest().len_in_bytes()];
+                            let header_len = header.write_to(entry.decompressed_size as u64, &mut header_buf[..])?;
+                            let header = &header_buf[..header_len];
+                            self.output.write_all(header)?;
+                            self.output.write_all(&entry.compressed_data)?;
+                            index_entries.push(crate::index::write::Entry {
+                                id: entry.id,
+                                pack_offset,
+                                crc32: hash::crc32_update(hash::crc32(header), &entry.compressed_data),
+                            });
+                            self.written += (header_len + entry.compressed_data.len()) as u64;
+                        }
+                        None => {
+                            self.written += header.write_to(entry.decompressed_size as u64, &mut self.output)? as u64;
+                            self.written += std::io::copy(&mut &*entry.compressed_data, &mut self.output)?;
+                        }
+                    }
                 }
             }
             None => {
                 let digest = self.output.hash.clone().digest();
-                self.output.inner.write_all(&digest[..])?;
-                self.written += digest.len() as u64;
+                self.output.inner.write_all(digest.as_slice())?;
+                self.written += digest.as_slice().len() as u64;
                 self.output.inner.flush()?;
                 self.is_done = true;
-                self.trailer = Some(git_hash::ObjectId::from(digest));
+                self.trailer = Some(digest);
             }
         };
         Ok(self.written - previous_written)
@@ -1,4 +1,5 @@
 use git_hash::ObjectId;
+use git_object::bstr::BStr;
 
 use crate::data::output::Count;
 
@@ -35,10 +36,22 @@ impl Count {
         Count {
             id: oid.into(),
             entry_pack_location: PackLocation::LookedUp(location),
+            name_hash: 0,
+            try_delta: true,
         }
     }
 }
 
+/// Compute a hash of `path` which makes paths sharing the same suffix, like objects with the same file name or extension,
+/// sort close to each other. It's the same hash that `git pack-objects` uses.
+///
+/// Note that whitespace is ignored, and that `0` is returned for empty paths.
+pub fn name_hash(path: &BStr) -> u32 {
+    path.iter()
+        .filter(|b| !b.is_ascii_whitespace())
+        .fold(0u32, |hash, b| (hash >> 2).wrapping_add((*b as u32) << 24))
+}
+
 #[path = "objects/mod.rs"]
 mod objects_impl;
 pub use objects_impl::{objects, objects_unthreaded};
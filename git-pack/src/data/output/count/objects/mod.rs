@@ -5,6 +5,7 @@ use std::{
 
 use git_features::{parallel, progress::Progress};
 use git_hash::ObjectId;
+use git_object::bstr::BStr;
 
 use crate::{data::output, find};
 
@@ -33,6 +34,9 @@ pub type Result<E1, E2> = std::result::Result<(Vec<output::Count>, Outcome), Err
 ///   * a way to obtain progress information
 /// * `should_interrupt`
 ///  * A flag that is set to true if the operation should stop
+/// * `try_delta(path) -> bool`
+///   * return `false` if the object found at `path` during tree traversal should not be delta-compressed, which is typically the case
+///     if the `delta` attribute is unset for it. This value is stored in [`Count::try_delta`][output::Count::try_delta].
 /// * `options`
 ///   * more configuration
 pub fn objects<Find, Iter, IterErr, Oid>(
@@ -40,6 +44,7 @@ pub fn objects<Find, Iter, IterErr, Oid>(
     objects_ids: Iter,
     progress: impl Progress,
     should_interrupt: &AtomicBool,
+    try_delta: impl Fn(&BStr) -> bool + Sync,
     Options {
         thread_limit,
         input_object_expansion,
@@ -83,12 +88,14 @@ where
                         p.init(None, git_features::progress::count("objects"));
                         p
                     },
+                    util::Bitmaps::default(),
                 )
             }
         },
         {
             let seen_objs = &seen_objs;
-            move |oids: Vec<std::result::Result<Oid, IterErr>>, (buf1, buf2, progress)| {
+            let try_delta = &try_delta;
+            move |oids: Vec<std::result::Result<Oid, IterErr>>, (buf1, buf2, progress, bitmaps)| {
                 expand::this(
                     &db,
                     input_object_expansion,
@@ -97,7 +104,9 @@ where
                     buf1,
                     buf2,
                     progress,
+                    bitmaps,
                     should_interrupt,
+                    try_delta,
                     true, /*allow pack lookups*/
                 )
             }
@@ -112,6 +121,7 @@ pub fn objects_unthreaded<Find, IterErr, Oid>(
     object_ids: impl Iterator<Item = std::result::Result<Oid, IterErr>>,
     mut progress: impl Progress,
     should_interrupt: &AtomicBool,
+    try_delta: impl Fn(&BStr) -> bool,
     input_object_expansion: ObjectExpansion,
 ) -> Result<find::existing::Error<Find::Error>, IterErr>
 where
@@ -130,7 +140,9 @@ where
         &mut buf1,
         &mut buf2,
         &mut progress,
+        &mut util::Bitmaps::default(),
         should_interrupt,
+        &try_delta,
         false, /*allow pack lookups*/
     )
 }
@@ -140,7 +152,7 @@ mod expand {
 
     use git_features::progress::Progress;
     use git_hash::{oid, ObjectId};
-    use git_object::{CommitRefIter, TagRefIter};
+    use git_object::{bstr::BStr, CommitRefIter, TagRefIter};
 
     use super::{
         tree,
@@ -161,7 +173,9 @@ mod expand {
         buf1: &mut Vec<u8>,
         #[allow(clippy::ptr_arg)] buf2: &mut Vec<u8>,
         progress: &mut impl Progress,
+        bitmaps: &mut util::Bitmaps,
         should_interrupt: &AtomicBool,
+        try_delta: &impl Fn(&BStr) -> bool,
         allow_pack_lookups: bool,
     ) -> super::Result<find::existing::Error<Find::Error>, IterErr>
     where
@@ -175,8 +189,8 @@ mod expand {
         let mut tree_traversal_state = git_traverse::tree::breadthfirst::State::default();
         let mut tree_diff_state = git_diff::tree::State::default();
         let mut parent_commit_ids = Vec::new();
-        let mut traverse_delegate = tree::traverse::AllUnseen::new(seen_objs);
-        let mut changes_delegate = tree::changes::AllNew::new(seen_objs);
+        let mut traverse_delegate = tree::traverse::AllUnseen::new(seen_objs, try_delta);
+        let mut changes_delegate = tree::changes::AllNew::new(seen_objs, try_delta);
         let mut outcome = Outcome::default();
 
         let stats = &mut outcome;
@@ -229,11 +243,12 @@ mod expand {
                                     push_obj_count_unique(
                                         &mut out, seen_objs, &tree_id, location, progress, stats, true,
                                     );
-                                    git_object::TreeRefIter::from_bytes(obj.data)
+                                    git_object::TreeRefIter::from_bytes(obj.data, obj.object_hash)
                                 };
 
                                 let objects = if parent_commit_ids.is_empty() {
                                     traverse_delegate.clear();
+                                    let trees_start = out.len();
                                     git_traverse::tree::breadthfirst(
                                         current_tree_iter,
                                         &mut tree_traversal_state,
@@ -252,8 +267,11 @@ mod expand {
                                         &mut traverse_delegate,
                                     )
                                     .map_err(Error::TreeTraverse)?;
+                                    set_tree_path_info(&mut out[trees_start..], &traverse_delegate.trees);
                                     &traverse_delegate.non_trees
                                 } else {
+                                    // Objects new compared to any of the parents are needed, so collect them across all parents.
+                                    changes_delegate.clear();
                                     for commit_id in &parent_commit_ids {
                                         let parent_tree_id = {
                                             let (parent_commit_obj, location) = db.find(commit_id, buf2)?;
@@ -276,10 +294,13 @@ mod expand {
                                                 stats,
                                                 true,
                                             );
-                                            git_object::TreeRefIter::from_bytes(parent_tree_obj.data)
+                                            git_object::TreeRefIter::from_bytes(
+                                                parent_tree_obj.data,
+                                                parent_tree_obj.object_hash,
+                                            )
                                         };
 
-                                        changes_delegate.clear();
+                                        changes_delegate.clear_path();
                                         git_diff::tree::Changes::from(Some(parent_tree))
                                             .needed_to_obtain(
                                                 current_tree_iter.clone(),
@@ -294,25 +315,36 @@ mod expand {
                                     }
                                     &changes_delegate.objects
                                 };
-                                for id in objects.iter() {
-                                    out.push(id_to_count(db, buf2, id, progress, stats, allow_pack_lookups));
+                                for (id, name_hash, try_delta) in objects.iter() {
+                                    out.push(id_to_count(
+                                        db,
+                                        buf2,
+                                        id,
+                                        *name_hash,
+                                        *try_delta,
+                                        progress,
+                                        stats,
+                                        allow_pack_lookups,
+                                    ));
                                 }
                                 break;
                             }
                         }
                     }
                 }
-                TreeContents => {
+                TreeContents | TreeContentsOfAncestry => {
                     use git_object::Kind::*;
                     let mut id = id;
                     let mut obj = (obj, location);
                     loop {
-                        push_obj_count_unique(&mut out, seen_objs, &id, obj.1.clone(), progress, stats, false);
+                        let inserted =
+                            push_obj_count_unique(&mut out, seen_objs, &id, obj.1.clone(), progress, stats, false);
                         match obj.0.kind {
                             Tree => {
                                 traverse_delegate.clear();
+                                let trees_start = out.len();
                                 git_traverse::tree::breadthfirst(
-                                    git_object::TreeRefIter::from_bytes(obj.0.data),
+                                    git_object::TreeRefIter::from_bytes(obj.0.data, obj.0.object_hash),
                                     &mut tree_traversal_state,
                                     |oid, buf| {
                                         stats.decoded_objects += 1;
@@ -329,12 +361,43 @@ mod expand {
                                     &mut traverse_delegate,
                                 )
                                 .map_err(Error::TreeTraverse)?;
-                                for id in traverse_delegate.non_trees.iter() {
-                                    out.push(id_to_count(db, buf1, id, progress, stats, allow_pack_lookups));
+                                set_tree_path_info(&mut out[trees_start..], &traverse_delegate.trees);
+                                for (id, name_hash, try_delta) in traverse_delegate.non_trees.iter() {
+                                    out.push(id_to_count(
+                                        db,
+                                        buf1,
+                                        id,
+                                        *name_hash,
+                                        *try_delta,
+                                        progress,
+                                        stats,
+                                        allow_pack_lookups,
+                                    ));
                                 }
                                 break;
                             }
                             Commit => {
+                                if input_object_expansion == TreeContentsOfAncestry {
+                                    if !inserted {
+                                        break;
+                                    }
+                                    if let Some(pack_id) = obj.1.as_ref().map(|location| location.pack_id) {
+                                        if count_reachable_with_bitmap(
+                                            db,
+                                            bitmaps,
+                                            &id,
+                                            pack_id,
+                                            &mut out,
+                                            seen_objs,
+                                            buf2,
+                                            progress,
+                                            stats,
+                                            allow_pack_lookups,
+                                        ) {
+                                            break;
+                                        }
+                                    }
+                                }
                                 id = CommitRefIter::from_bytes(obj.0.data)
                                     .tree_id()
                                     .expect("every commit has a tree");
@@ -354,7 +417,9 @@ mod expand {
                         }
                     }
                 }
-                AsIs => push_obj_count_unique(&mut out, seen_objs, &id, location, progress, stats, false),
+                AsIs => {
+                    push_obj_count_unique(&mut out, seen_objs, &id, location, progress, stats, false);
+                }
             }
         }
         outcome.total_objects = out.len();
@@ -370,7 +435,7 @@ mod expand {
         progress: &mut impl Progress,
         statistics: &mut Outcome,
         count_expanded: bool,
-    ) {
+    ) -> bool {
         let inserted = all_seen.insert(id.to_owned());
         if inserted {
             progress.inc();
@@ -380,13 +445,77 @@ mod expand {
             }
             out.push(output::Count::from_data(id, location));
         }
+        inserted
+    }
+
+    /// Count all unseen objects reachable from the commit `id` in the pack with `pack_id` using the pack's bitmap,
+    /// and return `true` if there was a bitmap for the commit.
+    #[allow(clippy::too_many_arguments)]
+    fn count_reachable_with_bitmap<Find: crate::Find>(
+        db: &Find,
+        bitmaps: &mut util::Bitmaps,
+        id: &oid,
+        pack_id: u32,
+        out: &mut Vec<output::Count>,
+        all_seen: &impl util::InsertImmutable<ObjectId>,
+        buf: &mut Vec<u8>,
+        progress: &mut impl Progress,
+        statistics: &mut Outcome,
+        allow_pack_lookups: bool,
+    ) -> bool {
+        let (bitmap, index_positions_in_pack_order) = match bitmaps.get(db, pack_id) {
+            Some(bitmap) => bitmap,
+            None => return false,
+        };
+        let reachable = match bitmap
+            .index
+            .lookup(id)
+            .and_then(|index_position| bitmap.bitmap.lookup(index_position))
+        {
+            Some(reachable) => reachable,
+            None => return false,
+        };
+        for pack_position in reachable.iter() {
+            let index_position = match index_positions_in_pack_order.get(pack_position) {
+                Some(index_position) => *index_position,
+                None => break,
+            };
+            let id = bitmap.index.oid_at_index(index_position);
+            if all_seen.insert(id.to_owned()) {
+                let name_hash = bitmap.bitmap.name_hash(index_position).unwrap_or(0);
+                out.push(id_to_count(
+                    db,
+                    buf,
+                    id,
+                    name_hash,
+                    true,
+                    progress,
+                    statistics,
+                    allow_pack_lookups,
+                ));
+            }
+        }
+        true
+    }
+
+    /// Assign path information to `trees`, the counts pushed while traversing a tree, which are in the same order as the
+    /// `path_info` collected for them as the traversal is breadth-first.
+    fn set_tree_path_info(trees: &mut [output::Count], path_info: &[(u32, bool)]) {
+        debug_assert_eq!(trees.len(), path_info.len(), "each entered tree was looked up");
+        for (count, (name_hash, try_delta)) in trees.iter_mut().zip(path_info) {
+            count.name_hash = *name_hash;
+            count.try_delta = *try_delta;
+        }
     }
 
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     fn id_to_count<Find: crate::Find>(
         db: &Find,
         buf: &mut Vec<u8>,
         id: &oid,
+        name_hash: u32,
+        try_delta: bool,
         progress: &mut impl Progress,
         statistics: &mut Outcome,
         allow_pack_lookups: bool,
@@ -400,6 +529,8 @@ mod expand {
             } else {
                 PackLocation::NotLookedUp
             },
+            name_hash,
+            try_delta,
         }
     }
 }
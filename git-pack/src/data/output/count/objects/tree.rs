@@ -6,39 +6,59 @@ pub mod changes {
     use git_hash::ObjectId;
     use git_object::{bstr::BStr, tree::EntryMode};
 
-    use crate::data::output::count::objects_impl::util::InsertImmutable;
+    use crate::data::output::count::objects_impl::util::{InsertImmutable, Path};
 
-    pub struct AllNew<'a, H> {
-        pub objects: Vec<ObjectId>,
+    pub struct AllNew<'a, H, D> {
+        /// The new objects along with the name hash of their path and whether they may be delta-compressed.
+        pub objects: Vec<(ObjectId, u32, bool)>,
         all_seen: &'a H,
+        try_delta: &'a D,
+        path: Path,
     }
 
-    impl<'a, H> AllNew<'a, H>
+    impl<'a, H, D> AllNew<'a, H, D>
     where
         H: InsertImmutable<ObjectId>,
+        D: Fn(&BStr) -> bool,
     {
-        pub fn new(all_seen: &'a H) -> Self {
+        pub fn new(all_seen: &'a H, try_delta: &'a D) -> Self {
             AllNew {
                 objects: Default::default(),
                 all_seen,
+                try_delta,
+                path: Default::default(),
             }
         }
         pub fn clear(&mut self) {
             self.objects.clear();
+            self.path.clear();
+        }
+        /// Prepare for diffing against another tree while keeping the objects found so far.
+        pub fn clear_path(&mut self) {
+            self.path.clear();
         }
     }
 
-    impl<'a, H> Visit for AllNew<'a, H>
+    impl<'a, H, D> Visit for AllNew<'a, H, D>
     where
         H: InsertImmutable<ObjectId>,
+        D: Fn(&BStr) -> bool,
     {
-        fn pop_front_tracked_path_and_set_current(&mut self) {}
+        fn pop_front_tracked_path_and_set_current(&mut self) {
+            self.path.pop_front_tracked_and_set_current();
+        }
 
-        fn push_back_tracked_path_component(&mut self, _component: &BStr) {}
+        fn push_back_tracked_path_component(&mut self, component: &BStr) {
+            self.path.push_back_tracked(component);
+        }
 
-        fn push_path_component(&mut self, _component: &BStr) {}
+        fn push_path_component(&mut self, component: &BStr) {
+            self.path.push(component);
+        }
 
-        fn pop_path_component(&mut self) {}
+        fn pop_path_component(&mut self) {
+            self.path.pop();
+        }
 
         fn visit(&mut self, change: Change) -> Action {
             match change {
@@ -48,7 +68,9 @@ pub mod changes {
                     }
                     let inserted = self.all_seen.insert(oid);
                     if inserted {
-                        self.objects.push(oid);
+                        let path = self.path.as_bstr();
+                        self.objects
+                            .push((oid, crate::data::output::count::name_hash(path), (self.try_delta)(path)));
                     }
                 }
                 Change::Deletion { .. } => {}
@@ -66,43 +88,71 @@ pub mod traverse {
     };
     use git_traverse::tree::{visit::Action, Visit};
 
-    use crate::data::output::count::objects_impl::util::InsertImmutable;
+    use crate::data::output::count::objects_impl::util::{InsertImmutable, Path};
 
-    pub struct AllUnseen<'a, H> {
-        pub non_trees: Vec<ObjectId>,
+    pub struct AllUnseen<'a, H, D> {
+        /// All non-tree objects along with the name hash of their path and whether they may be delta-compressed.
+        pub non_trees: Vec<(ObjectId, u32, bool)>,
+        /// The name hash of the path of each tree we decided to enter, and whether it may be delta-compressed,
+        /// in the order in which the trees will be looked up.
+        pub trees: Vec<(u32, bool)>,
         all_seen: &'a H,
+        try_delta: &'a D,
+        path: Path,
     }
 
-    impl<'a, H> AllUnseen<'a, H>
+    impl<'a, H, D> AllUnseen<'a, H, D>
     where
         H: InsertImmutable<ObjectId>,
+        D: Fn(&BStr) -> bool,
     {
-        pub fn new(all_seen: &'a H) -> Self {
+        pub fn new(all_seen: &'a H, try_delta: &'a D) -> Self {
             AllUnseen {
                 non_trees: Default::default(),
+                trees: Default::default(),
                 all_seen,
+                try_delta,
+                path: Default::default(),
             }
         }
         pub fn clear(&mut self) {
             self.non_trees.clear();
+            self.trees.clear();
+            self.path.clear();
+        }
+
+        fn path_info(&self) -> (u32, bool) {
+            let path = self.path.as_bstr();
+            (crate::data::output::count::name_hash(path), (self.try_delta)(path))
         }
     }
 
-    impl<'a, H> Visit for AllUnseen<'a, H>
+    impl<'a, H, D> Visit for AllUnseen<'a, H, D>
     where
         H: InsertImmutable<ObjectId>,
+        D: Fn(&BStr) -> bool,
     {
-        fn pop_front_tracked_path_and_set_current(&mut self) {}
+        fn pop_front_tracked_path_and_set_current(&mut self) {
+            self.path.pop_front_tracked_and_set_current();
+        }
 
-        fn push_back_tracked_path_component(&mut self, _component: &BStr) {}
+        fn push_back_tracked_path_component(&mut self, component: &BStr) {
+            self.path.push_back_tracked(component);
+        }
 
-        fn push_path_component(&mut self, _component: &BStr) {}
+        fn push_path_component(&mut self, component: &BStr) {
+            self.path.push(component);
+        }
 
-        fn pop_path_component(&mut self) {}
+        fn pop_path_component(&mut self) {
+            self.path.pop();
+        }
 
         fn visit_tree(&mut self, entry: &EntryRef<'_>) -> Action {
             let inserted = self.all_seen.insert(entry.oid.to_owned());
             if inserted {
+                let info = self.path_info();
+                self.trees.push(info);
                 Action::Continue
             } else {
                 Action::Skip
@@ -116,7 +166,8 @@ pub mod traverse {
             }
             let inserted = self.all_seen.insert(entry.oid.to_owned());
             if inserted {
-                self.non_trees.push(entry.oid.to_owned());
+                let (name_hash, try_delta) = self.path_info();
+                self.non_trees.push((entry.oid.to_owned(), name_hash, try_delta));
             }
             Action::Continue
         }
@@ -43,6 +43,15 @@ pub enum ObjectExpansion {
     ///
     /// This mode is useful if all reachable objects should be added, as in cloning a repository.
     TreeContents,
+    /// The same as [`TreeContents`][ObjectExpansion::TreeContents], but the input is known to contain all ancestors of input commits,
+    /// as produced by a commit graph traversal. This allows to use reachability bitmaps of packs if available, so that all objects
+    /// reachable from an input commit are counted at once without traversing commits or trees. Input commits that were already
+    /// counted this way are skipped.
+    ///
+    /// Note that objects obtained from a bitmap are always marked for delta compression, as their path isn't known.
+    ///
+    /// This mode is useful if all objects reachable from a set of tips should be added, as in cloning a repository.
+    TreeContentsOfAncestry,
     /// If the input is a commit, obtain its ancestors and turn them into pack entries. Obtain the ancestor trees along with the commits
     /// tree and turn them into pack entries. Finally obtain the added/changed objects when comparing the ancestor trees with the
     /// current tree and turn them into entries as well.
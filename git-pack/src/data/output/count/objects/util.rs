@@ -1,3 +1,9 @@
+use std::collections::{HashMap, VecDeque};
+
+use git_object::bstr::{BStr, BString, ByteSlice, ByteVec};
+
+use crate::{find, index::EntryIndex};
+
 pub trait InsertImmutable<Item: Eq + std::hash::Hash> {
     fn insert(&self, item: Item) -> bool;
 }
@@ -22,3 +28,71 @@ mod trait_impls {
         }
     }
 }
+
+/// Keep track of the path of the entry currently visited during tree traversal or tree diffs.
+#[derive(Default)]
+pub struct Path {
+    deque: VecDeque<BString>,
+    current: BString,
+}
+
+impl Path {
+    pub fn clear(&mut self) {
+        self.deque.clear();
+        self.current.clear();
+    }
+
+    pub fn as_bstr(&self) -> &BStr {
+        self.current.as_bstr()
+    }
+
+    pub fn pop_front_tracked_and_set_current(&mut self) {
+        self.current = self.deque.pop_front().expect("every parent is set only once");
+    }
+
+    pub fn push_back_tracked(&mut self, component: &BStr) {
+        self.push(component);
+        self.deque.push_back(self.current.clone());
+    }
+
+    pub fn push(&mut self, component: &BStr) {
+        if !self.current.is_empty() {
+            self.current.push(b'/');
+        }
+        self.current.push_str(component);
+    }
+
+    pub fn pop(&mut self) {
+        if let Some(pos) = self.current.rfind_byte(b'/') {
+            self.current.resize(pos, 0);
+        } else {
+            self.current.clear();
+        }
+    }
+}
+
+/// The reachability bitmaps of packs, loaded on first use along with the index positions of each pack's objects in pack order.
+#[derive(Default)]
+pub struct Bitmaps {
+    by_pack_id: HashMap<u32, Option<(find::Bitmap, Vec<EntryIndex>)>>,
+}
+
+impl Bitmaps {
+    /// Return the bitmap of the pack with `pack_id` and the index positions of its objects in pack order, or `None` if
+    /// the pack has no bitmap.
+    pub fn get(&mut self, db: &impl crate::Find, pack_id: u32) -> Option<(&find::Bitmap, &[EntryIndex])> {
+        self.by_pack_id
+            .entry(pack_id)
+            .or_insert_with(|| {
+                db.bitmap(pack_id).map(|bitmap| {
+                    let index = &bitmap.index;
+                    let mut index_positions_in_pack_order: Vec<EntryIndex> = (0..index.num_objects()).collect();
+                    index_positions_in_pack_order
+                        .sort_by_key(|index_position| index.pack_offset_at_index(*index_position));
+                    (bitmap, index_positions_in_pack_order)
+                })
+            })
+            .as_ref()
+            .map(|(bitmap, index_positions)| (bitmap, index_positions.as_slice()))
+    }
+}
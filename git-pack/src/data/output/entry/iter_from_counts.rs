@@ -3,7 +3,7 @@ pub(crate) mod function {
 
     use git_features::{parallel, parallel::SequenceId, progress::Progress};
 
-    use super::{reduce, util, Error, Mode, Options, Outcome, ProgressId};
+    use super::{delta, reduce, util, Error, Mode, Options, Outcome, ProgressId};
     use crate::data::output;
 
     /// Given a known list of object `counts`, calculate entries ready to be put into a data pack.
@@ -92,7 +92,7 @@ pub(crate) mod function {
             progress.lock().show_throughput(start);
         }
         let counts_range_by_pack_id = match mode {
-            Mode::PackCopyAndBaseObjects => {
+            Mode::PackCopyAndBaseObjects | Mode::PackCopyAndDeltaCompression { .. } => {
                 let mut progress = progress.add_child_with_id("sorting", ProgressId::SortEntries.into());
                 progress.init(Some(counts.len()), git_features::progress::count("counts"));
                 let start = std::time::Instant::now();
@@ -128,8 +128,26 @@ pub(crate) mod function {
                 index
             }
         };
+        let plan = match mode {
+            Mode::PackCopyAndBaseObjects => None,
+            Mode::PackCopyAndDeltaCompression { window, depth } => Some(delta::plan(
+                &counts,
+                &counts_range_by_pack_id,
+                &db,
+                delta::Options {
+                    window,
+                    depth,
+                    allow_thin_pack,
+                    thread_limit,
+                    chunk_size,
+                    version,
+                },
+                progress.add_child_with_id("compressing deltas", ProgressId::DeltaCompression.into()),
+            )),
+        };
 
         let counts = Arc::new(counts);
+        let plan = Arc::new(plan);
         let progress = Arc::new(parking_lot::Mutex::new(progress));
         let chunks = util::ChunkRanges::new(chunk_size, counts.len());
 
@@ -149,15 +167,30 @@ pub(crate) mod function {
             },
             {
                 let counts = Arc::clone(&counts);
+                let plan = Arc::clone(&plan);
                 move |(chunk_id, chunk_range): (SequenceId, std::ops::Range<usize>), (buf, progress)| {
                     let mut out = Vec::new();
-                    let chunk = &counts[chunk_range];
                     let mut stats = Outcome::default();
                     let mut pack_offsets_to_id = None;
-                    progress.init(Some(chunk.len()), git_features::progress::count("objects"));
+                    let plan = (*plan).as_ref();
+                    progress.init(Some(chunk_range.len()), git_features::progress::count("objects"));
 
-                    for count in chunk.iter() {
-                        out.push(match count
+                    for pack_position in chunk_range {
+                        let count_index = plan.map_or(pack_position, |plan| plan.order[pack_position]);
+                        let count = &counts[count_index];
+                        if let Some((plan, delta)) =
+                            plan.and_then(|plan| plan.deltas[count_index].as_ref().map(|delta| (plan, delta)))
+                        {
+                            out.push(output::Entry::from_delta(
+                                count,
+                                plan.position[delta.base],
+                                &delta.data,
+                            )?);
+                            stats.delta_compressed_objects += 1;
+                            progress.inc();
+                            continue;
+                        }
+                        let mut entry = match count
                             .entry_pack_location
                             .as_ref()
                             .and_then(|l| db.entry_by_location(l).map(|pe| (l, pe)))
@@ -226,7 +259,11 @@ pub(crate) mod function {
                                     Ok(output::Entry::invalid())
                                 }
                             },
-                        }?);
+                        }?;
+                        if let (Some(plan), output::entry::Kind::DeltaRef { object_index }) = (plan, &mut entry.kind) {
+                            *object_index = plan.position[*object_index];
+                        }
+                        out.push(entry);
                         progress.inc();
                     }
                     Ok((chunk_id, out, stats))
@@ -271,6 +308,326 @@ mod util {
     }
 }
 
+mod delta {
+    use std::{cmp::Reverse, collections::VecDeque, ops::Range, sync::Arc};
+
+    use git_features::{parallel, progress::Progress};
+
+    use super::reduce;
+    use crate::data::{self, delta::Index, output};
+
+    /// Objects smaller than this aren't worth delta-compressing.
+    const MIN_OBJECT_SIZE: usize = 50;
+    /// The amount of sorted delta candidates that are processed as one unit of work, each with its own sliding window.
+    /// It's independent of the amount of threads to produce the same pack no matter how many threads are used.
+    const CANDIDATES_PER_SEGMENT: usize = 10_000;
+
+    /// Delta instructions to produce an object from its base.
+    pub struct Delta {
+        /// The index of the count serving as base.
+        pub base: usize,
+        /// The delta instructions, including their header.
+        pub data: Vec<u8>,
+    }
+
+    /// The result of the delta search.
+    pub struct Plan {
+        /// The delta to write instead of the object, for each count.
+        pub deltas: Vec<Option<Delta>>,
+        /// Indices into counts in the order in which they should be written, with each base being written before its deltas.
+        pub order: Vec<usize>,
+        /// The inverse of `order`, the position within the pack for each count.
+        pub position: Vec<usize>,
+    }
+
+    pub struct Options {
+        pub window: usize,
+        pub depth: usize,
+        pub allow_thin_pack: bool,
+        pub thread_limit: Option<usize>,
+        pub chunk_size: usize,
+        pub version: data::Version,
+    }
+
+    /// An object which may be delta-compressed and may serve as base for others.
+    struct Candidate {
+        /// The index into counts.
+        index: usize,
+        kind: git_object::Kind,
+        size: usize,
+        name_hash: u32,
+    }
+
+    /// An object in the sliding window.
+    struct Slot {
+        index: usize,
+        kind: git_object::Kind,
+        data: Vec<u8>,
+        /// The index to delta-compress objects against `data`, created when first needed.
+        delta_index: Option<Index>,
+        /// The length of the delta chain leading up to this object.
+        depth: usize,
+    }
+
+    /// Find deltas for all `counts`, sorted by pack location as described by `counts_range_by_pack_id`, which can't be copied
+    /// from a pack as delta already.
+    ///
+    /// Note that objects which can't be found are skipped silently, as the error is reported once the entry is produced.
+    pub fn plan<Find>(
+        counts: &[output::Count],
+        counts_range_by_pack_id: &[(u32, Range<usize>)],
+        db: &Find,
+        Options {
+            window,
+            depth,
+            allow_thin_pack,
+            thread_limit,
+            chunk_size,
+            version,
+        }: Options,
+        mut progress: impl Progress,
+    ) -> Plan
+    where
+        Find: crate::Find + Send + Clone,
+    {
+        let mut candidates = if window > 1 && depth > 0 {
+            parallel::in_parallel_if(
+                || counts.len() > 4_000,
+                counts.chunks(chunk_size).enumerate(),
+                thread_limit,
+                |_n| Vec::<u8>::new(),
+                {
+                    let db = db.clone();
+                    move |(chunk_id, chunk): (usize, &[output::Count]), buf| {
+                        (chunk_id * chunk_size..)
+                            .zip(chunk)
+                            .filter(|(_, count)| count.try_delta)
+                            .filter_map(|(index, count)| {
+                                candidate(
+                                    count,
+                                    index,
+                                    counts,
+                                    counts_range_by_pack_id,
+                                    &db,
+                                    allow_thin_pack,
+                                    version,
+                                    buf,
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                    }
+                },
+                reduce::Collect::default(),
+            )
+            .expect("infallible")
+        } else {
+            Vec::new()
+        };
+        candidates.sort_by_key(|c| (c.kind, c.name_hash, Reverse(c.size), c.index));
+
+        progress.init(Some(candidates.len()), git_features::progress::count("objects"));
+        let start = std::time::Instant::now();
+        let progress = Arc::new(parking_lot::Mutex::new(progress));
+        let num_segments = (candidates.len() + CANDIDATES_PER_SEGMENT - 1) / CANDIDATES_PER_SEGMENT;
+        let found = parallel::in_parallel_if(
+            || num_segments > 1,
+            candidates.chunks(CANDIDATES_PER_SEGMENT),
+            thread_limit,
+            |_n| Vec::<u8>::new(),
+            {
+                let progress = Arc::clone(&progress);
+                let db = db.clone();
+                move |segment: &[Candidate], buf| {
+                    let deltas = search(segment, counts, &db, window, depth, buf);
+                    progress.lock().inc_by(segment.len());
+                    deltas
+                }
+            },
+            reduce::Collect::default(),
+        )
+        .expect("infallible");
+        progress.lock().show_throughput(start);
+
+        let mut deltas: Vec<Option<Delta>> = std::iter::repeat_with(|| None).take(counts.len()).collect();
+        for (index, delta) in found {
+            deltas[index] = Some(delta);
+        }
+        let order = write_order(&deltas);
+        let mut position = vec![0; order.len()];
+        for (pack_position, index) in order.iter().enumerate() {
+            position[*index] = pack_position;
+        }
+        Plan {
+            deltas,
+            order,
+            position,
+        }
+    }
+
+    /// Return a candidate for delta compression if `count` is an object that won't be copied as delta from a pack.
+    #[allow(clippy::too_many_arguments)]
+    fn candidate<Find: crate::Find>(
+        count: &output::Count,
+        index: usize,
+        counts: &[output::Count],
+        counts_range_by_pack_id: &[(u32, Range<usize>)],
+        db: &Find,
+        allow_thin_pack: bool,
+        version: data::Version,
+        buf: &mut Vec<u8>,
+    ) -> Option<Candidate> {
+        let object_info = |buf: &mut Vec<u8>| {
+            db.try_find(count.id, buf)
+                .ok()
+                .flatten()
+                .map(|(obj, _location)| (obj.kind, obj.data.len()))
+        };
+        let (kind, size) = match count
+            .entry_pack_location
+            .as_ref()
+            .and_then(|l| db.entry_by_location(l).map(|pe| (l, pe)))
+        {
+            Some((location, entry)) if entry.version == version => {
+                let pack_offset_must_be_zero = 0;
+                let pack_entry =
+                    data::Entry::from_bytes(&entry.data, pack_offset_must_be_zero, count.id.as_slice().len());
+                match pack_entry.header {
+                    data::entry::Header::OfsDelta { base_distance } => {
+                        let base_offset = location
+                            .pack_offset
+                            .checked_sub(base_distance)
+                            .expect("pack-offset - distance is firmly within the pack");
+                        let pack_range = counts_range_by_pack_id[counts_range_by_pack_id
+                            .binary_search_by_key(&location.pack_id, |e| e.0)
+                            .expect("pack-id always present")]
+                        .1
+                        .clone();
+                        let base_is_in_pack = counts[pack_range]
+                            .binary_search_by(|e| {
+                                e.entry_pack_location
+                                    .as_ref()
+                                    .expect("packed")
+                                    .pack_offset
+                                    .cmp(&base_offset)
+                            })
+                            .is_ok();
+                        if base_is_in_pack || allow_thin_pack {
+                            return None;
+                        }
+                        object_info(buf)?
+                    }
+                    data::entry::Header::RefDelta { .. } => object_info(buf)?,
+                    header => (
+                        header.as_kind().expect("non-delta objects have a kind"),
+                        pack_entry.decompressed_size as usize,
+                    ),
+                }
+            }
+            _ => object_info(buf)?,
+        };
+        (size >= MIN_OBJECT_SIZE).then_some(Candidate {
+            index,
+            kind,
+            size,
+            name_hash: count.name_hash,
+        })
+    }
+
+    /// Slide a window over all `candidates`, trying to delta-compress each of them against the objects currently in the window.
+    fn search<Find: crate::Find>(
+        candidates: &[Candidate],
+        counts: &[output::Count],
+        db: &Find,
+        window: usize,
+        max_depth: usize,
+        buf: &mut Vec<u8>,
+    ) -> Vec<(usize, Delta)> {
+        let mut out = Vec::new();
+        let mut slots = VecDeque::<Slot>::with_capacity(window - 1);
+        for candidate in candidates {
+            let id = counts[candidate.index].id;
+            let data = match db.try_find(id, buf) {
+                Ok(Some((obj, _location))) => obj.data.to_vec(),
+                _ => continue,
+            };
+            let mut best: Option<(usize, Vec<u8>)> = None;
+            for (slot_index, slot) in slots.iter_mut().enumerate().rev() {
+                if slot.kind != candidate.kind {
+                    break;
+                }
+                if slot.depth >= max_depth || data.len() < slot.data.len() / 32 {
+                    continue;
+                }
+                let mut max_size =
+                    (data.len() / 2).saturating_sub(id.as_slice().len()) * (max_depth - slot.depth) / max_depth;
+                if let Some((_, delta)) = &best {
+                    max_size = max_size.min(delta.len() - 1);
+                }
+                if data.len().saturating_sub(slot.data.len()) >= max_size {
+                    continue;
+                }
+                let Slot {
+                    data: base,
+                    delta_index,
+                    ..
+                } = slot;
+                let delta_index = delta_index.get_or_insert_with(|| Index::new(base));
+                if let Some(delta) = delta_index.create_delta(base, &data, max_size) {
+                    best = Some((slot_index, delta));
+                }
+            }
+
+            let depth = match best {
+                Some((slot_index, delta)) => {
+                    let base = &slots[slot_index];
+                    out.push((
+                        candidate.index,
+                        Delta {
+                            base: base.index,
+                            data: delta,
+                        },
+                    ));
+                    base.depth + 1
+                }
+                None => 0,
+            };
+            if slots.len() == window - 1 {
+                slots.pop_front();
+            }
+            slots.push_back(Slot {
+                index: candidate.index,
+                kind: candidate.kind,
+                data,
+                delta_index: None,
+                depth,
+            });
+        }
+        out
+    }
+
+    /// Keep the order of objects, but move the bases of new deltas forward so they are written before the delta.
+    /// Deltas copied from packs already refer to objects that are written before them.
+    fn write_order(deltas: &[Option<Delta>]) -> Vec<usize> {
+        let mut written = vec![false; deltas.len()];
+        let mut order = Vec::with_capacity(deltas.len());
+        let mut chain = Vec::new();
+        for mut index in 0..deltas.len() {
+            while !written[index] {
+                chain.push(index);
+                match &deltas[index] {
+                    Some(delta) if !written[delta.base] => index = delta.base,
+                    _ => break,
+                }
+            }
+            while let Some(index) = chain.pop() {
+                written[index] = true;
+                order.push(index);
+            }
+        }
+        order
+    }
+}
+
 mod reduce {
     use std::marker::PhantomData;
 
@@ -293,6 +650,33 @@ mod reduce {
         }
     }
 
+    /// Collect the items produced by each thread, in no particular order.
+    pub struct Collect<T> {
+        items: Vec<T>,
+    }
+
+    impl<T> Default for Collect<T> {
+        fn default() -> Self {
+            Collect { items: Vec::new() }
+        }
+    }
+
+    impl<T> parallel::Reduce for Collect<T> {
+        type Input = Vec<T>;
+        type FeedProduce = ();
+        type Output = Vec<T>;
+        type Error = std::convert::Infallible;
+
+        fn feed(&mut self, items: Self::Input) -> Result<Self::FeedProduce, Self::Error> {
+            self.items.extend(items);
+            Ok(())
+        }
+
+        fn finalize(self) -> Result<Self::Output, Self::Error> {
+            Ok(self.items)
+        }
+    }
+
     impl<Error> parallel::Reduce for Statistics<Error> {
         type Input = Result<(SequenceId, Vec<output::Entry>, Outcome), Error>;
         type FeedProduce = (SequenceId, Vec<output::Entry>);
@@ -328,6 +712,9 @@ mod types {
         pub objects_copied_from_pack: usize,
         /// The amount of objects that ref to their base as ref-delta, an indication for a thin back being created.
         pub ref_delta_objects: usize,
+        /// The amount of objects that were delta-compressed against another object in the pack, which is only done in
+        /// [`Mode::PackCopyAndDeltaCompression`].
+        pub delta_compressed_objects: usize,
     }
 
     impl Outcome {
@@ -338,12 +725,14 @@ mod types {
                 missing_objects,
                 objects_copied_from_pack,
                 ref_delta_objects,
+                delta_compressed_objects,
             }: Self,
         ) {
             self.decoded_and_recompressed_objects += decoded_objects;
             self.missing_objects += missing_objects;
             self.objects_copied_from_pack += objects_copied_from_pack;
             self.ref_delta_objects += ref_delta_objects;
+            self.delta_compressed_objects += delta_compressed_objects;
         }
     }
 
@@ -356,6 +745,20 @@ mod types {
         /// from existing pack compression and spending the smallest possible time on compressing unpacked objects at
         /// the cost of bandwidth.
         PackCopyAndBaseObjects,
+        /// Copy base objects and deltas from packs like [`PackCopyAndBaseObjects`][Mode::PackCopyAndBaseObjects], but try to
+        /// delta-compress all other objects against similar objects in the pack, which are found by sorting them by kind, by the
+        /// hash of their path and by size, and trying the objects in a sliding window as base.
+        ///
+        /// This takes more time, but can produce much smaller packs, especially if many objects are loose.
+        /// Objects which must not be delta-compressed according to [`Count::try_delta`][crate::data::output::Count::try_delta]
+        /// are left as they are.
+        PackCopyAndDeltaCompression {
+            /// The size of the sliding window, with `window - 1` objects being tried as base for each object, similar to `pack.window`.
+            /// Values smaller than 2 disable delta compression.
+            window: usize,
+            /// The maximum length of the chains of newly created deltas, similar to `pack.depth`.
+            depth: usize,
+        },
     }
 
     /// Configuration options for the pack generation functions provided in [`iter_from_counts()`][crate::data::output::entry::iter_from_counts()].
@@ -414,6 +817,8 @@ mod types {
         ResolveCounts,
         /// Layout pack entries for placement into a pack (by pack-id and by offset).
         SortEntries,
+        /// The amount of objects which were tried to be delta-compressed against other objects.
+        DeltaCompression,
     }
 
     impl From<ProgressId> for git_features::progress::Id {
@@ -421,6 +826,7 @@ mod types {
             match v {
                 ProgressId::ResolveCounts => *b"ECRC",
                 ProgressId::SortEntries => *b"ECSE",
+                ProgressId::DeltaCompression => *b"ECDC",
             }
         }
     }
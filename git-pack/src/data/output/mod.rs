@@ -14,6 +14,12 @@ pub struct Count {
     pub id: ObjectId,
     /// A way to locate a pack entry in the object database, only available if the object is in a pack.
     pub entry_pack_location: count::PackLocation,
+    /// A hash of the path at which the object was first encountered, as computed by [`count::name_hash()`], or `0` if
+    /// no path is known. It's used to place objects likely to be similar next to each other during delta compression.
+    pub name_hash: u32,
+    /// If `false`, the object should neither be delta-compressed nor serve as base for other deltas,
+    /// typically because of the `delta` attribute being unset for its path.
+    pub try_delta: bool,
 }
 
 /// An entry to be written to a file.
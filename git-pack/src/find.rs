@@ -61,3 +61,12 @@ pub struct Entry {
     /// The version of the pack file containing `data`
     pub version: crate::data::Version,
 }
+
+/// A reachability bitmap along with the index of the pack it refers to, as returned by [`Find::bitmap()`][crate::Find::bitmap()].
+#[derive(Clone)]
+pub struct Bitmap {
+    /// The reachability bitmap of the pack.
+    pub bitmap: std::sync::Arc<crate::bitmap::File>,
+    /// The index of the pack, to translate between object ids and the positions used in `bitmap`.
+    pub index: std::sync::Arc<crate::index::File>,
+}
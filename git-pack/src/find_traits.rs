@@ -64,6 +64,14 @@ pub trait Find {
     /// Custom implementations might be interested in providing their own meta-data with `object`,
     /// which currently isn't possible as the `Locate` trait requires GATs to work like that.
     fn entry_by_location(&self, location: &data::entry::Location) -> Option<find::Entry>;
+
+    /// Return the reachability bitmap of the pack with `pack_id` along with the pack's index, or `None` if there is no bitmap.
+    ///
+    /// The default implementation returns `None`, which is suitable for implementations that don't support bitmaps.
+    fn bitmap(&self, pack_id: u32) -> Option<find::Bitmap> {
+        let _ = pack_id;
+        None
+    }
 }
 
 mod ext {
@@ -195,6 +203,10 @@ mod find_impls {
         fn entry_by_location(&self, location: &data::entry::Location) -> Option<find::Entry> {
             (*self).entry_by_location(location)
         }
+
+        fn bitmap(&self, pack_id: u32) -> Option<find::Bitmap> {
+            (*self).bitmap(pack_id)
+        }
     }
 
     impl<T> super::Find for std::sync::Arc<T>
@@ -227,6 +239,10 @@ mod find_impls {
         fn entry_by_location(&self, object: &data::entry::Location) -> Option<find::Entry> {
             self.deref().entry_by_location(object)
         }
+
+        fn bitmap(&self, pack_id: u32) -> Option<find::Bitmap> {
+            self.deref().bitmap(pack_id)
+        }
     }
 
     impl<T> super::Find for Rc<T>
@@ -259,6 +275,10 @@ mod find_impls {
         fn entry_by_location(&self, location: &data::entry::Location) -> Option<find::Entry> {
             self.deref().entry_by_location(location)
         }
+
+        fn bitmap(&self, pack_id: u32) -> Option<find::Bitmap> {
+            self.deref().bitmap(pack_id)
+        }
     }
 
     impl<T> super::Find for Box<T>
@@ -291,5 +311,9 @@ mod find_impls {
         fn entry_by_location(&self, location: &data::entry::Location) -> Option<find::Entry> {
             self.deref().entry_by_location(location)
         }
+
+        fn bitmap(&self, pack_id: u32) -> Option<find::Bitmap> {
+            self.deref().bitmap(pack_id)
+        }
     }
 }
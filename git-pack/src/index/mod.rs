@@ -148,7 +148,7 @@ pub use access::Entry;
 
 ///
 pub mod traverse;
-mod util;
+pub(crate) mod util;
 ///
 pub mod verify;
 ///
@@ -30,6 +30,12 @@ pub enum Error<E: std::error::Error + Send + Sync + 'static> {
         offset: u64,
         kind: git_object::Kind,
     },
+    #[error("The {kind} object at offset {offset} is part of a SHA1 collision attack")]
+    PackObjectCollisionAttack {
+        offset: u64,
+        kind: git_object::Kind,
+        source: git_features::hash::Error,
+    },
     #[error(
         "The CRC32 of {kind} object at offset {offset} didn't match the checksum in the index file: expected {expected}, got {actual}"
     )]
@@ -220,7 +220,11 @@ where
         hasher.update(&git_object::encode::loose_header(object_kind, decompressed.len()));
         hasher.update(decompressed);
 
-        let actual_oid = git_hash::ObjectId::from(hasher.digest());
+        let actual_oid = hasher.try_digest().map_err(|source| Error::PackObjectCollisionAttack {
+            offset: index_entry.pack_offset,
+            kind: object_kind,
+            source,
+        })?;
         if actual_oid != index_entry.oid {
             return Err(Error::PackObjectMismatch {
                 actual: actual_oid,
@@ -8,13 +8,17 @@ use git_features::{
     progress::{self, Progress},
 };
 
-use crate::index::{util::Count, V2_SIGNATURE};
+use crate::index::{util::Count, write::Entry, V2_SIGNATURE};
+
+const REVERSE_INDEX_SIGNATURE: &[u8] = b"RIDX";
+const REVERSE_INDEX_VERSION: u32 = 1;
 
 pub(crate) fn write_to(
     out: impl io::Write,
-    entries_sorted_by_oid: Vec<crate::cache::delta::Item<crate::index::write::TreeEntry>>,
+    entries_sorted_by_oid: &[Entry],
     pack_hash: &git_hash::ObjectId,
     kind: crate::index::Version,
+    object_hash: git_hash::Kind,
     mut progress: impl Progress,
 ) -> io::Result<git_hash::ObjectId> {
     use io::Write;
@@ -27,7 +31,7 @@ pub(crate) fn write_to(
     // Write header
     let mut out = Count::new(std::io::BufWriter::with_capacity(
         8 * 4096,
-        hash::Write::new(out, kind.hash()),
+        hash::Write::new(out, object_hash),
     ));
     out.write_all(V2_SIGNATURE)?;
     out.write_all(&(kind as u32).to_be_bytes())?;
@@ -35,7 +39,7 @@ pub(crate) fn write_to(
     progress.init(Some(4), progress::steps());
     let start = std::time::Instant::now();
     let _info = progress.add_child_with_id("writing fan-out table", git_features::progress::UNKNOWN);
-    let fan_out = fanout(entries_sorted_by_oid.iter().map(|e| e.data.id.first_byte()));
+    let fan_out = fanout(entries_sorted_by_oid.iter().map(|e| e.id.first_byte()));
 
     for value in fan_out.iter() {
         out.write_all(&value.to_be_bytes())?;
@@ -43,30 +47,30 @@ pub(crate) fn write_to(
 
     progress.inc();
     let _info = progress.add_child_with_id("writing ids", git_features::progress::UNKNOWN);
-    for entry in &entries_sorted_by_oid {
-        out.write_all(entry.data.id.as_slice())?;
+    for entry in entries_sorted_by_oid {
+        out.write_all(entry.id.as_slice())?;
     }
 
     progress.inc();
     let _info = progress.add_child_with_id("writing crc32", git_features::progress::UNKNOWN);
-    for entry in &entries_sorted_by_oid {
-        out.write_all(&entry.data.crc32.to_be_bytes())?;
+    for entry in entries_sorted_by_oid {
+        out.write_all(&entry.crc32.to_be_bytes())?;
     }
 
     progress.inc();
     let _info = progress.add_child_with_id("writing offsets", git_features::progress::UNKNOWN);
     {
         let mut offsets64 = Vec::<u64>::new();
-        for entry in &entries_sorted_by_oid {
-            let offset: u32 = if entry.offset > LARGE_OFFSET_THRESHOLD {
+        for entry in entries_sorted_by_oid {
+            let offset: u32 = if entry.pack_offset > LARGE_OFFSET_THRESHOLD {
                 assert!(
                     offsets64.len() < LARGE_OFFSET_THRESHOLD as usize,
                     "Encoding breakdown - way too many 64bit offsets"
                 );
-                offsets64.push(entry.offset);
+                offsets64.push(entry.pack_offset);
                 ((offsets64.len() - 1) as u32) | HIGH_BIT
             } else {
-                entry.offset as u32
+                entry.pack_offset as u32
             };
             out.write_all(&offset.to_be_bytes())?;
         }
@@ -79,14 +83,14 @@ pub(crate) fn write_to(
 
     let bytes_written_without_trailer = out.bytes;
     let mut out = out.inner.into_inner()?;
-    let index_hash: git_hash::ObjectId = out.hash.digest().into();
+    let index_hash = out.hash.digest();
     out.inner.write_all(index_hash.as_slice())?;
     out.inner.flush()?;
 
     progress.inc();
     progress.show_throughput_with(
         start,
-        (bytes_written_without_trailer + 20) as usize,
+        (bytes_written_without_trailer + object_hash.len_in_bytes() as u64) as usize,
         progress::bytes().expect("unit always set"),
         progress::MessageLevel::Success,
     );
@@ -94,6 +98,39 @@ pub(crate) fn write_to(
     Ok(index_hash)
 }
 
+/// Write a reverse index, mapping the position of each object in the pack data file to its position in the
+/// index file, whose entries are `entries_sorted_by_oid`.
+pub(crate) fn write_reverse_to(
+    out: impl io::Write,
+    entries_sorted_by_oid: &[Entry],
+    pack_hash: &git_hash::ObjectId,
+    object_hash: git_hash::Kind,
+) -> io::Result<git_hash::ObjectId> {
+    use io::Write;
+    assert!(
+        entries_sorted_by_oid.len() <= u32::MAX as usize,
+        "a pack cannot have more than u32::MAX objects"
+    );
+
+    let mut out = std::io::BufWriter::with_capacity(8 * 4096, hash::Write::new(out, object_hash));
+    out.write_all(REVERSE_INDEX_SIGNATURE)?;
+    out.write_all(&REVERSE_INDEX_VERSION.to_be_bytes())?;
+    out.write_all(&(object_hash as u32).to_be_bytes())?;
+
+    let mut index_positions_in_pack_order: Vec<u32> = (0..entries_sorted_by_oid.len() as u32).collect();
+    index_positions_in_pack_order.sort_by_key(|idx| entries_sorted_by_oid[*idx as usize].pack_offset);
+    for idx in index_positions_in_pack_order {
+        out.write_all(&idx.to_be_bytes())?;
+    }
+    out.write_all(pack_hash.as_slice())?;
+
+    let mut out = out.into_inner()?;
+    let reverse_index_hash = out.hash.digest();
+    out.inner.write_all(reverse_index_hash.as_slice())?;
+    out.inner.flush()?;
+    Ok(reverse_index_hash)
+}
+
 pub(crate) fn fanout(iter: impl ExactSizeIterator<Item = u8>) -> [u32; 256] {
     let mut fan_out = [0u32; 256];
     let entries_len = iter.len() as u32;
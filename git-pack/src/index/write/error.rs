@@ -18,6 +18,11 @@ pub enum Error {
     IteratorInvariantTooManyObjects(usize),
     #[error("{pack_offset} is not a valid offset for pack offset {distance}")]
     IteratorInvariantBaseOffset { pack_offset: u64, distance: u64 },
+    #[error("The object at pack offset {pack_offset} is part of a SHA1 collision attack")]
+    CollisionAttack {
+        pack_offset: u64,
+        source: git_features::hash::Error,
+    },
     #[error(transparent)]
     Tree(#[from] crate::cache::delta::Error),
     #[error(transparent)]
@@ -1,4 +1,8 @@
-use std::{convert::TryInto, io, sync::atomic::AtomicBool};
+use std::{
+    convert::{TryFrom, TryInto},
+    io,
+    sync::atomic::AtomicBool,
+};
 
 pub use error::Error;
 use git_features::progress::{self, Progress};
@@ -13,6 +17,18 @@ pub(crate) struct TreeEntry {
     pub crc32: u32,
 }
 
+/// Information about a single entry in a pack data file, as needed to write an index for it.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    /// The ID of the object stored in the pack entry.
+    pub id: git_hash::ObjectId,
+    /// The offset to the entry's header in the pack data file.
+    pub pack_offset: crate::data::Offset,
+    /// The CRC32 hash over all bytes of the pack data entry, i.e. its header and its compressed data.
+    pub crc32: u32,
+}
+
 /// Information gathered while executing [`write_data_iter_to_stream()`][crate::index::File::write_data_iter_to_stream]
 #[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
@@ -186,39 +202,55 @@ impl crate::index::File {
 
         let resolver = make_resolver()?;
         let sorted_pack_offsets_by_oid = {
-            let traverse::Outcome { roots, children } = tree.traverse(
-                resolver,
-                pack_entries_end,
-                || (),
-                |data,
-                 _progress,
-                 traverse::Context {
-                     entry,
-                     decompressed: bytes,
-                     ..
-                 }| {
-                    modify_base(data, entry, bytes, version.hash());
-                    Ok::<_, Error>(())
-                },
-                traverse::Options {
-                    object_progress: root_progress.add_child_with_id("Resolving", ProgressId::ResolveObjects.into()),
-                    size_progress: root_progress.add_child_with_id("Decoding", ProgressId::DecodedBytes.into()),
-                    thread_limit,
-                    should_interrupt,
-                    object_hash,
-                },
-            )?;
+            let traverse::Outcome { roots, children } = tree
+                .traverse(
+                    resolver,
+                    pack_entries_end,
+                    || (),
+                    |data,
+                     _progress,
+                     traverse::Context {
+                         entry,
+                         decompressed: bytes,
+                         ..
+                     }| {
+                        modify_base(data, entry, bytes, object_hash)?;
+                        Ok::<_, Error>(())
+                    },
+                    traverse::Options {
+                        object_progress: root_progress
+                            .add_child_with_id("Resolving", ProgressId::ResolveObjects.into()),
+                        size_progress: root_progress.add_child_with_id("Decoding", ProgressId::DecodedBytes.into()),
+                        thread_limit,
+                        should_interrupt,
+                        object_hash,
+                    },
+                )
+                .map_err(|err| match err {
+                    traverse::Error::Inspect(err) => match err.downcast::<Error>() {
+                        Ok(err) => *err,
+                        Err(err) => traverse::Error::Inspect(err).into(),
+                    },
+                    err => err.into(),
+                })?;
             root_progress.inc();
 
-            let mut items = roots;
-            items.extend(children);
+            let mut entries: Vec<_> = roots
+                .into_iter()
+                .chain(children)
+                .map(|item| Entry {
+                    id: item.data.id,
+                    pack_offset: item.offset,
+                    crc32: item.data.crc32,
+                })
+                .collect();
             {
                 let _progress = root_progress.add_child_with_id("sorting by id", git_features::progress::UNKNOWN);
-                items.sort_by_key(|e| e.data.id);
+                entries.sort_by_key(|e| e.id);
             }
 
             root_progress.inc();
-            items
+            entries
         };
 
         let pack_hash = match last_seen_trailer {
@@ -227,15 +259,16 @@ impl crate::index::File {
                 let header = crate::data::header::encode(pack_version, 0);
                 let mut hasher = git_features::hash::hasher(object_hash);
                 hasher.update(&header);
-                git_hash::ObjectId::from(hasher.digest())
+                hasher.digest()
             }
             None => return Err(Error::IteratorInvariantTrailer),
         };
         let index_hash = encode::write_to(
             out,
-            sorted_pack_offsets_by_oid,
+            &sorted_pack_offsets_by_oid,
             &pack_hash,
             version,
+            object_hash,
             root_progress.add_child_with_id("writing index file", ProgressId::IndexBytesWritten.into()),
         )?;
         root_progress.show_throughput_with(
@@ -251,17 +284,80 @@ impl crate::index::File {
             num_objects,
         })
     }
+
+    /// Write a pack index of `version` for `entries` of a pack data file whose trailing hash is `pack_hash` into the `out` stream,
+    /// using `object_hash` for the index checksum.
+    ///
+    /// This is useful if the pack data file was just written, for instance by [`FromEntriesIter`][crate::data::output::bytes::FromEntriesIter],
+    /// as all information is known already and no pack entry has to be decoded.
+    /// `entries` will be sorted by their object id, making them suitable for [`write_reverse_index_to_stream()`][Self::write_reverse_index_to_stream()]
+    /// in a subsequent call.
+    pub fn write_entries_to_stream(
+        version: crate::index::Version,
+        entries: &mut [Entry],
+        pack_hash: &git_hash::ObjectId,
+        out: impl io::Write,
+        object_hash: git_hash::Kind,
+        progress: impl Progress,
+    ) -> Result<Outcome, Error> {
+        if version != crate::index::Version::default() {
+            return Err(Error::Unsupported(version));
+        }
+        let num_objects: u32 = entries
+            .len()
+            .try_into()
+            .map_err(|_| Error::IteratorInvariantTooManyObjects(entries.len()))?;
+        entries.sort_by_key(|e| e.id);
+        let index_hash = encode::write_to(out, entries, pack_hash, version, object_hash, progress)?;
+        Ok(Outcome {
+            index_version: version,
+            index_hash,
+            data_hash: *pack_hash,
+            num_objects,
+        })
+    }
+
+    /// Write a reverse index for `entries` of a pack data file whose trailing hash is `pack_hash` into the `out` stream,
+    /// using `object_hash` for the reverse index checksum, and return the checksum.
+    ///
+    /// A reverse index is stored next to the pack index in a file with the `.rev` extension and maps positions of objects in the
+    /// pack data file to their positions in the pack index. `entries` will be sorted by their object id to learn their index position.
+    pub fn write_reverse_index_to_stream(
+        entries: &mut [Entry],
+        pack_hash: &git_hash::ObjectId,
+        out: impl io::Write,
+        object_hash: git_hash::Kind,
+    ) -> Result<git_hash::ObjectId, Error> {
+        if u32::try_from(entries.len()).is_err() {
+            return Err(Error::IteratorInvariantTooManyObjects(entries.len()));
+        }
+        entries.sort_by_key(|e| e.id);
+        Ok(encode::write_reverse_to(out, entries, pack_hash, object_hash)?)
+    }
 }
 
-fn modify_base(entry: &mut TreeEntry, pack_entry: &crate::data::Entry, decompressed: &[u8], hash: git_hash::Kind) {
-    fn compute_hash(kind: git_object::Kind, bytes: &[u8], object_hash: git_hash::Kind) -> git_hash::ObjectId {
+fn modify_base(
+    entry: &mut TreeEntry,
+    pack_entry: &crate::data::Entry,
+    decompressed: &[u8],
+    hash: git_hash::Kind,
+) -> Result<(), Error> {
+    fn compute_hash(
+        kind: git_object::Kind,
+        bytes: &[u8],
+        object_hash: git_hash::Kind,
+    ) -> Result<git_hash::ObjectId, git_features::hash::Error> {
         let mut hasher = git_features::hash::hasher(object_hash);
         hasher.update(&git_object::encode::loose_header(kind, bytes.len()));
         hasher.update(bytes);
-        git_hash::ObjectId::from(hasher.digest())
+        hasher.try_digest()
     }
 
     let object_kind = pack_entry.header.as_kind().expect("base object as source of iteration");
-    let id = compute_hash(object_kind, decompressed, hash);
+    let id = compute_hash(object_kind, decompressed, hash).map_err(|source| Error::CollisionAttack {
+        pack_offset: pack_entry.pack_offset(),
+        source,
+    })?;
     entry.id = id;
+    Ok(())
 }
@@ -18,6 +18,8 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 #![deny(missing_docs, rust_2018_idioms, unsafe_code)]
 
+///
+pub mod bitmap;
 ///
 pub mod bundle;
 /// A bundle of pack data and the corresponding pack index
@@ -43,6 +45,8 @@ pub use find_traits::{Find, FindExt};
 ///
 pub mod index;
 ///
+pub mod mtimes;
+///
 pub mod multi_index;
 
 ///
@@ -0,0 +1,162 @@
+use std::{
+    convert::{TryFrom, TryInto},
+    io,
+    path::{Path, PathBuf},
+};
+
+use memmap2::Mmap;
+
+use crate::index::EntryIndex;
+
+/// A file storing the modification time of each object in a _cruft pack_, typically stored next to its pack with the `.mtimes`
+/// extension.
+///
+/// Cruft packs contain unreachable objects, and their modification times are needed to learn when these objects expire.
+pub struct File {
+    data: Mmap,
+    path: PathBuf,
+    object_hash: git_hash::Kind,
+    num_objects: u32,
+}
+
+mod error {
+    /// The error returned by [File::at()][super::File::at()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("Could not open mtimes file at '{path}'")]
+        Io {
+            source: std::io::Error,
+            path: std::path::PathBuf,
+        },
+        #[error("{message}")]
+        Corrupt { message: &'static str },
+        #[error("Unsupported mtimes version: {version}")]
+        UnsupportedVersion { version: u32 },
+        #[error("The mtimes file uses hash function {actual}, but {expected} was expected")]
+        ObjectHashMismatch { actual: u32, expected: git_hash::Kind },
+    }
+}
+pub use error::Error;
+
+const SIGNATURE: &[u8] = b"MTME";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 4 /*signature*/ + 4 /*version*/ + 4 /*hash function id*/;
+
+/// Initialization
+impl File {
+    /// Open the mtimes file at the given `path`, expecting hashes of kind `object_hash`.
+    pub fn at(path: impl AsRef<Path>, object_hash: git_hash::Kind) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let data = crate::mmap::read_only(path).map_err(|source| Error::Io {
+            source,
+            path: path.to_owned(),
+        })?;
+        // The trailer consists of the pack checksum and the checksum of the file itself.
+        let trailer_len = 2 * object_hash.len_in_bytes();
+        let table_len = data.len().checked_sub(HEADER_LEN + trailer_len).ok_or(Error::Corrupt {
+            message: "mtimes file is truncated and too short",
+        })?;
+        if table_len % 4 != 0 {
+            return Err(Error::Corrupt {
+                message: "mtimes table doesn't contain one 32 bit value per object",
+            });
+        }
+        if &data[..4] != SIGNATURE {
+            return Err(Error::Corrupt {
+                message: "Invalid signature",
+            });
+        }
+        let version = crate::read_u32(&data[4..8]);
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion { version });
+        }
+        let hash_function = crate::read_u32(&data[8..12]);
+        if hash_function != object_hash as u32 {
+            return Err(Error::ObjectHashMismatch {
+                actual: hash_function,
+                expected: object_hash,
+            });
+        }
+        Ok(File {
+            num_objects: (table_len / 4).try_into().map_err(|_| Error::Corrupt {
+                message: "mtimes file contains more than u32::MAX objects",
+            })?,
+            data,
+            path: path.to_owned(),
+            object_hash,
+        })
+    }
+}
+
+/// Access
+impl File {
+    /// The path of the opened mtimes file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// The kind of hash we assume.
+    pub fn object_hash(&self) -> git_hash::Kind {
+        self.object_hash
+    }
+    /// The amount of objects for which a modification time is stored, which is the amount of objects in the pack.
+    pub fn num_objects(&self) -> u32 {
+        self.num_objects
+    }
+    /// The checksum of the pack whose objects the modification times belong to.
+    pub fn pack_checksum(&self) -> &git_hash::oid {
+        let start = HEADER_LEN + self.num_objects as usize * 4;
+        git_hash::oid::from_bytes_unchecked(&self.data[start..][..self.object_hash.len_in_bytes()])
+    }
+    /// The checksum over all bytes of the file, stored in its trailer.
+    pub fn checksum(&self) -> git_hash::ObjectId {
+        git_hash::ObjectId::from(&self.data[self.data.len() - self.object_hash.len_in_bytes()..])
+    }
+    /// Return the modification time in seconds since unix epoch of the object at `index_position` in the pack index, or `None`
+    /// if it is out of bounds.
+    pub fn mtime_at_index(&self, index_position: EntryIndex) -> Option<u32> {
+        (index_position < self.num_objects).then(|| {
+            let start = HEADER_LEN + index_position as usize * 4;
+            crate::read_u32(&self.data[start..][..4])
+        })
+    }
+}
+
+/// Writing
+impl File {
+    /// Write the modification time of each object in `entries` as obtained by `mtime(id)` into the `out` stream for the pack
+    /// whose trailing hash is `pack_hash`, using `object_hash` for the checksum of the file, and return that checksum.
+    ///
+    /// `entries` are all objects of the pack, and will be sorted by their object id to learn their index position.
+    pub fn write_to_stream(
+        entries: &mut [crate::index::write::Entry],
+        mut mtime: impl FnMut(&git_hash::oid) -> u32,
+        pack_hash: &git_hash::oid,
+        out: impl io::Write,
+        object_hash: git_hash::Kind,
+    ) -> io::Result<git_hash::ObjectId> {
+        use io::Write;
+        if u32::try_from(entries.len()).is_err() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a pack cannot have more than u32::MAX objects",
+            ));
+        }
+        entries.sort_by_key(|e| e.id);
+
+        let mut out = io::BufWriter::with_capacity(8 * 4096, git_features::hash::Write::new(out, object_hash));
+        out.write_all(SIGNATURE)?;
+        out.write_all(&VERSION.to_be_bytes())?;
+        out.write_all(&(object_hash as u32).to_be_bytes())?;
+        for entry in entries.iter() {
+            out.write_all(&mtime(&entry.id).to_be_bytes())?;
+        }
+        out.write_all(pack_hash.as_bytes())?;
+
+        let mut out = out.into_inner()?;
+        let checksum = out.hash.digest();
+        out.inner.write_all(checksum.as_slice())?;
+        out.inner.flush()?;
+        Ok(checksum)
+    }
+}
@@ -301,6 +301,9 @@ impl File {
                                 offset,
                                 kind,
                             },
+                            PackObjectCollisionAttack { offset, kind, source } => {
+                                PackObjectCollisionAttack { offset, kind, source }
+                            }
                             Crc32Mismatch {
                                 expected,
                                 actual,
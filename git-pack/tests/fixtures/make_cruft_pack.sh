@@ -0,0 +1,15 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+git checkout -q -b main
+
+echo content > file
+git add file
+git commit -qm "initial"
+
+for n in $(seq 5); do
+  echo "unreachable $n" | git hash-object -w --stdin >/dev/null
+done
+
+git repack --cruft -d
@@ -0,0 +1,177 @@
+use std::{
+    convert::Infallible,
+    path::{Path, PathBuf},
+    sync::atomic::AtomicBool,
+};
+
+use git_features::progress;
+use git_odb::pack::FindExt;
+use git_pack::{bitmap, data::output::count};
+use git_traverse::commit;
+
+use crate::pack::hex_to_id;
+
+fn repo_dir() -> PathBuf {
+    crate::scripted_fixture_read_only("make_pack_gen_repo_bitmap.sh").expect("test fixture exists")
+}
+
+fn bitmap_path(repo_dir: &Path) -> PathBuf {
+    std::fs::read_dir(repo_dir.join(".git/objects/pack"))
+        .expect("pack dir exists")
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .find(|p| p.extension().and_then(|e| e.to_str()) == Some("bitmap"))
+        .expect("git wrote a bitmap")
+}
+
+fn db(repo_dir: &Path) -> crate::Result<git_odb::Handle> {
+    Ok(git_odb::at(repo_dir.join(".git/objects"))?)
+}
+
+/// Return the ids of all objects reachable from `tip`, as counted by traversing commits and trees.
+fn reachable_ids(db: &git_odb::Handle, tip: git_hash::ObjectId) -> crate::Result<Vec<git_hash::ObjectId>> {
+    let commits = commit::Ancestors::new(Some(tip), commit::ancestors::State::default(), |oid, buf| {
+        db.find_commit_iter(oid, buf).map(|t| t.0)
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+    let (counts, _) = count::objects_unthreaded(
+        db,
+        commits.into_iter().map(Ok::<_, Infallible>),
+        progress::Discard,
+        &AtomicBool::new(false),
+        |_path| true,
+        count::objects::ObjectExpansion::TreeContents,
+    )?;
+    let mut ids: Vec<_> = counts.into_iter().map(|count| count.id).collect();
+    ids.sort();
+    Ok(ids)
+}
+
+fn ids_of(bitmap: &bitmap::Bitmap, index: &git_pack::index::File) -> Vec<git_hash::ObjectId> {
+    let mut index_positions_in_pack_order: Vec<_> = (0..index.num_objects()).collect();
+    index_positions_in_pack_order.sort_by_key(|index_position| index.pack_offset_at_index(*index_position));
+    let mut ids: Vec<_> = bitmap
+        .iter()
+        .map(|pack_position| {
+            index
+                .oid_at_index(index_positions_in_pack_order[pack_position])
+                .to_owned()
+        })
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn read_bitmap_written_by_git() -> crate::Result {
+    let repo_dir = repo_dir();
+    let path = bitmap_path(&repo_dir);
+    let index = git_pack::index::File::at(path.with_extension("idx"), git_hash::Kind::Sha1)?;
+    let file = bitmap::File::at(&path, git_hash::Kind::Sha1)?;
+    assert_eq!(file.version(), bitmap::Version::V1);
+    assert_eq!(file.path(), path);
+    assert_eq!(file.pack_checksum(), index.pack_checksum());
+    assert!(file.has_name_hash_cache());
+    assert!(file.num_commits() > 0);
+
+    use git_object::Kind::*;
+    let by_kind = [Commit, Tree, Blob, Tag].map(|kind| file.objects_of_kind(kind).len());
+    assert_eq!(by_kind, [16, 40, 811, 1]);
+    assert_eq!(
+        by_kind.iter().sum::<usize>(),
+        index.num_objects() as usize,
+        "each object has exactly one kind"
+    );
+
+    let db = db(&repo_dir)?;
+    for index_position in file.iter_commits() {
+        let reachable = file.lookup(index_position).expect("each stored commit has a bitmap");
+        assert_eq!(
+            ids_of(&reachable, &index),
+            reachable_ids(&db, index.oid_at_index(index_position).to_owned())?,
+            "the bitmap contains exactly the objects reachable from the commit"
+        );
+    }
+    assert_eq!(file.lookup(index.num_objects()), None, "out of bounds");
+    Ok(())
+}
+
+#[test]
+fn write_bitmap_like_git() -> crate::Result {
+    let repo_dir = repo_dir();
+    let expected_path = bitmap_path(&repo_dir);
+    let index = git_pack::index::File::at(expected_path.with_extension("idx"), git_hash::Kind::Sha1)?;
+    let expected = bitmap::File::at(&expected_path, git_hash::Kind::Sha1)?;
+
+    let dir = tempfile::TempDir::new()?;
+    let path = dir.path().join(expected_path.file_name().expect("present"));
+    let mut data = Vec::new();
+    let head = hex_to_id("dfcb5e39ac6eb30179808bbab721e8a28ce1b52e");
+    let outcome = bitmap::File::write_from_index(
+        &index,
+        db(&repo_dir)?,
+        Some(head),
+        &mut data,
+        progress::Discard,
+        &AtomicBool::new(false),
+        bitmap::write::Options {
+            object_hash: git_hash::Kind::Sha1,
+        },
+    )?;
+    std::fs::write(&path, &data)?;
+    assert_eq!(
+        outcome.checksum,
+        git_hash::ObjectId::from(&data[data.len() - 20..]),
+        "the checksum is the trailer of the file"
+    );
+
+    let actual = bitmap::File::at(&path, git_hash::Kind::Sha1)?;
+    assert_eq!(actual.num_commits(), outcome.num_commits);
+    assert_eq!(actual.pack_checksum(), expected.pack_checksum());
+    assert!(actual.has_name_hash_cache());
+    use git_object::Kind::*;
+    for kind in [Commit, Tree, Blob, Tag] {
+        assert_eq!(actual.objects_of_kind(kind), expected.objects_of_kind(kind));
+    }
+
+    let head_index_position = index.lookup(head).expect("head is in pack");
+    assert!(
+        actual
+            .iter_commits()
+            .any(|index_position| index_position == head_index_position),
+        "tips always get a bitmap"
+    );
+    let mut num_compared = 0;
+    for index_position in actual.iter_commits() {
+        let bitmap = actual.lookup(index_position).expect("present");
+        assert!(
+            ids_of(&bitmap, &index)
+                .binary_search(&index.oid_at_index(index_position).to_owned())
+                .is_ok(),
+            "each commit is reachable from itself"
+        );
+        if let Some(expected) = expected.lookup(index_position) {
+            assert_eq!(bitmap, expected, "bitmaps of the same commit are the same");
+            num_compared += 1;
+        }
+    }
+    assert!(num_compared > 0, "at least the bitmap of the tip is shared with git");
+
+    let objects_in_trees = {
+        let mut trees = expected.objects_of_kind(Tree);
+        trees.union_with(&expected.objects_of_kind(Blob));
+        ids_of(&trees, &index)
+    };
+    for index_position in (0..index.num_objects()).filter(|index_position| {
+        objects_in_trees
+            .binary_search(&index.oid_at_index(*index_position).to_owned())
+            .is_ok()
+    }) {
+        assert_eq!(
+            actual.name_hash(index_position),
+            expected.name_hash(index_position),
+            "name hashes of tree entries match those computed by git for {}",
+            index.oid_at_index(index_position)
+        );
+    }
+    Ok(())
+}
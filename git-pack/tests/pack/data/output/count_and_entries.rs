@@ -1,9 +1,13 @@
-use std::{convert::Infallible, sync::atomic::AtomicBool};
+use std::{
+    convert::{Infallible, TryInto},
+    sync::atomic::AtomicBool,
+};
 
 use git_features::{
     parallel::{reduce::Finalize, InOrderIter},
     progress,
 };
+use git_object::bstr::BStr;
 use git_odb::{pack, pack::FindExt};
 use git_pack::data::{
     output,
@@ -122,6 +126,7 @@ fn traversals() -> crate::Result {
                     missing_objects: 0,
                     objects_copied_from_pack: 16,
                     ref_delta_objects: 0,
+                    delta_compressed_objects: 0,
                 },
                 hex_to_id("b920bbb055e1efb9080592a409d3975738b6efb3"),
                 None,
@@ -155,6 +160,7 @@ fn traversals() -> crate::Result {
                     missing_objects: 0,
                     objects_copied_from_pack: 103,
                     ref_delta_objects: 74,
+                    delta_compressed_objects: 0,
                 },
                 hex_to_id("25114bd8820b393c402cd53ad8ec7f6a84bb0633"),
                 Some(hex_to_id("29ab9797aff1ca826afb699680356695d19c5acb")),
@@ -188,6 +194,7 @@ fn traversals() -> crate::Result {
                     missing_objects: 0,
                     objects_copied_from_pack: 29,
                     ref_delta_objects: 0,
+                    delta_compressed_objects: 0,
                 },
                 hex_to_id("d83d42128e40957c5174920189a0390b5a70f446"),
                 None,
@@ -209,6 +216,7 @@ fn traversals() -> crate::Result {
                     missing_objects: 0,
                     objects_copied_from_pack: 868,
                     ref_delta_objects: 0,
+                    delta_compressed_objects: 0,
                 },
                 hex_to_id("542ad1d1c7c762ea4e36907570ff9e4b5b7dde1b"),
                 None,
@@ -230,6 +238,7 @@ fn traversals() -> crate::Result {
                     missing_objects: 0,
                     objects_copied_from_pack: 868,
                     ref_delta_objects: 0,
+                    delta_compressed_objects: 0,
                 },
                 hex_to_id("542ad1d1c7c762ea4e36907570ff9e4b5b7dde1b"),
                 None,
@@ -265,6 +274,7 @@ fn traversals() -> crate::Result {
                     .map(Ok::<_, Infallible>),
                 progress::Discard,
                 &AtomicBool::new(false),
+                |_path| true,
                 count::objects::Options {
                     input_object_expansion: expansion_mode,
                     thread_limit: deterministic_count_needs_single_thread,
@@ -321,6 +331,195 @@ fn traversals() -> crate::Result {
     Ok(())
 }
 
+#[test]
+fn traversal_with_bitmaps() -> crate::Result {
+    let head = hex_to_id("dfcb5e39ac6eb30179808bbab721e8a28ce1b52e");
+    let tag = hex_to_id("e3fb53cbb4c346d48732a24f09cf445e49bc63d6");
+    let count_ids = |db_kind, expansion_mode, thread_limit| -> crate::Result<_> {
+        let db = db(db_kind)?;
+        let commits = commit::Ancestors::new(Some(head), commit::ancestors::State::default(), {
+            let db = db.clone();
+            move |oid, buf| db.find_commit_iter(oid, buf).map(|t| t.0)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+        let (counts, stats) = output::count::objects(
+            db,
+            commits.into_iter().chain(Some(tag)).map(Ok::<_, Infallible>),
+            progress::Discard,
+            &AtomicBool::new(false),
+            |_path| true,
+            count::objects::Options {
+                input_object_expansion: expansion_mode,
+                thread_limit,
+                chunk_size: 2,
+            },
+        )?;
+        let mut ids: Vec<_> = counts.into_iter().map(|count| count.id).collect();
+        ids.sort();
+        Ok((ids, stats))
+    };
+
+    let (expected_ids, expected_stats) = count_ids(
+        DbKind::DeterministicGeneratedContent,
+        count::objects::ObjectExpansion::TreeContents,
+        Some(1),
+    )?;
+    assert_eq!(expected_ids.len(), 868);
+
+    let (ids, stats) = count_ids(
+        DbKind::DeterministicGeneratedContent,
+        count::objects::ObjectExpansion::TreeContentsOfAncestry,
+        Some(1),
+    )?;
+    assert_eq!(ids, expected_ids, "without bitmaps, trees are traversed as usual");
+    assert_eq!(stats, expected_stats);
+
+    for thread_limit in [Some(1), None] {
+        let (ids, stats) = count_ids(
+            DbKind::DeterministicGeneratedContentWithBitmap,
+            count::objects::ObjectExpansion::TreeContentsOfAncestry,
+            thread_limit,
+        )?;
+        assert_eq!(ids, expected_ids, "bitmaps yield the same objects");
+        assert_eq!(stats.total_objects, expected_stats.total_objects);
+        assert!(
+            stats.decoded_objects < expected_stats.decoded_objects,
+            "trees aren't decoded when bitmaps are used: {} < {}",
+            stats.decoded_objects,
+            expected_stats.decoded_objects
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn delta_compression() -> crate::Result {
+    let db = db(DbKind::DeterministicGeneratedContent)?;
+    let head = hex_to_id("dfcb5e39ac6eb30179808bbab721e8a28ce1b52e");
+    let count_objects = |expansion_mode, take: Option<usize>, try_delta: &(dyn Fn(&BStr) -> bool + Sync)| {
+        let mut commits = commit::Ancestors::new(Some(head), commit::ancestors::State::default(), {
+            let db = db.clone();
+            move |oid, buf| db.find_commit_iter(oid, buf).map(|t| t.0)
+        })
+        .map(Result::unwrap)
+        .collect::<Vec<_>>();
+        if let Some(take) = take {
+            commits.truncate(take);
+        }
+        output::count::objects(
+            db.clone(),
+            commits.into_iter().map(Ok::<_, Infallible>),
+            progress::Discard,
+            &AtomicBool::new(false),
+            try_delta,
+            count::objects::Options {
+                input_object_expansion: expansion_mode,
+                thread_limit: Some(1),
+                ..Default::default()
+            },
+        )
+        .map(|(counts, _stats)| counts)
+    };
+    let make_entries = |counts: Vec<output::Count>, mode| -> crate::Result<_> {
+        let mut entries_iter = output::entry::iter_from_counts(
+            counts,
+            db.clone(),
+            progress::Discard,
+            output::entry::iter_from_counts::Options {
+                mode,
+                ..Default::default()
+            },
+        );
+        let entries: Vec<_> = InOrderIter::from(entries_iter.by_ref())
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+        Ok((entries, entries_iter.finalize()?))
+    };
+    let delta_compression =
+        output::entry::iter_from_counts::Mode::PackCopyAndDeltaCompression { window: 10, depth: 50 };
+
+    let counts = count_objects(
+        count::objects::ObjectExpansion::TreeAdditionsComparedToAncestor,
+        Some(1),
+        &|_path| true,
+    )?;
+    assert!(
+        counts.iter().filter(|c| c.name_hash != 0).count() > 90,
+        "objects reachable through trees know the hash of their path"
+    );
+    let (entries, stats) = make_entries(counts.clone(), delta_compression)?;
+    assert_eq!(
+        stats,
+        output::entry::iter_from_counts::Outcome {
+            decoded_and_recompressed_objects: 4,
+            missing_objects: 0,
+            objects_copied_from_pack: 29,
+            ref_delta_objects: 0,
+            delta_compressed_objects: 70,
+        }
+    );
+    write_and_verify(
+        db.clone(),
+        entries,
+        hex_to_id("68d5adbabf51c3ec610083c07ba31bfec9069ace"),
+        None,
+    )?;
+
+    let (entries, stats) = make_entries(
+        counts
+            .into_iter()
+            .map(|c| output::Count { try_delta: false, ..c })
+            .collect(),
+        delta_compression,
+    )?;
+    assert_eq!(
+        stats.delta_compressed_objects, 0,
+        "objects may opt out of delta compression"
+    );
+    write_and_verify(
+        db.clone(),
+        entries,
+        hex_to_id("d83d42128e40957c5174920189a0390b5a70f446"),
+        None,
+    )?;
+
+    let counts = count_objects(count::objects::ObjectExpansion::TreeContents, None, &|path| {
+        !path.starts_with(b"a/")
+    })?;
+    assert!(counts.iter().any(|c| !c.try_delta), "objects in 'a/' were opted out");
+    let (entries, stats) = make_entries(counts, delta_compression)?;
+    assert_eq!(
+        stats,
+        output::entry::iter_from_counts::Outcome {
+            decoded_and_recompressed_objects: 1,
+            missing_objects: 0,
+            objects_copied_from_pack: 858,
+            ref_delta_objects: 0,
+            delta_compressed_objects: 5,
+        }
+    );
+    write_and_verify(db, entries, hex_to_id("be6d278ee0eb2517b3499f6a5fde423ba53d1b4b"), None)?;
+    Ok(())
+}
+
+#[test]
+fn name_hash_sorts_paths_with_the_same_suffix_together() {
+    assert_eq!(count::name_hash("".into()), 0);
+    assert_eq!(
+        count::name_hash("dir/file name.rs".into()),
+        count::name_hash("dir/filename.rs".into()),
+        "whitespace is ignored"
+    );
+    let hash = count::name_hash("a/b/main.rs".into());
+    assert_eq!(
+        hash >> 24,
+        count::name_hash("other/main.rs".into()) >> 24,
+        "the last characters of the path dominate"
+    );
+}
+
 #[test]
 fn empty_pack_is_allowed() {
     write_and_verify(
@@ -344,7 +543,7 @@ fn write_and_verify(
         .write(true)
         .create_new(true)
         .open(&pack_file_path)?;
-    let (num_written_bytes, pack_hash) = {
+    let (num_written_bytes, pack_hash, mut index_entries) = {
         let num_entries = entries.len();
         let mut pack_writer = output::bytes::FromEntriesIter::new(
             std::iter::once(Ok::<_, entry::iter_from_counts::Error<git_odb::store::find::Error>>(
@@ -354,7 +553,8 @@ fn write_and_verify(
             num_entries as u32,
             pack::data::Version::V2,
             git_hash::Kind::Sha1,
-        );
+        )
+        .with_index_entries();
         let mut n = pack_writer.next().expect("one entries bundle was written")?;
         n += pack_writer.next().expect("the trailer was written")?;
         assert!(
@@ -363,9 +563,10 @@ fn write_and_verify(
         );
         // verify we can still get the original parts back
         let hash = pack_writer.digest().expect("digest is available when iterator is done");
+        let index_entries = pack_writer.take_index_entries().expect("index entries were recorded");
         let _ = pack_writer.input;
         let _ = pack_writer.into_write();
-        (n, hash)
+        (n, hash, index_entries)
     };
     assert_eq!(
         num_written_bytes,
@@ -382,21 +583,45 @@ fn write_and_verify(
 
     assert_eq!(hash, expected_pack_hash, "pack hashes are stable if the input is");
 
-    // Re-generate the index from the pack for validation.
     let object_hash = git_hash::Kind::Sha1; // TODO: parameterize this
-    let bundle = pack::Bundle::at(
-        pack::Bundle::write_to_directory(
-            std::io::BufReader::new(std::fs::File::open(pack_file_path)?),
-            Some(tmp_dir.path()),
-            progress::Discard,
-            &should_interrupt,
-            Some(Box::new(move |oid, buf| db.find(oid, buf).ok().map(|t| t.0))),
-            pack::bundle::write::Options::default(),
-        )?
-        .data_path
-        .expect("directory set"),
+    let mut index = Vec::new();
+    let index_outcome = pack::index::File::write_entries_to_stream(
+        pack::index::Version::V2,
+        &mut index_entries,
+        &pack_hash,
+        &mut index,
         object_hash,
+        progress::Discard,
+    )?;
+    assert_eq!(index_outcome.data_hash, pack_hash);
+    assert_eq!(index_outcome.num_objects as usize, index_entries.len());
+    let mut reverse_index = Vec::new();
+    let reverse_index_hash = pack::index::File::write_reverse_index_to_stream(
+        &mut index_entries,
+        &pack_hash,
+        &mut reverse_index,
+        object_hash,
+    )?;
+    assert_reverse_index(&reverse_index, &index_entries, &pack_hash, &reverse_index_hash);
+
+    // Re-generate the index from the pack for validation.
+    let write_outcome = pack::Bundle::write_to_directory(
+        std::io::BufReader::new(std::fs::File::open(pack_file_path)?),
+        Some(tmp_dir.path()),
+        progress::Discard,
+        &should_interrupt,
+        Some(Box::new(move |oid, buf| db.find(oid, buf).ok().map(|t| t.0))),
+        pack::bundle::write::Options::default(),
     )?;
+    if expected_thin_pack_hash.is_none() {
+        assert_eq!(
+            index,
+            std::fs::read(write_outcome.index_path.as_ref().expect("directory set"))?,
+            "the index written along with the pack is the same as the one generated from the pack"
+        );
+        assert_eq!(write_outcome.index.index_hash, index_outcome.index_hash);
+    }
+    let bundle = pack::Bundle::at(write_outcome.data_path.expect("directory set"), object_hash)?;
     if let Some(thin_pack_checksum) = expected_thin_pack_hash {
         let actual_checksum = bundle
             .pack
@@ -421,3 +646,27 @@ fn write_and_verify(
 
     Ok(())
 }
+
+fn assert_reverse_index(
+    reverse_index: &[u8],
+    entries_sorted_by_oid: &[pack::index::write::Entry],
+    pack_hash: &git_hash::ObjectId,
+    reverse_index_hash: &git_hash::ObjectId,
+) {
+    assert_eq!(&reverse_index[..4], b"RIDX", "signature");
+    assert_eq!(&reverse_index[4..12], &[0, 0, 0, 1, 0, 0, 0, 1], "version 1 with SHA1");
+    let (table, trailer) = reverse_index[12..].split_at(entries_sorted_by_oid.len() * 4);
+    let pack_offsets: Vec<_> = table
+        .chunks(4)
+        .map(|idx| entries_sorted_by_oid[u32::from_be_bytes(idx.try_into().unwrap()) as usize].pack_offset)
+        .collect();
+    let mut sorted_pack_offsets = pack_offsets.clone();
+    sorted_pack_offsets.sort_unstable();
+    sorted_pack_offsets.dedup();
+    assert_eq!(
+        pack_offsets, sorted_pack_offsets,
+        "each object appears once, in pack order"
+    );
+    assert_eq!(&trailer[..20], pack_hash.as_slice());
+    assert_eq!(&trailer[20..], reverse_index_hash.as_slice());
+}
@@ -6,7 +6,7 @@ use git_pack::data::output;
 fn size_of_entry() {
     assert_eq!(
         std::mem::size_of::<output::Entry>(),
-        80,
+        112,
         "The size of the structure shouldn't change unexpectedly"
     )
 }
@@ -15,7 +15,7 @@ fn size_of_entry() {
 fn size_of_count() {
     assert_eq!(
         std::mem::size_of::<output::Count>(),
-        56,
+        72,
         "The size of the structure shouldn't change unexpectedly"
     )
 }
@@ -23,6 +23,7 @@ fn size_of_count() {
 enum DbKind {
     DeterministicGeneratedContent,
     DeterministicGeneratedContentMultiIndex,
+    DeterministicGeneratedContentWithBitmap,
 }
 
 fn db(kind: DbKind) -> crate::Result<git_odb::HandleArc> {
@@ -30,6 +31,7 @@ fn db(kind: DbKind) -> crate::Result<git_odb::HandleArc> {
     let name = match kind {
         DeterministicGeneratedContent => "make_pack_gen_repo.sh",
         DeterministicGeneratedContentMultiIndex => "make_pack_gen_repo_multi_index.sh",
+        DeterministicGeneratedContentWithBitmap => "make_pack_gen_repo_bitmap.sh",
     };
     let path: PathBuf = crate::scripted_fixture_read_only(name)?.join(".git").join("objects");
     git_odb::Store::at_opts(path, Vec::new(), git_odb::store::init::Options::default())
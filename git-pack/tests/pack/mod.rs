@@ -37,8 +37,10 @@ pub fn fixup(v: Vec<u8>) -> Vec<u8> {
     v.replace(b"\r\n", "\n")
 }
 
+mod bitmap;
 mod bundle;
 mod data;
 mod index;
 mod iter;
+mod mtimes;
 mod multi_index;
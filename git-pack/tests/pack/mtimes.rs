@@ -0,0 +1,50 @@
+use git_pack::{index, mtimes};
+
+#[test]
+fn read_and_write_like_git() -> crate::Result {
+    let pack_dir = crate::scripted_fixture_read_only("make_cruft_pack.sh")?.join(".git/objects/pack");
+    let path = std::fs::read_dir(pack_dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .find(|p| p.extension().and_then(|e| e.to_str()) == Some("mtimes"))
+        .expect("git wrote a cruft pack");
+    let index = index::File::at(path.with_extension("idx"), git_hash::Kind::Sha1)?;
+    let file = mtimes::File::at(&path, git_hash::Kind::Sha1)?;
+    assert_eq!(file.path(), path);
+    assert_eq!(file.num_objects(), 5, "one for each unreachable object");
+    assert_eq!(file.num_objects(), index.num_objects());
+    assert_eq!(file.pack_checksum(), index.pack_checksum());
+    assert_eq!(file.mtime_at_index(file.num_objects()), None, "out of bounds");
+
+    let mtimes: Vec<_> = (0..file.num_objects())
+        .map(|index_position| file.mtime_at_index(index_position).expect("in bounds"))
+        .collect();
+    assert!(
+        mtimes.iter().all(|mtime| *mtime > 0),
+        "the mtimes of the loose objects are kept"
+    );
+
+    let mut entries: Vec<_> = index
+        .iter()
+        .map(|entry| index::write::Entry {
+            id: entry.oid,
+            pack_offset: entry.pack_offset,
+            crc32: entry.crc32.unwrap_or_default(),
+        })
+        .collect();
+    entries.reverse();
+    let mut out = Vec::new();
+    let checksum = mtimes::File::write_to_stream(
+        &mut entries,
+        |id| mtimes[index.lookup(id).expect("present") as usize],
+        &index.pack_checksum(),
+        &mut out,
+        git_hash::Kind::Sha1,
+    )?;
+    assert_eq!(
+        out,
+        std::fs::read(&path)?,
+        "the written file is the same as the one written by git"
+    );
+    assert_eq!(checksum, file.checksum());
+    Ok(())
+}
@@ -125,10 +125,12 @@ impl Arguments {
         }
     }
     /// Deepen the commit history in a relative instead of absolute fashion.
+    ///
+    /// Note that in protocol V1, this is a feature which must be set before the first [`want()`][Arguments::want()] is added.
     pub fn deepen_relative(&mut self) {
         debug_assert!(self.deepen_relative, "'deepen-relative' feature required");
         if self.deepen_relative {
-            self.args.push("deepen-relative".into());
+            self.feature_or_argument("deepen-relative");
         }
     }
     /// Do not include commits reachable by the given `ref_path` when deepening the history.
@@ -149,16 +151,23 @@ impl Arguments {
     }
     /// Permanently allow the server to include tags that point to commits or objects it would return.
     ///
-    /// Needs to only be called once.
+    /// Needs to only be called once, and in protocol V1 it must be called before the first [`want()`][Arguments::want()] is added.
     pub fn use_include_tag(&mut self) {
         debug_assert!(self.supports_include_tag, "'include-tag' feature required");
         if self.supports_include_tag {
-            self.args.push("include-tag".into());
+            self.feature_or_argument("include-tag");
         }
     }
     fn prefixed(&mut self, prefix: &str, value: impl fmt::Display) {
         self.args.push(format!("{prefix}{value}").into());
     }
+    /// In V1, `name` is a feature sent along with the first `want`, whereas in V2 it's an argument of its own.
+    fn feature_or_argument(&mut self, name: &str) {
+        match self.features_for_first_want.as_mut() {
+            Some(features) => features.push(name.into()),
+            None => self.args.push(name.into()),
+        }
+    }
     /// Create a new instance to help setting up arguments to send to the server as part of a `fetch` operation
     /// for which `features` are the available and configured features to use.
     #[cfg(any(feature = "async-client", feature = "blocking-client"))]
@@ -180,6 +189,8 @@ impl Arguments {
                 supports_include_tag = has("include-tag");
                 let baked_features = features
                     .iter()
+                    // These change what the server sends, so they are only sent if requested by the respective method.
+                    .filter(|(n, _)| !matches!(*n, "deepen-relative" | "include-tag"))
                     .map(|(n, v)| match v {
                         Some(v) => format!("{n}={v}"),
                         None => n.to_string(),
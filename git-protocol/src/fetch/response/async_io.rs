@@ -9,6 +9,18 @@ use crate::fetch::{
     Response,
 };
 
+/// Read exactly one packet line into `line`, which is what the blocking implementation of `read_line()` does as well.
+///
+/// Using [`AsyncBufReadExt::read_line()`] instead would read up to the next newline, which spans multiple packet lines
+/// if these don't end with a newline, like `shallow` and `unshallow` lines.
+async fn read_line(reader: &mut (impl client::ExtendedBufRead + Unpin), line: &mut String) -> io::Result<usize> {
+    let data = reader.fill_buf().await?;
+    let bytes = data.len();
+    line.push_str(std::str::from_utf8(data).map_err(|err| io::Error::new(io::ErrorKind::Other, err))?);
+    reader.consume(bytes);
+    Ok(bytes)
+}
+
 async fn parse_v2_section<T>(
     line: &mut String,
     reader: &mut (impl client::ExtendedBufRead + Unpin),
@@ -16,7 +28,7 @@ async fn parse_v2_section<T>(
     parse: impl Fn(&str) -> Result<T, response::Error>,
 ) -> Result<bool, response::Error> {
     line.clear();
-    while reader.read_line(line).await? != 0 {
+    while read_line(reader, line).await? != 0 {
         res.push(parse(line)?);
         line.clear();
     }
@@ -33,8 +45,12 @@ async fn parse_v2_section<T>(
 
 impl Response {
     /// Parse a response of the given `version` of the protocol from `reader`.
+    ///
+    /// `client_expects_pack` is only relevant for V1 stateful connections, and if `false`, causes us to stop parsing when seeing `NAK`,
+    /// and if `true` we will keep parsing until we get to the pack.
     pub async fn from_line_reader(
         version: Protocol,
+        client_expects_pack: bool,
         reader: &mut (impl client::ExtendedBufRead + Unpin),
     ) -> Result<Response, response::Error> {
         match version {
@@ -62,7 +78,7 @@ impl Response {
                                 Some(client::MessageKind::Flush),
                                 "If this isn't a flush packet, we don't know what's going on"
                             );
-                            reader.read_line(&mut line).await?;
+                            read_line(reader, &mut line).await?;
                             reader.reset(Protocol::V1);
                             match reader.peek_data_line().await {
                                 Some(Ok(Ok(line))) => String::from_utf8_lossy(line),
@@ -76,7 +92,11 @@ impl Response {
                     if Response::parse_v1_ack_or_shallow_or_assume_pack(&mut acks, &mut shallows, &peeked_line) {
                         break 'lines true;
                     }
-                    assert_ne!(reader.read_line(&mut line).await?, 0, "consuming a peeked line works");
+                    assert_ne!(read_line(reader, &mut line).await?, 0, "consuming a peeked line works");
+                    // Without `done`, the server ends each round with `NAK` and waits for more `have` lines, so there is nothing more to read.
+                    if !client_expects_pack && acks.last() == Some(&Acknowledgement::Nak) {
+                        break 'lines false;
+                    }
                 };
                 Ok(Response {
                     acks,
@@ -94,7 +114,7 @@ impl Response {
                 let mut wanted_refs = Vec::<WantedRef>::new();
                 let has_pack = 'section: loop {
                     line.clear();
-                    if reader.read_line(&mut line).await? == 0 {
+                    if read_line(reader, &mut line).await? == 0 {
                         return Err(response::Error::Io(io::Error::new(
                             io::ErrorKind::UnexpectedEof,
                             "Could not read message headline",
@@ -32,8 +32,12 @@ fn parse_v2_section<T>(
 
 impl Response {
     /// Parse a response of the given `version` of the protocol from `reader`.
+    ///
+    /// `client_expects_pack` is only relevant for V1 stateful connections, and if `false`, causes us to stop parsing when seeing `NAK`,
+    /// and if `true` we will keep parsing until we get to the pack.
     pub fn from_line_reader(
         version: Protocol,
+        client_expects_pack: bool,
         reader: &mut impl client::ExtendedBufRead,
     ) -> Result<Response, response::Error> {
         match version {
@@ -76,6 +80,10 @@ impl Response {
                         break 'lines true;
                     }
                     assert_ne!(reader.read_line(&mut line)?, 0, "consuming a peeked line works");
+                    // Without `done`, the server ends each round with `NAK` and waits for more `have` lines, so there is nothing more to read.
+                    if !client_expects_pack && acks.last() == Some(&Acknowledgement::Nak) {
+                        break 'lines false;
+                    }
                 };
                 Ok(Response {
                     acks,
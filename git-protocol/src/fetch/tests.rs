@@ -171,8 +171,7 @@ mod arguments {
             arguments.send(&mut t, true).await.expect("sending to buffer to work");
             assert_eq!(
                 out.as_bstr(),
-                b"0048want ff333369de1221f9bfbbe03a3a13e9a09bc1ffff include-tag feature-b
-0010include-tag
+                b"0048want ff333369de1221f9bfbbe03a3a13e9a09bc1ffff feature-b include-tag
 00000009done
 "
                 .as_bstr()
@@ -262,6 +261,38 @@ mod arguments {
                 .as_bstr()
             );
         }
+        #[maybe_async::test(feature = "blocking-client", async(feature = "async-client", async_std::test))]
+        async fn deepen_relative_is_only_sent_as_feature_if_requested() {
+            for request_relative in [false, true] {
+                let mut out = Vec::new();
+                let mut t = transport(&mut out, true);
+                let mut arguments = arguments_v1(["feature-a", "shallow", "deepen-relative"].iter().copied());
+                assert!(arguments.can_use_deepen_relative());
+
+                arguments.deepen(1);
+                if request_relative {
+                    arguments.deepen_relative();
+                }
+                arguments.want(id("7b333369de1221f9bfbbe03a3a13e9a09bc1c907"));
+                arguments.send(&mut t, true).await.expect("sending to buffer to work");
+                let expected: &[u8] = if request_relative {
+                    b"0054want 7b333369de1221f9bfbbe03a3a13e9a09bc1c907 feature-a shallow deepen-relative
+000ddeepen 1
+00000009done
+"
+                } else {
+                    b"0044want 7b333369de1221f9bfbbe03a3a13e9a09bc1c907 feature-a shallow
+000ddeepen 1
+00000009done
+"
+                };
+                assert_eq!(
+                    out.as_bstr(),
+                    expected.as_bstr(),
+                    "the feature changes how `deepen` is interpreted by the server"
+                );
+            }
+        }
     }
 
     mod v2 {
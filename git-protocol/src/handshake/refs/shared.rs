@@ -121,6 +121,10 @@ pub(in crate::handshake::refs) fn parse_v1(
     if path.is_empty() {
         return Err(Error::MalformedV1RefLine(trimmed.to_owned().into()));
     }
+    if path == b"capabilities^{}" {
+        // Servers without any ref, like `git-receive-pack` in an empty repository, advertise their capabilities with this placeholder.
+        return Ok(());
+    }
     match path.strip_suffix(b"^{}") {
         Some(stripped) => {
             let (previous_path, tag) =
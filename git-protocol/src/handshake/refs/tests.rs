@@ -109,6 +109,21 @@ dce0ea858eef7ff61ad345cc5cdac62203fb3c10 refs/tags/git-commitgraph-v0.0.0
     )
 }
 
+#[maybe_async::test(feature = "blocking-client", async(feature = "async-client", async_std::test))]
+async fn extract_no_references_from_v1_capabilities_placeholder() {
+    let input = &mut Fixture("0000000000000000000000000000000000000000 capabilities^{}".as_bytes());
+    let out = refs::from_v1_refs_received_as_part_of_handshake_and_capabilities(
+        input,
+        Capabilities::from_bytes(b"\0report-status delete-refs")
+            .expect("valid capabilities")
+            .0
+            .iter(),
+    )
+    .await
+    .expect("the placeholder is valid input");
+    assert!(out.is_empty(), "the placeholder isn't a reference");
+}
+
 #[test]
 fn extract_symbolic_references_from_capabilities() -> Result<(), client::Error> {
     let caps = client::Capabilities::from_bytes(
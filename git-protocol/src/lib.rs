@@ -1,4 +1,7 @@
-//! An abstraction over [fetching][fetch()] a pack from the server.
+//! An abstraction over [fetching][fetch()] a pack from the server, and over pushing to it.
+//!
+//! The server side of fetching is provided by [`upload_pack()`], which serves packs to clients on behalf of a
+//! [delegate][upload_pack::Delegate] with access to a repository. Similarly, [`receive_pack()`] is the server side of pushing.
 //!
 //! This implementation hides the transport layer, statefulness and the protocol version to the [fetch delegate][fetch::Delegate],
 //! the actual client implementation.
@@ -58,6 +61,23 @@ pub mod ls_refs;
 #[cfg(any(feature = "blocking-client", feature = "async-client"))]
 pub use ls_refs::function::ls_refs;
 
+///
+pub mod push;
+#[cfg(any(feature = "blocking-client", feature = "async-client"))]
+pub use push::function::push;
+
+///
+#[cfg(any(feature = "blocking-client", feature = "async-client"))]
+pub mod upload_pack;
+#[cfg(feature = "blocking-client")]
+pub use upload_pack::function::upload_pack;
+
+///
+#[cfg(any(feature = "blocking-client", feature = "async-client"))]
+pub mod receive_pack;
+#[cfg(feature = "blocking-client")]
+pub use receive_pack::function::receive_pack;
+
 mod util;
 pub use util::agent;
 #[cfg(any(feature = "blocking-client", feature = "async-client"))]
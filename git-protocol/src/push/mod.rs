@@ -0,0 +1,153 @@
+use bstr::BString;
+
+///
+pub mod response;
+pub use response::Response;
+
+/// A single reference update to request from `git-receive-pack`.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Command {
+    /// The value the reference is expected to have on the server, or the null id if it should be created.
+    pub old_id: git_hash::ObjectId,
+    /// The value to set the reference to, or the null id if it should be deleted.
+    pub new_id: git_hash::ObjectId,
+    /// The full name of the reference on the server.
+    pub ref_name: BString,
+}
+
+impl Command {
+    /// Return true if this command deletes the reference on the server.
+    pub fn is_delete(&self) -> bool {
+        self.new_id.is_null()
+    }
+}
+
+mod error {
+    /// The error returned by [push()][crate::push()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error(transparent)]
+        Io(#[from] std::io::Error),
+        #[error(transparent)]
+        Transport(#[from] git_transport::client::Error),
+        #[error(transparent)]
+        PacketlineDecode(#[from] git_transport::packetline::decode::Error),
+        #[error("Currently we require feature {feature:?}, which is not supported by the server")]
+        MissingServerCapability { feature: &'static str },
+        #[error("The server did not send a report after receiving the pack")]
+        MissingReport,
+        #[error(transparent)]
+        Response(#[from] super::response::Error),
+    }
+
+    impl git_transport::IsSpuriousError for Error {
+        fn is_spurious(&self) -> bool {
+            match self {
+                Error::Io(err) => err.is_spurious(),
+                Error::Transport(err) => err.is_spurious(),
+                _ => false,
+            }
+        }
+    }
+}
+pub use error::Error;
+
+#[cfg(any(feature = "blocking-client", feature = "async-client"))]
+pub(crate) mod function {
+    #[cfg(feature = "async-client")]
+    use futures_lite::io::AsyncWriteExt;
+    #[cfg(feature = "blocking-client")]
+    use std::io::Write;
+
+    use bstr::{BString, ByteSlice};
+    use git_transport::{
+        client::{Capabilities, MessageKind, Transport, WriteMode},
+        Protocol,
+    };
+    use maybe_async::maybe_async;
+
+    use super::{Command, Error, Response};
+
+    /// Send `commands` to `git-receive-pack` on the other side of `transport`, which requires a prior handshake that yielded
+    /// server `capabilities`. Unless all commands are deletions, `write_pack(out)` is called to write the pack containing the
+    /// objects needed by the server to `out`.
+    ///
+    /// `agent` is the name of the git client to present to the server, like `"my-app (v2.0)"`.
+    ///
+    /// The report of the server is requested with `report-status-v2` if supported, or with `report-status` otherwise.
+    /// If `commands` is empty, the server is merely told that nothing is to be done, yielding an empty response.
+    ///
+    /// _Note_ that `write_pack` is blocking even if the transport is not, so this call should be on an executor which can
+    /// handle that.
+    #[maybe_async]
+    pub async fn push(
+        mut transport: impl Transport,
+        capabilities: &Capabilities,
+        commands: &[Command],
+        agent: impl Into<String>,
+        write_pack: impl FnOnce(&mut dyn std::io::Write) -> std::io::Result<()>,
+    ) -> Result<Response, Error> {
+        if commands.is_empty() {
+            transport
+                .request(WriteMode::Binary, MessageKind::Flush)?
+                .into_read()
+                .await?;
+            return Ok(Response::default());
+        }
+        let mut features = vec![if capabilities.contains("report-status-v2") {
+            "report-status-v2"
+        } else if capabilities.contains("report-status") {
+            "report-status"
+        } else {
+            return Err(Error::MissingServerCapability {
+                feature: "report-status",
+            });
+        }
+        .to_owned()];
+        if commands.iter().any(Command::is_delete) {
+            if !capabilities.contains("delete-refs") {
+                return Err(Error::MissingServerCapability { feature: "delete-refs" });
+            }
+            features.push("delete-refs".into());
+        }
+        if capabilities.contains("agent") {
+            features.push(format!("agent={}", crate::agent(agent)));
+        }
+
+        let mut writer = transport.request(WriteMode::Binary, MessageKind::Flush)?;
+        for (idx, command) in commands.iter().enumerate() {
+            let mut line = BString::from(format!("{} {} ", command.old_id, command.new_id));
+            line.extend_from_slice(&command.ref_name);
+            if idx == 0 {
+                line.push(0);
+                line.extend_from_slice(features.join(" ").as_bytes());
+            }
+            writer.write_all(&line).await?;
+        }
+        writer.write_message(MessageKind::Flush).await?;
+        let (mut writer, mut reader) = writer.into_parts();
+        if !commands.iter().all(Command::is_delete) {
+            #[cfg(feature = "async-client")]
+            write_pack(&mut futures_lite::io::BlockOn::new(&mut writer))?;
+            #[cfg(feature = "blocking-client")]
+            write_pack(&mut writer)?;
+        }
+        writer.flush().await?;
+        drop(writer);
+
+        reader.reset(Protocol::V1);
+        let mut lines = Vec::<BString>::new();
+        while let Some(line) = reader.readline().await {
+            let line = line??;
+            if let Some(data) = line.as_slice() {
+                lines.push(data.into());
+            }
+        }
+        if lines.is_empty() {
+            return Err(Error::MissingReport);
+        }
+        Ok(Response::from_lines(lines.iter().map(|line| line.as_bstr()))?)
+    }
+}
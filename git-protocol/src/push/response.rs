@@ -0,0 +1,117 @@
+use bstr::{BStr, BString, ByteSlice};
+
+/// The error returned by [`Response::from_lines()`].
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("The report did not start with an 'unpack' line")]
+    MissingUnpackStatus,
+    #[error("Encountered an unknown line prefix in {line:?}")]
+    UnknownLineType { line: BString },
+    #[error("An option line was received before any reference status in {line:?}")]
+    OptionWithoutRefStatus { line: BString },
+    #[error("Could not decode the object id in option line {line:?}")]
+    DecodeObjectId {
+        line: BString,
+        source: git_hash::decode::Error,
+    },
+}
+
+/// The status of a single reference update as reported by the server.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct RefStatus {
+    /// The name of the reference as sent in the command.
+    pub ref_name: BString,
+    /// If `None`, the update was performed, otherwise it contains the reason for why it was rejected.
+    pub error: Option<BString>,
+    /// The name of the reference that was actually updated, if it differs from `ref_name`, as reported with `report-status-v2`.
+    pub updated_ref_name: Option<BString>,
+    /// The previous value of the reference as seen by the server, as reported with `report-status-v2`.
+    pub old_id: Option<git_hash::ObjectId>,
+    /// The new value of the reference on the server, as reported with `report-status-v2`.
+    pub new_id: Option<git_hash::ObjectId>,
+    /// If `true`, the update wasn't a fast-forward, as reported with `report-status-v2`.
+    pub forced_update: bool,
+}
+
+impl RefStatus {
+    /// Create the status of the update of `ref_name`, which failed if `error` is set, without any of the details that
+    /// `report-status-v2` can convey.
+    pub fn new(ref_name: &BStr, error: Option<&BStr>) -> Self {
+        RefStatus {
+            ref_name: ref_name.to_owned(),
+            error: error.map(ToOwned::to_owned),
+            updated_ref_name: None,
+            old_id: None,
+            new_id: None,
+            forced_update: false,
+        }
+    }
+}
+
+/// The report of the server after receiving all commands and the pack, as sent when `report-status` or `report-status-v2`
+/// was requested.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Response {
+    /// If `None`, the pack was received and unpacked successfully, otherwise it contains the reason for it to fail.
+    pub unpack_error: Option<BString>,
+    /// The status of each reference update, in the order in which they were reported.
+    pub refs: Vec<RefStatus>,
+}
+
+impl Response {
+    /// Return true if the pack was unpacked and all reference updates were performed.
+    pub fn is_success(&self) -> bool {
+        self.unpack_error.is_none() && self.refs.iter().all(|r| r.error.is_none())
+    }
+
+    /// Parse all `lines` of a `report-status` or `report-status-v2` response, without their packet line framing.
+    pub fn from_lines<'a>(lines: impl IntoIterator<Item = &'a BStr>) -> Result<Self, Error> {
+        let mut lines = lines
+            .into_iter()
+            .map(|line| line.trim_end_with(|c| c == '\n').as_bstr());
+        let unpack_error = match lines
+            .next()
+            .and_then(|line| line.strip_prefix(b"unpack ").map(ByteSlice::as_bstr))
+        {
+            Some(status) if status == "ok" => None,
+            Some(status) => Some(status.to_owned()),
+            None => return Err(Error::MissingUnpackStatus),
+        };
+        let mut refs = Vec::<RefStatus>::new();
+        for line in lines {
+            let (kind, rest) = line.split_once_str(" ").unwrap_or((line, &[]));
+            let rest = rest.as_bstr();
+            match kind {
+                b"ok" => refs.push(RefStatus::new(rest, None)),
+                b"ng" => {
+                    let (name, reason) = rest.split_once_str(" ").unwrap_or((rest, b"unknown error"));
+                    refs.push(RefStatus::new(name.as_bstr(), Some(reason.as_bstr())))
+                }
+                b"option" => {
+                    let status = refs
+                        .last_mut()
+                        .ok_or_else(|| Error::OptionWithoutRefStatus { line: line.to_owned() })?;
+                    let (key, value) = rest.split_once_str(" ").unwrap_or((rest, &[]));
+                    let decode = |value: &[u8]| {
+                        git_hash::ObjectId::from_hex(value).map_err(|source| Error::DecodeObjectId {
+                            line: line.to_owned(),
+                            source,
+                        })
+                    };
+                    match key {
+                        b"refname" => status.updated_ref_name = Some(value.into()),
+                        b"old-oid" => status.old_id = Some(decode(value)?),
+                        b"new-oid" => status.new_id = Some(decode(value)?),
+                        b"forced-update" => status.forced_update = true,
+                        _ => {}
+                    }
+                }
+                _ => return Err(Error::UnknownLineType { line: line.to_owned() }),
+            }
+        }
+        Ok(Response { unpack_error, refs })
+    }
+}
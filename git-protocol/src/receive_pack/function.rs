@@ -0,0 +1,233 @@
+use std::{cell::RefCell, io};
+
+use bstr::{BString, ByteSlice, ByteVec};
+use git_transport::packetline::{encode, Channel, StreamingPeekableIter};
+
+use super::{Delegate, Error, Options, Request};
+use crate::{
+    handshake::Ref,
+    push::{response::RefStatus, Response},
+    upload_pack::{
+        function::{format_ref, object_format, read_line, Band, Line},
+        request::SideBand,
+    },
+};
+
+/// Serve a client of `git-receive-pack` which sends its requests via `input` and receives our responses via `out`, and use
+/// `delegate` to obtain references, store the pack sent by the client and update references.
+///
+/// After advertising all references, the commands of the client are read along with the pack, if one is needed, before
+/// the commands are applied and their outcome is reported to the client if it asked for it. The returned [`Response`]
+/// is the report as sent to the client, and it indicates failures to unpack the pack or to perform commands.
+///
+/// Errors caused by the client, like malformed requests, are also sent to the client as `ERR` packet line before being returned.
+///
+/// ### Deviation
+///
+/// Only protocol V1 is supported, just like `git` does, and pushes from shallow clones as well as signed pushes are rejected.
+pub fn receive_pack(
+    input: impl io::Read,
+    mut out: impl io::Write,
+    delegate: &mut impl Delegate,
+    options: &Options,
+) -> Result<Response, Error> {
+    let res = serve(input, &mut out, delegate, options);
+    if let Err(err) = &res {
+        if matches!(
+            err,
+            Error::PacketlineDecode(_)
+                | Error::Request(_)
+                | Error::UnexpectedLine { .. }
+                | Error::IncompatibleObjectHash { .. }
+        ) {
+            encode::error_to_write(err.to_string().as_bytes(), &mut out).ok();
+        }
+    }
+    out.flush()?;
+    res
+}
+
+fn serve(
+    input: impl io::Read,
+    out: &mut impl io::Write,
+    delegate: &mut impl Delegate,
+    options: &Options,
+) -> Result<Response, Error> {
+    if !options.stateless_rpc || options.advertise_refs {
+        let refs = delegate.refs().map_err(Error::Delegate)?;
+        advertise(&refs, options, &mut *out)?;
+        out.flush()?;
+    }
+    if options.advertise_refs {
+        return Ok(Response::default());
+    }
+
+    let mut reader = StreamingPeekableIter::new(input, &[]);
+    let mut request = Request::default();
+    loop {
+        match read_line::<Error>(&mut reader)? {
+            Line::Data(line) => request.parse_command_line(line.as_ref())?,
+            Line::Flush => break,
+            Line::Delimiter => return Err(Error::UnexpectedLine { line: "0001".into() }),
+            Line::Eof => return Ok(Response::default()),
+        }
+    }
+    if request.commands.is_empty() {
+        return Ok(Response::default());
+    }
+    if let Some(format) = &request.object_format {
+        if format != object_format(options.object_hash) {
+            return Err(Error::IncompatibleObjectHash {
+                client: format.clone(),
+                server: options.object_hash,
+            });
+        }
+    }
+    if let Some(push_options) = request.push_options.as_mut() {
+        loop {
+            match read_line::<Error>(&mut reader)? {
+                Line::Data(line) => push_options.push(line.trim_end_with(|c| c == '\n').into()),
+                Line::Flush => break,
+                Line::Delimiter => return Err(Error::UnexpectedLine { line: "0001".into() }),
+                Line::Eof => return Ok(Response::default()),
+            }
+        }
+    }
+
+    let out = RefCell::new(out);
+    let mut progress = Band {
+        out: &out,
+        channel: Channel::Progress,
+        max_data_len: SideBand::Large.max_data_len(),
+    };
+    let mut sink = io::sink();
+    let progress: &mut dyn io::Write = if request.side_band && !request.quiet {
+        &mut progress
+    } else {
+        &mut sink
+    };
+
+    let mut unpack_error = None;
+    if request.expects_pack() {
+        let mut pack = io::BufReader::new(reader.into_inner());
+        if let Err(err) = delegate.store_pack(&mut pack, progress) {
+            unpack_error = Some(BString::from(err.to_string().replace('\n', " ")));
+        }
+    }
+    let refs = match unpack_error {
+        None => {
+            let refs = delegate.update_refs(&request, progress);
+            if refs.len() != request.commands.len() {
+                return Err(Error::StatusCountMismatch {
+                    expected: request.commands.len(),
+                    actual: refs.len(),
+                });
+            }
+            refs
+        }
+        Some(_) => request
+            .commands
+            .iter()
+            .map(|command| RefStatus::new(command.ref_name.as_ref(), Some("unpacker error".into())))
+            .collect(),
+    };
+    let response = Response { unpack_error, refs };
+
+    if request.report_status || request.report_status_v2 {
+        let mut report = Vec::new();
+        for line in report_lines(&response, request.report_status_v2) {
+            encode::text_to_write(&line, &mut report)?;
+        }
+        encode::flush_to_write(&mut report)?;
+        if request.side_band {
+            io::Write::write_all(
+                &mut Band {
+                    out: &out,
+                    channel: Channel::Data,
+                    max_data_len: SideBand::Large.max_data_len(),
+                },
+                &report,
+            )?;
+        } else {
+            out.borrow_mut().write_all(&report)?;
+        }
+    }
+    if request.side_band {
+        encode::flush_to_write(&mut *out.borrow_mut())?;
+    }
+    Ok(response)
+}
+
+fn advertise(refs: &[Ref], options: &Options, mut out: impl io::Write) -> Result<(), Error> {
+    let mut lines: Vec<BString> = refs
+        .iter()
+        .filter_map(|r| match r {
+            Ref::Direct { full_ref_name, object }
+            | Ref::Symbolic {
+                full_ref_name, object, ..
+            }
+            | Ref::Peeled {
+                full_ref_name,
+                tag: object,
+                ..
+            } => Some(format_ref(object.to_string(), full_ref_name)),
+            Ref::Unborn { .. } => None,
+        })
+        .collect();
+    if lines.is_empty() {
+        // Empty repositories advertise a placeholder to be able to transmit their capabilities.
+        lines.push(format_ref(
+            git_hash::ObjectId::null(options.object_hash).to_string(),
+            b"capabilities^{}",
+        ));
+    }
+    lines[0].push(0);
+    lines[0].push_str(format!(
+        "report-status report-status-v2 delete-refs side-band-64k quiet atomic ofs-delta push-options object-format={} agent={}",
+        object_format(options.object_hash),
+        crate::agent(options.agent.clone())
+    ));
+    for line in lines {
+        encode::text_to_write(&line, &mut out)?;
+    }
+    encode::flush_to_write(&mut out)?;
+    Ok(())
+}
+
+/// Produce the lines of a `report-status` report, or of a `report-status-v2` report if `v2` is true.
+fn report_lines(response: &Response, v2: bool) -> Vec<BString> {
+    let mut lines = vec![BString::from(format!(
+        "unpack {}",
+        response
+            .unpack_error
+            .as_ref()
+            .map_or("ok".into(), |err| err.to_str_lossy())
+    ))];
+    for status in &response.refs {
+        let mut line = BString::from(if status.error.is_some() { "ng " } else { "ok " });
+        line.push_str(&status.ref_name);
+        if let Some(err) = &status.error {
+            line.push(b' ');
+            line.push_str(err);
+        }
+        lines.push(line);
+        if !v2 {
+            continue;
+        }
+        if let Some(name) = &status.updated_ref_name {
+            let mut line = BString::from("option refname ");
+            line.push_str(name);
+            lines.push(line);
+        }
+        if let Some(id) = status.old_id {
+            lines.push(format!("option old-oid {}", id).into());
+        }
+        if let Some(id) = status.new_id {
+            lines.push(format!("option new-oid {}", id).into());
+        }
+        if status.forced_update {
+            lines.push("option forced-update".into());
+        }
+    }
+    lines
+}
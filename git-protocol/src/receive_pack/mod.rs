@@ -0,0 +1,84 @@
+use std::io;
+
+use crate::{handshake::Ref, push::response::RefStatus};
+
+///
+pub mod request;
+pub use request::Request;
+
+/// Options to configure how [`receive_pack()`][crate::receive_pack()] serves a client.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The kind of object ids used by the repository we serve.
+    pub object_hash: git_hash::Kind,
+    /// If true, the client connects anew for each request as it does over HTTP, which is why no capabilities or references
+    /// are advertised unless `advertise_refs` is set.
+    pub stateless_rpc: bool,
+    /// If true, only advertise references and capabilities, and return without reading anything.
+    pub advertise_refs: bool,
+    /// The name of this server as presented to clients, like `"my-app (v2.0)"`.
+    pub agent: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            object_hash: git_hash::Kind::Sha1,
+            stateless_rpc: false,
+            advertise_refs: false,
+            agent: concat!("oxide-", env!("CARGO_PKG_VERSION")).into(),
+        }
+    }
+}
+
+/// The error returned by the methods of a [`Delegate`].
+pub type DelegateError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// The part of the `receive-pack` server that has access to a repository.
+pub trait Delegate {
+    /// Return all references to advertise to the client, in the order in which they should be advertised.
+    ///
+    /// Peeled references are advertised with the id of the tag they point to, and unborn references aren't advertised at all.
+    fn refs(&mut self) -> Result<Vec<Ref>, DelegateError>;
+
+    /// Read the pack sent by the client from `pack` and store its objects in a quarantine, which makes them available
+    /// to [`update_refs()`][Delegate::update_refs()] without making them visible to other readers of the repository.
+    ///
+    /// `progress` receives human-readable progress messages for the client, like `"Resolving deltas: 5\r"`.
+    fn store_pack(&mut self, pack: &mut dyn io::BufRead, progress: &mut dyn io::Write) -> Result<(), DelegateError>;
+
+    /// Check that all objects needed by the `commands` of `request` are present, move the quarantined objects into
+    /// the repository, and apply the commands, all at once if `request.atomic` is set.
+    ///
+    /// Return the status of each command in the order of `request.commands`. If no pack was sent as all commands
+    /// are deletions, [`store_pack()`][Delegate::store_pack()] isn't called beforehand.
+    fn update_refs(&mut self, request: &Request, progress: &mut dyn io::Write) -> Vec<RefStatus>;
+}
+
+mod error {
+    use bstr::BString;
+
+    /// The error returned by [receive_pack()][crate::receive_pack()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error(transparent)]
+        Io(#[from] std::io::Error),
+        #[error(transparent)]
+        PacketlineDecode(#[from] git_transport::packetline::decode::Error),
+        #[error(transparent)]
+        Request(#[from] super::request::Error),
+        #[error("Expected a text line, got {line:?}")]
+        UnexpectedLine { line: BString },
+        #[error("The client announced {client} object ids, but the repository uses {server}")]
+        IncompatibleObjectHash { client: BString, server: git_hash::Kind },
+        #[error("The delegate returned {actual} statuses for {expected} commands")]
+        StatusCountMismatch { expected: usize, actual: usize },
+        #[error(transparent)]
+        Delegate(super::DelegateError),
+    }
+}
+pub use error::Error;
+
+#[cfg(feature = "blocking-client")]
+pub(crate) mod function;
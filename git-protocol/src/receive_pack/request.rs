@@ -0,0 +1,107 @@
+use bstr::{BStr, BString, ByteSlice};
+
+use crate::push::Command;
+
+/// The error returned by [`Request::parse_command_line()`].
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("Could not parse the command line {line:?}")]
+    InvalidCommand { line: BString },
+    #[error("Could not decode the object id in line {line:?}")]
+    DecodeObjectId {
+        line: BString,
+        source: git_hash::decode::Error,
+    },
+    #[error("The argument {name:?} is not supported by this server")]
+    Unsupported { name: &'static str },
+}
+
+/// Everything a client sends to describe the reference updates it wants to perform.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Request {
+    /// The reference updates to perform, in the order they were sent.
+    pub commands: Vec<Command>,
+    /// If true, the client wants to receive a report of the outcome of each command.
+    pub report_status: bool,
+    /// If true, the client wants to receive a report that may include the actual changes to each reference.
+    pub report_status_v2: bool,
+    /// If true, progress and the report are multiplexed using packet lines with up to 65520 bytes of data.
+    pub side_band: bool,
+    /// If true, the client doesn't want to receive progress information.
+    pub quiet: bool,
+    /// If true, either all commands must succeed or none of them may be performed.
+    pub atomic: bool,
+    /// The push options sent by the client after the commands, or `None` if the client didn't enable them.
+    pub push_options: Option<Vec<BString>>,
+    /// The name of the client, as sent with the `agent` capability.
+    pub agent: Option<BString>,
+    /// The kind of object ids the client uses, as sent with the `object-format` capability.
+    pub object_format: Option<BString>,
+}
+
+impl Request {
+    /// Return true if a pack follows the commands, which is the case unless all of them are deletions.
+    pub fn expects_pack(&self) -> bool {
+        !self.commands.iter().all(Command::is_delete)
+    }
+
+    /// Parse a single command `line` like `<old-id> <new-id> <ref-name>` and add it to our commands.
+    ///
+    /// The first command line may be followed by a null byte and the space-separated capabilities of the client, which are
+    /// parsed as well.
+    pub fn parse_command_line(&mut self, line: &BStr) -> Result<(), Error> {
+        let line = line.trim_end_with(|c| c == '\n').as_bstr();
+        let (command, capabilities) = match line.find_byte(0) {
+            Some(pos) if self.commands.is_empty() => (line[..pos].as_bstr(), Some(line[pos + 1..].as_bstr())),
+            _ => (line, None),
+        };
+        if command.starts_with(b"shallow ") {
+            return Err(Error::Unsupported { name: "shallow" });
+        }
+        if command.starts_with(b"push-cert") {
+            return Err(Error::Unsupported { name: "push-cert" });
+        }
+        let mut tokens = command.splitn_str(3, b" ");
+        let (old_id, new_id, ref_name) = match (tokens.next(), tokens.next(), tokens.next()) {
+            (Some(old_id), Some(new_id), Some(ref_name)) if !ref_name.is_empty() => (old_id, new_id, ref_name),
+            _ => return Err(Error::InvalidCommand { line: line.to_owned() }),
+        };
+        self.commands.push(Command {
+            old_id: decode_id(old_id.as_bstr(), line)?,
+            new_id: decode_id(new_id.as_bstr(), line)?,
+            ref_name: ref_name.into(),
+        });
+        for capability in capabilities.into_iter().flat_map(|c| c.split_str(b" ")) {
+            self.parse_capability(capability.as_bstr());
+        }
+        Ok(())
+    }
+
+    /// Unknown capabilities are ignored, just like `git` does.
+    fn parse_capability(&mut self, capability: &BStr) {
+        match capability.as_bytes() {
+            b"report-status" => self.report_status = true,
+            b"report-status-v2" => self.report_status_v2 = true,
+            b"side-band-64k" => self.side_band = true,
+            b"quiet" => self.quiet = true,
+            b"atomic" => self.atomic = true,
+            b"push-options" => self.push_options = Some(Vec::new()),
+            _ => {
+                if let Some(agent) = capability.strip_prefix(b"agent=") {
+                    self.agent = Some(agent.into());
+                } else if let Some(format) = capability.strip_prefix(b"object-format=") {
+                    self.object_format = Some(format.into());
+                }
+            }
+        }
+    }
+}
+
+fn decode_id(value: &BStr, line: &BStr) -> Result<git_hash::ObjectId, Error> {
+    git_hash::ObjectId::from_hex(value).map_err(|err| Error::DecodeObjectId {
+        line: line.to_owned(),
+        source: err,
+    })
+}
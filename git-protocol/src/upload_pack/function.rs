@@ -0,0 +1,564 @@
+use std::{cell::RefCell, io};
+
+use bstr::{BString, ByteSlice, ByteVec};
+use git_transport::{
+    packetline::{encode, Channel, PacketLineRef, StreamingPeekableIter},
+    Protocol,
+};
+
+use super::{request::SideBand, Delegate, Error, Options, Request, Shallow};
+use crate::handshake::Ref;
+
+/// Serve a client of `git-upload-pack` which sends its requests via `input` and receives our responses via `out`, and obtain
+/// references and objects from `delegate`.
+///
+/// Depending on the protocol version in `options`, V1 clients are served by advertising all references followed by the
+/// negotiation of a single pack, and V2 clients are served by advertising our capabilities and answering `ls-refs` and
+/// `fetch` commands until the client disconnects.
+///
+/// Errors caused by the client, like malformed requests, are also sent to the client as `ERR` packet line before being returned.
+///
+/// ### Deviation
+///
+/// Clients can only ask for objects that are pointed to by advertised references, as if `uploadpack.allowTipSHA1InWant`
+/// and similar options were unset, and only `multi_ack_detailed` is offered for negotiating in V1.
+pub fn upload_pack(
+    input: impl io::Read,
+    mut out: impl io::Write,
+    delegate: &mut impl Delegate,
+    options: &Options,
+) -> Result<(), Error> {
+    let mut reader = StreamingPeekableIter::new(input, &[]);
+    let res = match options.version {
+        Protocol::V1 => serve_v1(&mut reader, &mut out, delegate, options),
+        Protocol::V2 => serve_v2(&mut reader, &mut out, delegate, options),
+    };
+    if let Err(err) = &res {
+        if matches!(
+            err,
+            Error::PacketlineDecode(_)
+                | Error::Request(_)
+                | Error::UnexpectedLine { .. }
+                | Error::UnknownCommand { .. }
+                | Error::UnadvertisedWant { .. }
+                | Error::IncompatibleObjectHash { .. }
+        ) {
+            encode::error_to_write(err.to_string().as_bytes(), &mut out).ok();
+        }
+    }
+    out.flush()?;
+    res
+}
+
+pub(crate) enum Line {
+    Data(BString),
+    Flush,
+    Delimiter,
+    Eof,
+}
+
+pub(crate) fn read_line<E>(reader: &mut StreamingPeekableIter<impl io::Read>) -> Result<Line, E>
+where
+    E: From<io::Error> + From<git_transport::packetline::decode::Error>,
+{
+    Ok(match reader.read_line() {
+        None => Line::Eof,
+        Some(Err(err)) if err.kind() == io::ErrorKind::UnexpectedEof => Line::Eof,
+        Some(Err(err)) => return Err(err.into()),
+        Some(Ok(Err(err))) => return Err(err.into()),
+        Some(Ok(Ok(line))) => match line {
+            PacketLineRef::Data(data) => Line::Data(data.into()),
+            PacketLineRef::Flush | PacketLineRef::ResponseEnd => Line::Flush,
+            PacketLineRef::Delimiter => Line::Delimiter,
+        },
+    })
+}
+
+fn serve_v1(
+    reader: &mut StreamingPeekableIter<impl io::Read>,
+    out: &mut impl io::Write,
+    delegate: &mut impl Delegate,
+    options: &Options,
+) -> Result<(), Error> {
+    let refs = delegate.refs().map_err(Error::Delegate)?;
+    if !options.stateless_rpc || options.advertise_refs {
+        advertise_v1(&refs, options, &mut *out)?;
+        out.flush()?;
+    }
+    if options.advertise_refs {
+        return Ok(());
+    }
+
+    let mut request = Request::default();
+    loop {
+        match read_line::<Error>(reader)? {
+            Line::Data(line) => request.parse_line(line.as_ref(), Protocol::V1)?,
+            Line::Flush => break,
+            Line::Delimiter => return Err(Error::UnexpectedLine { line: "0001".into() }),
+            Line::Eof => return Ok(()),
+        }
+    }
+    if request.wants.is_empty() {
+        return Ok(());
+    }
+    check_wants(&refs, &request.wants)?;
+    let shallow = if request.is_deepening() || !request.shallow.is_empty() {
+        let shallow = delegate.shallow(&request).map_err(Error::Delegate)?;
+        write_shallow(&shallow, &mut *out)?;
+        encode::flush_to_write(&mut *out)?;
+        out.flush()?;
+        Some(shallow)
+    } else {
+        None
+    };
+
+    let multi_ack = request.multi_ack_detailed;
+    let mut common = Vec::<git_hash::ObjectId>::new();
+    let (mut got_common, mut got_other, mut sent_ready) = (false, false, false);
+    loop {
+        match read_line::<Error>(reader)? {
+            Line::Data(line) => {
+                let num_haves = request.haves.len();
+                request.parse_line(line.as_ref(), Protocol::V1)?;
+                if request.done {
+                    match common.last() {
+                        Some(last) if multi_ack => ack(last, None, &mut *out)?,
+                        Some(_) => {}
+                        None => {
+                            encode::text_to_write(b"NAK", &mut *out)?;
+                        }
+                    }
+                    break;
+                }
+                if let Some(have) = request.haves.get(num_haves).copied() {
+                    if delegate.contains(&have) {
+                        got_common = true;
+                        if !common.contains(&have) {
+                            common.push(have);
+                        }
+                        if multi_ack {
+                            ack(&have, Some("common"), &mut *out)?;
+                        } else if common.len() == 1 {
+                            ack(&have, None, &mut *out)?;
+                        }
+                    } else {
+                        got_other = true;
+                        if multi_ack && !common.is_empty() && delegate.is_ready(&request.wants, &common) {
+                            sent_ready = true;
+                            ack(&have, Some("ready"), &mut *out)?;
+                        }
+                    }
+                }
+            }
+            Line::Flush => {
+                if multi_ack && got_common && !got_other && delegate.is_ready(&request.wants, &common) {
+                    sent_ready = true;
+                    ack(common.last().expect("got common"), Some("ready"), &mut *out)?;
+                }
+                if common.is_empty() || multi_ack {
+                    encode::text_to_write(b"NAK", &mut *out)?;
+                }
+                if request.no_done && sent_ready {
+                    ack(common.last().expect("ready implies common"), None, &mut *out)?;
+                    break;
+                }
+                out.flush()?;
+                if options.stateless_rpc {
+                    return Ok(());
+                }
+                got_common = false;
+                got_other = false;
+            }
+            Line::Delimiter => return Err(Error::UnexpectedLine { line: "0001".into() }),
+            Line::Eof => return Ok(()),
+        }
+    }
+
+    let shallow = shallow_boundary(&request, shallow.as_ref());
+    send_pack(&request, &common, &shallow, request.side_band, delegate, out)
+}
+
+fn serve_v2(
+    reader: &mut StreamingPeekableIter<impl io::Read>,
+    out: &mut impl io::Write,
+    delegate: &mut impl Delegate,
+    options: &Options,
+) -> Result<(), Error> {
+    if !options.stateless_rpc || options.advertise_refs {
+        advertise_v2(options, &mut *out)?;
+        out.flush()?;
+    }
+    if options.advertise_refs {
+        return Ok(());
+    }
+
+    let mut refs = None;
+    loop {
+        let command = match read_line::<Error>(reader)? {
+            Line::Data(line) => match line.trim_end_with(|c| c == '\n').strip_prefix(b"command=") {
+                Some(name) => BString::from(name),
+                None => return Err(Error::UnexpectedLine { line }),
+            },
+            Line::Flush | Line::Eof => return Ok(()),
+            Line::Delimiter => return Err(Error::UnexpectedLine { line: "0001".into() }),
+        };
+        let mut arguments = Vec::new();
+        let mut in_arguments = false;
+        loop {
+            match read_line::<Error>(reader)? {
+                Line::Data(line) if in_arguments => arguments.push(line),
+                Line::Data(line) => {
+                    let line = line.trim_end_with(|c| c == '\n');
+                    if let Some(format) = line.strip_prefix(b"object-format=") {
+                        if format != object_format(options.object_hash).as_bytes() {
+                            return Err(Error::IncompatibleObjectHash {
+                                client: format.into(),
+                                server: options.object_hash,
+                            });
+                        }
+                    }
+                }
+                Line::Delimiter if !in_arguments => in_arguments = true,
+                Line::Delimiter => return Err(Error::UnexpectedLine { line: "0001".into() }),
+                Line::Flush => break,
+                Line::Eof => return Ok(()),
+            }
+        }
+
+        if refs.is_none() {
+            refs = Some(delegate.refs().map_err(Error::Delegate)?);
+        }
+        let refs = refs.as_deref().expect("set");
+        match command.as_bytes() {
+            b"ls-refs" => ls_refs(refs, &arguments, &mut *out)?,
+            b"fetch" => fetch(refs, &arguments, delegate, &mut *out)?,
+            _ => return Err(Error::UnknownCommand { name: command }),
+        }
+        out.flush()?;
+        if options.stateless_rpc {
+            return Ok(());
+        }
+    }
+}
+
+fn ls_refs(refs: &[Ref], arguments: &[BString], mut out: impl io::Write) -> Result<(), Error> {
+    let (mut symrefs, mut peel, mut unborn) = (false, false, false);
+    let mut prefixes = Vec::new();
+    for argument in arguments {
+        let argument = argument.trim_end_with(|c| c == '\n');
+        match argument.as_bytes() {
+            b"symrefs" => symrefs = true,
+            b"peel" => peel = true,
+            b"unborn" => unborn = true,
+            _ => match argument.strip_prefix(b"ref-prefix ") {
+                Some(prefix) => prefixes.push(prefix.as_bstr()),
+                None => return Err(super::request::Error::UnknownArgument { line: argument.into() }.into()),
+            },
+        }
+    }
+
+    for r in refs {
+        let (name, _, _) = r.unpack();
+        if !prefixes.is_empty() && !prefixes.iter().any(|prefix| name.starts_with(prefix)) {
+            continue;
+        }
+        let line = match r {
+            Ref::Direct { full_ref_name, object } => format_ref(object.to_string(), full_ref_name),
+            Ref::Peeled {
+                full_ref_name,
+                tag,
+                object,
+            } => {
+                let mut line = format_ref(tag.to_string(), full_ref_name);
+                if peel {
+                    line.push_str(format!(" peeled:{}", object));
+                }
+                line
+            }
+            Ref::Symbolic {
+                full_ref_name,
+                target,
+                object,
+            } => {
+                let mut line = format_ref(object.to_string(), full_ref_name);
+                if symrefs {
+                    line.push_str(" symref-target:");
+                    line.push_str(target);
+                }
+                line
+            }
+            Ref::Unborn { full_ref_name, target } => {
+                if !unborn {
+                    continue;
+                }
+                let mut line = format_ref("unborn".into(), full_ref_name);
+                if symrefs {
+                    line.push_str(" symref-target:");
+                    line.push_str(target);
+                }
+                line
+            }
+        };
+        encode::text_to_write(&line, &mut out)?;
+    }
+    encode::flush_to_write(&mut out)?;
+    Ok(())
+}
+
+fn fetch(
+    refs: &[Ref],
+    arguments: &[BString],
+    delegate: &mut impl Delegate,
+    out: &mut impl io::Write,
+) -> Result<(), Error> {
+    let mut request = Request::default();
+    for argument in arguments {
+        request.parse_line(argument.as_ref(), Protocol::V2)?;
+    }
+    check_wants(refs, &request.wants)?;
+    request.side_band = Some(SideBand::Large);
+
+    let mut common = Vec::<git_hash::ObjectId>::new();
+    for have in &request.haves {
+        if !common.contains(have) && delegate.contains(have) {
+            common.push(*have);
+        }
+    }
+    if !request.done {
+        let ready = !request.wait_for_done
+            && !request.wants.is_empty()
+            && !common.is_empty()
+            && delegate.is_ready(&request.wants, &common);
+        encode::text_to_write(b"acknowledgments", &mut *out)?;
+        if common.is_empty() {
+            encode::text_to_write(b"NAK", &mut *out)?;
+        }
+        for id in &common {
+            ack(id, None, &mut *out)?;
+        }
+        if !ready {
+            encode::flush_to_write(&mut *out)?;
+            return Ok(());
+        }
+        encode::text_to_write(b"ready", &mut *out)?;
+        encode::delim_to_write(&mut *out)?;
+    }
+
+    let shallow = if request.is_deepening() || !request.shallow.is_empty() {
+        let shallow = delegate.shallow(&request).map_err(Error::Delegate)?;
+        encode::text_to_write(b"shallow-info", &mut *out)?;
+        write_shallow(&shallow, &mut *out)?;
+        encode::delim_to_write(&mut *out)?;
+        Some(shallow)
+    } else {
+        None
+    };
+    encode::text_to_write(b"packfile", &mut *out)?;
+    let shallow = shallow_boundary(&request, shallow.as_ref());
+    send_pack(&request, &common, &shallow, request.side_band, delegate, out)
+}
+
+fn advertise_v1(refs: &[Ref], options: &Options, mut out: impl io::Write) -> Result<(), Error> {
+    let mut capabilities: Vec<BString> = [
+        "multi_ack_detailed",
+        "no-done",
+        "thin-pack",
+        "side-band",
+        "side-band-64k",
+        "ofs-delta",
+        "shallow",
+        "deepen-since",
+        "deepen-not",
+        "deepen-relative",
+        "no-progress",
+        "include-tag",
+        "filter",
+    ]
+    .iter()
+    .map(|c| (*c).into())
+    .collect();
+    for r in refs {
+        if let Ref::Symbolic {
+            full_ref_name, target, ..
+        } = r
+        {
+            let mut symref = BString::from("symref=");
+            symref.push_str(full_ref_name);
+            symref.push(b':');
+            symref.push_str(target);
+            capabilities.push(symref);
+        }
+    }
+    capabilities.push(format!("object-format={}", object_format(options.object_hash)).into());
+    capabilities.push(format!("agent={}", crate::agent(options.agent.clone())).into());
+
+    let mut lines = Vec::new();
+    for r in refs {
+        match r {
+            Ref::Direct { full_ref_name, object }
+            | Ref::Symbolic {
+                full_ref_name, object, ..
+            } => lines.push(format_ref(object.to_string(), full_ref_name)),
+            Ref::Peeled {
+                full_ref_name,
+                tag,
+                object,
+            } => {
+                lines.push(format_ref(tag.to_string(), full_ref_name));
+                let mut line = format_ref(object.to_string(), full_ref_name);
+                line.push_str("^{}");
+                lines.push(line);
+            }
+            Ref::Unborn { .. } => {}
+        }
+    }
+    if lines.is_empty() {
+        // Empty repositories advertise a placeholder to be able to transmit their capabilities.
+        lines.push(format_ref(
+            git_hash::ObjectId::null(options.object_hash).to_string(),
+            b"capabilities^{}",
+        ));
+    }
+    lines[0].push(0);
+    lines[0].push_str(bstr::join(" ", &capabilities));
+    for line in lines {
+        encode::text_to_write(&line, &mut out)?;
+    }
+    encode::flush_to_write(&mut out)?;
+    Ok(())
+}
+
+fn advertise_v2(options: &Options, mut out: impl io::Write) -> Result<(), Error> {
+    for line in [
+        "version 2".to_owned(),
+        format!("agent={}", crate::agent(options.agent.clone())),
+        "ls-refs=unborn".into(),
+        "fetch=shallow filter wait-for-done".into(),
+        "server-option".into(),
+        format!("object-format={}", object_format(options.object_hash)),
+    ] {
+        encode::text_to_write(line.as_bytes(), &mut out)?;
+    }
+    encode::flush_to_write(&mut out)?;
+    Ok(())
+}
+
+fn send_pack(
+    request: &Request,
+    common: &[git_hash::ObjectId],
+    shallow: &[git_hash::ObjectId],
+    side_band: Option<SideBand>,
+    delegate: &mut impl Delegate,
+    out: &mut impl io::Write,
+) -> Result<(), Error> {
+    match side_band {
+        Some(side_band) => {
+            let out = RefCell::new(out);
+            let mut data = Band {
+                out: &out,
+                channel: Channel::Data,
+                max_data_len: side_band.max_data_len(),
+            };
+            let mut progress = Band {
+                out: &out,
+                channel: Channel::Progress,
+                max_data_len: side_band.max_data_len(),
+            };
+            let mut sink = io::sink();
+            let progress: &mut dyn io::Write = if request.no_progress { &mut sink } else { &mut progress };
+            let res = delegate.write_pack(request, common, shallow, progress, &mut data);
+            let mut out = out.borrow_mut();
+            if let Err(err) = res {
+                encode::band_to_write(Channel::Error, err.to_string().as_bytes(), &mut **out).ok();
+                return Err(Error::Delegate(err));
+            }
+            encode::flush_to_write(&mut **out)?;
+        }
+        None => delegate
+            .write_pack(request, common, shallow, &mut io::sink(), out)
+            .map_err(Error::Delegate)?,
+    }
+    Ok(())
+}
+
+/// Write everything into the side-band `channel`, using as many packet lines as needed.
+pub(crate) struct Band<'a, W> {
+    pub(crate) out: &'a RefCell<W>,
+    pub(crate) channel: Channel,
+    pub(crate) max_data_len: usize,
+}
+
+impl<'a, W: io::Write> io::Write for Band<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut out = self.out.borrow_mut();
+        for chunk in buf.chunks(self.max_data_len) {
+            encode::band_to_write(self.channel, chunk, &mut *out)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.borrow_mut().flush()
+    }
+}
+
+fn check_wants(refs: &[Ref], wants: &[git_hash::ObjectId]) -> Result<(), Error> {
+    for want in wants {
+        let is_advertised = refs.iter().any(|r| {
+            let (_name, target, peeled) = r.unpack();
+            target == Some(want.as_ref()) || peeled == Some(want.as_ref())
+        });
+        if !is_advertised {
+            return Err(Error::UnadvertisedWant { id: *want });
+        }
+    }
+    Ok(())
+}
+
+/// Return the shallow boundary of the client after receiving the pack.
+fn shallow_boundary(request: &Request, shallow: Option<&Shallow>) -> Vec<git_hash::ObjectId> {
+    let mut out: Vec<_> = request
+        .shallow
+        .iter()
+        .filter(|id| shallow.map_or(true, |s| !s.unshallow.contains(id)))
+        .copied()
+        .collect();
+    for id in shallow.into_iter().flat_map(|s| &s.shallow) {
+        if !out.contains(id) {
+            out.push(*id);
+        }
+    }
+    out
+}
+
+fn write_shallow(shallow: &Shallow, mut out: impl io::Write) -> io::Result<()> {
+    for id in &shallow.shallow {
+        encode::text_to_write(format!("shallow {}", id).as_bytes(), &mut out)?;
+    }
+    for id in &shallow.unshallow {
+        encode::text_to_write(format!("unshallow {}", id).as_bytes(), &mut out)?;
+    }
+    Ok(())
+}
+
+fn ack(id: &git_hash::oid, status: Option<&str>, out: impl io::Write) -> io::Result<()> {
+    let line = match status {
+        Some(status) => format!("ACK {} {}", id, status),
+        None => format!("ACK {}", id),
+    };
+    encode::text_to_write(line.as_bytes(), out).map(|_| ())
+}
+
+pub(crate) fn format_ref(id: String, name: &[u8]) -> BString {
+    let mut line = BString::from(id);
+    line.push(b' ');
+    line.push_str(name);
+    line
+}
+
+pub(crate) fn object_format(kind: git_hash::Kind) -> &'static str {
+    match kind {
+        git_hash::Kind::Sha1 => "sha1",
+        git_hash::Kind::Sha256 => "sha256",
+    }
+}
@@ -0,0 +1,116 @@
+use std::io;
+
+use git_transport::Protocol;
+
+use crate::handshake::Ref;
+
+///
+pub mod request;
+pub use request::Request;
+
+/// Options to configure how [`upload_pack()`][crate::upload_pack()] serves a client.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The protocol version to speak, usually derived from the `GIT_PROTOCOL` environment variable set by the transport.
+    pub version: Protocol,
+    /// The kind of object ids used by the repository we serve.
+    pub object_hash: git_hash::Kind,
+    /// If true, the client connects anew for each request as it does over HTTP, which is why only a single request is handled
+    /// and no capabilities or references are advertised unless `advertise_refs` is set.
+    pub stateless_rpc: bool,
+    /// If true, only advertise capabilities, along with references in V1, and return without reading anything.
+    pub advertise_refs: bool,
+    /// The name of this server as presented to clients, like `"my-app (v2.0)"`.
+    pub agent: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            version: Protocol::V2,
+            object_hash: git_hash::Kind::Sha1,
+            stateless_rpc: false,
+            advertise_refs: false,
+            agent: concat!("oxide-", env!("CARGO_PKG_VERSION")).into(),
+        }
+    }
+}
+
+/// The changes to the shallow boundary of a client, as computed by [`Delegate::shallow()`].
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Shallow {
+    /// Commits which are sent, but whose parents are not, and which thus become shallow on the client.
+    pub shallow: Vec<git_hash::ObjectId>,
+    /// Commits which were shallow on the client, but whose parents are sent now.
+    pub unshallow: Vec<git_hash::ObjectId>,
+}
+
+/// The error returned by the methods of a [`Delegate`].
+pub type DelegateError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// The part of the `upload-pack` server that has access to a repository.
+pub trait Delegate {
+    /// Return all references to advertise to the client, in the order in which they should be advertised.
+    ///
+    /// Objects the client wants must be one of the objects these point to.
+    fn refs(&mut self) -> Result<Vec<Ref>, DelegateError>;
+
+    /// Return true if the object with `id` exists in the repository.
+    fn contains(&mut self, id: &git_hash::oid) -> bool;
+
+    /// Return true if every object in `wants` is reachable from at least one of the `common` commits that both sides have,
+    /// which allows to stop the negotiation early.
+    ///
+    /// Returning `false` is always correct, but causes the client to send all of its commits until it gives up by itself.
+    fn is_ready(&mut self, _wants: &[git_hash::ObjectId], _common: &[git_hash::ObjectId]) -> bool {
+        false
+    }
+
+    /// Compute how the shallow boundary of the client changes with `request`, which is only called if the client asks
+    /// to deepen its history, or if it is shallow already.
+    fn shallow(&mut self, request: &Request) -> Result<Shallow, DelegateError>;
+
+    /// Write a pack containing all objects reachable from the `wants` of `request` to `out`, leaving out all objects reachable
+    /// from `common`, along with the parents of the commits in `shallow` and their history.
+    ///
+    /// `progress` receives human-readable progress messages for the client, like `"Counting objects: 5\r"`.
+    fn write_pack(
+        &mut self,
+        request: &Request,
+        common: &[git_hash::ObjectId],
+        shallow: &[git_hash::ObjectId],
+        progress: &mut dyn io::Write,
+        out: &mut dyn io::Write,
+    ) -> Result<(), DelegateError>;
+}
+
+mod error {
+    use bstr::BString;
+
+    /// The error returned by [upload_pack()][crate::upload_pack()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error(transparent)]
+        Io(#[from] std::io::Error),
+        #[error(transparent)]
+        PacketlineDecode(#[from] git_transport::packetline::decode::Error),
+        #[error(transparent)]
+        Request(#[from] super::request::Error),
+        #[error("Expected a text line, got {line:?}")]
+        UnexpectedLine { line: BString },
+        #[error("The command {name:?} is unknown")]
+        UnknownCommand { name: BString },
+        #[error("The client wants {id} which isn't the tip of an advertised reference")]
+        UnadvertisedWant { id: git_hash::ObjectId },
+        #[error("The client announced {client} object ids, but the repository uses {server}")]
+        IncompatibleObjectHash { client: BString, server: git_hash::Kind },
+        #[error(transparent)]
+        Delegate(super::DelegateError),
+    }
+}
+pub use error::Error;
+
+#[cfg(feature = "blocking-client")]
+pub(crate) mod function;
@@ -0,0 +1,170 @@
+use bstr::{BStr, BString, ByteSlice};
+use git_transport::Protocol;
+
+/// The error returned by [`Request::parse_line()`].
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("Could not parse the argument line {line:?}")]
+    UnknownArgument { line: BString },
+    #[error("Could not decode the object id in line {line:?}")]
+    DecodeObjectId {
+        line: BString,
+        source: git_hash::decode::Error,
+    },
+    #[error("Could not parse the number in line {line:?}")]
+    InvalidNumber { line: BString },
+    #[error("The argument {name:?} is not supported by this server")]
+    Unsupported { name: &'static str },
+    #[error("'deepen' cannot be combined with 'deepen-since' or 'deepen-not'")]
+    ConflictingDeepen,
+}
+
+/// The side-band mode requested by a client to multiplex pack data and progress.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub enum SideBand {
+    /// Use packet lines with up to 1000 bytes of data.
+    Basic,
+    /// Use packet lines with up to 65520 bytes of data, which is what all protocol V2 clients use.
+    Large,
+}
+
+impl SideBand {
+    /// The maximum amount of bytes to send in a single packet line, excluding the length prefix and the band.
+    pub fn max_data_len(&self) -> usize {
+        match self {
+            SideBand::Basic => 1000 - 4 - 1,
+            SideBand::Large => 65520 - 4 - 1,
+        }
+    }
+}
+
+/// Everything a client sends to describe the pack it wants, accumulated over all negotiation rounds.
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct Request {
+    /// The objects the client wants, typically the tips of references.
+    pub wants: Vec<git_hash::ObjectId>,
+    /// The objects the client has, in the order they were sent.
+    pub haves: Vec<git_hash::ObjectId>,
+    /// The commits at which the history of the client is currently cut off if it is a shallow repository.
+    pub shallow: Vec<git_hash::ObjectId>,
+    /// The amount of commits to receive from each tip, if set.
+    pub depth: Option<u32>,
+    /// If set, commits older than this time in seconds since the unix epoch shouldn't be sent.
+    pub deepen_since: Option<u32>,
+    /// The names of references whose history shouldn't be sent.
+    pub deepen_not: Vec<BString>,
+    /// If true, `depth` is relative to the current `shallow` boundary of the client.
+    pub deepen_relative: bool,
+    /// A filter specification like `blob:none` for creating partial clones.
+    pub filter: Option<BString>,
+    /// If true, the client doesn't want to negotiate any further.
+    pub done: bool,
+    /// If true, the client can deal with packs that refer to objects it already has.
+    pub thin_pack: bool,
+    /// If true, the client can handle deltas referring to their base by offset.
+    pub ofs_delta: bool,
+    /// If true, the client doesn't want to receive progress information.
+    pub no_progress: bool,
+    /// If true, annotated tags pointing to objects in the pack should be sent as well.
+    pub include_tag: bool,
+    /// The side-band mode to use for sending the pack, or `None` to send it as is.
+    pub side_band: Option<SideBand>,
+    /// If true, the client understands `ACK <id> common` and `ACK <id> ready` during the V1 negotiation.
+    pub multi_ack_detailed: bool,
+    /// If true, the client doesn't send `done` once the server signalled it is ready, in V1.
+    pub no_done: bool,
+    /// If true, the client sent `wait-for-done` and wants the server to keep negotiating until it sends `done`, in V2.
+    pub wait_for_done: bool,
+    /// The name of the client, as sent with the `agent` capability.
+    pub agent: Option<BString>,
+}
+
+impl Request {
+    /// Return true if the client asked to change the depth of its history.
+    pub fn is_deepening(&self) -> bool {
+        self.depth.is_some() || self.deepen_since.is_some() || !self.deepen_not.is_empty()
+    }
+
+    /// Parse a single argument `line` as sent by a client speaking protocol `version` and update `self` accordingly.
+    ///
+    /// In V1, the first `want` line may be followed by the space-separated capabilities of the client, which are parsed as well.
+    /// Note that the `shallow`, `deepen*` and `filter` arguments aren't validated beyond their syntax.
+    pub fn parse_line(&mut self, line: &BStr, version: Protocol) -> Result<(), Error> {
+        let line = line.trim_end_with(|c| c == '\n').as_bstr();
+        let (name, value) = match line.find_byte(b' ') {
+            Some(pos) => (line[..pos].as_bstr(), Some(line[pos + 1..].as_bstr())),
+            None => (line, None),
+        };
+        match (name.as_bytes(), value) {
+            (b"want", Some(value)) => {
+                let value = match version {
+                    Protocol::V1 => {
+                        let mut tokens = value.split_str(b" ");
+                        let id = tokens.next().expect("at least one token").as_bstr();
+                        for capability in tokens {
+                            self.parse_v1_capability(capability.as_bstr());
+                        }
+                        id
+                    }
+                    Protocol::V2 => value,
+                };
+                self.wants.push(decode_id(value, line)?);
+            }
+            (b"have", Some(value)) => self.haves.push(decode_id(value, line)?),
+            (b"shallow", Some(value)) => self.shallow.push(decode_id(value, line)?),
+            (b"deepen", Some(value)) => self.depth = Some(decode_number(value, line)?),
+            (b"deepen-since", Some(value)) => self.deepen_since = Some(decode_number(value, line)?),
+            (b"deepen-not", Some(value)) => self.deepen_not.push(value.to_owned()),
+            (b"deepen-relative", None) => self.deepen_relative = true,
+            (b"filter", Some(value)) => self.filter = Some(value.to_owned()),
+            (b"done", None) => self.done = true,
+            (b"thin-pack", None) if version == Protocol::V2 => self.thin_pack = true,
+            (b"ofs-delta", None) if version == Protocol::V2 => self.ofs_delta = true,
+            (b"no-progress", None) if version == Protocol::V2 => self.no_progress = true,
+            (b"include-tag", None) if version == Protocol::V2 => self.include_tag = true,
+            (b"wait-for-done", None) if version == Protocol::V2 => self.wait_for_done = true,
+            (b"sideband-all", None) if version == Protocol::V2 => {}
+            (b"packfile-uris", Some(_)) if version == Protocol::V2 => {}
+            (b"want-ref", Some(_)) if version == Protocol::V2 => return Err(Error::Unsupported { name: "want-ref" }),
+            _ => return Err(Error::UnknownArgument { line: line.to_owned() }),
+        }
+        if self.depth.is_some() && (self.deepen_since.is_some() || !self.deepen_not.is_empty()) {
+            return Err(Error::ConflictingDeepen);
+        }
+        Ok(())
+    }
+
+    /// Unknown capabilities are ignored, just like `git` does.
+    fn parse_v1_capability(&mut self, capability: &BStr) {
+        match capability.as_bytes() {
+            b"multi_ack_detailed" => self.multi_ack_detailed = true,
+            b"no-done" => self.no_done = true,
+            b"side-band" => self.side_band = self.side_band.or(Some(SideBand::Basic)),
+            b"side-band-64k" => self.side_band = Some(SideBand::Large),
+            b"thin-pack" => self.thin_pack = true,
+            b"ofs-delta" => self.ofs_delta = true,
+            b"no-progress" => self.no_progress = true,
+            b"include-tag" => self.include_tag = true,
+            b"deepen-relative" => self.deepen_relative = true,
+            _ => {
+                if let Some(agent) = capability.strip_prefix(b"agent=") {
+                    self.agent = Some(agent.into());
+                }
+            }
+        }
+    }
+}
+
+fn decode_id(value: &BStr, line: &BStr) -> Result<git_hash::ObjectId, Error> {
+    git_hash::ObjectId::from_hex(value).map_err(|err| Error::DecodeObjectId {
+        line: line.to_owned(),
+        source: err,
+    })
+}
+
+fn decode_number(value: &BStr, line: &BStr) -> Result<u32, Error> {
+    btoi::btou(value).map_err(|_| Error::InvalidNumber { line: line.to_owned() })
+}
@@ -6,4 +6,7 @@ pub fn fixture_bytes(path: &str) -> Vec<u8> {
 }
 
 mod fetch;
+mod push;
+mod receive_pack;
 mod remote_progress;
+mod upload_pack;
@@ -29,7 +29,7 @@ mod v1 {
         async fn clone() -> crate::Result {
             let mut provider = mock_reader("v1/clone-only.response");
             let mut reader = provider.as_read_without_sidebands();
-            let r = fetch::Response::from_line_reader(Protocol::V1, &mut reader).await?;
+            let r = fetch::Response::from_line_reader(Protocol::V1, true, &mut reader).await?;
             assert_eq!(r.acknowledgements(), &[Acknowledgement::Nak]);
             assert!(r.has_pack());
             let mut buf = Vec::new();
@@ -42,7 +42,7 @@ mod v1 {
         async fn shallow_clone() -> crate::Result {
             let mut provider = mock_reader("v1/clone-deepen-1.response");
             let mut reader = provider.as_read_without_sidebands();
-            let r = fetch::Response::from_line_reader(Protocol::V1, &mut reader).await?;
+            let r = fetch::Response::from_line_reader(Protocol::V1, true, &mut reader).await?;
             assert_eq!(
                 r.shallow_updates(),
                 &[ShallowUpdate::Shallow(id("808e50d724f604f69ab93c6da2919c014667bedb"))]
@@ -59,7 +59,7 @@ mod v1 {
         async fn empty_shallow_clone_due_to_depth_being_too_high() -> crate::Result {
             let mut provider = mock_reader("v1/clone-deepen-5.response");
             let mut reader = provider.as_read_without_sidebands();
-            let r = fetch::Response::from_line_reader(Protocol::V1, &mut reader).await?;
+            let r = fetch::Response::from_line_reader(Protocol::V1, true, &mut reader).await?;
             assert!(r.shallow_updates().is_empty());
             assert_eq!(r.acknowledgements(), &[Acknowledgement::Nak]);
             assert!(r.has_pack());
@@ -72,7 +72,8 @@ mod v1 {
         #[maybe_async::test(feature = "blocking-client", async(feature = "async-client", async_std::test))]
         async fn fetch_acks_without_pack() -> crate::Result {
             let mut provider = mock_reader("v1/fetch-no-pack.response");
-            let r = fetch::Response::from_line_reader(Protocol::V1, &mut provider.as_read_without_sidebands()).await?;
+            let r = fetch::Response::from_line_reader(Protocol::V1, false, &mut provider.as_read_without_sidebands())
+                .await?;
             assert_eq!(
                 r.acknowledgements(),
                 &[
@@ -84,11 +85,73 @@ mod v1 {
             Ok(())
         }
 
+        #[maybe_async::test(feature = "blocking-client", async(feature = "async-client", async_std::test))]
+        async fn multi_round_negotiation_stops_at_each_nak() -> crate::Result {
+            let mut provider = git_packetline::StreamingPeekableIter::new(
+                crate::fetch::Cursor::new(
+                    b"0038ACK 47ee0b7fe4f3a7d776c78794873e6467e1c47e59 common\n0008NAK\n\
+                      0037ACK 3f02c0ad360d96e8dbba92f97b42ebbaa4319db1 ready\n0008NAK\n"
+                        .to_vec(),
+                ),
+                &[git_packetline::PacketLineRef::Flush],
+            );
+            let r = fetch::Response::from_line_reader(Protocol::V1, false, &mut provider.as_read_without_sidebands())
+                .await?;
+            assert_eq!(
+                r.acknowledgements(),
+                &[
+                    Acknowledgement::Common(id("47ee0b7fe4f3a7d776c78794873e6467e1c47e59")),
+                    Acknowledgement::Nak,
+                ],
+                "the response of the second round isn't consumed"
+            );
+            assert!(!r.has_pack());
+            let r = fetch::Response::from_line_reader(Protocol::V1, false, &mut provider.as_read_without_sidebands())
+                .await?;
+            assert_eq!(r.acknowledgements(), &[Acknowledgement::Ready, Acknowledgement::Nak]);
+            Ok(())
+        }
+
+        #[maybe_async::test(feature = "blocking-client", async(feature = "async-client", async_std::test))]
+        async fn shallow_updates_without_trailing_newline_when_deepening() -> crate::Result {
+            let mut provider = git_packetline::StreamingPeekableIter::new(
+                crate::fetch::Cursor::new(
+                    b"0034shallow dfd0954dabef3b64f458321ef15571cc1a46d552\
+                      0034shallow 2d9d136fb0765f2e24c44a0f91984318d580d03b\
+                      0036unshallow f99771fe6a1b535783af3163eba95a927aae21d5\
+                      0000\
+                      0038ACK 47ee0b7fe4f3a7d776c78794873e6467e1c47e59 common\n0008NAK\n"
+                        .to_vec(),
+                ),
+                &[git_packetline::PacketLineRef::Flush],
+            );
+            let r = fetch::Response::from_line_reader(Protocol::V1, false, &mut provider.as_read_without_sidebands())
+                .await?;
+            assert_eq!(
+                r.shallow_updates(),
+                &[
+                    ShallowUpdate::Shallow(id("dfd0954dabef3b64f458321ef15571cc1a46d552")),
+                    ShallowUpdate::Shallow(id("2d9d136fb0765f2e24c44a0f91984318d580d03b")),
+                    ShallowUpdate::Unshallow(id("f99771fe6a1b535783af3163eba95a927aae21d5")),
+                ],
+                "each packet line is its own update even without newline"
+            );
+            assert_eq!(
+                r.acknowledgements(),
+                &[
+                    Acknowledgement::Common(id("47ee0b7fe4f3a7d776c78794873e6467e1c47e59")),
+                    Acknowledgement::Nak,
+                ]
+            );
+            assert!(!r.has_pack());
+            Ok(())
+        }
+
         #[maybe_async::test(feature = "blocking-client", async(feature = "async-client", async_std::test))]
         async fn fetch_acks_and_pack() -> crate::Result {
             let mut provider = mock_reader("v1/fetch.response");
             let mut reader = provider.as_read_without_sidebands();
-            let r = fetch::Response::from_line_reader(Protocol::V1, &mut reader).await?;
+            let r = fetch::Response::from_line_reader(Protocol::V1, true, &mut reader).await?;
             assert_eq!(
                 r.acknowledgements(),
                 &[
@@ -131,7 +194,7 @@ mod v2 {
                 );
                 let mut provider = mock_reader(&fixture);
                 let mut reader = provider.as_read_without_sidebands();
-                let r = fetch::Response::from_line_reader(Protocol::V2, &mut reader).await?;
+                let r = fetch::Response::from_line_reader(Protocol::V2, true, &mut reader).await?;
                 assert!(r.acknowledgements().is_empty(), "it should go straight to the packfile");
                 assert!(r.has_pack());
                 reader.set_progress_handler(Some(Box::new(|_is_err, _text| ())));
@@ -146,7 +209,7 @@ mod v2 {
         async fn shallow_clone() -> crate::Result {
             let mut provider = mock_reader("v2/clone-deepen-1.response");
             let mut reader = provider.as_read_without_sidebands();
-            let r = fetch::Response::from_line_reader(Protocol::V2, &mut reader).await?;
+            let r = fetch::Response::from_line_reader(Protocol::V2, true, &mut reader).await?;
             assert!(r.acknowledgements().is_empty(), "it should go straight to the packfile");
             assert_eq!(
                 r.shallow_updates(),
@@ -159,11 +222,36 @@ mod v2 {
             Ok(())
         }
 
+        #[maybe_async::test(feature = "blocking-client", async(feature = "async-client", async_std::test))]
+        async fn shallow_info_without_trailing_newline() -> crate::Result {
+            let mut provider = git_packetline::StreamingPeekableIter::new(
+                crate::fetch::Cursor::new(
+                    b"0011shallow-info\n\
+                      0034shallow dfd0954dabef3b64f458321ef15571cc1a46d552\
+                      0036unshallow f99771fe6a1b535783af3163eba95a927aae21d5\
+                      0001000dpackfile\n"
+                        .to_vec(),
+                ),
+                &[git_packetline::PacketLineRef::Flush],
+            );
+            let mut reader = provider.as_read_without_sidebands();
+            let r = fetch::Response::from_line_reader(Protocol::V2, true, &mut reader).await?;
+            assert_eq!(
+                r.shallow_updates(),
+                &[
+                    ShallowUpdate::Shallow(id("dfd0954dabef3b64f458321ef15571cc1a46d552")),
+                    ShallowUpdate::Unshallow(id("f99771fe6a1b535783af3163eba95a927aae21d5")),
+                ]
+            );
+            assert!(r.has_pack());
+            Ok(())
+        }
+
         #[maybe_async::test(feature = "blocking-client", async(feature = "async-client", async_std::test))]
         async fn empty_shallow_clone() -> crate::Result {
             let mut provider = mock_reader("v2/clone-deepen-5.response");
             let mut reader = provider.as_read_without_sidebands();
-            let r = fetch::Response::from_line_reader(Protocol::V2, &mut reader).await?;
+            let r = fetch::Response::from_line_reader(Protocol::V2, true, &mut reader).await?;
             assert!(r.acknowledgements().is_empty(), "it should go straight to the packfile");
             assert!(r.shallow_updates().is_empty(), "it should go straight to the packfile");
             assert!(r.has_pack());
@@ -177,7 +265,7 @@ mod v2 {
         async fn clone_with_sidebands() -> crate::Result {
             let mut provider = mock_reader("v2/clone-only-2.response");
             let mut reader = provider.as_read_without_sidebands();
-            let r = fetch::Response::from_line_reader(Protocol::V2, &mut reader).await?;
+            let r = fetch::Response::from_line_reader(Protocol::V2, true, &mut reader).await?;
             assert!(r.acknowledgements().is_empty(), "it should go straight to the packfile");
             assert!(r.has_pack());
 
@@ -198,7 +286,8 @@ mod v2 {
         #[maybe_async::test(feature = "blocking-client", async(feature = "async-client", async_std::test))]
         async fn fetch_acks_without_pack() -> crate::Result {
             let mut provider = mock_reader("v2/fetch-no-pack.response");
-            let r = fetch::Response::from_line_reader(Protocol::V2, &mut provider.as_read_without_sidebands()).await?;
+            let r = fetch::Response::from_line_reader(Protocol::V2, true, &mut provider.as_read_without_sidebands())
+                .await?;
             assert_eq!(r.acknowledgements(), &[Acknowledgement::Nak,]);
             Ok(())
         }
@@ -208,7 +297,7 @@ mod v2 {
             let mut provider = mock_reader("v2/fetch-err-line.response");
             provider.fail_on_err_lines(true);
             let mut sidebands = provider.as_read_without_sidebands();
-            match fetch::Response::from_line_reader(Protocol::V2, &mut sidebands).await {
+            match fetch::Response::from_line_reader(Protocol::V2, true, &mut sidebands).await {
                 Ok(_) => panic!("need error response"),
                 Err(err) => match err {
                     fetch::response::Error::UploadPack(err) => {
@@ -223,7 +312,7 @@ mod v2 {
         async fn fetch_acks_and_pack() -> crate::Result {
             let mut provider = mock_reader("v2/fetch.response");
             let mut reader = provider.as_read_without_sidebands();
-            let r = fetch::Response::from_line_reader(Protocol::V2, &mut reader).await?;
+            let r = fetch::Response::from_line_reader(Protocol::V2, true, &mut reader).await?;
             assert_eq!(
                 r.acknowledgements(),
                 &[
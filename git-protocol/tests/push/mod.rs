@@ -0,0 +1,130 @@
+mod response {
+    use bstr::ByteSlice;
+    use git_protocol::push::{response::RefStatus, Response};
+
+    fn parse(lines: &[&str]) -> Result<Response, git_protocol::push::response::Error> {
+        Response::from_lines(lines.iter().map(|line| line.as_bytes().as_bstr()))
+    }
+
+    fn status(name: &str, error: Option<&str>) -> RefStatus {
+        RefStatus {
+            ref_name: name.into(),
+            error: error.map(Into::into),
+            updated_ref_name: None,
+            old_id: None,
+            new_id: None,
+            forced_update: false,
+        }
+    }
+
+    #[test]
+    fn report_status_with_accepted_and_rejected_refs() -> crate::Result {
+        let res = parse(&[
+            "unpack ok\n",
+            "ok refs/heads/main\n",
+            "ng refs/heads/feature non-fast-forward\n",
+        ])?;
+        assert_eq!(
+            res,
+            Response {
+                unpack_error: None,
+                refs: vec![
+                    status("refs/heads/main", None),
+                    status("refs/heads/feature", Some("non-fast-forward")),
+                ]
+            }
+        );
+        assert!(!res.is_success());
+        Ok(())
+    }
+
+    #[test]
+    fn report_status_v2_options_apply_to_the_preceding_ref() -> crate::Result {
+        let res = parse(&[
+            "unpack ok",
+            "ok refs/for/main",
+            "option refname refs/heads/main",
+            "option old-oid 0000000000000000000000000000000000000000",
+            "option new-oid 3f7ee4f1e6a6b2d6a1f4e8d30c9d0b1dc5fa7e27",
+            "option forced-update",
+            "ok refs/heads/other",
+        ])?;
+        assert!(res.is_success());
+        let first = &res.refs[0];
+        assert_eq!(first.updated_ref_name.as_ref().expect("set"), "refs/heads/main");
+        assert_eq!(first.old_id, Some(git_hash::ObjectId::null(git_hash::Kind::Sha1)));
+        assert_eq!(
+            first.new_id,
+            Some(git_hash::ObjectId::from_hex(
+                b"3f7ee4f1e6a6b2d6a1f4e8d30c9d0b1dc5fa7e27"
+            )?)
+        );
+        assert!(first.forced_update);
+        assert_eq!(res.refs[1], status("refs/heads/other", None));
+        Ok(())
+    }
+
+    #[test]
+    fn unpack_failures_are_retained() -> crate::Result {
+        let res = parse(&["unpack index-pack abnormal exit", "ng refs/heads/main unpacker error"])?;
+        assert_eq!(res.unpack_error.as_ref().expect("set"), "index-pack abnormal exit");
+        assert!(!res.is_success());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_reports() {
+        assert!(matches!(
+            parse(&["ok refs/heads/main"]),
+            Err(git_protocol::push::response::Error::MissingUnpackStatus)
+        ));
+        assert!(matches!(
+            parse(&["unpack ok", "option forced-update"]),
+            Err(git_protocol::push::response::Error::OptionWithoutRefStatus { .. })
+        ));
+        assert!(matches!(
+            parse(&["unpack ok", "what refs/heads/main"]),
+            Err(git_protocol::push::response::Error::UnknownLineType { .. })
+        ));
+    }
+}
+
+#[maybe_async::test(feature = "blocking-client", async(feature = "async-client", async_std::test))]
+async fn push_sends_commands_and_pack_and_parses_the_report() -> crate::Result {
+    use bstr::ByteSlice;
+    use git_transport::{client::Capabilities, Protocol};
+
+    use crate::fetch::{oid, transport};
+
+    let mut out = Vec::new();
+    let (capabilities, _) = Capabilities::from_bytes(b"\0report-status delete-refs")?;
+    let commands = [git_protocol::push::Command {
+        old_id: git_hash::Kind::Sha1.null(),
+        new_id: oid("808e50d724f604f69ab93c6da2919c014667bedb"),
+        ref_name: "refs/heads/main".into(),
+    }];
+    let res = git_protocol::push(
+        transport(
+            &mut out,
+            "v1/push.response",
+            Protocol::V1,
+            git_transport::client::git::ConnectMode::Process,
+        ),
+        &capabilities,
+        &commands,
+        "agent",
+        |out| out.write_all(b"PACK"),
+    )
+    .await?;
+    assert!(res.is_success());
+    assert_eq!(res.refs.len(), 1);
+    assert_eq!(
+        out.as_bstr(),
+        b"0073\
+0000000000000000000000000000000000000000 808e50d724f604f69ab93c6da2919c014667bedb refs/heads/main\0report-status\
+0000PACK"
+            .as_bstr(),
+        "the pack follows the commands and their capabilities"
+    );
+    Ok(())
+}
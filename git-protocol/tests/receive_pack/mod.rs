@@ -0,0 +1,206 @@
+use std::io::BufRead;
+
+use bstr::ByteSlice;
+use git_protocol::{
+    handshake::Ref,
+    push::response::RefStatus,
+    receive_pack::{self, DelegateError, Request},
+};
+
+use crate::upload_pack::{oid, request, response};
+
+#[derive(Default)]
+struct Delegate {
+    refs: Vec<Ref>,
+    /// If set, storing the pack fails with this message.
+    unpack_error: Option<&'static str>,
+    pack: Option<Vec<u8>>,
+    request: Option<Request>,
+}
+
+impl receive_pack::Delegate for Delegate {
+    fn refs(&mut self) -> Result<Vec<Ref>, DelegateError> {
+        Ok(self.refs.clone())
+    }
+
+    fn store_pack(&mut self, pack: &mut dyn BufRead, progress: &mut dyn std::io::Write) -> Result<(), DelegateError> {
+        let mut buf = Vec::new();
+        pack.read_to_end(&mut buf)?;
+        self.pack = Some(buf);
+        progress.write_all(b"progress")?;
+        match self.unpack_error {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+
+    fn update_refs(&mut self, request: &Request, _progress: &mut dyn std::io::Write) -> Vec<RefStatus> {
+        self.request = Some(request.clone());
+        request
+            .commands
+            .iter()
+            .map(|command| {
+                let error = command
+                    .ref_name
+                    .starts_with(b"refs/tags/")
+                    .then_some("tags are immutable".into());
+                RefStatus::new(command.ref_name.as_ref(), error)
+            })
+            .collect()
+    }
+}
+
+/// Like `request()`, but followed by the raw `pack`.
+fn request_with_pack(lines: &[&str], pack: &[u8]) -> Vec<u8> {
+    let mut out = request(lines);
+    out.extend_from_slice(pack);
+    out
+}
+
+fn serve(
+    input: Vec<u8>,
+    delegate: &mut Delegate,
+) -> (Vec<String>, Result<git_protocol::push::Response, receive_pack::Error>) {
+    let mut out = Vec::new();
+    let res = git_protocol::receive_pack(
+        input.as_slice(),
+        &mut out,
+        delegate,
+        &receive_pack::Options {
+            agent: "test".into(),
+            ..Default::default()
+        },
+    );
+    (response(&out), res)
+}
+
+const CAPABILITIES: &str = "report-status report-status-v2 delete-refs side-band-64k quiet atomic ofs-delta push-options object-format=sha1 agent=git/test";
+
+#[test]
+fn empty_repositories_advertise_their_capabilities() -> crate::Result {
+    let mut delegate = Delegate::default();
+    let (lines, res) = serve(request(&["0000"]), &mut delegate);
+    assert!(res?.refs.is_empty());
+    assert_eq!(
+        lines,
+        [
+            format!("{} capabilities^{{}}\0{}", oid("0"), CAPABILITIES),
+            "0000".into()
+        ]
+    );
+    assert!(delegate.request.is_none(), "nothing was done without commands");
+    Ok(())
+}
+
+#[test]
+fn commands_and_pack_with_report_status() -> crate::Result {
+    let mut delegate = Delegate {
+        refs: vec![Ref::Direct {
+            full_ref_name: "refs/heads/main".into(),
+            object: oid("a"),
+        }],
+        ..Default::default()
+    };
+    let (a, b, null) = (oid("a"), oid("b"), oid("0"));
+    let (lines, res) = serve(
+        request_with_pack(
+            &[
+                &format!("{a} {b} refs/heads/main\0report-status agent=git/2.39"),
+                &format!("{null} {b} refs/tags/v1"),
+                "0000",
+            ],
+            b"PACK",
+        ),
+        &mut delegate,
+    );
+    let response = res?;
+    assert_eq!(
+        lines,
+        [
+            format!("{a} refs/heads/main\0{CAPABILITIES}"),
+            "0000".into(),
+            "unpack ok".into(),
+            "ok refs/heads/main".into(),
+            "ng refs/tags/v1 tags are immutable".into(),
+            "0000".into(),
+        ]
+    );
+    assert!(!response.is_success());
+    assert_eq!(delegate.pack.as_deref(), Some(&b"PACK"[..]));
+    let request = delegate.request.expect("refs were updated");
+    assert_eq!(request.commands.len(), 2);
+    assert_eq!(request.agent.as_ref().map(|a| a.as_bytes()), Some(&b"git/2.39"[..]));
+    assert!(request.report_status && !request.side_band && !request.atomic);
+    Ok(())
+}
+
+#[test]
+fn unpack_errors_reject_all_commands_and_are_sent_via_side_band() -> crate::Result {
+    let mut delegate = Delegate {
+        unpack_error: Some("corrupt pack"),
+        ..Default::default()
+    };
+    let (lines, res) = serve(
+        request_with_pack(
+            &[
+                &format!(
+                    "{} {} refs/heads/main\0report-status side-band-64k push-options",
+                    oid("0"),
+                    oid("b")
+                ),
+                "0000",
+                "ci.skip",
+                "0000",
+            ],
+            b"PACK",
+        ),
+        &mut delegate,
+    );
+    let response = res?;
+    assert_eq!(
+        response.unpack_error.as_ref().map(|e| e.as_bytes()),
+        Some(&b"corrupt pack"[..])
+    );
+    assert_eq!(
+        &lines[2..],
+        [
+            "\u{2}progress",
+            "\u{1}0018unpack corrupt pack\n0026ng refs/heads/main unpacker error\n0000",
+            "0000"
+        ]
+    );
+    assert_eq!(
+        delegate.pack.as_deref(),
+        Some(&b"PACK"[..]),
+        "push options are read before the pack"
+    );
+    assert!(delegate.request.is_none(), "refs are not updated if unpacking failed");
+    Ok(())
+}
+
+#[test]
+fn deletions_are_not_followed_by_a_pack() -> crate::Result {
+    let mut delegate = Delegate::default();
+    let (lines, res) = serve(
+        request(&[
+            &format!("{} {} refs/heads/main\0report-status-v2 quiet", oid("a"), oid("0")),
+            "0000",
+        ]),
+        &mut delegate,
+    );
+    assert!(res?.is_success());
+    assert_eq!(&lines[2..], ["unpack ok", "ok refs/heads/main", "0000"]);
+    assert!(delegate.pack.is_none());
+    Ok(())
+}
+
+#[test]
+fn malformed_commands_are_rejected() {
+    let mut delegate = Delegate::default();
+    let (lines, res) = serve(request(&["shallow 1234", "0000"]), &mut delegate);
+    assert!(matches!(res, Err(receive_pack::Error::Request(_))));
+    assert_eq!(
+        lines.last().map(String::as_str),
+        Some("ERR The argument \"shallow\" is not supported by this server")
+    );
+}
@@ -0,0 +1,409 @@
+use bstr::ByteSlice;
+use git_protocol::{
+    handshake::Ref,
+    transport::{
+        packetline::{encode, PacketLineRef, StreamingPeekableIter},
+        Protocol,
+    },
+    upload_pack::{self, DelegateError, Request, Shallow},
+};
+
+pub(crate) fn oid(hex: &str) -> git_hash::ObjectId {
+    git_hash::ObjectId::from_hex(hex.repeat(40).as_bytes()).expect("valid hex")
+}
+
+#[derive(Default)]
+struct Delegate {
+    /// The objects we have besides the ones advertised.
+    objects: Vec<git_hash::ObjectId>,
+    ready: bool,
+    pack_request: Option<(Request, Vec<git_hash::ObjectId>, Vec<git_hash::ObjectId>)>,
+}
+
+impl upload_pack::Delegate for Delegate {
+    fn refs(&mut self) -> Result<Vec<Ref>, DelegateError> {
+        Ok(vec![
+            Ref::Symbolic {
+                full_ref_name: "HEAD".into(),
+                target: "refs/heads/main".into(),
+                object: oid("a"),
+            },
+            Ref::Direct {
+                full_ref_name: "refs/heads/main".into(),
+                object: oid("a"),
+            },
+            Ref::Peeled {
+                full_ref_name: "refs/tags/v1".into(),
+                tag: oid("e"),
+                object: oid("a"),
+            },
+        ])
+    }
+
+    fn contains(&mut self, id: &git_hash::oid) -> bool {
+        self.objects.iter().any(|obj| obj.as_ref() == id)
+    }
+
+    fn is_ready(&mut self, _wants: &[git_hash::ObjectId], _common: &[git_hash::ObjectId]) -> bool {
+        self.ready
+    }
+
+    fn shallow(&mut self, _request: &Request) -> Result<Shallow, DelegateError> {
+        Ok(Shallow {
+            shallow: vec![oid("b")],
+            unshallow: vec![oid("c")],
+        })
+    }
+
+    fn write_pack(
+        &mut self,
+        request: &Request,
+        common: &[git_hash::ObjectId],
+        shallow: &[git_hash::ObjectId],
+        progress: &mut dyn std::io::Write,
+        out: &mut dyn std::io::Write,
+    ) -> Result<(), DelegateError> {
+        self.pack_request = Some((request.clone(), common.to_vec(), shallow.to_vec()));
+        progress.write_all(b"progress")?;
+        out.write_all(b"PACK")?;
+        Ok(())
+    }
+}
+
+/// Encode `lines` as packet lines, with `"0000"` and `"0001"` representing flush and delimiter packets.
+pub(crate) fn request(lines: &[&str]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for line in lines {
+        match *line {
+            "0000" => encode::flush_to_write(&mut out),
+            "0001" => encode::delim_to_write(&mut out),
+            line => encode::text_to_write(line.as_bytes(), &mut out),
+        }
+        .expect("in-memory writes don't fail");
+    }
+    out
+}
+
+/// Decode the response into lines, with `"0000"` and `"0001"` representing flush and delimiter packets.
+pub(crate) fn response(out: &[u8]) -> Vec<String> {
+    let mut reader = StreamingPeekableIter::new(out, &[]);
+    let mut lines = Vec::new();
+    while let Some(Ok(Ok(line))) = reader.read_line() {
+        lines.push(match line {
+            PacketLineRef::Data(data) => data.trim_end_with(|c| c == '\n').to_str_lossy().into_owned(),
+            PacketLineRef::Flush => "0000".into(),
+            PacketLineRef::Delimiter => "0001".into(),
+            PacketLineRef::ResponseEnd => "0002".into(),
+        });
+    }
+    lines
+}
+
+fn serve(version: Protocol, input: &[&str], delegate: &mut Delegate) -> (Vec<String>, Result<(), upload_pack::Error>) {
+    let mut out = Vec::new();
+    let res = git_protocol::upload_pack(
+        request(input).as_slice(),
+        &mut out,
+        delegate,
+        &upload_pack::Options {
+            version,
+            agent: "test".into(),
+            ..Default::default()
+        },
+    );
+    (response(&out), res)
+}
+
+mod v1 {
+    use git_protocol::transport::Protocol;
+
+    use super::{oid, serve, Delegate};
+
+    #[test]
+    fn advertisement_and_negotiation_with_multi_ack_detailed() -> crate::Result {
+        let mut delegate = Delegate {
+            objects: vec![oid("c")],
+            ..Default::default()
+        };
+        let want = format!(
+            "want {} multi_ack_detailed side-band-64k ofs-delta agent=git/2.39",
+            oid("a")
+        );
+        let (lines, res) = serve(
+            Protocol::V1,
+            &[
+                &want,
+                "0000",
+                &format!("have {}", oid("c")),
+                &format!("have {}", oid("d")),
+                "0000",
+                "done",
+            ],
+            &mut delegate,
+        );
+        res?;
+        let (a, c, e) = (oid("a"), oid("c"), oid("e"));
+        assert_eq!(
+            lines,
+            [
+                format!("{a} HEAD\0multi_ack_detailed no-done thin-pack side-band side-band-64k ofs-delta shallow deepen-since deepen-not deepen-relative no-progress include-tag filter symref=HEAD:refs/heads/main object-format=sha1 agent=git/test"),
+                format!("{a} refs/heads/main"),
+                format!("{e} refs/tags/v1"),
+                format!("{a} refs/tags/v1^{{}}"),
+                "0000".into(),
+                format!("ACK {c} common"),
+                "NAK".into(),
+                format!("ACK {c}"),
+                "\u{2}progress".into(),
+                "\u{1}PACK".into(),
+                "0000".into(),
+            ]
+        );
+        let (request, common, shallow) = delegate.pack_request.expect("pack was written");
+        assert_eq!(request.wants, [a]);
+        assert_eq!(request.haves, [c, oid("d")]);
+        assert!(request.multi_ack_detailed && request.ofs_delta && !request.thin_pack);
+        assert_eq!(request.agent.as_ref().map(|a| a.as_slice()), Some(&b"git/2.39"[..]));
+        assert_eq!(common, [c]);
+        assert!(shallow.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn no_done_sends_the_pack_once_ready() -> crate::Result {
+        let mut delegate = Delegate {
+            objects: vec![oid("c")],
+            ready: true,
+            ..Default::default()
+        };
+        let want = format!("want {} multi_ack_detailed no-done side-band", oid("a"));
+        let (lines, res) = serve(
+            Protocol::V1,
+            &[
+                &want,
+                &format!("shallow {}", oid("c")),
+                "deepen 1",
+                "0000",
+                &format!("have {}", oid("c")),
+                "0000",
+            ],
+            &mut delegate,
+        );
+        res?;
+        let (b, c) = (oid("b"), oid("c"));
+        assert_eq!(
+            &lines[5..],
+            [
+                format!("shallow {b}"),
+                format!("unshallow {c}"),
+                "0000".into(),
+                format!("ACK {c} common"),
+                format!("ACK {c} ready"),
+                "NAK".into(),
+                format!("ACK {c}"),
+                "\u{2}progress".into(),
+                "\u{1}PACK".into(),
+                "0000".into(),
+            ]
+        );
+        let (request, _common, shallow) = delegate.pack_request.expect("pack was written");
+        assert_eq!(request.depth, Some(1));
+        assert_eq!(
+            shallow,
+            [b],
+            "the unshallowed commit isn't part of the boundary anymore"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn clients_without_wants_get_nothing() -> crate::Result {
+        let mut delegate = Delegate::default();
+        let (lines, res) = serve(Protocol::V1, &["0000"], &mut delegate);
+        res?;
+        assert_eq!(lines.last().map(String::as_str), Some("0000"));
+        assert!(delegate.pack_request.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn unadvertised_wants_are_rejected() {
+        let mut delegate = Delegate::default();
+        let want = format!("want {}", oid("f"));
+        let (lines, res) = serve(Protocol::V1, &[&want, "0000"], &mut delegate);
+        assert!(matches!(
+            res,
+            Err(git_protocol::upload_pack::Error::UnadvertisedWant { .. })
+        ));
+        assert_eq!(
+            lines.last().expect("error line"),
+            &format!(
+                "ERR The client wants {} which isn't the tip of an advertised reference",
+                oid("f")
+            )
+        );
+    }
+}
+
+mod v2 {
+    use git_protocol::transport::Protocol;
+
+    use super::{oid, serve, Delegate};
+
+    #[test]
+    fn capabilities_and_ls_refs() -> crate::Result {
+        let mut delegate = Delegate::default();
+        let (lines, res) = serve(
+            Protocol::V2,
+            &[
+                "command=ls-refs",
+                "object-format=sha1",
+                "0001",
+                "symrefs",
+                "peel",
+                "ref-prefix HEAD",
+                "ref-prefix refs/tags/",
+                "0000",
+            ],
+            &mut delegate,
+        );
+        res?;
+        let (a, e) = (oid("a"), oid("e"));
+        assert_eq!(
+            lines,
+            [
+                "version 2".to_string(),
+                "agent=git/test".into(),
+                "ls-refs=unborn".into(),
+                "fetch=shallow filter wait-for-done".into(),
+                "server-option".into(),
+                "object-format=sha1".into(),
+                "0000".into(),
+                format!("{a} HEAD symref-target:refs/heads/main"),
+                format!("{e} refs/tags/v1 peeled:{a}"),
+                "0000".into(),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fetch_negotiates_until_ready() -> crate::Result {
+        let mut delegate = Delegate {
+            objects: vec![oid("c")],
+            ..Default::default()
+        };
+        let (want, have_c, have_d) = (
+            format!("want {}", oid("a")),
+            format!("have {}", oid("c")),
+            format!("have {}", oid("d")),
+        );
+        let (lines, res) = serve(
+            Protocol::V2,
+            &[
+                "command=fetch",
+                "0001",
+                "thin-pack",
+                &want,
+                &have_d,
+                "0000",
+                "command=fetch",
+                "0001",
+                "no-progress",
+                &want,
+                &have_c,
+                &have_d,
+                "done",
+                "0000",
+            ],
+            &mut delegate,
+        );
+        res?;
+        assert_eq!(
+            &lines[7..],
+            ["acknowledgments", "NAK", "0000", "packfile", "\u{1}PACK", "0000"],
+            "the second request is done and thus doesn't receive acknowledgments, nor progress"
+        );
+        let (request, common, _shallow) = delegate.pack_request.expect("pack was written");
+        assert!(request.done && request.no_progress);
+        assert!(!request.thin_pack, "each request stands on its own");
+        assert_eq!(common, [oid("c")]);
+        Ok(())
+    }
+
+    #[test]
+    fn fetch_sends_the_pack_once_ready() -> crate::Result {
+        let mut delegate = Delegate {
+            objects: vec![oid("c")],
+            ready: true,
+            ..Default::default()
+        };
+        let (lines, res) = serve(
+            Protocol::V2,
+            &[
+                "command=fetch",
+                "0001",
+                &format!("want {}", oid("a")),
+                &format!("have {}", oid("c")),
+                "deepen 3",
+                "0000",
+            ],
+            &mut delegate,
+        );
+        res?;
+        let (b, c) = (oid("b"), oid("c"));
+        assert_eq!(
+            &lines[7..],
+            [
+                "acknowledgments".to_string(),
+                format!("ACK {c}"),
+                "ready".into(),
+                "0001".into(),
+                "shallow-info".into(),
+                format!("shallow {b}"),
+                format!("unshallow {c}"),
+                "0001".into(),
+                "packfile".into(),
+                "\u{2}progress".into(),
+                "\u{1}PACK".into(),
+                "0000".into(),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_commands_are_rejected() {
+        let mut delegate = Delegate::default();
+        let (lines, res) = serve(Protocol::V2, &["command=frobnicate", "0000"], &mut delegate);
+        assert!(matches!(
+            res,
+            Err(git_protocol::upload_pack::Error::UnknownCommand { .. })
+        ));
+        assert_eq!(
+            lines.last().map(String::as_str),
+            Some("ERR The command \"frobnicate\" is unknown")
+        );
+    }
+}
+
+mod request {
+    use git_protocol::{transport::Protocol, upload_pack::Request};
+
+    #[test]
+    fn deepen_cannot_be_combined_with_deepen_since() {
+        let mut request = Request::default();
+        request
+            .parse_line("deepen 1\n".into(), Protocol::V2)
+            .expect("valid line");
+        assert!(request.parse_line("deepen-since 1234".into(), Protocol::V2).is_err());
+    }
+
+    #[test]
+    fn v1_capabilities_are_only_valid_on_want_lines() {
+        let mut request = Request::default();
+        assert!(request.parse_line("thin-pack".into(), Protocol::V1).is_err());
+        assert!(request.parse_line("thin-pack".into(), Protocol::V2).is_ok());
+        assert!(request.thin_pack);
+    }
+}
@@ -14,6 +14,8 @@
 //!     * one reference maps to a file on disk
 //!   * **packed**
 //!     * references are stored in a single human-readable file, along with their targets if they are symbolic.
+//! * **[reftable][reftable::Store]**
+//!   * references and their logs are stored in a stack of binary tables.
 //!
 //! ## Feature Flags
 #![cfg_attr(
@@ -31,7 +33,7 @@ use git_object::bstr::{BStr, BString};
 
 #[path = "store/mod.rs"]
 mod store_impl;
-pub use store_impl::{file, packed};
+pub use store_impl::{file, packed, reftable};
 
 mod fullname;
 ///
@@ -85,6 +87,7 @@ pub mod store {
     #[allow(dead_code)]
     pub(crate) enum State {
         Loose { store: file::Store },
+        Reftable { store: reftable::Store },
     }
 
     pub(crate) mod general;
@@ -94,7 +97,7 @@ pub mod store {
     mod handle;
     pub use handle::find;
 
-    use crate::file;
+    use crate::{file, reftable};
 }
 
 /// The git reference store.
@@ -125,7 +128,7 @@ pub struct PartialNameCow<'a>(Cow<'a, BStr>);
 pub struct PartialNameRef(BStr);
 
 /// A validated complete and fully qualified owned reference name, safe to use for all operations.
-#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd)]
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
 pub struct PartialName(BString);
 
 /// A _validated_ prefix for references to act as a namespace.
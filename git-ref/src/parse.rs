@@ -2,6 +2,7 @@ use git_object::bstr::{BStr, ByteSlice};
 use nom::{
     branch::alt,
     bytes::complete::{tag, take_while_m_n},
+    combinator::verify,
     error::ParseError,
     IResult,
 };
@@ -10,14 +11,21 @@ fn is_hex_digit_lc(b: u8) -> bool {
     matches!(b, b'0'..=b'9' | b'a'..=b'f')
 }
 
+fn is_hash_len(hex: &[u8]) -> bool {
+    git_hash::Kind::from_hex_len(hex.len()).map_or(false, |kind| kind.len_in_hex() == hex.len())
+}
+
 /// Copy from https://github.com/Byron/gitoxide/blob/f270850ff92eab15258023b8e59346ec200303bd/git-object/src/immutable/parse.rs#L64
 pub fn hex_hash<'a, E: ParseError<&'a [u8]>>(i: &'a [u8]) -> IResult<&'a [u8], &'a BStr, E> {
     // NOTE: It's important to be able to read all hashes, do not parameterize it. Hashes can be rejected at a later stage
     // if needed.
-    take_while_m_n(
-        git_hash::Kind::shortest().len_in_hex(),
-        git_hash::Kind::longest().len_in_hex(),
-        is_hex_digit_lc,
+    verify(
+        take_while_m_n(
+            git_hash::Kind::shortest().len_in_hex(),
+            git_hash::Kind::longest().len_in_hex(),
+            is_hex_digit_lc,
+        ),
+        is_hash_len,
     )(i)
     .map(|(i, hex)| (i, hex.as_bstr()))
 }
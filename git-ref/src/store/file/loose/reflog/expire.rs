@@ -0,0 +1,197 @@
+use std::{convert::TryInto, io::Write};
+
+use git_hash::{oid, ObjectId};
+use git_object::bstr::ByteSlice;
+
+use crate::{
+    log::Line,
+    store_impl::{file, file::log},
+    FullNameRef,
+};
+
+/// Options to control which entries are removed by [`Store::reflog_expire()`][file::Store::reflog_expire()].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// Entries older than this time, in seconds since unix epoch, are removed, similar to `gc.reflogExpire`.
+    ///
+    /// If `None`, entries are never removed for their age alone.
+    pub expire_total: Option<u32>,
+    /// Entries older than this time, in seconds since unix epoch, are removed if their previous or new object isn't reachable
+    /// from the tip of the reference anymore, similar to `gc.reflogExpireUnreachable`.
+    ///
+    /// If `None`, unreachable entries are not removed unless they are older than [`expire_total`][Options::expire_total].
+    pub expire_unreachable: Option<u32>,
+    /// If `true`, set the previous object of each kept entry to the new object of the entry kept before it,
+    /// which keeps the log consistent after removing entries in the middle, similar to `git reflog expire --rewrite`.
+    pub rewrite: bool,
+    /// If `true`, determine which entries would be removed without altering the reflog.
+    pub dry_run: bool,
+}
+
+/// The outcome of [`Store::reflog_expire()`][file::Store::reflog_expire()] and
+/// [`Store::reflog_delete_entry()`][file::Store::reflog_delete_entry()].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// The amount of entries that remain in the reflog.
+    pub kept: usize,
+    /// All removed entries, from oldest to newest.
+    pub removed: Vec<Line>,
+}
+
+mod error {
+    use crate::{store_impl::file::log, FullName};
+
+    /// The error returned by [`Store::reflog_expire()`][crate::file::Store::reflog_expire()] and
+    /// [`Store::reflog_delete_entry()`][crate::file::Store::reflog_delete_entry()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("The reflog name or path is not a valid ref name")]
+        RefnameValidation(#[from] crate::name::Error),
+        #[error("A lock could not be obtained for reference {full_name:?} or its reflog")]
+        LockAcquire {
+            source: git_lock::acquire::Error,
+            full_name: FullName,
+        },
+        #[error("The reflog could not be read or written")]
+        Io(#[from] std::io::Error),
+        #[error("A reflog entry could not be parsed")]
+        Decode(#[from] log::iter::decode::Error),
+        #[error("The rewritten reflog could not be moved into place")]
+        Commit(#[from] git_lock::commit::Error<git_lock::File>),
+    }
+}
+pub use error::Error;
+
+impl file::Store {
+    /// Remove all entries of the reflog of `name` that are expired according to `options`, similar to `git reflog expire`.
+    ///
+    /// `is_reachable(id)` is called for the previous and new object of entries older than [`Options::expire_unreachable`],
+    /// and returns `true` if `id` can be reached from the tip of the reference.
+    /// Null object ids, which mark the creation or deletion of a reference, are always considered reachable.
+    ///
+    /// The reference and its reflog are locked using `lock_mode` while the log is rewritten, which makes this operation safe
+    /// in the presence of transactions that append to the log concurrently.
+    /// Return `Ok(None)` if no reflog exists.
+    pub fn reflog_expire<'a, Name, E>(
+        &self,
+        name: Name,
+        options: Options,
+        mut is_reachable: impl FnMut(&oid) -> bool,
+        lock_mode: git_lock::acquire::Fail,
+    ) -> Result<Option<Outcome>, Error>
+    where
+        Name: TryInto<&'a FullNameRef, Error = E>,
+        crate::name::Error: From<E>,
+    {
+        let name: &FullNameRef = name.try_into().map_err(|err| Error::RefnameValidation(err.into()))?;
+        self.reflog_rewrite(name, lock_mode, options.rewrite, options.dry_run, |_index, line| {
+            let time = line.signature.time.seconds_since_unix_epoch;
+            if options.expire_total.map_or(false, |cutoff| time < cutoff) {
+                return true;
+            }
+            if options.expire_unreachable.map_or(false, |cutoff| time < cutoff) {
+                let mut unreachable = |id: ObjectId| !id.is_null() && !is_reachable(&id);
+                return unreachable(line.previous_oid()) || unreachable(line.new_oid());
+            }
+            false
+        })
+    }
+
+    /// Remove the entry at `index` from the reflog of `name`, with `0` being the most recent entry just like in `name@{0}`,
+    /// similar to `git reflog delete`.
+    ///
+    /// If `rewrite` is `true`, the previous object of the entry following the removed one is adjusted to keep the log consistent,
+    /// see [`Options::rewrite`] for details.
+    /// The reference and its reflog are locked using `lock_mode` while the log is rewritten.
+    /// Return `Ok(None)` if no reflog exists, and an empty [`Outcome::removed`] field if there was no entry at `index`.
+    pub fn reflog_delete_entry<'a, Name, E>(
+        &self,
+        name: Name,
+        index: usize,
+        rewrite: bool,
+        lock_mode: git_lock::acquire::Fail,
+    ) -> Result<Option<Outcome>, Error>
+    where
+        Name: TryInto<&'a FullNameRef, Error = E>,
+        crate::name::Error: From<E>,
+    {
+        let name: &FullNameRef = name.try_into().map_err(|err| Error::RefnameValidation(err.into()))?;
+        self.reflog_rewrite(name, lock_mode, rewrite, false, |index_from_newest, _line| {
+            index_from_newest == index
+        })
+    }
+
+    /// Rewrite the reflog of `name` while holding a lock on the reference, dropping all lines for which `should_remove(index_from_newest, line)`
+    /// returns `true`.
+    fn reflog_rewrite(
+        &self,
+        name: &FullNameRef,
+        lock_mode: git_lock::acquire::Fail,
+        rewrite: bool,
+        dry_run: bool,
+        mut should_remove: impl FnMut(usize, &log::LineRef<'_>) -> bool,
+    ) -> Result<Option<Outcome>, Error> {
+        let lock_err = |source| Error::LockAcquire {
+            source,
+            full_name: name.to_owned(),
+        };
+        let (ref_base, ref_path) = self.reference_path_with_base(name);
+        let _ref_lock = git_lock::Marker::acquire_to_hold_resource(
+            ref_base.join(ref_path.as_ref()),
+            lock_mode,
+            Some(ref_base.clone().into_owned()),
+        )
+        .map_err(lock_err)?;
+
+        let (log_base, log_path) = self.reflog_base_and_relative_path(name);
+        let log_path = log_base.join(log_path.as_ref());
+        let buf = match std::fs::read(&log_path) {
+            Ok(buf) => buf,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(_) if log_path.is_dir() => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let lines = buf
+            .lines()
+            .enumerate()
+            .map(|(ln, line)| {
+                log::LineRef::from_bytes(line)
+                    .map(|parsed| (line, parsed))
+                    .map_err(|err| log::iter::decode::Error::new(err, log::iter::decode::LineNumber::FromStart(ln)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let num_lines = lines.len();
+        let mut out = Vec::with_capacity(buf.len());
+        let mut outcome = Outcome::default();
+        let mut last_kept_oid = self.object_hash.null();
+        let mut needs_write = false;
+        for (index, (raw_line, line)) in lines.into_iter().enumerate() {
+            if should_remove(num_lines - index - 1, &line) {
+                outcome.removed.push(line.to_owned());
+                needs_write = true;
+                continue;
+            }
+            outcome.kept += 1;
+            match line.previous_oid() {
+                previous_oid if rewrite && previous_oid != last_kept_oid => {
+                    write!(out, "{last_kept_oid}")?;
+                    out.extend_from_slice(&raw_line[line.previous_oid.len()..]);
+                    needs_write = true;
+                }
+                _ => out.extend_from_slice(raw_line),
+            }
+            out.push(b'\n');
+            last_kept_oid = line.new_oid();
+        }
+
+        if needs_write && !dry_run {
+            let mut lock =
+                git_lock::File::acquire_to_update_resource(&log_path, lock_mode, Some(log_base)).map_err(lock_err)?;
+            lock.with_mut(|file| file.write_all(&out))?;
+            lock.commit()?;
+        }
+        Ok(Some(outcome))
+    }
+}
@@ -1,3 +1,5 @@
+use git_object::bstr::BString;
+
 use crate::{
     store_impl::file::{transaction::PackedRefs, Transaction},
     transaction::{Change, LogChange, RefEdit, RefLog},
@@ -18,10 +20,11 @@ impl<'s, 'p> Transaction<'s, 'p> {
     /// In this stage, we perform the following operations:
     ///
     /// * update the ref log
+    /// * copy the ref log of renamed or copied refs
     /// * move updated refs into place
     /// * delete reflogs and empty parent directories
     /// * delete packed refs
-    /// * delete their corresponding reference (if applicable)
+    /// * delete their corresponding reference (if applicable), including the source of renames,
     ///   along with empty parent directories
     ///
     /// Note that transactions will be prepared automatically as needed.
@@ -31,6 +34,7 @@ impl<'s, 'p> Transaction<'s, 'p> {
 
     fn commit_inner(self, committer: Option<git_actor::SignatureRef<'_>>) -> Result<Vec<RefEdit>, Error> {
         let mut updates = self.updates.expect("BUG: must call prepare before commit");
+        // Initial transactions write into packed-refs only and have no loose references to delete.
         let delete_loose_refs = matches!(
             self.packed_refs,
             PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(_)
@@ -90,53 +94,77 @@ impl<'s, 'p> Transaction<'s, 'p> {
                         continue;
                     }
                     if update_ref {
-                        if let Some(Err(err)) = lock.map(|l| l.commit()) {
-                            // TODO: when Kind::IsADirectory becomes stable, use that.
-                            let err = if err.instance.resource_path().is_dir() {
-                                git_tempfile::remove_dir::empty_depth_first(err.instance.resource_path())
-                                    .map_err(|io_err| std::io::Error::new(std::io::ErrorKind::Other, io_err))
-                                    .and_then(|_| err.instance.commit().map_err(|err| err.error))
-                                    .err()
-                            } else {
-                                Some(err.error)
-                            };
-
-                            if let Some(err) = err {
-                                return Err(Error::LockCommit {
-                                    source: err,
-                                    full_name: change.name(),
-                                });
-                            }
-                        };
+                        commit_lock(lock, || change.name())?;
+                    }
+                }
+                // reflog first, then reference, just like updates
+                Change::Rename {
+                    source,
+                    expected,
+                    message,
+                    ..
+                } => {
+                    let lock = change.lock.take();
+                    match std::fs::read(self.store.reflog_path(source.as_ref())) {
+                        Ok(log) => {
+                            let reflog_path = self.store.reflog_path(change.update.name.as_ref());
+                            git_tempfile::create_dir::all(
+                                reflog_path.parent().expect("always with parent directory"),
+                                Default::default(),
+                            )
+                            .and_then(|_| std::fs::write(&reflog_path, log))
+                            .map_err(|err| Error::CopyReflog {
+                                source: err,
+                                full_name: change.name(),
+                            })?;
+                        }
+                        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                        Err(err) => {
+                            return Err(Error::CopyReflog {
+                                source: err,
+                                full_name: change.name(),
+                            })
+                        }
                     }
+                    if let PreviousValue::MustExistAndMatch(Target::Peeled(oid)) = expected {
+                        self.store.reflog_create_or_append(
+                            change.update.name.as_ref(),
+                            Some(oid.to_owned()),
+                            oid,
+                            committer,
+                            message.as_ref(),
+                            false,
+                        )?;
+                    }
+                    commit_lock(lock, || change.name())?;
                 }
                 Change::Delete { .. } => {}
             }
         }
 
         for change in updates.iter_mut() {
-            let (reflog_root, relative_name) = self.store.reflog_base_and_relative_path(change.update.name.as_ref());
-            match &change.update.change {
-                Change::Update { .. } => {}
-                Change::Delete { .. } => {
-                    // Reflog deletion happens first in case it fails a ref without log is less terrible than
-                    // a log without a reference.
-                    let reflog_path = reflog_root.join(relative_name);
-                    if let Err(err) = std::fs::remove_file(&reflog_path) {
-                        if err.kind() != std::io::ErrorKind::NotFound {
-                            return Err(Error::DeleteReflog {
-                                source: err,
-                                full_name: change.name(),
-                            });
-                        }
-                    } else {
-                        git_tempfile::remove_dir::empty_upward_until_boundary(
-                            reflog_path.parent().expect("never without parent"),
-                            &reflog_root,
-                        )
-                        .ok();
-                    }
+            let name = match &change.update.change {
+                Change::Update { .. } | Change::Rename { copy: true, .. } => continue,
+                Change::Delete { .. } => change.update.name.as_ref(),
+                Change::Rename { source, .. } => source.as_ref(),
+            };
+            // Reflog deletion happens first in case it fails a ref without log is less terrible than
+            // a log without a reference.
+            let (reflog_root, relative_name) = self.store.reflog_base_and_relative_path(name);
+            let reflog_path = reflog_root.join(relative_name);
+            if let Err(err) = std::fs::remove_file(&reflog_path) {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    return Err(Error::DeleteReflog {
+                        source: err,
+                        full_name: name.as_bstr().to_owned(),
+                    });
                 }
+            } else {
+                git_tempfile::remove_dir::empty_upward_until_boundary(
+                    reflog_path.parent().expect("never without parent"),
+                    &reflog_root,
+                )
+                .ok();
             }
         }
 
@@ -148,31 +176,60 @@ impl<'s, 'p> Transaction<'s, 'p> {
         }
 
         for change in updates.iter_mut() {
-            let take_lock_and_delete = match &change.update.change {
+            let (name_to_delete, lock) = match &change.update.change {
                 Change::Update {
                     log: LogChange { mode, .. },
                     new,
                     ..
-                } => delete_loose_refs && *mode == RefLog::AndReference && matches!(new, Target::Peeled(_)),
-                Change::Delete { log: mode, .. } => *mode == RefLog::AndReference,
+                } if delete_loose_refs && *mode == RefLog::AndReference && matches!(new, Target::Peeled(_)) => {
+                    (change.update.name.as_ref(), change.lock.take())
+                }
+                Change::Delete {
+                    log: RefLog::AndReference,
+                    ..
+                } => (change.update.name.as_ref(), change.lock.take()),
+                Change::Rename {
+                    source, copy: false, ..
+                } => (source.as_ref(), change.source_lock.take()),
+                Change::Update { .. } | Change::Delete { .. } | Change::Rename { .. } => continue,
             };
-            if take_lock_and_delete {
-                let lock = change.lock.take();
-                let reference_path = self.store.reference_path(change.update.name.as_ref());
-                if let Err(err) = std::fs::remove_file(reference_path) {
-                    if err.kind() != std::io::ErrorKind::NotFound {
-                        return Err(Error::DeleteReference {
-                            err,
-                            full_name: change.name(),
-                        });
-                    }
+            let reference_path = self.store.reference_path(name_to_delete);
+            if let Err(err) = std::fs::remove_file(reference_path) {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    return Err(Error::DeleteReference {
+                        err,
+                        full_name: name_to_delete.as_bstr().to_owned(),
+                    });
                 }
-                drop(lock)
             }
+            drop(lock)
         }
         Ok(updates.into_iter().map(|edit| edit.update).collect())
     }
 }
+/// Commit `lock`, if present, into place, removing an empty directory that might be in the way.
+fn commit_lock(lock: Option<git_lock::Marker>, full_name: impl FnOnce() -> BString) -> Result<(), Error> {
+    if let Some(Err(err)) = lock.map(|l| l.commit()) {
+        // TODO: when Kind::IsADirectory becomes stable, use that.
+        let err = if err.instance.resource_path().is_dir() {
+            git_tempfile::remove_dir::empty_depth_first(err.instance.resource_path())
+                .map_err(|io_err| std::io::Error::new(std::io::ErrorKind::Other, io_err))
+                .and_then(|_| err.instance.commit().map_err(|err| err.error))
+                .err()
+        } else {
+            Some(err.error)
+        };
+
+        if let Some(err) = err {
+            return Err(Error::LockCommit {
+                source: err,
+                full_name: full_name(),
+            });
+        }
+    };
+    Ok(())
+}
+
 mod error {
     use git_object::bstr::BString;
 
@@ -192,6 +249,8 @@ mod error {
         DeleteReference { full_name: BString, err: std::io::Error },
         #[error("The reflog of reference {full_name:?} could not be deleted")]
         DeleteReflog { full_name: BString, source: std::io::Error },
+        #[error("The reflog of the renamed or copied reference {full_name:?} could not be written")]
+        CopyReflog { full_name: BString, source: std::io::Error },
         #[error("The reflog could not be created or updated")]
         CreateOrUpdateRefLog(#[from] file::log::create_or_update::Error),
     }
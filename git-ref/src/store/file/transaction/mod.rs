@@ -28,6 +28,14 @@ pub enum PackedRefs<'a> {
     /// reference which is originally updated if it exists. If it doesn't, the new value will be written into the packed ref right away.
     /// Note that this doesn't affect symbolic references at all, which can't be placed into packed refs.
     DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(Box<FindObjectFn<'a>>),
+    /// If the store doesn't contain a single reference yet, write all updates to references which are peeled directly into a new
+    /// packed-refs file in one sorted pass, without checking for or locking loose references, which is the fastest way to create
+    /// a lot of references at once, like when cloning. Edits that expect a reference to exist fail, just like those whose names
+    /// clash with each other, like `refs/heads/a` and `refs/heads/a/b`.
+    /// Note that this assumes no other process is writing references at the same time.
+    ///
+    /// If the store isn't empty, this behaves like [`DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference`][PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference].
+    Initial(Box<FindObjectFn<'a>>),
 }
 
 impl Default for PackedRefs<'_> {
@@ -46,6 +54,8 @@ pub(in crate::store_impl::file) struct Edit {
     /// For symbolic refs, this is the previous OID to put into the reflog instead of our own previous value. It's the
     /// peeled value of the leaf referent.
     leaf_referent_previous_oid: Option<ObjectId>,
+    /// The lock on the `source` of a rename, held until it is deleted.
+    source_lock: Option<git_lock::Marker>,
 }
 
 impl Edit {
@@ -92,6 +102,17 @@ impl<'s, 'p> Transaction<'s, 'p> {
     }
 }
 
+/// Access
+impl Transaction<'_, '_> {
+    /// Return all edits of a [prepared][Transaction::prepare()] transaction, including the splits of symbolic references,
+    /// with their previous values set to what was encountered after locking them.
+    ///
+    /// The iterator is empty if the transaction wasn't prepared yet.
+    pub fn edits(&self) -> impl Iterator<Item = &RefEdit> + '_ {
+        self.updates.iter().flatten().map(|edit| &edit.update)
+    }
+}
+
 impl std::fmt::Debug for Transaction<'_, '_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Transaction")
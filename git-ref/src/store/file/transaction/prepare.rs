@@ -1,3 +1,7 @@
+use std::collections::BTreeSet;
+
+use git_object::bstr::ByteSlice;
+
 use crate::{
     packed,
     packed::transaction::buffer_into_transaction,
@@ -21,6 +25,7 @@ impl<'s, 'p> Transaction<'s, 'p> {
         change: &mut Edit,
         has_global_lock: bool,
         direct_to_packed_refs: bool,
+        store_is_empty: bool,
     ) -> Result<(), Error> {
         use std::io::Write;
         assert!(
@@ -28,30 +33,11 @@ impl<'s, 'p> Transaction<'s, 'p> {
             "locks can only be acquired once and it's all or nothing"
         );
 
-        let existing_ref = store
-            .ref_contents(change.update.name.as_ref())
-            .map_err(Error::from)
-            .and_then(|maybe_loose| {
-                maybe_loose
-                    .map(|buf| {
-                        loose::Reference::try_from_path(change.update.name.clone(), &buf)
-                            .map(Reference::from)
-                            .map_err(Error::from)
-                    })
-                    .transpose()
-            })
-            .or_else(|err| match err {
-                Error::ReferenceDecode(_) => Ok(None),
-                other => Err(other),
-            })
-            .and_then(|maybe_loose| match (maybe_loose, packed) {
-                (None, Some(packed)) => packed
-                    .try_find(change.update.name.as_ref())
-                    .map(|opt| opt.map(Into::into))
-                    .map_err(Error::from),
-                (None, None) => Ok(None),
-                (maybe_loose, _) => Ok(maybe_loose),
-            });
+        let existing_ref = if store_is_empty {
+            Ok(None)
+        } else {
+            find_existing(store, packed, change.update.name.as_ref())
+        };
         let lock = match &mut change.update.change {
             Change::Delete { expected, .. } => {
                 let (base, relative_path) = store.reference_path_with_base(change.update.name.as_ref());
@@ -191,12 +177,155 @@ impl<'s, 'p> Transaction<'s, 'p> {
                     None
                 }
             }
+            Change::Rename {
+                source, expected, copy, ..
+            } => {
+                let source_lock = if *copy || has_global_lock {
+                    None
+                } else {
+                    let (base, relative_path) = store.reference_path_with_base(source.as_ref());
+                    git_lock::Marker::acquire_to_hold_resource(
+                        base.join(relative_path.as_ref()),
+                        lock_fail_mode,
+                        Some(base.clone().into_owned()),
+                    )
+                    .map_err(|err| Error::LockAcquire {
+                        source: err,
+                        full_name: "borrowchk won't allow change.name() and this will be corrected by caller".into(),
+                    })?
+                    .into()
+                };
+
+                let source_target = match (&expected, find_existing(store, packed, source.as_ref())?) {
+                    (PreviousValue::MustNotExist, _) => {
+                        panic!("BUG: MustNotExist constraint makes no sense if references are to be renamed")
+                    }
+                    (PreviousValue::MustExistAndMatch(previous) | PreviousValue::ExistingMustMatch(previous), None) => {
+                        return Err(Error::MustExist {
+                            full_name: source.as_bstr().to_owned(),
+                            expected: previous.clone(),
+                        })
+                    }
+                    (PreviousValue::MustExist | PreviousValue::Any, None) => {
+                        return Err(Error::MustExist {
+                            full_name: source.as_bstr().to_owned(),
+                            expected: Target::Peeled(store.object_hash.null()),
+                        })
+                    }
+                    (
+                        PreviousValue::MustExistAndMatch(previous) | PreviousValue::ExistingMustMatch(previous),
+                        Some(existing),
+                    ) if previous != &existing.target => {
+                        return Err(Error::ReferenceOutOfDate {
+                            full_name: source.as_bstr().to_owned(),
+                            expected: previous.clone(),
+                            actual: existing.target,
+                        })
+                    }
+                    (_, Some(existing)) => existing.target,
+                };
+
+                if let Some(existing) = existing_ref? {
+                    return Err(Error::MustNotExist {
+                        full_name: change.name(),
+                        actual: existing.target,
+                        new: source_target,
+                    });
+                }
+                if let Some(existing) = clashing_reference(store, packed, change.update.name.as_ref())? {
+                    return Err(Error::NameClash {
+                        full_name: change.name(),
+                        existing: existing.into_inner(),
+                    });
+                }
+
+                let (base, relative_path) = store.reference_path_with_base(change.update.name.as_ref());
+                let mut lock = git_lock::File::acquire_to_update_resource(
+                    base.join(relative_path.as_ref()),
+                    lock_fail_mode,
+                    Some(base.clone().into_owned()),
+                )
+                .map_err(|err| Error::LockAcquire {
+                    source: err,
+                    full_name: "borrowchk won't allow change.name() and this will be corrected by caller".into(),
+                })?;
+                lock.with_mut(|file| match &source_target {
+                    Target::Peeled(oid) => write!(file, "{oid}"),
+                    Target::Symbolic(name) => write!(file, "ref: {}", name.0),
+                })?;
+
+                *expected = PreviousValue::MustExistAndMatch(source_target);
+                change.source_lock = source_lock;
+                Some(lock.close()?)
+            }
         };
         change.lock = lock;
         Ok(())
     }
 }
 
+/// Find the reference `name` as loose reference, or in `packed` if it isn't loose, treating loose references that fail to decode
+/// as non-existing.
+fn find_existing(
+    store: &file::Store,
+    packed: Option<&packed::Buffer>,
+    name: &FullNameRef,
+) -> Result<Option<Reference>, Error> {
+    store
+        .ref_contents(name)
+        .map_err(Error::from)
+        .and_then(|maybe_loose| {
+            maybe_loose
+                .map(|buf| {
+                    loose::Reference::try_from_path(name.to_owned(), &buf)
+                        .map(Reference::from)
+                        .map_err(Error::from)
+                })
+                .transpose()
+        })
+        .or_else(|err| match err {
+            Error::ReferenceDecode(_) => Ok(None),
+            other => Err(other),
+        })
+        .and_then(|maybe_loose| match (maybe_loose, packed) {
+            (None, Some(packed)) => packed
+                .try_find(name)
+                .map(|opt| opt.map(Into::into))
+                .map_err(Error::from),
+            (None, None) => Ok(None),
+            (maybe_loose, _) => Ok(maybe_loose),
+        })
+}
+
+/// Return `true` if `store` doesn't contain a single loose or packed reference, ignoring `HEAD` and other pseudo-references.
+fn is_empty(store: &file::Store) -> Result<bool, Error> {
+    let packed = store.assure_packed_refs_uptodate()?;
+    Ok(store.iter_packed(packed.as_ref().map(|b| &***b))?.next().is_none())
+}
+
+/// Return the name of an existing reference that prevents the creation of `name` as one of them would have to be a directory,
+/// like `refs/heads/a` and `refs/heads/a/b`.
+fn clashing_reference(
+    store: &file::Store,
+    packed: Option<&packed::Buffer>,
+    name: &FullNameRef,
+) -> Result<Option<FullName>, Error> {
+    let name = name.as_bstr();
+    for slash_pos in name.find_iter(b"/") {
+        let parent = FullNameRef::new_unchecked(name[..slash_pos].as_bstr());
+        if find_existing(store, packed, parent)?.is_some() {
+            return Ok(Some(parent.to_owned()));
+        }
+    }
+    let mut prefix = name.to_owned();
+    prefix.push(b'/');
+    Ok(store
+        .iter_prefixed_packed(git_path::from_bstr(prefix.as_bstr()), packed)?
+        .filter_map(Result::ok)
+        .find(|r| r.name.as_bstr().starts_with(prefix.as_slice()))
+        .map(|r| r.name))
+}
+
 impl<'s, 'p> Transaction<'s, 'p> {
     /// Prepare for calling [`commit(…)`][Transaction::commit()] in a way that can be rolled back perfectly.
     ///
@@ -218,6 +347,7 @@ impl<'s, 'p> Transaction<'s, 'p> {
                 lock: None,
                 parent_index: None,
                 leaf_referent_previous_oid: None,
+                source_lock: None,
             })
             .collect();
         updates
@@ -234,13 +364,51 @@ impl<'s, 'p> Transaction<'s, 'p> {
                     lock: None,
                     parent_index: Some(idx),
                     leaf_referent_previous_oid: None,
+                    source_lock: None,
                 },
             )
             .map_err(Error::PreprocessingFailed)?;
 
+        // Initial transactions are only possible if there is nothing to conflict with.
+        let store_is_empty = match std::mem::take(&mut self.packed_refs) {
+            PackedRefs::Initial(find) => {
+                let store_is_empty = is_empty(store)?;
+                self.packed_refs = if store_is_empty {
+                    PackedRefs::Initial(find)
+                } else {
+                    PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(find)
+                };
+                store_is_empty
+            }
+            other => {
+                self.packed_refs = other;
+                false
+            }
+        };
+        if store_is_empty {
+            let names: BTreeSet<_> = updates
+                .iter()
+                .filter(|edit| matches!(edit.update.change, Change::Update { .. }))
+                .map(|edit| edit.update.name.as_bstr())
+                .collect();
+            for name in &names {
+                if let Some(parent) = name
+                    .find_iter(b"/")
+                    .map(|slash_pos| name[..slash_pos].as_bstr())
+                    .find(|parent| names.contains(parent))
+                {
+                    return Err(Error::NameClash {
+                        full_name: (*name).to_owned(),
+                        existing: parent.to_owned(),
+                    });
+                }
+            }
+        }
+
         let mut maybe_updates_for_packed_refs = match self.packed_refs {
             PackedRefs::DeletionsAndNonSymbolicUpdates(_)
-            | PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(_) => Some(0_usize),
+            | PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(_)
+            | PackedRefs::Initial(_) => Some(0_usize),
             PackedRefs::DeletionsOnly => None,
         };
         if maybe_updates_for_packed_refs.is_some()
@@ -250,12 +418,31 @@ impl<'s, 'p> Transaction<'s, 'p> {
             let mut edits_for_packed_transaction = Vec::<RefEdit>::new();
             let mut needs_packed_refs_lookups = false;
             for edit in updates.iter() {
+                if let Change::Rename { source, copy, .. } = &edit.update.change {
+                    // Sources may be packed, and names must not clash with packed references either.
+                    needs_packed_refs_lookups = true;
+                    if let Some(name) = (!copy)
+                        .then(|| possibly_adjust_name_for_prefixes(source.as_ref()))
+                        .flatten()
+                    {
+                        edits_for_packed_transaction.push(RefEdit {
+                            change: Change::Delete {
+                                expected: PreviousValue::Any,
+                                log: RefLog::AndReference,
+                            },
+                            name,
+                            deref: false,
+                        });
+                    }
+                    continue;
+                }
                 let log_mode = match edit.update.change {
                     Change::Update {
                         log: LogChange { mode, .. },
                         ..
                     } => mode,
                     Change::Delete { log, .. } => log,
+                    Change::Rename { .. } => unreachable!("BUG: handled above"),
                 };
                 if log_mode == RefLog::Only {
                     continue;
@@ -324,9 +511,8 @@ impl<'s, 'p> Transaction<'s, 'p> {
                 if let Some(transaction) = packed_transaction {
                     self.packed_transaction = Some(match &mut self.packed_refs {
                         PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(f)
-                        | PackedRefs::DeletionsAndNonSymbolicUpdates(f) => {
-                            transaction.prepare(edits_for_packed_transaction, f)?
-                        }
+                        | PackedRefs::DeletionsAndNonSymbolicUpdates(f)
+                        | PackedRefs::Initial(f) => transaction.prepare(edits_for_packed_transaction, f)?,
                         PackedRefs::DeletionsOnly => transaction
                             .prepare(edits_for_packed_transaction, &mut |_, _| {
                                 unreachable!("BUG: deletions never trigger object lookups")
@@ -346,8 +532,9 @@ impl<'s, 'p> Transaction<'s, 'p> {
                 self.packed_transaction.is_some(),
                 matches!(
                     self.packed_refs,
-                    PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(_)
+                    PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(_) | PackedRefs::Initial(_)
                 ),
+                store_is_empty,
             ) {
                 let err = match err {
                     Error::LockAcquire {
@@ -470,6 +657,8 @@ mod error {
             expected: Target,
             actual: Target,
         },
+        #[error("Reference {full_name:?} cannot be created as it clashes with the existing reference {existing:?}")]
+        NameClash { full_name: BString, existing: BString },
         #[error("Could not read reference")]
         ReferenceDecode(#[from] file::loose::reference::decode::Error),
     }
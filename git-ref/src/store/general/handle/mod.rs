@@ -4,6 +4,7 @@ use crate::{store, Namespace};
 #[derive(Clone)]
 pub(crate) enum State {
     Loose { store: crate::file::Store },
+    Reftable { store: crate::reftable::Store },
 }
 
 impl crate::Store {
@@ -13,7 +14,9 @@ impl crate::Store {
         Self::new_handle_inner(&self.inner, None)
     }
 
-    /// As above, but supports a namespace to be set
+    /// As above, but supports a namespace to be set.
+    ///
+    /// Note that namespaces are ignored by reftable stores.
     pub fn to_handle_namespaced(&self, namespace: Option<Namespace>) -> store::Handle {
         Self::new_handle_inner(&self.inner, namespace)
     }
@@ -28,6 +31,7 @@ impl crate::Store {
                         store
                     },
                 },
+                store::State::Reftable { store } => store::handle::State::Reftable { store: store.clone() },
             },
         }
     }
@@ -218,6 +218,9 @@ fn write_edit(mut out: impl std::io::Write, edit: &Edit, lines_written: &mut i32
             new: Target::Symbolic(_),
             ..
         } => unreachable!("BUG: packed refs cannot contain symbolic refs, catch that in prepare(…)"),
+        Change::Rename { .. } => {
+            unreachable!("BUG: renames are turned into deletions of their source by loose ref transactions")
+        }
     }
     Ok(())
 }
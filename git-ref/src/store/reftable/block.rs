@@ -0,0 +1,355 @@
+use std::borrow::Cow;
+
+use crate::store_impl::reftable::table::decode::Error;
+
+pub(crate) const REF: u8 = b'r';
+pub(crate) const LOG: u8 = b'g';
+pub(crate) const OBJ: u8 = b'o';
+pub(crate) const INDEX: u8 = b'i';
+
+/// The amount of bytes for the block type and the block length.
+pub(crate) const HEADER_LEN: usize = 4;
+
+pub(crate) fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    let mut buf = [0u8; 10];
+    let mut pos = buf.len() - 1;
+    buf[pos] = (value & 0x7f) as u8;
+    loop {
+        value >>= 7;
+        if value == 0 {
+            break;
+        }
+        value -= 1;
+        pos -= 1;
+        buf[pos] = 0x80 | (value & 0x7f) as u8;
+    }
+    out.extend_from_slice(&buf[pos..]);
+}
+
+pub(crate) fn get_varint(input: &mut &[u8]) -> Option<u64> {
+    let (&first, mut rest) = input.split_first()?;
+    let mut value = u64::from(first & 0x7f);
+    let mut byte = first;
+    while byte & 0x80 != 0 {
+        let (&next, tail) = rest.split_first()?;
+        value = value.checked_add(1)?.checked_mul(128)? | u64::from(next & 0x7f);
+        byte = next;
+        rest = tail;
+    }
+    *input = rest;
+    Some(value)
+}
+
+pub(crate) fn get_bytes<'a>(input: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if input.len() < len {
+        return None;
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Some(bytes)
+}
+
+pub(crate) fn get_u24(data: &[u8]) -> usize {
+    (data[0] as usize) << 16 | (data[1] as usize) << 8 | data[2] as usize
+}
+
+pub(crate) fn put_u24(out: &mut [u8], value: usize) {
+    out[0] = (value >> 16) as u8;
+    out[1] = (value >> 8) as u8;
+    out[2] = value as u8;
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+/// A single block of a table, with log blocks already inflated.
+pub(crate) struct Block<'a> {
+    /// The type of the block, like [`REF`].
+    pub typ: u8,
+    /// All bytes of the block up to its length, which for the first block in a file includes the file header.
+    data: Cow<'a, [u8]>,
+    /// The offset at which the first record starts.
+    records_start: usize,
+    /// The offset at which the table of restart offsets starts, which is also the end of all records.
+    restarts_start: usize,
+    /// The amount of restart points.
+    num_restarts: usize,
+    /// The amount of bytes this block occupies in the file, including padding, to find the next block.
+    pub full_len: usize,
+    /// The offset of the next record to read.
+    pos: usize,
+    /// The key of the record read last, as keys are prefix-compressed.
+    key: Vec<u8>,
+}
+
+impl<'a> Block<'a> {
+    /// Read the block at `offset` in `table`, with `header_len` being the length of the file header preceding the first block.
+    /// `block_size` is the size to which blocks are padded, or 0 if they are unaligned.
+    pub fn at(table: &'a [u8], offset: usize, header_len: usize, block_size: usize) -> Result<Self, Error> {
+        let corrupt = |message| Error::Corrupt { offset, message };
+        let start = offset + header_len;
+        let header = table
+            .get(start..start + HEADER_LEN)
+            .ok_or_else(|| corrupt("truncated block header"))?;
+        let typ = header[0];
+        let block_len = get_u24(&header[1..]);
+        if block_len < header_len + HEADER_LEN + 2 {
+            return Err(corrupt("block too small"));
+        }
+
+        let (data, full_len) = if typ == LOG {
+            let mut data = Vec::with_capacity(block_len);
+            data.extend_from_slice(&table[offset..start + HEADER_LEN]);
+            data.resize(block_len, 0);
+            let compressed = &table[start + HEADER_LEN..];
+            let mut inflate = git_features::zlib::Inflate::default();
+            let (mut consumed, mut written) = (0, start + HEADER_LEN - offset);
+            loop {
+                let (status, consumed_now, written_now) = inflate
+                    .once(&compressed[consumed..], &mut data[written..])
+                    .map_err(|source| Error::Inflate { offset, source })?;
+                consumed += consumed_now;
+                written += written_now;
+                match status {
+                    git_features::zlib::Status::StreamEnd => break,
+                    _ if consumed_now == 0 && written_now == 0 => return Err(corrupt("truncated log block")),
+                    _ => continue,
+                }
+            }
+            if written != block_len {
+                return Err(corrupt("inflated log block has unexpected size"));
+            }
+            (Cow::Owned(data), header_len + HEADER_LEN + consumed)
+        } else {
+            let data = table
+                .get(offset..offset + block_len)
+                .ok_or_else(|| corrupt("block exceeds the table"))?;
+            let next_is_unaligned = table.get(offset + block_len).map_or(false, |b| *b != 0);
+            let full_len = if block_size == 0 || block_len >= block_size || next_is_unaligned {
+                block_len
+            } else {
+                block_size
+            };
+            (Cow::Borrowed(data), full_len)
+        };
+
+        let num_restarts = (data[block_len - 2] as usize) << 8 | data[block_len - 1] as usize;
+        let restarts_start = (block_len - 2)
+            .checked_sub(num_restarts * 3)
+            .filter(|restarts_start| *restarts_start >= header_len + HEADER_LEN && num_restarts > 0)
+            .ok_or_else(|| corrupt("invalid restart table"))?;
+        Ok(Block {
+            typ,
+            data,
+            records_start: header_len + HEADER_LEN,
+            restarts_start,
+            num_restarts,
+            full_len,
+            pos: header_len + HEADER_LEN,
+            key: Vec::new(),
+        })
+    }
+
+    fn restart_offset(&self, index: usize) -> usize {
+        get_u24(&self.data[self.restarts_start + index * 3..])
+    }
+
+    /// Decode the full key stored at the restart point at `offset`.
+    fn restart_key(&self, offset: usize) -> Option<&[u8]> {
+        let mut input = self.data.get(offset..self.restarts_start)?;
+        let _prefix = get_varint(&mut input)?;
+        let suffix_len = get_varint(&mut input)? >> 3;
+        get_bytes(&mut input, suffix_len as usize)
+    }
+
+    /// Position the block so that the next record read is the first one whose key is equal or greater than `key`.
+    ///
+    /// Return `Ok(false)` if all keys in the block are smaller than `key`.
+    pub fn seek(
+        &mut self,
+        key: &[u8],
+        mut skip_value: impl FnMut(u8, &mut &[u8]) -> Option<()>,
+    ) -> Result<bool, Error> {
+        let (mut lo, mut hi) = (0, self.num_restarts);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let restart_key = self.restart_key(self.restart_offset(mid)).ok_or(Error::Corrupt {
+                offset: self.records_start,
+                message: "invalid restart point",
+            })?;
+            if restart_key <= key {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        self.pos = if lo == 0 {
+            self.records_start
+        } else {
+            self.restart_offset(lo - 1)
+        };
+        self.key.clear();
+        loop {
+            let (pos, previous_key) = (self.pos, self.key.clone());
+            match self.next(|_key, value_type, input| skip_value(value_type, input)) {
+                Some(Ok(())) if self.key.as_slice() >= key => {
+                    self.pos = pos;
+                    self.key = previous_key;
+                    return Ok(true);
+                }
+                Some(Ok(())) => {}
+                Some(Err(err)) => return Err(err),
+                None => return Ok(false),
+            }
+        }
+    }
+
+    /// Read the next record, passing its key, value type and the remaining input to `decode_value`, which is expected
+    /// to advance the input past the value.
+    pub fn next<T>(
+        &mut self,
+        decode_value: impl FnOnce(&[u8], u8, &mut &[u8]) -> Option<T>,
+    ) -> Option<Result<T, Error>> {
+        if self.pos >= self.restarts_start {
+            return None;
+        }
+        let corrupt = Error::Corrupt {
+            offset: self.pos,
+            message: "invalid record",
+        };
+        let mut input = &self.data[self.pos..self.restarts_start];
+        let before = input.len();
+        let res = (|| {
+            let prefix = get_varint(&mut input)? as usize;
+            let suffix_and_type = get_varint(&mut input)?;
+            let suffix = get_bytes(&mut input, (suffix_and_type >> 3) as usize)?;
+            if prefix > self.key.len() {
+                return None;
+            }
+            self.key.truncate(prefix);
+            self.key.extend_from_slice(suffix);
+            decode_value(&self.key, (suffix_and_type & 0x7) as u8, &mut input)
+        })();
+        match res {
+            Some(value) => {
+                self.pos += before - input.len();
+                Some(Ok(value))
+            }
+            None => {
+                self.pos = self.restarts_start;
+                Some(Err(corrupt))
+            }
+        }
+    }
+
+    /// Return the first key in the block.
+    pub fn first_key(&self) -> Option<&[u8]> {
+        self.restart_key(self.records_start)
+    }
+}
+
+/// A writer for a single block.
+pub(crate) struct Writer {
+    typ: u8,
+    /// The block bytes, starting with the file header if this is the first block.
+    buf: Vec<u8>,
+    header_len: usize,
+    restarts: Vec<usize>,
+    entries: usize,
+    last_key: Vec<u8>,
+    block_size: usize,
+    restart_interval: usize,
+}
+
+impl Writer {
+    /// Start a new block of `typ`, with `file_header` being non-empty if this is the first block in the file.
+    pub fn new(typ: u8, file_header: Vec<u8>, block_size: usize, restart_interval: usize) -> Self {
+        let header_len = file_header.len();
+        let mut buf = file_header;
+        buf.push(typ);
+        buf.extend_from_slice(&[0, 0, 0]);
+        Writer {
+            typ,
+            buf,
+            header_len,
+            restarts: Vec::new(),
+            entries: 0,
+            last_key: Vec::new(),
+            block_size,
+            restart_interval: restart_interval.max(1),
+        }
+    }
+
+    pub fn typ(&self) -> u8 {
+        self.typ
+    }
+
+    /// Return `true` if the block exceeds the block size, which can happen if a single record doesn't fit.
+    pub fn is_oversized(&self) -> bool {
+        self.block_size != 0 && self.buf.len() + self.restarts.len() * 3 + 2 > self.block_size
+    }
+
+    /// The key of the last added record.
+    pub fn last_key(&self) -> &[u8] {
+        &self.last_key
+    }
+
+    /// Add a record with `key`, `value_type` and the encoded `value`, and return `false` if it doesn't fit anymore.
+    ///
+    /// Records always fit into an empty block, which may then exceed the block size.
+    pub fn add(&mut self, key: &[u8], value_type: u8, value: &[u8]) -> bool {
+        let is_restart = self.entries % self.restart_interval == 0;
+        let prefix = if is_restart {
+            0
+        } else {
+            common_prefix_len(&self.last_key, key)
+        };
+        let mut record = Vec::with_capacity(key.len() - prefix + value.len() + 6);
+        put_varint(&mut record, prefix as u64);
+        put_varint(&mut record, ((key.len() - prefix) as u64) << 3 | u64::from(value_type));
+        record.extend_from_slice(&key[prefix..]);
+        record.extend_from_slice(value);
+
+        let restarts_len = (self.restarts.len() + usize::from(is_restart)) * 3 + 2;
+        if self.entries != 0 && self.block_size != 0 && self.buf.len() + record.len() + restarts_len > self.block_size {
+            return false;
+        }
+        if is_restart {
+            self.restarts.push(self.buf.len());
+        }
+        self.buf.extend_from_slice(&record);
+        self.last_key.clear();
+        self.last_key.extend_from_slice(key);
+        self.entries += 1;
+        true
+    }
+
+    /// Finish the block and return its bytes as they should be written to the file, with log blocks being compressed.
+    pub fn finish(mut self, pad: bool) -> std::io::Result<Vec<u8>> {
+        for restart in &self.restarts {
+            let pos = self.buf.len();
+            self.buf.extend_from_slice(&[0, 0, 0]);
+            put_u24(&mut self.buf[pos..], *restart);
+        }
+        self.buf.extend_from_slice(&(self.restarts.len() as u16).to_be_bytes());
+        let block_len = self.buf.len();
+        put_u24(&mut self.buf[self.header_len + 1..], block_len);
+
+        if self.typ == LOG {
+            use std::io::Write;
+            let uncompressed_len = self.header_len + HEADER_LEN;
+            let mut out = self.buf[..uncompressed_len].to_vec();
+            let mut deflate = git_features::zlib::stream::deflate::Write::new(&mut out);
+            deflate.write_all(&self.buf[uncompressed_len..])?;
+            deflate.flush()?;
+            drop(deflate);
+            Ok(out)
+        } else {
+            if pad && self.buf.len() < self.block_size {
+                self.buf.resize(self.block_size, 0);
+            }
+            Ok(self.buf)
+        }
+    }
+}
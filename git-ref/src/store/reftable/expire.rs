@@ -0,0 +1,155 @@
+use std::convert::TryInto;
+
+use git_hash::{oid, ObjectId};
+
+use crate::{
+    file::log::expire::{Options, Outcome},
+    log::Line,
+    store_impl::reftable::{write, LogRecord, Store},
+    FullNameRef,
+};
+
+mod error {
+    use crate::store_impl::reftable::{stack, table, write};
+
+    /// The error returned by [`Store::reflog_expire()`][crate::reftable::Store::reflog_expire()] and
+    /// [`Store::reflog_delete_entry()`][crate::reftable::Store::reflog_delete_entry()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("The reflog name or path is not a valid ref name")]
+        RefnameValidation(#[from] crate::name::Error),
+        #[error("The lock for the list of tables could not be obtained")]
+        LockAcquire(#[source] git_lock::acquire::Error),
+        #[error(transparent)]
+        Load(#[from] stack::load::Error),
+        #[error("A reftable could not be read")]
+        Decode(#[from] table::decode::Error),
+        #[error("The new table could not be created")]
+        Write(#[from] write::Error),
+        #[error("The new table or the list of tables could not be written")]
+        Io(#[from] std::io::Error),
+        #[error("The list of tables could not be moved into place")]
+        Commit(#[from] git_lock::commit::Error<git_lock::File>),
+    }
+}
+pub use error::Error;
+
+impl Store {
+    /// Remove all entries of the reflog of `name` that are expired according to `options`, similar to `git reflog expire`.
+    ///
+    /// This works like [`file::Store::reflog_expire()`][crate::file::Store::reflog_expire()], but removed entries are hidden
+    /// by tombstones in a new table until the stack is compacted, which happens right away if [`Store::auto_compact`] is set.
+    /// Return `Ok(None)` if no reflog exists.
+    pub fn reflog_expire<'a, Name, E>(
+        &self,
+        name: Name,
+        options: Options,
+        mut is_reachable: impl FnMut(&oid) -> bool,
+        lock_mode: git_lock::acquire::Fail,
+    ) -> Result<Option<Outcome>, Error>
+    where
+        Name: TryInto<&'a FullNameRef, Error = E>,
+        crate::name::Error: From<E>,
+    {
+        let name: &FullNameRef = name.try_into().map_err(|err| Error::RefnameValidation(err.into()))?;
+        self.reflog_rewrite(name, lock_mode, options.rewrite, options.dry_run, |_index, line| {
+            let time = line.signature.time.seconds_since_unix_epoch;
+            if options.expire_total.map_or(false, |cutoff| time < cutoff) {
+                return true;
+            }
+            if options.expire_unreachable.map_or(false, |cutoff| time < cutoff) {
+                let mut unreachable = |id: &ObjectId| !id.is_null() && !is_reachable(id);
+                return unreachable(&line.previous_oid) || unreachable(&line.new_oid);
+            }
+            false
+        })
+    }
+
+    /// Remove the entry at `index` from the reflog of `name`, with `0` being the most recent entry just like in `name@{0}`,
+    /// similar to `git reflog delete`.
+    ///
+    /// This works like [`file::Store::reflog_delete_entry()`][crate::file::Store::reflog_delete_entry()].
+    /// Return `Ok(None)` if no reflog exists, and an empty [`Outcome::removed`] field if there was no entry at `index`.
+    pub fn reflog_delete_entry<'a, Name, E>(
+        &self,
+        name: Name,
+        index: usize,
+        rewrite: bool,
+        lock_mode: git_lock::acquire::Fail,
+    ) -> Result<Option<Outcome>, Error>
+    where
+        Name: TryInto<&'a FullNameRef, Error = E>,
+        crate::name::Error: From<E>,
+    {
+        let name: &FullNameRef = name.try_into().map_err(|err| Error::RefnameValidation(err.into()))?;
+        self.reflog_rewrite(name, lock_mode, rewrite, false, |index_from_newest, _line| {
+            index_from_newest == index
+        })
+    }
+
+    /// Write a new table which drops all entries of the reflog of `name` for which `should_remove(index_from_newest, line)`
+    /// returns `true`, while holding the lock on the list of tables.
+    fn reflog_rewrite(
+        &self,
+        name: &FullNameRef,
+        lock_mode: git_lock::acquire::Fail,
+        rewrite: bool,
+        dry_run: bool,
+        mut should_remove: impl FnMut(usize, &Line) -> bool,
+    ) -> Result<Option<Outcome>, Error> {
+        let mut lock = self.lock_tables_list(lock_mode).map_err(Error::LockAcquire)?;
+        let stack = self.stack()?;
+        let records = stack.logs_of(name)?;
+        if records.is_empty() {
+            return Ok(None);
+        }
+
+        let num_records = records.len();
+        let mut logs = Vec::new();
+        let mut outcome = Outcome::default();
+        let mut last_kept_oid = self.object_hash.null();
+        for (index, record) in records.into_iter().rev().enumerate() {
+            let line = record.value.as_ref().expect("tombstones are filtered");
+            if should_remove(num_records - index - 1, line) {
+                outcome.removed.push(line.clone());
+                logs.push(LogRecord { value: None, ..record });
+                continue;
+            }
+            outcome.kept += 1;
+            let new_oid = line.new_oid;
+            if rewrite && line.previous_oid != last_kept_oid {
+                logs.push(LogRecord {
+                    value: Some(Line {
+                        previous_oid: last_kept_oid,
+                        ..line.clone()
+                    }),
+                    ..record
+                });
+            }
+            last_kept_oid = new_oid;
+        }
+
+        if !logs.is_empty() && !dry_run {
+            let update_index = stack.max_update_index() + 1;
+            let data = write::table(
+                &[],
+                &logs,
+                update_index,
+                update_index,
+                self.object_hash,
+                &self.write_options,
+            )?;
+            let table_name = self.write_table_file(&data, update_index, update_index)?;
+            let mut names = stack.names().to_vec();
+            names.push(table_name);
+            Store::write_tables_list(&mut lock, &names)?;
+            lock.commit()?;
+
+            if self.auto_compact {
+                self.auto_compact(git_lock::acquire::Fail::Immediately).ok();
+            }
+        }
+        Ok(Some(outcome))
+    }
+}
@@ -0,0 +1,186 @@
+use std::convert::TryInto;
+
+pub use error::Error;
+
+use crate::{
+    log::Line,
+    store_impl::reftable::{stack::Stack, table::decode, Iter, Store},
+    BString, FullNameRef, PartialNameRef, Reference,
+};
+
+impl Stack {
+    /// Find a reference by its partial name, following the same lookup rules as [`file::Store::try_find()`][crate::file::Store::try_find()].
+    pub(crate) fn find_one_with_verified_input(
+        &self,
+        partial_name: &PartialNameRef,
+    ) -> Result<Option<Reference>, decode::Error> {
+        let mut buf = BString::default();
+        if partial_name.looks_like_full_name() {
+            let full_name = partial_name.construct_full_name_ref(false, "", &mut buf);
+            if let Some(r) = self.find(full_name)? {
+                return Ok(r.into_reference());
+            }
+        }
+
+        for inbetween in &["", "tags", "heads", "remotes"] {
+            let full_name = partial_name.construct_full_name_ref(true, inbetween, &mut buf);
+            if let Some(r) = self.find(full_name)? {
+                return Ok(r.into_reference());
+            }
+        }
+        let partial_name = partial_name.to_owned().join("HEAD").expect("HEAD is valid name");
+        let full_name = partial_name.as_ref().construct_full_name_ref(true, "remotes", &mut buf);
+        Ok(self.find(full_name)?.and_then(|r| r.into_reference()))
+    }
+}
+
+impl Store {
+    /// Find a single reference by the given `path` which is required to be a valid reference name.
+    ///
+    /// Returns `Ok(None)` if no such ref exists.
+    ///
+    /// The lookup algorithm is the same as the one of [`file::Store::try_find()`][crate::file::Store::try_find()].
+    pub fn try_find<'a, Name, E>(&self, partial: Name) -> Result<Option<Reference>, Error>
+    where
+        Name: TryInto<&'a PartialNameRef, Error = E>,
+        Error: From<E>,
+    {
+        let partial = partial.try_into()?;
+        Ok(self.stack()?.find_one_with_verified_input(partial)?)
+    }
+
+    /// Similar to [`Store::try_find()`], but a non-existing ref is treated as error.
+    pub fn find<'a, Name, E>(&self, partial: Name) -> Result<Reference, existing::Error>
+    where
+        Name: TryInto<&'a PartialNameRef, Error = E>,
+        crate::name::Error: From<E>,
+    {
+        let path = partial
+            .try_into()
+            .map_err(|err| Error::RefnameValidation(crate::name::Error::from(err)))?;
+        match self.stack().map_err(Error::from)?.find_one_with_verified_input(path) {
+            Ok(Some(r)) => Ok(r),
+            Ok(None) => Err(existing::Error::NotFound {
+                name: path.as_bstr().to_owned(),
+            }),
+            Err(err) => Err(Error::from(err).into()),
+        }
+    }
+
+    /// Return an iterator over all references in the stack, ordered by name.
+    pub fn iter(&self) -> Result<Iter, Error> {
+        self.iter_prefixed("")
+    }
+
+    /// Return an iterator over all references whose name starts with `prefix`, like `refs/heads/`, ordered by name.
+    pub fn iter_prefixed(&self, prefix: impl AsRef<[u8]>) -> Result<Iter, Error> {
+        let records = self.stack()?.refs_prefixed(prefix.as_ref())?;
+        Ok(Iter {
+            inner: records
+                .into_iter()
+                .filter_map(|r| r.into_reference())
+                .collect::<Vec<_>>()
+                .into_iter(),
+        })
+    }
+
+    /// Return all references pointing to `id`, either directly or because `id` is their peeled value, ordered by name.
+    ///
+    /// The object index of tables is used where available.
+    pub fn refs_by_object(&self, id: &git_hash::oid) -> Result<Vec<Reference>, Error> {
+        Ok(self
+            .stack()?
+            .refs_by_object(id)?
+            .into_iter()
+            .filter_map(|r| r.into_reference())
+            .collect())
+    }
+
+    /// Returns true if a reflog with at least one entry exists for the given reference `name`.
+    pub fn reflog_exists<'a, Name, E>(&self, name: Name) -> Result<bool, Error>
+    where
+        Name: TryInto<&'a FullNameRef, Error = E>,
+        crate::name::Error: From<E>,
+    {
+        let name = name.try_into().map_err(|err| Error::RefnameValidation(err.into()))?;
+        Ok(!self.stack()?.logs_of(name)?.is_empty())
+    }
+
+    /// Return an iterator over the reflog of `name`, from oldest to newest entry, or `None` if there is no reflog.
+    pub fn reflog_iter<'a, Name, E>(&self, name: Name) -> Result<Option<std::vec::IntoIter<Line>>, Error>
+    where
+        Name: TryInto<&'a FullNameRef, Error = E>,
+        crate::name::Error: From<E>,
+    {
+        let mut lines = self.reflog_lines(name)?;
+        Ok(lines.as_mut().map(|lines| {
+            lines.reverse();
+            std::mem::take(lines).into_iter()
+        }))
+    }
+
+    /// Return an iterator over the reflog of `name`, from newest to oldest entry, or `None` if there is no reflog.
+    pub fn reflog_iter_rev<'a, Name, E>(&self, name: Name) -> Result<Option<std::vec::IntoIter<Line>>, Error>
+    where
+        Name: TryInto<&'a FullNameRef, Error = E>,
+        crate::name::Error: From<E>,
+    {
+        Ok(self.reflog_lines(name)?.map(Vec::into_iter))
+    }
+
+    fn reflog_lines<'a, Name, E>(&self, name: Name) -> Result<Option<Vec<Line>>, Error>
+    where
+        Name: TryInto<&'a FullNameRef, Error = E>,
+        crate::name::Error: From<E>,
+    {
+        let name = name.try_into().map_err(|err| Error::RefnameValidation(err.into()))?;
+        let lines: Vec<_> = self
+            .stack()?
+            .logs_of(name)?
+            .into_iter()
+            .filter_map(|record| record.value)
+            .collect();
+        Ok((!lines.is_empty()).then_some(lines))
+    }
+}
+
+mod error {
+    use std::convert::Infallible;
+
+    use crate::store_impl::reftable::{stack, table};
+
+    /// The error returned by [`Store::try_find()`][crate::reftable::Store::try_find()] and other methods reading references.
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("The ref name or path is not a valid ref name")]
+        RefnameValidation(#[from] crate::name::Error),
+        #[error(transparent)]
+        Load(#[from] stack::load::Error),
+        #[error("A reftable could not be read")]
+        Decode(#[from] table::decode::Error),
+    }
+
+    impl From<Infallible> for Error {
+        fn from(_: Infallible) -> Self {
+            unreachable!("this impl is needed to allow passing a known valid partial path as parameter")
+        }
+    }
+}
+
+///
+pub mod existing {
+    use git_object::bstr::BString;
+
+    use crate::store_impl::reftable::find;
+
+    /// The error returned by [`Store::find()`][crate::reftable::Store::find()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("An error occurred while trying to find a reference")]
+        Find(#[from] find::Error),
+        #[error("The ref partially named {name:?} could not be found")]
+        NotFound { name: BString },
+    }
+}
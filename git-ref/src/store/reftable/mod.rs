@@ -0,0 +1,124 @@
+//! An implementation of the [reftable format][spec], a binary format to store references and their logs
+//! in a stack of immutable tables, as used by repositories configured with `extensions.refStorage = reftable`.
+//!
+//! Tables are read fully into memory and are cached by their file name for as long as they are part of the stack.
+//! All changes are made through [transactions][Store::transaction()] using the same [`RefEdit`][crate::transaction::RefEdit]
+//! type as the [file store][crate::file::Store].
+//!
+//! ### Limitations
+//!
+//! * Namespaces and the per-worktree stacks of linked worktrees are not supported.
+//! * Peeled values are read and preserved during compaction, but never computed when writing.
+//!
+//! [spec]: https://git-scm.com/docs/reftable
+use std::{collections::HashMap, path::PathBuf};
+
+use git_features::threading::{MutableOnDemand, OwnShared};
+use git_hash::ObjectId;
+
+use crate::{log::Line, store::WriteReflog, FullName};
+
+/// A store for references which keeps them in a stack of reftables in the `reftable/` directory of a repository.
+#[derive(Debug, Clone)]
+pub struct Store {
+    /// The `.git` directory whose `reftable/` subdirectory contains the stack.
+    git_dir: PathBuf,
+    /// The kind of hash used in all tables of the stack.
+    object_hash: git_hash::Kind,
+    /// The way to handle reflog edits
+    pub write_reflog: WriteReflog,
+    /// Options to use when writing new tables.
+    pub write_options: write::Options,
+    /// If `true`, the default, tables are compacted after each transaction to keep their sizes in a geometric sequence.
+    pub auto_compact: bool,
+    /// Tables by their file name, shared among clones of this instance.
+    tables: OwnShared<MutableOnDemand<HashMap<String, OwnShared<Table>>>>,
+}
+
+/// A single reftable, fully loaded into memory.
+pub struct Table {
+    data: Vec<u8>,
+    header: table::Header,
+    footer: table::Footer,
+}
+
+/// The value of a reference as stored in a [`RefRecord`].
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub enum RefValue {
+    /// The reference was deleted, hiding all records of the same name in older tables.
+    Deletion,
+    /// The reference points to an object.
+    Object(ObjectId),
+    /// The reference points to an object, usually an annotated tag, which ultimately points to `peeled`.
+    Peeled {
+        /// The object the reference points to.
+        target: ObjectId,
+        /// The object the reference points to after following all tags.
+        peeled: ObjectId,
+    },
+    /// The reference points to another reference.
+    Symbolic(FullName),
+}
+
+/// A reference as stored in a reftable.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct RefRecord {
+    /// The full name of the reference.
+    pub name: FullName,
+    /// The update index at which the reference was last changed.
+    pub update_index: u64,
+    /// The value of the reference.
+    pub value: RefValue,
+}
+
+/// A reflog entry as stored in a reftable.
+#[derive(PartialEq, Eq, Debug, Hash, Ord, PartialOrd, Clone)]
+pub struct LogRecord {
+    /// The full name of the reference this log entry belongs to.
+    pub name: FullName,
+    /// The update index of the change this log entry is describing.
+    pub update_index: u64,
+    /// The log entry itself, or `None` if this is a tombstone hiding an entry of the same name and update index in older tables.
+    pub value: Option<Line>,
+}
+
+/// An iterator over references in a [`Store`], ordered by name.
+pub struct Iter {
+    inner: std::vec::IntoIter<crate::Reference>,
+}
+
+impl Iterator for Iter {
+    type Item = crate::Reference;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+/// A transaction on a reftable store, see [`Store::transaction()`].
+pub struct Transaction<'s> {
+    store: &'s Store,
+    lock: Option<git_lock::File>,
+    stack: Option<stack::Stack>,
+    updates: Option<Vec<transaction::Edit>>,
+}
+
+mod block;
+
+///
+pub mod table;
+
+///
+pub mod write;
+
+///
+pub mod stack;
+
+///
+pub mod find;
+
+///
+pub mod transaction;
+
+///
+pub mod expire;
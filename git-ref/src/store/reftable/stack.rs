@@ -0,0 +1,383 @@
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    hash::{BuildHasher, Hasher},
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use git_features::threading::{get_mut, get_ref, OwnShared};
+
+use crate::{
+    store::WriteReflog,
+    store_impl::reftable::{table::decode, write, LogRecord, RefRecord, RefValue, Store, Table},
+    FullName, FullNameRef,
+};
+
+/// The name of the file listing all tables of the stack, oldest first.
+pub(crate) const TABLES_LIST: &str = "tables.list";
+
+/// A snapshot of all tables in a [`Store`], oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct Stack {
+    names: Vec<String>,
+    tables: Vec<OwnShared<Table>>,
+}
+
+///
+pub mod load {
+    use std::path::PathBuf;
+
+    use crate::store_impl::reftable::table;
+
+    /// The error returned by [`Store::stack()`][crate::reftable::Store::stack()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("Could not read {path:?}")]
+        Io { source: std::io::Error, path: PathBuf },
+        #[error("The reftable at {path:?} could not be decoded")]
+        Decode {
+            source: table::decode::Error,
+            path: PathBuf,
+        },
+        #[error("The list of tables kept changing while trying to load them")]
+        Unstable,
+    }
+}
+
+///
+pub mod compact {
+    use crate::store_impl::reftable::{stack, table, write};
+
+    /// The error returned by [`Store::compact_all()`][crate::reftable::Store::compact_all()] and
+    /// [`Store::auto_compact()`][crate::reftable::Store::auto_compact()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("The lock for the list of tables could not be obtained")]
+        LockAcquire(#[source] git_lock::acquire::Error),
+        #[error(transparent)]
+        Load(#[from] stack::load::Error),
+        #[error("A table could not be read while merging it")]
+        Decode(#[from] table::decode::Error),
+        #[error("The compacted table could not be created")]
+        Write(#[from] write::Error),
+        #[error("The compacted table or the list of tables could not be written")]
+        Io(#[from] std::io::Error),
+        #[error("The list of tables could not be moved into place")]
+        Commit(#[from] git_lock::commit::Error<git_lock::File>),
+    }
+}
+
+impl Stack {
+    /// The file names of all tables, oldest first.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// All tables, oldest first.
+    pub fn tables(&self) -> &[OwnShared<Table>] {
+        &self.tables
+    }
+
+    /// The highest update index of all tables, or 0 if there is no table.
+    pub fn max_update_index(&self) -> u64 {
+        self.tables.last().map_or(0, |t| t.max_update_index())
+    }
+
+    /// Find the current record of reference `name`, which is the one in the newest table that contains it.
+    ///
+    /// Return `None` if it doesn't exist or was deleted.
+    pub fn find(&self, name: &FullNameRef) -> Result<Option<RefRecord>, decode::Error> {
+        for table in self.tables.iter().rev() {
+            if let Some(record) = table.find(name)? {
+                return Ok((record.value != RefValue::Deletion).then_some(record));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Return the current records of all references whose name starts with `prefix`, ordered by name.
+    pub fn refs_prefixed(&self, prefix: &[u8]) -> Result<Vec<RefRecord>, decode::Error> {
+        let mut merged = BTreeMap::new();
+        for table in &self.tables {
+            for record in table.refs_prefixed(prefix)? {
+                let record = record?;
+                merged.insert(record.name.clone(), record);
+            }
+        }
+        Ok(merged
+            .into_values()
+            .filter(|record| record.value != RefValue::Deletion)
+            .collect())
+    }
+
+    /// Return all reflog entries of `name` from newest to oldest, without the ones that were deleted.
+    pub fn logs_of(&self, name: &FullNameRef) -> Result<Vec<LogRecord>, decode::Error> {
+        let mut merged = BTreeMap::new();
+        for table in &self.tables {
+            for record in table.logs_of(name)? {
+                let record = record?;
+                merged.insert(Reverse(record.update_index), record);
+            }
+        }
+        Ok(merged.into_values().filter(|record| record.value.is_some()).collect())
+    }
+
+    /// Return the current records of all references pointing to `id`, either directly or as their peeled value.
+    pub fn refs_by_object(&self, id: &git_hash::oid) -> Result<Vec<RefRecord>, decode::Error> {
+        let mut names = Vec::new();
+        for table in &self.tables {
+            names.extend(table.refs_by_object(id)?.into_iter().map(|r| r.name));
+        }
+        names.sort();
+        names.dedup();
+        let mut out = Vec::new();
+        for name in names {
+            if let Some(record) = self.find(name.as_ref())? {
+                let points_to_id = match &record.value {
+                    RefValue::Object(target) => target.as_ref() == id,
+                    RefValue::Peeled { target, peeled } => target.as_ref() == id || peeled.as_ref() == id,
+                    RefValue::Deletion | RefValue::Symbolic(_) => false,
+                };
+                if points_to_id {
+                    out.push(record);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Merge the tables in `range` into the records of a single table, dropping deletions if `range` includes the oldest table.
+    fn merge(&self, range: Range<usize>) -> Result<(Vec<RefRecord>, Vec<LogRecord>), decode::Error> {
+        let is_base = range.start == 0;
+        let mut refs = BTreeMap::<FullName, RefRecord>::new();
+        let mut logs = BTreeMap::<(FullName, Reverse<u64>), LogRecord>::new();
+        for table in &self.tables[range] {
+            for record in table.refs() {
+                let record = record?;
+                refs.insert(record.name.clone(), record);
+            }
+            for record in table.logs() {
+                let record = record?;
+                logs.insert((record.name.clone(), Reverse(record.update_index)), record);
+            }
+        }
+        Ok((
+            refs.into_values()
+                .filter(|record| !is_base || record.value != RefValue::Deletion)
+                .collect(),
+            logs.into_values()
+                .filter(|record| !is_base || record.value.is_some())
+                .collect(),
+        ))
+    }
+}
+
+/// Initialization
+impl Store {
+    /// Create a new instance for the repository at `git_dir`, whose reftables are expected in its `reftable/` subdirectory.
+    /// The `object_hash` defines which kind of hash we should recognize.
+    pub fn at(git_dir: impl Into<PathBuf>, write_reflog: WriteReflog, object_hash: git_hash::Kind) -> Self {
+        Store {
+            git_dir: git_dir.into(),
+            object_hash,
+            write_reflog,
+            write_options: Default::default(),
+            auto_compact: true,
+            tables: Default::default(),
+        }
+    }
+
+    /// Return the `.git` directory of the repository.
+    pub fn git_dir(&self) -> &Path {
+        &self.git_dir
+    }
+
+    /// Return the directory containing all tables as well as the list of tables.
+    pub fn reftable_dir(&self) -> PathBuf {
+        self.git_dir.join("reftable")
+    }
+
+    /// The kind of hash used by all tables.
+    pub fn object_hash(&self) -> git_hash::Kind {
+        self.object_hash
+    }
+}
+
+/// Stack handling
+impl Store {
+    /// Load a snapshot of all tables currently in the stack, reusing tables that were loaded previously.
+    pub fn stack(&self) -> Result<Stack, load::Error> {
+        let dir = self.reftable_dir();
+        let list_path = dir.join(TABLES_LIST);
+        'retry: for _attempt in 0..10 {
+            let names: Vec<String> = match std::fs::read(&list_path) {
+                Ok(buf) => String::from_utf8_lossy(&buf)
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(ToOwned::to_owned)
+                    .collect(),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                Err(err) => {
+                    return Err(load::Error::Io {
+                        source: err,
+                        path: list_path,
+                    })
+                }
+            };
+
+            let mut tables = Vec::with_capacity(names.len());
+            for name in &names {
+                let cached = get_ref(&self.tables).get(name).cloned();
+                let table = match cached {
+                    Some(table) => table,
+                    None => {
+                        let path = dir.join(name);
+                        let data = match std::fs::read(&path) {
+                            Ok(data) => data,
+                            // The table was removed by a concurrent compaction, which also changed the list.
+                            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue 'retry,
+                            Err(err) => return Err(load::Error::Io { source: err, path }),
+                        };
+                        let table = OwnShared::new(
+                            Table::from_bytes(data, self.object_hash)
+                                .map_err(|source| load::Error::Decode { source, path })?,
+                        );
+                        get_mut(&self.tables).insert(name.clone(), OwnShared::clone(&table));
+                        table
+                    }
+                };
+                tables.push(table);
+            }
+            get_mut(&self.tables).retain(|name, _| names.contains(name));
+            return Ok(Stack { names, tables });
+        }
+        Err(load::Error::Unstable)
+    }
+
+    pub(crate) fn lock_tables_list(
+        &self,
+        lock_mode: git_lock::acquire::Fail,
+    ) -> Result<git_lock::File, git_lock::acquire::Error> {
+        git_lock::File::acquire_to_update_resource(
+            self.reftable_dir().join(TABLES_LIST),
+            lock_mode,
+            Some(self.git_dir.clone()),
+        )
+    }
+
+    /// Write `data` into a new table file for the given update index range and return its name.
+    pub(crate) fn write_table_file(
+        &self,
+        data: &[u8],
+        min_update_index: u64,
+        max_update_index: u64,
+    ) -> std::io::Result<String> {
+        let dir = self.reftable_dir();
+        let suffix = std::collections::hash_map::RandomState::new().build_hasher().finish() as u32;
+        let name = format!("0x{min_update_index:012x}-0x{max_update_index:012x}-{suffix:08x}.ref");
+        let mut file = git_tempfile::new(
+            &dir,
+            git_tempfile::ContainingDirectory::Exists,
+            git_tempfile::AutoRemove::Tempfile,
+        )?;
+        file.with_mut(|file| file.write_all(data))??;
+        file.persist(dir.join(&name)).map_err(|err| err.error)?;
+        Ok(name)
+    }
+
+    pub(crate) fn write_tables_list(lock: &mut git_lock::File, names: &[String]) -> std::io::Result<()> {
+        lock.with_mut(|file| {
+            for name in names {
+                writeln!(file, "{name}")?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Merge all tables of the stack into a single one, removing all deletions and deleted reflog entries in the process.
+    ///
+    /// Return `Ok(false)` if there was nothing to compact.
+    pub fn compact_all(&self, lock_mode: git_lock::acquire::Fail) -> Result<bool, compact::Error> {
+        self.compact(lock_mode, |stack| Some(0..stack.tables.len()))
+    }
+
+    /// Merge tables so that the size of each table is at least twice as large as the size of the next newer table,
+    /// which keeps the amount of tables logarithmic to the amount of changes.
+    ///
+    /// This happens automatically after each transaction if [`Store::auto_compact`] is set.
+    /// Return `Ok(false)` if there was nothing to compact.
+    pub fn auto_compact(&self, lock_mode: git_lock::acquire::Fail) -> Result<bool, compact::Error> {
+        self.compact(lock_mode, |stack| {
+            let sizes: Vec<_> = stack.tables.iter().map(|t| t.blocks_len()).collect();
+            suggest_compaction_segment(&sizes, 2)
+        })
+    }
+
+    fn compact(
+        &self,
+        lock_mode: git_lock::acquire::Fail,
+        select: impl FnOnce(&Stack) -> Option<Range<usize>>,
+    ) -> Result<bool, compact::Error> {
+        let mut lock = self.lock_tables_list(lock_mode).map_err(compact::Error::LockAcquire)?;
+        let stack = self.stack()?;
+        let range = match select(&stack) {
+            Some(range) if range.len() > 1 || (range.len() == 1 && range.start == 0) => range,
+            _ => return Ok(false),
+        };
+
+        let (refs, logs) = stack.merge(range.clone())?;
+        let min_update_index = stack.tables[range.start].min_update_index();
+        let max_update_index = stack.tables[range.end - 1].max_update_index();
+        let data = write::table(
+            &refs,
+            &logs,
+            min_update_index,
+            max_update_index,
+            self.object_hash,
+            &self.write_options,
+        )?;
+        let name = self.write_table_file(&data, min_update_index, max_update_index)?;
+
+        let mut names = stack.names[..range.start].to_vec();
+        names.push(name);
+        names.extend_from_slice(&stack.names[range.end..]);
+        Self::write_tables_list(&mut lock, &names)?;
+        lock.commit()?;
+
+        let dir = self.reftable_dir();
+        for name in &stack.names[range] {
+            std::fs::remove_file(dir.join(name)).ok();
+        }
+        get_mut(&self.tables).retain(|name, _| names.contains(name));
+        Ok(true)
+    }
+}
+
+/// Return the range of tables to compact so that the sizes of the tables, oldest first, decrease geometrically by `factor`.
+fn suggest_compaction_segment(sizes: &[u64], factor: u64) -> Option<Range<usize>> {
+    if sizes.len() < 2 {
+        return None;
+    }
+    let mut end = None;
+    for i in (1..sizes.len()).rev() {
+        if sizes[i - 1] < sizes[i] * factor {
+            end = Some((i + 1, sizes[i]));
+            break;
+        }
+    }
+    let (end, mut bytes) = end?;
+    let mut start = end - 1;
+    while start > 0 {
+        let previous = start - 1;
+        if bytes * factor <= sizes[previous] {
+            break;
+        }
+        start = previous;
+        bytes += sizes[previous];
+    }
+    Some(start..end)
+}
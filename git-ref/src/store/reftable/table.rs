@@ -0,0 +1,658 @@
+use std::convert::TryFrom;
+
+use git_hash::ObjectId;
+use git_object::bstr::{BString, ByteSlice};
+
+use crate::{
+    log::Line,
+    store_impl::{
+        reftable,
+        reftable::{
+            block,
+            block::{get_bytes, get_varint, Block},
+            LogRecord, RefRecord, RefValue, Table,
+        },
+    },
+    FullName, FullNameRef,
+};
+
+pub(crate) const MAGIC: &[u8] = b"REFT";
+
+/// The information in the header of a table, which is repeated in its footer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Header {
+    pub version: u8,
+    pub block_size: u32,
+    pub min_update_index: u64,
+    pub max_update_index: u64,
+    pub object_hash: git_hash::Kind,
+}
+
+impl Header {
+    pub fn len(&self) -> usize {
+        header_len(self.version)
+    }
+
+    pub fn write_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(MAGIC);
+        out.push(self.version);
+        out.extend_from_slice(&self.block_size.to_be_bytes()[1..]);
+        out.extend_from_slice(&self.min_update_index.to_be_bytes());
+        out.extend_from_slice(&self.max_update_index.to_be_bytes());
+        if self.version == 2 {
+            out.extend_from_slice(match self.object_hash {
+                git_hash::Kind::Sha1 => b"sha1",
+                git_hash::Kind::Sha256 => b"s256",
+            });
+        }
+    }
+
+    fn from_bytes(data: &[u8]) -> Result<Self, decode::Error> {
+        if data.len() < header_len(1) || &data[..4] != MAGIC {
+            return Err(decode::Error::InvalidHeader);
+        }
+        let version = data[4];
+        let object_hash = match version {
+            1 => git_hash::Kind::Sha1,
+            2 => match data.get(24..28).ok_or(decode::Error::InvalidHeader)? {
+                b"sha1" => git_hash::Kind::Sha1,
+                b"s256" => git_hash::Kind::Sha256,
+                unknown => return Err(decode::Error::UnsupportedHash(unknown.into())),
+            },
+            version => return Err(decode::Error::UnsupportedVersion(version)),
+        };
+        Ok(Header {
+            version,
+            block_size: block::get_u24(&data[5..]) as u32,
+            min_update_index: u64::from_be_bytes(data[8..16].try_into().expect("8 bytes")),
+            max_update_index: u64::from_be_bytes(data[16..24].try_into().expect("8 bytes")),
+            object_hash,
+        })
+    }
+}
+
+pub(crate) fn header_len(version: u8) -> usize {
+    if version == 2 {
+        28
+    } else {
+        24
+    }
+}
+
+pub(crate) fn footer_len(version: u8) -> usize {
+    header_len(version) + 5 * 8 + 4
+}
+
+/// The positions of the sections of a table as stored in its footer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Footer {
+    pub ref_index_pos: u64,
+    pub obj_pos: u64,
+    pub obj_id_len: u8,
+    pub obj_index_pos: u64,
+    pub log_pos: u64,
+    pub log_index_pos: u64,
+}
+
+impl Footer {
+    pub fn write_to(&self, header: &Header, out: &mut Vec<u8>) {
+        let start = out.len();
+        header.write_to(out);
+        for value in [
+            self.ref_index_pos,
+            self.obj_pos << 5 | u64::from(self.obj_id_len),
+            self.obj_index_pos,
+            self.log_pos,
+            self.log_index_pos,
+        ] {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        let crc = git_features::hash::crc32(&out[start..]);
+        out.extend_from_slice(&crc.to_be_bytes());
+    }
+}
+
+///
+pub mod decode {
+    use git_object::bstr::BString;
+
+    /// The error returned when reading a [`Table`][crate::reftable::Table].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("The data is not a reftable or it is truncated")]
+        InvalidHeader,
+        #[error("Reftable version {0} is not supported")]
+        UnsupportedVersion(u8),
+        #[error("The hash with identifier {0:?} is not supported")]
+        UnsupportedHash(BString),
+        #[error("The table was written with hash {actual}, but {expected} was expected")]
+        ObjectHashMismatch {
+            expected: git_hash::Kind,
+            actual: git_hash::Kind,
+        },
+        #[error("The footer does not match the header or its checksum is invalid")]
+        InvalidFooter,
+        #[error("The table is corrupt at offset {offset}: {message}")]
+        Corrupt { offset: usize, message: &'static str },
+        #[error("The log block at offset {offset} could not be inflated")]
+        Inflate {
+            offset: usize,
+            source: git_features::zlib::inflate::Error,
+        },
+    }
+}
+
+impl std::fmt::Debug for Table {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Table")
+            .field("len", &self.data.len())
+            .field("header", &self.header)
+            .field("footer", &self.footer)
+            .finish()
+    }
+}
+
+/// Initialization and information
+impl Table {
+    /// Parse `data` as reftable whose objects use `object_hash`, validating its header and footer.
+    pub fn from_bytes(data: Vec<u8>, object_hash: git_hash::Kind) -> Result<Self, decode::Error> {
+        let header = Header::from_bytes(&data)?;
+        if header.object_hash != object_hash {
+            return Err(decode::Error::ObjectHashMismatch {
+                expected: object_hash,
+                actual: header.object_hash,
+            });
+        }
+        let footer_len = footer_len(header.version);
+        if data.len() < header.len() + footer_len {
+            return Err(decode::Error::InvalidHeader);
+        }
+        let footer = &data[data.len() - footer_len..];
+        let (footer_data, crc) = footer.split_at(footer_len - 4);
+        if footer_data[..header.len()] != data[..header.len()]
+            || git_features::hash::crc32(footer_data).to_be_bytes() != crc
+        {
+            return Err(decode::Error::InvalidFooter);
+        }
+        let values = &footer_data[header.len()..];
+        let value_at = |index: usize| u64::from_be_bytes(values[index * 8..][..8].try_into().expect("8 bytes"));
+        let footer = Footer {
+            ref_index_pos: value_at(0),
+            obj_pos: value_at(1) >> 5,
+            obj_id_len: (value_at(1) & 0x1f) as u8,
+            obj_index_pos: value_at(2),
+            log_pos: value_at(3),
+            log_index_pos: value_at(4),
+        };
+        Ok(Table { data, header, footer })
+    }
+
+    /// The smallest update index of all records in this table.
+    pub fn min_update_index(&self) -> u64 {
+        self.header.min_update_index
+    }
+
+    /// The largest update index of all records in this table.
+    pub fn max_update_index(&self) -> u64 {
+        self.header.max_update_index
+    }
+
+    /// The size to which blocks are padded, or 0 if blocks are not aligned.
+    pub fn block_size(&self) -> u32 {
+        self.header.block_size
+    }
+
+    /// The kind of hash used for all object ids in this table.
+    pub fn object_hash(&self) -> git_hash::Kind {
+        self.header.object_hash
+    }
+
+    /// Returns `true` if this table has an object index to quickly find references by the object they point to.
+    pub fn has_object_index(&self) -> bool {
+        self.footer.obj_pos != 0
+    }
+
+    /// The raw bytes of this table.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The amount of bytes used by blocks, excluding header and footer, as used to decide which tables to compact.
+    pub(crate) fn blocks_len(&self) -> u64 {
+        (self.data.len() - footer_len(self.header.version)) as u64 - self.header.len() as u64
+    }
+}
+
+/// Access
+impl Table {
+    /// Return an iterator over all reference records in this table, including deletions, ordered by name.
+    pub fn refs(&self) -> Refs<'_> {
+        Refs {
+            section: Section::new(self, block::REF, self.section_start(block::REF)),
+            prefix: None,
+        }
+    }
+
+    /// Return an iterator over all reference records whose name starts with `prefix`, ordered by name.
+    pub fn refs_prefixed(&self, prefix: &[u8]) -> Result<Refs<'_>, decode::Error> {
+        Ok(Refs {
+            section: self.seek(block::REF, prefix)?,
+            prefix: Some(prefix.into()),
+        })
+    }
+
+    /// Return an iterator over all log records in this table, ordered by name and then from newest to oldest.
+    pub fn logs(&self) -> Logs<'_> {
+        Logs {
+            section: Section::new(self, block::LOG, self.section_start(block::LOG)),
+            name: None,
+        }
+    }
+
+    /// Return an iterator over all log records of the reference `name`, from newest to oldest.
+    pub fn logs_of(&self, name: &FullNameRef) -> Result<Logs<'_>, decode::Error> {
+        let mut key: BString = name.as_bstr().into();
+        key.push(0);
+        Ok(Logs {
+            section: self.seek(block::LOG, &key)?,
+            name: Some(key),
+        })
+    }
+
+    /// Find the record of the reference `name`, which may also be a deletion.
+    pub fn find(&self, name: &FullNameRef) -> Result<Option<RefRecord>, decode::Error> {
+        let mut section = self.seek(block::REF, name.as_bstr())?;
+        match section.next_record(|key, value_type, input| self.decode_ref(key, value_type, input)) {
+            Some(Ok(record)) if record.name.as_ref() == name => Ok(Some(record)),
+            Some(Err(err)) => Err(err),
+            _ => Ok(None),
+        }
+    }
+
+    /// Return all reference records in this table that point to `id`, either directly or as their peeled value.
+    ///
+    /// The object index is used if present, otherwise all references are scanned.
+    pub fn refs_by_object(&self, id: &git_hash::oid) -> Result<Vec<RefRecord>, decode::Error> {
+        let points_to_id = |record: &RefRecord| match &record.value {
+            RefValue::Object(target) => target.as_ref() == id,
+            RefValue::Peeled { target, peeled } => target.as_ref() == id || peeled.as_ref() == id,
+            RefValue::Deletion | RefValue::Symbolic(_) => false,
+        };
+        if self.footer.obj_pos == 0 {
+            return self
+                .refs()
+                .filter(|res| res.as_ref().map_or(true, points_to_id))
+                .collect();
+        }
+
+        let prefix = &id.as_bytes()[..usize::from(self.footer.obj_id_len).min(id.as_bytes().len())];
+        let mut section = self.seek(block::OBJ, prefix)?;
+        let positions = match section.next_record(|key, value_type, input| {
+            let mut count = u64::from(value_type);
+            if count == 0 {
+                count = get_varint(input)?;
+            }
+            let mut positions = Vec::with_capacity(count as usize);
+            let mut position = 0;
+            for _ in 0..count {
+                position += get_varint(input)?;
+                positions.push(position as usize);
+            }
+            Some((key == prefix).then_some(positions))
+        }) {
+            Some(Ok(Some(positions))) => positions,
+            Some(Err(err)) => return Err(err),
+            _ => return Ok(Vec::new()),
+        };
+
+        let mut out = Vec::new();
+        for position in positions {
+            let mut block = self.block_at(position)?.ok_or(decode::Error::Corrupt {
+                offset: position,
+                message: "object index points past the end of the table",
+            })?;
+            while let Some(record) = block.next(|key, value_type, input| self.decode_ref(key, value_type, input)) {
+                let record = record?;
+                if points_to_id(&record) {
+                    out.push(record);
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Decoding
+impl Table {
+    fn footer_start(&self) -> usize {
+        self.data.len() - footer_len(self.header.version)
+    }
+
+    fn first_block_type(&self) -> Option<u8> {
+        let pos = self.header.len();
+        (pos < self.footer_start()).then(|| self.data[pos])
+    }
+
+    fn section_start(&self, typ: u8) -> Option<usize> {
+        match typ {
+            block::REF => (self.first_block_type() == Some(block::REF)).then_some(0),
+            block::OBJ => (self.footer.obj_pos > 0).then_some(self.footer.obj_pos as usize),
+            block::LOG => match self.footer.log_pos {
+                0 => (self.first_block_type() == Some(block::LOG)).then_some(0),
+                pos => Some(pos as usize),
+            },
+            _ => None,
+        }
+    }
+
+    fn index_pos(&self, typ: u8) -> u64 {
+        match typ {
+            block::REF => self.footer.ref_index_pos,
+            block::OBJ => self.footer.obj_index_pos,
+            block::LOG => self.footer.log_index_pos,
+            _ => 0,
+        }
+    }
+
+    /// Read the block at `offset`, or return `None` if it is past the last block.
+    pub(crate) fn block_at(&self, offset: usize) -> Result<Option<Block<'_>>, decode::Error> {
+        if offset >= self.footer_start() {
+            return Ok(None);
+        }
+        let header_len = if offset == 0 { self.header.len() } else { 0 };
+        Block::at(
+            &self.data[..self.footer_start()],
+            offset,
+            header_len,
+            self.header.block_size as usize,
+        )
+        .map(Some)
+    }
+
+    /// Return a section of type `typ` positioned at the first record whose key is equal or greater than `key`,
+    /// using the section index if available.
+    fn seek(&self, typ: u8, key: &[u8]) -> Result<Section<'_>, decode::Error> {
+        let start = match self.section_start(typ) {
+            Some(start) => start,
+            None => return Ok(Section::new(self, typ, None)),
+        };
+        let skip_value = |value_type: u8, input: &mut &[u8]| self.skip_value(typ, value_type, input);
+
+        let index_pos = self.index_pos(typ);
+        if index_pos > 0 {
+            let mut offset = index_pos as usize;
+            loop {
+                let mut block = self.block_at(offset)?.ok_or(decode::Error::Corrupt {
+                    offset,
+                    message: "index points past the end of the table",
+                })?;
+                if block.typ == typ {
+                    let found = block.seek(key, skip_value)?;
+                    let next_offset = offset + block.full_len;
+                    return Ok(Section {
+                        table: self,
+                        typ,
+                        block: found.then_some(block),
+                        next_offset: Some(next_offset),
+                    });
+                }
+                if block.typ != block::INDEX || !block.seek(key, |_, input| get_varint(input).map(|_| ()))? {
+                    return Ok(Section::new(self, typ, None));
+                }
+                offset = match block.next(|_key, _value_type, input| get_varint(input)) {
+                    Some(res) => res? as usize,
+                    None => return Ok(Section::new(self, typ, None)),
+                };
+            }
+        }
+
+        let mut offset = start;
+        let mut candidate = None;
+        while let Some(block) = self.block_at(offset)? {
+            if block.typ != typ || block.first_key().map_or(true, |first_key| first_key > key) {
+                break;
+            }
+            let next_offset = offset + block.full_len;
+            candidate = Some((block, next_offset));
+            offset = next_offset;
+        }
+        Ok(match candidate {
+            None => Section::new(self, typ, Some(start)),
+            Some((mut block, next_offset)) => {
+                let found = block.seek(key, skip_value)?;
+                Section {
+                    table: self,
+                    typ,
+                    block: found.then_some(block),
+                    next_offset: Some(next_offset),
+                }
+            }
+        })
+    }
+
+    fn skip_value(&self, typ: u8, value_type: u8, input: &mut &[u8]) -> Option<()> {
+        match typ {
+            block::REF => self.decode_ref_value(value_type, input).map(|_| ()),
+            block::LOG => self.decode_log_value(value_type, input).map(|_| ()),
+            block::OBJ => {
+                let mut count = u64::from(value_type);
+                if count == 0 {
+                    count = get_varint(input)?;
+                }
+                for _ in 0..count {
+                    get_varint(input)?;
+                }
+                Some(())
+            }
+            _ => get_varint(input).map(|_| ()),
+        }
+    }
+
+    fn decode_id(&self, input: &mut &[u8]) -> Option<ObjectId> {
+        get_bytes(input, self.header.object_hash.len_in_bytes())
+            .and_then(|bytes| git_hash::oid::try_from_bytes(bytes).ok())
+            .map(ToOwned::to_owned)
+    }
+
+    fn decode_ref_value(&self, value_type: u8, input: &mut &[u8]) -> Option<(u64, RefValue)> {
+        let update_index = self.header.min_update_index.checked_add(get_varint(input)?)?;
+        let value = match value_type {
+            0 => RefValue::Deletion,
+            1 => RefValue::Object(self.decode_id(input)?),
+            2 => RefValue::Peeled {
+                target: self.decode_id(input)?,
+                peeled: self.decode_id(input)?,
+            },
+            3 => {
+                let len = get_varint(input)? as usize;
+                let target = get_bytes(input, len)?;
+                RefValue::Symbolic(FullName::try_from(target.as_bstr()).ok()?)
+            }
+            _ => return None,
+        };
+        Some((update_index, value))
+    }
+
+    fn decode_ref(&self, key: &[u8], value_type: u8, input: &mut &[u8]) -> Option<RefRecord> {
+        let (update_index, value) = self.decode_ref_value(value_type, input)?;
+        Some(RefRecord {
+            name: FullName::try_from(key.as_bstr()).ok()?,
+            update_index,
+            value,
+        })
+    }
+
+    fn decode_log_value(&self, value_type: u8, input: &mut &[u8]) -> Option<Option<Line>> {
+        if value_type == 0 {
+            return Some(None);
+        }
+        let previous_oid = self.decode_id(input)?;
+        let new_oid = self.decode_id(input)?;
+        let mut string = || {
+            let len = get_varint(input)? as usize;
+            get_bytes(input, len)
+        };
+        let name = string()?.as_bstr().to_owned();
+        let email = string()?.as_bstr().to_owned();
+        let seconds = get_varint(input)?;
+        // Like git, the offset is stored as decimal `HHMM`, i.e. `-0130` is `-130`.
+        let tz = i16::from_be_bytes(get_bytes(input, 2)?.try_into().expect("2 bytes"));
+        let tz_minutes = i32::from(tz.unsigned_abs() / 100) * 60 + i32::from(tz.unsigned_abs() % 100);
+        let len = get_varint(input)? as usize;
+        let message = get_bytes(input, len)?;
+        Some(Some(Line {
+            previous_oid,
+            new_oid,
+            signature: git_actor::Signature {
+                name,
+                email,
+                time: git_actor::Time {
+                    seconds_since_unix_epoch: u32::try_from(seconds).ok()?,
+                    offset_in_seconds: tz_minutes * 60 * i32::from(tz.signum()),
+                    sign: if tz < 0 {
+                        git_actor::Sign::Minus
+                    } else {
+                        git_actor::Sign::Plus
+                    },
+                },
+            },
+            message: message.strip_suffix(b"\n").unwrap_or(message).as_bstr().to_owned(),
+        }))
+    }
+
+    fn decode_log(&self, key: &[u8], value_type: u8, input: &mut &[u8]) -> Option<LogRecord> {
+        if key.len() < 9 || key[key.len() - 9] != 0 {
+            return None;
+        }
+        let (name, update_index) = key.split_at(key.len() - 9);
+        Some(LogRecord {
+            name: FullName::try_from(name.as_bstr()).ok()?,
+            update_index: u64::MAX - u64::from_be_bytes(update_index[1..].try_into().expect("8 bytes")),
+            value: self.decode_log_value(value_type, input)?,
+        })
+    }
+}
+
+/// A sequence of blocks of the same type, read one record at a time.
+struct Section<'a> {
+    table: &'a Table,
+    typ: u8,
+    block: Option<Block<'a>>,
+    next_offset: Option<usize>,
+}
+
+impl<'a> Section<'a> {
+    fn new(table: &'a Table, typ: u8, offset: Option<usize>) -> Self {
+        Section {
+            table,
+            typ,
+            block: None,
+            next_offset: offset,
+        }
+    }
+
+    fn next_record<T>(
+        &mut self,
+        mut decode: impl FnMut(&[u8], u8, &mut &[u8]) -> Option<T>,
+    ) -> Option<Result<T, decode::Error>> {
+        loop {
+            if let Some(block) = &mut self.block {
+                match block.next(&mut decode) {
+                    Some(res) => {
+                        if res.is_err() {
+                            self.block = None;
+                            self.next_offset = None;
+                        }
+                        return Some(res);
+                    }
+                    None => self.block = None,
+                }
+            }
+            let offset = self.next_offset.take()?;
+            match self.table.block_at(offset) {
+                Ok(Some(block)) if block.typ == self.typ => {
+                    self.next_offset = Some(offset + block.full_len);
+                    self.block = Some(block);
+                }
+                Ok(_) => return None,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// An iterator over the reference records of a [`Table`].
+pub struct Refs<'a> {
+    section: Section<'a>,
+    /// If set, only references whose name starts with this prefix are returned.
+    prefix: Option<BString>,
+}
+
+impl<'a> Iterator for Refs<'a> {
+    type Item = Result<RefRecord, decode::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let table = self.section.table;
+        let prefix = self.prefix.as_ref();
+        let mut is_done = false;
+        let res = self.section.next_record(|key, value_type, input| {
+            if prefix.map_or(false, |prefix| !key.starts_with(prefix)) {
+                is_done = true;
+                return Some(None);
+            }
+            table.decode_ref(key, value_type, input).map(Some)
+        })?;
+        if is_done {
+            self.section.block = None;
+            self.section.next_offset = None;
+        }
+        res.transpose()
+    }
+}
+
+/// An iterator over the log records of a [`Table`].
+pub struct Logs<'a> {
+    section: Section<'a>,
+    /// If set, the key prefix of the reference whose logs to return.
+    name: Option<BString>,
+}
+
+impl<'a> Iterator for Logs<'a> {
+    type Item = Result<LogRecord, decode::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let table = self.section.table;
+        let name = self.name.as_ref();
+        let mut is_done = false;
+        let res = self.section.next_record(|key, value_type, input| {
+            if name.map_or(false, |name| !key.starts_with(name)) {
+                is_done = true;
+                return Some(None);
+            }
+            table.decode_log(key, value_type, input).map(Some)
+        })?;
+        if is_done {
+            self.section.block = None;
+            self.section.next_offset = None;
+        }
+        res.transpose()
+    }
+}
+
+impl reftable::RefRecord {
+    /// Convert this record into a reference, or return `None` if it is a deletion.
+    pub fn into_reference(self) -> Option<crate::Reference> {
+        let (target, peeled) = match self.value {
+            RefValue::Deletion => return None,
+            RefValue::Object(id) => (crate::Target::Peeled(id), None),
+            RefValue::Peeled { target, peeled } => (crate::Target::Peeled(target), Some(peeled)),
+            RefValue::Symbolic(name) => (crate::Target::Symbolic(name), None),
+        };
+        Some(crate::Reference {
+            name: self.name,
+            target,
+            peeled,
+        })
+    }
+}
@@ -0,0 +1,577 @@
+use git_hash::ObjectId;
+use git_object::bstr::{BStr, ByteSlice};
+
+use crate::{
+    log::Line,
+    store::WriteReflog,
+    store_impl::reftable::{stack::Stack, table::decode, write, LogRecord, RefRecord, RefValue, Store, Transaction},
+    transaction::{Change, PreviousValue, RefEdit, RefEditsExt, RefLog},
+    FullName, FullNameRef, Target,
+};
+
+#[derive(Debug)]
+pub(crate) struct Edit {
+    update: RefEdit,
+    /// Set if this update is coming from a symbolic reference and used to make it appear like it is the one that is handled,
+    /// instead of the referent reference.
+    parent_index: Option<usize>,
+    /// For symbolic refs, this is the previous OID to put into the reflog instead of our own previous value. It's the
+    /// peeled value of the leaf referent.
+    leaf_referent_previous_oid: Option<ObjectId>,
+}
+
+impl std::borrow::Borrow<RefEdit> for Edit {
+    fn borrow(&self) -> &RefEdit {
+        &self.update
+    }
+}
+
+impl std::borrow::BorrowMut<RefEdit> for Edit {
+    fn borrow_mut(&mut self) -> &mut RefEdit {
+        &mut self.update
+    }
+}
+
+/// Edits
+impl Store {
+    /// Open a transaction, which locks the stack of tables when it is [prepared][Transaction::prepare()] and adds a single table
+    /// with all changes when it is [committed][Transaction::commit()].
+    pub fn transaction(&self) -> Transaction<'_> {
+        Transaction {
+            store: self,
+            lock: None,
+            stack: None,
+            updates: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for Transaction<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Transaction")
+            .field("store", self.store)
+            .field("edits", &self.updates.as_ref().map(|u| u.len()))
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'s> Transaction<'s> {
+    /// Prepare for calling [`commit(…)`][Transaction::commit()] by locking the list of tables using `lock_mode`, and validating
+    /// all `edits` against the current state of references.
+    ///
+    /// The validation and the way `edits` are adjusted is the same as in the [file store][crate::file::Transaction::prepare()].
+    /// If the operation succeeds, the transaction can be committed or dropped to cause a rollback automatically.
+    pub fn prepare(
+        mut self,
+        edits: impl IntoIterator<Item = RefEdit>,
+        lock_mode: git_lock::acquire::Fail,
+    ) -> Result<Self, prepare::Error> {
+        use prepare::Error;
+        assert!(self.updates.is_none(), "BUG: Must not call prepare(…) multiple times");
+        let lock = self.store.lock_tables_list(lock_mode).map_err(Error::LockAcquire)?;
+        let stack = self.store.stack()?;
+
+        let mut updates: Vec<_> = edits
+            .into_iter()
+            .map(|update| Edit {
+                update,
+                parent_index: None,
+                leaf_referent_previous_oid: None,
+            })
+            .collect();
+        updates
+            .pre_process(
+                |name| {
+                    stack
+                        .find_one_with_verified_input(name)
+                        .ok()
+                        .flatten()
+                        .map(|r| r.target)
+                },
+                |idx, update| Edit {
+                    update,
+                    parent_index: Some(idx),
+                    leaf_referent_previous_oid: None,
+                },
+            )
+            .map_err(Error::PreprocessingFailed)?;
+
+        for cid in 0..updates.len() {
+            let existing = stack
+                .find(updates[cid].update.name.as_ref())?
+                .and_then(|r| r.into_reference())
+                .map(|r| r.target);
+            let clash = match (&updates[cid].update.change, &existing) {
+                (Change::Update { .. } | Change::Rename { .. }, None) => clashing_reference(&stack, &updates, cid)?,
+                _ => None,
+            };
+            let change = &mut updates[cid];
+            let full_name = || change.update.name.as_bstr().to_owned();
+            match &mut change.update.change {
+                Change::Delete { expected, .. } => {
+                    match (&expected, &existing) {
+                        (PreviousValue::MustNotExist, _) => {
+                            panic!("BUG: MustNotExist constraint makes no sense if references are to be deleted")
+                        }
+                        (PreviousValue::ExistingMustMatch(_), None)
+                        | (PreviousValue::MustExist, Some(_))
+                        | (PreviousValue::Any, _) => {}
+                        (PreviousValue::MustExist, None) | (PreviousValue::MustExistAndMatch(_), None) => {
+                            return Err(Error::DeleteReferenceMustExist { full_name: full_name() })
+                        }
+                        (PreviousValue::MustExistAndMatch(previous), Some(actual))
+                        | (PreviousValue::ExistingMustMatch(previous), Some(actual)) => {
+                            if previous != actual {
+                                return Err(Error::ReferenceOutOfDate {
+                                    full_name: full_name(),
+                                    expected: previous.clone(),
+                                    actual: actual.clone(),
+                                });
+                            }
+                        }
+                    }
+                    if let Some(existing) = existing {
+                        *expected = PreviousValue::MustExistAndMatch(existing);
+                    }
+                }
+                Change::Update { expected, new, .. } => {
+                    match (&expected, &existing) {
+                        (PreviousValue::Any, _)
+                        | (PreviousValue::MustExist, Some(_))
+                        | (PreviousValue::MustNotExist, None)
+                        | (PreviousValue::ExistingMustMatch(_), None) => {}
+                        (PreviousValue::MustExist, None) => {
+                            return Err(Error::MustExist {
+                                full_name: full_name(),
+                                expected: Target::Peeled(self.store.object_hash.null()),
+                            });
+                        }
+                        (PreviousValue::MustNotExist, Some(actual)) => {
+                            if actual != new {
+                                return Err(Error::MustNotExist {
+                                    full_name: full_name(),
+                                    actual: actual.clone(),
+                                    new: new.clone(),
+                                });
+                            }
+                        }
+                        (PreviousValue::MustExistAndMatch(previous), Some(actual))
+                        | (PreviousValue::ExistingMustMatch(previous), Some(actual)) => {
+                            if previous != actual {
+                                return Err(Error::ReferenceOutOfDate {
+                                    full_name: full_name(),
+                                    expected: previous.clone(),
+                                    actual: actual.clone(),
+                                });
+                            }
+                        }
+                        (PreviousValue::MustExistAndMatch(previous), None) => {
+                            return Err(Error::MustExist {
+                                full_name: full_name(),
+                                expected: previous.clone(),
+                            });
+                        }
+                    }
+                    if let Some(existing) = clash {
+                        return Err(Error::NameClash {
+                            full_name: full_name(),
+                            existing: existing.into_inner(),
+                        });
+                    }
+                    if let Some(existing) = existing {
+                        *expected = PreviousValue::MustExistAndMatch(existing);
+                    }
+                }
+                Change::Rename { source, expected, .. } => {
+                    let source_target = stack
+                        .find(source.as_ref())?
+                        .and_then(|r| r.into_reference())
+                        .map(|r| r.target);
+                    let source_target = match (&expected, source_target) {
+                        (PreviousValue::MustNotExist, _) => {
+                            panic!("BUG: MustNotExist constraint makes no sense if references are to be renamed")
+                        }
+                        (
+                            PreviousValue::MustExistAndMatch(previous) | PreviousValue::ExistingMustMatch(previous),
+                            None,
+                        ) => {
+                            return Err(Error::MustExist {
+                                full_name: source.as_bstr().to_owned(),
+                                expected: previous.clone(),
+                            })
+                        }
+                        (PreviousValue::MustExist | PreviousValue::Any, None) => {
+                            return Err(Error::MustExist {
+                                full_name: source.as_bstr().to_owned(),
+                                expected: Target::Peeled(self.store.object_hash.null()),
+                            })
+                        }
+                        (
+                            PreviousValue::MustExistAndMatch(previous) | PreviousValue::ExistingMustMatch(previous),
+                            Some(actual),
+                        ) if *previous != actual => {
+                            return Err(Error::ReferenceOutOfDate {
+                                full_name: source.as_bstr().to_owned(),
+                                expected: previous.clone(),
+                                actual,
+                            })
+                        }
+                        (_, Some(actual)) => actual,
+                    };
+                    if let Some(actual) = existing {
+                        return Err(Error::MustNotExist {
+                            full_name: full_name(),
+                            actual,
+                            new: source_target,
+                        });
+                    }
+                    if let Some(existing) = clash {
+                        return Err(Error::NameClash {
+                            full_name: full_name(),
+                            existing: existing.into_inner(),
+                        });
+                    }
+                    *expected = PreviousValue::MustExistAndMatch(source_target);
+                }
+            }
+
+            // traverse parent chain from leaf/peeled ref and set the leaf previous oid accordingly
+            // to help with their reflog entries
+            if let (Some(crate::TargetRef::Peeled(oid)), Some(parent_idx)) =
+                (change.update.change.previous_value(), change.parent_index)
+            {
+                let oid = oid.to_owned();
+                let mut parent_idx_cursor = Some(parent_idx);
+                while let Some(parent) = parent_idx_cursor.take().map(|idx| &mut updates[idx]) {
+                    parent_idx_cursor = parent.parent_index;
+                    parent.leaf_referent_previous_oid = Some(oid);
+                }
+            }
+        }
+
+        self.lock = Some(lock);
+        self.stack = Some(stack);
+        self.updates = Some(updates);
+        Ok(self)
+    }
+
+    /// Make all [prepared][Transaction::prepare()] edits permanent by writing them into a new table on top of the stack,
+    /// and return the performed edits which represent the current state of the affected refs in the ref store in that instant.
+    ///
+    /// `committer` is used in the reflog and only if the reflog is actually written, which is why it is optional.
+    /// Unlike with the file store, all changes are applied atomically, so if the reflog would have to be written without
+    /// a `committer`, nothing is changed.
+    ///
+    /// Reflogs of deleted references are removed as well. If [`Store::auto_compact`] is set, tables are compacted afterwards,
+    /// ignoring any error as compaction is an optimization only.
+    pub fn commit<'a>(
+        self,
+        committer: impl Into<Option<git_actor::SignatureRef<'a>>>,
+    ) -> Result<Vec<RefEdit>, commit::Error> {
+        self.commit_inner(committer.into())
+    }
+
+    fn commit_inner(self, committer: Option<git_actor::SignatureRef<'_>>) -> Result<Vec<RefEdit>, commit::Error> {
+        let updates = self.updates.expect("BUG: must call prepare before commit");
+        let stack = self.stack.expect("present after prepare");
+        let mut lock = self.lock.expect("present after prepare");
+        let store = self.store;
+        let update_index = stack.max_update_index() + 1;
+
+        let mut refs = Vec::new();
+        let mut logs = Vec::new();
+        for change in &updates {
+            assert!(!change.update.deref, "Deref mode is turned into splits and turned off");
+            let name = &change.update.name;
+            match &change.update.change {
+                Change::Update { log, new, expected } => {
+                    if log.mode == RefLog::AndReference {
+                        refs.push(RefRecord {
+                            name: name.clone(),
+                            update_index,
+                            value: match new {
+                                Target::Peeled(id) => RefValue::Object(*id),
+                                Target::Symbolic(name) => RefValue::Symbolic(name.clone()),
+                            },
+                        });
+                    }
+
+                    let log_update = match new {
+                        Target::Symbolic(_) => {
+                            // no reflog for symref changes, unless the ref is new and we can obtain a peeled id
+                            // identified by the expectation of what could be there, as is the case when cloning.
+                            match expected {
+                                PreviousValue::ExistingMustMatch(Target::Peeled(oid)) => {
+                                    Some((Some(git_hash::ObjectId::null(oid.kind())), *oid))
+                                }
+                                _ => None,
+                            }
+                        }
+                        Target::Peeled(new_oid) => {
+                            let previous = match expected {
+                                PreviousValue::MustExistAndMatch(Target::Peeled(oid)) => Some(oid.to_owned()),
+                                _ => None,
+                            }
+                            .or(change.leaf_referent_previous_oid);
+                            Some((previous, *new_oid))
+                        }
+                    };
+                    if let Some((previous, new_oid)) = log_update {
+                        let do_update = previous.map_or(true, |previous| previous != new_oid);
+                        if do_update && store.should_write_reflog(&stack, name.as_ref(), log.force_create_reflog)? {
+                            let committer = committer.ok_or(commit::Error::MissingCommitter)?;
+                            logs.push(LogRecord {
+                                name: name.clone(),
+                                update_index,
+                                value: Some(Line {
+                                    previous_oid: previous.unwrap_or_else(|| new_oid.kind().null()),
+                                    new_oid,
+                                    signature: committer.to_owned(),
+                                    message: log.message.clone(),
+                                }),
+                            });
+                        }
+                    }
+                }
+                Change::Delete { log: mode, .. } => {
+                    if *mode == RefLog::AndReference {
+                        refs.push(RefRecord {
+                            name: name.clone(),
+                            update_index,
+                            value: RefValue::Deletion,
+                        });
+                    }
+                    logs.extend(
+                        stack
+                            .logs_of(name.as_ref())?
+                            .into_iter()
+                            .map(|record| LogRecord { value: None, ..record }),
+                    );
+                }
+                Change::Rename {
+                    source,
+                    expected,
+                    copy,
+                    message,
+                } => {
+                    let target = match expected {
+                        PreviousValue::MustExistAndMatch(target) => target,
+                        _ => unreachable!("BUG: the source value is set when preparing"),
+                    };
+                    refs.push(RefRecord {
+                        name: name.clone(),
+                        update_index,
+                        value: match target {
+                            Target::Peeled(id) => RefValue::Object(*id),
+                            Target::Symbolic(name) => RefValue::Symbolic(name.clone()),
+                        },
+                    });
+                    if !copy {
+                        refs.push(RefRecord {
+                            name: source.clone(),
+                            update_index,
+                            value: RefValue::Deletion,
+                        });
+                    }
+
+                    // The reflog replaces what's left of a previous reflog of the same name.
+                    let source_logs = stack.logs_of(source.as_ref())?;
+                    logs.extend(
+                        stack
+                            .logs_of(name.as_ref())?
+                            .into_iter()
+                            .filter(|record| !source_logs.iter().any(|r| r.update_index == record.update_index))
+                            .map(|record| LogRecord { value: None, ..record }),
+                    );
+                    logs.extend(source_logs.iter().map(|record| LogRecord {
+                        name: name.clone(),
+                        ..record.clone()
+                    }));
+                    if !copy {
+                        logs.extend(source_logs.iter().map(|record| LogRecord {
+                            value: None,
+                            ..record.clone()
+                        }));
+                    }
+
+                    if let Target::Peeled(id) = target {
+                        let write_reflog = store.write_reflog != WriteReflog::Disable
+                            && (!source_logs.is_empty() || store.should_write_reflog(&stack, name.as_ref(), false)?);
+                        if write_reflog {
+                            let committer = committer.ok_or(commit::Error::MissingCommitter)?;
+                            logs.push(LogRecord {
+                                name: name.clone(),
+                                update_index,
+                                value: Some(Line {
+                                    previous_oid: *id,
+                                    new_oid: *id,
+                                    signature: committer.to_owned(),
+                                    message: message.clone(),
+                                }),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        if refs.is_empty() && logs.is_empty() {
+            return Ok(updates.into_iter().map(|edit| edit.update).collect());
+        }
+        let data = write::table(
+            &refs,
+            &logs,
+            update_index,
+            update_index,
+            store.object_hash,
+            &store.write_options,
+        )?;
+        let table_name = store.write_table_file(&data, update_index, update_index)?;
+        let mut names = stack.names().to_vec();
+        names.push(table_name);
+        Store::write_tables_list(&mut lock, &names)?;
+        lock.commit()?;
+
+        if store.auto_compact {
+            store.auto_compact(git_lock::acquire::Fail::Immediately).ok();
+        }
+        Ok(updates.into_iter().map(|edit| edit.update).collect())
+    }
+
+    /// Return all edits of a [prepared][Transaction::prepare()] transaction, including the splits of symbolic references,
+    /// with their previous values set to what was encountered after locking the list of tables.
+    ///
+    /// The iterator is empty if the transaction wasn't prepared yet.
+    pub fn edits(&self) -> impl Iterator<Item = &RefEdit> + '_ {
+        self.updates.iter().flatten().map(|edit| &edit.update)
+    }
+
+    /// Rollback all intermediate state and return the `RefEdits` as we know them thus far.
+    ///
+    /// Note that they have been altered compared to what was initially provided as they have
+    /// been split and know about their current state on disk.
+    ///
+    /// # Note
+    ///
+    /// A rollback happens automatically as this instance is dropped as well.
+    pub fn rollback(self) -> Vec<RefEdit> {
+        self.updates
+            .map(|updates| updates.into_iter().map(|u| u.update).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl Store {
+    fn should_write_reflog(
+        &self,
+        stack: &crate::store_impl::reftable::stack::Stack,
+        name: &FullNameRef,
+        force_create_reflog: bool,
+    ) -> Result<bool, commit::Error> {
+        Ok(match self.write_reflog {
+            WriteReflog::Disable => false,
+            WriteReflog::Always => true,
+            WriteReflog::Normal => {
+                let name = name.as_bstr();
+                force_create_reflog
+                    || name == "HEAD"
+                    || ["refs/heads/", "refs/remotes/", "refs/notes/", "refs/worktree/"]
+                        .iter()
+                        .any(|prefix| name.starts_with(prefix.as_bytes()))
+                    || !stack.logs_of(FullNameRef::new_unchecked(name))?.is_empty()
+            }
+        })
+    }
+}
+
+/// Return the name of an existing reference or one created by another of the `updates` that prevents the creation of the
+/// one edited by `updates[idx]` as git couldn't store both of them in a directory, like `refs/heads/a` and `refs/heads/a/b`.
+fn clashing_reference(stack: &Stack, updates: &[Edit], idx: usize) -> Result<Option<FullName>, decode::Error> {
+    let name = updates[idx].update.name.as_bstr();
+    let is_parent_of = |parent: &BStr, child: &BStr| {
+        child.len() > parent.len() && child.starts_with(parent) && child[parent.len()] == b'/'
+    };
+    if let Some(edit) = updates.iter().find(|edit| {
+        let other = edit.update.name.as_bstr();
+        !matches!(edit.update.change, Change::Delete { .. }) && (is_parent_of(other, name) || is_parent_of(name, other))
+    }) {
+        return Ok(Some(edit.update.name.clone()));
+    }
+
+    for slash_pos in name.find_iter(b"/") {
+        let parent = FullNameRef::new_unchecked(name[..slash_pos].as_bstr());
+        if stack.find(parent)?.and_then(|r| r.into_reference()).is_some() {
+            return Ok(Some(parent.to_owned()));
+        }
+    }
+    let mut prefix = name.to_owned();
+    prefix.push(b'/');
+    Ok(stack
+        .refs_prefixed(&prefix)?
+        .into_iter()
+        .find_map(|r| r.into_reference())
+        .map(|r| r.name))
+}
+
+///
+pub mod prepare {
+    use git_object::bstr::BString;
+
+    use crate::{
+        store_impl::reftable::{stack, table},
+        Target,
+    };
+
+    /// The error returned by [`Transaction::prepare()`][crate::reftable::Transaction::prepare()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("The lock for the list of tables could not be obtained")]
+        LockAcquire(#[source] git_lock::acquire::Error),
+        #[error(transparent)]
+        Load(#[from] stack::load::Error),
+        #[error("A reftable could not be read")]
+        Decode(#[from] table::decode::Error),
+        #[error("Edit preprocessing failed with an error")]
+        PreprocessingFailed(#[source] std::io::Error),
+        #[error("The reference {full_name:?} for deletion did not exist or could not be parsed")]
+        DeleteReferenceMustExist { full_name: BString },
+        #[error("Reference {full_name:?} was not supposed to exist when writing it with value {new:?}, but actual content was {actual:?}")]
+        MustNotExist {
+            full_name: BString,
+            actual: Target,
+            new: Target,
+        },
+        #[error("Reference {full_name:?} was supposed to exist with value {expected}, but didn't.")]
+        MustExist { full_name: BString, expected: Target },
+        #[error("The reference {full_name:?} should have content {expected}, actual content was {actual}")]
+        ReferenceOutOfDate {
+            full_name: BString,
+            expected: Target,
+            actual: Target,
+        },
+        #[error("Reference {full_name:?} cannot be created as it clashes with the existing reference {existing:?}")]
+        NameClash { full_name: BString, existing: BString },
+    }
+}
+
+///
+pub mod commit {
+    use crate::store_impl::reftable::{table, write};
+
+    /// The error returned by [`Transaction::commit()`][crate::reftable::Transaction::commit()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("Reflog entries could not be written as no committer was provided")]
+        MissingCommitter,
+        #[error("A reftable could not be read")]
+        Decode(#[from] table::decode::Error),
+        #[error("The new table could not be created")]
+        Write(#[from] write::Error),
+        #[error("The new table or the list of tables could not be written")]
+        Io(#[from] std::io::Error),
+        #[error("The list of tables could not be moved into place")]
+        Commit(#[from] git_lock::commit::Error<git_lock::File>),
+    }
+}
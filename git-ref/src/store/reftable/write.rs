@@ -0,0 +1,335 @@
+use std::collections::BTreeMap;
+
+use git_hash::ObjectId;
+use git_object::bstr::BString;
+
+use crate::{
+    log::Line,
+    store_impl::reftable::{
+        block,
+        block::put_varint,
+        table::{Footer, Header},
+        LogRecord, RefRecord, RefValue,
+    },
+    FullNameRef,
+};
+
+/// Options to control how tables are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// The size to which reference, object and index blocks are padded, or 0 to write unaligned blocks.
+    pub block_size: u32,
+    /// The amount of records after which a restart point with an uncompressed key is written.
+    pub restart_interval: u16,
+    /// If `true`, the default, write an index from object ids to the blocks of references pointing to them
+    /// if the table is large enough to have a reference index.
+    pub index_objects: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            block_size: 4096,
+            restart_interval: 16,
+            index_objects: true,
+        }
+    }
+}
+
+mod error {
+    use git_object::bstr::BString;
+
+    /// The error returned by [`table()`][super::table()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("The record of reference {name:?} is too large to fit into a block")]
+        RecordTooLarge { name: BString },
+        #[error("The update index {update_index} of reference {name:?} is outside of the range of the table")]
+        UpdateIndexOutOfRange { name: BString, update_index: u64 },
+        #[error("The block size {0} exceeds the maximum of 16MB")]
+        BlockSizeTooLarge(u32),
+        #[error("A log block could not be compressed")]
+        Io(#[from] std::io::Error),
+    }
+}
+pub use error::Error;
+
+/// Write a table containing the reference records `refs` and log records `logs` and return its bytes.
+///
+/// Records are sorted as needed, and all reference records must have an update index within `min_update_index..=max_update_index`.
+/// Log records may have any update index, which is needed to write tombstones for existing log entries.
+/// `object_hash` determines the table version, which is 1 for SHA1 and 2 otherwise.
+pub fn table(
+    refs: &[RefRecord],
+    logs: &[LogRecord],
+    min_update_index: u64,
+    max_update_index: u64,
+    object_hash: git_hash::Kind,
+    options: &Options,
+) -> Result<Vec<u8>, Error> {
+    if options.block_size >= 1 << 24 {
+        return Err(Error::BlockSizeTooLarge(options.block_size));
+    }
+    if let Some(record) = refs
+        .iter()
+        .find(|r| !(min_update_index..=max_update_index).contains(&r.update_index))
+    {
+        return Err(Error::UpdateIndexOutOfRange {
+            name: record.name.as_bstr().to_owned(),
+            update_index: record.update_index,
+        });
+    }
+
+    let header = Header {
+        version: match object_hash {
+            git_hash::Kind::Sha1 => 1,
+            git_hash::Kind::Sha256 => 2,
+        },
+        block_size: options.block_size,
+        min_update_index,
+        max_update_index,
+        object_hash,
+    };
+    let mut header_bytes = Vec::new();
+    header.write_to(&mut header_bytes);
+    let mut writer = Writer {
+        out: Vec::new(),
+        file_header: Some(header_bytes),
+        options,
+    };
+    let mut footer = Footer::default();
+
+    let mut refs: Vec<_> = refs.iter().collect();
+    refs.sort_by(|a, b| a.name.cmp(&b.name));
+    let (ref_blocks, ref_block_of_record) = writer.write_blocks(
+        block::REF,
+        refs.iter().map(|r| encode_ref(r, min_update_index)),
+        |index| refs[index].name.as_bstr().to_owned(),
+    )?;
+    footer.ref_index_pos = writer.write_index(ref_blocks)?;
+
+    if options.index_objects && footer.ref_index_pos > 0 {
+        let mut blocks_by_id = BTreeMap::<ObjectId, Vec<u64>>::new();
+        for (record, block_pos) in refs.iter().zip(ref_block_of_record) {
+            let ids = match &record.value {
+                RefValue::Object(id) => [Some(id), None],
+                RefValue::Peeled { target, peeled } => [Some(target), Some(peeled)],
+                RefValue::Deletion | RefValue::Symbolic(_) => continue,
+            };
+            for id in ids.into_iter().flatten() {
+                let positions = blocks_by_id.entry(*id).or_default();
+                if positions.last() != Some(&block_pos) {
+                    positions.push(block_pos);
+                }
+            }
+        }
+        let ids: Vec<_> = blocks_by_id.keys().collect();
+        let longest_common_prefix = ids
+            .windows(2)
+            .map(|w| {
+                w[0].as_bytes()
+                    .iter()
+                    .zip(w[1].as_bytes())
+                    .take_while(|(a, b)| a == b)
+                    .count()
+            })
+            .max()
+            .unwrap_or(0);
+        let id_len = (longest_common_prefix + 1).clamp(2, object_hash.len_in_bytes());
+        footer.obj_id_len = id_len as u8;
+        footer.obj_pos = writer.out.len() as u64;
+        let (obj_blocks, _) = writer.write_blocks(
+            block::OBJ,
+            blocks_by_id.iter().map(|(id, positions)| {
+                let mut value = Vec::new();
+                let value_type = match positions.len() {
+                    count @ 1..=7 => count as u8,
+                    count => {
+                        put_varint(&mut value, count as u64);
+                        0
+                    }
+                };
+                let mut previous = 0;
+                for position in positions {
+                    put_varint(&mut value, position - previous);
+                    previous = *position;
+                }
+                (id.as_bytes()[..id_len].to_vec(), value_type, value)
+            }),
+            |_| "<object index>".into(),
+        )?;
+        footer.obj_index_pos = writer.write_index(obj_blocks)?;
+    }
+
+    let mut logs: Vec<_> = logs.iter().collect();
+    logs.sort_by(|a, b| a.name.cmp(&b.name).then(b.update_index.cmp(&a.update_index)));
+    if !logs.is_empty() {
+        footer.log_pos = writer.out.len() as u64;
+    }
+    let (log_blocks, _) = writer.write_blocks(block::LOG, logs.iter().map(|l| encode_log(l)), |index| {
+        logs[index].name.as_bstr().to_owned()
+    })?;
+    footer.log_index_pos = writer.write_index(log_blocks)?;
+
+    let mut out = match writer.file_header.take() {
+        Some(header_bytes) => header_bytes,
+        None => writer.out,
+    };
+    footer.write_to(&header, &mut out);
+    Ok(out)
+}
+
+pub(crate) fn log_key(name: &FullNameRef, update_index: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(name.as_bstr().len() + 9);
+    key.extend_from_slice(name.as_bstr());
+    key.push(0);
+    key.extend_from_slice(&(u64::MAX - update_index).to_be_bytes());
+    key
+}
+
+fn encode_ref(record: &RefRecord, min_update_index: u64) -> (Vec<u8>, u8, Vec<u8>) {
+    let mut value = Vec::new();
+    put_varint(&mut value, record.update_index - min_update_index);
+    let value_type = match &record.value {
+        RefValue::Deletion => 0,
+        RefValue::Object(id) => {
+            value.extend_from_slice(id.as_bytes());
+            1
+        }
+        RefValue::Peeled { target, peeled } => {
+            value.extend_from_slice(target.as_bytes());
+            value.extend_from_slice(peeled.as_bytes());
+            2
+        }
+        RefValue::Symbolic(target) => {
+            put_varint(&mut value, target.as_bstr().len() as u64);
+            value.extend_from_slice(target.as_bstr());
+            3
+        }
+    };
+    (record.name.as_bstr().to_vec(), value_type, value)
+}
+
+fn encode_log(record: &LogRecord) -> (Vec<u8>, u8, Vec<u8>) {
+    let key = log_key(record.name.as_ref(), record.update_index);
+    let line = match &record.value {
+        None => return (key, 0, Vec::new()),
+        Some(line) => line,
+    };
+    let Line {
+        previous_oid,
+        new_oid,
+        signature,
+        message,
+    } = line;
+    let mut value = Vec::new();
+    value.extend_from_slice(previous_oid.as_bytes());
+    value.extend_from_slice(new_oid.as_bytes());
+    for field in [&signature.name, &signature.email] {
+        put_varint(&mut value, field.len() as u64);
+        value.extend_from_slice(field);
+    }
+    put_varint(&mut value, u64::from(signature.time.seconds_since_unix_epoch));
+    let tz_minutes = signature.time.offset_in_seconds / 60;
+    let tz = (tz_minutes.abs() / 60 * 100 + tz_minutes.abs() % 60) * tz_minutes.signum();
+    value.extend_from_slice(&(tz as i16).to_be_bytes());
+    if message.is_empty() {
+        put_varint(&mut value, 0);
+    } else {
+        put_varint(&mut value, message.len() as u64 + 1);
+        value.extend_from_slice(message);
+        value.push(b'\n');
+    }
+    (key, 1, value)
+}
+
+struct Writer<'a> {
+    out: Vec<u8>,
+    /// The file header, until the first block was started which will contain it.
+    file_header: Option<Vec<u8>>,
+    options: &'a Options,
+}
+
+/// The last key and the position of each block written.
+type BlockIndex = Vec<(Vec<u8>, u64)>;
+
+impl<'a> Writer<'a> {
+    fn start_block(&mut self, typ: u8) -> block::Writer {
+        block::Writer::new(
+            typ,
+            self.file_header.take().unwrap_or_default(),
+            self.options.block_size as usize,
+            self.options.restart_interval as usize,
+        )
+    }
+
+    fn finish_block(&mut self, block: block::Writer, blocks: &mut BlockIndex) -> Result<u64, Error> {
+        let pos = self.out.len() as u64;
+        blocks.push((block.last_key().to_vec(), pos));
+        let pad = block.typ() != block::LOG;
+        self.out.extend_from_slice(&block.finish(pad)?);
+        Ok(pos)
+    }
+
+    /// Write all `records` into blocks of type `typ` and return the index of all blocks, along with the position of the block
+    /// of each record.
+    fn write_blocks(
+        &mut self,
+        typ: u8,
+        records: impl Iterator<Item = (Vec<u8>, u8, Vec<u8>)>,
+        name_of_record: impl Fn(usize) -> BString,
+    ) -> Result<(BlockIndex, Vec<u64>), Error> {
+        let mut blocks = Vec::new();
+        let mut block_of_record = Vec::new();
+        let mut records_in_block = 0;
+        let mut current: Option<block::Writer> = None;
+        for (index, (key, value_type, value)) in records.enumerate() {
+            let mut block = match current.take() {
+                Some(block) => block,
+                None => self.start_block(typ),
+            };
+            if !block.add(&key, value_type, &value) {
+                let pos = self.finish_block(block, &mut blocks)?;
+                block_of_record.extend(std::iter::repeat(pos).take(records_in_block));
+                records_in_block = 0;
+                block = self.start_block(typ);
+                block.add(&key, value_type, &value);
+            }
+            if typ != block::LOG && block.is_oversized() {
+                return Err(Error::RecordTooLarge {
+                    name: name_of_record(index),
+                });
+            }
+            records_in_block += 1;
+            current = Some(block);
+        }
+        if let Some(block) = current {
+            let pos = self.finish_block(block, &mut blocks)?;
+            block_of_record.extend(std::iter::repeat(pos).take(records_in_block));
+        }
+        Ok((blocks, block_of_record))
+    }
+
+    /// Write a possibly multi-level index for `blocks` if there are enough of them and return the position of its root,
+    /// or 0 if no index was written.
+    fn write_index(&mut self, mut blocks: BlockIndex) -> Result<u64, Error> {
+        let mut threshold = if self.options.block_size == 0 { 1 } else { 3 };
+        let mut root = 0;
+        while blocks.len() > threshold {
+            threshold = 1;
+            root = self.out.len() as u64;
+            let (index_blocks, _) = self.write_blocks(
+                block::INDEX,
+                blocks.into_iter().map(|(key, pos)| {
+                    let mut value = Vec::new();
+                    put_varint(&mut value, pos);
+                    (key, 0, value)
+                }),
+                |_| "<index>".into(),
+            )?;
+            blocks = index_blocks;
+        }
+        Ok(root)
+    }
+}
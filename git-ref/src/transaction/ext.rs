@@ -10,7 +10,8 @@ pub trait RefEditsExt<T>
 where
     T: std::borrow::Borrow<RefEdit> + std::borrow::BorrowMut<RefEdit>,
 {
-    /// Return true if each ref `name` has exactly one `edit` across multiple ref edits
+    /// Return true if each ref `name` has exactly one `edit` across multiple ref edits, counting the `source` of renames and copies
+    /// as well.
     fn assure_one_name_has_one_edit(&self) -> Result<(), BString>;
 
     /// Split all symbolic refs into updates for the symbolic ref as well as all their referents if the `deref` flag is enabled.
@@ -45,7 +46,17 @@ where
     E: std::borrow::Borrow<RefEdit> + std::borrow::BorrowMut<RefEdit>,
 {
     fn assure_one_name_has_one_edit(&self) -> Result<(), BString> {
-        let mut names: Vec<_> = self.iter().map(|e| &e.borrow().name).collect();
+        let mut names: Vec<_> = self
+            .iter()
+            .flat_map(|e| {
+                let edit = e.borrow();
+                let source = match &edit.change {
+                    Change::Rename { source, .. } => Some(source),
+                    Change::Update { .. } | Change::Delete { .. } => None,
+                };
+                std::iter::once(&edit.name).chain(source)
+            })
+            .collect();
         names.sort();
         match names.windows(2).find(|v| v[0] == v[1]) {
             Some(name) => Err(name[0].as_bstr().to_owned()),
@@ -67,6 +78,10 @@ where
                 if !edit.deref {
                     continue;
                 };
+                if let Change::Rename { .. } = edit.change {
+                    edit.deref = false;
+                    continue;
+                }
 
                 // we can't tell what happened and we are here because it's a non-existing ref or an invalid one.
                 // In any case, we don't want the following algorithms to try dereffing it and assume they deal with
@@ -111,6 +126,7 @@ where
                                     deref: true,
                                 }
                             }
+                            Change::Rename { .. } => unreachable!("BUG: renames are never split"),
                         },
                     ));
                 }
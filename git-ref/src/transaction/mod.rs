@@ -5,6 +5,7 @@
 //!
 //! * create or update reference
 //! * delete references
+//! * rename or copy references along with their reflog
 //!
 //! The following guarantees are made:
 //!
@@ -80,18 +81,43 @@ pub enum Change {
         /// How to treat the reference log during deletion.
         log: RefLog,
     },
+    /// Rename or copy the existing reference `source` to the name of this edit, along with its reflog.
+    ///
+    /// The reference named by this edit must not exist yet, and it must not clash with existing references like
+    /// `refs/heads/a` does with `refs/heads/a/b`, which includes `source` itself.
+    /// The reference is renamed or copied as is, symbolic references aren't followed and [`RefEdit::deref`] has no effect.
+    Rename {
+        /// The name of the existing reference to rename or copy.
+        source: FullName,
+        /// The expected value of `source`, with the `MustNotExist` variant being invalid.
+        ///
+        /// It will be filled in automatically as `MustExistAndMatch(actual_value)` and can be accessed
+        /// if the transaction was committed successfully.
+        expected: PreviousValue,
+        /// If `true`, `source` and its reflog are kept, making this a copy.
+        copy: bool,
+        /// The message to put into the reflog of the new reference. It must be a single line, hence newlines are forbidden.
+        message: BString,
+    },
 }
 
 impl Change {
-    /// Return references to values that are the new value after the change is applied, if this is an update.
+    /// Return references to values that are the new value after the change is applied, if this is an update,
+    /// or a rename or copy whose `source` value is known.
     pub fn new_value(&self) -> Option<crate::TargetRef<'_>> {
         match self {
             Change::Update { new, .. } => new.to_ref().into(),
-            Change::Delete { .. } => None,
+            Change::Rename {
+                expected: PreviousValue::MustExistAndMatch(source) | PreviousValue::ExistingMustMatch(source),
+                ..
+            } => source.to_ref().into(),
+            Change::Delete { .. } | Change::Rename { .. } => None,
         }
     }
 
     /// Return references to values that are in common between all variants and denote the previous observed value.
+    ///
+    /// Renames and copies never have a previous value as the reference they create must not exist.
     pub fn previous_value(&self) -> Option<crate::TargetRef<'_>> {
         match self {
             // TODO: use or-patterns once MRV is larger than 1.52 (and this is supported)
@@ -65,3 +65,150 @@ mod iter_rev {
         Ok(())
     }
 }
+
+mod expire {
+    use git_lock::acquire::Fail;
+    use git_ref::file::log::expire::{Error, Options};
+
+    use crate::file::store_writable;
+
+    fn lines(store: &crate::file::Store, name: &str) -> crate::Result<Vec<git_ref::log::Line>> {
+        let mut buf = Vec::new();
+        Ok(store
+            .reflog_iter(name, &mut buf)?
+            .expect("log exists")
+            .map(|line| line.map(|line| line.to_owned()))
+            .collect::<Result<_, _>>()?)
+    }
+
+    #[test]
+    fn non_existing_log_yields_none() -> crate::Result {
+        let (_keep, store) = store_writable("make_repo_for_reflog.sh")?;
+        assert_eq!(
+            store.reflog_expire(
+                "refs/heads/does-not-exist",
+                Options::default(),
+                |_| true,
+                Fail::Immediately
+            )?,
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn entries_older_than_cutoff_are_removed() -> crate::Result {
+        let (_keep, store) = store_writable("make_repo_for_reflog.sh")?;
+        let before = lines(&store, "HEAD")?;
+        let time = before[0].signature.time.seconds_since_unix_epoch;
+
+        let options = Options {
+            expire_total: Some(time),
+            ..Default::default()
+        };
+        let outcome = store
+            .reflog_expire("HEAD", options, |_| unreachable!("no need to check"), Fail::Immediately)?
+            .expect("log exists");
+        assert_eq!(outcome.kept, 5, "cutoffs are exclusive");
+        assert!(outcome.removed.is_empty());
+
+        let options = Options {
+            expire_total: Some(time + 1),
+            dry_run: true,
+            ..Default::default()
+        };
+        let outcome = store
+            .reflog_expire("HEAD", options, |_| unreachable!("no need to check"), Fail::Immediately)?
+            .expect("log exists");
+        assert_eq!(outcome.kept, 0);
+        assert_eq!(outcome.removed, before, "all entries are expired");
+        assert_eq!(lines(&store, "HEAD")?, before, "dry-runs don't alter the log");
+
+        let options = Options {
+            dry_run: false,
+            ..options
+        };
+        store.reflog_expire("HEAD", options, |_| true, Fail::Immediately)?;
+        assert!(lines(&store, "HEAD")?.is_empty(), "the log remains, but is empty");
+        assert_eq!(lines(&store, "refs/heads/main")?.len(), 5, "other logs are unaffected");
+        Ok(())
+    }
+
+    #[test]
+    fn unreachable_entries_are_removed_and_remaining_ones_can_be_rewritten() -> crate::Result {
+        let (_keep, store) = store_writable("make_repo_for_reflog.sh")?;
+        let before = lines(&store, "HEAD")?;
+        let amended_away = before[3].new_oid;
+
+        let options = Options {
+            expire_unreachable: Some(u32::MAX),
+            rewrite: true,
+            ..Default::default()
+        };
+        let outcome = store
+            .reflog_expire("HEAD", options, |id| id != amended_away, Fail::Immediately)?
+            .expect("log exists");
+        assert_eq!(outcome.kept, 3);
+        assert_eq!(
+            outcome.removed,
+            &before[3..],
+            "both entries mentioning the unreachable commit are gone"
+        );
+        assert_eq!(lines(&store, "HEAD")?, &before[..3]);
+        Ok(())
+    }
+
+    #[test]
+    fn the_reference_lock_is_respected() -> crate::Result {
+        let (dir, store) = store_writable("make_repo_for_reflog.sh")?;
+        std::fs::write(dir.path().join(".git").join("refs/heads/main.lock"), b"")?;
+        let err = store
+            .reflog_delete_entry("refs/heads/main", 0, false, Fail::Immediately)
+            .unwrap_err();
+        assert!(matches!(err, Error::LockAcquire { .. }));
+        assert_eq!(lines(&store, "refs/heads/main")?.len(), 5, "nothing was changed");
+        Ok(())
+    }
+
+    mod delete_entry {
+        use git_lock::acquire::Fail;
+
+        use super::lines;
+        use crate::file::store_writable;
+
+        #[test]
+        fn entries_are_counted_from_the_most_recent_one() -> crate::Result {
+            let (_keep, store) = store_writable("make_repo_for_reflog.sh")?;
+            let before = lines(&store, "HEAD")?;
+
+            let outcome = store
+                .reflog_delete_entry("HEAD", 5, false, Fail::Immediately)?
+                .expect("log exists");
+            assert!(outcome.removed.is_empty(), "there is no such entry");
+            assert_eq!(outcome.kept, 5);
+
+            let outcome = store
+                .reflog_delete_entry("HEAD", 1, false, Fail::Immediately)?
+                .expect("log exists");
+            assert_eq!(outcome.removed, &before[3..4]);
+            assert_eq!(outcome.kept, 4);
+            let after = lines(&store, "HEAD")?;
+            assert_eq!(after[..3], before[..3]);
+            assert_eq!(after[3], before[4], "entries are kept as is without rewriting");
+
+            let outcome = store
+                .reflog_delete_entry("HEAD", 1, true, Fail::Immediately)?
+                .expect("log exists");
+            assert_eq!(outcome.removed, &before[2..3]);
+            let after = lines(&store, "HEAD")?;
+            assert_eq!(after.len(), 3);
+            assert_eq!(
+                after[2].previous_oid, before[1].new_oid,
+                "the next entry now follows the last kept one"
+            );
+            assert_eq!(after[2].new_oid, before[4].new_oid);
+            assert_eq!(after[2].message, before[4].message);
+            Ok(())
+        }
+    }
+}
@@ -12,7 +12,7 @@ pub(crate) mod prepare_and_commit {
 
     use crate::util::hex_to_id;
 
-    fn reflog_lines(store: &file::Store, name: &str) -> crate::Result<Vec<git_ref::log::Line>> {
+    pub(crate) fn reflog_lines(store: &file::Store, name: &str) -> crate::Result<Vec<git_ref::log::Line>> {
         let mut buf = Vec::new();
         let res = store
             .reflog_iter(name, &mut buf)?
@@ -49,7 +49,7 @@ pub(crate) mod prepare_and_commit {
         }
     }
 
-    fn create_at(name: &str) -> RefEdit {
+    pub(crate) fn create_at(name: &str) -> RefEdit {
         RefEdit {
             change: Change::Update {
                 log: LogChange {
@@ -65,7 +65,7 @@ pub(crate) mod prepare_and_commit {
         }
     }
 
-    fn create_symbolic_at(name: &str, symbolic_target: &str) -> RefEdit {
+    pub(crate) fn create_symbolic_at(name: &str, symbolic_target: &str) -> RefEdit {
         RefEdit {
             change: Change::Update {
                 log: LogChange::default(),
@@ -91,4 +91,8 @@ pub(crate) mod prepare_and_commit {
     mod create_or_update;
 
     mod delete;
+
+    mod initial;
+
+    mod rename;
 }
@@ -0,0 +1,154 @@
+use std::convert::TryInto;
+
+use git_lock::acquire::Fail;
+use git_object::bstr::BString;
+use git_odb::Find;
+use git_ref::{
+    file::transaction::PackedRefs,
+    transaction::{Change, LogChange, PreviousValue, RefEdit},
+    Target,
+};
+
+use crate::file::{
+    store_with_packed_refs, store_writable,
+    transaction::prepare_and_commit::{committer, create_at, create_symbolic_at, empty_store},
+};
+
+fn initial(objects_dir: std::path::PathBuf) -> crate::Result<PackedRefs<'static>> {
+    let odb = git_odb::at(objects_dir)?;
+    Ok(PackedRefs::Initial(Box::new(move |oid, buf| {
+        odb.try_find(oid, buf)
+            .map(|obj| obj.map(|obj| obj.kind))
+            .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send + Sync>)
+    })))
+}
+
+#[test]
+fn empty_stores_receive_all_peeled_references_as_packed_refs() -> crate::Result {
+    let packed_store = store_with_packed_refs()?;
+    let packed = packed_store.open_packed_buffer()?.expect("packed refs present");
+    let mut edits: Vec<_> = packed
+        .iter()?
+        .map(|r| {
+            let r = r.expect("valid");
+            RefEdit {
+                change: Change::Update {
+                    log: LogChange::default(),
+                    expected: PreviousValue::MustNotExist,
+                    new: Target::Peeled(r.target()),
+                },
+                name: r.name.to_owned(),
+                deref: false,
+            }
+        })
+        .collect();
+    edits.reverse();
+    edits.push(create_symbolic_at("refs/heads/symbolic", "refs/heads/main"));
+
+    let (_keep, store) = empty_store()?;
+    let edits = store
+        .transaction()
+        .packed_refs(initial(packed_store.git_dir().join("objects"))?)
+        .prepare(edits, Fail::Immediately, Fail::Immediately)?
+        .commit(committer().to_ref())?;
+    assert_eq!(edits.len(), packed.iter()?.count() + 1);
+
+    let expected_packed_data: BString = std::fs::read(packed_store.packed_refs_path())?.into();
+    let actual_packed_data: BString = std::fs::read(store.packed_refs_path())?.into();
+    assert_eq!(
+        actual_packed_data, expected_packed_data,
+        "all peeled references are sorted and peeled just like git would"
+    );
+    assert_eq!(
+        store
+            .loose_iter()?
+            .map(|r| r.map(|r| r.name.as_bstr().to_owned()))
+            .collect::<Result<Vec<_>, _>>()?,
+        vec!["refs/heads/symbolic"],
+        "only symbolic references are written as loose references"
+    );
+    assert!(
+        store.reflog_exists("refs/heads/main")?,
+        "reflogs are still written as configured"
+    );
+    Ok(())
+}
+
+#[test]
+fn clashing_names_and_expectations_of_existing_references_are_rejected() -> crate::Result {
+    let packed_store = store_with_packed_refs()?;
+    let main = packed_store.find("main")?;
+    let objects = packed_store.git_dir().join("objects");
+    for (edits, expected_err) in [
+        (
+            vec![create_at("refs/heads/a"), create_at("refs/heads/a/b")],
+            "Reference \"refs/heads/a/b\" cannot be created as it clashes with the existing reference \"refs/heads/a\"",
+        ),
+        (
+            vec![RefEdit {
+                change: Change::Update {
+                    log: LogChange::default(),
+                    expected: PreviousValue::MustExist,
+                    new: main.target,
+                },
+                name: "refs/heads/a".try_into()?,
+                deref: false,
+            }],
+            "Reference \"refs/heads/a\" was supposed to exist with value 0000000000000000000000000000000000000000, but didn't.",
+        ),
+    ] {
+        let (_keep, store) = empty_store()?;
+        let err = store
+            .transaction()
+            .packed_refs(initial(objects.clone())?)
+            .prepare(edits, Fail::Immediately, Fail::Immediately)
+            .unwrap_err();
+        assert_eq!(err.to_string(), expected_err);
+        assert!(!store.packed_refs_path().exists(), "nothing was written");
+    }
+    Ok(())
+}
+
+#[test]
+fn stores_with_references_are_handled_like_any_other_packed_refs_transaction() -> crate::Result {
+    let (_keep, store) = store_writable("make_ref_repository.sh")?;
+    let main = store.find_loose("main")?;
+
+    store
+        .transaction()
+        .packed_refs(initial(store.git_dir().join("objects"))?)
+        .prepare(
+            [
+                RefEdit {
+                    change: Change::Update {
+                        log: LogChange::default(),
+                        expected: PreviousValue::MustNotExist,
+                        new: main.target.clone(),
+                    },
+                    name: "refs/heads/new".try_into()?,
+                    deref: false,
+                },
+                RefEdit {
+                    change: Change::Update {
+                        log: LogChange::default(),
+                        expected: PreviousValue::MustExistAndMatch(main.target.clone()),
+                        new: main.target.clone(),
+                    },
+                    name: main.name.clone(),
+                    deref: false,
+                },
+            ],
+            Fail::Immediately,
+            Fail::Immediately,
+        )?
+        .commit(committer().to_ref())?;
+
+    assert!(
+        store.try_find_loose("main")?.is_none(),
+        "existing loose references are moved into packed-refs"
+    );
+    let packed = store.open_packed_buffer()?.expect("present");
+    assert_eq!(packed.find("main")?.target(), main.target.into_id());
+    assert!(packed.try_find("refs/heads/new")?.is_some());
+    Ok(())
+}
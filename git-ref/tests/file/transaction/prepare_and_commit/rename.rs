@@ -0,0 +1,204 @@
+use std::convert::TryInto;
+
+use git_lock::acquire::Fail;
+use git_ref::{
+    file::transaction::{self, PackedRefs},
+    transaction::{Change, PreviousValue, RefEdit},
+    Target,
+};
+
+use crate::file::{
+    store_writable,
+    transaction::prepare_and_commit::{committer, reflog_lines},
+};
+
+fn rename(source: &str, name: &str, copy: bool) -> RefEdit {
+    RefEdit {
+        change: Change::Rename {
+            source: source.try_into().expect("valid"),
+            expected: PreviousValue::MustExist,
+            copy,
+            message: "renamed".into(),
+        },
+        name: name.try_into().expect("valid"),
+        deref: false,
+    }
+}
+
+#[test]
+fn rename_moves_the_reference_along_with_its_reflog() -> crate::Result {
+    let (_keep, store) = store_writable("make_repo_for_reflog.sh")?;
+    let main = store.find_loose("main")?;
+    let main_log = reflog_lines(&store, "refs/heads/main")?;
+
+    let edits = store
+        .transaction()
+        .prepare(
+            Some(rename("refs/heads/main", "refs/heads/renamed", false)),
+            Fail::Immediately,
+            Fail::Immediately,
+        )?
+        .commit(committer().to_ref())?;
+    assert_eq!(
+        edits,
+        vec![RefEdit {
+            change: Change::Rename {
+                source: "refs/heads/main".try_into()?,
+                expected: PreviousValue::MustExistAndMatch(main.target.clone()),
+                copy: false,
+                message: "renamed".into(),
+            },
+            name: "refs/heads/renamed".try_into()?,
+            deref: false,
+        }],
+        "the previous value of the source is filled in"
+    );
+    assert_eq!(edits[0].change.new_value(), Some(main.target.to_ref()));
+
+    assert!(store.try_find_loose("main")?.is_none(), "the source is gone");
+    assert!(!store.reflog_exists("refs/heads/main")?, "and so is its reflog");
+    let renamed = store.find_loose("renamed")?;
+    assert_eq!(renamed.target, main.target);
+
+    let log = reflog_lines(&store, "refs/heads/renamed")?;
+    assert_eq!(log.len(), main_log.len() + 1, "one line was added to the moved reflog");
+    assert_eq!(&log[..main_log.len()], main_log.as_slice());
+    let last = log.last().expect("present");
+    let id = main.target.try_id().expect("peeled");
+    assert_eq!(last.previous_oid, id);
+    assert_eq!(last.new_oid, id);
+    assert_eq!(last.message, "renamed");
+    Ok(())
+}
+
+#[test]
+fn copy_keeps_the_source_and_its_reflog() -> crate::Result {
+    let (_keep, store) = store_writable("make_repo_for_reflog.sh")?;
+    let main_log = reflog_lines(&store, "refs/heads/main")?;
+
+    store
+        .transaction()
+        .prepare(
+            Some(rename("refs/heads/main", "refs/heads/copy", true)),
+            Fail::Immediately,
+            Fail::Immediately,
+        )?
+        .commit(committer().to_ref())?;
+
+    assert_eq!(store.find_loose("main")?.target, store.find_loose("copy")?.target);
+    assert_eq!(
+        reflog_lines(&store, "refs/heads/main")?,
+        main_log,
+        "the source is untouched"
+    );
+    assert_eq!(reflog_lines(&store, "refs/heads/copy")?.len(), main_log.len() + 1);
+    Ok(())
+}
+
+#[test]
+fn packed_references_can_be_renamed() -> crate::Result {
+    let (_keep, store) = store_writable("make_packed_ref_repository.sh")?;
+    let d1 = store.find("refs/heads/d1")?;
+    assert!(
+        store.try_find_loose("refs/heads/d1")?.is_none(),
+        "the source is only packed"
+    );
+
+    for packed_refs in [
+        PackedRefs::DeletionsOnly,
+        PackedRefs::DeletionsAndNonSymbolicUpdates(Box::new(|_, _| Ok(Some(git_object::Kind::Commit)))),
+    ] {
+        let (source, name) = match packed_refs {
+            PackedRefs::DeletionsOnly => ("refs/heads/d1", "refs/heads/renamed"),
+            _ => ("refs/heads/renamed", "refs/heads/d1"),
+        };
+        store
+            .transaction()
+            .packed_refs(packed_refs)
+            .prepare(Some(rename(source, name, false)), Fail::Immediately, Fail::Immediately)?
+            .commit(committer().to_ref())?;
+        assert!(store.try_find(source)?.is_none(), "the source isn't packed anymore");
+        assert_eq!(store.find(name)?.target, d1.target);
+    }
+    Ok(())
+}
+
+#[test]
+fn conflicts_are_rejected_without_changing_anything() -> crate::Result {
+    let (_keep, store) = store_writable("make_packed_ref_repository.sh")?;
+    let packed_main = store.find("refs/heads/main")?;
+
+    for (source, name, expected_error) in [
+        (
+            "refs/heads/main",
+            "refs/heads/dt1",
+            "Reference \"refs/heads/dt1\" was not supposed to exist when writing it with value Peeled(",
+        ),
+        (
+            "refs/heads/main",
+            "refs/heads/d1/sub",
+            "Reference \"refs/heads/d1/sub\" cannot be created as it clashes with the existing reference \"refs/heads/d1\"",
+        ),
+        (
+            "refs/heads/main",
+            "refs/heads/main/sub",
+            "Reference \"refs/heads/main/sub\" cannot be created as it clashes with the existing reference \"refs/heads/main\"",
+        ),
+        (
+            "refs/d1",
+            "refs/remotes/origin",
+            "Reference \"refs/remotes/origin\" cannot be created as it clashes with the existing reference \"refs/remotes/origin/HEAD\"",
+        ),
+        (
+            "refs/heads/does-not-exist",
+            "refs/heads/new",
+            "Reference \"refs/heads/does-not-exist\" was supposed to exist with value 0000000000000000000000000000000000000000, but didn't.",
+        ),
+    ] {
+        let err = store
+            .transaction()
+            .prepare(Some(rename(source, name, false)), Fail::Immediately, Fail::Immediately)
+            .unwrap_err();
+        assert!(err.to_string().starts_with(expected_error), "{err}");
+    }
+
+    let err = store
+        .transaction()
+        .prepare(
+            Some(RefEdit {
+                change: Change::Rename {
+                    source: "refs/heads/main".try_into()?,
+                    expected: PreviousValue::MustExistAndMatch(Target::Symbolic("refs/heads/other".try_into()?)),
+                    copy: false,
+                    message: Default::default(),
+                },
+                name: "refs/heads/new".try_into()?,
+                deref: false,
+            }),
+            Fail::Immediately,
+            Fail::Immediately,
+        )
+        .unwrap_err();
+    assert!(matches!(err, transaction::prepare::Error::ReferenceOutOfDate { .. }));
+
+    let err = store
+        .transaction()
+        .prepare(
+            [
+                rename("refs/heads/main", "refs/heads/new", false),
+                rename("refs/heads/main", "refs/heads/other", true),
+            ],
+            Fail::Immediately,
+            Fail::Immediately,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Edit preprocessing failed with an error",
+        "a reference can only be the source of one rename"
+    );
+
+    assert_eq!(store.find("refs/heads/main")?, packed_main, "nothing changed");
+    assert!(store.try_find("refs/heads/new")?.is_none());
+    Ok(())
+}
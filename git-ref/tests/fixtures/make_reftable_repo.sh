@@ -0,0 +1,17 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q --ref-format=reftable
+
+git checkout -q -b main
+git commit -q --allow-empty -m c1
+git tag -m "annotated" annotated
+git commit -q --allow-empty -m c2
+
+# enough references to need more than one ref block, which is when an object index is written
+current_commit="$(git rev-parse HEAD)"
+for refname in $(seq -w 500); do
+  echo "create refs/heads/a-branch-with-a-rather-long-name-to-fill-blocks-$refname $current_commit"
+done | git update-ref --stdin
+
+git pack-refs --all
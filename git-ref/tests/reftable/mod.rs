@@ -0,0 +1,719 @@
+use git_actor::{Sign, Time};
+use git_hash::ObjectId;
+use git_ref::{
+    reftable,
+    transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog},
+    FullName, Target,
+};
+
+use crate::util::hex_to_id;
+
+fn empty_store() -> (tempfile::TempDir, reftable::Store) {
+    let dir = tempfile::TempDir::new().unwrap();
+    let store = reftable::Store::at(dir.path(), git_ref::store::WriteReflog::Normal, git_hash::Kind::Sha1);
+    (dir, store)
+}
+
+fn committer() -> git_actor::Signature {
+    git_actor::Signature {
+        name: "committer".into(),
+        email: "committer@example.com".into(),
+        time: Time {
+            seconds_since_unix_epoch: 1234,
+            offset_in_seconds: -5400,
+            sign: Sign::Minus,
+        },
+    }
+}
+
+fn name(name: &str) -> FullName {
+    name.try_into().expect("valid")
+}
+
+fn id(index: u8) -> ObjectId {
+    let mut id = ObjectId::null(git_hash::Kind::Sha1);
+    id.as_mut_slice()[0] = index;
+    id.as_mut_slice()[19] = 1;
+    id
+}
+
+fn update(full_name: &str, new: Target, expected: PreviousValue) -> RefEdit {
+    RefEdit {
+        change: Change::Update {
+            log: LogChange {
+                mode: RefLog::AndReference,
+                force_create_reflog: false,
+                message: "update".into(),
+            },
+            expected,
+            new,
+        },
+        name: name(full_name),
+        deref: false,
+    }
+}
+
+fn commit(store: &reftable::Store, edits: impl IntoIterator<Item = RefEdit>) -> crate::Result<Vec<RefEdit>> {
+    Ok(store
+        .transaction()
+        .prepare(edits, git_lock::acquire::Fail::Immediately)?
+        .commit(committer().to_ref())?)
+}
+
+mod table {
+    use git_ref::reftable::{self, LogRecord, RefRecord, RefValue, Table};
+
+    use super::{committer, id, name};
+
+    fn records(count: usize) -> (Vec<RefRecord>, Vec<LogRecord>) {
+        let refs = (0..count)
+            .map(|i| RefRecord {
+                name: name(&format!("refs/heads/branch-{i:04}")),
+                update_index: 1 + (i % 3) as u64,
+                value: match i % 4 {
+                    0 => RefValue::Deletion,
+                    1 => RefValue::Peeled {
+                        target: id((i % 256) as u8),
+                        peeled: id(0),
+                    },
+                    2 => RefValue::Symbolic(name("refs/heads/main")),
+                    _ => RefValue::Object(id((i % 256) as u8)),
+                },
+            })
+            .collect();
+        let logs = (0..count)
+            .map(|i| LogRecord {
+                name: name(&format!("refs/heads/branch-{:04}", i / 2)),
+                update_index: 1 + (i % 2) as u64,
+                value: (i % 5 != 0).then(|| git_ref::log::Line {
+                    previous_oid: id(1),
+                    new_oid: id(2),
+                    signature: committer(),
+                    message: format!("message {i}").into(),
+                }),
+            })
+            .collect();
+        (refs, logs)
+    }
+
+    #[test]
+    fn round_trip_with_indices_in_small_and_unaligned_blocks() -> crate::Result {
+        for block_size in [0, 256, 4096] {
+            for count in [0, 1, 500] {
+                let (refs, mut logs) = records(count);
+                let options = reftable::write::Options {
+                    block_size,
+                    ..Default::default()
+                };
+                let data = reftable::write::table(&refs, &logs, 1, 3, git_hash::Kind::Sha1, &options)?;
+                let table = Table::from_bytes(data, git_hash::Kind::Sha1)?;
+                assert_eq!((table.min_update_index(), table.max_update_index()), (1, 3));
+
+                assert_eq!(table.refs().collect::<Result<Vec<_>, _>>()?, refs);
+                logs.sort_by(|a, b| a.name.cmp(&b.name).then(b.update_index.cmp(&a.update_index)));
+                assert_eq!(table.logs().collect::<Result<Vec<_>, _>>()?, logs);
+
+                for record in &refs {
+                    assert_eq!(table.find(record.name.as_ref())?.as_ref(), Some(record));
+                    let expected: Vec<_> = logs.iter().filter(|l| l.name == record.name).cloned().collect();
+                    assert_eq!(
+                        table.logs_of(record.name.as_ref())?.collect::<Result<Vec<_>, _>>()?,
+                        expected
+                    );
+                }
+                assert_eq!(table.find(name("refs/heads/a").as_ref())?, None);
+                assert_eq!(table.find(name("refs/heads/zz").as_ref())?, None);
+
+                let prefixed = table
+                    .refs_prefixed(b"refs/heads/branch-01")?
+                    .collect::<Result<Vec<_>, _>>()?;
+                assert_eq!(
+                    prefixed,
+                    refs.iter()
+                        .filter(|r| r.name.as_bstr().starts_with(b"refs/heads/branch-01"))
+                        .cloned()
+                        .collect::<Vec<_>>()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn objects_are_found_with_and_without_object_index() -> crate::Result {
+        let (refs, logs) = records(500);
+        for index_objects in [true, false] {
+            let options = reftable::write::Options {
+                block_size: 256,
+                index_objects,
+                ..Default::default()
+            };
+            let data = reftable::write::table(&refs, &logs, 1, 3, git_hash::Kind::Sha1, &options)?;
+            let table = Table::from_bytes(data, git_hash::Kind::Sha1)?;
+
+            let names = |records: Vec<RefRecord>| records.into_iter().map(|r| r.name).collect::<Vec<_>>();
+            assert_eq!(
+                names(table.refs_by_object(&id(7))?),
+                vec![
+                    name("refs/heads/branch-0007"),
+                    name("refs/heads/branch-0263"),
+                    name("refs/heads/branch-0519")
+                ]
+                .into_iter()
+                .filter(|n| refs.iter().any(|r| &r.name == n))
+                .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                table.refs_by_object(&id(0))?.len(),
+                125,
+                "peeled values are indexed as well"
+            );
+            assert!(table.refs_by_object(&id(0xfe))?.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn corrupt_tables_are_detected() -> crate::Result {
+        let (refs, logs) = records(10);
+        let data = reftable::write::table(&refs, &logs, 1, 3, git_hash::Kind::Sha1, &Default::default())?;
+
+        let mut corrupt = data.clone();
+        let last = corrupt.len() - 10;
+        corrupt[last] ^= 0xff;
+        assert!(matches!(
+            Table::from_bytes(corrupt, git_hash::Kind::Sha1),
+            Err(reftable::table::decode::Error::InvalidFooter)
+        ));
+        assert!(matches!(
+            Table::from_bytes(data[..20].to_vec(), git_hash::Kind::Sha1),
+            Err(reftable::table::decode::Error::InvalidHeader)
+        ));
+        assert!(matches!(
+            Table::from_bytes(data, git_hash::Kind::Sha256),
+            Err(reftable::table::decode::Error::ObjectHashMismatch { .. })
+        ));
+        Ok(())
+    }
+}
+
+mod transaction {
+    use git_ref::{
+        reftable,
+        transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog},
+        Target,
+    };
+
+    use super::{commit, committer, empty_store, hex_to_id, name, update};
+
+    #[test]
+    fn create_update_and_delete_references_along_with_their_reflogs() -> crate::Result {
+        let (_keep, store) = empty_store();
+        let (first, second) = (
+            hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242"),
+            hex_to_id("134385f6d781b7e97062102c6a483440bfda2a03"),
+        );
+        let edits = commit(
+            &store,
+            [
+                update("refs/heads/main", Target::Peeled(first), PreviousValue::MustNotExist),
+                update(
+                    "HEAD",
+                    Target::Symbolic(name("refs/heads/main")),
+                    PreviousValue::MustNotExist,
+                ),
+                update("refs/tags/v1", Target::Peeled(first), PreviousValue::Any),
+            ],
+        )?;
+        assert_eq!(edits.len(), 3);
+        assert!(
+            !store.reflog_exists("refs/tags/v1")?,
+            "tags don't get reflogs by default"
+        );
+
+        let head = store.find("HEAD")?;
+        assert_eq!(head.target, Target::Symbolic(name("refs/heads/main")));
+        assert_eq!(store.find("main")?.target, Target::Peeled(first));
+        assert_eq!(store.find("v1")?.name, name("refs/tags/v1"));
+
+        let mut edit = update("HEAD", Target::Peeled(second), PreviousValue::MustExist);
+        edit.deref = true;
+        let edits = commit(&store, Some(edit))?;
+        assert_eq!(edits.len(), 2, "HEAD was split to also update its referent");
+        assert_eq!(
+            edits[1].change.previous_value().map(|v| v.into_owned()),
+            Some(Target::Peeled(first)),
+            "previous values are filled in"
+        );
+        assert_eq!(
+            store.find("HEAD")?.target,
+            Target::Symbolic(name("refs/heads/main")),
+            "symbolic refs stay in place"
+        );
+        assert_eq!(store.find("main")?.target, Target::Peeled(second));
+
+        let main_log: Vec<_> = store.reflog_iter("refs/heads/main")?.expect("log exists").collect();
+        assert_eq!(main_log.len(), 2);
+        assert_eq!(main_log[0].previous_oid, git_hash::Kind::Sha1.null());
+        assert_eq!((main_log[1].previous_oid, main_log[1].new_oid), (first, second));
+        assert_eq!(main_log[1].signature, committer());
+        assert_eq!(main_log[1].message, "update");
+        let head_log: Vec<_> = store.reflog_iter_rev("HEAD")?.expect("log exists").collect();
+        assert_eq!(
+            head_log.len(),
+            1,
+            "HEAD only gets a log entry once it points to an object"
+        );
+        assert_eq!((head_log[0].previous_oid, head_log[0].new_oid), (first, second));
+
+        let references: Vec<_> = store.iter()?.map(|r| r.name).collect();
+        assert_eq!(
+            references,
+            vec![name("HEAD"), name("refs/heads/main"), name("refs/tags/v1")]
+        );
+        assert_eq!(store.iter_prefixed("refs/heads/")?.count(), 1);
+        assert_eq!(
+            store
+                .refs_by_object(&first)?
+                .into_iter()
+                .map(|r| r.name)
+                .collect::<Vec<_>>(),
+            vec![name("refs/tags/v1")]
+        );
+
+        commit(
+            &store,
+            Some(RefEdit {
+                change: Change::Delete {
+                    expected: PreviousValue::MustExistAndMatch(Target::Peeled(second)),
+                    log: RefLog::AndReference,
+                },
+                name: name("refs/heads/main"),
+                deref: false,
+            }),
+        )?;
+        assert!(store.try_find("main")?.is_none());
+        assert!(
+            store.reflog_iter("refs/heads/main")?.is_none(),
+            "reflogs are deleted as well"
+        );
+        assert!(store.reflog_iter("HEAD")?.is_some());
+
+        assert!(store.compact_all(git_lock::acquire::Fail::Immediately)?);
+        let stack = store.stack()?;
+        assert_eq!(stack.tables().len(), 1);
+        assert_eq!(
+            stack.tables()[0].logs().count(),
+            1,
+            "deletions and deleted log entries are dropped when compacting everything"
+        );
+        assert_eq!(store.iter()?.count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn expectations_are_validated_and_nothing_is_written_on_failure() -> crate::Result {
+        let (_keep, store) = empty_store();
+        let first = hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242");
+        commit(
+            &store,
+            Some(update("refs/heads/main", Target::Peeled(first), PreviousValue::Any)),
+        )?;
+
+        let err = commit(
+            &store,
+            Some(update(
+                "refs/heads/main",
+                Target::Peeled(first),
+                PreviousValue::MustExistAndMatch(Target::Peeled(git_hash::Kind::Sha1.null())),
+            )),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("should have content"), "{err}");
+
+        let err = store
+            .transaction()
+            .prepare(
+                Some(RefEdit {
+                    change: Change::Delete {
+                        expected: PreviousValue::MustExist,
+                        log: RefLog::AndReference,
+                    },
+                    name: name("refs/heads/missing"),
+                    deref: false,
+                }),
+                git_lock::acquire::Fail::Immediately,
+            )
+            .expect_err("must fail");
+        assert!(matches!(
+            err,
+            reftable::transaction::prepare::Error::DeleteReferenceMustExist { .. }
+        ));
+
+        let err = store
+            .transaction()
+            .prepare(
+                Some(update(
+                    "refs/heads/other",
+                    Target::Peeled(first),
+                    PreviousValue::MustNotExist,
+                )),
+                git_lock::acquire::Fail::Immediately,
+            )?
+            .commit(None)
+            .unwrap_err();
+        assert!(matches!(err, reftable::transaction::commit::Error::MissingCommitter));
+        assert!(store.try_find("other")?.is_none(), "transactions are atomic");
+
+        let _prepared = store.transaction().prepare(
+            Some(update("refs/heads/a", Target::Peeled(first), PreviousValue::Any)),
+            git_lock::acquire::Fail::Immediately,
+        )?;
+        assert!(
+            matches!(
+                store
+                    .transaction()
+                    .prepare(
+                        Some(update("refs/heads/b", Target::Peeled(first), PreviousValue::Any)),
+                        git_lock::acquire::Fail::Immediately,
+                    )
+                    .err(),
+                Some(reftable::transaction::prepare::Error::LockAcquire(_))
+            ),
+            "the stack is locked while a transaction is prepared"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn references_clashing_with_existing_or_new_ones_are_rejected() -> crate::Result {
+        let (_keep, store) = empty_store();
+        let first = hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242");
+        commit(
+            &store,
+            [
+                update("refs/heads/a", Target::Peeled(first), PreviousValue::Any),
+                update("refs/heads/b/c", Target::Peeled(first), PreviousValue::Any),
+            ],
+        )?;
+
+        for (edits, expected) in [
+            (
+                vec![update("refs/heads/a/b", Target::Peeled(first), PreviousValue::Any)],
+                "Reference \"refs/heads/a/b\" cannot be created as it clashes with the existing reference \"refs/heads/a\"",
+            ),
+            (
+                vec![update("refs/heads/b", Target::Peeled(first), PreviousValue::MustNotExist)],
+                "Reference \"refs/heads/b\" cannot be created as it clashes with the existing reference \"refs/heads/b/c\"",
+            ),
+            (
+                vec![
+                    update("refs/heads/new", Target::Peeled(first), PreviousValue::Any),
+                    update("refs/heads/new/sub", Target::Peeled(first), PreviousValue::Any),
+                ],
+                "Reference \"refs/heads/new\" cannot be created as it clashes with the existing reference \"refs/heads/new/sub\"",
+            ),
+        ] {
+            let err = store
+                .transaction()
+                .prepare(edits, git_lock::acquire::Fail::Immediately)
+                .expect_err("must fail");
+            assert_eq!(err.to_string(), expected);
+        }
+        assert_eq!(
+            store.iter()?.map(|r| r.name).collect::<Vec<_>>(),
+            vec![name("refs/heads/a"), name("refs/heads/b/c")],
+            "nothing was written"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reflogs_follow_the_write_reflog_mode() -> crate::Result {
+        let (_keep, mut store) = empty_store();
+        let first = hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242");
+        let mut forced = update("refs/tags/forced", Target::Peeled(first), PreviousValue::Any);
+        if let Change::Update { log, .. } = &mut forced.change {
+            *log = LogChange {
+                force_create_reflog: true,
+                ..log.clone()
+            };
+        }
+        commit(&store, Some(forced))?;
+        assert!(store.reflog_exists("refs/tags/forced")?);
+
+        store.write_reflog = git_ref::store::WriteReflog::Disable;
+        commit(
+            &store,
+            Some(update("refs/heads/main", Target::Peeled(first), PreviousValue::Any)),
+        )?;
+        assert!(!store.reflog_exists("refs/heads/main")?);
+
+        store.write_reflog = git_ref::store::WriteReflog::Always;
+        commit(
+            &store,
+            Some(update("refs/tags/always", Target::Peeled(first), PreviousValue::Any)),
+        )?;
+        assert!(store.reflog_exists("refs/tags/always")?);
+        Ok(())
+    }
+
+    #[test]
+    fn references_are_renamed_and_copied_along_with_their_reflogs() -> crate::Result {
+        let (_keep, store) = empty_store();
+        let (first, second) = (
+            hex_to_id("28ce6a8b26aa170e1de65536fe8abe1832bd3242"),
+            hex_to_id("134385f6d781b7e97062102c6a483440bfda2a03"),
+        );
+        commit(
+            &store,
+            Some(update("refs/heads/main", Target::Peeled(first), PreviousValue::Any)),
+        )?;
+        commit(
+            &store,
+            Some(update("refs/heads/main", Target::Peeled(second), PreviousValue::Any)),
+        )?;
+        let rename = |source: &str, full_name: &str, copy: bool| RefEdit {
+            change: Change::Rename {
+                source: name(source),
+                expected: PreviousValue::MustExist,
+                copy,
+                message: "renamed".into(),
+            },
+            name: name(full_name),
+            deref: false,
+        };
+
+        let edits = commit(&store, Some(rename("refs/heads/main", "refs/heads/renamed", false)))?;
+        assert_eq!(
+            edits[0].change.new_value(),
+            Some(Target::Peeled(second).to_ref()),
+            "the value of the source is filled in"
+        );
+        assert!(store.try_find("refs/heads/main")?.is_none());
+        assert!(!store.reflog_exists("refs/heads/main")?, "the reflog moved…");
+        assert_eq!(store.find("refs/heads/renamed")?.target, Target::Peeled(second));
+        let log: Vec<_> = store.reflog_iter("refs/heads/renamed")?.expect("present").collect();
+        assert_eq!(log.len(), 3, "…and an entry was added");
+        assert_eq!((log[0].previous_oid, log[0].new_oid), (first.kind().null(), first));
+        assert_eq!((log[2].previous_oid, log[2].new_oid), (second, second));
+        assert_eq!(log[2].message, "renamed");
+
+        commit(&store, Some(rename("refs/heads/renamed", "refs/heads/copy", true)))?;
+        assert_eq!(store.find("refs/heads/copy")?.target, Target::Peeled(second));
+        assert_eq!(store.reflog_iter("refs/heads/renamed")?.expect("present").count(), 3);
+        assert_eq!(store.reflog_iter("refs/heads/copy")?.expect("present").count(), 4);
+
+        for (source, full_name, expected) in [
+            (
+                "refs/heads/renamed",
+                "refs/heads/copy",
+                "Reference \"refs/heads/copy\" was not supposed to exist",
+            ),
+            (
+                "refs/heads/renamed",
+                "refs/heads/copy/sub",
+                "Reference \"refs/heads/copy/sub\" cannot be created as it clashes with the existing reference \"refs/heads/copy\"",
+            ),
+            (
+                "refs/heads/copy",
+                "refs/heads",
+                "Reference \"refs/heads\" cannot be created as it clashes with the existing reference \"refs/heads/copy\"",
+            ),
+            (
+                "refs/heads/missing",
+                "refs/heads/new",
+                "Reference \"refs/heads/missing\" was supposed to exist",
+            ),
+        ] {
+            let err = store
+                .transaction()
+                .prepare(
+                    Some(rename(source, full_name, false)),
+                    git_lock::acquire::Fail::Immediately,
+                )
+                .unwrap_err();
+            assert!(err.to_string().starts_with(expected), "{err}");
+        }
+        Ok(())
+    }
+}
+
+mod stack {
+    use git_ref::{transaction::PreviousValue, Target};
+
+    use super::{commit, empty_store, id, update};
+
+    #[test]
+    fn tables_are_compacted_automatically_and_reused_across_clones() -> crate::Result {
+        let (_keep, store) = empty_store();
+        for i in 0..64u8 {
+            commit(
+                &store,
+                Some(update(
+                    &format!("refs/heads/b{i}"),
+                    Target::Peeled(id(i)),
+                    PreviousValue::MustNotExist,
+                )),
+            )?;
+        }
+        let stack = store.stack()?;
+        assert!(
+            stack.tables().len() <= 7,
+            "table sizes follow a geometric sequence, got {} tables",
+            stack.tables().len()
+        );
+        assert_eq!(stack.max_update_index(), 64);
+        assert_eq!(
+            std::fs::read_dir(store.reftable_dir())?.count(),
+            stack.tables().len() + 1
+        );
+
+        let clone = store.clone();
+        assert_eq!(clone.iter()?.count(), 64);
+        for i in 0..64u8 {
+            assert_eq!(clone.find(format!("b{i}").as_str())?.target, Target::Peeled(id(i)));
+        }
+
+        let mut store = store;
+        store.auto_compact = false;
+        commit(
+            &store,
+            Some(update("refs/heads/b0", Target::Peeled(id(1)), PreviousValue::Any)),
+        )?;
+        assert_eq!(store.stack()?.tables().len(), stack.tables().len() + 1);
+        assert_eq!(store.find("b0")?.target, Target::Peeled(id(1)), "newer tables win");
+        Ok(())
+    }
+}
+
+mod reflog {
+    use git_lock::acquire::Fail;
+    use git_ref::{file::log::expire::Options, transaction::PreviousValue, Target};
+
+    use super::{commit, empty_store, id, update};
+
+    #[test]
+    fn entries_are_deleted_and_expired() -> crate::Result {
+        let (_keep, store) = empty_store();
+        for index in 1..=4 {
+            commit(
+                &store,
+                Some(update("refs/heads/main", Target::Peeled(id(index)), PreviousValue::Any)),
+            )?;
+        }
+        let log = |store: &git_ref::reftable::Store| -> crate::Result<Vec<_>> {
+            Ok(store
+                .reflog_iter("refs/heads/main")?
+                .expect("present")
+                .map(|line| (line.previous_oid, line.new_oid))
+                .collect())
+        };
+
+        let outcome = store
+            .reflog_delete_entry("refs/heads/main", 1, true, Fail::Immediately)?
+            .expect("log present");
+        assert_eq!(outcome.kept, 3);
+        assert_eq!(outcome.removed.len(), 1);
+        assert_eq!(outcome.removed[0].new_oid, id(3), "`1` is the second-most recent entry");
+        assert_eq!(
+            log(&store)?,
+            vec![(id(1).kind().null(), id(1)), (id(1), id(2)), (id(2), id(4))],
+            "the entry after the removed one was rewritten"
+        );
+        assert!(store
+            .reflog_delete_entry("refs/heads/main", 10, true, Fail::Immediately)?
+            .expect("log present")
+            .removed
+            .is_empty());
+        assert!(store
+            .reflog_delete_entry("refs/heads/missing", 0, false, Fail::Immediately)?
+            .is_none());
+
+        let options = Options {
+            expire_total: Some(1235),
+            dry_run: true,
+            ..Default::default()
+        };
+        let outcome = store
+            .reflog_expire(
+                "refs/heads/main",
+                options,
+                |_| unreachable!("not needed"),
+                Fail::Immediately,
+            )?
+            .expect("log present");
+        assert_eq!((outcome.kept, outcome.removed.len()), (0, 3));
+        assert_eq!(log(&store)?.len(), 3, "nothing was changed in a dry-run");
+
+        let options = Options {
+            expire_unreachable: Some(1235),
+            ..Default::default()
+        };
+        let outcome = store
+            .reflog_expire("refs/heads/main", options, |oid| oid != id(1), Fail::Immediately)?
+            .expect("log present");
+        assert_eq!((outcome.kept, outcome.removed.len()), (1, 2));
+        assert_eq!(log(&store)?, vec![(id(2), id(4))]);
+        Ok(())
+    }
+}
+
+mod produced_by_git {
+    use git_ref::{bstr::ByteSlice, reftable, Target};
+
+    use super::name;
+
+    #[test]
+    fn tables_with_refs_logs_and_object_index_are_decoded() -> crate::Result {
+        if git_testtools::should_skip_as_git_version_is_smaller_than(2, 45, 0) {
+            return Ok(());
+        }
+        let git_dir = git_testtools::scripted_fixture_read_only_standalone("make_reftable_repo.sh")?.join(".git");
+        let store = reftable::Store::at(git_dir, git_ref::store::WriteReflog::Normal, git_hash::Kind::Sha1);
+
+        let stack = store.stack()?;
+        assert_eq!(stack.tables().len(), 1, "all tables were compacted by `git pack-refs`");
+        assert!(
+            stack.tables()[0].has_object_index(),
+            "git writes an object index if there is more than one ref block"
+        );
+
+        assert_eq!(store.find("HEAD")?.target, Target::Symbolic(name("refs/heads/main")));
+        let c2 = store.find("main")?.target.try_id().expect("peeled").to_owned();
+        let annotated = store.find("annotated")?;
+        let c1 = annotated.peeled.expect("git stores the peeled value of annotated tags");
+        assert_ne!(
+            annotated.target.try_id(),
+            Some(c1.as_ref()),
+            "the target is the tag object"
+        );
+        assert_ne!(c1, c2);
+
+        assert_eq!(
+            store
+                .iter_prefixed("refs/heads/a-branch-with-a-rather-long-name-to-fill-blocks-")?
+                .count(),
+            500
+        );
+        assert_eq!(
+            store.refs_by_object(&c2)?.len(),
+            501,
+            "all branches point to the second commit, found through the object index"
+        );
+        assert_eq!(
+            store.refs_by_object(&c1)?,
+            vec![annotated],
+            "peeled values are indexed as well"
+        );
+
+        let log: Vec<_> = store.reflog_iter("refs/heads/main")?.expect("log present").collect();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].message.trim().as_bstr(), "commit (initial): c1");
+        assert!(log[0].previous_oid.is_null());
+        assert_eq!(log[1].message.trim().as_bstr(), "commit: c2");
+        assert_eq!(log[1].previous_oid, log[0].new_oid);
+        assert_eq!(log[1].new_oid, c2);
+        Ok(())
+    }
+}
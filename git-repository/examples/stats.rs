@@ -13,6 +13,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .ok_or("There are no commits - nothing to do here.")??
         .ancestors()
         .all()?
+        .map(|info| info.map(|info| info.id))
         .inspect(|id| {
             if let Ok(Ok(object)) = id.as_ref().map(|id| id.object()) {
                 avg_commit_size += object.data.len();
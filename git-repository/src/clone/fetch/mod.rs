@@ -121,9 +121,10 @@ impl PrepareFetch {
             .with_reflog_message(RefLogMessage::Override {
                 message: reflog_message.clone(),
             })
+            .with_shallow(self.shallow.clone())
             .receive(should_interrupt)?;
 
-        util::replace_changed_local_config_file(repo, config);
+        repo.replace_changed_local_config_file(config);
         util::update_head(
             repo,
             &outcome.ref_map.remote_refs,
@@ -158,6 +159,13 @@ impl PrepareFetch {
         self.fetch_options = opts;
         self
     }
+
+    /// Make this clone a shallow one with the respective choice of shallow-ness.
+    #[cfg(any(feature = "async-network-client", feature = "blocking-network-client"))]
+    pub fn with_shallow(mut self, shallow: crate::remote::fetch::Shallow) -> Self {
+        self.shallow = shallow;
+        self
+    }
     /// Use `f` to apply arbitrary changes to the remote that is about to be used to fetch a pack.
     ///
     /// The passed in `remote` will be un-named and pre-configured to be a default remote as we know it from git-clone.
@@ -27,29 +27,6 @@ pub fn write_remote_to_local_config_file(
     Ok(config)
 }
 
-pub fn replace_changed_local_config_file(repo: &mut Repository, mut config: git_config::File<'static>) {
-    let repo_config = git_features::threading::OwnShared::make_mut(&mut repo.config.resolved);
-    let ids_to_remove: Vec<_> = repo_config
-        .sections_and_ids()
-        .filter_map(|(s, id)| {
-            matches!(s.meta().source, git_config::Source::Local | git_config::Source::Api).then(|| id)
-        })
-        .collect();
-    for id in ids_to_remove {
-        repo_config.remove_section_by_id(id);
-    }
-    crate::config::overrides::append(
-        &mut config,
-        &repo.options.api_config_overrides,
-        git_config::Source::Api,
-        |_| None,
-    )
-    .expect("applied once and can be applied again");
-    repo_config.append(config);
-    repo.reread_values_and_clear_caches()
-        .expect("values could be read once and can be read again");
-}
-
 /// HEAD cannot be written by means of refspec by design, so we have to do it manually here. Also create the pointed-to ref
 /// if we have to, as it might not have been naturally included in the ref-specs.
 pub fn update_head(
@@ -94,50 +71,37 @@ pub fn update_head(
                 head_ref_name: referent.to_owned(),
                 source: err,
             })?;
-            repo.refs
-                .transaction()
-                .packed_refs(git_ref::file::transaction::PackedRefs::DeletionsAndNonSymbolicUpdates(
-                    Box::new(|oid, buf| {
-                        repo.objects
-                            .try_find(oid, buf)
-                            .map(|obj| obj.map(|obj| obj.kind))
-                            .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send + Sync + 'static>)
-                    }),
-                ))
-                .prepare(
-                    {
-                        let mut edits = vec![RefEdit {
+            repo.edit_references_with_packed_refs(
+                {
+                    let mut edits = vec![RefEdit {
+                        change: git_ref::transaction::Change::Update {
+                            log: reflog_message(),
+                            expected: PreviousValue::Any,
+                            new: Target::Symbolic(referent.clone()),
+                        },
+                        name: head.clone(),
+                        deref: false,
+                    }];
+                    if let Some(head_peeled_id) = head_peeled_id {
+                        edits.push(RefEdit {
                             change: git_ref::transaction::Change::Update {
                                 log: reflog_message(),
                                 expected: PreviousValue::Any,
-                                new: Target::Symbolic(referent.clone()),
+                                new: Target::Peeled(head_peeled_id.to_owned()),
                             },
-                            name: head.clone(),
+                            name: referent.clone(),
                             deref: false,
-                        }];
-                        if let Some(head_peeled_id) = head_peeled_id {
-                            edits.push(RefEdit {
-                                change: git_ref::transaction::Change::Update {
-                                    log: reflog_message(),
-                                    expected: PreviousValue::Any,
-                                    new: Target::Peeled(head_peeled_id.to_owned()),
-                                },
-                                name: referent.clone(),
-                                deref: false,
-                            });
-                        };
-                        edits
-                    },
-                    git_lock::acquire::Fail::Immediately,
-                    git_lock::acquire::Fail::Immediately,
-                )
-                .map_err(crate::reference::edit::Error::from)?
-                .commit(
-                    repo.committer()
-                        .transpose()
-                        .map_err(|err| Error::HeadUpdate(crate::reference::edit::Error::ParseCommitterTime(err)))?,
-                )
-                .map_err(crate::reference::edit::Error::from)?;
+                        });
+                    };
+                    edits
+                },
+                git_ref::file::transaction::PackedRefs::DeletionsAndNonSymbolicUpdates(Box::new(|oid, buf| {
+                    repo.objects
+                        .try_find(oid, buf)
+                        .map(|obj| obj.map(|obj| obj.kind))
+                        .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send + Sync + 'static>)
+                })),
+            )?;
 
             if let Some(head_peeled_id) = head_peeled_id {
                 let mut log = reflog_message();
@@ -219,7 +183,7 @@ fn setup_branch_config(
             Some(branch.as_bstr()),
         );
         std::fs::write(config_path, config.to_bstring())?;
-        replace_changed_local_config_file(repo, config);
+        repo.replace_changed_local_config_file(config);
     }
     Ok(())
 }
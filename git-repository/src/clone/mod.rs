@@ -19,6 +19,9 @@ pub struct PrepareFetch {
     /// Options for preparing a fetch operation.
     #[cfg(any(feature = "async-network-client", feature = "blocking-network-client"))]
     fetch_options: crate::remote::ref_map::Options,
+    /// How to handle shallow clones
+    #[cfg(any(feature = "async-network-client", feature = "blocking-network-client"))]
+    shallow: crate::remote::fetch::Shallow,
     /// The url to clone from
     #[cfg_attr(not(feature = "blocking-network-client"), allow(dead_code))]
     url: git_url::Url,
@@ -69,6 +72,8 @@ impl PrepareFetch {
             url,
             #[cfg(any(feature = "async-network-client", feature = "blocking-network-client"))]
             fetch_options: Default::default(),
+            #[cfg(any(feature = "async-network-client", feature = "blocking-network-client"))]
+            shallow: Default::default(),
             repo: Some(repo),
             remote_name: None,
             configure_remote: None,
@@ -0,0 +1,44 @@
+//! Commit-graphs cache commit information like parents, commit dates and generation numbers to speed up history traversals.
+//!
+//! They are stored in the object database's `info` directory and can be updated with
+//! [`Repository::write_commit_graph()`][crate::Repository::write_commit_graph()].
+pub use git_commitgraph::*;
+
+///
+pub mod write {
+    pub use git_commitgraph::graph::write::Mode;
+
+    /// Options for use in [`Repository::write_commit_graph()`][crate::Repository::write_commit_graph()].
+    #[derive(Debug, Clone, Copy)]
+    pub struct Options {
+        /// How to write the commit-graph.
+        pub mode: Mode,
+        /// If `true`, compute changed-path Bloom filters for all new commits, similar to `git commit-graph write --changed-paths`.
+        ///
+        /// Filters are also computed if the existing commit-graph has them, even if this is `false`.
+        pub changed_paths: bool,
+    }
+
+    /// The error returned by [`Repository::write_commit_graph()`][crate::Repository::write_commit_graph()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error(transparent)]
+        FindExistingObject(#[from] crate::object::find::existing::Error),
+        #[error("Could not decode commit {id}")]
+        DecodeCommit {
+            source: git_object::decode::Error,
+            id: git_hash::ObjectId,
+        },
+        #[error(transparent)]
+        PeelToTree(#[from] crate::object::peel::to_kind::Error),
+        #[error(transparent)]
+        DiffConfig(#[from] Box<crate::object::tree::diff::rewrites::Error>),
+        #[error("Could not compute the paths changed by a commit")]
+        DiffTree(#[from] crate::object::tree::diff::for_each::Error),
+        #[error(transparent)]
+        ShallowOpen(#[from] crate::shallow::open::Error),
+        #[error(transparent)]
+        Write(#[from] git_commitgraph::graph::write::Error),
+    }
+}
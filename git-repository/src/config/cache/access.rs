@@ -1,5 +1,5 @@
 #![allow(clippy::result_large_err)]
-use std::{borrow::Cow, path::PathBuf, time::Duration};
+use std::{borrow::Cow, convert::TryInto, path::PathBuf, time::Duration};
 
 use git_lock::acquire::Fail;
 
@@ -9,13 +9,15 @@ use crate::{
     config::{
         cache::util::{ApplyLeniency, ApplyLeniencyDefault},
         checkout_options,
-        tree::{Checkout, Core, Key},
+        tree::{Checkout, Core, Gc, Key},
         Cache,
     },
     remote,
     repository::identity,
 };
 
+const DAY_IN_SECONDS: u32 = 24 * 60 * 60;
+
 /// Access
 impl Cache {
     pub(crate) fn diff_algorithm(&self) -> Result<git_diff::blob::Algorithm, config::diff::algorithm::Error> {
@@ -37,6 +39,17 @@ impl Cache {
             .copied()
     }
 
+    /// Returns the rewrite tracking configuration as configured by `diff.renames` and `diff.renameLimit`.
+    pub(crate) fn diff_renames(
+        &self,
+    ) -> Result<Option<crate::object::tree::diff::Rewrites>, crate::object::tree::diff::rewrites::Error> {
+        self.diff_renames
+            .get_or_try_init(|| {
+                crate::object::tree::diff::rewrites::try_from_config(&self.resolved, self.lenient_config)
+            })
+            .copied()
+    }
+
     /// Returns a user agent for use with servers.
     #[cfg(any(feature = "async-network-client", feature = "blocking-network-client"))]
     pub(crate) fn user_agent_tuple(&self) -> (&'static str, Option<Cow<'static, str>>) {
@@ -89,6 +102,40 @@ impl Cache {
         Ok((out[0], out[1]))
     }
 
+    /// Returns (reflog-expire, reflog-expire-unreachable) as seconds since unix epoch before which entries expire,
+    /// or `None` if they never expire.
+    pub(crate) fn reflog_expire(&self) -> Result<(Option<u32>, Option<u32>), config::time::Error> {
+        Ok((
+            self.expiry_date(&Gc::REFLOG_EXPIRE, 90 * DAY_IN_SECONDS)?,
+            self.expiry_date(&Gc::REFLOG_EXPIRE_UNREACHABLE, 30 * DAY_IN_SECONDS)?,
+        ))
+    }
+
+    /// Returns the time as seconds since unix epoch before which unreachable objects expire, or `None` if they never expire.
+    pub(crate) fn prune_expire(&self) -> Result<Option<u32>, config::time::Error> {
+        self.expiry_date(&Gc::PRUNE_EXPIRE, 14 * DAY_IN_SECONDS)
+    }
+
+    /// Returns the date of `key` as seconds since unix epoch, or the date `default_seconds_ago` if it is unset.
+    fn expiry_date(
+        &self,
+        key: &'static config::tree::gc::ExpiryDate,
+        default_seconds_ago: u32,
+    ) -> Result<Option<u32>, config::time::Error> {
+        let now = std::time::SystemTime::now();
+        let now_in_seconds = now
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs().try_into().unwrap_or(u32::MAX))
+            .unwrap_or_default();
+        Ok(self
+            .resolved
+            .string_filter("gc", None, key.name, &mut self.filter_config_section.clone())
+            .map(|value| key.try_into_expiry_date(value, now))
+            .transpose()
+            .with_leniency(self.lenient_config)?
+            .unwrap_or_else(|| Some(now_in_seconds.saturating_sub(default_seconds_ago))))
+    }
+
     /// The path to the user-level excludes file to ignore certain files in the worktree.
     pub(crate) fn excludes_file(&self) -> Option<Result<PathBuf, git_config::path::interpolate::Error>> {
         self.trusted_file_path("core", None, Core::EXCLUDES_FILE.name)?
@@ -96,6 +143,13 @@ impl Cache {
             .into()
     }
 
+    /// The directory configured in `core.hooksPath` to contain hooks instead of the default `hooks` directory.
+    pub(crate) fn hooks_path(&self) -> Option<Result<PathBuf, git_config::path::interpolate::Error>> {
+        self.trusted_file_path("core", None, Core::HOOKS_PATH.name)?
+            .map(|p| p.into_owned())
+            .into()
+    }
+
     /// A helper to obtain a file from trusted configuration at `section_name`, `subsection_name`, and `key`, which is interpolated
     /// if present.
     pub(crate) fn trusted_file_path(
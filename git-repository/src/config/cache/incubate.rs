@@ -12,6 +12,8 @@ pub(crate) struct StageOne {
     pub lossy: Option<bool>,
     pub object_hash: git_hash::Kind,
     pub reflog: Option<git_ref::store::WriteReflog>,
+    /// If `true`, references are stored in reftables as configured by `extensions.refStorage`, instead of files.
+    pub reftable: bool,
 }
 
 /// Initialization
@@ -49,6 +51,19 @@ impl StageOne {
             })
             .transpose()?
             .unwrap_or(git_hash::Kind::Sha1);
+        let reftable = match (repo_format_version == 1)
+            .then(|| config.string("extensions", None, Extensions::REF_STORAGE.name))
+            .flatten()
+        {
+            None => false,
+            Some(name) if name.as_ref() == "files" => false,
+            Some(name) if name.as_ref() == "reftable" => true,
+            Some(name) => {
+                return Err(Error::UnsupportedRefStorage {
+                    name: name.into_owned(),
+                })
+            }
+        };
 
         let extension_worktree = util::config_bool(
             &config,
@@ -76,6 +91,7 @@ impl StageOne {
             lossy,
             object_hash,
             reflog,
+            reftable,
         })
     }
 }
@@ -26,6 +26,7 @@ impl Cache {
             is_bare,
             object_hash,
             reflog: _,
+            reftable: _,
         }: StageOne,
         git_dir: &std::path::Path,
         branch_name: Option<&git_ref::FullNameRef>,
@@ -182,6 +183,7 @@ impl Cache {
             #[cfg(any(feature = "blocking-network-client", feature = "async-network-client"))]
             url_scheme: Default::default(),
             diff_algorithm: Default::default(),
+            diff_renames: Default::default(),
         })
     }
 
@@ -227,6 +229,7 @@ impl Cache {
         self.personas = Default::default();
         self.url_rewrite = Default::default();
         self.diff_algorithm = Default::default();
+        self.diff_renames = Default::default();
         (self.pack_cache_bytes, self.object_cache_bytes) =
             util::parse_object_caches(config, self.lenient_config, self.filter_config_section)?;
         #[cfg(any(feature = "blocking-network-client", feature = "async-network-client"))]
@@ -243,7 +246,6 @@ impl crate::Repository {
     ///
     /// Similar to `reread_values_and_clear_caches_replacing_config()`, but works on the existing instance instead of a passed
     /// in one that it them makes the default.
-    #[cfg(feature = "blocking-network-client")]
     pub(crate) fn reread_values_and_clear_caches(&mut self) -> Result<(), Error> {
         self.config.reread_values_and_clear_caches()?;
         self.apply_changed_values();
@@ -262,6 +264,9 @@ impl crate::Repository {
 
     fn apply_changed_values(&mut self) {
         self.refs.write_reflog = util::reflog_or_default(self.config.reflog, self.work_dir().is_some());
+        if let Some(reftable) = self.reftable.as_mut() {
+            reftable.write_reflog = self.refs.write_reflog;
+        }
     }
 }
 
@@ -60,6 +60,8 @@ pub enum Error {
     ConfigTypedString(#[from] key::GenericErrorWithValue),
     #[error("Cannot handle objects formatted as {:?}", .name)]
     UnsupportedObjectFormat { name: BString },
+    #[error("Cannot handle references stored as {:?}, only the 'files' and 'reftable' backends are supported", .name)]
+    UnsupportedRefStorage { name: BString },
     #[error(transparent)]
     CoreAbbrev(#[from] abbrev::Error),
     #[error("Could not read configuration file")]
@@ -430,6 +432,8 @@ pub(crate) struct Cache {
     pub(crate) url_scheme: OnceCell<crate::remote::url::SchemePermission>,
     /// The algorithm to use when diffing blobs
     pub(crate) diff_algorithm: OnceCell<git_diff::blob::Algorithm>,
+    /// The rewrite tracking to perform when diffing trees, or `None` if it is disabled.
+    pub(crate) diff_renames: OnceCell<Option<crate::object::tree::diff::Rewrites>>,
     /// The amount of bytes to use for a memory backed delta pack cache. If `Some(0)`, no cache is used, if `None`
     /// a standard cache is used which costs near to nothing and always pays for itself.
     pub(crate) pack_cache_bytes: Option<usize>,
@@ -0,0 +1,65 @@
+use crate::{
+    config,
+    config::tree::{keys, Fetch, Key, Section},
+};
+
+impl Fetch {
+    /// The `fetch.negotiationAlgorithm` key.
+    pub const NEGOTIATION_ALGORITHM: NegotiationAlgorithm = NegotiationAlgorithm::new_with_validate(
+        "negotiationAlgorithm",
+        &config::Tree::FETCH,
+        validate::NegotiationAlgorithm,
+    );
+    /// The `fetch.writeCommitGraph` key.
+    pub const WRITE_COMMIT_GRAPH: keys::Boolean = keys::Boolean::new_boolean("writeCommitGraph", &config::Tree::FETCH);
+}
+
+impl Section for Fetch {
+    fn name(&self) -> &str {
+        "fetch"
+    }
+
+    fn keys(&self) -> &[&dyn Key] {
+        &[&Self::NEGOTIATION_ALGORITHM, &Self::WRITE_COMMIT_GRAPH]
+    }
+}
+
+/// The `fetch.negotiationAlgorithm` key.
+pub type NegotiationAlgorithm = keys::Any<validate::NegotiationAlgorithm>;
+
+mod algorithm {
+    #[cfg(any(feature = "blocking-network-client", feature = "async-network-client"))]
+    impl crate::config::tree::sections::fetch::NegotiationAlgorithm {
+        /// Derive the negotiation algorithm identified by `name`, case-sensitively.
+        pub fn try_into_negotiation_algorithm(
+            &'static self,
+            name: std::borrow::Cow<'_, crate::bstr::BStr>,
+        ) -> Result<crate::remote::fetch::negotiate::Algorithm, crate::config::key::GenericErrorWithValue> {
+            use crate::remote::fetch::negotiate::Algorithm;
+            Ok(match name.as_ref().as_ref() {
+                b"noop" => Algorithm::Noop,
+                b"consecutive" | b"default" => Algorithm::Consecutive,
+                b"skipping" => Algorithm::Skipping,
+                _ => {
+                    return Err(crate::config::key::GenericErrorWithValue::from_value(
+                        self,
+                        name.into_owned(),
+                    ))
+                }
+            })
+        }
+    }
+}
+
+mod validate {
+    use crate::{bstr::BStr, config::tree::keys};
+
+    pub struct NegotiationAlgorithm;
+    impl keys::Validate for NegotiationAlgorithm {
+        fn validate(&self, _value: &BStr) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+            #[cfg(any(feature = "blocking-network-client", feature = "async-network-client"))]
+            crate::config::tree::Fetch::NEGOTIATION_ALGORITHM.try_into_negotiation_algorithm(_value.into())?;
+            Ok(())
+        }
+    }
+}
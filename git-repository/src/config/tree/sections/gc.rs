@@ -0,0 +1,100 @@
+use crate::{
+    config,
+    config::tree::{keys, Gc, Key, Section},
+};
+
+impl Gc {
+    /// The `gc.reflogExpire` key.
+    pub const REFLOG_EXPIRE: ExpiryDate =
+        ExpiryDate::new_with_validate("reflogExpire", &config::Tree::GC, validate::ExpiryDate)
+            .with_note("Per-reference configuration in `gc.<pattern>.reflogExpire` is not yet supported");
+    /// The `gc.reflogExpireUnreachable` key.
+    pub const REFLOG_EXPIRE_UNREACHABLE: ExpiryDate =
+        ExpiryDate::new_with_validate("reflogExpireUnreachable", &config::Tree::GC, validate::ExpiryDate)
+            .with_note("Per-reference configuration in `gc.<pattern>.reflogExpireUnreachable` is not yet supported");
+    /// The `gc.pruneExpire` key.
+    pub const PRUNE_EXPIRE: ExpiryDate =
+        ExpiryDate::new_with_validate("pruneExpire", &config::Tree::GC, validate::ExpiryDate);
+    /// The `gc.cruftPacks` key.
+    pub const CRUFT_PACKS: keys::Boolean = keys::Boolean::new_boolean("cruftPacks", &config::Tree::GC);
+}
+
+/// A key for dates before which items expire, like `gc.reflogExpire`.
+pub type ExpiryDate = keys::Any<validate::ExpiryDate>;
+
+mod expiry_date {
+    use std::{borrow::Cow, time::SystemTime};
+
+    use crate::{
+        bstr::{BStr, ByteSlice},
+        config,
+        config::tree::sections::gc::ExpiryDate,
+    };
+
+    impl ExpiryDate {
+        /// Interpret `value` as the time before which items expire, in seconds since unix epoch, with `now` as reference for
+        /// relative dates like `90.days.ago` or `2 weeks`.
+        ///
+        /// Return `None` if items never expire, as indicated by `never` or `false`, or `u32::MAX` if all items expire,
+        /// as indicated by `all` or `now`.
+        pub fn try_into_expiry_date(
+            &'static self,
+            value: Cow<'_, BStr>,
+            now: SystemTime,
+        ) -> Result<Option<u32>, config::time::Error> {
+            let to_err = |err| config::time::Error::from_value(self, value.clone().into_owned()).with_source(err);
+            let input = value.to_str().map_err(|_| {
+                to_err(git_date::parse::Error::InvalidDateString {
+                    input: value.to_string(),
+                })
+            })?;
+            Ok(match input.trim() {
+                "never" | "false" => None,
+                "all" | "now" => Some(u32::MAX),
+                input => {
+                    let relative = input.replace('.', " ");
+                    let relative = relative.trim();
+                    git_date::parse(input, Some(now))
+                        .or_else(|_| git_date::parse(relative, Some(now)))
+                        .or_else(|err| {
+                            if relative.ends_with("ago") {
+                                Err(err)
+                            } else {
+                                git_date::parse(&format!("{relative} ago"), Some(now))
+                            }
+                        })
+                        .map_err(to_err)?
+                        .seconds_since_unix_epoch
+                        .into()
+                }
+            })
+        }
+    }
+}
+
+impl Section for Gc {
+    fn name(&self) -> &str {
+        "gc"
+    }
+
+    fn keys(&self) -> &[&dyn Key] {
+        &[
+            &Self::REFLOG_EXPIRE,
+            &Self::REFLOG_EXPIRE_UNREACHABLE,
+            &Self::PRUNE_EXPIRE,
+            &Self::CRUFT_PACKS,
+        ]
+    }
+}
+
+mod validate {
+    use crate::{bstr::BStr, config::tree::keys};
+
+    pub struct ExpiryDate;
+    impl keys::Validate for ExpiryDate {
+        fn validate(&self, value: &BStr) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+            super::Gc::REFLOG_EXPIRE.try_into_expiry_date(value.into(), std::time::SystemTime::now())?;
+            Ok(())
+        }
+    }
+}
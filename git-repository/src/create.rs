@@ -118,6 +118,11 @@ pub struct Options {
     /// If set, use these filesystem capabilities to populate the respective git-config fields.
     /// If `None`, the directory will be probed.
     pub fs_capabilities: Option<git_worktree::fs::Capabilities>,
+    /// The kind of hash to use for object ids in the new repository, defaulting to `Sha1`.
+    ///
+    /// Anything but `Sha1` causes the repository format version to be raised to `1` along with
+    /// setting the `extensions.objectFormat` configuration.
+    pub object_hash: git_hash::Kind,
 }
 
 /// Create a new `.git` repository of `kind` within the possibly non-existing `directory`
@@ -131,6 +136,7 @@ pub fn into(
     Options {
         fs_capabilities,
         destination_must_be_empty,
+        object_hash,
     }: Options,
 ) -> Result<git_discover::repository::Path, Error> {
     let mut dot_git = directory.into();
@@ -211,7 +217,11 @@ pub fn into(
             let caps = fs_capabilities.unwrap_or_else(|| git_worktree::fs::Capabilities::probe(&dot_git));
             let mut core = config.new_section("core", None).expect("valid section name");
 
-            core.push(key("repositoryformatversion"), Some("0".into()));
+            let repo_format_version = match object_hash {
+                git_hash::Kind::Sha1 => "0",
+                _ => "1",
+            };
+            core.push(key("repositoryformatversion"), Some(repo_format_version.into()));
             core.push(key("filemode"), Some(bool(caps.executable_bit).into()));
             core.push(key("bare"), Some(bool(bare).into()));
             core.push(key("logallrefupdates"), Some(bool(!bare).into()));
@@ -219,6 +229,10 @@ pub fn into(
             core.push(key("ignorecase"), Some(bool(caps.ignore_case).into()));
             core.push(key("precomposeunicode"), Some(bool(caps.precompose_unicode).into()));
         }
+        if object_hash != git_hash::Kind::Sha1 {
+            let mut extensions = config.new_section("extensions", None).expect("valid section name");
+            extensions.push(key("objectformat"), Some(object_format(object_hash).into()));
+        }
         let mut cursor = PathCursor(&mut dot_git);
         let config_path = cursor.at("config");
         std::fs::write(config_path, config.to_bstring()).map_err(|err| Error::IoWrite {
@@ -243,6 +257,13 @@ fn key(name: &'static str) -> section::Key<'static> {
     section::Key::try_from(name).expect("valid key name")
 }
 
+fn object_format(kind: git_hash::Kind) -> &'static str {
+    match kind {
+        git_hash::Kind::Sha1 => "sha1",
+        git_hash::Kind::Sha256 => "sha256",
+    }
+}
+
 fn bool(v: bool) -> &'static str {
     match v {
         true => "true",
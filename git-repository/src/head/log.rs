@@ -9,12 +9,8 @@ use crate::{
 
 impl<'repo> Head<'repo> {
     /// Return a platform for obtaining iterators on the reference log associated with the `HEAD` reference.
-    pub fn log_iter(&self) -> git_ref::file::log::iter::Platform<'static, 'repo> {
-        git_ref::file::log::iter::Platform {
-            store: &self.repo.refs,
-            name: "HEAD".try_into().expect("HEAD is always valid"),
-            buf: Vec::new(),
-        }
+    pub fn log_iter(&self) -> crate::reference::log::iter::Platform<'static, 'repo> {
+        crate::reference::log::iter::Platform::new(self.repo, "HEAD".try_into().expect("HEAD is always valid"))
     }
 
     /// Return a list of all branch names that were previously checked out with the first-ever checked out branch
@@ -188,7 +188,7 @@ mod tests {
     fn size_of_oid() {
         assert_eq!(
             std::mem::size_of::<Id<'_>>(),
-            32,
+            48,
             "size of oid shouldn't change without notice"
         )
     }
@@ -126,6 +126,7 @@ pub use types::{
 ///
 pub mod clone;
 pub mod commit;
+pub mod commitgraph;
 pub mod head;
 pub mod id;
 pub mod object;
@@ -254,6 +255,8 @@ pub mod revision;
 ///
 pub mod remote;
 
+pub mod shallow;
+
 ///
 pub mod init;
 
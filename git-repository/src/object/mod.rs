@@ -148,7 +148,7 @@ impl<'repo> Object<'repo> {
 
     /// Obtain a fully parsed commit whose fields reference our data buffer.
     pub fn try_to_commit_ref(&self) -> Result<git_object::CommitRef<'_>, conversion::Error> {
-        git_object::Data::new(self.kind, &self.data)
+        git_object::Data::new(self.kind, &self.data, self.id.kind())
             .decode()?
             .into_commit()
             .ok_or(conversion::Error::UnexpectedType {
@@ -163,14 +163,14 @@ impl<'repo> Object<'repo> {
     ///
     /// - this object is not a commit
     pub fn to_commit_ref_iter(&self) -> git_object::CommitRefIter<'_> {
-        git_object::Data::new(self.kind, &self.data)
+        git_object::Data::new(self.kind, &self.data, self.id.kind())
             .try_into_commit_iter()
             .expect("BUG: This object must be a commit")
     }
 
     /// Obtain a commit token iterator from the data in this instance, if it is a commit.
     pub fn try_to_commit_ref_iter(&self) -> Option<git_object::CommitRefIter<'_>> {
-        git_object::Data::new(self.kind, &self.data).try_into_commit_iter()
+        git_object::Data::new(self.kind, &self.data, self.id.kind()).try_into_commit_iter()
     }
 
     /// Obtain a tag token iterator from the data in this instance.
@@ -179,7 +179,7 @@ impl<'repo> Object<'repo> {
     ///
     /// - this object is not a tag
     pub fn to_tag_ref_iter(&self) -> git_object::TagRefIter<'_> {
-        git_object::Data::new(self.kind, &self.data)
+        git_object::Data::new(self.kind, &self.data, self.id.kind())
             .try_into_tag_iter()
             .expect("BUG: this object must be a tag")
     }
@@ -190,7 +190,7 @@ impl<'repo> Object<'repo> {
     ///
     /// - this object is not a tag
     pub fn try_to_tag_ref_iter(&self) -> Option<git_object::TagRefIter<'_>> {
-        git_object::Data::new(self.kind, &self.data).try_into_tag_iter()
+        git_object::Data::new(self.kind, &self.data, self.id.kind()).try_into_tag_iter()
     }
 
     /// Obtain a tag object from the data in this instance.
@@ -205,7 +205,7 @@ impl<'repo> Object<'repo> {
 
     /// Obtain a fully parsed tag object whose fields reference our data buffer.
     pub fn try_to_tag_ref(&self) -> Result<git_object::TagRef<'_>, conversion::Error> {
-        git_object::Data::new(self.kind, &self.data)
+        git_object::Data::new(self.kind, &self.data, self.id.kind())
             .decode()?
             .into_tag()
             .ok_or(conversion::Error::UnexpectedType {
@@ -1,10 +1,10 @@
 use git_object::tree::EntryMode;
 
-use crate::Id;
+use crate::{bstr::BStr, Id};
 
 /// An event emitted when finding differences between two trees.
 #[derive(Debug, Clone, Copy)]
-pub enum Event<'old, 'new> {
+pub enum Event<'a, 'old, 'new> {
     /// An entry was added, like the addition of a file or directory.
     Addition {
         /// The mode of the added entry.
@@ -32,11 +32,44 @@ pub enum Event<'old, 'new> {
         /// The object id after the modification.
         id: Id<'new>,
     },
+    /// Entries are considered rewritten if they are not trees and they, according to some understanding of identity, were renamed
+    /// or copied.
+    /// In case of renames, this means they originally appeared as [`Deletion`][Event::Deletion] signalling their source as well as an
+    /// [`Addition`][Event::Addition] acting as destination.
+    ///
+    /// In case of copies, the `copy` flag is true and typically represents a perfect copy of a source was made.
+    ///
+    /// This variant can only be encountered if [rewrite tracking][super::Platform::track_rewrites()] is enabled.
+    ///
+    /// Note that mode changes may have occurred as well, i.e. changes from executable to non-executable or vice-versa.
+    Rewrite {
+        /// The location of the source of the rename operation.
+        ///
+        /// It may be empty if neither [file names][super::Platform::track_filename()] nor [file paths][super::Platform::track_path()]
+        /// are tracked.
+        source_location: &'a BStr,
+        /// The mode of the entry before the rename.
+        source_entry_mode: git_object::tree::EntryMode,
+        /// The object id of the entry before the rename.
+        ///
+        /// Note that this is the same as `id` if we require the [similarity to be 100%][super::Rewrites::percentage], but may
+        /// be different otherwise.
+        source_id: Id<'old>,
+
+        /// The mode of the entry after the rename.
+        /// It could differ but still be considered a rename as we are concerned only about content.
+        entry_mode: git_object::tree::EntryMode,
+        /// The object id after the rename.
+        id: Id<'new>,
+        /// If true, this rewrite is created by copy, and `source_id` is pointing to its source. Otherwise it's a rename, and `source_id`
+        /// points to a deleted object, as renames are tracked as deletions and additions of the same or similar content.
+        copy: bool,
+    },
 }
 
-impl<'old, 'new> Event<'old, 'new> {
+impl<'a, 'old, 'new> Event<'a, 'old, 'new> {
     /// Produce a platform for performing a line-diff, or `None` if this is not a [`Modification`][Event::Modification]
-    /// or one of the entries to compare is not a blob.
+    /// or [`Rewrite`][Event::Rewrite], or one of the entries to compare is not a blob.
     pub fn diff(
         &self,
     ) -> Option<Result<crate::object::blob::diff::Platform<'old, 'new>, crate::object::blob::diff::init::Error>> {
@@ -46,6 +79,13 @@ impl<'old, 'new> Event<'old, 'new> {
                 previous_id,
                 entry_mode: EntryMode::BlobExecutable | EntryMode::Blob,
                 id,
+            }
+            | Event::Rewrite {
+                source_entry_mode: EntryMode::BlobExecutable | EntryMode::Blob,
+                source_id: previous_id,
+                entry_mode: EntryMode::BlobExecutable | EntryMode::Blob,
+                id,
+                ..
             } => Some(crate::object::blob::diff::Platform::from_ids(previous_id, id)),
             _ => None,
         }
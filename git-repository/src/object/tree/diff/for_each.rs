@@ -1,7 +1,7 @@
 use std::collections::VecDeque;
 
 use git_object::TreeRefIter;
-use git_odb::FindExt;
+use git_odb::{Find, FindExt};
 
 use super::{change, Action, Change, Platform, Tracking};
 use crate::{
@@ -18,6 +18,15 @@ pub enum Error {
     Diff(#[from] git_diff::tree::changes::Error),
     #[error("The user-provided callback failed")]
     ForEach(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    #[error(transparent)]
+    Rewrites(#[from] git_diff::tree::rewrites::Error),
+    #[error("Could not find tree to compare with the index")]
+    FindExistingTree(#[from] git_odb::find::existing_object::Error<git_odb::store::find::Error>),
+    #[error("The index entry at '{path}' has an invalid mode: {mode:?}")]
+    InvalidIndexEntryMode {
+        path: BString,
+        mode: git_index::entry::Mode,
+    },
 }
 
 /// Add the item to compare to.
@@ -26,6 +35,9 @@ impl<'a, 'old> Platform<'a, 'old> {
     ///
     /// `other` could also be created with the [`empty_tree()`][crate::Repository::empty_tree()] method to handle the first commit
     /// in a repository - it doesn't have a parent, equivalent to compare 'nothing' to something.
+    ///
+    /// If [rewrite tracking][Platform::track_rewrites()] is enabled, additions and deletions of files are held back until
+    /// all changes are known so they can be turned into [rewrites][change::Event::Rewrite] if possible.
     pub fn for_each_to_obtain_tree<'new, E>(
         &mut self,
         other: &Tree<'new>,
@@ -35,21 +47,36 @@ impl<'a, 'old> Platform<'a, 'old> {
         E: std::error::Error + Sync + Send + 'static,
     {
         let repo = self.lhs.repo;
+        let other_repo = other.repo;
         let mut delegate = Delegate {
-            repo: self.lhs.repo,
-            other_repo: other.repo,
+            repo,
+            other_repo,
             tracking: self.tracking,
             location: BString::default(),
             path_deque: Default::default(),
             visit: for_each,
+            tracked: None,
             err: None,
         };
-        match git_diff::tree::Changes::from(TreeRefIter::from_bytes(&self.lhs.data)).needed_to_obtain(
-            TreeRefIter::from_bytes(&other.data),
-            &mut self.state,
-            |oid, buf| repo.objects.find_tree_iter(oid, buf),
-            &mut delegate,
-        ) {
+        let changes = git_diff::tree::Changes::from(TreeRefIter::from_bytes(&self.lhs.data, self.lhs.id.kind()));
+        let other = TreeRefIter::from_bytes(&other.data, other.id.kind());
+        let res = match self.rewrites {
+            Some(rewrites) => changes.needed_to_obtain_with_rewrites(
+                other,
+                &mut self.state,
+                |oid, buf| repo.objects.find_tree_iter(oid, buf),
+                |oid, buf| find_blob(repo, other_repo, oid, buf),
+                rewrites,
+                &mut delegate,
+            ),
+            None => changes.needed_to_obtain(
+                other,
+                &mut self.state,
+                |oid, buf| repo.objects.find_tree_iter(oid, buf),
+                &mut delegate,
+            ),
+        };
+        match res {
             Ok(()) => match delegate.err {
                 Some(err) => Err(Error::ForEach(Box::new(err))),
                 None => Ok(()),
@@ -63,6 +90,371 @@ impl<'a, 'old> Platform<'a, 'old> {
     }
 }
 
+/// Compare to the index.
+impl<'a, 'old> Platform<'a, 'old> {
+    /// Call `for_each` repeatedly with all changes that are needed to convert the source of the diff to the tree represented
+    /// by `index`, which are the staged changes if the source is the tree of `HEAD`.
+    ///
+    /// Only changes to blobs, symbolic links and submodules are emitted as trees aren't stored in the index.
+    /// Subtrees which are unchanged according to the [tree extension][git_index::State::tree()] of the index aren't traversed at all.
+    /// Conflicting entries are skipped entirely, as are entries which are [only intended to be added][git_index::entry::Flags::INTENT_TO_ADD].
+    ///
+    /// If [rewrite tracking][Platform::track_rewrites()] is enabled, additions and deletions of files are held back until
+    /// all changes are known so they can be turned into [rewrites][change::Event::Rewrite] if possible.
+    pub fn for_each_to_obtain_index<E>(
+        &mut self,
+        index: &git_index::State,
+        for_each: impl FnMut(Change<'_, 'old, 'old>) -> Result<Action, E>,
+    ) -> Result<(), Error>
+    where
+        E: std::error::Error + Sync + Send + 'static,
+    {
+        let repo = self.lhs.repo;
+        let mut delegate = Delegate {
+            repo,
+            other_repo: repo,
+            tracking: self.tracking,
+            location: BString::default(),
+            path_deque: Default::default(),
+            visit: for_each,
+            tracked: self.rewrites.map(git_diff::tree::rewrites::Tracker::new),
+            err: None,
+        };
+        let action = index::changes(
+            &mut delegate,
+            index,
+            Some(self.lhs.id),
+            index.entries(),
+            0,
+            index.tree(),
+        )?;
+        let action = match delegate.tracked.take() {
+            Some(mut tracker) if action == git_diff::tree::visit::Action::Continue && delegate.err.is_none() => tracker
+                .emit(
+                    |dest| delegate.emit_rewrite(dest),
+                    |oid, buf| find_blob(repo, repo, oid, buf),
+                    TreeRefIter::from_bytes(&self.lhs.data, self.lhs.id.kind()),
+                    |oid, buf| repo.objects.find_tree_iter(oid, buf),
+                )?,
+            _ => action,
+        };
+        match delegate.err {
+            Some(err) => Err(Error::ForEach(Box::new(err))),
+            None if action == git_diff::tree::visit::Action::Cancel => {
+                Err(Error::Diff(git_diff::tree::changes::Error::Cancelled))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+mod index {
+    use std::cmp::Ordering;
+
+    use git_diff::tree::{
+        visit::{Action, Change},
+        Visit,
+    };
+    use git_index::{entry, extension, Entry};
+    use git_object::tree::EntryMode;
+    use git_odb::FindExt;
+
+    use super::{Delegate, Error};
+    use crate::{
+        bstr::{BStr, ByteSlice},
+        object::tree::diff,
+    };
+
+    /// An item of the index at a certain tree level.
+    enum Item<'a> {
+        /// A file, symbolic link, submodule or sparse directory, along with all of its stages.
+        Entry { name: &'a BStr, stages: &'a [Entry] },
+        /// A directory with all entries it contains.
+        Dir { name: &'a BStr, entries: &'a [Entry] },
+    }
+
+    impl<'a> Item<'a> {
+        fn name(&self) -> &'a BStr {
+            match self {
+                Item::Entry { name, .. } | Item::Dir { name, .. } => name,
+            }
+        }
+
+        fn is_dir(&self) -> bool {
+            match self {
+                Item::Entry { stages, .. } => stages[0].mode.is_sparse(),
+                Item::Dir { .. } => true,
+            }
+        }
+    }
+
+    /// Emit all changes needed to turn the tree with `tree_id` into the one represented by `entries`, which all share a directory
+    /// prefix of `prefix_len` bytes. `cached` is the tree extension matching this directory, if present.
+    ///
+    /// If `tree_id` is `None`, all entries are additions.
+    pub(super) fn changes<'old, VisitFn, E>(
+        delegate: &mut Delegate<'old, 'old, VisitFn, E>,
+        index: &git_index::State,
+        tree_id: Option<git_hash::ObjectId>,
+        entries: &[Entry],
+        prefix_len: usize,
+        cached: Option<&extension::Tree>,
+    ) -> Result<Action, Error>
+    where
+        VisitFn: for<'delegate> FnMut(diff::Change<'delegate, 'old, 'old>) -> Result<diff::Action, E>,
+        E: std::error::Error + Sync + Send + 'static,
+    {
+        if let (Some(tree_id), Some(cached)) = (tree_id, cached) {
+            if cached.id == tree_id && cached.num_entries == Some(entries.len() as u32) {
+                return Ok(Action::Continue);
+            }
+        }
+        let tree_entries: Vec<_> = match tree_id {
+            Some(tree_id) => {
+                let mut buf = Vec::new();
+                delegate
+                    .repo
+                    .objects
+                    .find_tree(tree_id, &mut buf)?
+                    .entries
+                    .into_iter()
+                    .map(|entry| (entry.mode, entry.filename.to_owned(), entry.oid.to_owned()))
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+        let items = items(index, entries, prefix_len);
+
+        let (mut tree_entries, mut items) = (tree_entries.iter().peekable(), items.iter().peekable());
+        loop {
+            let order = match (tree_entries.peek(), items.peek()) {
+                (None, None) => break,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((mode, name, _)), Some(item)) => {
+                    compare_names(name.as_ref(), mode.is_tree(), item.name(), item.is_dir())
+                }
+            };
+            let action = match order {
+                Ordering::Less => {
+                    let (mode, name, id) = tree_entries.next().expect("peeked");
+                    deletion(delegate, index, name.as_ref(), *mode, *id)?
+                }
+                Ordering::Greater => {
+                    let item = items.next().expect("peeked");
+                    addition(delegate, index, item, prefix_len, cached)?
+                }
+                Ordering::Equal => {
+                    let (mode, name, id) = tree_entries.next().expect("peeked");
+                    let item = items.next().expect("peeked");
+                    match item {
+                        Item::Dir { entries, .. } => {
+                            delegate.push_path_component(name.as_ref());
+                            let action = changes(
+                                delegate,
+                                index,
+                                Some(*id),
+                                entries,
+                                prefix_len + name.len() + 1,
+                                child(cached, name.as_ref()),
+                            )?;
+                            delegate.pop_path_component();
+                            action
+                        }
+                        Item::Entry { stages, .. } => match entry_mode(index, stages)? {
+                            Some(entry_mode) if entry_mode != *mode || stages[0].id != *id => emit(
+                                delegate,
+                                name.as_ref(),
+                                Change::Modification {
+                                    previous_entry_mode: *mode,
+                                    previous_oid: *id,
+                                    entry_mode,
+                                    oid: stages[0].id,
+                                },
+                            ),
+                            Some(_) | None => Action::Continue,
+                        },
+                    }
+                }
+            };
+            if action == Action::Cancel {
+                return Ok(action);
+            }
+        }
+        Ok(Action::Continue)
+    }
+
+    fn deletion<'old, VisitFn, E>(
+        delegate: &mut Delegate<'old, 'old, VisitFn, E>,
+        index: &git_index::State,
+        name: &BStr,
+        mode: EntryMode,
+        id: git_hash::ObjectId,
+    ) -> Result<Action, Error>
+    where
+        VisitFn: for<'delegate> FnMut(diff::Change<'delegate, 'old, 'old>) -> Result<diff::Action, E>,
+        E: std::error::Error + Sync + Send + 'static,
+    {
+        if mode.is_tree() {
+            delegate.push_path_component(name);
+            let action = changes(delegate, index, Some(id), &[], 0, None)?;
+            delegate.pop_path_component();
+            Ok(action)
+        } else {
+            Ok(emit(
+                delegate,
+                name,
+                Change::Deletion {
+                    entry_mode: mode,
+                    oid: id,
+                },
+            ))
+        }
+    }
+
+    fn addition<'old, VisitFn, E>(
+        delegate: &mut Delegate<'old, 'old, VisitFn, E>,
+        index: &git_index::State,
+        item: &Item<'_>,
+        prefix_len: usize,
+        cached: Option<&extension::Tree>,
+    ) -> Result<Action, Error>
+    where
+        VisitFn: for<'delegate> FnMut(diff::Change<'delegate, 'old, 'old>) -> Result<diff::Action, E>,
+        E: std::error::Error + Sync + Send + 'static,
+    {
+        Ok(match item {
+            Item::Dir { name, entries } => {
+                delegate.push_path_component(name);
+                let action = changes(
+                    delegate,
+                    index,
+                    None,
+                    entries,
+                    prefix_len + name.len() + 1,
+                    child(cached, name),
+                )?;
+                delegate.pop_path_component();
+                action
+            }
+            Item::Entry { name, stages } => match entry_mode(index, stages)? {
+                Some(entry_mode) => emit(
+                    delegate,
+                    name,
+                    Change::Addition {
+                        entry_mode,
+                        oid: stages[0].id,
+                    },
+                ),
+                None => Action::Continue,
+            },
+        })
+    }
+
+    fn emit<'old, VisitFn, E>(delegate: &mut Delegate<'old, 'old, VisitFn, E>, name: &BStr, change: Change) -> Action
+    where
+        VisitFn: for<'delegate> FnMut(diff::Change<'delegate, 'old, 'old>) -> Result<diff::Action, E>,
+        E: std::error::Error + Sync + Send + 'static,
+    {
+        delegate.push_path_component(name);
+        let action = delegate.visit(change);
+        delegate.pop_path_component();
+        action
+    }
+
+    /// Return the tree entry mode of the entry with the given `stages`, or `None` if it is conflicting.
+    fn entry_mode(index: &git_index::State, stages: &[Entry]) -> Result<Option<EntryMode>, Error> {
+        let entry = &stages[0];
+        if entry.stage() != 0 {
+            return Ok(None);
+        }
+        entry
+            .mode
+            .to_tree_entry_mode()
+            .map(Some)
+            .ok_or_else(|| Error::InvalidIndexEntryMode {
+                path: entry.path(index).to_owned(),
+                mode: entry.mode,
+            })
+    }
+
+    fn child<'a>(cached: Option<&'a extension::Tree>, name: &BStr) -> Option<&'a extension::Tree> {
+        cached.and_then(|cached| {
+            cached
+                .children
+                .iter()
+                .find(|tree| tree.name.as_slice() == name.as_bytes())
+        })
+    }
+
+    /// Compare names like `git` does, where directories sort as if they had a trailing slash.
+    fn compare_names(lhs: &BStr, lhs_is_dir: bool, rhs: &BStr, rhs_is_dir: bool) -> Ordering {
+        let common = lhs.len().min(rhs.len());
+        lhs[..common].cmp(&rhs[..common]).then_with(|| {
+            let lhs = lhs.get(common).copied().or(lhs_is_dir.then_some(b'/'));
+            let rhs = rhs.get(common).copied().or(rhs_is_dir.then_some(b'/'));
+            lhs.cmp(&rhs)
+        })
+    }
+
+    /// Group `entries`, which share a directory prefix of `prefix_len` bytes, into the items of their tree level.
+    /// Entries which are only intended to be added are skipped.
+    fn items<'a>(index: &'a git_index::State, entries: &'a [Entry], prefix_len: usize) -> Vec<Item<'a>> {
+        let mut items = Vec::new();
+        let mut idx = 0;
+        while idx < entries.len() {
+            let entry = &entries[idx];
+            let path = entry.path(index);
+            let rest = &path[prefix_len..];
+            let end = match rest
+                .find_byte(b'/')
+                .filter(|pos| !(entry.mode.is_sparse() && *pos + 1 == rest.len()))
+            {
+                Some(pos) => {
+                    let dir_prefix = &path[..prefix_len + pos + 1];
+                    let end = idx
+                        + entries[idx..]
+                            .iter()
+                            .position(|entry| !entry.path(index).starts_with(dir_prefix))
+                            .unwrap_or(entries.len() - idx);
+                    items.push(Item::Dir {
+                        name: rest[..pos].as_bstr(),
+                        entries: &entries[idx..end],
+                    });
+                    end
+                }
+                None => {
+                    let end = idx
+                        + entries[idx..]
+                            .iter()
+                            .position(|other| other.path(index) != path)
+                            .unwrap_or(entries.len() - idx);
+                    if !entry.flags.contains(entry::Flags::INTENT_TO_ADD) {
+                        items.push(Item::Entry {
+                            name: rest.trim_end_with(|c| c == '/').as_bstr(),
+                            stages: &entries[idx..end],
+                        });
+                    }
+                    end
+                }
+            };
+            idx = end;
+        }
+        items
+    }
+}
+
+/// Find the blob with `id` in `repo`, or in `other_repo` if it isn't present there as it's the destination of a change.
+fn find_blob<'b>(
+    repo: &Repository,
+    other_repo: &Repository,
+    id: &git_hash::oid,
+    buf: &'b mut Vec<u8>,
+) -> Result<git_object::BlobRef<'b>, git_odb::find::existing_object::Error<git_odb::store::find::Error>> {
+    let repo = if repo.objects.contains(id) { repo } else { other_repo };
+    repo.objects.find_blob(id, buf)
+}
+
 struct Delegate<'old, 'new, VisitFn, E> {
     repo: &'old Repository,
     other_repo: &'new Repository,
@@ -70,9 +462,115 @@ struct Delegate<'old, 'new, VisitFn, E> {
     location: BString,
     path_deque: VecDeque<BString>,
     visit: VisitFn,
+    /// The rewrite tracker used when comparing to the index, as it doesn't use [`git_diff::tree::Changes`].
+    tracked: Option<git_diff::tree::rewrites::Tracker>,
     err: Option<E>,
 }
 
+impl<'old, 'new, VisitFn, E> Delegate<'old, 'new, VisitFn, E>
+where
+    VisitFn: for<'delegate> FnMut(Change<'delegate, 'old, 'new>) -> Result<Action, E>,
+    E: std::error::Error + Sync + Send + 'static,
+{
+    /// Call `visit` on an attached version of `change`.
+    fn emit_change(
+        change: git_diff::tree::visit::Change,
+        location: &BStr,
+        visit: &mut VisitFn,
+        repo: &'old Repository,
+        other_repo: &'new Repository,
+        stored_err: &mut Option<E>,
+    ) -> git_diff::tree::visit::Action {
+        use git_diff::tree::visit::Change::*;
+        let event = match change {
+            Addition { entry_mode, oid } => change::Event::Addition {
+                entry_mode,
+                id: oid.attach(other_repo),
+            },
+            Deletion { entry_mode, oid } => change::Event::Deletion {
+                entry_mode,
+                id: oid.attach(repo),
+            },
+            Modification {
+                previous_entry_mode,
+                previous_oid,
+                entry_mode,
+                oid,
+            } => change::Event::Modification {
+                previous_entry_mode,
+                entry_mode,
+                previous_id: previous_oid.attach(repo),
+                id: oid.attach(other_repo),
+            },
+        };
+        match visit(Change { event, location }) {
+            Ok(Action::Cancel) => git_diff::tree::visit::Action::Cancel,
+            Ok(Action::Continue) => git_diff::tree::visit::Action::Continue,
+            Err(err) => {
+                *stored_err = Some(err);
+                git_diff::tree::visit::Action::Cancel
+            }
+        }
+    }
+
+    /// Emit a held-back addition or deletion, which is a rewrite if it has a source.
+    ///
+    /// Locations are always full paths, so they are shortened according to our path tracking.
+    fn emit_rewrite(&mut self, dest: git_diff::tree::rewrites::Destination<'_>) -> git_diff::tree::visit::Action {
+        let git_diff::tree::rewrites::Destination {
+            change,
+            location,
+            source,
+        } = dest;
+        let location = tracked_location(self.tracking, location);
+        let source = match source {
+            Some(source) => source,
+            None => {
+                return Self::emit_change(
+                    change,
+                    location,
+                    &mut self.visit,
+                    self.repo,
+                    self.other_repo,
+                    &mut self.err,
+                )
+            }
+        };
+        let (entry_mode, oid) = match change {
+            git_diff::tree::visit::Change::Addition { entry_mode, oid }
+            | git_diff::tree::visit::Change::Deletion { entry_mode, oid } => (entry_mode, oid),
+            git_diff::tree::visit::Change::Modification { entry_mode, oid, .. } => (entry_mode, oid),
+        };
+        let event = change::Event::Rewrite {
+            source_location: tracked_location(self.tracking, source.location),
+            source_entry_mode: source.mode,
+            source_id: source.id.attach(self.repo),
+            entry_mode,
+            id: oid.attach(self.other_repo),
+            copy: source.copy,
+        };
+        match (self.visit)(Change { event, location }) {
+            Ok(Action::Cancel) => git_diff::tree::visit::Action::Cancel,
+            Ok(Action::Continue) => git_diff::tree::visit::Action::Continue,
+            Err(err) => {
+                self.err = Some(err);
+                git_diff::tree::visit::Action::Cancel
+            }
+        }
+    }
+}
+
+/// Return the portion of the full path `location` that is visible with the given `tracking`.
+fn tracked_location(tracking: Option<Tracking>, location: &BStr) -> &BStr {
+    match tracking {
+        Some(Tracking::Path) => location,
+        Some(Tracking::FileName) => location
+            .rfind_byte(b'/')
+            .map_or(location, |pos| location[pos + 1..].as_bstr()),
+        None => "".into(),
+    }
+}
+
 impl<A, B> Delegate<'_, '_, A, B> {
     fn pop_element(&mut self) {
         if let Some(pos) = self.location.rfind_byte(b'/') {
@@ -131,38 +629,41 @@ where
     }
 
     fn visit(&mut self, change: git_diff::tree::visit::Change) -> git_diff::tree::visit::Action {
-        use git_diff::tree::visit::Change::*;
-        let event = match change {
-            Addition { entry_mode, oid } => change::Event::Addition {
-                entry_mode,
-                id: oid.attach(self.other_repo),
-            },
-            Deletion { entry_mode, oid } => change::Event::Deletion {
-                entry_mode,
-                id: oid.attach(self.repo),
-            },
-            Modification {
-                previous_entry_mode,
-                previous_oid,
-                entry_mode,
-                oid,
-            } => change::Event::Modification {
-                previous_entry_mode,
-                entry_mode,
-                previous_id: previous_oid.attach(self.repo),
-                id: oid.attach(self.other_repo),
-            },
-        };
-        match (self.visit)(Change {
-            event,
-            location: self.location.as_ref(),
-        }) {
-            Ok(Action::Cancel) => git_diff::tree::visit::Action::Cancel,
-            Ok(Action::Continue) => git_diff::tree::visit::Action::Continue,
-            Err(err) => {
-                self.err = Some(err);
-                git_diff::tree::visit::Action::Cancel
-            }
+        match self.tracked.as_mut() {
+            Some(tracked) => tracked
+                .try_push_change(change, self.location.as_ref())
+                .map(|change| {
+                    Self::emit_change(
+                        change,
+                        self.location.as_ref(),
+                        &mut self.visit,
+                        self.repo,
+                        self.other_repo,
+                        &mut self.err,
+                    )
+                })
+                .unwrap_or(git_diff::tree::visit::Action::Continue),
+            None => Self::emit_change(
+                change,
+                self.location.as_ref(),
+                &mut self.visit,
+                self.repo,
+                self.other_repo,
+                &mut self.err,
+            ),
         }
     }
 }
+
+impl<'old, 'new, VisitFn, E> git_diff::tree::rewrites::Visit for Delegate<'old, 'new, VisitFn, E>
+where
+    VisitFn: for<'delegate> FnMut(Change<'delegate, 'old, 'new>) -> Result<Action, E>,
+    E: std::error::Error + Sync + Send + 'static,
+{
+    fn visit_tracked(
+        &mut self,
+        destination: git_diff::tree::rewrites::Destination<'_>,
+    ) -> git_diff::tree::visit::Action {
+        self.emit_rewrite(destination)
+    }
+}
@@ -23,7 +23,7 @@ pub struct Change<'a, 'old, 'new> {
     /// Otherwise this value is always an empty path.
     pub location: &'a BStr,
     /// The diff event itself to provide information about what would need to change.
-    pub event: change::Event<'old, 'new>,
+    pub event: change::Event<'a, 'old, 'new>,
 }
 
 ///
@@ -33,15 +33,19 @@ pub mod change;
 impl<'repo> Tree<'repo> {
     /// Return a platform to see the changes needed to create other trees, for instance.
     ///
+    /// Rename tracking is configured according to `diff.renames` and `diff.renameLimit`, and can be adjusted
+    /// with [`Platform::track_rewrites()`].
+    ///
     /// # Performance
     ///
     /// It's highly recommended to set an object cache to avoid extracting the same object multiple times.
-    pub fn changes<'a>(&'a self) -> Platform<'a, 'repo> {
-        Platform {
+    pub fn changes<'a>(&'a self) -> Result<Platform<'a, 'repo>, rewrites::Error> {
+        Ok(Platform {
             state: Default::default(),
             lhs: self,
             tracking: None,
-        }
+            rewrites: self.repo.config.diff_renames()?,
+        })
     }
 }
 
@@ -51,8 +55,15 @@ pub struct Platform<'a, 'repo> {
     state: git_diff::tree::State,
     lhs: &'a Tree<'repo>,
     tracking: Option<Tracking>,
+    rewrites: Option<Rewrites>,
 }
 
+#[doc(inline)]
+pub use git_diff::tree::rewrites::Rewrites;
+
+///
+pub mod rewrites;
+
 #[derive(Clone, Copy)]
 enum Tracking {
     FileName,
@@ -74,6 +85,18 @@ impl<'a, 'repo> Platform<'a, 'repo> {
         self.tracking = Some(Tracking::Path);
         self
     }
+
+    /// Provide `None` to disable rewrite tracking entirely, or pass `Some(<configuration>)` to control to
+    /// what extend rename and copy tracking is performed.
+    ///
+    /// Note that by default, the git configuration determines rewrite tracking and git defaults are used
+    /// if nothing is configured, which turns rename tracking with 50% similarity on, while not tracking copies at all.
+    ///
+    /// Rewrite tracking works best with [path tracking][Platform::track_path()] as source locations are otherwise unknown.
+    pub fn track_rewrites(&mut self, renames: Option<Rewrites>) -> &mut Self {
+        self.rewrites = renames;
+        self
+    }
 }
 
 ///
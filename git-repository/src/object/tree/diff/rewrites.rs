@@ -0,0 +1,64 @@
+use crate::{
+    config::{cache::util::ApplyLeniency, tree::Diff},
+    object::tree::diff::Rewrites,
+};
+
+pub use git_diff::tree::rewrites::{Copies, CopySource};
+
+/// The kind of rename tracking to perform, as configured by `diff.renames`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Tracking {
+    /// Do not track renames at all.
+    Disabled,
+    /// Track renames.
+    Renames,
+    /// Track renames and copies.
+    ///
+    /// This is the most expensive option.
+    RenamesAndCopies,
+}
+
+/// The error returned by [`try_from_config()`].
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error(transparent)]
+    ConfigDiffRenames(#[from] crate::config::key::GenericErrorWithValue),
+    #[error(transparent)]
+    ConfigDiffRenameLimit(#[from] crate::config::unsigned_integer::Error),
+}
+
+/// Create an instance by reading all relevant information from the `config`uration, while being `lenient` or not.
+/// Returns `Ok(None)` if rename tracking is disabled.
+///
+/// Note that missing values will be defaulted similar to what git does.
+#[allow(clippy::result_large_err)]
+pub fn try_from_config(config: &git_config::File<'static>, lenient: bool) -> Result<Option<Rewrites>, Error> {
+    let key = "diff.renames";
+    let copies = match config
+        .boolean_by_key(key)
+        .map(|value| Diff::RENAMES.try_into_renames(value, || config.string_by_key(key)))
+        .transpose()
+        .with_leniency(lenient)?
+    {
+        Some(renames) => match renames {
+            Tracking::Disabled => return Ok(None),
+            Tracking::Renames => None,
+            Tracking::RenamesAndCopies => Some(Copies::default()),
+        },
+        None => None,
+    };
+
+    let default = Rewrites::default();
+    Ok(Rewrites {
+        copies,
+        limit: config
+            .integer_by_key("diff.renameLimit")
+            .map(|value| Diff::RENAME_LIMIT.try_into_usize(value))
+            .transpose()
+            .with_leniency(lenient)?
+            .unwrap_or(default.limit),
+        ..default
+    }
+    .into())
+}
@@ -178,7 +178,15 @@ impl ThreadSafeRepository {
                 None => crate::RefStore::at(&git_dir, reflog, object_hash),
             }
         };
-        let head = refs.find("HEAD").ok();
+        // Linked worktrees don't have their own stack yet, so all of their references are kept in the common stack.
+        let mut reftable = repo_config.reftable.then(|| {
+            let reflog = repo_config.reflog.unwrap_or(git_ref::store::WriteReflog::Disable);
+            git_ref::reftable::Store::at(common_dir_ref, reflog, repo_config.object_hash)
+        });
+        let head = match &reftable {
+            Some(reftable) => reftable.find("HEAD").ok(),
+            None => refs.find("HEAD").ok(),
+        };
         let git_install_dir = crate::path::install_dir().ok();
         let home = std::env::var_os("HOME")
             .map(PathBuf::from)
@@ -231,13 +239,27 @@ impl ThreadSafeRepository {
         }
 
         refs.write_reflog = config::cache::util::reflog_or_default(config.reflog, worktree_dir.is_some());
+        if let Some(reftable) = reftable.as_mut() {
+            reftable.write_reflog = refs.write_reflog;
+        }
         let replacements = replacement_objects_refs_prefix(&config.resolved, lenient_config, filter_config_section)?
             .and_then(|prefix| {
-                let platform = refs.iter().ok()?;
-                let iter = platform.prefixed(&prefix).ok()?;
+                let references: Vec<_> = match &reftable {
+                    Some(reftable) => reftable
+                        .iter_prefixed(git_path::into_bstr(&prefix).as_ref())
+                        .ok()?
+                        .collect(),
+                    None => refs
+                        .iter()
+                        .ok()?
+                        .prefixed(&prefix)
+                        .ok()?
+                        .filter_map(Result::ok)
+                        .collect(),
+                };
                 let prefix = prefix.to_str()?;
-                let replacements = iter
-                    .filter_map(Result::ok)
+                let replacements = references
+                    .into_iter()
                     .filter_map(|r: git_ref::Reference| {
                         let target = r.target.try_id()?.to_owned();
                         let source =
@@ -262,6 +284,7 @@ impl ThreadSafeRepository {
             )?),
             common_dir,
             refs,
+            reftable,
             work_tree: worktree_dir,
             config,
             // used when spawning new repositories off this one when following worktrees
@@ -12,11 +12,52 @@ pub mod edit {
         #[error(transparent)]
         FileTransactionCommit(#[from] git_ref::file::transaction::commit::Error),
         #[error(transparent)]
+        ReftableTransactionPrepare(#[from] git_ref::reftable::transaction::prepare::Error),
+        #[error(transparent)]
+        ReftableTransactionCommit(#[from] git_ref::reftable::transaction::commit::Error),
+        #[error(transparent)]
         NameValidation(#[from] git_validate::reference::name::Error),
         #[error("Could not interpret core.filesRefLockTimeout or core.packedRefsTimeout, it must be the number in milliseconds to wait for locks or negative to wait forever")]
         LockTimeoutConfiguration(#[from] config::lock_timeout::Error),
         #[error(transparent)]
         ParseCommitterTime(#[from] crate::config::time::Error),
+        #[error("Reference updates are forbidden inside the quarantine environment")]
+        Quarantine,
+        #[error("Could not interpolate the path configured in core.hooksPath")]
+        HooksPath(#[from] git_config::path::interpolate::Error),
+        #[error("The reference-transaction hook at {path:?} could not be run")]
+        RunHook {
+            path: std::path::PathBuf,
+            source: std::io::Error,
+        },
+        #[error("Reference updates were aborted by the reference-transaction hook, which exited with {status}")]
+        AbortedByHook { status: std::process::ExitStatus },
+    }
+}
+
+///
+pub mod rename {
+    /// The error returned by [Repository::rename_reference(…)][crate::Repository::rename_reference()] and
+    /// [Repository::copy_reference(…)][crate::Repository::copy_reference()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error(transparent)]
+        NameValidation(#[from] git_validate::reference::name::Error),
+        #[error("Could not obtain the name of the reference HEAD points to")]
+        FindHead(#[from] crate::reference::find::existing::Error),
+        #[error(transparent)]
+        Edit(#[from] crate::reference::edit::Error),
+        #[error("Failed to load repo-local git configuration before writing")]
+        LoadConfig(#[from] git_config::file::init::from_paths::Error),
+        #[error("Could not lock the repository configuration for writing")]
+        LockConfig(#[from] git_lock::acquire::Error),
+        #[error("Could not create the configuration section of the renamed branch")]
+        ConfigSection(#[from] git_config::parse::section::header::Error),
+        #[error("Could not rename the configuration section of the renamed branch")]
+        RenameConfigSection(#[from] git_config::file::rename_section::Error),
+        #[error("Failed to write repository configuration to disk")]
+        SaveConfig(#[from] std::io::Error),
     }
 }
 
@@ -29,6 +70,8 @@ pub mod peel {
     pub enum Error {
         #[error(transparent)]
         ToId(#[from] git_ref::peel::to_id::Error),
+        #[error("Could not follow a single level of a symbolic reference")]
+        FollowReftable(#[from] git_ref::reftable::find::existing::Error),
         #[error(transparent)]
         PackedRefsOpen(#[from] git_ref::packed::buffer::open::Error),
     }
@@ -84,6 +127,8 @@ pub mod find {
         #[error(transparent)]
         Find(#[from] git_ref::file::find::Error),
         #[error(transparent)]
+        FindReftable(#[from] git_ref::reftable::find::Error),
+        #[error(transparent)]
         PackedRefsOpen(#[from] git_ref::packed::buffer::open::Error),
     }
 }
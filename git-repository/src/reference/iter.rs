@@ -13,15 +13,22 @@ pub struct Platform<'r> {
 
 /// An iterator over references, with or without filter.
 pub struct Iter<'r> {
-    inner: git_ref::file::iter::LooseThenPacked<'r, 'r>,
+    inner: Inner<'r>,
     peel: bool,
     repo: &'r crate::Repository,
 }
 
+/// The iterator of the store references are kept in.
+#[allow(clippy::large_enum_variant)]
+enum Inner<'r> {
+    Files(git_ref::file::iter::LooseThenPacked<'r, 'r>),
+    Reftable(git_ref::reftable::Iter),
+}
+
 impl<'r> Iter<'r> {
-    fn new(repo: &'r crate::Repository, platform: git_ref::file::iter::LooseThenPacked<'r, 'r>) -> Self {
+    fn new(repo: &'r crate::Repository, inner: Inner<'r>) -> Self {
         Iter {
-            inner: platform,
+            inner,
             peel: false,
             repo,
         }
@@ -34,7 +41,11 @@ impl<'r> Platform<'r> {
     /// Even broken or otherwise unparsible or inaccessible references are returned and have to be handled by the caller on a
     /// case by case basis.
     pub fn all(&self) -> Result<Iter<'_>, init::Error> {
-        Ok(Iter::new(self.repo, self.platform.all()?))
+        let inner = match &self.repo.reftable {
+            Some(reftable) => Inner::Reftable(reftable.iter_prefixed("refs/")?),
+            None => Inner::Files(self.platform.all()?),
+        };
+        Ok(Iter::new(self.repo, inner))
     }
 
     /// Return an iterator over all references that match the given `prefix`.
@@ -43,7 +54,11 @@ impl<'r> Platform<'r> {
     // TODO: Create a custom `Path` type that enforces the requirements of git naturally, this type is surprising possibly on windows
     //       and when not using a trailing '/' to signal directories.
     pub fn prefixed(&self, prefix: impl AsRef<Path>) -> Result<Iter<'_>, init::Error> {
-        Ok(Iter::new(self.repo, self.platform.prefixed(prefix)?))
+        let inner = match &self.repo.reftable {
+            Some(reftable) => Inner::Reftable(reftable.iter_prefixed(git_path::into_bstr(prefix.as_ref()).as_ref())?),
+            None => Inner::Files(self.platform.prefixed(prefix)?),
+        };
+        Ok(Iter::new(self.repo, inner))
     }
 
     // TODO: tests
@@ -51,7 +66,7 @@ impl<'r> Platform<'r> {
     ///
     /// They are all prefixed with `refs/tags`.
     pub fn tags(&self) -> Result<Iter<'_>, init::Error> {
-        Ok(Iter::new(self.repo, self.platform.prefixed("refs/tags/")?))
+        self.prefixed("refs/tags/")
     }
 
     // TODO: tests
@@ -59,7 +74,7 @@ impl<'r> Platform<'r> {
     ///
     /// They are all prefixed with `refs/heads`.
     pub fn local_branches(&self) -> Result<Iter<'_>, init::Error> {
-        Ok(Iter::new(self.repo, self.platform.prefixed("refs/heads/")?))
+        self.prefixed("refs/heads/")
     }
 
     // TODO: tests
@@ -67,7 +82,7 @@ impl<'r> Platform<'r> {
     ///
     /// They are all prefixed with `refs/remotes`.
     pub fn remote_branches(&self) -> Result<Iter<'_>, init::Error> {
-        Ok(Iter::new(self.repo, self.platform.prefixed("refs/remotes/")?))
+        self.prefixed("refs/remotes/")
     }
 }
 
@@ -90,25 +105,35 @@ impl<'r> Iterator for Iter<'r> {
     type Item = Result<crate::Reference<'r>, Box<dyn std::error::Error + Send + Sync + 'static>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|res| {
-            res.map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send + Sync + 'static>)
-                .and_then(|mut r| {
-                    if self.peel {
-                        let handle = &self.repo;
-                        r.peel_to_id_in_place(&handle.refs, |oid, buf| {
-                            handle
-                                .objects
-                                .try_find(oid, buf)
-                                .map(|po| po.map(|(o, _l)| (o.kind, o.data)))
-                        })
-                        .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send + Sync + 'static>)
-                        .map(|_| r)
-                    } else {
-                        Ok(r)
-                    }
-                })
-                .map(|r| crate::Reference::from_ref(r, self.repo))
-        })
+        match &mut self.inner {
+            Inner::Files(inner) => inner.next().map(|res| {
+                res.map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send + Sync + 'static>)
+                    .and_then(|mut r| {
+                        if self.peel {
+                            let handle = &self.repo;
+                            r.peel_to_id_in_place(&handle.refs, |oid, buf| {
+                                handle
+                                    .objects
+                                    .try_find(oid, buf)
+                                    .map(|po| po.map(|(o, _l)| (o.kind, o.data)))
+                            })
+                            .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send + Sync + 'static>)
+                            .map(|_| r)
+                        } else {
+                            Ok(r)
+                        }
+                    })
+                    .map(|r| crate::Reference::from_ref(r, self.repo))
+            }),
+            Inner::Reftable(inner) => inner.next().map(|r| {
+                let mut r = crate::Reference::from_ref(r, self.repo);
+                if self.peel {
+                    r.peel_to_id_in_place()
+                        .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send + Sync + 'static>)?;
+                }
+                Ok(r)
+            }),
+        }
     }
 }
 
@@ -120,6 +145,8 @@ pub mod init {
     pub enum Error {
         #[error(transparent)]
         Io(#[from] std::io::Error),
+        #[error(transparent)]
+        Reftable(#[from] git_ref::reftable::find::Error),
     }
 }
 
@@ -1,6 +1,8 @@
 //!
+use std::collections::HashSet;
+
+use git_hash::ObjectId;
 use git_object::commit::MessageRef;
-use git_ref::file::ReferenceExt;
 
 use crate::{
     bstr::{BStr, BString, ByteVec},
@@ -9,8 +11,205 @@ use crate::{
 
 impl<'repo> Reference<'repo> {
     /// Return a platform for obtaining iterators over reference logs.
-    pub fn log_iter(&self) -> git_ref::file::log::iter::Platform<'_, '_> {
-        self.inner.log_iter(&self.repo.refs)
+    pub fn log_iter(&self) -> iter::Platform<'_, 'repo> {
+        iter::Platform::new(self.repo, self.inner.name.as_ref())
+    }
+
+    /// Remove all entries from the log of this reference that expired according to `options`, similar to `git reflog expire`,
+    /// and return the outcome or `None` if there is no log.
+    ///
+    /// Use [`Repository::reflog_expire_options()`][crate::Repository::reflog_expire_options()] to obtain options as configured
+    /// by `gc.reflogExpire` and `gc.reflogExpireUnreachable`.
+    /// Commits in the log are reachable if they are ancestors of the tip of this reference, or of any reference if this is `HEAD`.
+    pub fn log_expire(
+        &self,
+        options: git_ref::file::log::expire::Options,
+    ) -> Result<Option<git_ref::file::log::expire::Outcome>, expire::Error> {
+        let needs_reachability = options.expire_unreachable.map_or(false, |unreachable| {
+            options.expire_total.map_or(true, |total| unreachable > total)
+        });
+        let reachable = if needs_reachability {
+            self.reachable_commits()?
+        } else {
+            Default::default()
+        };
+        let (lock_mode, _) = self.repo.config.lock_timeout()?;
+        let is_reachable = |id: &git_hash::oid| reachable.contains(&id.to_owned());
+        Ok(match &self.repo.reftable {
+            Some(reftable) => reftable.reflog_expire(self.name(), options, is_reachable, lock_mode)?,
+            None => self
+                .repo
+                .refs
+                .reflog_expire(self.name(), options, is_reachable, lock_mode)?,
+        })
+    }
+
+    /// Remove the entry at `index` from the log of this reference, with `0` being the most recent one like in `<ref>@{0}`,
+    /// similar to `git reflog delete`, and return the outcome or `None` if there is no log.
+    ///
+    /// If `rewrite` is `true`, the entry following the removed one will be adjusted to start where the previous entry left off.
+    pub fn log_delete_entry(
+        &self,
+        index: usize,
+        rewrite: bool,
+    ) -> Result<Option<git_ref::file::log::expire::Outcome>, delete_entry::Error> {
+        let (lock_mode, _) = self.repo.config.lock_timeout()?;
+        Ok(match &self.repo.reftable {
+            Some(reftable) => reftable.reflog_delete_entry(self.name(), index, rewrite, lock_mode)?,
+            None => self
+                .repo
+                .refs
+                .reflog_delete_entry(self.name(), index, rewrite, lock_mode)?,
+        })
+    }
+
+    fn reachable_commits(&self) -> Result<HashSet<ObjectId>, expire::Error> {
+        let repo = self.repo;
+        let mut tips = Vec::new();
+        if self.name().as_bstr() == "HEAD" {
+            for reference in repo.references()?.all()?.peeled() {
+                if let Some(id) = reference.ok().and_then(|r| r.target().try_id().map(ToOwned::to_owned)) {
+                    tips.push(id);
+                }
+            }
+        }
+        if let Ok(id) = self.clone().into_fully_peeled_id() {
+            tips.push(id.detach());
+        }
+        tips.retain(|id| {
+            repo.find_object(*id)
+                .map_or(false, |obj| obj.kind == git_object::Kind::Commit)
+        });
+
+        let mut reachable = HashSet::new();
+        for info in repo.rev_walk(tips).all()? {
+            reachable.insert(info?.id.detach());
+        }
+        Ok(reachable)
+    }
+}
+
+///
+pub mod iter {
+    use git_ref::{file::log::iter::Forward, log::Line, FullNameRef};
+
+    use crate::Repository;
+
+    /// A platform to obtain iterators over the log of a reference, independently of the way references are stored.
+    #[must_use = "Iterators should be obtained from this platform"]
+    pub struct Platform<'a, 'repo> {
+        files: git_ref::file::log::iter::Platform<'a, 'repo>,
+        reftable: Option<&'repo git_ref::reftable::Store>,
+    }
+
+    impl<'a, 'repo> Platform<'a, 'repo> {
+        pub(crate) fn new(repo: &'repo Repository, name: &'a FullNameRef) -> Self {
+            Platform {
+                files: git_ref::file::log::iter::Platform {
+                    store: &repo.refs,
+                    name,
+                    buf: Vec::new(),
+                },
+                reftable: repo.reftable.as_ref(),
+            }
+        }
+
+        /// Return an iterator over all log-lines, most recent to oldest.
+        pub fn rev(&mut self) -> std::io::Result<Option<Reverse<'_>>> {
+            Ok(match self.reftable {
+                Some(store) => store
+                    .reflog_iter_rev(self.files.name)
+                    .map_err(into_io_err)?
+                    .map(|lines| Reverse {
+                        inner: Inner::Reftable(lines),
+                    }),
+                None => self.files.rev()?.map(|lines| Reverse {
+                    inner: Inner::Files(lines),
+                }),
+            })
+        }
+
+        /// Return an iterator over all log-lines, oldest to most recent.
+        pub fn all(&mut self) -> std::io::Result<Option<Forward<'_>>> {
+            match self.reftable {
+                Some(store) => {
+                    let lines = match store.reflog_iter(self.files.name).map_err(into_io_err)? {
+                        Some(lines) => lines,
+                        None => return Ok(None),
+                    };
+                    // Lines are serialized like in a reflog file to be able to use the same iterator for all stores.
+                    self.files.buf.clear();
+                    for line in lines {
+                        line.write_to(&mut self.files.buf)?;
+                    }
+                    Ok(Some(git_ref::file::log::iter::forward(&self.files.buf)))
+                }
+                None => self.files.all(),
+            }
+        }
+    }
+
+    fn into_io_err(err: git_ref::reftable::find::Error) -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::Other, err)
+    }
+
+    /// An iterator over log-lines, most recent to oldest, as obtained by [`Platform::rev()`].
+    pub struct Reverse<'a> {
+        inner: Inner<'a>,
+    }
+
+    enum Inner<'a> {
+        Files(git_ref::file::log::iter::Reverse<'a, std::fs::File>),
+        Reftable(std::vec::IntoIter<Line>),
+    }
+
+    impl<'a> Iterator for Reverse<'a> {
+        type Item = Result<Line, git_ref::file::log::iter::reverse::Error>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            match &mut self.inner {
+                Inner::Files(lines) => lines.next(),
+                Inner::Reftable(lines) => lines.next().map(Ok),
+            }
+        }
+    }
+}
+
+///
+pub mod expire {
+    /// The error returned by [`Reference::log_expire()`][crate::Reference::log_expire()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error(transparent)]
+        Expire(#[from] git_ref::file::log::expire::Error),
+        #[error(transparent)]
+        ExpireReftable(#[from] git_ref::reftable::expire::Error),
+        #[error("Could not interpret core.filesRefLockTimeout, it must be the number in milliseconds to wait for locks or negative to wait forever")]
+        LockTimeoutConfiguration(#[from] crate::config::lock_timeout::Error),
+        #[error(transparent)]
+        PackedRefsOpen(#[from] crate::reference::iter::Error),
+        #[error(transparent)]
+        ReferenceIterInit(#[from] crate::reference::iter::init::Error),
+        #[error(transparent)]
+        WalkInit(#[from] crate::revision::walk::Error),
+        #[error(transparent)]
+        Walk(#[from] git_traverse::commit::ancestors::Error),
+    }
+}
+
+///
+pub mod delete_entry {
+    /// The error returned by [`Reference::log_delete_entry()`][crate::Reference::log_delete_entry()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error(transparent)]
+        DeleteEntry(#[from] git_ref::file::log::expire::Error),
+        #[error(transparent)]
+        DeleteEntryReftable(#[from] git_ref::reftable::expire::Error),
+        #[error("Could not interpret core.filesRefLockTimeout, it must be the number in milliseconds to wait for locks or negative to wait forever")]
+        LockTimeoutConfiguration(#[from] crate::config::lock_timeout::Error),
     }
 }
 
@@ -10,7 +10,7 @@ pub mod iter;
 pub mod remote;
 
 mod errors;
-pub use errors::{edit, find, head_commit, head_id, peel};
+pub use errors::{edit, find, head_commit, head_id, peel, rename};
 
 use crate::ext::ObjectIdExt;
 
@@ -69,6 +69,21 @@ impl<'repo> Reference<'repo> {
     /// This is useful to learn where this reference is ulitmately pointing to.
     pub fn peel_to_id_in_place(&mut self) -> Result<Id<'repo>, peel::Error> {
         let repo = &self.repo;
+        if let Some(reftable) = &repo.reftable {
+            // Follow symbolic references here as the file store would do it otherwise, cycles end at the depth limit.
+            const MAX_REF_DEPTH: usize = 5;
+            let mut depth = 0;
+            while let git_ref::Target::Symbolic(name) = &self.inner.target {
+                if depth == MAX_REF_DEPTH {
+                    return Err(git_ref::peel::to_id::Error::DepthLimitExceeded {
+                        max_depth: MAX_REF_DEPTH,
+                    }
+                    .into());
+                }
+                self.inner = reftable.find(name.as_ref())?;
+                depth += 1;
+            }
+        }
         let oid = self.inner.peel_to_id_in_place(&repo.refs, |oid, buf| {
             repo.objects
                 .try_find(oid, buf)
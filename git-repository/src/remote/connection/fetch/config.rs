@@ -1,6 +1,10 @@
 use super::Error;
 use crate::{
-    config::{cache::util::ApplyLeniency, tree::Pack},
+    config::{
+        cache::util::ApplyLeniency,
+        tree::{Fetch, Key, Pack},
+    },
+    remote::fetch::negotiate::Algorithm,
     Repository,
 };
 
@@ -24,3 +28,25 @@ pub fn pack_index_version(repo: &Repository) -> Result<git_pack::index::Version,
         .with_leniency(repo.options.lenient_config)?
         .unwrap_or(git_pack::index::Version::V2))
 }
+
+pub fn negotiation_algorithm(repo: &Repository) -> Result<Algorithm, Error> {
+    Ok(repo
+        .config
+        .resolved
+        .string_by_key(Fetch::NEGOTIATION_ALGORITHM.logical_name().as_str())
+        .map(|n| Fetch::NEGOTIATION_ALGORITHM.try_into_negotiation_algorithm(n))
+        .transpose()
+        .with_leniency(repo.options.lenient_config)?
+        .unwrap_or_default())
+}
+
+pub fn write_commit_graph(repo: &Repository) -> Result<bool, Error> {
+    Ok(repo
+        .config
+        .resolved
+        .boolean_by_key(Fetch::WRITE_COMMIT_GRAPH.logical_name().as_str())
+        .map(|value| Fetch::WRITE_COMMIT_GRAPH.enrich_error(value))
+        .transpose()
+        .with_leniency(repo.options.lenient_config)?
+        .unwrap_or(false))
+}
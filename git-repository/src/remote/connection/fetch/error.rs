@@ -8,6 +8,19 @@ pub enum Error {
     PackThreads(#[from] config::unsigned_integer::Error),
     #[error("The value to configure the pack index version should be 1 or 2")]
     PackIndexVersion(#[from] config::key::GenericError),
+    #[error(transparent)]
+    ConfigBoolean(#[from] config::boolean::Error),
+    #[error(transparent)]
+    NegotiationAlgorithmConfig(#[from] config::key::GenericErrorWithValue),
+    #[error(transparent)]
+    ShallowOpen(#[from] crate::shallow::open::Error),
+    #[error("Server lack feature {feature:?}: {description}")]
+    MissingServerFeature {
+        feature: &'static str,
+        description: &'static str,
+    },
+    #[error("Could not write 'shallow' file to incorporate remote updates after fetching")]
+    WriteShallowFile(#[from] crate::shallow::write::Error),
     #[error("Could not decode server reply")]
     FetchResponse(#[from] git_protocol::fetch::response::Error),
     #[error("Cannot fetch from a remote that uses {remote} while local repository uses {local} for object hashes")]
@@ -23,6 +36,8 @@ pub enum Error {
     WritePack(#[from] git_pack::bundle::write::Error),
     #[error(transparent)]
     UpdateRefs(#[from] super::refs::update::Error),
+    #[error("Could not write the commit-graph after fetching")]
+    WriteCommitGraph(#[from] crate::commitgraph::write::Error),
     #[error("Failed to remove .keep file at \"{}\"", path.display())]
     RemovePackKeepFile {
         path: std::path::PathBuf,
@@ -81,12 +81,15 @@ pub struct Outcome {
 pub enum ProgressId {
     /// The progress name is defined by the remote and the progress messages it sets, along with their progress values and limits.
     RemoteProgress,
+    /// Write a commit-graph layer with the newly fetched commits, if `fetch.writeCommitGraph` is set.
+    WriteCommitGraph,
 }
 
 impl From<ProgressId> for git_features::progress::Id {
     fn from(v: ProgressId) -> Self {
         match v {
             ProgressId::RemoteProgress => *b"FERP",
+            ProgressId::WriteCommitGraph => *b"FEWC",
         }
     }
 }
@@ -149,6 +152,7 @@ where
             dry_run: DryRun::No,
             reflog_message: None,
             write_packed_refs: WritePackedRefs::Never,
+            shallow: Default::default(),
         })
     }
 }
@@ -179,6 +183,7 @@ where
     dry_run: DryRun,
     reflog_message: Option<RefLogMessage>,
     write_packed_refs: WritePackedRefs,
+    shallow: remote::fetch::Shallow,
 }
 
 /// Builder
@@ -198,6 +203,8 @@ where
     ///
     /// This improves performance and allows case-sensitive filesystems to deal with ref names that would otherwise
     /// collide.
+    /// If the repository doesn't have any references yet, they are written into a new `packed-refs` file without any additional checks,
+    /// which is what makes clones with a lot of references fast.
     pub fn with_write_packed_refs_only(mut self, enabled: bool) -> Self {
         self.write_packed_refs = if enabled {
             WritePackedRefs::Only
@@ -212,6 +219,15 @@ where
         self.reflog_message = reflog_message.into();
         self
     }
+
+    /// Define what to do with the shallow boundary of the repository, which may create a shallow repository,
+    /// change its boundary or turn it into a complete repository.
+    ///
+    /// The default is to leave the shallow boundary unchanged.
+    pub fn with_shallow(mut self, shallow: remote::fetch::Shallow) -> Self {
+        self.shallow = shallow;
+        self
+    }
 }
 
 impl<'remote, 'repo, T, P> Drop for Prepare<'remote, 'repo, T, P>
@@ -0,0 +1,171 @@
+use std::collections::BinaryHeap;
+
+use git_hash::{oid, ObjectId};
+use git_hashtable::HashMap;
+
+use super::{find_commit, Commit, Error};
+
+type Flags = u8;
+/// The commit is known to be common between the server and us.
+const COMMON: Flags = 1 << 0;
+/// The commit is advertised by the server, which makes it common, but unlike [`COMMON`] it's still sent as `have`.
+const COMMON_REF: Flags = 1 << 1;
+/// The commit was added to the queue.
+const SEEN: Flags = 1 << 2;
+/// The commit was taken from the queue.
+const POPPED: Flags = 1 << 3;
+
+#[derive(Default)]
+struct Entry {
+    flags: Flags,
+    /// `None` if the commit wasn't looked up yet or if it doesn't exist.
+    commit: Option<Commit>,
+    looked_up: bool,
+}
+
+/// An implementation of `git`'s `consecutive` negotiation algorithm, which sends all commits of local history, newest first,
+/// while skipping the ancestors of commits known to be common.
+pub(super) struct Negotiator<'repo> {
+    repo: &'repo crate::Repository,
+    /// The commit-graph to obtain parents and commit times from, if there is one.
+    graph: Option<git_commitgraph::Graph>,
+    commits: HashMap<ObjectId, Entry>,
+    /// The commits to look at next, ordered by commit time, newest first.
+    queue: BinaryHeap<(u32, ObjectId)>,
+    /// The amount of commits in `queue` which aren't known to be common.
+    non_common_revs: usize,
+    buf: Vec<u8>,
+}
+
+impl<'repo> Negotiator<'repo> {
+    pub(super) fn new(repo: &'repo crate::Repository) -> Self {
+        Negotiator {
+            repo,
+            graph: repo.commit_graph().ok(),
+            commits: HashMap::default(),
+            queue: BinaryHeap::new(),
+            non_common_revs: 0,
+            buf: Vec::new(),
+        }
+    }
+
+    fn flags(&self, id: &oid) -> Flags {
+        self.commits.get(id).map_or(0, |entry| entry.flags)
+    }
+
+    /// Return true if `id` was added to the queue, which is the case for all [`SEEN`] commits that exist.
+    fn is_queued(&self, id: &oid) -> bool {
+        self.commits.get(id).map_or(false, |entry| entry.commit.is_some())
+    }
+
+    fn parents(&self, id: &oid) -> Vec<ObjectId> {
+        self.commits
+            .get(id)
+            .and_then(|entry| entry.commit.as_ref())
+            .map(|commit| commit.parents.to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Add `mark` to `id` and queue it, unless it was marked already or doesn't exist.
+    fn rev_list_push(&mut self, id: ObjectId, mark: Flags) -> Result<(), Error> {
+        let entry = self.commits.entry(id).or_default();
+        if entry.flags & mark != 0 {
+            return Ok(());
+        }
+        entry.flags |= mark;
+        if !entry.looked_up {
+            entry.commit = find_commit(self.repo, self.graph.as_ref(), &id, &mut self.buf)?;
+            entry.looked_up = true;
+        }
+        if let Some(commit) = &entry.commit {
+            self.queue.push((commit.time, id));
+            if entry.flags & COMMON == 0 {
+                self.non_common_revs += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Mark `id` and all of its ancestors as common, or only its ancestors if `ancestors_only` is true.
+    fn mark_common(&mut self, id: ObjectId, ancestors_only: bool) -> Result<(), Error> {
+        let mut stack = vec![(id, ancestors_only)];
+        while let Some((id, ancestors_only)) = stack.pop() {
+            let flags = self.flags(&id);
+            if flags & COMMON != 0 {
+                continue;
+            }
+            if !ancestors_only {
+                self.commits.entry(id).or_default().flags |= COMMON;
+            }
+            if flags & SEEN == 0 {
+                self.rev_list_push(id, SEEN)?;
+            } else {
+                if !ancestors_only && flags & POPPED == 0 && self.is_queued(&id) {
+                    self.non_common_revs -= 1;
+                }
+                stack.extend(self.parents(&id).into_iter().map(|parent| (parent, false)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl super::Negotiator for Negotiator<'_> {
+    fn known_common(&mut self, id: ObjectId) -> Result<(), Error> {
+        if self.flags(&id) & SEEN == 0 {
+            self.rev_list_push(id, COMMON_REF | SEEN)?;
+            self.mark_common(id, true)?;
+        }
+        Ok(())
+    }
+
+    fn add_tip(&mut self, id: ObjectId) -> Result<(), Error> {
+        self.rev_list_push(id, SEEN)
+    }
+
+    fn next_have(&mut self) -> Option<Result<ObjectId, Error>> {
+        loop {
+            if self.non_common_revs == 0 {
+                return None;
+            }
+            let (_time, id) = self.queue.pop()?;
+            let entry = self.commits.get_mut(&id).expect("queued commits are known");
+            entry.flags |= POPPED;
+            let flags = entry.flags;
+            if flags & COMMON == 0 {
+                self.non_common_revs -= 1;
+            }
+
+            let (send, mark) = if flags & COMMON != 0 {
+                // Do not send it, and ignore its ancestors.
+                (false, COMMON | SEEN)
+            } else if flags & COMMON_REF != 0 {
+                // Send it, but ignore its ancestors.
+                (true, COMMON | SEEN)
+            } else {
+                (true, SEEN)
+            };
+            for parent in self.parents(&id) {
+                if self.flags(&parent) & SEEN == 0 {
+                    if let Err(err) = self.rev_list_push(parent, mark) {
+                        return Some(Err(err));
+                    }
+                }
+                if mark & COMMON != 0 {
+                    if let Err(err) = self.mark_common(parent, true) {
+                        return Some(Err(err));
+                    }
+                }
+            }
+            if send {
+                return Some(Ok(id));
+            }
+        }
+    }
+
+    fn in_common_with_remote(&mut self, id: ObjectId) -> Result<bool, Error> {
+        let known_to_be_common = self.flags(&id) & COMMON != 0;
+        self.mark_common(id, false)?;
+        Ok(known_to_be_common)
+    }
+}
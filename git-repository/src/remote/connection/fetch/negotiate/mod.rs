@@ -0,0 +1,314 @@
+use git_hash::{oid, ObjectId};
+use git_odb::Find;
+use git_protocol::fetch::response::Acknowledgement;
+use smallvec::SmallVec;
+
+mod consecutive;
+mod skipping;
+
+/// The way the negotiation is performed, as configured by `fetch.negotiationAlgorithm`.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// Do not send any `have` lines, which makes the server send all objects reachable from our wants.
+    Noop,
+    /// Walk over consecutive commits of our local history, newest first, and send each of them as `have`.
+    ///
+    /// This is the default as it assures that the pack we receive is as small as possible.
+    #[default]
+    Consecutive,
+    /// Like [`Consecutive`][Algorithm::Consecutive], but skip more and more commits the longer no common commit is found.
+    ///
+    /// This converges faster for repositories with many local commits at the cost of possibly receiving objects we already have.
+    Skipping,
+}
+
+/// The error returned during negotiation.
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("We were unable to figure out what objects the server should send after {rounds} round(s)")]
+    NegotiationFailed { rounds: usize },
+    #[error("Could not find commit {id} while walking local history")]
+    FindCommit {
+        id: ObjectId,
+        source: git_odb::store::find::Error,
+    },
+    #[error("Could not obtain the parents of commit {id} from the commit-graph while walking local history")]
+    CommitGraph {
+        id: ObjectId,
+        source: git_commitgraph::file::commit::Error,
+    },
+    #[error("Could not decode commit {id} while walking local history")]
+    DecodeCommit {
+        id: ObjectId,
+        source: git_object::decode::Error,
+    },
+    #[error(transparent)]
+    IterReferences(#[from] crate::reference::iter::Error),
+    #[error(transparent)]
+    IterReferencesInit(#[from] crate::reference::iter::init::Error),
+    #[error("Could not obtain the next local reference to use as starting point for walking local history")]
+    IterReferencesNext(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+}
+
+/// The amount of `have` lines to send in the first round.
+const INITIAL_FLUSH: usize = 16;
+/// The amount of `have` lines to send without receiving a new common commit before giving up, once the first one was received.
+const MAX_IN_VAIN: usize = 256;
+
+/// Add a `want` line to `arguments` for each object in `ref_map` that differs from what the local tracking branch points to,
+/// or for all of them if the `shallow` boundary is to be changed, as the remote only sends a new boundary for what we want.
+pub(crate) fn add_wants(
+    repo: &crate::Repository,
+    ref_map: &crate::remote::fetch::RefMap,
+    fetch_tags: crate::remote::fetch::Tags,
+    shallow: &crate::remote::fetch::Shallow,
+    arguments: &mut git_protocol::fetch::Arguments,
+) {
+    let tag_refspec_to_ignore = fetch_tags
+        .to_refspec()
+        .filter(|_| matches!(fetch_tags, crate::remote::fetch::Tags::Included));
+    for mapping in &ref_map.mappings {
+        if tag_refspec_to_ignore.map_or(false, |tag_spec| {
+            mapping
+                .spec_index
+                .implicit_index()
+                .and_then(|idx| ref_map.extra_refspecs.get(idx))
+                .map_or(false, |spec| spec.to_ref() == tag_spec)
+        }) {
+            continue;
+        }
+        let want_id = match mapping.remote.as_id() {
+            Some(id) => id,
+            None => continue,
+        };
+        let have_id = mapping.local.as_ref().and_then(|name| {
+            repo.find_reference(name)
+                .ok()
+                .and_then(|r| r.target().try_id().map(ToOwned::to_owned))
+        });
+        if have_id.as_deref() != Some(want_id) || !matches!(shallow, crate::remote::fetch::Shallow::NoChange) {
+            arguments.want(want_id);
+        }
+    }
+}
+
+/// The state of a negotiation which may span multiple rounds, each of which sends a batch of `have` lines to the server.
+pub(crate) struct Negotiate<'repo> {
+    negotiator: Box<dyn Negotiator + 'repo>,
+    /// If true, the server doesn't remember what we sent in previous rounds, which happens with protocol V2 or stateless
+    /// transports like HTTP.
+    stateless: bool,
+    /// The amount of `have` lines to send in the next round.
+    haves_to_send: usize,
+    /// The amount of `have` lines we sent since the last commit the server acknowledged as common.
+    in_vain: usize,
+    /// If true, the server acknowledged at least one commit as common.
+    seen_ack: bool,
+    /// If true, the server signalled that it is ready to send a pack.
+    seen_ready: bool,
+    /// The commits acknowledged by the server, which have to be sent again each round if the server is `stateless`.
+    common: Vec<ObjectId>,
+}
+
+impl<'repo> Negotiate<'repo> {
+    /// Prepare a negotiation with `algorithm` which walks the history of all local references in `repo`, using what the remote
+    /// advertised in `ref_map` as commits we have in common.
+    ///
+    /// `stateless` is true if the server doesn't keep the state of previous rounds.
+    pub(crate) fn new(
+        algorithm: Algorithm,
+        repo: &'repo crate::Repository,
+        ref_map: &crate::remote::fetch::RefMap,
+        stateless: bool,
+    ) -> Result<Self, Error> {
+        let mut negotiator: Box<dyn Negotiator + 'repo> = match algorithm {
+            Algorithm::Noop => Box::new(Noop),
+            Algorithm::Consecutive => Box::new(consecutive::Negotiator::new(repo)),
+            Algorithm::Skipping => Box::new(skipping::Negotiator::new(repo)),
+        };
+        if algorithm != Algorithm::Noop {
+            for remote_ref in &ref_map.remote_refs {
+                let (_name, target, peeled) = remote_ref.unpack();
+                if let Some(id) = peeled.or(target) {
+                    negotiator.known_common(id.to_owned())?;
+                }
+            }
+            for local_ref in repo.references()?.all()? {
+                let mut local_ref = local_ref.map_err(Error::IterReferencesNext)?;
+                if let Ok(id) = local_ref.peel_to_id_in_place() {
+                    negotiator.add_tip(id.detach())?;
+                }
+            }
+        }
+        Ok(Negotiate {
+            negotiator,
+            stateless,
+            haves_to_send: INITIAL_FLUSH,
+            in_vain: 0,
+            seen_ack: false,
+            seen_ready: false,
+            common: Vec::new(),
+        })
+    }
+
+    /// Learn from the acknowledgements in `previous_response` if this isn't the first round, and add the `have` lines
+    /// of this round to `arguments`.
+    ///
+    /// Returns `true` if the negotiation is done from our side as there is nothing more to tell, so the server can send a pack.
+    pub(crate) fn one_round(
+        &mut self,
+        arguments: &mut git_protocol::fetch::Arguments,
+        previous_response: Option<&git_protocol::fetch::Response>,
+    ) -> Result<bool, Error> {
+        for ack in previous_response.map(|r| r.acknowledgements()).unwrap_or_default() {
+            match ack {
+                Acknowledgement::Common(id) => {
+                    let was_common = self.negotiator.in_common_with_remote(*id)?;
+                    if !was_common && self.stateless {
+                        self.common.push(*id);
+                    }
+                    if !was_common || !self.stateless {
+                        self.in_vain = 0;
+                    }
+                    self.seen_ack = true;
+                }
+                Acknowledgement::Ready => self.seen_ready = true,
+                Acknowledgement::Nak => {}
+            }
+        }
+
+        for id in &self.common {
+            arguments.have(id);
+        }
+        let mut haves_added = 0;
+        let gave_up = self.seen_ack && self.in_vain >= MAX_IN_VAIN;
+        if !self.seen_ready && !gave_up {
+            while haves_added < self.haves_to_send {
+                match self.negotiator.next_have() {
+                    Some(id) => arguments.have(id?),
+                    None => break,
+                }
+                haves_added += 1;
+            }
+            self.haves_to_send = next_flush(self.stateless, self.haves_to_send);
+        }
+        self.in_vain += haves_added;
+        Ok(haves_added == 0)
+    }
+}
+
+/// Return the amount of `have` lines to send in the round after one in which `count` lines were sent, growing faster
+/// for `stateless` servers to reduce the amount of requests.
+fn next_flush(stateless: bool, count: usize) -> usize {
+    const PIPESAFE_FLUSH: usize = 32;
+    const LARGE_FLUSH: usize = 16384;
+    if stateless {
+        if count < LARGE_FLUSH {
+            count * 2
+        } else {
+            count * 11 / 10
+        }
+    } else if count < PIPESAFE_FLUSH {
+        count * 2
+    } else {
+        count + PIPESAFE_FLUSH
+    }
+}
+
+/// An algorithm to find the commits to send as `have` lines, learning from the commits the server has in common with us.
+trait Negotiator {
+    /// Mark `id` as known to be common between the server and us, as the server advertised it. Missing objects are ignored.
+    ///
+    /// Must be called before [`add_tip()`][Negotiator::add_tip()].
+    fn known_common(&mut self, id: ObjectId) -> Result<(), Error>;
+    /// Add `id` as starting point for walking local history. Missing objects are ignored.
+    fn add_tip(&mut self, id: ObjectId) -> Result<(), Error>;
+    /// Produce the next commit to send as `have`, or `None` if there is none left.
+    fn next_have(&mut self) -> Option<Result<ObjectId, Error>>;
+    /// Mark `id` as common as the server acknowledged it, and return `true` if it was known to be common already.
+    fn in_common_with_remote(&mut self, id: ObjectId) -> Result<bool, Error>;
+}
+
+/// A negotiator which never sends any `have` lines.
+struct Noop;
+
+impl Negotiator for Noop {
+    fn known_common(&mut self, _id: ObjectId) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn add_tip(&mut self, _id: ObjectId) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn next_have(&mut self) -> Option<Result<ObjectId, Error>> {
+        None
+    }
+
+    fn in_common_with_remote(&mut self, _id: ObjectId) -> Result<bool, Error> {
+        Ok(false)
+    }
+}
+
+/// The parts of a commit needed to walk local history.
+struct Commit {
+    /// The time at which the commit was committed, in seconds since the unix epoch.
+    time: u32,
+    parents: SmallVec<[ObjectId; 1]>,
+}
+
+/// Read the commit `id` from the commit-`graph` if it is contained in it, or from the object database of `repo` otherwise,
+/// or return `None` if it doesn't exist or isn't a commit.
+fn find_commit(
+    repo: &crate::Repository,
+    graph: Option<&git_commitgraph::Graph>,
+    id: &oid,
+    buf: &mut Vec<u8>,
+) -> Result<Option<Commit>, Error> {
+    if let Some((graph, commit)) = graph.and_then(|graph| graph.commit_by_id(id).map(|commit| (graph, commit))) {
+        return Ok(Some(Commit {
+            time: commit.committer_timestamp().try_into().unwrap_or(u32::MAX),
+            parents: commit
+                .iter_parents()
+                .map(|pos| pos.map(|pos| graph.id_at(pos).to_owned()))
+                .collect::<Result<_, _>>()
+                .map_err(|err| Error::CommitGraph {
+                    id: id.to_owned(),
+                    source: err,
+                })?,
+        }));
+    }
+    let iter = match repo
+        .objects
+        .try_find(id, buf)
+        .map_err(|err| Error::FindCommit {
+            id: id.to_owned(),
+            source: err,
+        })?
+        .and_then(|data| data.try_into_commit_iter())
+    {
+        Some(iter) => iter,
+        None => return Ok(None),
+    };
+    let mut commit = Commit {
+        time: 0,
+        parents: SmallVec::new(),
+    };
+    for token in iter {
+        use git_object::commit::ref_iter::Token;
+        match token.map_err(|err| Error::DecodeCommit {
+            id: id.to_owned(),
+            source: err,
+        })? {
+            Token::Tree { .. } | Token::Author { .. } => continue,
+            Token::Parent { id } => commit.parents.push(id),
+            Token::Committer { signature } => {
+                commit.time = signature.time.seconds_since_unix_epoch;
+                break;
+            }
+            _ => break,
+        }
+    }
+    Ok(Some(commit))
+}
@@ -0,0 +1,199 @@
+use std::collections::BinaryHeap;
+
+use git_hash::{oid, ObjectId};
+use git_hashtable::HashMap;
+
+use super::{find_commit, Commit, Error};
+
+type Flags = u8;
+/// The commit is known to be common between the server and us.
+const COMMON: Flags = 1 << 0;
+/// The commit is advertised by the server.
+const ADVERTISED: Flags = 1 << 1;
+/// The commit was added to the queue.
+const SEEN: Flags = 1 << 2;
+/// The commit was taken from the queue.
+const POPPED: Flags = 1 << 3;
+
+#[derive(Default)]
+struct Entry {
+    flags: Flags,
+    /// `None` if the commit wasn't looked up yet or if it doesn't exist.
+    commit: Option<Commit>,
+    looked_up: bool,
+    /// The amount of commits to skip after the one this commit was reached from was sent.
+    original_ttl: u16,
+    /// The amount of commits that are still to be skipped before one is sent, including this one.
+    ttl: u16,
+}
+
+/// An implementation of `git`'s `skipping` negotiation algorithm, which sends commits of local history, newest first,
+/// while skipping an increasing amount of commits between the ones it sends, as long as none of them is known to be common.
+pub(super) struct Negotiator<'repo> {
+    repo: &'repo crate::Repository,
+    /// The commit-graph to obtain parents and commit times from, if there is one.
+    graph: Option<git_commitgraph::Graph>,
+    commits: HashMap<ObjectId, Entry>,
+    /// The commits to look at next, ordered by commit time, newest first.
+    queue: BinaryHeap<(u32, ObjectId)>,
+    /// The amount of commits in `queue` which aren't known to be common.
+    non_common_revs: usize,
+    buf: Vec<u8>,
+}
+
+impl<'repo> Negotiator<'repo> {
+    pub(super) fn new(repo: &'repo crate::Repository) -> Self {
+        Negotiator {
+            repo,
+            graph: repo.commit_graph().ok(),
+            commits: HashMap::default(),
+            queue: BinaryHeap::new(),
+            non_common_revs: 0,
+            buf: Vec::new(),
+        }
+    }
+
+    fn flags(&self, id: &oid) -> Flags {
+        self.commits.get(id).map_or(0, |entry| entry.flags)
+    }
+
+    /// Return true if `id` was added to the queue, which is the case for all [`SEEN`] commits that exist.
+    fn is_queued(&self, id: &oid) -> bool {
+        self.commits.get(id).map_or(false, |entry| entry.commit.is_some())
+    }
+
+    fn parents(&self, id: &oid) -> Vec<ObjectId> {
+        self.commits
+            .get(id)
+            .and_then(|entry| entry.commit.as_ref())
+            .map(|commit| commit.parents.to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Add `mark` to `id` and queue it, returning `false` if it doesn't exist and thus wasn't queued.
+    fn rev_list_push(&mut self, id: ObjectId, mark: Flags) -> Result<bool, Error> {
+        let entry = self.commits.entry(id).or_default();
+        entry.flags |= mark | SEEN;
+        if !entry.looked_up {
+            entry.commit = find_commit(self.repo, self.graph.as_ref(), &id, &mut self.buf)?;
+            entry.looked_up = true;
+        }
+        Ok(match &entry.commit {
+            Some(commit) => {
+                self.queue.push((commit.time, id));
+                if mark & COMMON == 0 {
+                    self.non_common_revs += 1;
+                }
+                true
+            }
+            None => false,
+        })
+    }
+
+    /// Mark the [`SEEN`] commit `id` and all of its ancestors that are `SEEN` as common.
+    fn mark_common(&mut self, id: ObjectId) {
+        if self.flags(&id) & COMMON != 0 {
+            return;
+        }
+        self.commits.entry(id).or_default().flags |= COMMON;
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if self.flags(&id) & POPPED == 0 && self.is_queued(&id) {
+                self.non_common_revs -= 1;
+            }
+            for parent in self.parents(&id) {
+                match self.commits.get_mut(&parent) {
+                    Some(entry) if entry.flags & SEEN != 0 && entry.flags & COMMON == 0 => {
+                        entry.flags |= COMMON;
+                        stack.push(parent);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Make sure that `parent` of the commit `child` is queued and inherits its time to live, or is marked common if `child` is.
+    ///
+    /// Returns `false` if `parent` doesn't exist or was already taken from the queue, which can happen due to clock skew.
+    fn push_parent(&mut self, child: &oid, parent: ObjectId) -> Result<bool, Error> {
+        let parent_flags = self.flags(&parent);
+        if parent_flags & SEEN != 0 {
+            if parent_flags & POPPED != 0 || !self.is_queued(&parent) {
+                return Ok(false);
+            }
+        } else if !self.rev_list_push(parent, 0)? {
+            return Ok(false);
+        }
+
+        let child = &self.commits[child];
+        if child.flags & (COMMON | ADVERTISED) != 0 {
+            self.mark_common(parent);
+        } else {
+            let (new_original_ttl, new_ttl) = if child.ttl > 0 {
+                (child.original_ttl, child.ttl - 1)
+            } else {
+                let ttl = child.original_ttl.saturating_mul(3) / 2 + 1;
+                (ttl, ttl)
+            };
+            let parent = self.commits.get_mut(&parent).expect("parent was queued");
+            if parent.original_ttl < new_original_ttl {
+                parent.original_ttl = new_original_ttl;
+                parent.ttl = new_ttl;
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl super::Negotiator for Negotiator<'_> {
+    fn known_common(&mut self, id: ObjectId) -> Result<(), Error> {
+        if self.flags(&id) & SEEN == 0 {
+            self.rev_list_push(id, ADVERTISED)?;
+        }
+        Ok(())
+    }
+
+    fn add_tip(&mut self, id: ObjectId) -> Result<(), Error> {
+        if self.flags(&id) & SEEN == 0 {
+            self.rev_list_push(id, 0)?;
+        }
+        Ok(())
+    }
+
+    fn next_have(&mut self) -> Option<Result<ObjectId, Error>> {
+        loop {
+            if self.non_common_revs == 0 {
+                return None;
+            }
+            let (_time, id) = self.queue.pop()?;
+            let entry = self.commits.get_mut(&id).expect("queued commits are known");
+            entry.flags |= POPPED;
+            let (flags, ttl) = (entry.flags, entry.ttl);
+            if flags & COMMON == 0 {
+                self.non_common_revs -= 1;
+            }
+
+            let mut parent_pushed = false;
+            for parent in self.parents(&id) {
+                match self.push_parent(&id, parent) {
+                    Ok(pushed) => parent_pushed |= pushed,
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+            // Commits without parents that can still be walked are sent no matter what.
+            if flags & COMMON == 0 && (ttl == 0 || !parent_pushed) {
+                return Some(Ok(id));
+            }
+        }
+    }
+
+    fn in_common_with_remote(&mut self, id: ObjectId) -> Result<bool, Error> {
+        let flags = self.flags(&id);
+        // Only commits we sent can be acknowledged, but let's not rely on it.
+        if flags & SEEN != 0 {
+            self.mark_common(id);
+        }
+        Ok(flags & COMMON != 0)
+    }
+}
@@ -25,8 +25,17 @@ where
     ///
     /// ### Negotiation
     ///
-    /// "fetch.negotiationAlgorithm" describes algorithms `git` uses currently, with the default being `consecutive` and `skipping` being
-    /// experimented with. We currently implement something we could call 'naive' which works for now.
+    /// The algorithm to find the commits we have in common with the remote is configured with `fetch.negotiationAlgorithm`, and
+    /// just like `git`, we implement `consecutive` (the default), `skipping` and `noop`. Starting from all local references,
+    /// the algorithm walks local history and sends what it finds in batches of growing size until the remote is ready to send
+    /// a pack, or until we ran out of commits to send.
+    ///
+    /// ### Shallow Repositories
+    ///
+    /// If the repository [is shallow][crate::Repository::is_shallow()] or if the shallow boundary should be changed
+    /// [as configured][Prepare::with_shallow()], the server is informed about our shallow commits and the desired depth.
+    /// The shallow boundary it sends back is written to the repository's [`shallow` file][crate::Repository::shallow_file()]
+    /// once the pack was received.
     ///
     /// ### Pack `.keep` files
     ///
@@ -60,6 +69,9 @@ where
     /// ### Configuration
     ///
     /// - `gitoxide.userAgent` is read to obtain the application user agent for git servers and for HTTP servers as well.
+    /// - `fetch.negotiationAlgorithm` is read to select the algorithm to find the commits we have in common with the server.
+    /// - `fetch.writeCommitGraph` is read to add the commits of updated references to a new layer of a split commit-graph
+    ///   after fetching, which is merged with existing layers like `git` does by default.
     ///
     #[git_protocol::maybe_async::maybe_async]
     pub async fn receive(mut self, should_interrupt: &AtomicBool) -> Result<Outcome, Error> {
@@ -86,6 +98,7 @@ where
             }
             arguments.use_include_tag();
         }
+        let negotiation_algorithm = config::negotiation_algorithm(repo)?;
         let mut previous_response = None::<git_protocol::fetch::Response>;
         let mut round = 1;
 
@@ -96,50 +109,78 @@ where
             });
         }
 
+        let shallow_commits = repo.shallow_commits()?;
+        if (shallow_commits.is_some() || !matches!(self.shallow, fetch::Shallow::NoChange))
+            && !arguments.can_use_shallow()
+        {
+            // NOTE: if this is an issue, we could always just not deepen and fetch the whole history,
+            //       which would effectively unshallow the repository.
+            return Err(Error::MissingServerFeature {
+                feature: "shallow",
+                description: "shallow clones need server support to remain shallow, otherwise bigger than expected packs are sent effectively unshallowing the repository",
+            });
+        }
+        add_shallow_args(&mut arguments, &self.shallow, shallow_commits.as_deref())?;
+
+        negotiate::add_wants(
+            repo,
+            &self.ref_map,
+            con.remote.fetch_tags,
+            &self.shallow,
+            &mut arguments,
+        );
+        if arguments.is_empty() {
+            git_protocol::indicate_end_of_interaction(&mut con.transport).await.ok();
+            let update_refs = refs::update(
+                repo,
+                self.reflog_message
+                    .take()
+                    .unwrap_or_else(|| RefLogMessage::Prefixed { action: "fetch".into() }),
+                &self.ref_map.mappings,
+                con.remote.refspecs(remote::Direction::Fetch),
+                &self.ref_map.extra_refspecs,
+                con.remote.fetch_tags,
+                self.dry_run,
+                self.write_packed_refs,
+            )?;
+            return Ok(Outcome {
+                ref_map: std::mem::take(&mut self.ref_map),
+                status: Status::NoPackReceived { update_refs },
+            });
+        }
+
+        let mut negotiation = match negotiate::Negotiate::new(
+            negotiation_algorithm,
+            repo,
+            &self.ref_map,
+            matches!(protocol_version, git_protocol::transport::Protocol::V2)
+                || !con.transport.connection_persists_across_multiple_requests(),
+        ) {
+            Ok(negotiation) => negotiation,
+            Err(err) => {
+                git_protocol::indicate_end_of_interaction(&mut con.transport).await.ok();
+                return Err(err.into());
+            }
+        };
+        let mut shallow_updates = Vec::new();
         let reader = 'negotiation: loop {
             progress.step();
             progress.set_name(format!("negotiate (round {round})"));
 
-            let is_done = match negotiate::one_round(
-                negotiate::Algorithm::Naive,
-                round,
-                repo,
-                &self.ref_map,
-                con.remote.fetch_tags,
-                &mut arguments,
-                previous_response.as_ref(),
-            ) {
-                Ok(_) if arguments.is_empty() => {
-                    git_protocol::indicate_end_of_interaction(&mut con.transport).await.ok();
-                    let update_refs = refs::update(
-                        repo,
-                        self.reflog_message
-                            .take()
-                            .unwrap_or_else(|| RefLogMessage::Prefixed { action: "fetch".into() }),
-                        &self.ref_map.mappings,
-                        con.remote.refspecs(remote::Direction::Fetch),
-                        &self.ref_map.extra_refspecs,
-                        con.remote.fetch_tags,
-                        self.dry_run,
-                        self.write_packed_refs,
-                    )?;
-                    return Ok(Outcome {
-                        ref_map: std::mem::take(&mut self.ref_map),
-                        status: Status::NoPackReceived { update_refs },
-                    });
-                }
+            let is_done = match negotiation.one_round(&mut arguments, previous_response.as_ref()) {
                 Ok(is_done) => is_done,
                 Err(err) => {
                     git_protocol::indicate_end_of_interaction(&mut con.transport).await.ok();
                     return Err(err.into());
                 }
             };
-            round += 1;
             let mut reader = arguments.send(&mut con.transport, is_done).await?;
             if sideband_all {
                 setup_remote_progress(progress, &mut reader);
             }
-            let response = git_protocol::fetch::Response::from_line_reader(protocol_version, &mut reader).await?;
+            let response =
+                git_protocol::fetch::Response::from_line_reader(protocol_version, is_done, &mut reader).await?;
+            shallow_updates.extend_from_slice(response.shallow_updates());
             if response.has_pack() {
                 progress.step();
                 progress.set_name("receiving pack");
@@ -147,9 +188,12 @@ where
                     setup_remote_progress(progress, &mut reader);
                 }
                 break 'negotiation reader;
+            } else if is_done {
+                return Err(negotiate::Error::NegotiationFailed { rounds: round }.into());
             } else {
                 previous_response = Some(response);
             }
+            round += 1;
         };
 
         let options = git_pack::bundle::write::Options {
@@ -159,6 +203,11 @@ where
             object_hash: con.remote.repo.object_hash(),
         };
 
+        let mut commit_graph_progress =
+            (matches!(self.dry_run, fetch::DryRun::No) && config::write_commit_graph(repo)?).then(|| {
+                con.progress
+                    .add_child_with_id("write commit-graph", ProgressId::WriteCommitGraph.into())
+            });
         let mut write_pack_bundle = if matches!(self.dry_run, fetch::DryRun::No) {
             Some(git_pack::Bundle::write_to_directory(
                 #[cfg(feature = "async-network-client")]
@@ -187,6 +236,10 @@ where
             git_protocol::indicate_end_of_interaction(&mut con.transport).await.ok();
         }
 
+        if matches!(self.dry_run, fetch::DryRun::No) {
+            crate::shallow::write(&repo.shallow_file(), shallow_commits, &shallow_updates)?;
+        }
+
         let update_refs = refs::update(
             repo,
             self.reflog_message
@@ -208,6 +261,25 @@ where
             }
         }
 
+        if let Some(progress) = commit_graph_progress.take() {
+            let tips = update_refs
+                .edits
+                .iter()
+                .filter_map(|edit| edit.change.new_value()?.try_id().map(ToOwned::to_owned));
+            repo.write_commit_graph(
+                tips,
+                crate::commitgraph::write::Options {
+                    mode: crate::commitgraph::write::Mode::Split {
+                        size_multiple: 2,
+                        max_commits: None,
+                    },
+                    changed_paths: false,
+                },
+                progress,
+                should_interrupt,
+            )?;
+        }
+
         Ok(Outcome {
             ref_map: std::mem::take(&mut self.ref_map),
             status: match write_pack_bundle {
@@ -221,6 +293,73 @@ where
     }
 }
 
+fn add_shallow_args(
+    args: &mut git_protocol::fetch::Arguments,
+    shallow: &fetch::Shallow,
+    shallow_commits: Option<&[git_hash::ObjectId]>,
+) -> Result<(), Error> {
+    /// The depth `git` uses to receive all history, as used by `git fetch --unshallow`.
+    const INFINITE_DEPTH: usize = 0x7fffffff;
+
+    for commit in shallow_commits.into_iter().flatten() {
+        args.shallow(commit);
+    }
+    match shallow {
+        fetch::Shallow::NoChange => {}
+        fetch::Shallow::DepthAtRemote(depth) => args.deepen(depth.get() as usize),
+        fetch::Shallow::Deepen(depth) => {
+            if *depth > 0 {
+                if !args.can_use_deepen_relative() {
+                    return Err(Error::MissingServerFeature {
+                        feature: "deepen-relative",
+                        description: "deepening the shallow boundary requires the server to count commits from there",
+                    });
+                }
+                args.deepen(*depth as usize);
+                args.deepen_relative();
+            }
+        }
+        fetch::Shallow::Since { cutoff } => {
+            if !args.can_use_deepen_since() {
+                return Err(Error::MissingServerFeature {
+                    feature: "deepen-since",
+                    description: "setting the shallow boundary by date requires server support",
+                });
+            }
+            args.deepen_since(cutoff.seconds_since_unix_epoch as usize);
+        }
+        fetch::Shallow::Exclude {
+            remote_refs,
+            since_cutoff,
+        } => {
+            if !args.can_use_deepen_not() {
+                return Err(Error::MissingServerFeature {
+                    feature: "deepen-not",
+                    description: "excluding the history of references requires server support",
+                });
+            }
+            if let Some(cutoff) = since_cutoff {
+                if !args.can_use_deepen_since() {
+                    return Err(Error::MissingServerFeature {
+                        feature: "deepen-since",
+                        description: "setting the shallow boundary by date requires server support",
+                    });
+                }
+                args.deepen_since(cutoff.seconds_since_unix_epoch as usize);
+            }
+            for remote_ref in remote_refs {
+                args.deepen_not(remote_ref.as_ref().as_bstr());
+            }
+        }
+        fetch::Shallow::Undo => {
+            if shallow_commits.is_some() {
+                args.deepen(INFINITE_DEPTH);
+            }
+        }
+    }
+    Ok(())
+}
+
 fn setup_remote_progress<P>(
     progress: &mut P,
     reader: &mut Box<dyn git_protocol::transport::client::ExtendedBufRead + Unpin + '_>,
@@ -226,30 +226,20 @@ pub(crate) fn update(
     }
 
     let edits = match dry_run {
-        fetch::DryRun::No => {
-            let (file_lock_fail, packed_refs_lock_fail) = repo
-                .config
-                .lock_timeout()
-                .map_err(crate::reference::edit::Error::from)?;
-            repo.refs
-                .transaction()
-                .packed_refs(
-                    match write_packed_refs {
-                        fetch::WritePackedRefs::Only => {
-                            git_ref::file::transaction::PackedRefs::DeletionsAndNonSymbolicUpdatesRemoveLooseSourceReference(Box::new(|oid, buf| {
-                                repo.objects
-                                    .try_find(oid, buf)
-                                    .map(|obj| obj.map(|obj| obj.kind))
-                                    .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send + Sync + 'static>)
-                            }))},
-                        fetch::WritePackedRefs::Never => git_ref::file::transaction::PackedRefs::DeletionsOnly
-                    }
-                )
-                .prepare(edits, file_lock_fail, packed_refs_lock_fail)
-                .map_err(crate::reference::edit::Error::from)?
-                .commit(repo.committer().transpose().map_err(|err| update::Error::EditReferences(crate::reference::edit::Error::ParseCommitterTime(err)))?)
-                .map_err(crate::reference::edit::Error::from)?
-        }
+        fetch::DryRun::No => repo.edit_references_with_packed_refs(
+            edits,
+            match write_packed_refs {
+                fetch::WritePackedRefs::Only => {
+                    git_ref::file::transaction::PackedRefs::Initial(Box::new(|oid, buf| {
+                        repo.objects
+                            .try_find(oid, buf)
+                            .map(|obj| obj.map(|obj| obj.kind))
+                            .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send + Sync + 'static>)
+                    }))
+                }
+                fetch::WritePackedRefs::Never => git_ref::file::transaction::PackedRefs::DeletionsOnly,
+            },
+        )?,
         fetch::DryRun::Yes => edits,
     };
 
@@ -27,3 +27,6 @@ pub mod ref_map;
 
 ///
 pub mod fetch;
+
+///
+pub mod push;
@@ -0,0 +1,68 @@
+use crate::bstr::BString;
+
+/// The error returned by [`send()`](super::Prepare::send()).
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("Cannot push to a remote that uses {remote} while local repository uses {local} for object hashes")]
+    IncompatibleObjectHash {
+        local: git_hash::Kind,
+        remote: git_hash::Kind,
+    },
+    #[error(transparent)]
+    ObjectFormat(#[from] crate::remote::ref_map::Error),
+    #[error("Could not open local references for iteration")]
+    IterReferences(#[from] crate::reference::iter::Error),
+    #[error("Could not open local references for iteration")]
+    IterReferencesInit(#[from] crate::reference::iter::init::Error),
+    #[error("Could not read the next local reference")]
+    IterReferencesNext(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    #[error(transparent)]
+    FindReference(#[from] crate::reference::find::Error),
+    #[error("The source of refspec {spec:?} could not be resolved to an object")]
+    ResolveSource {
+        spec: BString,
+        source: crate::revision::spec::parse::single::Error,
+    },
+    #[error("The destination {name:?} of refspec {spec:?} is neither a full reference name nor does it match a reference on the remote")]
+    UnqualifiedDestination { name: BString, spec: BString },
+    #[error("The destination {name:?} is updated by more than one refspec")]
+    DuplicateDestination { name: BString },
+    #[error(transparent)]
+    Traverse(#[from] git_traverse::commit::ancestors::Error),
+    #[error(transparent)]
+    Walk(#[from] crate::revision::walk::Error),
+    #[error("Could not determine if the update of a remote reference is a fast-forward")]
+    IsAncestor(#[from] crate::revision::merge_base::Error),
+    #[error(transparent)]
+    FindObject(#[from] crate::object::find::existing::Error),
+    #[error(transparent)]
+    DecodeCommit(#[from] git_object::decode::Error),
+    #[error(transparent)]
+    OpenObjectDatabase(#[from] std::io::Error),
+    #[error("Failed to count the objects to send")]
+    CountObjects(
+        #[from]
+        git_pack::data::output::count::objects::Error<
+            git_pack::find::existing::Error<git_odb::store::find::Error>,
+            std::convert::Infallible,
+        >,
+    ),
+    #[error("Failed to create the pack entries to send")]
+    CreateEntries(#[from] git_pack::data::output::entry::iter_from_counts::Error<git_odb::store::find::Error>),
+    #[error(transparent)]
+    Push(#[from] git_protocol::push::Error),
+    #[error("The remote reported a status for {ref_name:?} which we didn't ask to update")]
+    UnexpectedRefStatus { ref_name: BString },
+    #[error(transparent)]
+    EditTrackingReferences(#[from] crate::reference::edit::Error),
+}
+
+impl git_protocol::transport::IsSpuriousError for Error {
+    fn is_spurious(&self) -> bool {
+        match self {
+            Error::Push(err) => err.is_spurious(),
+            _ => false,
+        }
+    }
+}
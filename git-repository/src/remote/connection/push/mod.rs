@@ -0,0 +1,156 @@
+use git_protocol::transport::{client::Transport, Service};
+
+use crate::{
+    remote,
+    remote::{fetch::DryRun, push::Update, Connection},
+    Progress,
+};
+
+mod error;
+pub use error::Error;
+
+mod send;
+mod update;
+
+/// The outcome of pushing to a remote via [`Prepare::send()`].
+#[derive(Debug, Clone)]
+pub struct Outcome {
+    /// All updates derived from the push refspecs, in the order of the refspecs, including those rejected by us or the remote.
+    pub updates: Vec<Update>,
+    /// The report of the remote after receiving our commands, or `None` if no command was sent or if this was a dry-run.
+    pub response: Option<git_protocol::push::Response>,
+    /// The amount of objects in the pack sent to the remote, or 0 if no pack was sent.
+    pub num_objects_sent: usize,
+    /// The edits of remote tracking references performed after the remote accepted our updates.
+    pub tracking_ref_edits: Vec<git_ref::transaction::RefEdit>,
+}
+
+///
+pub mod prepare {
+    /// The error returned by [`prepare_push()`][super::Connection::prepare_push()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("Cannot perform a meaningful push operation without any configured ref-specs")]
+        MissingRefSpecs,
+        #[error(transparent)]
+        Handshake(#[from] crate::remote::ref_map::Error),
+        #[error("The remote didn't advertise its references, which is required for pushing with protocol V1")]
+        MissingRemoteRefs,
+    }
+
+    impl git_protocol::transport::IsSpuriousError for Error {
+        fn is_spurious(&self) -> bool {
+            match self {
+                Error::Handshake(err) => err.is_spurious(),
+                _ => false,
+            }
+        }
+    }
+}
+
+impl<'remote, 'repo, T, P> Connection<'remote, 'repo, T, P>
+where
+    T: Transport,
+    P: Progress,
+{
+    /// Perform a handshake with `git-receive-pack` on the remote to learn about its references, in preparation for
+    /// [sending][Prepare::send()] updates according to the push refspecs of our remote.
+    ///
+    /// Note that the connection has to be created for [pushing][remote::Direction::Push] to use the correct URL.
+    ///
+    /// # Async Experimental
+    ///
+    /// Just like [`prepare_fetch()`][Self::prepare_fetch()], this relies on Drop semantics to close the connection if nothing
+    /// is sent, which blocks the executor in async mode.
+    #[allow(clippy::result_large_err)]
+    #[git_protocol::maybe_async::maybe_async]
+    pub async fn prepare_push(mut self) -> Result<Prepare<'remote, 'repo, T, P>, prepare::Error> {
+        if self.remote.refspecs(remote::Direction::Push).is_empty() {
+            return Err(prepare::Error::MissingRefSpecs);
+        }
+        let mut handshake = self
+            .handshake(Service::ReceivePack, remote::Direction::Push, Vec::new())
+            .await?;
+        let remote_refs = match handshake.refs.take() {
+            Some(refs) => refs,
+            None => {
+                git_protocol::indicate_end_of_interaction(&mut self.transport)
+                    .await
+                    .ok();
+                return Err(prepare::Error::MissingRemoteRefs);
+            }
+        };
+        Ok(Prepare {
+            con: Some(self),
+            handshake,
+            remote_refs,
+            dry_run: DryRun::No,
+        })
+    }
+}
+
+/// A structure to hold the result of the handshake with `git-receive-pack` and configure the upcoming push operation.
+pub struct Prepare<'remote, 'repo, T, P>
+where
+    T: Transport,
+{
+    con: Option<Connection<'remote, 'repo, T, P>>,
+    handshake: git_protocol::handshake::Outcome,
+    remote_refs: Vec<git_protocol::handshake::Ref>,
+    dry_run: DryRun,
+}
+
+/// Access
+impl<'remote, 'repo, T, P> Prepare<'remote, 'repo, T, P>
+where
+    T: Transport,
+{
+    /// Return the references advertised by the remote.
+    pub fn remote_refs(&self) -> &[git_protocol::handshake::Ref] {
+        &self.remote_refs
+    }
+
+    /// Return additional information provided by the server as part of the handshake.
+    ///
+    /// Note that its `refs` field is always `None` as the refs are available via [`remote_refs()`][Self::remote_refs()].
+    pub fn handshake(&self) -> &git_protocol::handshake::Outcome {
+        &self.handshake
+    }
+}
+
+/// Builder
+impl<'remote, 'repo, T, P> Prepare<'remote, 'repo, T, P>
+where
+    T: Transport,
+{
+    /// If dry run is enabled, no change will be made to the remote or to the local repository.
+    ///
+    /// This works by computing all updates without sending them, nor will remote tracking refs be updated.
+    pub fn with_dry_run(mut self, enabled: bool) -> Self {
+        self.dry_run = if enabled { DryRun::Yes } else { DryRun::No };
+        self
+    }
+}
+
+impl<'remote, 'repo, T, P> Drop for Prepare<'remote, 'repo, T, P>
+where
+    T: Transport,
+{
+    fn drop(&mut self) {
+        if let Some(mut con) = self.con.take() {
+            #[cfg(feature = "async-network-client")]
+            {
+                // TODO: this should be an async drop once the feature is available, see the `Drop` implementation for fetching.
+                git_protocol::futures_lite::future::block_on(git_protocol::indicate_end_of_interaction(
+                    &mut con.transport,
+                ))
+                .ok();
+            }
+            #[cfg(not(feature = "async-network-client"))]
+            {
+                git_protocol::indicate_end_of_interaction(&mut con.transport).ok();
+            }
+        }
+    }
+}
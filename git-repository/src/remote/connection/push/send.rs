@@ -0,0 +1,330 @@
+#![allow(clippy::result_large_err)]
+use std::{
+    collections::HashSet,
+    convert::TryInto,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use git_hash::ObjectId;
+use git_odb::Find;
+use git_pack::data::output;
+use git_protocol::transport::client::Transport;
+use git_ref::{
+    transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog},
+    Target,
+};
+
+use crate::{
+    remote,
+    remote::{
+        connection::push::update,
+        connection::ref_map::extract_object_format,
+        fetch::DryRun,
+        push::{Error, Mode, Outcome, Prepare, Update},
+    },
+    Progress, Repository,
+};
+
+impl<'remote, 'repo, T, P> Prepare<'remote, 'repo, T, P>
+where
+    T: Transport,
+    P: Progress,
+    P::SubProgress: 'static,
+{
+    /// Compute the updates of remote references from the push refspecs of our remote, send the objects the remote doesn't have
+    /// yet in a pack along with the reference updates, and update our remote tracking references for all updates the remote accepted.
+    ///
+    /// Updates which aren't fast-forwards are rejected unless forced by their refspec, and so are updates of tags that already
+    /// exist on the remote, and deletions of references the remote doesn't have. These don't cause an error, but are returned
+    /// with the respective [`Mode`] instead, just like updates that were rejected by the remote.
+    ///
+    /// ### Remote tracking references
+    ///
+    /// Remote tracking references are determined by mapping the updated remote references through the _fetch_ refspecs of
+    /// our remote. They are updated or deleted according to the accepted updates, without performing a fetch.
+    ///
+    /// ### Deviation
+    ///
+    /// Packs are sent without deltas and never as thin packs, and all objects reachable from advertised remote references are
+    /// assumed to be present on the remote.
+    ///
+    /// ### Configuration
+    ///
+    /// - `gitoxide.userAgent` is read to obtain the application user agent for git servers.
+    ///
+    /// ### Async Mode
+    ///
+    /// The pack is created and written in a blocking fashion, so it's best to run this on an executor that can handle it.
+    #[allow(clippy::result_large_err)]
+    #[git_protocol::maybe_async::maybe_async]
+    pub async fn send(mut self, should_interrupt: &AtomicBool) -> Result<Outcome, Error> {
+        let mut con = self.con.take().expect("send() can only be called once");
+        let repo = con.remote.repo;
+
+        let remote_object_hash = extract_object_format(repo, &self.handshake)?;
+        if remote_object_hash != repo.object_hash() {
+            git_protocol::indicate_end_of_interaction(&mut con.transport).await.ok();
+            return Err(Error::IncompatibleObjectHash {
+                local: repo.object_hash(),
+                remote: remote_object_hash,
+            });
+        }
+
+        let mut updates = match update::compute(repo, &self.remote_refs, con.remote.refspecs(remote::Direction::Push)) {
+            Ok(updates) => updates,
+            Err(err) => {
+                git_protocol::indicate_end_of_interaction(&mut con.transport).await.ok();
+                return Err(err);
+            }
+        };
+        let commands: Vec<_> = updates
+            .iter()
+            .filter(|u| u.mode.needs_command())
+            .map(|u| git_protocol::push::Command {
+                old_id: u.previous_id.unwrap_or_else(|| ObjectId::null(repo.object_hash())),
+                new_id: u.new_id.unwrap_or_else(|| ObjectId::null(repo.object_hash())),
+                ref_name: u.remote_ref.clone(),
+            })
+            .collect();
+        if self.dry_run == DryRun::Yes || commands.is_empty() {
+            git_protocol::indicate_end_of_interaction(&mut con.transport).await.ok();
+            return Ok(Outcome {
+                updates,
+                response: None,
+                num_objects_sent: 0,
+                tracking_ref_edits: Vec::new(),
+            });
+        }
+
+        let mut db = repo.objects.clone().into_arc()?;
+        db.prevent_pack_unload();
+        db.ignore_replacements = true;
+        let counts = {
+            let tips: Vec<_> = updates
+                .iter()
+                .filter(|u| u.mode.needs_command())
+                .filter_map(|u| u.new_id)
+                .collect();
+            let Input { objects, exclude } = objects_to_send(repo, &self.remote_refs, tips)?;
+            let mut progress = con.progress.add_child("counting");
+            progress.init(None, git_features::progress::count("objects"));
+            let mut counts = output::count::objects(
+                db.clone(),
+                objects.into_iter().map(Ok::<_, std::convert::Infallible>),
+                progress,
+                should_interrupt,
+                |_path| true,
+                output::count::objects::Options {
+                    // Expanding objects by comparing trees to their ancestors is only precise if done by a single thread.
+                    thread_limit: Some(1),
+                    input_object_expansion: output::count::objects::ObjectExpansion::TreeAdditionsComparedToAncestor,
+                    chunk_size: 50,
+                },
+            )?
+            .0;
+            counts.retain(|count| !exclude.contains(&count.id));
+            counts
+        };
+        let num_objects_sent = counts.len();
+
+        let mut write_pack_error = None;
+        let response = {
+            let progress = con.progress.add_child("creating entries");
+            let object_hash = repo.object_hash();
+            let agent = repo
+                .config
+                .user_agent_tuple()
+                .1
+                .expect("agent is always set")
+                .into_owned();
+            git_protocol::push(
+                &mut con.transport,
+                &self.handshake.capabilities,
+                &commands,
+                agent,
+                |out| {
+                    let entries = git_features::parallel::InOrderIter::from(output::entry::iter_from_counts(
+                        counts,
+                        db,
+                        progress,
+                        output::entry::iter_from_counts::Options {
+                            thread_limit: None,
+                            mode: output::entry::iter_from_counts::Mode::PackCopyAndBaseObjects,
+                            allow_thin_pack: false,
+                            chunk_size: 50,
+                            version: Default::default(),
+                        },
+                    ));
+                    for res in output::bytes::FromEntriesIter::new(
+                        entries,
+                        out,
+                        num_objects_sent as u32,
+                        git_pack::data::Version::V2,
+                        object_hash,
+                    ) {
+                        if should_interrupt.load(Ordering::Relaxed) {
+                            return Err(std::io::Error::new(std::io::ErrorKind::Other, "Interrupted"));
+                        }
+                        match res {
+                            Ok(_) => {}
+                            Err(output::bytes::Error::Io(err)) => return Err(err),
+                            Err(output::bytes::Error::Input(err)) => {
+                                let io_err = std::io::Error::new(std::io::ErrorKind::Other, err.to_string());
+                                write_pack_error = Some(err);
+                                return Err(io_err);
+                            }
+                        }
+                    }
+                    Ok(())
+                },
+            )
+            .await
+        };
+        let response = match (response, write_pack_error) {
+            (_, Some(err)) => return Err(err.into()),
+            (res, None) => res?,
+        };
+
+        for status in &response.refs {
+            let update = updates
+                .iter_mut()
+                .find(|u| u.mode.needs_command() && u.remote_ref == status.ref_name)
+                .ok_or_else(|| Error::UnexpectedRefStatus {
+                    ref_name: status.ref_name.clone(),
+                })?;
+            if let Some(reason) = &status.error {
+                update.mode = Mode::RejectedByRemote { reason: reason.clone() };
+            }
+        }
+
+        let tracking_ref_edits = update_tracking_refs(
+            repo,
+            con.remote.refspecs(remote::Direction::Fetch),
+            updates.iter().filter(|u| u.mode.needs_command()),
+        )?;
+        Ok(Outcome {
+            updates,
+            response: Some(response),
+            num_objects_sent,
+            tracking_ref_edits,
+        })
+    }
+}
+
+/// The objects to expand into the pack to send.
+struct Input {
+    /// The objects to expand.
+    objects: Vec<ObjectId>,
+    /// Objects which the remote already has, but which the expansion adds as it compares trees to the trees of parent commits.
+    exclude: HashSet<ObjectId>,
+}
+
+/// Return all objects that need to be expanded to obtain the objects the remote doesn't have to receive `tips`,
+/// assuming it has all commits reachable from the objects advertised in `remote_refs`.
+///
+/// The traversal stops at commits known to the remote, so its cost depends on the amount of new commits only.
+fn objects_to_send(
+    repo: &Repository,
+    remote_refs: &[git_protocol::handshake::Ref],
+    tips: Vec<ObjectId>,
+) -> Result<Input, Error> {
+    let advertised: HashSet<_> = remote_refs
+        .iter()
+        .flat_map(|r| {
+            let (_name, target, peeled) = r.unpack();
+            target.into_iter().chain(peeled)
+        })
+        .filter(|id| repo.objects.contains(id))
+        .map(ToOwned::to_owned)
+        .collect();
+    let mut known_commits = Vec::new();
+    for id in &advertised {
+        if let Ok(commit) = repo.find_object(*id)?.peel_to_kind(git_object::Kind::Commit) {
+            known_commits.push(commit.id);
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut commits = Vec::new();
+    for tip in tips {
+        if advertised.contains(&tip) {
+            continue;
+        }
+        let object = repo.find_object(tip)?;
+        if object.kind == git_object::Kind::Commit {
+            commits.push(tip);
+        } else {
+            out.push(tip);
+            if let Ok(Ok(commit)) = object.peel_tags_to_end().map(crate::Object::try_into_commit) {
+                commits.push(commit.id);
+            }
+        }
+    }
+    let mut exclude = HashSet::new();
+    for info in repo.rev_walk(commits).hide(known_commits).boundary().all()? {
+        let info = info?;
+        if info.is_boundary {
+            // The remote has these parents of new commits, but the expansion compares trees to them.
+            exclude.insert(info.id.detach());
+            exclude.insert(info.id.object()?.into_commit().tree_id()?.detach());
+        } else {
+            out.push(info.id.detach());
+        }
+    }
+    Ok(Input { objects: out, exclude })
+}
+
+/// Update or delete the remote tracking references of all `updates` that were accepted by the remote, as derived
+/// from the `fetch_specs` of the remote.
+fn update_tracking_refs<'a>(
+    repo: &Repository,
+    fetch_specs: &[git_refspec::RefSpec],
+    updates: impl Iterator<Item = &'a Update>,
+) -> Result<Vec<RefEdit>, Error> {
+    let updates: Vec<_> = updates.filter(|u| !u.mode.is_rejected()).collect();
+    let null = ObjectId::null(repo.object_hash());
+    let group = git_refspec::MatchGroup::from_fetch_specs(fetch_specs.iter().map(|s| s.to_ref()));
+    let outcome = group.match_remotes(updates.iter().map(|u| git_refspec::match_group::Item {
+        full_ref_name: u.remote_ref.as_ref(),
+        target: u.new_id.as_ref().map(|id| id.as_ref()).unwrap_or(&null),
+        object: None,
+    }));
+
+    let mut edits = Vec::new();
+    for mapping in outcome.mappings {
+        let (update, local) = match (mapping.item_index, mapping.rhs) {
+            (Some(idx), Some(local)) => (updates[idx], local),
+            _ => continue,
+        };
+        let name: git_ref::FullName = match local.as_ref().try_into() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        let change = match update.new_id {
+            Some(new) => Change::Update {
+                log: LogChange {
+                    mode: RefLog::AndReference,
+                    force_create_reflog: false,
+                    message: "update by push".into(),
+                },
+                expected: PreviousValue::Any,
+                new: Target::Peeled(new),
+            },
+            None => {
+                if repo.try_find_reference(name.as_ref())?.is_none() {
+                    continue;
+                }
+                Change::Delete {
+                    expected: PreviousValue::Any,
+                    log: RefLog::AndReference,
+                }
+            }
+        };
+        edits.push(RefEdit {
+            change,
+            name,
+            deref: false,
+        });
+    }
+    Ok(repo.edit_references(edits)?)
+}
@@ -0,0 +1,245 @@
+#![allow(clippy::result_large_err)]
+use std::collections::BTreeMap;
+
+use git_hash::ObjectId;
+use git_odb::Find;
+use git_ref::{file::ReferenceExt, TargetRef};
+use git_refspec::{instruction::Push, Instruction};
+
+use crate::{
+    bstr::{BStr, BString, ByteSlice, ByteVec},
+    remote::push::{Error, Mode, Update},
+    Repository,
+};
+
+/// A reference update to perform on the remote, before it was classified.
+struct Candidate {
+    spec_index: usize,
+    remote_ref: BString,
+    /// The local reference and the object to set `remote_ref` to, or `None` for deletions.
+    source: Option<(Option<BString>, ObjectId)>,
+    allow_non_fast_forward: bool,
+}
+
+/// Turn the push `specs` into updates of `remote_refs`, rejecting those that aren't allowed.
+pub(crate) fn compute(
+    repo: &Repository,
+    remote_refs: &[git_protocol::handshake::Ref],
+    specs: &[git_refspec::RefSpec],
+) -> Result<Vec<Update>, Error> {
+    let remote: BTreeMap<&BStr, ObjectId> = remote_refs
+        .iter()
+        .filter_map(|r| {
+            let (name, target, _peeled) = r.unpack();
+            target.map(|id| (name, id.to_owned()))
+        })
+        .collect();
+    let local = local_refs(repo)?;
+
+    let mut candidates = Vec::<Candidate>::new();
+    for (spec_index, spec) in specs.iter().enumerate() {
+        let spec = spec.to_ref();
+        match spec.instruction() {
+            Instruction::Push(Push::Matching {
+                src,
+                dst,
+                allow_non_fast_forward,
+            }) => match (split_pattern(src), split_pattern(dst)) {
+                (Some((src_prefix, src_suffix)), Some((dst_prefix, dst_suffix))) => {
+                    for (name, id) in &local {
+                        if let Some(middle) = match_pattern(name.as_ref(), src_prefix, src_suffix) {
+                            candidates.push(Candidate {
+                                spec_index,
+                                remote_ref: substitute(dst_prefix, middle, dst_suffix),
+                                source: Some((Some(name.clone()), *id)),
+                                allow_non_fast_forward,
+                            })
+                        }
+                    }
+                }
+                _ => {
+                    let (local_ref, id) = resolve_source(repo, src)?;
+                    let remote_ref = match qualify_remote_name(&remote, dst) {
+                        Some(name) => name,
+                        None => match local_ref.as_ref() {
+                            Some(local_ref) if dst == src => local_ref.clone(),
+                            Some(local_ref)
+                                if local_ref.starts_with(b"refs/heads/") || local_ref.starts_with(b"refs/tags/") =>
+                            {
+                                let category_len = local_ref[5..].find_byte(b'/').expect("present") + 6;
+                                let mut name: BString = local_ref[..category_len].into();
+                                name.push_str(dst);
+                                name
+                            }
+                            _ => {
+                                return Err(Error::UnqualifiedDestination {
+                                    name: dst.into(),
+                                    spec: spec.to_bstring(),
+                                })
+                            }
+                        },
+                    };
+                    candidates.push(Candidate {
+                        spec_index,
+                        remote_ref,
+                        source: Some((local_ref, id)),
+                        allow_non_fast_forward,
+                    })
+                }
+            },
+            Instruction::Push(Push::Delete { ref_or_pattern }) => match split_pattern(ref_or_pattern) {
+                Some((prefix, suffix)) => {
+                    for name in remote.keys() {
+                        if match_pattern(name, prefix, suffix).is_some() {
+                            candidates.push(Candidate {
+                                spec_index,
+                                remote_ref: (*name).to_owned(),
+                                source: None,
+                                allow_non_fast_forward: false,
+                            })
+                        }
+                    }
+                }
+                None => candidates.push(Candidate {
+                    spec_index,
+                    remote_ref: qualify_remote_name(&remote, ref_or_pattern).unwrap_or_else(|| ref_or_pattern.into()),
+                    source: None,
+                    allow_non_fast_forward: false,
+                }),
+            },
+            Instruction::Push(Push::AllMatchingBranches { allow_non_fast_forward }) => {
+                for (name, id) in &local {
+                    if name.starts_with(b"refs/heads/") && remote.contains_key(name.as_bstr()) {
+                        candidates.push(Candidate {
+                            spec_index,
+                            remote_ref: name.clone(),
+                            source: Some((Some(name.clone()), *id)),
+                            allow_non_fast_forward,
+                        })
+                    }
+                }
+            }
+            Instruction::Fetch(_) => unreachable!("push specs are parsed for pushing"),
+        }
+    }
+
+    let mut updates = Vec::<Update>::with_capacity(candidates.len());
+    for candidate in candidates {
+        let new_id = candidate.source.as_ref().map(|(_, id)| *id);
+        if let Some(existing) = updates.iter().find(|u| u.remote_ref == candidate.remote_ref) {
+            if existing.new_id == new_id {
+                continue;
+            }
+            return Err(Error::DuplicateDestination {
+                name: candidate.remote_ref,
+            });
+        }
+        let previous_id = remote.get(candidate.remote_ref.as_bstr()).copied();
+        let mode = match (previous_id, new_id) {
+            (None, None) => Mode::RejectedRemoteRefDoesNotExist,
+            (Some(_), None) => Mode::Delete,
+            (None, Some(_)) => Mode::New,
+            (Some(previous), Some(new)) => classify(
+                repo,
+                candidate.remote_ref.as_ref(),
+                previous,
+                new,
+                candidate.allow_non_fast_forward,
+            )?,
+        };
+        updates.push(Update {
+            remote_ref: candidate.remote_ref,
+            local_ref: candidate.source.and_then(|(local_ref, _)| local_ref),
+            previous_id,
+            new_id,
+            mode,
+            spec_index: candidate.spec_index,
+        });
+    }
+    Ok(updates)
+}
+
+fn classify(
+    repo: &Repository,
+    remote_ref: &BStr,
+    previous: ObjectId,
+    new: ObjectId,
+    allow_non_fast_forward: bool,
+) -> Result<Mode, Error> {
+    let forced_or = |rejected: Mode| if allow_non_fast_forward { Mode::Forced } else { rejected };
+    Ok(if previous == new {
+        Mode::UpToDate
+    } else if remote_ref.starts_with(b"refs/tags/") {
+        forced_or(Mode::RejectedAlreadyExists)
+    } else if !repo.objects.contains(previous) {
+        forced_or(Mode::RejectedFetchFirst)
+    } else if repo.is_ancestor(previous, new)? {
+        Mode::FastForward
+    } else {
+        forced_or(Mode::RejectedNonFastForward)
+    })
+}
+
+/// Return all local references which point to an object directly, along with that object.
+fn local_refs(repo: &Repository) -> Result<Vec<(BString, ObjectId)>, Error> {
+    let mut out = Vec::new();
+    for r in repo.references()?.all()? {
+        let r = r.map_err(Error::IterReferencesNext)?;
+        if let TargetRef::Peeled(id) = r.target() {
+            out.push((r.name().as_bstr().to_owned(), id.to_owned()));
+        }
+    }
+    Ok(out)
+}
+
+/// Resolve `src` to a local reference, following symbolic references, or to any object if it isn't a reference.
+fn resolve_source(repo: &Repository, src: &BStr) -> Result<(Option<BString>, ObjectId), Error> {
+    if let Ok(Some(mut r)) = repo.try_find_reference(src) {
+        while let git_ref::Target::Symbolic(name) = &r.inner.target {
+            match repo.try_find_reference(name.as_ref())? {
+                Some(next) => r = next,
+                None => break,
+            }
+        }
+        if let git_ref::Target::Peeled(id) = r.inner.target {
+            return Ok((Some(r.inner.name.as_bstr().to_owned()), id));
+        }
+    }
+    let id = repo.rev_parse_single(src).map_err(|err| Error::ResolveSource {
+        spec: src.into(),
+        source: err,
+    })?;
+    Ok((None, id.detach()))
+}
+
+/// Return the full name of the remote reference `name` refers to, possibly by expanding it like `git` would if it's partial.
+fn qualify_remote_name(remote: &BTreeMap<&BStr, ObjectId>, name: &BStr) -> Option<BString> {
+    if name.starts_with(b"refs/") {
+        return Some(name.into());
+    }
+    ["", "refs/", "refs/tags/", "refs/heads/", "refs/remotes/"]
+        .iter()
+        .map(|prefix| {
+            let mut full_name: BString = (*prefix).into();
+            full_name.push_str(name);
+            full_name
+        })
+        .find(|full_name| remote.contains_key(full_name.as_bstr()))
+}
+
+fn split_pattern(name: &BStr) -> Option<(&BStr, &BStr)> {
+    let pos = name.find_byte(b'*')?;
+    Some((name[..pos].as_bstr(), name[pos + 1..].as_bstr()))
+}
+
+fn match_pattern<'a>(name: &'a BStr, prefix: &BStr, suffix: &BStr) -> Option<&'a BStr> {
+    (name.len() >= prefix.len() + suffix.len() && name.starts_with(prefix) && name.ends_with(suffix))
+        .then(|| name[prefix.len()..name.len() - suffix.len()].as_bstr())
+}
+
+fn substitute(prefix: &BStr, middle: &BStr, suffix: &BStr) -> BString {
+    let mut out: BString = prefix.into();
+    out.push_str(middle);
+    out.push_str(suffix);
+    out
+}
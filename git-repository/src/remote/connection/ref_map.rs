@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 
 use git_features::progress::Progress;
-use git_protocol::transport::client::Transport;
+use git_protocol::transport::{client::Transport, Service};
 
 use crate::{
     bstr,
@@ -180,37 +180,9 @@ where
         extra_parameters: Vec<(String, Option<String>)>,
         refspecs: &[git_refspec::RefSpec],
     ) -> Result<HandshakeWithRefs, Error> {
-        let mut credentials_storage;
-        let url = self.transport.to_url();
-        let authenticate = match self.authenticate.as_mut() {
-            Some(f) => f,
-            None => {
-                let url = self
-                    .remote
-                    .url(Direction::Fetch)
-                    .map(ToOwned::to_owned)
-                    .unwrap_or_else(|| git_url::parse(url.as_ref()).expect("valid URL to be provided by transport"));
-                credentials_storage = self.configured_credentials(url)?;
-                &mut credentials_storage
-            }
-        };
-
-        if self.transport_options.is_none() {
-            self.transport_options = self
-                .remote
-                .repo
-                .transport_options(url.as_ref(), self.remote.name().map(|n| n.as_bstr()))
-                .map_err(|err| Error::GatherTransportConfig {
-                    source: err,
-                    url: url.into_owned(),
-                })?;
-        }
-        if let Some(config) = self.transport_options.as_ref() {
-            self.transport.configure(&**config)?;
-        }
-        let mut outcome =
-            git_protocol::fetch::handshake(&mut self.transport, authenticate, extra_parameters, &mut self.progress)
-                .await?;
+        let mut outcome = self
+            .handshake(Service::UploadPack, Direction::Fetch, extra_parameters)
+            .await?;
         let refs = match outcome.refs.take() {
             Some(refs) => refs,
             None => {
@@ -245,9 +217,61 @@ where
     }
 }
 
+impl<'remote, 'repo, T, P> Connection<'remote, 'repo, T, P>
+where
+    T: Transport,
+    P: Progress,
+{
+    /// Configure the transport and credentials for `direction` and perform the handshake for `service`.
+    #[allow(clippy::result_large_err)]
+    #[git_protocol::maybe_async::maybe_async]
+    pub(crate) async fn handshake(
+        &mut self,
+        service: Service,
+        direction: Direction,
+        extra_parameters: Vec<(String, Option<String>)>,
+    ) -> Result<git_protocol::handshake::Outcome, Error> {
+        let mut credentials_storage;
+        let url = self.transport.to_url();
+        let authenticate = match self.authenticate.as_mut() {
+            Some(f) => f,
+            None => {
+                let url =
+                    self.remote.url(direction).map(ToOwned::to_owned).unwrap_or_else(|| {
+                        git_url::parse(url.as_ref()).expect("valid URL to be provided by transport")
+                    });
+                credentials_storage = self.configured_credentials(url)?;
+                &mut credentials_storage
+            }
+        };
+
+        if self.transport_options.is_none() {
+            self.transport_options = self
+                .remote
+                .repo
+                .transport_options(url.as_ref(), self.remote.name().map(|n| n.as_bstr()))
+                .map_err(|err| Error::GatherTransportConfig {
+                    source: err,
+                    url: url.into_owned(),
+                })?;
+        }
+        if let Some(config) = self.transport_options.as_ref() {
+            self.transport.configure(&**config)?;
+        }
+        Ok(git_protocol::handshake(
+            &mut self.transport,
+            service,
+            authenticate,
+            extra_parameters,
+            &mut self.progress,
+        )
+        .await?)
+    }
+}
+
 /// Assume sha1 if server says nothing, otherwise configure anything beyond sha1 in the local repo configuration
 #[allow(clippy::result_large_err)]
-fn extract_object_format(
+pub(crate) fn extract_object_format(
     _repo: &crate::Repository,
     outcome: &git_protocol::handshake::Outcome,
 ) -> Result<git_hash::Kind, Error> {
@@ -259,6 +283,7 @@ fn extract_object_format(
             })?;
             match object_format {
                 "sha1" => git_hash::Kind::Sha1,
+                "sha256" => git_hash::Kind::Sha256,
                 unknown => return Err(Error::UnknownObjectFormat { format: unknown.into() }),
             }
         } else {
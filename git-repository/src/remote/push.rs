@@ -0,0 +1,71 @@
+use crate::bstr::BString;
+
+/// Information about the update of a single reference on the remote, as derived from the push refspecs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Update {
+    /// The full name of the reference on the remote.
+    pub remote_ref: BString,
+    /// The full name of the local reference we pushed from, or `None` if the source was an object or the update is a deletion.
+    pub local_ref: Option<BString>,
+    /// The value of the remote reference as advertised by the server, or `None` if it didn't exist yet.
+    pub previous_id: Option<git_hash::ObjectId>,
+    /// The value the remote reference should have after the push, or `None` if it is to be deleted.
+    pub new_id: Option<git_hash::ObjectId>,
+    /// The way the update was performed or the reason for it to be rejected.
+    pub mode: Mode,
+    /// The index into the push refspecs of the remote that produced this update.
+    pub spec_index: usize,
+}
+
+/// The way a single reference on the remote is updated, or the reason for it to be rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    /// The remote reference doesn't exist yet and will be created.
+    New,
+    /// The remote reference is an ancestor of the new value.
+    FastForward,
+    /// The remote reference isn't an ancestor of the new value, but the update is forced.
+    Forced,
+    /// The remote reference will be deleted.
+    Delete,
+    /// The remote reference already has the desired value, nothing is sent for it.
+    UpToDate,
+    /// The update was rejected as the new value isn't a descendant of the remote reference, and the update wasn't forced.
+    RejectedNonFastForward,
+    /// The update was rejected as the object of the remote reference isn't present locally, and the update wasn't forced.
+    ///
+    /// The remote should be fetched first to learn if the update would be a fast-forward.
+    RejectedFetchFirst,
+    /// The update was rejected as a tag with that name already exists on the remote, and the update wasn't forced.
+    RejectedAlreadyExists,
+    /// The deletion was rejected as the remote reference doesn't exist, similar to `git` refusing it with `remote ref does not exist`.
+    RejectedRemoteRefDoesNotExist,
+    /// The update was sent, but the remote refused to perform it for the given `reason`.
+    RejectedByRemote {
+        /// The reason as provided by the remote, like `non-fast-forward` or `deny updating a hidden ref`.
+        reason: BString,
+    },
+}
+
+impl Mode {
+    /// Return true if the update was rejected, either by us or by the remote.
+    pub fn is_rejected(&self) -> bool {
+        matches!(
+            self,
+            Mode::RejectedNonFastForward
+                | Mode::RejectedFetchFirst
+                | Mode::RejectedAlreadyExists
+                | Mode::RejectedRemoteRefDoesNotExist
+                | Mode::RejectedByRemote { .. }
+        )
+    }
+
+    /// Return true if a command has to be sent to the remote for this update.
+    #[cfg(any(feature = "blocking-network-client", feature = "async-network-client"))]
+    pub(crate) fn needs_command(&self) -> bool {
+        matches!(self, Mode::New | Mode::FastForward | Mode::Forced | Mode::Delete)
+    }
+}
+
+#[cfg(any(feature = "blocking-network-client", feature = "async-network-client"))]
+pub use super::connection::push::{prepare, Error, Outcome, Prepare};
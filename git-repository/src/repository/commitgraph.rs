@@ -0,0 +1,134 @@
+use std::sync::atomic::AtomicBool;
+
+use git_commitgraph::{bloom, file::write::Commit, graph::write, Graph};
+use git_hashtable::HashSet;
+
+use crate::{
+    bstr::BString,
+    commitgraph,
+    object::tree::diff::{for_each, Action},
+    Progress,
+};
+
+impl crate::Repository {
+    /// The directory in which the commit-graph of this repository is stored, as single `commit-graph` file or as
+    /// `commit-graphs` directory of a split commit-graph, which may or may not exist.
+    pub fn commit_graph_dir(&self) -> std::path::PathBuf {
+        self.objects.store_ref().path().join("info")
+    }
+
+    /// Open the commit-graph of this repository, which fails if there is none.
+    ///
+    /// It's used to accelerate traversals, but doesn't have to contain all commits as it's usually updated only occasionally.
+    pub fn commit_graph(&self) -> Result<Graph, git_commitgraph::graph::Error> {
+        Graph::from_info_dir(self.commit_graph_dir())
+    }
+
+    /// Add all commits reachable from `tips` to the commit-graph of this repository, written as configured in `options`,
+    /// and return information about the written file or `None` if nothing was written.
+    ///
+    /// Tips that are tags are peeled, and those that don't point to a commit are ignored. The traversal stops at commits
+    /// that are already part of the existing commit-graph, which makes adding new commits after a fetch cheap.
+    /// Progress is sent to `progress` and interruptions checked via `should_interrupt`.
+    ///
+    /// Changed-path Bloom filters are computed for all new commits if [requested][commitgraph::write::Options::changed_paths]
+    /// or if the existing commit-graph has them already, similar to `git`.
+    ///
+    /// Note that just like `git`, no commit-graph is written in [shallow repositories][crate::Repository::is_shallow()]
+    /// as it would contain incorrect generation numbers.
+    pub fn write_commit_graph(
+        &self,
+        tips: impl IntoIterator<Item = impl Into<git_hash::ObjectId>>,
+        options: commitgraph::write::Options,
+        progress: impl Progress,
+        should_interrupt: &AtomicBool,
+    ) -> Result<Option<write::Outcome>, commitgraph::write::Error> {
+        if self.shallow_commits()?.is_some() {
+            return Ok(None);
+        }
+        let info_dir = self.commit_graph_dir();
+        let existing = Graph::from_info_dir(&info_dir).ok();
+        let changed_paths = options.changed_paths
+            || existing
+                .as_ref()
+                .map_or(false, |graph| graph.bloom_filter_settings().is_some());
+
+        let mut seen = HashSet::default();
+        let mut queue = Vec::new();
+        for tip in tips {
+            let object = self.find_object(tip.into())?.peel_tags_to_end()?;
+            if object.kind == git_object::Kind::Commit && seen.insert(object.id) {
+                queue.push(object.id);
+            }
+        }
+
+        let mut commits = Vec::new();
+        while let Some(id) = queue.pop() {
+            if existing.as_ref().map_or(false, |graph| graph.lookup(id).is_some()) {
+                continue;
+            }
+            let object = self.find_object(id)?;
+            let commit = git_object::CommitRef::from_bytes(&object.data)
+                .map_err(|err| commitgraph::write::Error::DecodeCommit { source: err, id })?;
+            let parents: Vec<_> = commit.parents().collect();
+            queue.extend(parents.iter().copied().filter(|parent| seen.insert(*parent)));
+            let bloom_filter = changed_paths
+                .then(|| self.changed_paths_bloom_filter(commit.tree(), parents.first().copied()))
+                .transpose()?;
+            commits.push(Commit {
+                id,
+                tree: commit.tree(),
+                parents,
+                committer_timestamp: commit.committer.time.seconds_since_unix_epoch.into(),
+                bloom_filter,
+            });
+        }
+
+        Ok(Graph::write_to_info_dir(
+            info_dir,
+            commits,
+            progress,
+            should_interrupt,
+            write::Options {
+                mode: options.mode,
+                object_hash: self.object_hash(),
+            },
+        )?)
+    }
+
+    /// Compute the Bloom filter for the paths changed in `tree` compared to the tree of `first_parent`, or to the empty tree
+    /// if there is no parent.
+    fn changed_paths_bloom_filter(
+        &self,
+        tree: git_hash::ObjectId,
+        first_parent: Option<git_hash::ObjectId>,
+    ) -> Result<Vec<u8>, commitgraph::write::Error> {
+        let tree = self.find_object(tree)?.peel_to_tree()?;
+        let parent_tree = match first_parent {
+            Some(parent) => self.find_object(parent)?.peel_to_tree()?,
+            None => self.empty_tree(),
+        };
+        let mut paths = Vec::<BString>::new();
+        let res = parent_tree
+            .changes()
+            .map_err(Box::new)?
+            .track_path()
+            .track_rewrites(None)
+            .for_each_to_obtain_tree(&tree, |change| {
+                paths.push(change.location.to_owned());
+                Ok::<_, std::convert::Infallible>(if paths.len() > bloom::MAX_CHANGED_PATHS {
+                    Action::Cancel
+                } else {
+                    Action::Continue
+                })
+            });
+        match res {
+            Ok(()) | Err(for_each::Error::Diff(git_diff::tree::changes::Error::Cancelled)) => {}
+            Err(err) => return Err(err.into()),
+        }
+        Ok(bloom::compute_filter(
+            paths.iter().map(AsRef::as_ref),
+            &bloom::Settings::default(),
+        ))
+    }
+}
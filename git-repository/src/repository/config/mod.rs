@@ -188,4 +188,29 @@ impl crate::Repository {
             })
             .unwrap_or_default()
     }
+
+    /// Replace all local and API configuration with the local `config` which was just written to disk, re-applying the
+    /// API configuration overrides, and re-read all cached values.
+    pub(crate) fn replace_changed_local_config_file(&mut self, mut config: git_config::File<'static>) {
+        let repo_config = git_features::threading::OwnShared::make_mut(&mut self.config.resolved);
+        let ids_to_remove: Vec<_> = repo_config
+            .sections_and_ids()
+            .filter_map(|(s, id)| {
+                matches!(s.meta().source, git_config::Source::Local | git_config::Source::Api).then(|| id)
+            })
+            .collect();
+        for id in ids_to_remove {
+            repo_config.remove_section_by_id(id);
+        }
+        crate::config::overrides::append(
+            &mut config,
+            &self.options.api_config_overrides,
+            git_config::Source::Api,
+            |_| None,
+        )
+        .expect("applied once and can be applied again");
+        repo_config.append(config);
+        self.reread_values_and_clear_caches()
+            .expect("values could be read once and can be read again");
+    }
 }
@@ -0,0 +1,145 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+};
+
+use git_ref::{
+    transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog},
+    Target,
+};
+
+use crate::bstr::BString;
+
+/// Hooks
+impl crate::Repository {
+    /// Return the path to the executable hook called `name`, like `reference-transaction`, if it exists and if the repository is
+    /// trusted enough to execute it.
+    ///
+    /// Hooks are looked up in the directory configured with `core.hooksPath`, or in the `hooks` directory of the common directory.
+    pub(crate) fn hook_path(&self, name: &str) -> Result<Option<PathBuf>, git_config::path::interpolate::Error> {
+        if self.git_dir_trust() != git_sec::Trust::Full {
+            return Ok(None);
+        }
+        let path = match self.config.hooks_path().transpose()? {
+            Some(dir) => self.hook_working_dir().join(dir),
+            None => self.common_dir().join("hooks"),
+        }
+        .join(name);
+        Ok(std::fs::metadata(&path)
+            .ok()
+            .filter(|meta| meta.is_file() && is_executable(meta))
+            .map(|_| path))
+    }
+
+    /// Run the `hook` at the given path with `arg` as its only argument and `stdin` as its input, and wait for it to complete.
+    ///
+    /// Just like in `git`, it runs in the working tree or in the `git` directory of bare repositories, with `GIT_DIR` set.
+    /// On platforms other than unix, the hook is run by `sh` just like `git` for windows does.
+    pub(crate) fn run_hook(&self, hook: &Path, arg: &str, stdin: &[u8]) -> std::io::Result<ExitStatus> {
+        let git_dir = std::env::current_dir()?.join(self.git_dir());
+        let mut child = hook_command(hook)
+            .arg(arg)
+            .current_dir(self.hook_working_dir())
+            .env("GIT_DIR", git_dir)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        let res = child.stdin.take().expect("configured").write_all(stdin);
+        let status = child.wait()?;
+        match res {
+            // The hook doesn't have to read all of its input.
+            Err(err) if err.kind() != std::io::ErrorKind::BrokenPipe => Err(err),
+            _ => Ok(status),
+        }
+    }
+
+    fn hook_working_dir(&self) -> &Path {
+        self.work_dir().unwrap_or_else(|| self.git_dir())
+    }
+}
+
+/// Produce the input of the `reference-transaction` hook from the `edits` of a prepared transaction, which is one
+/// `<old-value> SP <new-value> SP <ref-name> LF` line for each reference that is changed.
+///
+/// Missing values are represented by the null hash, and symbolic references by `ref:<target>`.
+pub(crate) fn reference_transaction_input<'a>(
+    edits: impl IntoIterator<Item = &'a RefEdit>,
+    object_hash: git_hash::Kind,
+) -> BString {
+    let null = Target::Peeled(object_hash.null());
+    let mut out = BString::default();
+    let mut push_line = |previous: Option<&Target>, new: Option<&Target>, name: &git_ref::FullName| {
+        for value in [previous.unwrap_or(&null), new.unwrap_or(&null)] {
+            match value {
+                Target::Peeled(id) => out.extend_from_slice(id.to_hex().to_string().as_bytes()),
+                Target::Symbolic(name) => {
+                    out.extend_from_slice(b"ref:");
+                    out.extend_from_slice(name.as_bstr());
+                }
+            }
+            out.push(b' ');
+        }
+        out.extend_from_slice(name.as_bstr());
+        out.push(b'\n');
+    };
+    for edit in edits {
+        let previous = match &edit.change {
+            Change::Update {
+                expected: PreviousValue::MustExistAndMatch(previous),
+                ..
+            }
+            | Change::Delete {
+                expected: PreviousValue::MustExistAndMatch(previous),
+                ..
+            } => Some(previous),
+            _ => None,
+        };
+        match &edit.change {
+            Change::Update {
+                log: LogChange { mode: RefLog::Only, .. },
+                ..
+            }
+            | Change::Delete { log: RefLog::Only, .. } => {}
+            Change::Update { new, .. } => push_line(previous, Some(new), &edit.name),
+            Change::Delete { .. } => push_line(previous, None, &edit.name),
+            Change::Rename {
+                source, expected, copy, ..
+            } => {
+                let target = match expected {
+                    PreviousValue::MustExistAndMatch(target) => Some(target),
+                    _ => None,
+                };
+                push_line(None, target, &edit.name);
+                if !copy {
+                    push_line(target, None, source);
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(unix)]
+fn is_executable(meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    meta.mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_meta: &std::fs::Metadata) -> bool {
+    // There is no executable bit, and hooks are run with `sh` anyway.
+    true
+}
+
+#[cfg(unix)]
+fn hook_command(hook: &Path) -> Command {
+    Command::new(hook)
+}
+
+#[cfg(not(unix))]
+fn hook_command(hook: &Path) -> Command {
+    // Hooks typically are shell scripts which can't be executed directly here.
+    let mut cmd = Command::new("sh");
+    cmd.arg(hook);
+    cmd
+}
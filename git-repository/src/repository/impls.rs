@@ -2,6 +2,7 @@ impl Clone for crate::Repository {
     fn clone(&self) -> Self {
         crate::Repository::from_refs_and_objects(
             self.refs.clone(),
+            self.reftable.clone(),
             self.objects.clone(),
             self.work_tree.clone(),
             self.common_dir.clone(),
@@ -34,6 +35,7 @@ impl From<&crate::ThreadSafeRepository> for crate::Repository {
     fn from(repo: &crate::ThreadSafeRepository) -> Self {
         crate::Repository::from_refs_and_objects(
             repo.refs.clone(),
+            repo.reftable.clone(),
             repo.objects.to_handle().into(),
             repo.work_tree.clone(),
             repo.common_dir.clone(),
@@ -48,6 +50,7 @@ impl From<crate::ThreadSafeRepository> for crate::Repository {
     fn from(repo: crate::ThreadSafeRepository) -> Self {
         crate::Repository::from_refs_and_objects(
             repo.refs,
+            repo.reftable,
             repo.objects.to_handle().into(),
             repo.work_tree,
             repo.common_dir,
@@ -62,6 +65,7 @@ impl From<crate::Repository> for crate::ThreadSafeRepository {
     fn from(r: crate::Repository) -> Self {
         crate::ThreadSafeRepository {
             refs: r.refs,
+            reftable: r.reftable,
             objects: r.objects.into_inner().store(),
             work_tree: r.work_tree,
             common_dir: r.common_dir,
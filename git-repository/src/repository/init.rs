@@ -1,8 +1,10 @@
 use std::cell::RefCell;
 
 impl crate::Repository {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn from_refs_and_objects(
         refs: crate::RefStore,
+        reftable: Option<git_ref::reftable::Store>,
         objects: crate::OdbHandle,
         work_tree: Option<std::path::PathBuf>,
         common_dir: Option<std::path::PathBuf>,
@@ -17,6 +19,7 @@ impl crate::Repository {
             common_dir,
             objects,
             refs,
+            reftable,
             config,
             options: linked_worktree_options,
             index,
@@ -20,7 +20,9 @@ impl crate::Repository {
 }
 
 mod cache;
+mod commitgraph;
 mod config;
+pub(crate) mod hook;
 pub(crate) mod identity;
 mod impls;
 mod init;
@@ -30,6 +32,7 @@ pub(crate) mod permissions;
 mod reference;
 mod remote;
 mod revision;
+mod shallow;
 mod snapshots;
 mod state;
 mod thread_safe;
@@ -211,4 +211,12 @@ impl crate::Repository {
             .expect("always present")
             .into_tree()
     }
+
+    /// Return the time in seconds since unix epoch before which unreachable objects expire as configured by `gc.pruneExpire`,
+    /// or `None` if they never expire.
+    ///
+    /// Just like in `git`, unreachable objects expire after two weeks if the key isn't set.
+    pub fn prune_expire(&self) -> Result<Option<u32>, crate::config::time::Error> {
+        self.config.prune_expire()
+    }
 }
@@ -1,12 +1,17 @@
-use std::convert::TryInto;
+use std::{borrow::Cow, convert::TryInto};
 
 use git_hash::ObjectId;
 use git_ref::{
+    file::transaction::PackedRefs,
     transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog},
-    FullName, PartialNameRef, Target,
+    Category, FullName, PartialNameRef, Target,
 };
 
-use crate::{bstr::BString, ext::ReferenceExt, reference, Reference};
+use crate::{
+    bstr::{BStr, BString},
+    ext::ReferenceExt,
+    reference, Reference,
+};
 
 /// Obtain and alter references comfortably
 impl crate::Repository {
@@ -114,6 +119,153 @@ impl crate::Repository {
         .attach(self))
     }
 
+    /// Atomically rename the reference `source` to `name`, like `refs/heads/main` to `refs/heads/trunk`, along with its reflog, and
+    /// write `log_message` into the reflog of the renamed reference.
+    ///
+    /// If `HEAD` points to `source`, it will be changed to point to `name` instead. If both are local branches, the
+    /// `branch.<name>` configuration sections in the repository-local configuration file are renamed as well.
+    /// The operation fails if `name` already exists or clashes with an existing reference, like `refs/heads/a/b` with `refs/heads/a`.
+    ///
+    /// The renamed reference is returned.
+    pub fn rename_reference<Name, E>(
+        &mut self,
+        source: Name,
+        name: Name,
+        log_message: impl Into<BString>,
+    ) -> Result<Reference<'_>, reference::rename::Error>
+    where
+        Name: TryInto<FullName, Error = E>,
+        git_validate::reference::name::Error: From<E>,
+    {
+        self.rename_or_copy_reference(
+            source.try_into().map_err(git_validate::reference::name::Error::from)?,
+            name.try_into().map_err(git_validate::reference::name::Error::from)?,
+            log_message.into(),
+            false,
+        )
+    }
+
+    /// Like [`rename_reference()`][Self::rename_reference()], but keeps the `source` reference, its reflog and its
+    /// branch configuration, while `HEAD` is left unchanged.
+    ///
+    /// The newly created copy is returned.
+    pub fn copy_reference<Name, E>(
+        &mut self,
+        source: Name,
+        name: Name,
+        log_message: impl Into<BString>,
+    ) -> Result<Reference<'_>, reference::rename::Error>
+    where
+        Name: TryInto<FullName, Error = E>,
+        git_validate::reference::name::Error: From<E>,
+    {
+        self.rename_or_copy_reference(
+            source.try_into().map_err(git_validate::reference::name::Error::from)?,
+            name.try_into().map_err(git_validate::reference::name::Error::from)?,
+            log_message.into(),
+            true,
+        )
+    }
+
+    fn rename_or_copy_reference(
+        &mut self,
+        source: FullName,
+        name: FullName,
+        message: BString,
+        copy: bool,
+    ) -> Result<Reference<'_>, reference::rename::Error> {
+        let mut edits = vec![RefEdit {
+            change: Change::Rename {
+                source: source.clone(),
+                expected: PreviousValue::MustExist,
+                copy,
+                message,
+            },
+            name: name.clone(),
+            deref: false,
+        }];
+        if !copy && self.head_name()?.as_ref() == Some(&source) {
+            edits.push(RefEdit {
+                change: Change::Update {
+                    log: Default::default(),
+                    expected: PreviousValue::MustExistAndMatch(Target::Symbolic(source.clone())),
+                    new: Target::Symbolic(name.clone()),
+                },
+                name: "HEAD".try_into().expect("valid"),
+                deref: false,
+            });
+        }
+        // Lock the configuration first so the branch configuration can't fail to move along once the references are changed.
+        let branch_config = match (source.category_and_short_name(), name.category_and_short_name()) {
+            (Some((Category::LocalBranch, source_short)), Some((Category::LocalBranch, short))) => {
+                self.prepare_branch_config_rename_or_copy(source_short, short.to_owned(), copy)?
+            }
+            _ => None,
+        };
+        let target = self.edit_references(edits)?[0]
+            .change
+            .new_value()
+            .expect("renames always know their target")
+            .into_owned();
+
+        if let Some((mut lock, config)) = branch_config {
+            config.write_to(&mut lock)?;
+            lock.commit().map_err(|err| err.error)?;
+            self.replace_changed_local_config_file(config);
+        }
+
+        Ok(git_ref::Reference {
+            name,
+            target,
+            peeled: None,
+        }
+        .attach(self))
+    }
+
+    /// Lock the local configuration file and return it along with a copy of its contents in which all `branch.<source>` sections
+    /// are moved or copied to `branch.<name>`, or `None` if there is no such section.
+    ///
+    /// Just like in `git`, the sections are renamed or copied as a whole, keeping comments and keys without value intact.
+    fn prepare_branch_config_rename_or_copy(
+        &self,
+        source: &BStr,
+        name: BString,
+        copy: bool,
+    ) -> Result<Option<(git_lock::File, git_config::File<'static>)>, reference::rename::Error> {
+        let config_path = self.common_dir().join("config");
+        let lock =
+            git_lock::File::acquire_to_update_resource(&config_path, git_lock::acquire::Fail::Immediately, None)?;
+        let mut metadata = git_config::file::Metadata::from(git_config::Source::Local);
+        metadata.path = Some(config_path);
+        let mut buf = Vec::new();
+        let mut config =
+            match git_config::File::from_paths_metadata_buf(Some(metadata), &mut buf, false, Default::default())? {
+                Some(config) => config,
+                None => return Ok(None),
+            };
+        let sections: Vec<_> = config
+            .sections_by_name("branch")
+            .into_iter()
+            .flatten()
+            .filter(|section| section.header().subsection_name() == Some(source))
+            .cloned()
+            .collect();
+        if sections.is_empty() {
+            return Ok(None);
+        }
+        let num_sections = sections.len();
+        if copy {
+            for section in sections {
+                config.push_section(section)?;
+            }
+        }
+        // Renaming affects the last matching section, which are the copies we just added if there are any.
+        for _ in 0..num_sections {
+            config.rename_section("branch", Some(source), "branch", Some(Cow::Owned(name.clone())))?;
+        }
+        Ok(Some((lock, config)))
+    }
+
     /// Edit a single reference as described in `edit`, and write reference logs as `log_committer`.
     ///
     /// One or more `RefEdit`s  are returned - symbolic reference splits can cause more edits to be performed. All edits have the previous
@@ -128,16 +280,93 @@ impl crate::Repository {
     ///
     /// Returns all reference edits, which might be more than where provided due the splitting of symbolic references, and
     /// whose previous (_old_) values are the ones seen on in storage after the reference was locked.
+    ///
+    /// Just like in `git`, the `reference-transaction` hook is invoked once the edits are prepared, which may abort the transaction,
+    /// and once they are committed or aborted. Edits are refused entirely while `GIT_QUARANTINE_PATH` is set, as objects
+    /// in quarantine may be deleted at any time.
     pub fn edit_references(
         &self,
         edits: impl IntoIterator<Item = RefEdit>,
     ) -> Result<Vec<RefEdit>, reference::edit::Error> {
+        self.edit_references_with_packed_refs(edits, PackedRefs::DeletionsOnly)
+    }
+
+    /// Like [`edit_references()`][Self::edit_references()], but with `packed_refs` to control how `packed-refs` are written.
+    pub(crate) fn edit_references_with_packed_refs(
+        &self,
+        edits: impl IntoIterator<Item = RefEdit>,
+        packed_refs: PackedRefs<'_>,
+    ) -> Result<Vec<RefEdit>, reference::edit::Error> {
+        if std::env::var_os("GIT_QUARANTINE_PATH").is_some() {
+            return Err(reference::edit::Error::Quarantine);
+        }
         let (file_lock_fail, packed_refs_lock_fail) = self.config.lock_timeout()?;
-        self.refs
-            .transaction()
-            .prepare(edits, file_lock_fail, packed_refs_lock_fail)?
-            .commit(self.committer().transpose()?)
-            .map_err(Into::into)
+        let committer = self.committer().transpose()?;
+        let hook = self.hook_path("reference-transaction")?;
+        let object_hash = self.object_hash();
+        match &self.reftable {
+            Some(reftable) => {
+                let transaction = reftable.transaction().prepare(edits, file_lock_fail)?;
+                let hook = hook.map(|hook| {
+                    let input = crate::repository::hook::reference_transaction_input(transaction.edits(), object_hash);
+                    (hook, input)
+                });
+                self.commit_with_reference_transaction_hook(hook, move || Ok(transaction.commit(committer)?))
+            }
+            None => {
+                let transaction = self.refs.transaction().packed_refs(packed_refs).prepare(
+                    edits,
+                    file_lock_fail,
+                    packed_refs_lock_fail,
+                )?;
+                let hook = hook.map(|hook| {
+                    let input = crate::repository::hook::reference_transaction_input(transaction.edits(), object_hash);
+                    (hook, input)
+                });
+                self.commit_with_reference_transaction_hook(hook, move || Ok(transaction.commit(committer)?))
+            }
+        }
+    }
+
+    /// Call `commit` to commit a prepared transaction, surrounded by invocations of the `reference-transaction` `hook` along
+    /// with its input, if there is one. If the hook refuses the transaction, `commit` is dropped instead, rolling it back.
+    fn commit_with_reference_transaction_hook(
+        &self,
+        hook: Option<(std::path::PathBuf, BString)>,
+        commit: impl FnOnce() -> Result<Vec<RefEdit>, reference::edit::Error>,
+    ) -> Result<Vec<RefEdit>, reference::edit::Error> {
+        let (hook, input) = match hook {
+            Some(hook) => hook,
+            None => return commit(),
+        };
+        let status = self
+            .run_hook(&hook, "prepared", &input)
+            .map_err(|err| reference::edit::Error::RunHook {
+                path: hook.clone(),
+                source: err,
+            })?;
+        if !status.success() {
+            drop(commit);
+            // Like `git`, we don't care about the outcome of hooks that are merely informed.
+            self.run_hook(&hook, "aborted", &input).ok();
+            return Err(reference::edit::Error::AbortedByHook { status });
+        }
+        let edits = commit()?;
+        self.run_hook(&hook, "committed", &input).ok();
+        Ok(edits)
+    }
+
+    /// Obtain options to expire reference logs as configured by `gc.reflogExpire` and `gc.reflogExpireUnreachable`,
+    /// for use with [`Reference::log_expire()`][crate::Reference::log_expire()].
+    ///
+    /// Just like in `git`, entries expire after 90 days, or after 30 days if they are unreachable, if these keys are not set.
+    pub fn reflog_expire_options(&self) -> Result<git_ref::file::log::expire::Options, crate::config::time::Error> {
+        let (expire_total, expire_unreachable) = self.config.reflog_expire()?;
+        Ok(git_ref::file::log::expire::Options {
+            expire_total,
+            expire_unreachable,
+            ..Default::default()
+        })
     }
 
     /// Return the repository head, an abstraction to help dealing with the `HEAD` reference.
@@ -231,13 +460,13 @@ impl crate::Repository {
         Name: TryInto<&'a PartialNameRef, Error = E>,
         git_ref::file::find::Error: From<E>,
     {
-        let state = self;
-        match state.refs.try_find(name) {
-            Ok(r) => match r {
-                Some(r) => Ok(Some(Reference::from_ref(r, self))),
-                None => Ok(None),
-            },
-            Err(err) => Err(err.into()),
-        }
+        let name = name
+            .try_into()
+            .map_err(|err| reference::find::Error::Find(err.into()))?;
+        let reference = match &self.reftable {
+            Some(reftable) => reftable.try_find::<_, std::convert::Infallible>(name)?,
+            None => self.refs.try_find::<_, std::convert::Infallible>(name)?,
+        };
+        Ok(reference.map(|r| Reference::from_ref(r, self)))
     }
 }
@@ -1,4 +1,6 @@
-use crate::{bstr::BStr, revision, Id};
+use git_odb::FindExt;
+
+use crate::{bstr::BStr, ext::ObjectIdExt, revision, Id};
 
 /// Methods for resolving revisions by spec or working with the commit graph.
 impl crate::Repository {
@@ -39,4 +41,120 @@ impl crate::Repository {
     ) -> revision::walk::Platform<'_> {
         revision::walk::Platform::new(tips, self)
     }
+
+    /// Create the baseline for a revision walk of all commits described by `specs`, similar to `git rev-list <specs>…`,
+    /// which is typically obtained with [`rev_parse()`][crate::Repository::rev_parse()].
+    ///
+    /// Ranges like `A..B` [hide][revision::walk::Platform::hide()] all commits reachable from `A`, and symmetric differences
+    /// like `A...B` hide all commits reachable from the merge-bases of `A` and `B`, while classifying all returned commits by the
+    /// [side][revision::walk::Info::side] they are on. Objects that aren't commits are peeled to commits.
+    pub fn rev_walk_specs(
+        &self,
+        specs: impl IntoIterator<Item = git_revision::Spec>,
+    ) -> Result<revision::walk::Platform<'_>, revision::walk::specs::Error> {
+        let peel_to_commit = |id: git_hash::ObjectId| -> Result<_, revision::walk::specs::Error> {
+            Ok(self
+                .find_object(id)?
+                .peel_to_kind(git_object::Kind::Commit)?
+                .into_commit())
+        };
+        let mut tips = Vec::new();
+        let mut hidden = Vec::new();
+        let mut left = None;
+        for spec in specs {
+            match spec {
+                git_revision::Spec::Include(id) => tips.push(peel_to_commit(id)?.id),
+                git_revision::Spec::Exclude(id) => hidden.push(peel_to_commit(id)?.id),
+                git_revision::Spec::Range { from, to } => {
+                    hidden.push(peel_to_commit(from)?.id);
+                    tips.push(peel_to_commit(to)?.id);
+                }
+                git_revision::Spec::Merge { theirs, ours } => {
+                    let (theirs, ours) = (peel_to_commit(theirs)?.id, peel_to_commit(ours)?.id);
+                    hidden.extend(self.merge_bases(theirs, Some(ours))?.into_iter().map(Id::detach));
+                    tips.push(ours);
+                    left.get_or_insert_with(Vec::new).push(theirs);
+                }
+                git_revision::Spec::IncludeOnlyParents(id) => {
+                    tips.extend(peel_to_commit(id)?.parent_ids().map(Id::detach));
+                }
+                git_revision::Spec::ExcludeParents(id) => {
+                    let commit = peel_to_commit(id)?;
+                    hidden.extend(commit.parent_ids().map(Id::detach));
+                    tips.push(commit.id);
+                }
+            }
+        }
+        let platform = self.rev_walk(tips).hide(hidden);
+        Ok(match left {
+            Some(left) => platform.left_right(left),
+            None => platform,
+        })
+    }
+
+    /// Return the best common ancestor of the commits `one` and `two`, similar to `git merge-base <one> <two>`,
+    /// or `None` if they have no common ancestor.
+    ///
+    /// If there are multiple merge-bases, the one with the newest commit time is returned.
+    /// The [commit-graph][crate::Repository::commit_graph()] is used to speed up the search if present.
+    pub fn merge_base(
+        &self,
+        one: impl Into<git_hash::ObjectId>,
+        two: impl Into<git_hash::ObjectId>,
+    ) -> Result<Option<Id<'_>>, revision::merge_base::Error> {
+        Ok(self.merge_bases(one, Some(two))?.into_iter().next())
+    }
+
+    /// Return all best common ancestors of `one` and a hypothetical merge of all `others`, similar to
+    /// `git merge-base --all <one> <others>…`, newest first.
+    ///
+    /// The list is empty if there is no common ancestor.
+    pub fn merge_bases(
+        &self,
+        one: impl Into<git_hash::ObjectId>,
+        others: impl IntoIterator<Item = impl Into<git_hash::ObjectId>>,
+    ) -> Result<Vec<Id<'_>>, revision::merge_base::Error> {
+        let others: Vec<_> = others.into_iter().map(Into::into).collect();
+        let graph = self.commit_graph().ok();
+        Ok(
+            git_traverse::commit::merge_base::all(one.into(), &others, graph.as_ref(), |oid, buf| {
+                self.objects.find_commit_iter(oid, buf)
+            })?
+            .into_iter()
+            .map(|id| id.attach(self))
+            .collect(),
+        )
+    }
+
+    /// Return `true` if `ancestor` can be reached from `descendant`, similar to `git merge-base --is-ancestor <ancestor> <descendant>`.
+    ///
+    /// Note that each commit is its own ancestor.
+    pub fn is_ancestor(
+        &self,
+        ancestor: impl Into<git_hash::ObjectId>,
+        descendant: impl Into<git_hash::ObjectId>,
+    ) -> Result<bool, revision::merge_base::Error> {
+        let graph = self.commit_graph().ok();
+        git_traverse::commit::merge_base::is_ancestor(ancestor.into(), descendant.into(), graph.as_ref(), |oid, buf| {
+            self.objects.find_commit_iter(oid, buf)
+        })
+    }
+
+    /// Return those of `commits` that can't be reached from any of the others, similar to `git merge-base --independent <commits>…`.
+    ///
+    /// Duplicates are removed, and the order of the remaining commits is retained.
+    pub fn independent_commits(
+        &self,
+        commits: impl IntoIterator<Item = impl Into<git_hash::ObjectId>>,
+    ) -> Result<Vec<Id<'_>>, revision::merge_base::Error> {
+        let graph = self.commit_graph().ok();
+        Ok(git_traverse::commit::merge_base::independent(
+            commits.into_iter().map(Into::into),
+            graph.as_ref(),
+            |oid, buf| self.objects.find_commit_iter(oid, buf),
+        )?
+        .into_iter()
+        .map(|id| id.attach(self))
+        .collect())
+    }
 }
@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use crate::shallow;
+
+impl crate::Repository {
+    /// Return `true` if the repository is a shallow clone, i.e. contains history only up to a certain depth.
+    pub fn is_shallow(&self) -> bool {
+        self.shallow_file()
+            .metadata()
+            .map_or(false, |m| m.is_file() && m.len() > 0)
+    }
+
+    /// Return the sorted commits that mark the boundary of a shallow clone, or `None` if this repository isn't shallow.
+    ///
+    /// The parents of these commits are not present in the object database, which is why history traversals stop there.
+    pub fn shallow_commits(&self) -> Result<Option<Vec<git_hash::ObjectId>>, shallow::open::Error> {
+        let path = self.shallow_file();
+        let data = match std::fs::read(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(shallow::open::Error::Io { source: err, path }),
+        };
+        let commits = shallow::parse(&data)?;
+        Ok((!commits.is_empty()).then_some(commits))
+    }
+
+    /// The path to the file listing the boundary commits of a shallow clone, which may or may not exist.
+    pub fn shallow_file(&self) -> PathBuf {
+        self.common_dir().join("shallow")
+    }
+}
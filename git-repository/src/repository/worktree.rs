@@ -117,3 +117,46 @@ impl crate::Repository {
             })
     }
 }
+
+/// Index and trees
+impl crate::Repository {
+    /// Write all trees needed to represent `index` into the object database, similar to `git write-tree`, and return the outcome
+    /// whose [`tree`][git_index::write_tree::Outcome::tree] has the id of the top-level tree.
+    ///
+    /// Valid entries of the [tree extension][git_index::State::tree()] are reused instead of writing their trees once more.
+    /// The returned tree extension can be used to [update][git_index::State::set_tree()] the one of the index.
+    pub fn write_index_tree(
+        &self,
+        index: &git_index::State,
+    ) -> Result<git_index::write_tree::Outcome, worktree::write_index_tree::Error> {
+        index.write_tree(|tree| self.write_object(tree).map(crate::Id::detach))
+    }
+
+    /// Call `for_each` with all changes that are staged for the next commit, i.e. the changes needed to turn the tree of `HEAD`
+    /// into the tree represented by the current [index][crate::Repository::index()].
+    ///
+    /// Paths are [tracked][crate::object::tree::diff::Platform::track_path()] and rename tracking is configured according to
+    /// `diff.renames`. In an unborn repository, all entries of the index are additions.
+    #[allow(clippy::result_large_err)]
+    pub fn staged_changes<'repo, E>(
+        &'repo self,
+        for_each: impl FnMut(
+            crate::object::tree::diff::Change<'_, 'repo, 'repo>,
+        ) -> Result<crate::object::tree::diff::Action, E>,
+    ) -> Result<(), worktree::staged_changes::Error>
+    where
+        E: std::error::Error + Sync + Send + 'static,
+    {
+        let index = self.index()?;
+        let mut head = self.head()?;
+        let tree = if head.is_unborn() {
+            self.empty_tree()
+        } else {
+            head.peel_to_commit_in_place()?.tree()?
+        };
+        tree.changes()?
+            .track_path()
+            .for_each_to_obtain_index(&index, for_each)?;
+        Ok(())
+    }
+}
@@ -11,6 +11,11 @@ pub use walk::iter::Walk;
 ///
 pub mod spec;
 
+///
+pub mod merge_base {
+    pub use git_traverse::commit::merge_base::Error;
+}
+
 /// The specification of a revision as parsed from a revision specification like `HEAD@{1}` or `v1.2.3...main`.
 /// It's typically created by [`repo.rev_parse()`][crate::Repository::rev_parse()].
 ///
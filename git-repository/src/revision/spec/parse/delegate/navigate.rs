@@ -56,30 +56,24 @@ impl<'repo> delegate::Navigate for Delegate<'repo> {
                 }
                 Traversal::NthAncestor(num) => {
                     let id = obj.attach(repo);
-                    match id
-                        .ancestors()
-                        .first_parent_only()
-                        .all()
-                        .expect("cannot fail without sorting")
-                        .skip(num)
-                        .filter_map(Result::ok)
-                        .next()
-                    {
-                        Some(id) => replacements.push((*obj, id.detach())),
-                        None => errors.push((
-                            *obj,
-                            Error::AncestorOutOfRange {
-                                oid: id.shorten_or_id(),
-                                desired: num,
-                                available: id
-                                    .ancestors()
-                                    .first_parent_only()
-                                    .all()
-                                    .expect("cannot fail without sorting")
-                                    .skip(1)
-                                    .count(),
-                            },
-                        )),
+                    match id.ancestors().first_parent_only().all() {
+                        Ok(ancestors) => match ancestors.skip(num).filter_map(Result::ok).next() {
+                            Some(info) => replacements.push((*obj, info.id.detach())),
+                            None => errors.push((
+                                *obj,
+                                Error::AncestorOutOfRange {
+                                    oid: id.shorten_or_id(),
+                                    desired: num,
+                                    available: id
+                                        .ancestors()
+                                        .first_parent_only()
+                                        .all()
+                                        .map(|ancestors| ancestors.skip(1).count())
+                                        .unwrap_or_default(),
+                                },
+                            )),
+                        },
+                        Err(err) => errors.push((*obj, err.into())),
                     }
                 }
             }
@@ -193,9 +187,8 @@ impl<'repo> delegate::Navigate for Delegate<'repo> {
                             let mut matched = false;
                             let mut count = 0;
                             let commits = iter.map(|res| {
-                                res.map_err(Error::from).and_then(|commit_id| {
-                                    commit_id.object().map_err(Error::from).map(|obj| obj.into_commit())
-                                })
+                                res.map_err(Error::from)
+                                    .and_then(|info| info.id.object().map_err(Error::from).map(|obj| obj.into_commit()))
                             });
                             for commit in commits {
                                 count += 1;
@@ -251,8 +244,8 @@ impl<'repo> delegate::Navigate for Delegate<'repo> {
                                 let mut matched = false;
                                 let mut count = 0;
                                 let commits = iter.map(|res| {
-                                    res.map_err(Error::from).and_then(|commit_id| {
-                                        commit_id.object().map_err(Error::from).map(|obj| obj.into_commit())
+                                    res.map_err(Error::from).and_then(|info| {
+                                        info.id.object().map_err(Error::from).map(|obj| obj.into_commit())
                                     })
                                 });
                                 for commit in commits {
@@ -18,12 +18,21 @@ impl<'repo> delegate::Revision for Delegate<'repo> {
         if !self.err.is_empty() && self.refs[self.idx].is_some() {
             return None;
         }
-        match self.repo.refs.find(name) {
-            Ok(r) => {
+        match self.repo.try_find_reference(name) {
+            Ok(Some(r)) => {
                 assert!(self.refs[self.idx].is_none(), "BUG: cannot set the same ref twice");
-                self.refs[self.idx] = Some(r);
+                self.refs[self.idx] = Some(r.detach());
                 Some(())
             }
+            Ok(None) => {
+                self.err.push(
+                    git_ref::file::find::existing::Error::NotFound {
+                        name: git_path::from_bstr(name).into_owned(),
+                    }
+                    .into(),
+                );
+                None
+            }
             Err(err) => {
                 self.err.push(err.into());
                 None
@@ -74,8 +83,9 @@ impl<'repo> delegate::Revision for Delegate<'repo> {
                         Some(())
                     }
                     RefsHint::PreferRef | RefsHint::PreferObjectOnFullLengthHexShaUseRefOtherwise | RefsHint::Fail => {
-                        match self.repo.refs.find(&prefix.to_string()) {
+                        match self.repo.find_reference(&prefix.to_string()) {
                             Ok(ref_) => {
+                                let ref_ = ref_.detach();
                                 assert!(self.refs[self.idx].is_none(), "BUG: cannot set the same ref twice");
                                 if self.opts.refs_hint == RefsHint::Fail {
                                     self.refs[self.idx] = Some(ref_.clone());
@@ -163,7 +173,7 @@ impl<'repo> delegate::Revision for Delegate<'repo> {
     fn nth_checked_out_branch(&mut self, branch_no: usize) -> Option<()> {
         self.unset_disambiguate_call();
         fn prior_checkouts_iter<'a>(
-            platform: &'a mut git_ref::file::log::iter::Platform<'static, '_>,
+            platform: &'a mut crate::reference::log::iter::Platform<'static, '_>,
         ) -> Result<impl Iterator<Item = (BString, ObjectId)> + 'a, Error> {
             match platform.rev().ok().flatten() {
                 Some(log) => Ok(log.filter_map(Result::ok).filter_map(|line| {
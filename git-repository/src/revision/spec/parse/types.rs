@@ -143,6 +143,8 @@ pub enum Error {
     #[error(transparent)]
     FindReference(#[from] git_ref::file::find::existing::Error),
     #[error(transparent)]
+    TryFindReference(#[from] reference::find::Error),
+    #[error(transparent)]
     FindObject(#[from] object::find::existing::Error),
     #[error(transparent)]
     LookupPrefix(#[from] git_odb::store::prefix::lookup::Error),
@@ -177,6 +179,8 @@ pub enum Error {
     },
     #[error(transparent)]
     Traverse(#[from] git_traverse::commit::ancestors::Error),
+    #[error(transparent)]
+    Walk(#[from] crate::revision::walk::Error),
     #[error("Spec does not contain a single object id")]
     SingleNotFound,
 }
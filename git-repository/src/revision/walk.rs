@@ -1,7 +1,53 @@
 use git_hash::ObjectId;
 use git_odb::FindExt;
 
-use crate::{revision, Repository};
+use crate::{bstr::BString, revision, Id, Repository};
+
+/// The error returned by [`Platform::all()`].
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error(transparent)]
+    AncestorIter(#[from] git_traverse::commit::ancestors::Error),
+    #[error(transparent)]
+    ShallowCommits(#[from] crate::shallow::open::Error),
+}
+
+///
+pub mod specs {
+    /// The error returned by [`Repository::rev_walk_specs()`][crate::Repository::rev_walk_specs()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error(transparent)]
+        FindExistingObject(#[from] crate::object::find::existing::Error),
+        #[error(transparent)]
+        PeelToCommit(#[from] crate::object::peel::to_kind::Error),
+        #[error(transparent)]
+        MergeBase(#[from] crate::revision::merge_base::Error),
+    }
+}
+
+/// The side of a symmetric difference like `A...B` a commit is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// The commit is reachable from the left side, `A` in `A...B`, similar to the `<` mark of `git rev-list --left-right`.
+    Left,
+    /// The commit is reachable from the right side, `B` in `A...B`, similar to the `>` mark of `git rev-list --left-right`.
+    Right,
+}
+
+/// Information about a commit returned by a [`Walk`][revision::Walk].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Info<'repo> {
+    /// The id of the commit.
+    pub id: Id<'repo>,
+    /// The side of the symmetric difference the commit is on, if [left tips][Platform::left_right()] were set.
+    pub side: Option<Side>,
+    /// If `true`, the commit is a hidden parent of a returned commit, which is only returned if
+    /// [boundary commits][Platform::boundary()] are requested, after all other commits.
+    pub is_boundary: bool,
+}
 
 /// A platform to traverse the revision graph by adding starting points as well as points which shouldn't be crossed,
 /// returned by [`Repository::rev_walk()`].
@@ -10,6 +56,11 @@ pub struct Platform<'repo> {
     pub(crate) tips: Vec<ObjectId>,
     pub(crate) sorting: git_traverse::commit::Sorting,
     pub(crate) parents: git_traverse::commit::Parents,
+    pub(crate) changed_paths: Vec<BString>,
+    pub(crate) reverse: bool,
+    pub(crate) hidden: Vec<ObjectId>,
+    pub(crate) left: Option<Vec<ObjectId>>,
+    pub(crate) boundary: bool,
 }
 
 impl<'repo> Platform<'repo> {
@@ -19,6 +70,11 @@ impl<'repo> Platform<'repo> {
             tips: tips.into_iter().map(Into::into).collect(),
             sorting: Default::default(),
             parents: Default::default(),
+            changed_paths: Vec::new(),
+            reverse: false,
+            hidden: Vec::new(),
+            left: None,
+            boundary: false,
         }
     }
 }
@@ -26,6 +82,10 @@ impl<'repo> Platform<'repo> {
 /// Create-time builder methods
 impl<'repo> Platform<'repo> {
     /// Set the sort mode for commits to the given value. The default is to order by topology.
+    ///
+    /// Use [`Sorting::TopoOrder`][git_traverse::commit::Sorting::TopoOrder], [`Sorting::DateOrder`][git_traverse::commit::Sorting::DateOrder]
+    /// or [`Sorting::AuthorDateOrder`][git_traverse::commit::Sorting::AuthorDateOrder] to never return a parent before all of its
+    /// children, similar to `git rev-list --topo-order`, `--date-order` and `--author-date-order` respectively.
     pub fn sorting(mut self, sorting: git_traverse::commit::Sorting) -> Self {
         self.sorting = sorting;
         self
@@ -36,6 +96,52 @@ impl<'repo> Platform<'repo> {
         self.parents = git_traverse::commit::Parents::First;
         self
     }
+
+    /// Only return commits that changed any of the given `paths` compared to their first parent, or that contain any of them
+    /// if they have no parent, similar to `git log --full-history -- <paths>`.
+    ///
+    /// `paths` are slash-separated and relative to the root of the repository, and may be files or directories.
+    /// The history of all other commits is still traversed.
+    pub fn changed_paths(mut self, paths: impl IntoIterator<Item = impl Into<BString>>) -> Self {
+        self.changed_paths = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Hide all commits reachable from `tips`, so that neither they nor their ancestors are returned, similar to `^<rev>`
+    /// in `git rev-list`. This can be called multiple times.
+    pub fn hide(mut self, tips: impl IntoIterator<Item = impl Into<ObjectId>>) -> Self {
+        self.hidden.extend(tips.into_iter().map(Into::into));
+        self
+    }
+
+    /// Add the `left` tips and classify all returned commits by the [side][Info::side] they are reachable from: the `left`
+    /// tips, or only the other tips, similar to `git rev-list --left-right A...B` with `A` being on the left.
+    ///
+    /// This is typically used with [hidden][Platform::hide()] merge-bases of all tips, which is what
+    /// [`Repository::rev_walk_specs()`] does for `A...B`.
+    pub fn left_right(mut self, left: impl IntoIterator<Item = impl Into<ObjectId>>) -> Self {
+        let left: Vec<ObjectId> = left.into_iter().map(Into::into).collect();
+        self.tips.extend(left.iter().copied());
+        self.left.get_or_insert_with(Vec::new).extend(left);
+        self
+    }
+
+    /// Also return all [hidden][Platform::hide()] parents of returned commits once all other commits were returned, marked as
+    /// [boundary commits][Info::is_boundary], similar to `git rev-list --boundary`.
+    ///
+    /// Just like with `git`, the boundary commits are returned in the reverse order in which they were found.
+    pub fn boundary(mut self) -> Self {
+        self.boundary = true;
+        self
+    }
+
+    /// Return the commits in reverse order, similar to `git rev-list --reverse`.
+    ///
+    /// Note that all commits are traversed before the first one is returned.
+    pub fn reverse(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
 }
 
 /// Produce the iterator
@@ -46,24 +152,74 @@ impl<'repo> Platform<'repo> {
     ///
     /// It's highly recommended to set an [`object cache`][Repository::object_cache_size()] on the parent repo
     /// to greatly speed up performance if the returned id is supposed to be looked up right after.
-    pub fn all(self) -> Result<revision::Walk<'repo>, git_traverse::commit::ancestors::Error> {
+    ///
+    /// # Shallow Repositories
+    ///
+    /// If the repository [is shallow][Repository::is_shallow()], the traversal stops at the [shallow commits][Repository::shallow_commits()]
+    /// whose parents are not present.
+    ///
+    /// The [commit-graph][Repository::commit_graph()] is used to obtain commit information if present.
+    ///
+    /// # Changed Paths
+    ///
+    /// If [changed paths][Platform::changed_paths()] are set, the changed-path Bloom filters of the commit-graph are consulted
+    /// to skip commits which definitely didn't change them. All other commits are checked by comparing the tree entries
+    /// at the given paths with the ones of their first parent.
+    ///
+    /// # Hidden Commits
+    ///
+    /// If commits are [hidden][Platform::hide()], the commits to hide are found before the first commit is returned, by
+    /// traversing the history of all tips until only hidden commits are left.
+    pub fn all(self) -> Result<revision::Walk<'repo>, Error> {
         let Platform {
             repo,
             tips,
             sorting,
             parents,
+            changed_paths,
+            reverse,
+            hidden,
+            left,
+            boundary,
         } = self;
-        Ok(revision::Walk {
-            repo,
-            inner: Box::new(
-                git_traverse::commit::Ancestors::new(
-                    tips,
+        let shallow_commits = repo.shallow_commits()?.unwrap_or_default();
+        let mut inner = git_traverse::commit::Ancestors::new(
+            tips,
+            git_traverse::commit::ancestors::State::default(),
+            move |oid, buf| repo.objects.find_commit_iter(oid, buf),
+        )
+        .commit_graph(repo.commit_graph().ok())
+        .parents(parents)
+        .shallow_commits(shallow_commits.iter().copied());
+        if !hidden.is_empty() {
+            inner = inner.hide(hidden)?;
+        }
+        let hidden = inner.hidden_commits().clone();
+        // Only commits that aren't hidden are returned, so all of them that are reachable from the left tips are on the left.
+        let left = left
+            .map(|left_tips| {
+                git_traverse::commit::Ancestors::filtered(
+                    left_tips,
                     git_traverse::commit::ancestors::State::default(),
                     move |oid, buf| repo.objects.find_commit_iter(oid, buf),
+                    |id| !hidden.contains(id),
                 )
-                .sorting(sorting)?
-                .parents(parents),
-            ),
+                .commit_graph(repo.commit_graph().ok())
+                .parents(parents)
+                .shallow_commits(shallow_commits.iter().copied())
+                .collect::<Result<git_hashtable::HashSet<_>, _>>()
+            })
+            .transpose()?;
+        Ok(revision::Walk {
+            repo,
+            inner: Box::new(inner.sorting(sorting)?.changed_paths(changed_paths.iter().cloned())),
+            changed_paths,
+            reverse,
+            reversed: None,
+            hidden,
+            left,
+            boundary: boundary.then(Default::default),
+            first_parent_only: matches!(parents, git_traverse::commit::Parents::First),
             is_shallow: None,
             error_on_missing_commit: false,
         })
@@ -71,7 +227,14 @@ impl<'repo> Platform<'repo> {
 }
 
 pub(crate) mod iter {
-    use crate::{ext::ObjectIdExt, Id};
+    use git_hashtable::HashSet;
+    use git_traverse::commit::ancestors;
+
+    use crate::{
+        bstr::BString,
+        ext::ObjectIdExt,
+        revision::walk::{Info, Side},
+    };
 
     /// The iterator returned by [`crate::revision::walk::Platform::all()`].
     pub struct Walk<'repo> {
@@ -79,6 +242,17 @@ pub(crate) mod iter {
         pub(crate) inner:
             Box<dyn Iterator<Item = Result<git_hash::ObjectId, git_traverse::commit::ancestors::Error>> + 'repo>,
         pub(crate) error_on_missing_commit: bool,
+        pub(crate) changed_paths: Vec<BString>,
+        pub(crate) reverse: bool,
+        /// All commits in the order they were traversed if `reverse` is set, collected on first use.
+        pub(crate) reversed: Option<Vec<Info<'repo>>>,
+        /// The hidden commits that weren't yet returned as boundary commits.
+        pub(crate) hidden: HashSet<git_hash::ObjectId>,
+        /// All commits reachable from the left tips, if set.
+        pub(crate) left: Option<HashSet<git_hash::ObjectId>>,
+        /// The boundary commits to return in reverse order once the traversal is done, if requested.
+        pub(crate) boundary: Option<Vec<git_hash::ObjectId>>,
+        pub(crate) first_parent_only: bool,
         // TODO: tests
         /// After iteration this flag is true if the iteration was stopped prematurely due to missing parent commits.
         /// Note that this flag won't be `Some` if any iteration error occurs, which is the case if
@@ -103,25 +277,155 @@ pub(crate) mod iter {
     }
 
     impl<'repo> Iterator for Walk<'repo> {
-        type Item = Result<Id<'repo>, git_traverse::commit::ancestors::Error>;
+        type Item = Result<Info<'repo>, git_traverse::commit::ancestors::Error>;
 
         fn next(&mut self) -> Option<Self::Item> {
-            match self.inner.next() {
-                None => {
-                    self.is_shallow = Some(false);
-                    None
+            if !self.reverse {
+                return self.next_in_order();
+            }
+            if self.reversed.is_none() {
+                let mut infos = Vec::new();
+                while let Some(res) = self.next_in_order() {
+                    match res {
+                        Ok(info) => infos.push(info),
+                        Err(err) => {
+                            self.reversed = Some(Vec::new());
+                            return Some(Err(err));
+                        }
+                    }
+                }
+                self.reversed = Some(infos);
+            }
+            self.reversed.as_mut()?.pop().map(Ok)
+        }
+    }
+
+    impl<'repo> Walk<'repo> {
+        fn next_in_order(&mut self) -> Option<Result<Info<'repo>, git_traverse::commit::ancestors::Error>> {
+            loop {
+                match self.inner.next() {
+                    None => {
+                        self.is_shallow.get_or_insert(false);
+                    }
+                    Some(Ok(oid)) => match self.changes_paths(oid) {
+                        Ok(true) => return Some(self.info(oid)),
+                        Ok(false) => continue,
+                        Err(err) => return Some(Err(err)),
+                    },
+                    Some(Err(err @ git_traverse::commit::ancestors::Error::FindExisting { .. })) => {
+                        if self.error_on_missing_commit {
+                            return Some(Err(err));
+                        } else {
+                            self.is_shallow = Some(true);
+                            self.inner = Box::new(std::iter::empty());
+                        }
+                    }
+                    Some(Err(err)) => return Some(Err(err)),
+                };
+                let repo = self.repo;
+                return self.boundary.as_mut()?.pop().map(|id| {
+                    Ok(Info {
+                        id: id.attach(repo),
+                        side: None,
+                        is_boundary: true,
+                    })
+                });
+            }
+        }
+
+        /// Return information about the commit with `id` that is about to be returned, and remember its hidden parents
+        /// as boundary commits if requested.
+        fn info(&mut self, id: git_hash::ObjectId) -> Result<Info<'repo>, ancestors::Error> {
+            if let Some(boundary) = self.boundary.as_mut() {
+                let commit = self
+                    .repo
+                    .find_object(id)
+                    .map_err(|err| ancestors::Error::FindExisting {
+                        oid: id,
+                        source: err.into(),
+                    })?
+                    .into_commit();
+                let parents = commit
+                    .parent_ids()
+                    .take(if self.first_parent_only { 1 } else { usize::MAX });
+                for parent_id in parents {
+                    // Removing it assures each boundary commit is returned only once.
+                    if self.hidden.remove(&parent_id.detach()) {
+                        boundary.push(parent_id.detach());
+                    }
                 }
-                Some(Ok(oid)) => Some(Ok(oid.attach(self.repo))),
-                Some(Err(err @ git_traverse::commit::ancestors::Error::FindExisting { .. })) => {
-                    if self.error_on_missing_commit {
-                        Some(Err(err))
-                    } else {
-                        self.is_shallow = Some(true);
-                        None
+            }
+            Ok(Info {
+                id: id.attach(self.repo),
+                side: self
+                    .left
+                    .as_ref()
+                    .map(|left| if left.contains(&id) { Side::Left } else { Side::Right }),
+                is_boundary: false,
+            })
+        }
+    }
+
+    impl<'repo> Walk<'repo> {
+        /// Return `true` if the commit with `id` changed any of our paths compared to its first parent, or if there are no paths.
+        fn changes_paths(&self, id: git_hash::ObjectId) -> Result<bool, ancestors::Error> {
+            if self.changed_paths.is_empty() {
+                return Ok(true);
+            }
+            let commit = self.find_object(id)?.into_commit();
+            let tree_id = commit.tree_id()?.detach();
+            let parent_tree_id = match commit.parent_ids().next() {
+                Some(parent_id) => {
+                    match self
+                        .repo
+                        .try_find_object(parent_id)
+                        .map_err(|err| ancestors::Error::FindExisting {
+                            oid: parent_id.detach(),
+                            source: err.into(),
+                        })? {
+                        Some(parent) => Some(parent.into_commit().tree_id()?.detach()),
+                        // The parent of a shallow commit is missing, so we can't know what changed.
+                        None => return Ok(true),
                     }
                 }
-                Some(Err(err)) => Some(Err(err)),
+                None => None,
+            };
+            for path in &self.changed_paths {
+                let entry = self.entry_at(tree_id, path)?;
+                let parent_entry = match parent_tree_id {
+                    Some(parent_tree_id) => self.entry_at(parent_tree_id, path)?,
+                    None => None,
+                };
+                if entry != parent_entry {
+                    return Ok(true);
+                }
             }
+            Ok(false)
+        }
+
+        /// Return the mode and id of the entry at the slash-separated `path` in the tree with `tree_id`, if there is one.
+        fn entry_at(
+            &self,
+            tree_id: git_hash::ObjectId,
+            path: &BString,
+        ) -> Result<Option<(git_object::tree::EntryMode, git_hash::ObjectId)>, ancestors::Error> {
+            let components = path.split(|b| *b == b'/').filter(|c| !c.is_empty());
+            Ok(self
+                .find_object(tree_id)?
+                .into_tree()
+                .lookup_entry(components)
+                .map_err(|err| ancestors::Error::FindExisting {
+                    oid: tree_id,
+                    source: err.into(),
+                })?
+                .map(|entry| (entry.mode(), entry.object_id())))
+        }
+
+        fn find_object(&self, id: git_hash::ObjectId) -> Result<crate::Object<'repo>, ancestors::Error> {
+            self.repo.find_object(id).map_err(|err| ancestors::Error::FindExisting {
+                oid: id,
+                source: err.into(),
+            })
         }
     }
 }
@@ -0,0 +1,98 @@
+//! Shallow repositories only contain the history up to a boundary of commits whose parents are not present.
+//!
+//! These commits are listed in the `shallow` file in the repository, which is read by
+//! [`Repository::shallow_commits()`][crate::Repository::shallow_commits()] and updated when fetching with
+//! a [`Shallow`][crate::remote::fetch::Shallow] configuration.
+
+///
+pub mod open {
+    use std::path::PathBuf;
+
+    use crate::bstr::BString;
+
+    /// The error returned by [`Repository::shallow_commits()`][crate::Repository::shallow_commits()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("Could not read the shallow file at '{}'", path.display())]
+        Io { source: std::io::Error, path: PathBuf },
+        #[error("Could not decode line {line:?} in the shallow file as object id")]
+        DecodeHash {
+            source: git_hash::decode::Error,
+            line: BString,
+        },
+    }
+}
+
+///
+#[cfg(any(feature = "blocking-network-client", feature = "async-network-client"))]
+pub mod write {
+    /// The error returned when updating the shallow file after fetching.
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("Could not lock the shallow file for writing")]
+        Lock(#[from] git_lock::acquire::Error),
+        #[error("Could not write or remove the shallow file")]
+        Io(#[from] std::io::Error),
+        #[error("Could not commit the shallow file")]
+        Commit(#[from] git_lock::commit::Error<git_lock::File>),
+    }
+}
+
+/// Parse the content of a shallow file into a sorted list of commits.
+pub(crate) fn parse(data: &[u8]) -> Result<Vec<git_hash::ObjectId>, open::Error> {
+    use crate::bstr::ByteSlice;
+    let mut commits = data
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            git_hash::ObjectId::from_hex(line).map_err(|err| open::Error::DecodeHash {
+                source: err,
+                line: line.into(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    commits.sort();
+    commits.dedup();
+    Ok(commits)
+}
+
+/// Apply `updates` as received from a remote to the `commits` of the shallow file at `path`, and write the result to `path`
+/// or remove it if no shallow commit is left.
+#[cfg(any(feature = "blocking-network-client", feature = "async-network-client"))]
+pub(crate) fn write(
+    path: &std::path::Path,
+    commits: Option<Vec<git_hash::ObjectId>>,
+    updates: &[git_protocol::fetch::response::ShallowUpdate],
+) -> Result<(), write::Error> {
+    use std::io::Write;
+
+    use git_protocol::fetch::response::ShallowUpdate;
+
+    if updates.is_empty() {
+        return Ok(());
+    }
+    let mut commits = commits.unwrap_or_default();
+    for update in updates {
+        match update {
+            ShallowUpdate::Shallow(id) => commits.push(*id),
+            ShallowUpdate::Unshallow(id) => commits.retain(|shallow| shallow != id),
+        }
+    }
+    commits.sort();
+    commits.dedup();
+
+    let mut file = git_lock::File::acquire_to_update_resource(path, git_lock::acquire::Fail::Immediately, None)?;
+    if commits.is_empty() {
+        match std::fs::remove_file(path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+            _ => return Ok(()),
+        }
+    }
+    for commit in commits {
+        writeln!(file, "{commit}")?;
+    }
+    file.commit()?;
+    Ok(())
+}
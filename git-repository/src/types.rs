@@ -137,6 +137,8 @@ pub struct Reference<'r> {
 pub struct Repository {
     /// A ref store with shared ownership (or the equivalent of it).
     pub refs: crate::RefStore,
+    /// The store to use for references instead of `refs` if they are kept in reftables, as configured with `extensions.refStorage`.
+    pub reftable: Option<git_ref::reftable::Store>,
     /// A way to access objects.
     pub objects: crate::OdbHandle,
 
@@ -164,6 +166,8 @@ pub struct Repository {
 pub struct ThreadSafeRepository {
     /// A store for references to point at objects
     pub refs: crate::RefStore,
+    /// The store to use for references instead of `refs` if they are kept in reftables, as configured with `extensions.refStorage`.
+    pub reftable: Option<git_ref::reftable::Store>,
     /// A store for objects that contain data
     pub objects: git_features::threading::OwnShared<git_odb::Store>,
     /// The path to the worktree at which to find checked out files
@@ -100,6 +100,33 @@ pub mod open_index {
     }
 }
 
+///
+pub mod write_index_tree {
+    /// The error returned by [`Repository::write_index_tree()`][crate::Repository::write_index_tree()].
+    pub type Error = git_index::write_tree::Error<crate::object::write::Error>;
+}
+
+///
+pub mod staged_changes {
+    /// The error returned by [`Repository::staged_changes()`][crate::Repository::staged_changes()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error(transparent)]
+        OpenIndex(#[from] super::open_index::Error),
+        #[error(transparent)]
+        FindHead(#[from] crate::reference::find::existing::Error),
+        #[error(transparent)]
+        PeelHeadToCommit(#[from] crate::head::peel::to_commit::Error),
+        #[error(transparent)]
+        CommitTree(#[from] crate::object::commit::Error),
+        #[error(transparent)]
+        RewritesConfiguration(#[from] crate::object::tree::diff::rewrites::Error),
+        #[error(transparent)]
+        Diff(#[from] crate::object::tree::diff::for_each::Error),
+    }
+}
+
 ///
 pub mod excludes {
     use std::path::PathBuf;
@@ -158,3 +185,105 @@ pub mod excludes {
         }
     }
 }
+
+///
+pub mod status {
+    use std::sync::atomic::AtomicBool;
+
+    use git_odb::FindExt;
+    pub use git_worktree::index::status::{Change, Options, Outcome};
+
+    use crate::bstr::BStr;
+
+    /// The error returned by [`Worktree::status()`][crate::Worktree::status()].
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error(transparent)]
+        OpenIndex(#[from] super::open_index::Error),
+        #[error(transparent)]
+        Excludes(#[from] super::excludes::Error),
+        #[error(transparent)]
+        Options(#[from] crate::config::checkout_options::Error),
+        #[error(transparent)]
+        Status(#[from] git_worktree::index::status::Error),
+    }
+
+    /// A single difference between the index and the worktree.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Item<'a> {
+        /// A tracked file differs from its entry in the index.
+        Modification {
+            /// The path of the file relative to the worktree root.
+            rela_path: &'a BStr,
+            /// The index entry of the file.
+            entry: &'a git_index::Entry,
+            /// How the file differs from its index entry.
+            change: Change,
+        },
+        /// A file or directory which isn't tracked and isn't excluded either.
+        ///
+        /// Directories are reported as a whole if they don't contain tracked files, in which case their path ends in a slash.
+        Untracked {
+            /// The path of the file or directory relative to the worktree root.
+            rela_path: &'a BStr,
+        },
+    }
+
+    impl<'repo> crate::Worktree<'repo> {
+        /// Return the options for comparing the index to the worktree as configured by `core.fileMode`, `core.symlinks`,
+        /// `core.ignoreCase`, `core.trustCTime` and `core.checkStat`.
+        #[allow(clippy::result_large_err)]
+        pub fn status_options(&self) -> Result<Options, crate::config::checkout_options::Error> {
+            let opts = self.parent.config.checkout_options(self.parent.git_dir())?;
+            Ok(Options {
+                fs: opts.fs,
+                stat: git_index::entry::stat::Options {
+                    trust_ctime: opts.trust_ctime,
+                    check_stat: opts.check_stat,
+                    ..Default::default()
+                },
+            })
+        }
+
+        /// Compare the index with the worktree and call `record` for each tracked file that differs from its index entry,
+        /// followed by all untracked files if `untracked` is true, honoring the exclude files of the repository.
+        ///
+        /// Note that interruption via `should_interrupt` still produces an `Ok(…)` value, so the caller should look at it
+        /// to communicate the outcome.
+        #[allow(clippy::result_large_err)]
+        pub fn status(
+            &self,
+            untracked: bool,
+            should_interrupt: &AtomicBool,
+            mut record: impl FnMut(Item<'_>),
+        ) -> Result<Outcome, Error> {
+            let index = self.index()?;
+            let outcome = git_worktree::index::status::compare_to_index(
+                &index,
+                self.path,
+                should_interrupt,
+                self.status_options()?,
+                |rela_path, entry, change| {
+                    record(Item::Modification {
+                        rela_path,
+                        entry,
+                        change,
+                    })
+                },
+            )?;
+            if untracked {
+                let mut cache = self.excludes(&index, None)?;
+                let repo = self.parent;
+                git_worktree::index::status::untracked(
+                    &index,
+                    &mut cache,
+                    |id, buf| repo.objects.find_blob(id, buf),
+                    should_interrupt,
+                    |rela_path| record(Item::Untracked { rela_path }),
+                )?;
+            }
+            Ok(outcome)
+        }
+    }
+}
@@ -235,6 +235,53 @@ mod blocking_io {
         assert!(path.is_absolute(), "{:?} must be absolute", path);
     }
 
+    #[test]
+    fn fetch_shallow_no_checkout_then_unshallow() -> crate::Result {
+        for protocol_version in ["1", "2"] {
+            let tmp = git_testtools::tempfile::TempDir::new()?;
+            let (repo, _out) = git::clone::PrepareFetch::new(
+                remote::repo("base").path(),
+                tmp.path(),
+                git::create::Kind::Bare,
+                Default::default(),
+                restricted().config_overrides(Some(format!("protocol.version={protocol_version}"))),
+            )?
+            .with_shallow(git::remote::fetch::Shallow::DepthAtRemote(1.try_into()?))
+            .fetch_only(git::progress::Discard, &std::sync::atomic::AtomicBool::default())?;
+
+            assert!(repo.is_shallow(), "the clone is shallow, V{protocol_version}");
+            let shallow_commits = repo.shallow_commits()?.expect("present");
+            assert!(
+                shallow_commits.contains(&repo.head_id()?.detach()),
+                "the commit that HEAD points to is at the shallow boundary"
+            );
+            assert_eq!(
+                repo.head_id()?.ancestors().all()?.count(),
+                1,
+                "traversals stop at the shallow boundary"
+            );
+
+            let outcome = repo
+                .find_remote("origin")?
+                .connect(git::remote::Direction::Fetch, git::progress::Discard)?
+                .prepare_fetch(Default::default())?
+                .with_shallow(git::remote::fetch::Shallow::Undo)
+                .receive(&std::sync::atomic::AtomicBool::default())?;
+            assert!(
+                matches!(outcome.status, git::remote::fetch::Status::Change { .. }),
+                "the missing history was received"
+            );
+            assert!(!repo.is_shallow(), "the shallow boundary was removed entirely");
+            assert!(!repo.shallow_file().exists(), "it's removed like git would");
+            assert_eq!(
+                repo.head_id()?.ancestors().all()?.count(),
+                remote::repo("base").head_id()?.ancestors().all()?.count(),
+                "all history is present"
+            );
+        }
+        Ok(())
+    }
+
     #[test]
     fn fetch_and_checkout() -> crate::Result {
         let tmp = git_testtools::tempfile::TempDir::new()?;
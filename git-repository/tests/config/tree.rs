@@ -120,10 +120,54 @@ mod ssh {
 
 mod diff {
     use git_diff::blob::Algorithm;
-    use git_repository::config::tree::{Diff, Key};
+    use git_repository::{
+        config::tree::{Diff, Key},
+        object::tree::diff::rewrites::Tracking,
+    };
 
     use crate::config::tree::bcow;
 
+    #[test]
+    fn renames() -> crate::Result {
+        assert_eq!(
+            Diff::RENAMES.try_into_renames(Ok(true), || unreachable!("not called"))?,
+            Tracking::Renames
+        );
+        assert_eq!(
+            Diff::RENAMES.try_into_renames(Ok(false), || unreachable!("not called"))?,
+            Tracking::Disabled
+        );
+        for value in ["copy", "copies"] {
+            assert_eq!(
+                Diff::RENAMES.try_into_renames(git_config::Boolean::try_from(bcow(value)).map(|b| b.0), || Some(
+                    bcow(value)
+                ))?,
+                Tracking::RenamesAndCopies
+            );
+            assert!(Diff::RENAMES.validate(value.into()).is_ok());
+        }
+        assert!(Diff::RENAMES.validate("yes".into()).is_ok());
+        assert_eq!(
+            Diff::RENAMES
+                .try_into_renames(git_config::Boolean::try_from(bcow("foo")).map(|b| b.0), || Some(bcow(
+                    "foo"
+                )))
+                .unwrap_err()
+                .to_string(),
+            "The key \"diff.renames=foo\" was invalid"
+        );
+        assert!(Diff::RENAMES.validate("foo".into()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rename_limit() -> crate::Result {
+        assert_eq!(Diff::RENAME_LIMIT.try_into_usize(Ok(42))?, 42);
+        assert!(Diff::RENAME_LIMIT.validate("100".into()).is_ok());
+        assert!(Diff::RENAME_LIMIT.validate("-1".into()).is_err());
+        Ok(())
+    }
+
     #[test]
     fn algorithm() -> crate::Result {
         for (actual, expected) in [
@@ -330,6 +374,10 @@ mod extensions {
             git_hash::Kind::Sha1,
             "case-insensitive"
         );
+        assert_eq!(
+            Extensions::OBJECT_FORMAT.try_into_object_format(bcow("sha256"))?,
+            git_hash::Kind::Sha256
+        );
         assert_eq!(
             Extensions::OBJECT_FORMAT
                 .try_into_object_format(bcow("invalid"))
@@ -338,11 +386,81 @@ mod extensions {
             "The key \"extensions.objectFormat=invalid\" was invalid"
         );
         assert!(Extensions::OBJECT_FORMAT.validate("sha1".into()).is_ok());
+        assert!(Extensions::OBJECT_FORMAT.validate("sha256".into()).is_ok());
         assert!(Extensions::OBJECT_FORMAT.validate("invalid".into()).is_err());
         Ok(())
     }
 }
 
+#[cfg(any(feature = "blocking-network-client", feature = "async-network-client"))]
+mod fetch {
+    use git_repository::{
+        config::tree::{Fetch, Key},
+        remote::fetch::negotiate::Algorithm,
+    };
+
+    use crate::config::tree::bcow;
+
+    #[test]
+    fn negotiation_algorithm() -> crate::Result {
+        for (actual, expected) in [
+            ("noop", Algorithm::Noop),
+            ("consecutive", Algorithm::Consecutive),
+            ("default", Algorithm::Consecutive),
+            ("skipping", Algorithm::Skipping),
+        ] {
+            assert_eq!(
+                Fetch::NEGOTIATION_ALGORITHM.try_into_negotiation_algorithm(bcow(actual))?,
+                expected
+            );
+            assert!(Fetch::NEGOTIATION_ALGORITHM.validate(actual.into()).is_ok());
+        }
+        assert_eq!(
+            Fetch::NEGOTIATION_ALGORITHM
+                .try_into_negotiation_algorithm(bcow("foo"))
+                .unwrap_err()
+                .to_string(),
+            "The key \"fetch.negotiationAlgorithm=foo\" was invalid"
+        );
+        assert!(Fetch::NEGOTIATION_ALGORITHM.validate("foo".into()).is_err());
+        Ok(())
+    }
+}
+
+mod gc {
+    use std::time::{Duration, SystemTime};
+
+    use git_repository::config::tree::{Gc, Key};
+
+    use crate::config::tree::bcow;
+
+    #[test]
+    fn expiry_dates() -> crate::Result {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(100 * 24 * 60 * 60);
+        for key in [&Gc::REFLOG_EXPIRE, &Gc::REFLOG_EXPIRE_UNREACHABLE, &Gc::PRUNE_EXPIRE] {
+            for (value, expected) in [
+                ("never", None),
+                ("false", None),
+                ("all", Some(u32::MAX)),
+                ("now", Some(u32::MAX)),
+                ("90.days.ago", Some(10 * 24 * 60 * 60)),
+                ("90 days ago", Some(10 * 24 * 60 * 60)),
+                ("2.weeks", Some(86 * 24 * 60 * 60)),
+                ("1234", Some(1234)),
+            ] {
+                assert_eq!(key.try_into_expiry_date(bcow(value), now)?, expected, "{value}");
+                assert!(key.validate(value.into()).is_ok());
+            }
+            assert_eq!(
+                key.try_into_expiry_date(bcow("foo"), now).unwrap_err().to_string(),
+                format!("The date format at key \"{}=foo\" was invalid", key.logical_name())
+            );
+            assert!(key.validate("foo".into()).is_err());
+        }
+        Ok(())
+    }
+}
+
 mod checkout {
     use git_repository::config::tree::{Checkout, Key};
 
@@ -17,3 +17,19 @@ git commit -q -am c2
 
 echo a1 >> a
 git commit -q -am c3
+
+git mv a a-renamed
+git commit -q -m r1-identity
+
+mkdir dir2
+seq 10 > dir2/s
+git add .
+git commit -q -m s1
+
+git mv dir2/s dir2/s-renamed
+echo 11 >> dir2/s-renamed
+git commit -q -am r2-similar
+
+cp dir2/s-renamed dir/s-copy
+git add .
+git commit -q -m c4-copy
@@ -0,0 +1,28 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q base
+(cd base
+  git commit -q --allow-empty -m c1
+  git tag v1
+  git branch to-delete
+  git commit -q --allow-empty -m c2
+  git branch diverged
+)
+git clone -q --bare base base.git
+git init -q --bare empty.git
+
+git clone -q -o base base.git clone
+(cd clone
+  echo a > a
+  git add a
+  git commit -q -m c3
+  git branch feature
+  git tag -m "annotated" v2
+  git checkout -q -b diverged base/diverged~1
+  echo b > b
+  git add b
+  git commit -q -m "diverged from remote"
+  git checkout -q main
+  git remote add empty "$PWD/../empty.git"
+)
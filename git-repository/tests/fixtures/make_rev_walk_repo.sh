@@ -0,0 +1,54 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+git config merge.ff false
+
+t=1000000000
+function commit() {
+  local message=${1:?message}
+  t=$((t + 60))
+  GIT_COMMITTER_DATE="$t +0000" git commit -q --allow-empty -m "$message"
+  git tag "$message"
+}
+function merge() {
+  local message=${1:?message} other=${2:?other}
+  t=$((t + 60))
+  GIT_COMMITTER_DATE="$t +0000" git merge -q "$other" -m "$message"
+  git tag "$message"
+}
+
+git checkout -q -b main
+commit c0
+commit c1
+
+git checkout -q -b a c1
+commit a1
+git checkout -q main
+commit c2
+git checkout -q a
+commit a2
+git checkout -q -b b c1
+commit b1
+git checkout -q main
+commit c3
+merge m1 a
+commit c4
+
+# each line is the name of the list followed by the arguments to `git rev-list`
+cat <<LISTS > lists
+range a..main
+exclude ^c3 main
+symmetric --left-right a...b
+symmetric-with-merge --left-right main...b
+range-boundary --boundary a..main
+symmetric-boundary --left-right --boundary main...b
+include-only-parents m1^@
+exclude-parents m1^!
+range-reverse --reverse a..main
+range-first-parent --first-parent c1..main
+LISTS
+
+while read -r name args; do
+  git rev-list --topo-order $args > "$name.list"
+done < lists
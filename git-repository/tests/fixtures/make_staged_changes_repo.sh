@@ -0,0 +1,23 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+
+mkdir dir other
+echo a > a
+echo b > b
+seq 10 > dir/c
+seq 20 > dir/d
+echo e > other/e
+git add .
+git commit -q -m c1
+
+echo a1 >> a
+git add a
+git rm -q b
+git mv dir/d dir/d-renamed
+echo new > new
+git add new
+
+echo untracked > untracked
+echo e1 >> other/e
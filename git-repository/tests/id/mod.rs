@@ -68,6 +68,7 @@ fn display_and_debug() -> crate::Result {
 }
 
 mod ancestors {
+    use git_repository as git;
     use git_traverse::commit;
 
     #[test]
@@ -99,4 +100,80 @@ mod ancestors {
         );
         Ok(())
     }
+
+    #[test]
+    fn topo_order_and_reverse() -> crate::Result {
+        let repo = crate::repo("make_repo_with_fork_and_dates.sh")?.to_thread_local();
+        let head = repo.head()?.into_fully_peeled_id().expect("born")?;
+        for sorting in [
+            commit::Sorting::TopoOrder,
+            commit::Sorting::DateOrder,
+            commit::Sorting::AuthorDateOrder,
+        ] {
+            let commits = head
+                .ancestors()
+                .sorting(sorting)
+                .all()?
+                .collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(commits.len(), 4);
+            assert_eq!(commits[0].id, head, "the tip comes first as it has no children");
+
+            let mut reversed = head
+                .ancestors()
+                .sorting(sorting)
+                .reverse()
+                .all()?
+                .collect::<Result<Vec<_>, _>>()?;
+            reversed.reverse();
+            assert_eq!(reversed, commits);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn changed_paths() -> crate::Result {
+        let (repo, _tmp) = crate::repo_rw("make_diff_repo.sh")?;
+        let head = repo.head_id()?;
+        let summaries_of = |paths: &[&str]| -> crate::Result<Vec<String>> {
+            head.ancestors()
+                .changed_paths(paths.iter().copied())
+                .all()?
+                .map(|info| -> crate::Result<_> {
+                    Ok(info?.id.object()?.into_commit().message()?.summary().to_string())
+                })
+                .collect()
+        };
+        let assert_changed_paths = || -> crate::Result {
+            assert_eq!(summaries_of(&["dir"])?, ["c4-copy", "c2", "c1"]);
+            assert_eq!(summaries_of(&["dir/c"])?, ["c2", "c1"]);
+            assert_eq!(
+                summaries_of(&["a"])?,
+                ["r1-identity", "c3", "c2", "c1"],
+                "renames are a deletion of the source"
+            );
+            assert_eq!(summaries_of(&["dir2/s", "d"])?, ["r2-similar", "s1", "c2", "c1"]);
+            assert_eq!(summaries_of(&["unknown"])?.len(), 0);
+            assert_eq!(summaries_of(&[])?.len(), 7, "without paths, all commits are returned");
+            Ok(())
+        };
+        assert_changed_paths()?;
+
+        repo.write_commit_graph(
+            Some(head),
+            git::commitgraph::write::Options {
+                mode: git::commitgraph::write::Mode::Single,
+                changed_paths: true,
+            },
+            git::progress::Discard,
+            &std::sync::atomic::AtomicBool::default(),
+        )?
+        .expect("written");
+        let graph = git::commitgraph::Graph::from_info_dir(repo.commit_graph_dir())?;
+        assert_eq!(
+            graph.bloom_filter_settings(),
+            Some(git::commitgraph::bloom::Settings::default())
+        );
+        assert_changed_paths()?;
+        Ok(())
+    }
 }
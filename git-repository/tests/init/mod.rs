@@ -75,6 +75,38 @@ mod non_bare {
         );
         Ok(())
     }
+    #[test]
+    fn init_with_sha256_object_hash_is_picked_up_when_opening() -> crate::Result {
+        let tmp = tempfile::tempdir()?;
+        let repo: git::Repository = git::ThreadSafeRepository::init_opts(
+            tmp.path(),
+            git::create::Kind::Bare,
+            git::create::Options {
+                object_hash: git::hash::Kind::Sha256,
+                ..Default::default()
+            },
+            git::open::Options::isolated(),
+        )?
+        .into();
+        assert_eq!(repo.object_hash(), git::hash::Kind::Sha256);
+
+        let blob_id = repo.write_blob("hello")?.detach();
+        assert_eq!(
+            blob_id,
+            git::hash::ObjectId::from_hex(b"8aec4e4876f854f688d0ebfc8f37598f38e5fd6903cccc850ca36591175aeb60")?,
+            "hashes are computed with sha256"
+        );
+
+        let repo = git::open_opts(repo.git_dir(), git::open::Options::isolated())?;
+        assert_eq!(
+            repo.object_hash(),
+            git::hash::Kind::Sha256,
+            "the object format is persisted"
+        );
+        assert_eq!(repo.find_object(blob_id)?.data, b"hello");
+        Ok(())
+    }
+
     #[test]
     fn init_into_empty_directory_creates_a_dot_git_dir() -> crate::Result {
         let tmp = tempfile::tempdir()?;
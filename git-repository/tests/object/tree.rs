@@ -8,11 +8,11 @@ mod diff {
     use crate::named_repo;
 
     #[test]
-    fn changes_against_tree_modified() {
+    fn changes_against_tree_modified() -> crate::Result {
         let repo = named_repo("make_diff_repo.sh").unwrap();
         let from = tree_named(&repo, "@^{/c3}~1");
         let to = tree_named(&repo, ":/c3");
-        from.changes()
+        from.changes()?
             .for_each_to_obtain_tree(&to, |change| -> Result<_, Infallible> {
                 assert_eq!(change.location, "", "without configuration the location field is empty");
                 match change.event {
@@ -27,7 +27,9 @@ mod diff {
                         assert_eq!(previous_id.object().unwrap().data.as_bstr(), "a\n");
                         assert_eq!(id.object().unwrap().data.as_bstr(), "a\na1\n");
                     }
-                    Event::Deletion { .. } | Event::Addition { .. } => unreachable!("only modification is expected"),
+                    Event::Deletion { .. } | Event::Addition { .. } | Event::Rewrite { .. } => {
+                        unreachable!("only modification is expected")
+                    }
                 };
 
                 let diff = change.event.diff().expect("changed file").expect("objects available");
@@ -44,18 +46,18 @@ mod diff {
                 })
                 .expect("infallible");
                 Ok(Default::default())
-            })
-            .unwrap();
+            })?;
+        Ok(())
     }
 
     #[test]
-    fn changes_against_tree_with_filename_tracking() {
+    fn changes_against_tree_with_filename_tracking() -> crate::Result {
         let repo = named_repo("make_diff_repo.sh").unwrap();
         let from = repo.empty_tree();
         let to = tree_named(&repo, ":/c1");
 
         let mut expected = vec!["a", "b", "c", "d"];
-        from.changes()
+        from.changes()?
             .track_filename()
             .for_each_to_obtain_tree(&to, |change| -> Result<_, Infallible> {
                 expected.retain(|name| name != change.location);
@@ -65,7 +67,7 @@ mod diff {
         assert_eq!(expected, Vec::<&str>::new(), "all paths should have been seen");
 
         let mut expected = vec!["a", "b", "dir/c", "d"];
-        from.changes()
+        from.changes()?
             .track_path()
             .for_each_to_obtain_tree(&to, |change| -> Result<_, Infallible> {
                 expected.retain(|name| name != change.location);
@@ -75,7 +77,7 @@ mod diff {
         assert_eq!(expected, Vec::<&str>::new(), "all paths should have been seen");
 
         let err = from
-            .changes()
+            .changes()?
             .track_path()
             .for_each_to_obtain_tree(&to, |_change| {
                 Err(std::io::Error::new(std::io::ErrorKind::Other, "custom error"))
@@ -86,6 +88,232 @@ mod diff {
             "The user-provided callback failed",
             "custom errors made visible and not squelched"
         );
+        Ok(())
+    }
+
+    #[test]
+    fn changes_against_index_match_the_changes_against_its_tree() -> crate::Result {
+        let repo = named_repo("make_diff_repo.sh")?;
+        let index = repo.open_index()?;
+        let to = tree_named(&repo, "HEAD");
+        for rev_spec in [":/c1", ":/c3", ":/r2-similar", "HEAD"] {
+            let from = tree_named(&repo, rev_spec);
+            let mut expected = Vec::new();
+            from.changes()?
+                .track_path()
+                .track_rewrites(None)
+                .for_each_to_obtain_tree(&to, |change| -> Result<_, Infallible> {
+                    let entry_mode = match change.event {
+                        Event::Addition { entry_mode, .. }
+                        | Event::Deletion { entry_mode, .. }
+                        | Event::Modification { entry_mode, .. }
+                        | Event::Rewrite { entry_mode, .. } => entry_mode,
+                    };
+                    if !entry_mode.is_tree() {
+                        expected.push((change.location.to_owned(), format!("{:?}", change.event)));
+                    }
+                    Ok(Default::default())
+                })?;
+
+            let mut actual = Vec::new();
+            from.changes()?
+                .track_path()
+                .track_rewrites(None)
+                .for_each_to_obtain_index(&index, |change| -> Result<_, Infallible> {
+                    actual.push((change.location.to_owned(), format!("{:?}", change.event)));
+                    Ok(Default::default())
+                })?;
+            expected.sort();
+            actual.sort();
+            assert_eq!(actual, expected, "{rev_spec}: the index represents the HEAD tree");
+        }
+        Ok(())
+    }
+
+    mod track_rewrites {
+        use std::convert::Infallible;
+
+        use git_object::tree::EntryMode;
+        use git_repository::object::tree::diff::{
+            change::Event,
+            rewrites::{Copies, CopySource},
+            Rewrites,
+        };
+
+        use super::tree_named;
+        use crate::named_repo;
+
+        fn is_tree_modification(event: &Event<'_, '_, '_>) -> bool {
+            matches!(
+                event,
+                Event::Modification {
+                    entry_mode: EntryMode::Tree,
+                    ..
+                }
+            )
+        }
+
+        #[test]
+        fn identity_renames_are_tracked_by_default() -> crate::Result {
+            let repo = named_repo("make_diff_repo.sh")?;
+            let from = tree_named(&repo, "@^{/r1-identity}~1");
+            let to = tree_named(&repo, ":/r1-identity");
+
+            let mut seen = 0;
+            from.changes()?
+                .track_path()
+                .for_each_to_obtain_tree(&to, |change| -> Result<_, Infallible> {
+                    if is_tree_modification(&change.event) {
+                        return Ok(Default::default());
+                    }
+                    seen += 1;
+                    match change.event {
+                        Event::Rewrite {
+                            source_location,
+                            source_entry_mode,
+                            source_id,
+                            entry_mode,
+                            id,
+                            copy,
+                        } => {
+                            assert_eq!(source_location, "a");
+                            assert_eq!(change.location, "a-renamed");
+                            assert_eq!(source_entry_mode, EntryMode::Blob);
+                            assert_eq!(entry_mode, EntryMode::Blob);
+                            assert_eq!(source_id, id, "identity is the same");
+                            assert!(!copy);
+                        }
+                        _ => unreachable!("only a rename is expected"),
+                    }
+                    Ok(Default::default())
+                })?;
+            assert_eq!(seen, 1);
+            Ok(())
+        }
+
+        #[test]
+        fn similar_renames_need_enough_similarity() -> crate::Result {
+            let repo = named_repo("make_diff_repo.sh")?;
+            let from = tree_named(&repo, "@^{/r2-similar}~1");
+            let to = tree_named(&repo, ":/r2-similar");
+
+            let mut seen = 0;
+            from.changes()?
+                .track_path()
+                .for_each_to_obtain_tree(&to, |change| -> Result<_, Infallible> {
+                    if is_tree_modification(&change.event) {
+                        return Ok(Default::default());
+                    }
+                    seen += 1;
+                    match change.event {
+                        Event::Rewrite {
+                            source_location,
+                            source_id,
+                            id,
+                            copy,
+                            ..
+                        } => {
+                            assert_eq!(source_location, "dir2/s");
+                            assert_eq!(change.location, "dir2/s-renamed");
+                            assert_ne!(source_id, id, "content was modified as well");
+                            assert!(!copy);
+                            let counts = change
+                                .event
+                                .diff()
+                                .expect("blobs")
+                                .expect("objects available")
+                                .line_counts();
+                            assert_eq!(counts.insertions, 1);
+                            assert_eq!(counts.removals, 0);
+                        }
+                        _ => unreachable!("only a rename is expected"),
+                    }
+                    Ok(Default::default())
+                })?;
+            assert_eq!(seen, 1);
+
+            for rewrites in [
+                None,
+                Some(Rewrites {
+                    percentage: None,
+                    ..Default::default()
+                }),
+                Some(Rewrites {
+                    percentage: Some(0.95),
+                    ..Default::default()
+                }),
+            ] {
+                let mut locations = Vec::new();
+                from.changes()?
+                    .track_path()
+                    .track_rewrites(rewrites)
+                    .for_each_to_obtain_tree(&to, |change| -> Result<_, Infallible> {
+                        if is_tree_modification(&change.event) {
+                            return Ok(Default::default());
+                        }
+                        match change.event {
+                            Event::Addition { .. } | Event::Deletion { .. } => {
+                                locations.push(change.location.to_owned())
+                            }
+                            _ => unreachable!("no rewrite is expected with {:?}", rewrites),
+                        }
+                        Ok(Default::default())
+                    })?;
+                assert_eq!(locations, ["dir2/s", "dir2/s-renamed"]);
+            }
+            Ok(())
+        }
+
+        #[test]
+        fn copies_from_unchanged_files_require_the_source_tree() -> crate::Result {
+            let repo = named_repo("make_diff_repo.sh")?;
+            let from = tree_named(&repo, "@^{/c4-copy}~1");
+            let to = tree_named(&repo, ":/c4-copy");
+
+            for (copies, expect_copy) in [
+                (Copies::default(), false),
+                (
+                    Copies {
+                        source: CopySource::FromSetOfModifiedFilesAndSourceTree,
+                        ..Default::default()
+                    },
+                    true,
+                ),
+            ] {
+                let mut seen = 0;
+                from.changes()?
+                    .track_path()
+                    .track_rewrites(Some(Rewrites {
+                        copies: Some(copies),
+                        ..Default::default()
+                    }))
+                    .for_each_to_obtain_tree(&to, |change| -> Result<_, Infallible> {
+                        if is_tree_modification(&change.event) {
+                            return Ok(Default::default());
+                        }
+                        seen += 1;
+                        assert_eq!(change.location, "dir/s-copy");
+                        match change.event {
+                            Event::Rewrite {
+                                source_location,
+                                source_id,
+                                id,
+                                copy,
+                                ..
+                            } if expect_copy => {
+                                assert_eq!(source_location, "dir2/s-renamed");
+                                assert_eq!(source_id, id);
+                                assert!(copy);
+                            }
+                            Event::Addition { .. } if !expect_copy => {}
+                            _ => unreachable!("unexpected event"),
+                        }
+                        Ok(Default::default())
+                    })?;
+                assert_eq!(seen, 1);
+            }
+            Ok(())
+        }
     }
 
     fn tree_named<'repo>(repo: &'repo git::Repository, rev_spec: &str) -> git::Tree<'repo> {
@@ -19,6 +19,68 @@ mod log {
             "rebase (merge): the subject"
         );
     }
+
+    #[test]
+    fn expire_unreachable_and_delete_entries() -> crate::Result {
+        let (mut repo, _tmp) = crate::repo_rw("make_basic_repo.sh")?;
+        let mut main = repo.find_reference("main")?;
+        let c1 = repo.rev_parse_single(":/c1")?.detach();
+        let c2 = main.id().detach();
+        main.set_target_id(c1, "reset: moving to c1")?;
+
+        let log_len = |reference: &git::Reference<'_>| -> crate::Result<usize> {
+            Ok(reference.log_iter().all()?.expect("log present").count())
+        };
+        assert_eq!(log_len(&main)?, 3);
+
+        let options = git_ref::file::log::expire::Options {
+            expire_unreachable: Some(u32::MAX),
+            dry_run: true,
+            ..Default::default()
+        };
+        let outcome = main.log_expire(options)?.expect("log present");
+        assert_eq!(outcome.kept, 1);
+        assert_eq!(
+            outcome
+                .removed
+                .iter()
+                .map(|line| (line.previous_oid, line.new_oid))
+                .collect::<Vec<_>>(),
+            [(c1, c2), (c2, c1)],
+            "all entries mentioning the commit that isn't reachable from main anymore are removed"
+        );
+        assert_eq!(log_len(&main)?, 3, "dry-runs don't change anything");
+
+        let options = repo.reflog_expire_options()?;
+        assert!(options.expire_total < options.expire_unreachable.map(|t| t + 1));
+        let outcome = main.log_expire(options)?.expect("log present");
+        assert_eq!(
+            (outcome.kept, outcome.removed.len()),
+            (1, 2),
+            "the fixture entries are old, but the most recent one was just created"
+        );
+
+        {
+            let mut config = repo.config_snapshot_mut();
+            config.set_raw_value("gc", None, "reflogExpire", "never")?;
+            config.set_raw_value("gc", None, "reflogExpireUnreachable", "false")?;
+        }
+        let main = repo.find_reference("main")?;
+        let options = repo.reflog_expire_options()?;
+        assert_eq!((options.expire_total, options.expire_unreachable), (None, None));
+        let outcome = main.log_expire(options)?.expect("log present");
+        assert_eq!((outcome.kept, outcome.removed.len()), (1, 0), "nothing ever expires");
+
+        let outcome = main.log_delete_entry(0, false)?.expect("log present");
+        assert_eq!(outcome.removed.len(), 1);
+        assert_eq!(log_len(&main)?, 0, "the log is empty, but still present");
+
+        assert!(
+            repo.find_reference("HEAD")?.log_delete_entry(0, true)?.is_some(),
+            "HEAD has a log as well"
+        );
+        Ok(())
+    }
 }
 mod find {
     use std::convert::TryInto;
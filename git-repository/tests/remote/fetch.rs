@@ -79,7 +79,7 @@ mod blocking_and_async_io {
                     assert_eq!(update_refs.edits.len(), expected_ref_count);
                     assert!(!write_pack_bundle.keep_path.as_deref().map_or(false, |p| p.is_file()), ".keep files are deleted if at least one ref-edit was made or the pack is empty");
                 },
-                _ => unreachable!("Naive negotiation sends the same have and wants, resulting in an empty pack (technically no change, but we don't detect it) - empty packs are fine")
+                _ => unreachable!("Negotiation sends our tips as haves, which include the wants, resulting in an empty pack (technically no change, but we don't detect it) - empty packs are fine")
             }
         }
         Ok(())
@@ -137,12 +137,217 @@ mod blocking_and_async_io {
                     assert_eq!(update_refs.edits.len(), expected_ref_edits, "{fetch_tags:?}");
                     assert_eq!(write_pack_bundle.keep_path.as_deref().map_or(false, |p| p.is_file()), update_refs.edits.is_empty(),".keep are kept if there was no edit to prevent `git gc` from clearing out the pack as it's not referred to necessarily");
                 },
-                _ => unreachable!("Naive negotiation sends the same have and wants, resulting in an empty pack (technically no change, but we don't detect it) - empty packs are fine")
+                _ => unreachable!("Negotiation sends our tips as haves, which include the wants, resulting in an empty pack (technically no change, but we don't detect it) - empty packs are fine")
             }
         }
         Ok(())
     }
 
+    #[maybe_async::test(
+        feature = "blocking-network-client",
+        async(feature = "async-network-client-async-std", async_std::test)
+    )]
+    async fn fetch_pack_with_many_local_commits_negotiates_over_multiple_rounds() -> crate::Result {
+        let daemon = spawn_git_daemon_if_async(repo_path("clone-as-base-with-changes"))?;
+        for (algorithm, expected_num_objects) in [("consecutive", Some(4)), ("skipping", Some(4)), ("noop", None)] {
+            for (version, with_commit_graph) in [
+                (git::protocol::transport::Protocol::V1, false),
+                (git::protocol::transport::Protocol::V2, false),
+                (git::protocol::transport::Protocol::V2, true),
+            ] {
+                let (mut repo, _tmp) = repo_rw("two-origins");
+                {
+                    let mut config = repo.config_snapshot_mut();
+                    config.set_raw_value("protocol", None, "version", (version as u8).to_string().as_str())?;
+                    config.set_raw_value("fetch", None, "negotiationAlgorithm", algorithm)?;
+                }
+                let head = repo.head_commit()?;
+                let tree = head.tree_id()?.detach();
+                let mut parent = head.id;
+                for time in 0..50 {
+                    let signature = git::actor::Signature {
+                        name: "local".into(),
+                        email: "local@example.com".into(),
+                        time: git::actor::Time::new(2_000_000_000 + time, 0),
+                    };
+                    parent = repo
+                        .commit_as(
+                            &signature,
+                            &signature,
+                            "refs/heads/local-changes",
+                            "a local change the remote doesn't know",
+                            tree,
+                            Some(parent),
+                        )?
+                        .detach();
+                }
+                if with_commit_graph {
+                    repo.write_commit_graph(
+                        Some(parent),
+                        git::commitgraph::write::Options {
+                            mode: git::commitgraph::write::Mode::Single,
+                            changed_paths: false,
+                        },
+                        progress::Discard,
+                        &AtomicBool::default(),
+                    )?
+                    .expect("written");
+                }
+
+                let remote = into_daemon_remote_if_async(
+                    repo.find_remote("changes-on-top-of-origin")?
+                        .with_fetch_tags(git::remote::fetch::Tags::None),
+                    daemon.as_ref(),
+                    None,
+                );
+                let outcome = remote
+                    .connect(Fetch, progress::Discard)
+                    .await?
+                    .prepare_fetch(Default::default())
+                    .await?
+                    .receive(&AtomicBool::default())
+                    .await?;
+                match outcome.status {
+                    fetch::Status::Change { write_pack_bundle, .. } => match expected_num_objects {
+                        Some(expected) => assert_eq!(
+                            write_pack_bundle.index.num_objects, expected,
+                            "{algorithm} {version:?} (commit-graph: {with_commit_graph}): the local commits are skipped until the common ones are found"
+                        ),
+                        None => assert!(
+                            write_pack_bundle.index.num_objects > 4,
+                            "{algorithm} {version:?}: without haves, everything is sent"
+                        ),
+                    },
+                    _ => unreachable!("there are changes to fetch"),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[maybe_async::test(
+        feature = "blocking-network-client",
+        async(feature = "async-network-client-async-std", async_std::test)
+    )]
+    async fn fetch_shallow_then_deepen_and_unshallow() -> crate::Result {
+        let daemon = spawn_git_daemon_if_async(repo_path("base"))?;
+        for version in [
+            git::protocol::transport::Protocol::V1,
+            git::protocol::transport::Protocol::V2,
+        ] {
+            let (mut repo, _tmp) = repo_rw("two-origins");
+            repo.config_snapshot_mut().set_raw_value(
+                "protocol",
+                None,
+                "version",
+                (version as u8).to_string().as_str(),
+            )?;
+            let tip = repo.find_reference("refs/remotes/origin/main")?.id().detach();
+            let num_commits =
+                |repo: &git::Repository| -> crate::Result<usize> { Ok(repo.rev_walk(Some(tip)).all()?.count()) };
+            let num_commits_unshallow = num_commits(&repo)?;
+            assert!(!repo.is_shallow());
+
+            let mut num_commits_before = 0;
+            for shallow in [
+                fetch::Shallow::DepthAtRemote(1.try_into()?),
+                fetch::Shallow::Deepen(1),
+                fetch::Shallow::Undo,
+            ] {
+                let mut remote = repo
+                    .find_remote("origin")?
+                    .with_fetch_tags(git::remote::fetch::Tags::None);
+                remote.replace_refspecs(Some("+refs/heads/main:refs/remotes/origin/main"), Fetch)?;
+                let remote = into_daemon_remote_if_async(remote, daemon.as_ref(), None);
+                let outcome = remote
+                    .connect(Fetch, progress::Discard)
+                    .await?
+                    .prepare_fetch(Default::default())
+                    .await?
+                    .with_shallow(shallow.clone())
+                    .receive(&AtomicBool::default())
+                    .await?;
+                assert!(
+                    matches!(outcome.status, fetch::Status::Change { .. }),
+                    "{version:?} {shallow:?}: a pack is received even if the tips are unchanged"
+                );
+
+                let num_commits = num_commits(&repo)?;
+                match shallow {
+                    fetch::Shallow::DepthAtRemote(_) => {
+                        assert!(repo.is_shallow(), "{version:?}: the existing history is cut off");
+                        assert_eq!(num_commits, 1, "{version:?}: traversals stop at the shallow boundary");
+                    }
+                    fetch::Shallow::Deepen(_) => {
+                        assert!(repo.is_shallow(), "{version:?}: the boundary moved");
+                        assert!(
+                            num_commits > num_commits_before && num_commits < num_commits_unshallow,
+                            "{version:?}: more history is visible, but not all of it"
+                        );
+                    }
+                    _ => {
+                        assert!(!repo.is_shallow(), "{version:?}: the shallow file was removed");
+                        assert_eq!(
+                            num_commits, num_commits_unshallow,
+                            "{version:?}: all history is visible"
+                        );
+                    }
+                }
+                num_commits_before = num_commits;
+            }
+        }
+        Ok(())
+    }
+
+    #[maybe_async::test(
+        feature = "blocking-network-client",
+        async(feature = "async-network-client-async-std", async_std::test)
+    )]
+    async fn fetch_writes_commit_graph_if_configured() -> crate::Result {
+        let daemon = spawn_git_daemon_if_async({
+            let mut p = repo_path("base");
+            p.pop();
+            p
+        })?;
+        let (mut repo, _tmp) = repo_rw("two-origins");
+        let graphs_dir = repo.commit_graph_dir().join("commit-graphs");
+        assert!(!graphs_dir.exists(), "no commit-graph to begin with");
+        repo.config_snapshot_mut()
+            .set_raw_value("fetch", None, "writeCommitGraph", "true")?;
+
+        let remote = into_daemon_remote_if_async(
+            repo.find_remote("changes-on-top-of-origin")?
+                .with_fetch_tags(git::remote::fetch::Tags::None),
+            daemon.as_ref(),
+            "clone-as-base-with-changes",
+        );
+        let outcome = remote
+            .connect(Fetch, progress::Discard)
+            .await?
+            .prepare_fetch(Default::default())
+            .await?
+            .receive(&AtomicBool::default())
+            .await?;
+        assert!(matches!(outcome.status, fetch::Status::Change { .. }));
+
+        assert!(
+            graphs_dir.join("commit-graph-chain").is_file(),
+            "commits are written into a layer of a split commit-graph"
+        );
+        let graph = git::commitgraph::Graph::from_info_dir(repo.commit_graph_dir())?;
+        let tip = repo
+            .find_reference("refs/remotes/changes-on-top-of-origin/main")?
+            .id()
+            .detach();
+        assert!(graph.lookup(tip).is_some(), "the fetched tip is part of the graph");
+        assert_eq!(
+            graph.num_commits() as usize,
+            repo.rev_walk(Some(tip)).all()?.count(),
+            "all of its history was added as there was no commit-graph yet"
+        );
+        Ok(())
+    }
+
     #[maybe_async::test(
         feature = "blocking-network-client",
         async(feature = "async-network-client-async-std", async_std::test)
@@ -0,0 +1,174 @@
+#[cfg(feature = "blocking-network-client")]
+mod blocking_io {
+    use std::sync::atomic::AtomicBool;
+
+    use git_repository as git;
+    use git_repository::remote::{
+        push::{Mode, Outcome},
+        Direction::Push,
+    };
+
+    fn repo_rw(name: &str) -> (git::Repository, git_testtools::tempfile::TempDir) {
+        let dir = git_testtools::scripted_fixture_writable_with_args(
+            "make_push_repos.sh",
+            None::<String>,
+            git_testtools::Creation::ExecuteScript,
+        )
+        .unwrap();
+        let repo = git::open_opts(dir.path().join(name), crate::restricted()).unwrap();
+        (repo, dir)
+    }
+
+    /// Push to the bare repository `remote_name`.git next to `repo` through the remote of the same name.
+    fn push(
+        repo: &git::Repository,
+        remote_name: &str,
+        specs: &[&str],
+        dry_run: bool,
+    ) -> Result<Outcome, Box<dyn std::error::Error>> {
+        let url = repo.work_dir().expect("non-bare").join(format!("../{remote_name}.git"));
+        let mut remote = repo.find_remote(remote_name)?.push_url(url)?;
+        remote.replace_refspecs(specs.iter().copied(), Push)?;
+        let out = remote
+            .connect(Push, git::progress::Discard)?
+            .prepare_push()?
+            .with_dry_run(dry_run)
+            .send(&AtomicBool::default())?;
+        Ok(out)
+    }
+
+    fn id(repo: &git::Repository, name: &str) -> Option<git::ObjectId> {
+        repo.try_find_reference(name)
+            .unwrap()
+            .map(|r| r.target().try_id().expect("peeled").to_owned())
+    }
+
+    #[test]
+    fn new_branches_and_fast_forwards_send_all_missing_objects() -> crate::Result {
+        let (repo, tmp) = repo_rw("clone");
+        let out = push(
+            &repo,
+            "base",
+            &["main", "refs/heads/feature:refs/heads/new-feature"],
+            false,
+        )?;
+
+        assert_eq!(
+            out.updates.iter().map(|u| u.mode.clone()).collect::<Vec<_>>(),
+            [Mode::FastForward, Mode::New]
+        );
+        assert_eq!(
+            out.updates[0].remote_ref, "refs/heads/main",
+            "partial names are qualified"
+        );
+        assert_eq!(
+            out.num_objects_sent, 3,
+            "the new commit, its tree and the blob, but nothing the remote has"
+        );
+        assert!(out.response.as_ref().expect("sent").is_success());
+
+        let remote = git::open_opts(tmp.path().join("base.git"), crate::restricted())?;
+        let main = id(&repo, "refs/heads/main");
+        assert_eq!(id(&remote, "refs/heads/main"), main);
+        assert_eq!(id(&remote, "refs/heads/new-feature"), main);
+        remote.find_object(main.expect("present"))?.try_into_commit()?.tree()?;
+
+        assert_eq!(out.tracking_ref_edits.len(), 2);
+        assert_eq!(id(&repo, "refs/remotes/base/main"), main, "tracking refs are updated");
+        assert_eq!(id(&repo, "refs/remotes/base/new-feature"), main);
+        Ok(())
+    }
+
+    #[test]
+    fn non_fast_forwards_are_rejected_unless_forced() -> crate::Result {
+        let (repo, tmp) = repo_rw("clone");
+        let remote = git::open_opts(tmp.path().join("base.git"), crate::restricted())?;
+        let previous = id(&remote, "refs/heads/diverged");
+
+        let out = push(&repo, "base", &["diverged", "v1"], false)?;
+        assert_eq!(
+            out.updates.iter().map(|u| u.mode.clone()).collect::<Vec<_>>(),
+            [Mode::RejectedNonFastForward, Mode::UpToDate]
+        );
+        assert!(out.response.is_none(), "nothing was sent");
+        assert_eq!(id(&remote, "refs/heads/diverged"), previous);
+
+        let out = push(&repo, "base", &["+diverged"], false)?;
+        assert_eq!(out.updates[0].mode, Mode::Forced);
+        assert_eq!(id(&remote, "refs/heads/diverged"), id(&repo, "refs/heads/diverged"));
+        assert_eq!(
+            id(&repo, "refs/remotes/base/diverged"),
+            id(&repo, "refs/heads/diverged")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn deletions_remove_the_remote_and_the_tracking_ref() -> crate::Result {
+        let (repo, tmp) = repo_rw("clone");
+        assert!(id(&repo, "refs/remotes/base/to-delete").is_some());
+
+        let out = push(
+            &repo,
+            "base",
+            &[":to-delete", ":does-not-exist", ":refs/heads/does-not-exist"],
+            false,
+        )?;
+        assert_eq!(
+            out.updates.iter().map(|u| u.mode.clone()).collect::<Vec<_>>(),
+            [
+                Mode::Delete,
+                Mode::RejectedRemoteRefDoesNotExist,
+                Mode::RejectedRemoteRefDoesNotExist
+            ],
+            "refs that don't exist on the remote can't be deleted, and no command is sent for them"
+        );
+        assert_eq!(out.updates[1].remote_ref, "does-not-exist");
+        assert_eq!(out.updates[2].remote_ref, "refs/heads/does-not-exist");
+        assert_eq!(out.response.expect("sent").refs.len(), 1);
+        assert_eq!(out.num_objects_sent, 0, "deletions don't need a pack");
+
+        let remote = git::open_opts(tmp.path().join("base.git"), crate::restricted())?;
+        assert_eq!(id(&remote, "refs/heads/to-delete"), None);
+        assert_eq!(id(&repo, "refs/remotes/base/to-delete"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn patterns_and_annotated_tags_into_empty_remote() -> crate::Result {
+        let (repo, tmp) = repo_rw("clone");
+        let out = push(&repo, "empty", &["refs/heads/*:refs/heads/*", "refs/tags/v2"], false)?;
+        assert_eq!(out.updates.len(), 4, "all three local branches and the tag");
+        assert!(out.updates.iter().all(|u| u.mode == Mode::New));
+
+        let remote = git::open_opts(tmp.path().join("empty.git"), crate::restricted())?;
+        for name in [
+            "refs/heads/main",
+            "refs/heads/feature",
+            "refs/heads/diverged",
+            "refs/tags/v2",
+        ] {
+            let id = id(&remote, name).expect("pushed");
+            assert_eq!(Some(id), self::id(&repo, name));
+            remote.find_object(id)?;
+        }
+        assert_eq!(
+            id(&repo, "refs/remotes/empty/diverged"),
+            id(&repo, "refs/heads/diverged")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn dry_run_changes_nothing() -> crate::Result {
+        let (repo, tmp) = repo_rw("clone");
+        let out = push(&repo, "base", &["main"], true)?;
+        assert_eq!(out.updates[0].mode, Mode::FastForward);
+        assert!(out.response.is_none());
+        assert!(out.tracking_ref_edits.is_empty());
+
+        let remote = git::open_opts(tmp.path().join("base.git"), crate::restricted())?;
+        assert_ne!(id(&remote, "refs/heads/main"), id(&repo, "refs/heads/main"));
+        Ok(())
+    }
+}
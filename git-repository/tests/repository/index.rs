@@ -0,0 +1,57 @@
+use std::convert::Infallible;
+
+use git_repository::object::tree::diff::{change::Event, Action};
+
+use crate::{named_repo, repo_rw};
+
+#[test]
+fn write_index_tree_reuses_the_tree_extension() -> crate::Result {
+    let (repo, _tmp) = repo_rw("make_diff_repo.sh")?;
+    let mut index = repo.open_index()?;
+    let head_tree_id = repo.head_commit()?.tree_id()?;
+
+    let outcome = repo.write_index_tree(&index)?;
+    assert_eq!(outcome.tree.id, head_tree_id);
+    assert_eq!(
+        outcome.num_trees_written, 0,
+        "the tree extension is valid after a commit"
+    );
+
+    index.set_tree(None);
+    let outcome = repo.write_index_tree(&index)?;
+    assert_eq!(outcome.tree.id, head_tree_id, "writing trees yields the same tree");
+    assert_eq!(outcome.num_trees_reused, 0);
+    assert_eq!(outcome.num_trees_written, 3, "the root tree, 'dir' and 'dir2'");
+    assert!(repo.find_object(head_tree_id).is_ok());
+    Ok(())
+}
+
+#[test]
+fn staged_changes() -> crate::Result {
+    let repo = named_repo("make_staged_changes_repo.sh")?;
+    let mut changes = Vec::new();
+    repo.staged_changes(|change| -> Result<_, Infallible> {
+        let kind = match change.event {
+            Event::Addition { .. } => "A".into(),
+            Event::Deletion { .. } => "D".into(),
+            Event::Modification { .. } => "M".into(),
+            Event::Rewrite {
+                source_location, copy, ..
+            } => format!("{}{}", if copy { "C" } else { "R" }, source_location),
+        };
+        changes.push((change.location.to_string(), kind));
+        Ok(Action::Continue)
+    })?;
+    changes.sort();
+    assert_eq!(
+        changes,
+        [
+            ("a".to_string(), "M".to_string()),
+            ("b".into(), "D".into()),
+            ("dir/d-renamed".into(), "Rdir/d".into()),
+            ("new".into(), "A".into()),
+        ],
+        "unstaged changes and untracked files are not included"
+    );
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use git_repository::Repository;
 
 mod config;
+mod index;
 mod object;
 mod open;
 mod reference;
@@ -11,7 +12,7 @@ mod worktree;
 #[test]
 fn size_in_memory() {
     let actual_size = std::mem::size_of::<Repository>();
-    let limit = 1000;
+    let limit = 1050;
     assert!(
         actual_size <= limit,
         "size of Repository shouldn't change without us noticing, it's meant to be cloned: should have been below {:?}, was {} (bigger on windows)",
@@ -77,7 +77,7 @@ mod find {
                 _ => unreachable!("BUG"),
             }
             for commit_id in repo.head()?.peeled()?.id().expect("born").ancestors().all()? {
-                let commit = commit_id?;
+                let commit = commit_id?.id;
                 assert_eq!(commit.object()?.kind, git_object::Kind::Commit);
                 if round == 2 {
                     assert_eq!(
@@ -308,6 +308,32 @@ mod commit {
     }
 }
 
+mod prune_expire {
+    use std::time::{Duration, SystemTime};
+
+    use crate::repository::object::empty_bare_repo;
+
+    #[test]
+    fn defaults_to_two_weeks_and_can_be_configured() -> crate::Result {
+        let (_tmp, mut repo) = empty_bare_repo()?;
+        let two_weeks_ago = (SystemTime::now() - Duration::from_secs(14 * 24 * 60 * 60))
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_secs() as u32;
+        let expire = repo.prune_expire()?.expect("objects expire by default");
+        assert!(
+            expire.abs_diff(two_weeks_ago) < 10,
+            "unreachable objects expire after two weeks: {expire} ~= {two_weeks_ago}"
+        );
+
+        for (value, expected) in [("never", None), ("now", Some(u32::MAX)), ("1234", Some(1234))] {
+            repo.config_snapshot_mut()
+                .set_raw_value("gc", None, "pruneExpire", value)?;
+            assert_eq!(repo.prune_expire()?, expected, "{value}");
+        }
+        Ok(())
+    }
+}
+
 fn empty_bare_repo() -> crate::Result<(tempfile::TempDir, git::Repository)> {
     let tmp = tempfile::tempdir()?;
     let repo = git::ThreadSafeRepository::init_opts(
@@ -47,6 +47,158 @@ mod not_a_repository {
     }
 }
 
+mod ref_storage {
+    use git_repository as git;
+    use git_testtools::tempfile;
+
+    #[test]
+    fn reftables_can_be_opened_and_edited() -> crate::Result {
+        let tmp = tempfile::tempdir()?;
+        let repo = git::init_bare(tmp.path())?;
+        let config_path = repo.git_dir().join("config");
+        let mut config = std::fs::read_to_string(&config_path)?;
+        config.push_str("[core]\n\trepositoryFormatVersion = 1\n[extensions]\n\trefStorage = reftable\n");
+        std::fs::write(config_path, config)?;
+        std::fs::create_dir(repo.git_dir().join("reftable"))?;
+        std::fs::write(repo.git_dir().join("reftable").join("tables.list"), "")?;
+
+        let repo = git::open_opts(repo.git_dir(), git::open::Options::isolated())?;
+        assert!(repo.reftable.is_some(), "the configured ref storage is used");
+        let id = repo.write_object(git::objs::Tree::empty())?.detach();
+        repo.reference(
+            "refs/heads/main",
+            id,
+            git::refs::transaction::PreviousValue::MustNotExist,
+            "create main",
+        )?;
+        repo.edit_reference(git::refs::transaction::RefEdit {
+            change: git::refs::transaction::Change::Update {
+                log: Default::default(),
+                expected: git::refs::transaction::PreviousValue::Any,
+                new: git::refs::Target::Symbolic("refs/heads/main".try_into()?),
+            },
+            name: "HEAD".try_into()?,
+            deref: false,
+        })?;
+        assert!(
+            !repo.git_dir().join("refs").join("heads").join("main").exists(),
+            "no loose reference was written"
+        );
+
+        let repo = git::open_opts(repo.git_dir(), git::open::Options::isolated())?;
+        assert_eq!(
+            repo.head_name()?.expect("symbolic").as_bstr(),
+            "refs/heads/main",
+            "HEAD is read from the reftable, not from the HEAD file"
+        );
+        assert_eq!(repo.head_id()?, id);
+        assert_eq!(repo.find_reference("main")?.id(), id);
+        let names = repo
+            .references()?
+            .all()?
+            .map(|r| r.expect("readable").name().as_bstr().to_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["refs/heads/main"]);
+        Ok(())
+    }
+
+    #[test]
+    fn rev_specs_and_reflogs_use_reftables() -> crate::Result {
+        use git::refs::{
+            transaction::{Change, LogChange, PreviousValue, RefEdit},
+            Target,
+        };
+        let tmp = tempfile::tempdir()?;
+        let repo = git::init_bare(tmp.path())?;
+        let config_path = repo.git_dir().join("config");
+        let mut config = std::fs::read_to_string(&config_path)?;
+        config.push_str(
+            "[core]\n\trepositoryFormatVersion = 1\n\tlogAllRefUpdates = true\n[extensions]\n\trefStorage = reftable\n",
+        );
+        config.push_str("[user]\n\tname = committer\n\temail = committer@example.com\n");
+        std::fs::write(config_path, config)?;
+        std::fs::create_dir(repo.git_dir().join("reftable"))?;
+        std::fs::write(repo.git_dir().join("reftable").join("tables.list"), "")?;
+
+        let repo = git::open_opts(repo.git_dir(), git::open::Options::isolated())?;
+        let main_id = repo.write_object(git::objs::Tree::empty())?.detach();
+        let other_id = repo.write_blob("other")?.detach();
+        repo.reference("refs/heads/main", main_id, PreviousValue::MustNotExist, "create main")?;
+        repo.reference(
+            "refs/heads/other",
+            other_id,
+            PreviousValue::MustNotExist,
+            "create other",
+        )?;
+        let set_head = |new: Target, message: &str| {
+            repo.edit_reference(RefEdit {
+                change: Change::Update {
+                    log: LogChange {
+                        message: message.into(),
+                        ..Default::default()
+                    },
+                    expected: PreviousValue::Any,
+                    new,
+                },
+                name: "HEAD".try_into().expect("valid"),
+                deref: false,
+            })
+        };
+        set_head(Target::Peeled(other_id), "checkout: moving from main to other")?;
+        set_head(Target::Peeled(main_id), "checkout: moving from other to main")?;
+        set_head(Target::Symbolic("refs/heads/main".try_into()?), "")?;
+
+        let repo = git::open_opts(repo.git_dir(), git::open::Options::isolated())?;
+        for (spec, expected) in [
+            ("main", main_id),
+            ("other", other_id),
+            ("main@{0}", main_id),
+            ("HEAD@{1}", other_id),
+            ("@{-1}", other_id),
+            ("@{-2}", main_id),
+        ] {
+            assert_eq!(repo.rev_parse_single(spec)?, expected, "{spec}");
+        }
+
+        let head_log: Vec<_> = repo
+            .head()?
+            .log_iter()
+            .rev()?
+            .expect("log present")
+            .map(|line| line.expect("valid").message)
+            .collect();
+        assert_eq!(
+            head_log,
+            [
+                "checkout: moving from other to main",
+                "checkout: moving from main to other"
+            ]
+        );
+        let main = repo.find_reference("main")?;
+        let main_log = main
+            .log_iter()
+            .all()?
+            .expect("log present")
+            .map(|line| line.map(|line| line.new_oid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(main_log, [main_id]);
+        assert_eq!(
+            repo.head()?.prior_checked_out_branches()?.expect("log present"),
+            [
+                ("main".into(), git::hash::Kind::Sha1.null()),
+                ("other".into(), other_id)
+            ]
+        );
+
+        let head = repo.find_reference("HEAD")?;
+        let outcome = head.log_delete_entry(1, false)?.expect("log present");
+        assert_eq!(outcome.kept, 1);
+        assert_eq!(head.log_iter().rev()?.expect("log present").count(), 1);
+        assert!(repo.rev_parse_single("HEAD@{1}").is_err(), "the entry was removed");
+        Ok(())
+    }
+}
+
 mod open_path_as_is {
     use git_repository as git;
 
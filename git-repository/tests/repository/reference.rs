@@ -227,3 +227,196 @@ mod head {
         Ok(())
     }
 }
+
+mod rename_and_copy {
+    use git_repository as git;
+
+    fn repo_with_branch_config() -> crate::Result<(git::Repository, git_testtools::tempfile::TempDir)> {
+        let (repo, keep) = crate::basic_rw_repo()?;
+        let config_path = repo.git_dir().join("config");
+        let mut config = std::fs::read(&config_path)?;
+        config.extend_from_slice(b"[branch \"main\"]\n\tremote = origin\n\tmerge = refs/heads/main\n\trebase\n");
+        std::fs::write(config_path, config)?;
+        Ok((git::open_opts(repo.path(), crate::restricted())?, keep))
+    }
+
+    #[test]
+    fn rename_moves_head_reflog_and_branch_configuration() -> crate::Result {
+        let (mut repo, _keep) = repo_with_branch_config()?;
+        let id = repo.head_id()?.detach();
+        let log_len = repo.find_reference("main")?.log_iter().all()?.expect("present").count();
+
+        let renamed = repo.rename_reference("refs/heads/main", "refs/heads/trunk", "renamed")?;
+        assert_eq!(renamed.name().as_bstr(), "refs/heads/trunk");
+        assert_eq!(renamed.id(), id);
+
+        assert!(repo.try_find_reference("main")?.is_none(), "the source is gone");
+        assert_eq!(
+            repo.head_name()?.expect("symbolic").as_bstr(),
+            "refs/heads/trunk",
+            "HEAD follows the renamed branch"
+        );
+        let trunk = repo.find_reference("trunk")?;
+        let mut log_iter = trunk.log_iter();
+        let log: Vec<_> = log_iter.all()?.expect("moved").collect::<Result<_, _>>()?;
+        assert_eq!(log.len(), log_len + 1, "the reflog moved and received another entry");
+        assert_eq!(log.last().expect("present").message, "renamed");
+
+        let config = repo.config_snapshot();
+        assert_eq!(config.string("branch.trunk.remote").as_deref(), Some("origin".into()));
+        assert_eq!(
+            config.string("branch.trunk.merge").as_deref(),
+            Some("refs/heads/main".into()),
+            "values are kept as is"
+        );
+        assert!(config.string("branch.main.remote").is_none());
+
+        let repo = git::open_opts(repo.path(), crate::restricted())?;
+        assert_eq!(
+            repo.config_snapshot().string("branch.trunk.remote").as_deref(),
+            Some("origin".into()),
+            "the configuration was persisted"
+        );
+        assert_eq!(repo.config_snapshot().boolean("branch.trunk.rebase"), Some(true));
+        let config = std::fs::read(repo.git_dir().join("config"))?;
+        assert!(
+            config.windows(8).any(|line| line == b"\trebase\n"),
+            "keys without value keep their implicit boolean value"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn a_locked_configuration_prevents_renaming() -> crate::Result {
+        let (mut repo, _keep) = repo_with_branch_config()?;
+        let lock_path = repo.git_dir().join("config.lock");
+        std::fs::write(&lock_path, b"")?;
+
+        assert!(matches!(
+            repo.rename_reference("refs/heads/main", "refs/heads/trunk", "renamed")
+                .unwrap_err(),
+            git::reference::rename::Error::LockConfig(_)
+        ));
+        assert!(
+            repo.try_find_reference("main")?.is_some(),
+            "the reference wasn't touched"
+        );
+        assert!(repo.try_find_reference("trunk")?.is_none());
+        assert!(lock_path.is_file(), "the lock of someone else is left alone");
+        Ok(())
+    }
+
+    #[test]
+    fn copy_keeps_the_source_and_duplicates_its_configuration() -> crate::Result {
+        let (mut repo, _keep) = repo_with_branch_config()?;
+        let id = repo.head_id()?.detach();
+
+        repo.copy_reference("refs/heads/main", "refs/heads/copy", "copied")?;
+        assert_eq!(repo.find_reference("main")?.id(), id);
+        assert_eq!(repo.find_reference("copy")?.id(), id);
+        assert_eq!(
+            repo.head_name()?.expect("symbolic").as_bstr(),
+            "refs/heads/main",
+            "HEAD is unaffected"
+        );
+
+        let config = repo.config_snapshot();
+        for name in ["main", "copy"] {
+            assert_eq!(
+                config.string(format!("branch.{name}.remote").as_str()).as_deref(),
+                Some("origin".into())
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn existing_or_clashing_names_are_rejected() -> crate::Result {
+        let (mut repo, _keep) = repo_with_branch_config()?;
+        repo.copy_reference("refs/heads/main", "refs/heads/other", "copied")?;
+
+        for name in ["refs/heads/other", "refs/heads/other/sub", "refs/heads/main/sub"] {
+            assert!(matches!(
+                repo.rename_reference("refs/heads/main", name, "renamed").unwrap_err(),
+                git::reference::rename::Error::Edit(_)
+            ));
+        }
+        assert!(repo.try_find_reference("main")?.is_some(), "nothing changed");
+        assert!(repo.config_snapshot().string("branch.main.remote").is_some());
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+mod transaction_hook {
+    use git_ref::transaction::PreviousValue;
+    use git_repository as git;
+
+    fn repo_with_hook(script: &str) -> crate::Result<(git::Repository, git_testtools::tempfile::TempDir)> {
+        use std::os::unix::fs::PermissionsExt;
+        let (repo, keep) = crate::basic_rw_repo()?;
+        let hook = repo.git_dir().join("hooks").join("reference-transaction");
+        std::fs::write(&hook, script)?;
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+        Ok((repo, keep))
+    }
+
+    const LOG_TO_FILE: &str = "#!/bin/sh\necho \"$1\" >> \"$GIT_DIR/hook.log\"\ncat >> \"$GIT_DIR/hook.log\"\n";
+
+    #[test]
+    fn is_informed_about_prepared_and_committed_edits() -> crate::Result {
+        let (repo, _keep) = repo_with_hook(LOG_TO_FILE)?;
+        let id = repo.head_id()?.detach();
+        repo.reference("refs/heads/new", id, PreviousValue::MustNotExist, "created")?;
+        repo.find_reference("new")?.delete()?;
+
+        let null = git::hash::Kind::Sha1.null();
+        assert_eq!(
+            std::fs::read_to_string(repo.git_dir().join("hook.log"))?,
+            format!(
+                "prepared\n{null} {id} refs/heads/new\ncommitted\n{null} {id} refs/heads/new\n\
+                 prepared\n{id} {null} refs/heads/new\ncommitted\n{id} {null} refs/heads/new\n"
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn can_abort_the_transaction_once_it_is_prepared() -> crate::Result {
+        let (repo, _keep) = repo_with_hook(&format!("{LOG_TO_FILE}test \"$1\" != prepared\n"))?;
+        let id = repo.head_id()?.detach();
+        let err = repo
+            .reference("refs/heads/new", id, PreviousValue::MustNotExist, "created")
+            .unwrap_err();
+        assert!(matches!(err, git::reference::edit::Error::AbortedByHook { .. }));
+        assert!(repo.try_find_reference("new")?.is_none(), "nothing was written");
+
+        let null = git::hash::Kind::Sha1.null();
+        assert_eq!(
+            std::fs::read_to_string(repo.git_dir().join("hook.log"))?,
+            format!("prepared\n{null} {id} refs/heads/new\naborted\n{null} {id} refs/heads/new\n")
+        );
+        Ok(())
+    }
+}
+
+mod quarantine {
+    use git_ref::transaction::PreviousValue;
+    use git_repository as git;
+    use serial_test::serial;
+
+    #[test]
+    #[serial]
+    fn reference_edits_are_refused() -> crate::Result {
+        let (repo, _keep) = crate::basic_rw_repo()?;
+        let id = repo.head_id()?.detach();
+        let err = {
+            let _env = git_testtools::Env::new().set("GIT_QUARANTINE_PATH", "objects/incoming");
+            repo.reference("refs/heads/new", id, PreviousValue::MustNotExist, "created")
+                .unwrap_err()
+        };
+        assert!(matches!(err, git::reference::edit::Error::Quarantine));
+        assert!(repo.try_find_reference("new")?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use git_repository as git;
+
+use crate::hex_to_id;
+
+#[test]
+fn queries_with_and_without_commit_graph() -> crate::Result {
+    let (repo, _tmp) = crate::repo_rw("make_repo_with_fork_and_dates.sh")?;
+    let c1 = hex_to_id("134385f6d781b7e97062102c6a483440bfda2a03");
+    let b1c1 = hex_to_id("bcb05040a6925f2ff5e10d3ae1f9264f2e8c43ac");
+    let c2 = hex_to_id("9902e3c3e8f0c569b4ab295ddf473e6de763e1e7");
+    let m1b1 = hex_to_id("288e509293165cb5630d08f4185bdf2445bf6170");
+
+    let assert_queries = || -> crate::Result {
+        assert_eq!(repo.merge_base(c2, b1c1)?.expect("present"), c1);
+        assert_eq!(repo.merge_base(m1b1, c2)?.expect("present"), c2);
+        assert_eq!(repo.merge_bases(c2, [b1c1, m1b1])?, [c2]);
+
+        assert!(repo.is_ancestor(b1c1, m1b1)?);
+        assert!(repo.is_ancestor(c1, c1)?);
+        assert!(!repo.is_ancestor(c2, b1c1)?);
+
+        assert_eq!(repo.independent_commits([c1, c2, b1c1, c2])?, [c2, b1c1]);
+        assert_eq!(repo.independent_commits([b1c1, m1b1])?, [m1b1]);
+        Ok(())
+    };
+    assert_queries()?;
+    assert!(repo.commit_graph().is_err(), "there is no commit-graph yet");
+
+    repo.write_commit_graph(
+        Some(m1b1),
+        git::commitgraph::write::Options {
+            mode: git::commitgraph::write::Mode::Single,
+            changed_paths: false,
+        },
+        git::progress::Discard,
+        &std::sync::atomic::AtomicBool::default(),
+    )?
+    .expect("written");
+    assert_eq!(repo.commit_graph()?.num_commits(), 4);
+    assert_queries()?;
+    assert_eq!(
+        repo.rev_walk(Some(m1b1)).all()?.count(),
+        4,
+        "walks use the commit-graph as well"
+    );
+    Ok(())
+}
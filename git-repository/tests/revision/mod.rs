@@ -1 +1,3 @@
+mod merge_base;
 mod spec;
+mod walk;
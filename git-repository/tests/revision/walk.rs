@@ -0,0 +1,57 @@
+use git_repository as git;
+
+use crate::util::named_repo;
+
+/// Walk the commits described by the rev-spec `args` of the list with `name` in the fixture, and format them like
+/// `git rev-list --topo-order` would.
+fn rev_list(repo: &git::Repository, name: &str, args: &str) -> crate::Result<Vec<String>> {
+    let mut specs = Vec::new();
+    let mut options = Vec::new();
+    for arg in args.split(' ') {
+        match arg {
+            "--left-right" => {}
+            "--boundary" | "--reverse" | "--first-parent" => options.push(arg),
+            spec => specs.push(repo.rev_parse(spec)?.detach()),
+        }
+    }
+    let mut platform = repo
+        .rev_walk_specs(specs)?
+        .sorting(git::traverse::commit::Sorting::TopoOrder);
+    for option in options {
+        platform = match option {
+            "--boundary" => platform.boundary(),
+            "--reverse" => platform.reverse(),
+            "--first-parent" => platform.first_parent_only(),
+            _ => unreachable!("{name}: unknown option {option}"),
+        };
+    }
+    platform
+        .all()?
+        .map(|info| -> crate::Result<_> {
+            let info = info?;
+            let mark = match (info.is_boundary, info.side) {
+                (true, _) => "-",
+                (false, Some(git::revision::walk::Side::Left)) => "<",
+                (false, Some(git::revision::walk::Side::Right)) => ">",
+                (false, None) => "",
+            };
+            Ok(format!("{mark}{}", info.id))
+        })
+        .collect()
+}
+
+#[test]
+fn ranges_exclusions_and_boundaries_like_in_git() -> crate::Result {
+    let repo = named_repo("make_rev_walk_repo.sh")?;
+    let dir = repo.work_dir().expect("non-bare");
+    let lists = std::fs::read_to_string(dir.join("lists"))?;
+    for line in lists.lines() {
+        let (name, args) = line.split_once(' ').expect("name and args");
+        let expected: Vec<_> = std::fs::read_to_string(dir.join(format!("{name}.list")))?
+            .lines()
+            .map(ToOwned::to_owned)
+            .collect();
+        assert_eq!(rev_list(&repo, name, args)?, expected, "{name}: git rev-list {args}");
+    }
+    Ok(())
+}
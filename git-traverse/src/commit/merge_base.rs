@@ -0,0 +1,348 @@
+//! Find the merge-bases of commits, learn whether a commit is an ancestor of another one, and reduce commits to those that are
+//! independent of each other.
+//!
+//! The algorithms are the ones used by `git merge-base`, which use generation numbers of the commit-graph if one is provided
+//! to stop the traversal early. Commits that aren't part of the commit-graph are decoded with `find()`.
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+};
+
+use git_hash::{oid, ObjectId};
+use git_hashtable::HashMap;
+use git_object::{commit::ref_iter::Token, CommitRefIter};
+
+/// The error returned by the functions in the [`merge_base`][self] module.
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("The commit {oid} could not be found")]
+    FindExisting {
+        oid: ObjectId,
+        source: Box<dyn std::error::Error + Send + Sync + 'static>,
+    },
+    #[error(transparent)]
+    ObjectDecode(#[from] git_object::decode::Error),
+    #[error(transparent)]
+    CommitGraph(#[from] git_commitgraph::file::commit::Error),
+}
+
+/// Return all merge-bases of `first` and `others`, that is the best common ancestors of `first` and a hypothetical merge of all
+/// `others`, similar to `git merge-base --all <first> <others>…`.
+///
+/// The merge-bases are sorted by commit time, newest first, and the list is empty if there is no common ancestor.
+/// `graph` is used to obtain commit information and generation numbers, and `find` to decode all commits it doesn't contain.
+pub fn all<Find, E>(
+    first: ObjectId,
+    others: &[ObjectId],
+    graph: Option<&git_commitgraph::Graph>,
+    find: Find,
+) -> Result<Vec<ObjectId>, Error>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<CommitRefIter<'a>, E>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    Commits::new(graph, find).merge_bases(first, others)
+}
+
+/// Return `true` if `ancestor` can be reached from `descendant`, similar to `git merge-base --is-ancestor <ancestor> <descendant>`.
+///
+/// Note that each commit is its own ancestor.
+/// `graph` is used to obtain commit information and generation numbers, and `find` to decode all commits it doesn't contain.
+pub fn is_ancestor<Find, E>(
+    ancestor: ObjectId,
+    descendant: ObjectId,
+    graph: Option<&git_commitgraph::Graph>,
+    find: Find,
+) -> Result<bool, Error>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<CommitRefIter<'a>, E>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    Commits::new(graph, find).is_ancestor(ancestor, descendant)
+}
+
+/// Return those of `commits` which can't be reached from any of the others, similar to `git merge-base --independent <commits>…`.
+///
+/// Duplicates are removed, and the order of the remaining commits is retained.
+/// `graph` is used to obtain commit information and generation numbers, and `find` to decode all commits it doesn't contain.
+pub fn independent<Find, E>(
+    commits: impl IntoIterator<Item = ObjectId>,
+    graph: Option<&git_commitgraph::Graph>,
+    find: Find,
+) -> Result<Vec<ObjectId>, Error>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<CommitRefIter<'a>, E>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut unique = Vec::new();
+    for id in commits {
+        if !unique.contains(&id) {
+            unique.push(id);
+        }
+    }
+    Commits::new(graph, find).remove_redundant(unique)
+}
+
+/// The generation number of commits that aren't in the commit-graph, which is larger than all others.
+const GENERATION_NUMBER_INFINITY: u64 = u64::MAX;
+
+type Flags = u8;
+/// The commit can be reached from the first commit.
+const PARENT1: Flags = 1 << 0;
+/// The commit can be reached from one of the other commits.
+const PARENT2: Flags = 1 << 1;
+/// The commit can be reached from a common ancestor and thus can't be a merge-base.
+const STALE: Flags = 1 << 2;
+/// The commit was added to the result already.
+const RESULT: Flags = 1 << 3;
+
+/// What we need to know about each commit.
+struct Info {
+    parents: Vec<ObjectId>,
+    commit_time: u64,
+    generation: u64,
+}
+
+/// A commit in the priority queue, ordered by generation number (if used), commit time and the order of insertion.
+#[derive(PartialEq, Eq)]
+struct Queued {
+    generation: u64,
+    commit_time: u64,
+    insertion: Reverse<usize>,
+    id: ObjectId,
+}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.generation, self.commit_time, self.insertion).cmp(&(other.generation, other.commit_time, other.insertion))
+    }
+}
+
+/// Commit information obtained from the commit-graph or by decoding commits, cached for the duration of a single query.
+struct Commits<'graph, Find> {
+    graph: Option<&'graph git_commitgraph::Graph>,
+    corrected_commit_dates: bool,
+    find: Find,
+    buf: Vec<u8>,
+    infos: HashMap<ObjectId, Info>,
+}
+
+impl<'graph, Find, E> Commits<'graph, Find>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<CommitRefIter<'a>, E>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn new(graph: Option<&'graph git_commitgraph::Graph>, find: Find) -> Self {
+        Commits {
+            graph,
+            corrected_commit_dates: graph.map_or(false, git_commitgraph::Graph::has_corrected_commit_dates),
+            find,
+            buf: Vec::new(),
+            infos: HashMap::default(),
+        }
+    }
+
+    fn merge_bases(&mut self, first: ObjectId, others: &[ObjectId]) -> Result<Vec<ObjectId>, Error> {
+        if others.contains(&first) {
+            return Ok(vec![first]);
+        }
+        self.load(&first)?;
+        for id in others {
+            self.load(id)?;
+        }
+
+        let mut flags = HashMap::default();
+        let bases: Vec<_> = self
+            .paint_down_to_common(first, others, 0, &mut flags)?
+            .into_iter()
+            .filter(|id| flags[id] & STALE == 0)
+            .collect();
+        if bases.len() < 2 {
+            return Ok(bases);
+        }
+        let mut bases = self.remove_redundant(bases)?;
+        bases.sort_by_key(|id| Reverse(self.infos[id].commit_time));
+        Ok(bases)
+    }
+
+    fn is_ancestor(&mut self, ancestor: ObjectId, descendant: ObjectId) -> Result<bool, Error> {
+        self.load(&ancestor)?;
+        self.load(&descendant)?;
+        let min_generation = self.infos[&ancestor].generation;
+        if min_generation > self.infos[&descendant].generation {
+            return Ok(false);
+        }
+
+        let mut flags = HashMap::default();
+        self.paint_down_to_common(ancestor, &[descendant], min_generation, &mut flags)?;
+        Ok(flags[&ancestor] & PARENT2 != 0)
+    }
+
+    /// Remove all `commits` which can be reached from any of the other `commits`, while keeping the order of the remaining ones.
+    fn remove_redundant(&mut self, commits: Vec<ObjectId>) -> Result<Vec<ObjectId>, Error> {
+        for id in &commits {
+            self.load(id)?;
+        }
+        let mut redundant = vec![false; commits.len()];
+        let mut others = Vec::with_capacity(commits.len());
+        let mut others_index = Vec::with_capacity(commits.len());
+        for (index, id) in commits.iter().enumerate() {
+            if redundant[index] {
+                continue;
+            }
+            others.clear();
+            others_index.clear();
+            let mut min_generation = self.infos[id].generation;
+            for (other_index, other) in commits.iter().enumerate() {
+                if other_index == index || redundant[other_index] {
+                    continue;
+                }
+                others.push(*other);
+                others_index.push(other_index);
+                min_generation = min_generation.min(self.infos[other].generation);
+            }
+
+            let mut flags = HashMap::default();
+            self.paint_down_to_common(*id, &others, min_generation, &mut flags)?;
+            if flags[id] & PARENT2 != 0 {
+                redundant[index] = true;
+            }
+            for (other, other_index) in others.iter().zip(&others_index) {
+                if flags[other] & PARENT1 != 0 {
+                    redundant[*other_index] = true;
+                }
+            }
+        }
+        Ok(commits
+            .into_iter()
+            .zip(redundant)
+            .filter_map(|(id, redundant)| (!redundant).then_some(id))
+            .collect())
+    }
+
+    /// Mark all commits reachable from `first` with `PARENT1` and those reachable from `others` with `PARENT2`, and return the
+    /// commits reachable from both, newest first, while marking all of their ancestors as `STALE`.
+    ///
+    /// Commits with a generation number lower than `min_generation` aren't traversed.
+    fn paint_down_to_common(
+        &mut self,
+        first: ObjectId,
+        others: &[ObjectId],
+        min_generation: u64,
+        flags: &mut HashMap<ObjectId, Flags>,
+    ) -> Result<Vec<ObjectId>, Error> {
+        let by_generation = min_generation != 0 || self.corrected_commit_dates;
+        let mut queue = BinaryHeap::new();
+        let mut insertion = 0;
+        let mut enqueue = |queue: &mut BinaryHeap<Queued>, id: ObjectId, info: &Info| {
+            insertion += 1;
+            queue.push(Queued {
+                generation: if by_generation { info.generation } else { 0 },
+                commit_time: info.commit_time,
+                insertion: Reverse(insertion),
+                id,
+            });
+        };
+
+        *flags.entry(first).or_default() |= PARENT1;
+        if others.is_empty() {
+            return Ok(vec![first]);
+        }
+        enqueue(&mut queue, first, &self.infos[&first]);
+        for id in others {
+            *flags.entry(*id).or_default() |= PARENT2;
+            enqueue(&mut queue, *id, &self.infos[id]);
+        }
+
+        let mut result = Vec::new();
+        while queue.iter().any(|queued| flags[&queued.id] & STALE == 0) {
+            let id = queue.pop().expect("non-empty queue").id;
+            let info = &self.infos[&id];
+            if info.generation < min_generation {
+                break;
+            }
+
+            let commit_flags = flags.get_mut(&id).expect("queued commits are flagged");
+            let mut parent_flags = *commit_flags & (PARENT1 | PARENT2 | STALE);
+            if parent_flags == PARENT1 | PARENT2 {
+                if *commit_flags & RESULT == 0 {
+                    *commit_flags |= RESULT;
+                    let commit_time = info.commit_time;
+                    let pos = result
+                        .iter()
+                        .position(|other| self.infos[other].commit_time < commit_time)
+                        .unwrap_or(result.len());
+                    result.insert(pos, id);
+                }
+                parent_flags |= STALE;
+            }
+
+            for parent_index in 0..self.infos[&id].parents.len() {
+                let parent = self.infos[&id].parents[parent_index];
+                if flags
+                    .get(&parent)
+                    .map_or(false, |flags| flags & parent_flags == parent_flags)
+                {
+                    continue;
+                }
+                self.load(&parent)?;
+                *flags.entry(parent).or_default() |= parent_flags;
+                enqueue(&mut queue, parent, &self.infos[&parent]);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Obtain the information about the commit with `id` from the commit-graph or by decoding it, unless we have it already.
+    fn load(&mut self, id: &oid) -> Result<(), Error> {
+        if self.infos.contains_key(id) {
+            return Ok(());
+        }
+        let info = match self
+            .graph
+            .and_then(|graph| graph.commit_by_id(id).map(|commit| (graph, commit)))
+        {
+            Some((graph, commit)) => Info {
+                parents: commit
+                    .iter_parents()
+                    .map(|pos| pos.map(|pos| graph.id_at(pos).to_owned()))
+                    .collect::<Result<_, _>>()?,
+                commit_time: commit.committer_timestamp(),
+                generation: graph.generation_of(&commit),
+            },
+            None => {
+                let commit_iter = (self.find)(id, &mut self.buf).map_err(|err| Error::FindExisting {
+                    oid: id.to_owned(),
+                    source: err.into(),
+                })?;
+                let mut parents = Vec::new();
+                let mut commit_time = None;
+                for token in commit_iter {
+                    match token? {
+                        Token::Tree { .. } | Token::Author { .. } => continue,
+                        Token::Parent { id } => parents.push(id),
+                        Token::Committer { signature } => {
+                            commit_time = Some(signature.time.seconds_since_unix_epoch.into());
+                            break;
+                        }
+                        _a_token_past_the_committer => break,
+                    }
+                }
+                Info {
+                    parents,
+                    commit_time: commit_time.unwrap_or_default(),
+                    generation: GENERATION_NUMBER_INFINITY,
+                }
+            }
+        };
+        self.infos.insert(id.to_owned(), info);
+        Ok(())
+    }
+}
@@ -0,0 +1,803 @@
+/// An iterator over the ancestors one or more starting commits
+pub struct Ancestors<Find, Predicate, StateMut> {
+    find: Find,
+    predicate: Predicate,
+    state: StateMut,
+    parents: Parents,
+    sorting: Sorting,
+    shallow: git_hashtable::HashSet<git_hash::ObjectId>,
+    commit_graph: Option<git_commitgraph::Graph>,
+    changed_paths: Vec<git_object::bstr::BString>,
+    hidden: git_hashtable::HashSet<git_hash::ObjectId>,
+}
+
+/// Specify how to handle commit parents during traversal.
+#[derive(Copy, Clone)]
+pub enum Parents {
+    /// Traverse all parents, useful for traversing the entire ancestry.
+    All,
+    /// Only traverse along the first parent, which commonly ignores all branches.
+    First,
+}
+
+impl Default for Parents {
+    fn default() -> Self {
+        Parents::All
+    }
+}
+
+/// Specify how to sort commits during traversal.
+#[derive(Copy, Clone)]
+pub enum Sorting {
+    /// Commits are sorted as they are mentioned in the commit graph.
+    Topological,
+    /// Commits are sorted by their commit time in descending order, that is newest first.
+    ///
+    /// The sorting applies to all currently queued commit ids and thus is full.
+    ///
+    /// # Performance
+    ///
+    /// This mode benefits greatly from having an object_cache in `find()`
+    /// to avoid having to lookup each commit twice.
+    ByCommitTimeNewestFirst,
+    /// This sorting is similar to `ByCommitTimeNewestFirst`, but adds a cutoff to not return commits older than
+    /// a given time, stopping the iteration once no younger commits is queued to be traversed.
+    ///
+    /// As the query is usually repeated with different cutoff dates, this search mode benefits greatly from an object cache.
+    ByCommitTimeNewestFirstCutoffOlderThan {
+        /// The amount of seconds since unix epoch, the same value obtained by any `git_date::Time` structure and the way git counts time.
+        time_in_seconds_since_epoch: u32,
+    },
+    /// Commits are sorted so that no parent is returned before all of its children, with the commits of each line of history
+    /// returned together, just like `git rev-list --topo-order`.
+    ///
+    /// # Performance
+    ///
+    /// All reachable commits are collected before the first one is returned, which benefits from a
+    /// [commit-graph][Ancestors::commit_graph()].
+    TopoOrder,
+    /// Commits are sorted so that no parent is returned before all of its children, and otherwise by their commit time
+    /// in descending order, just like `git rev-list --date-order`.
+    ///
+    /// # Performance
+    ///
+    /// All reachable commits are collected before the first one is returned, which benefits from a
+    /// [commit-graph][Ancestors::commit_graph()].
+    DateOrder,
+    /// Commits are sorted so that no parent is returned before all of its children, and otherwise by their author time
+    /// in descending order, just like `git rev-list --author-date-order`.
+    ///
+    /// # Performance
+    ///
+    /// All reachable commits are collected before the first one is returned. As the author time isn't stored in the
+    /// commit-graph, each commit has to be decoded.
+    AuthorDateOrder,
+}
+
+impl Default for Sorting {
+    fn default() -> Self {
+        Sorting::Topological
+    }
+}
+
+pub mod merge_base;
+
+///
+pub mod ancestors {
+    use std::{
+        borrow::{Borrow, BorrowMut},
+        cmp::Reverse,
+        collections::{BinaryHeap, VecDeque},
+        iter::FromIterator,
+    };
+
+    use git_hash::{oid, ObjectId};
+    use git_hashtable::{HashMap, HashSet};
+    use git_object::{commit::ref_iter::Token, CommitRefIter};
+
+    use crate::commit::{Ancestors, Parents, Sorting};
+
+    /// The error is part of the item returned by the [Ancestors] iterator.
+    #[derive(Debug, thiserror::Error)]
+    #[allow(missing_docs)]
+    pub enum Error {
+        #[error("The commit {oid} could not be found")]
+        FindExisting {
+            oid: ObjectId,
+            source: Box<dyn std::error::Error + Send + Sync + 'static>,
+        },
+        #[error(transparent)]
+        ObjectDecode(#[from] git_object::decode::Error),
+        #[error(transparent)]
+        CommitGraph(#[from] git_commitgraph::file::commit::Error),
+    }
+
+    type TimeInSeconds = u32;
+
+    /// The state used and potentially shared by multiple graph traversals.
+    #[derive(Default, Clone)]
+    pub struct State {
+        next: VecDeque<(ObjectId, TimeInSeconds)>,
+        buf: Vec<u8>,
+        seen: HashSet<ObjectId>,
+        parents_buf: Vec<u8>,
+        parent_ids: Vec<(ObjectId, Option<TimeInSeconds>)>,
+        /// All commits in the order in which they are returned, for sortings which need to see all commits first.
+        sorted: Option<VecDeque<ObjectId>>,
+    }
+
+    impl State {
+        fn clear(&mut self) {
+            self.next.clear();
+            self.buf.clear();
+            self.seen.clear();
+            self.sorted = None;
+        }
+    }
+
+    /// Builder
+    impl<Find, Predicate, StateMut> Ancestors<Find, Predicate, StateMut> {
+        /// Change our commit parent handling mode to the given one.
+        pub fn parents(mut self, mode: Parents) -> Self {
+            self.parents = mode;
+            self
+        }
+
+        /// Set the `commits` that form the boundary of a shallow repository, whose parents are not traversed as they
+        /// are not present in the object database.
+        ///
+        /// The shallow commits themselves are still returned.
+        pub fn shallow_commits(mut self, commits: impl IntoIterator<Item = ObjectId>) -> Self {
+            self.shallow = commits.into_iter().collect();
+            self
+        }
+
+        /// Use `graph` to accelerate the traversal, or disable its use with `None`.
+        ///
+        /// The parents and commit times of all commits it contains are obtained from it instead of decoding the commit objects
+        /// with `find()`. It's also used to consult the changed-path Bloom filters it contains, if
+        /// [changed paths][Self::changed_paths()] are set.
+        ///
+        /// Note that it should be set before [sorting][Self::sorting()] to also be used for the initial tips.
+        pub fn commit_graph(mut self, graph: Option<git_commitgraph::Graph>) -> Self {
+            self.commit_graph = graph;
+            self
+        }
+
+        /// Skip all commits that definitely didn't change any of the given `paths` compared to their first parent, according to the
+        /// changed-path Bloom filters of the [commit-graph][Self::commit_graph()]. Their parents are still traversed.
+        ///
+        /// As Bloom filters have false positives, returned commits *may* have changed the `paths`, and callers are expected to
+        /// check this themselves. Without a commit-graph or Bloom filters, no commit is skipped.
+        /// `paths` are slash-separated and relative to the root of the repository, and may be files or directories.
+        pub fn changed_paths(mut self, paths: impl IntoIterator<Item = impl Into<git_object::bstr::BString>>) -> Self {
+            self.changed_paths = paths.into_iter().map(Into::into).collect();
+            self
+        }
+    }
+
+    /// Builder
+    impl<Find, Predicate, StateMut, E> Ancestors<Find, Predicate, StateMut>
+    where
+        Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<CommitRefIter<'a>, E>,
+        StateMut: BorrowMut<State>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        /// Set the sorting method, either topological or by author date
+        pub fn sorting(mut self, sorting: Sorting) -> Result<Self, Error> {
+            self.sorting = sorting;
+            if !matches!(self.sorting, Sorting::Topological) {
+                let mut cutoff_time_storage = self.sorting.cutoff_time().map(|cot| (cot, Vec::new()));
+                let state = self.state.borrow_mut();
+                for (commit_id, commit_time) in state.next.iter_mut() {
+                    let time = commit_time_of(commit_id, self.commit_graph.as_ref(), &mut self.find, &mut state.buf)?;
+                    match &mut cutoff_time_storage {
+                        Some((cutoff_time, storage)) if time >= *cutoff_time => {
+                            storage.push((*commit_id, time));
+                        }
+                        Some(_) => {}
+                        None => *commit_time = time,
+                    }
+                }
+                let mut v = match cutoff_time_storage {
+                    Some((_, storage)) => storage,
+                    None => Vec::from_iter(std::mem::take(&mut state.next).into_iter()),
+                };
+                v.sort_by(|a, b| a.1.cmp(&b.1).reverse());
+                state.next = v.into();
+            }
+            Ok(self)
+        }
+    }
+
+    /// Builder
+    impl<Find, Predicate, StateMut, E> Ancestors<Find, Predicate, StateMut>
+    where
+        Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<CommitRefIter<'a>, E>,
+        Predicate: FnMut(&oid) -> bool,
+        StateMut: BorrowMut<State>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        /// Hide all commits reachable from `tips`, so that neither they nor any of their ancestors are returned, similar to
+        /// `^<rev>` in `git rev-list`. Tips that are hidden aren't returned either.
+        ///
+        /// To know which commits to hide, all commits reachable from our tips and the hidden `tips` are traversed by commit time
+        /// until only hidden commits are left to be traversed, which is the algorithm `git` uses as well.
+        /// Just like with `git`, the result may be incorrect if commit times are skewed.
+        ///
+        /// Note that the [commit-graph][Self::commit_graph()], [shallow commits][Self::shallow_commits()] and the
+        /// [parent mode][Self::parents()] should be set beforehand as they are used to find the commits to hide.
+        pub fn hide(mut self, tips: impl IntoIterator<Item = ObjectId>) -> Result<Self, Error> {
+            /// The amount of hidden commits to traverse after only hidden commits are left, to deal with clock skew.
+            const SLOP: usize = 5;
+            const HIDDEN: u8 = 1 << 0;
+            const PROCESSED: u8 = 1 << 1;
+
+            let mut flags = HashMap::<ObjectId, u8>::default();
+            let mut parents_of = HashMap::<ObjectId, Vec<ObjectId>>::default();
+            let mut queue = BinaryHeap::new();
+            let mut seq = 0_usize;
+            let mut interesting_in_queue = 0_usize;
+
+            let ours: Vec<_> = self.state.borrow().next.iter().map(|(id, _)| *id).collect();
+            for (id, is_hidden) in ours
+                .into_iter()
+                .map(|id| (id, false))
+                .chain(tips.into_iter().map(|id| (id, true)))
+            {
+                match flags.get_mut(&id) {
+                    Some(flags) => {
+                        if is_hidden && *flags & HIDDEN == 0 {
+                            *flags |= HIDDEN;
+                            interesting_in_queue -= 1;
+                        }
+                    }
+                    None => {
+                        let time = commit_time_of(
+                            &id,
+                            self.commit_graph.as_ref(),
+                            &mut self.find,
+                            &mut self.state.borrow_mut().buf,
+                        )?;
+                        flags.insert(id, if is_hidden { HIDDEN } else { 0 });
+                        interesting_in_queue += usize::from(!is_hidden);
+                        queue.push((time, Reverse(seq), id));
+                        seq += 1;
+                    }
+                }
+            }
+
+            let mut slop = SLOP;
+            let mut last_interesting_time = None;
+            while let Some((commit_time, _, id)) = queue.pop() {
+                let is_hidden = flags[&id] & HIDDEN != 0;
+                if !is_hidden {
+                    interesting_in_queue -= 1;
+                }
+                self.collect_parents(&id)?;
+                let state = self.state.borrow_mut();
+                let mut parents = Vec::with_capacity(state.parent_ids.len());
+                for (parent_id, parent_commit_time) in state.parent_ids.drain(..) {
+                    parents.push(parent_id);
+                    match flags.get(&parent_id) {
+                        Some(_) => {
+                            if is_hidden {
+                                interesting_in_queue -= mark_hidden(parent_id, &mut flags, &parents_of);
+                            }
+                        }
+                        None => {
+                            let parent_commit_time = match parent_commit_time {
+                                Some(time) => time,
+                                None => commit_time_of(
+                                    &parent_id,
+                                    self.commit_graph.as_ref(),
+                                    &mut self.find,
+                                    &mut state.parents_buf,
+                                )?,
+                            };
+                            flags.insert(parent_id, if is_hidden { HIDDEN } else { 0 });
+                            interesting_in_queue += usize::from(!is_hidden);
+                            queue.push((parent_commit_time, Reverse(seq), parent_id));
+                            seq += 1;
+                        }
+                    }
+                }
+                *flags.get_mut(&id).expect("present") |= PROCESSED;
+                parents_of.insert(id, parents);
+
+                if !is_hidden {
+                    last_interesting_time = Some(commit_time);
+                    continue;
+                }
+                slop = match queue.peek() {
+                    None => break,
+                    Some((oldest_queued_time, _, _))
+                        if last_interesting_time.map_or(false, |time| time <= *oldest_queued_time) =>
+                    {
+                        SLOP
+                    }
+                    Some(_) if interesting_in_queue != 0 => SLOP,
+                    Some(_) => slop - 1,
+                };
+                if slop == 0 {
+                    break;
+                }
+            }
+
+            /// Mark `id` and all of its known ancestors as hidden, and return the amount of queued commits that were hidden by it.
+            fn mark_hidden(
+                id: ObjectId,
+                flags: &mut HashMap<ObjectId, u8>,
+                parents_of: &HashMap<ObjectId, Vec<ObjectId>>,
+            ) -> usize {
+                let mut newly_hidden_in_queue = 0;
+                let mut stack = vec![id];
+                while let Some(id) = stack.pop() {
+                    let flags = flags.get_mut(&id).expect("all parents are known");
+                    if *flags & HIDDEN != 0 {
+                        continue;
+                    }
+                    *flags |= HIDDEN;
+                    if *flags & PROCESSED == 0 {
+                        newly_hidden_in_queue += 1;
+                    } else if let Some(parents) = parents_of.get(&id) {
+                        stack.extend(parents.iter().copied());
+                    }
+                }
+                newly_hidden_in_queue
+            }
+
+            self.hidden = flags
+                .into_iter()
+                .filter(|(_, flags)| flags & HIDDEN != 0)
+                .map(|(id, _)| id)
+                .collect();
+            let hidden = &self.hidden;
+            self.state.borrow_mut().next.retain(|(id, _)| !hidden.contains(id));
+            Ok(self)
+        }
+    }
+
+    /// Initialization
+    impl<Find, StateMut, E> Ancestors<Find, fn(&oid) -> bool, StateMut>
+    where
+        Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<CommitRefIter<'a>, E>,
+        StateMut: BorrowMut<State>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        /// Create a new instance.
+        ///
+        /// * `find` - a way to lookup new object data during traversal by their ObjectId, writing their data into buffer and returning
+        ///    an iterator over commit tokens if the object is present and is a commit. Caching should be implemented within this function
+        ///    as needed.
+        /// * `state` - all state used for the traversal. If multiple traversals are performed, allocations can be minimized by reusing
+        ///   this state.
+        /// * `tips`
+        ///   * the starting points of the iteration, usually commits
+        ///   * each commit they lead to will only be returned once, including the tip that started it
+        pub fn new(tips: impl IntoIterator<Item = impl Into<ObjectId>>, state: StateMut, find: Find) -> Self {
+            Self::filtered(tips, state, find, |_| true)
+        }
+    }
+
+    /// Initialization
+    impl<Find, Predicate, StateMut, E> Ancestors<Find, Predicate, StateMut>
+    where
+        Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<CommitRefIter<'a>, E>,
+        Predicate: FnMut(&oid) -> bool,
+        StateMut: BorrowMut<State>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        /// Create a new instance with commit filtering enabled.
+        ///
+        /// * `find` - a way to lookup new object data during traversal by their ObjectId, writing their data into buffer and returning
+        ///    an iterator over commit tokens if the object is present and is a commit. Caching should be implemented within this function
+        ///    as needed.
+        /// * `state` - all state used for the traversal. If multiple traversals are performed, allocations can be minimized by reusing
+        ///   this state.
+        /// * `tips`
+        ///   * the starting points of the iteration, usually commits
+        ///   * each commit they lead to will only be returned once, including the tip that started it
+        /// * `predicate` - indicate whether a given commit should be included in the result as well
+        ///   as whether its parent commits should be traversed.
+        pub fn filtered(
+            tips: impl IntoIterator<Item = impl Into<ObjectId>>,
+            mut state: StateMut,
+            find: Find,
+            mut predicate: Predicate,
+        ) -> Self {
+            let tips = tips.into_iter();
+            {
+                let state = state.borrow_mut();
+                state.clear();
+                state.next.reserve(tips.size_hint().0);
+                for tip in tips.map(Into::into) {
+                    let was_inserted = state.seen.insert(tip);
+                    if was_inserted && predicate(&tip) {
+                        state.next.push_back((tip, 0));
+                    }
+                }
+            }
+            Self {
+                find,
+                predicate,
+                state,
+                parents: Default::default(),
+                sorting: Default::default(),
+                shallow: Default::default(),
+                commit_graph: None,
+                changed_paths: Vec::new(),
+                hidden: Default::default(),
+            }
+        }
+    }
+    /// Access
+    impl<Find, Predicate, StateMut> Ancestors<Find, Predicate, StateMut>
+    where
+        StateMut: Borrow<State>,
+    {
+        /// Return all [hidden][Self::hide()] commits that were encountered while finding the commits to hide, which includes
+        /// all hidden commits that are parents of commits returned by this iterator.
+        pub fn hidden_commits(&self) -> &HashSet<ObjectId> {
+            &self.hidden
+        }
+
+        /// Return an iterator for accessing more of the current commits data.
+        ///
+        /// Note that commits which are obtained from the [commit-graph][Self::commit_graph()] aren't decoded, in which case
+        /// the data of the most recently decoded commit is returned.
+        pub fn commit_iter(&self) -> CommitRefIter<'_> {
+            CommitRefIter::from_bytes(&self.state.borrow().buf)
+        }
+    }
+
+    impl<Find, Predicate, StateMut, E> Iterator for Ancestors<Find, Predicate, StateMut>
+    where
+        Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<CommitRefIter<'a>, E>,
+        Predicate: FnMut(&oid) -> bool,
+        StateMut: BorrowMut<State>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        type Item = Result<ObjectId, Error>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            loop {
+                let next = match self.sorting {
+                    Sorting::TopoOrder | Sorting::DateOrder | Sorting::AuthorDateOrder => self.next_in_order(),
+                    _ if matches!(self.parents, Parents::First) => self.next_by_topology(),
+                    Sorting::Topological => self.next_by_topology(),
+                    Sorting::ByCommitTimeNewestFirst => self.next_by_commit_date(None),
+                    Sorting::ByCommitTimeNewestFirstCutoffOlderThan {
+                        time_in_seconds_since_epoch,
+                    } => self.next_by_commit_date(time_in_seconds_since_epoch.into()),
+                };
+                match next {
+                    Some(Ok(id)) if !self.may_have_changed_paths(&id) => continue,
+                    next => return next,
+                }
+            }
+        }
+    }
+
+    impl<Find, Predicate, StateMut> Ancestors<Find, Predicate, StateMut> {
+        /// Return `false` if the changed-path Bloom filter of the commit with `id` proves that it didn't change any of our paths.
+        fn may_have_changed_paths(&self, id: &oid) -> bool {
+            if self.changed_paths.is_empty() {
+                return true;
+            }
+            match self
+                .commit_graph
+                .as_ref()
+                .and_then(|graph| graph.commit_by_id(id))
+                .and_then(|commit| commit.bloom_filter())
+            {
+                Some(filter) => self
+                    .changed_paths
+                    .iter()
+                    .any(|path| filter.may_contain_path(path.as_ref())),
+                None => true,
+            }
+        }
+    }
+
+    impl Sorting {
+        /// If not topo sort, provide the cutoff date if present.
+        fn cutoff_time(&self) -> Option<u32> {
+            match self {
+                Sorting::ByCommitTimeNewestFirstCutoffOlderThan {
+                    time_in_seconds_since_epoch,
+                } => Some(*time_in_seconds_since_epoch),
+                _ => None,
+            }
+        }
+    }
+
+    /// Utilities
+    impl<Find, Predicate, StateMut, E> Ancestors<Find, Predicate, StateMut>
+    where
+        Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<CommitRefIter<'a>, E>,
+        Predicate: FnMut(&oid) -> bool,
+        StateMut: BorrowMut<State>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        fn next_by_commit_date(&mut self, cutoff_older_than: Option<TimeInSeconds>) -> Option<Result<ObjectId, Error>> {
+            let (oid, _commit_time) = self.state.borrow_mut().next.pop_front()?;
+            if let Err(err) = self.collect_parents(&oid) {
+                return Some(Err(err));
+            }
+            let state = self.state.borrow_mut();
+            for (id, parent_commit_time) in state.parent_ids.drain(..) {
+                let was_inserted = state.seen.insert(id);
+                if !(was_inserted && (self.predicate)(&id)) {
+                    continue;
+                }
+
+                let parent_commit_time = parent_commit_time
+                    .or_else(|| {
+                        commit_time_of(&id, self.commit_graph.as_ref(), &mut self.find, &mut state.parents_buf).ok()
+                    })
+                    .unwrap_or_default();
+                let pos = match state.next.binary_search_by(|c| c.1.cmp(&parent_commit_time).reverse()) {
+                    Ok(_) => None,
+                    Err(pos) => Some(pos),
+                };
+                match cutoff_older_than {
+                    Some(cutoff_older_than) if parent_commit_time < cutoff_older_than => continue,
+                    Some(_) | None => match pos {
+                        Some(pos) => state.next.insert(pos, (id, parent_commit_time)),
+                        None => state.next.push_back((id, parent_commit_time)),
+                    },
+                }
+            }
+            Some(Ok(oid))
+        }
+    }
+
+    /// Utilities
+    impl<Find, Predicate, StateMut, E> Ancestors<Find, Predicate, StateMut>
+    where
+        Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<CommitRefIter<'a>, E>,
+        Predicate: FnMut(&oid) -> bool,
+        StateMut: BorrowMut<State>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        /// Return the next commit of all commits sorted so that parents never come before their children.
+        ///
+        /// If collecting the commits fails, the error is returned once and the iteration ends.
+        fn next_in_order(&mut self) -> Option<Result<ObjectId, Error>> {
+            if self.state.borrow().sorted.is_none() {
+                let sorted = self.collect_all_in_order();
+                let state = self.state.borrow_mut();
+                match sorted {
+                    Ok(sorted) => state.sorted = Some(sorted),
+                    Err(err) => {
+                        state.sorted = Some(VecDeque::new());
+                        return Some(Err(err));
+                    }
+                }
+            }
+            self.state.borrow_mut().sorted.as_mut()?.pop_front().map(Ok)
+        }
+
+        /// Collect all reachable commits newest first like `git` does, and sort them so that no parent comes before
+        /// all of its children, which is the algorithm `git` uses as well.
+        fn collect_all_in_order(&mut self) -> Result<VecDeque<ObjectId>, Error> {
+            let by_author_date = matches!(self.sorting, Sorting::AuthorDateOrder);
+            let mut queue = BinaryHeap::new();
+            let mut seq = 0_usize;
+            for (id, commit_time) in self.state.borrow_mut().next.drain(..) {
+                queue.push((commit_time, Reverse(seq), id));
+                seq += 1;
+            }
+
+            let mut commits = Vec::<(ObjectId, Vec<ObjectId>, TimeInSeconds)>::new();
+            while let Some((commit_time, _, id)) = queue.pop() {
+                self.collect_parents(&id)?;
+                let state = self.state.borrow_mut();
+                let mut parents = Vec::with_capacity(state.parent_ids.len());
+                for (parent_id, parent_commit_time) in state.parent_ids.drain(..) {
+                    parents.push(parent_id);
+                    if !(state.seen.insert(parent_id) && (self.predicate)(&parent_id)) {
+                        continue;
+                    }
+                    let parent_commit_time = match parent_commit_time {
+                        Some(time) => time,
+                        None => commit_time_of(
+                            &parent_id,
+                            self.commit_graph.as_ref(),
+                            &mut self.find,
+                            &mut state.parents_buf,
+                        )?,
+                    };
+                    queue.push((parent_commit_time, Reverse(seq), parent_id));
+                    seq += 1;
+                }
+                let time = if by_author_date {
+                    let commit_iter = (self.find)(&id, &mut state.buf).map_err(|err| Error::FindExisting {
+                        oid: id,
+                        source: err.into(),
+                    })?;
+                    commit_iter.author()?.time.seconds_since_unix_epoch
+                } else {
+                    commit_time
+                };
+                commits.push((id, parents, time));
+            }
+
+            let index: HashMap<ObjectId, usize> = commits.iter().enumerate().map(|(idx, c)| (c.0, idx)).collect();
+            // An in-degree of 0 marks commits that were returned already, so everything else starts at 1.
+            let mut indegree = vec![1_usize; commits.len()];
+            for parent_id in commits.iter().flat_map(|c| c.1.iter()) {
+                if let Some(&idx) = index.get(parent_id) {
+                    indegree[idx] += 1;
+                }
+            }
+
+            let mut queue = match self.sorting {
+                Sorting::TopoOrder => Queue::Stack(Vec::new()),
+                _ => Queue::ByTime {
+                    heap: BinaryHeap::new(),
+                    seq: 0,
+                },
+            };
+            let tips: Vec<_> = (0..commits.len()).filter(|idx| indegree[*idx] == 1).collect();
+            if let Queue::Stack(stack) = &mut queue {
+                stack.extend(tips.into_iter().rev());
+            } else {
+                for idx in tips {
+                    queue.push(idx, commits[idx].2);
+                }
+            }
+
+            let mut out = VecDeque::with_capacity(commits.len());
+            while let Some(idx) = queue.pop() {
+                for parent_id in &commits[idx].1 {
+                    let parent_idx = match index.get(parent_id) {
+                        Some(&idx) if indegree[idx] != 0 => idx,
+                        _ => continue,
+                    };
+                    indegree[parent_idx] -= 1;
+                    if indegree[parent_idx] == 1 {
+                        queue.push(parent_idx, commits[parent_idx].2);
+                    }
+                }
+                indegree[idx] = 0;
+                out.push_back(commits[idx].0);
+            }
+            Ok(out)
+        }
+    }
+
+    /// The queue of commits whose children were all returned already, as indices into the list of all commits.
+    enum Queue {
+        /// The most recently added commit is returned first.
+        Stack(Vec<usize>),
+        /// The newest commit is returned first, and the one added first if times are equal.
+        ByTime {
+            heap: BinaryHeap<(TimeInSeconds, Reverse<usize>, usize)>,
+            seq: usize,
+        },
+    }
+
+    impl Queue {
+        fn push(&mut self, idx: usize, time: TimeInSeconds) {
+            match self {
+                Queue::Stack(stack) => stack.push(idx),
+                Queue::ByTime { heap, seq } => {
+                    heap.push((time, Reverse(*seq), idx));
+                    *seq += 1;
+                }
+            }
+        }
+
+        fn pop(&mut self) -> Option<usize> {
+            match self {
+                Queue::Stack(stack) => stack.pop(),
+                Queue::ByTime { heap, .. } => heap.pop().map(|(_, _, idx)| idx),
+            }
+        }
+    }
+
+    /// Utilities
+    impl<Find, Predicate, StateMut, E> Ancestors<Find, Predicate, StateMut>
+    where
+        Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<CommitRefIter<'a>, E>,
+        Predicate: FnMut(&oid) -> bool,
+        StateMut: BorrowMut<State>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        fn next_by_topology(&mut self) -> Option<Result<ObjectId, Error>> {
+            let (oid, _commit_time) = self.state.borrow_mut().next.pop_front()?;
+            if let Err(err) = self.collect_parents(&oid) {
+                return Some(Err(err));
+            }
+            let state = self.state.borrow_mut();
+            for (id, _commit_time) in state.parent_ids.drain(..) {
+                let was_inserted = state.seen.insert(id);
+                if was_inserted && (self.predicate)(&id) {
+                    state.next.push_back((id, 0));
+                }
+            }
+            Some(Ok(oid))
+        }
+
+        /// Collect the parents of the commit with `id` into our state, along with their commit time if it's known already,
+        /// while respecting our parent mode and shallow commits.
+        ///
+        /// The commit-graph is used if it contains the commit, otherwise the commit is decoded.
+        fn collect_parents(&mut self, id: &oid) -> Result<(), Error> {
+            let state = self.state.borrow_mut();
+            state.parent_ids.clear();
+            let first_parent_only = matches!(self.parents, Parents::First);
+            match self
+                .commit_graph
+                .as_ref()
+                .and_then(|graph| graph.commit_by_id(id).map(|commit| (graph, commit)))
+            {
+                Some((graph, commit)) => {
+                    if self.shallow.contains(id) {
+                        return Ok(());
+                    }
+                    for pos in commit.iter_parents() {
+                        let parent = graph.commit_at(pos?);
+                        state.parent_ids.push((
+                            parent.id().to_owned(),
+                            Some(parent.committer_timestamp() as TimeInSeconds),
+                        ));
+                        if first_parent_only {
+                            break;
+                        }
+                    }
+                }
+                None => {
+                    let commit_iter = (self.find)(id, &mut state.buf).map_err(|err| Error::FindExisting {
+                        oid: id.to_owned(),
+                        source: err.into(),
+                    })?;
+                    if self.shallow.contains(id) {
+                        return Ok(());
+                    }
+                    for token in commit_iter {
+                        match token {
+                            Ok(Token::Tree { .. }) => continue,
+                            Ok(Token::Parent { id }) => {
+                                state.parent_ids.push((id, None));
+                                if first_parent_only {
+                                    break;
+                                }
+                            }
+                            Ok(_a_token_past_the_parents) => break,
+                            Err(err) => return Err(err.into()),
+                        }
+                    }
+                }
+            }
+            if !self.hidden.is_empty() {
+                state.parent_ids.retain(|(id, _)| !self.hidden.contains(id));
+            }
+            Ok(())
+        }
+    }
+
+    /// Return the commit time of the commit with `id`, obtained from `graph` if it contains it, or by decoding it with `find`
+    /// into `buf` otherwise.
+    fn commit_time_of<Find, E>(
+        id: &oid,
+        graph: Option<&git_commitgraph::Graph>,
+        find: &mut Find,
+        buf: &mut Vec<u8>,
+    ) -> Result<TimeInSeconds, Error>
+    where
+        Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<CommitRefIter<'a>, E>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        if let Some(commit) = graph.and_then(|graph| graph.commit_by_id(id)) {
+            return Ok(commit.committer_timestamp() as TimeInSeconds);
+        }
+        let commit_iter = find(id, buf).map_err(|err| Error::FindExisting {
+            oid: id.to_owned(),
+            source: err.into(),
+        })?;
+        Ok(commit_iter.committer()?.time.seconds_since_unix_epoch)
+    }
+}
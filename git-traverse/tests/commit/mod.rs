@@ -193,6 +193,47 @@ mod ancestor {
         })
     }
 
+    #[test]
+    fn shallow_commits_are_returned_but_their_parents_are_not_traversed() -> crate::Result {
+        let dir = git_testtools::scripted_fixture_read_only_standalone("make_traversal_repo_for_commits.sh")?;
+        let store = git_odb::at(dir.join(".git").join("objects"))?;
+        for (parents, expected) in [
+            (
+                commit::Parents::All,
+                &[
+                    "01ec18a3ebf2855708ad3c9d244306bc1fae3e9b",
+                    "efd9a841189668f1bab5b8ebade9cd0a1b139a37",
+                    "ce2e8ffaa9608a26f7b21afc1db89cadb54fd353",
+                    "9556057aee5abb06912922e9f26c46386a816822",
+                    "9152eeee2328073cf23dcf8e90c949170b711659",
+                ][..],
+            ),
+            (
+                commit::Parents::First,
+                &[
+                    "01ec18a3ebf2855708ad3c9d244306bc1fae3e9b",
+                    "efd9a841189668f1bab5b8ebade9cd0a1b139a37",
+                    "9556057aee5abb06912922e9f26c46386a816822",
+                ][..],
+            ),
+        ] {
+            let oids: Result<Vec<_>, _> = commit::Ancestors::new(
+                Some(hex_to_id("01ec18a3ebf2855708ad3c9d244306bc1fae3e9b")),
+                commit::ancestors::State::default(),
+                |oid, buf| store.find_commit_iter(oid, buf).map(|t| t.0),
+            )
+            .parents(parents)
+            .shallow_commits(Some(hex_to_id("9556057aee5abb06912922e9f26c46386a816822")))
+            .collect();
+            assert_eq!(
+                oids?,
+                expected.iter().copied().map(hex_to_id).collect::<Vec<_>>(),
+                "the history of the shallow commit isn't traversed"
+            );
+        }
+        Ok(())
+    }
+
     #[test]
     fn graph_sorted_commits() -> crate::Result {
         TraversalAssertion::new(
@@ -271,3 +312,394 @@ mod ancestor {
         .check()
     }
 }
+
+mod changed_paths {
+    use git_odb::pack::FindExt;
+    use git_traverse::commit;
+
+    use crate::hex_to_id;
+
+    fn traverse(paths: &[&str], use_commit_graph: bool) -> crate::Result<Vec<git_hash::ObjectId>> {
+        let dir = git_testtools::scripted_fixture_read_only_standalone("make_traversal_repo_with_changed_paths.sh")?;
+        let objects_dir = dir.join(".git").join("objects");
+        let graph = use_commit_graph
+            .then(|| git_commitgraph::Graph::from_info_dir(objects_dir.join("info")))
+            .transpose()?;
+        let store = git_odb::at(objects_dir)?;
+        Ok(commit::Ancestors::new(
+            Some(hex_to_id(C5)),
+            commit::ancestors::State::default(),
+            move |oid, buf| store.find_commit_iter(oid, buf).map(|t| t.0),
+        )
+        .commit_graph(graph)
+        .changed_paths(paths.iter().copied())
+        .collect::<Result<_, _>>()?)
+    }
+
+    const C5: &str = "5fb343479f83a15168adceabc07e31bbe2c73cef";
+    const C4: &str = "c016dcb9d7425b5ae7e3e5ffe30eff28f3182c9f";
+    const C3: &str = "6c706914cc2eb0235620431755cc5543cca2a859";
+    const C2: &str = "1c412dbcabb5d52131e2d4f5c0cf11f77a164199";
+    const C1: &str = "7485b1bafeaff71f32550d87cf5f08064f8ebe82";
+
+    fn ids(hexes: &[&str]) -> Vec<git_hash::ObjectId> {
+        hexes.iter().copied().map(hex_to_id).collect()
+    }
+
+    #[test]
+    fn bloom_filters_skip_commits_that_did_not_change_any_path() -> crate::Result {
+        assert_eq!(
+            traverse(&["dir"], true)?,
+            ids(&[C5, C3, C1]),
+            "the tip isn't in the commit-graph, so it can't be skipped"
+        );
+        assert_eq!(traverse(&["dir/b/"], true)?, ids(&[C5, C3, C1]));
+        assert_eq!(traverse(&["a"], true)?, ids(&[C5, C2, C1]));
+        assert_eq!(traverse(&["a", "c"], true)?, ids(&[C5, C4, C2, C1]));
+        assert_eq!(traverse(&["unrelated"], true)?, ids(&[C5]));
+        Ok(())
+    }
+
+    #[test]
+    fn without_commit_graph_or_paths_no_commit_is_skipped() -> crate::Result {
+        let all = ids(&[C5, C4, C3, C2, C1]);
+        assert_eq!(traverse(&["a"], false)?, all);
+        assert_eq!(traverse(&[], true)?, all);
+        Ok(())
+    }
+}
+
+mod merge_base {
+    use std::path::{Path, PathBuf};
+
+    use git_hash::ObjectId;
+    use git_odb::pack::FindExt;
+    use git_traverse::commit::merge_base;
+
+    use crate::hex_to_id;
+
+    struct Fixture {
+        dir: PathBuf,
+        store: git_odb::Handle,
+    }
+
+    impl Fixture {
+        fn new() -> crate::Result<Self> {
+            let dir = git_testtools::scripted_fixture_read_only_standalone("make_merge_base_repo.sh")?;
+            let store = git_odb::at(dir.join(".git").join("objects"))?;
+            Ok(Fixture { dir, store })
+        }
+
+        /// All graphs to run queries with: none, one with generation numbers v1 and one with v2, both of which
+        /// lack the most recent commits.
+        fn graphs(&self) -> crate::Result<Vec<Option<git_commitgraph::Graph>>> {
+            let v1 = git_commitgraph::Graph::at(self.dir.join("v1.graph"))?;
+            assert!(!v1.has_corrected_commit_dates());
+            let v2 = git_commitgraph::Graph::from_info_dir(self.dir.join(".git").join("objects").join("info"))?;
+            assert!(v2.has_corrected_commit_dates());
+            Ok(vec![None, Some(v1), Some(v2)])
+        }
+
+        fn id(&self, name: &str) -> ObjectId {
+            tag_id(&self.dir, name)
+        }
+
+        fn ids(&self, names: &[&str]) -> Vec<ObjectId> {
+            names.iter().map(|name| self.id(name)).collect()
+        }
+
+        fn find(
+            &self,
+        ) -> impl for<'a> FnMut(
+            &git_hash::oid,
+            &'a mut Vec<u8>,
+        ) -> Result<
+            git_object::CommitRefIter<'a>,
+            git_odb::pack::find::existing_iter::Error<git_odb::store::find::Error>,
+        > + '_ {
+            move |oid, buf| self.store.find_commit_iter(oid, buf).map(|t| t.0)
+        }
+    }
+
+    fn tag_id(dir: &Path, name: &str) -> ObjectId {
+        let hex = std::fs::read_to_string(dir.join(".git").join("refs").join("tags").join(name)).expect("loose tag");
+        hex_to_id(hex.trim())
+    }
+
+    #[test]
+    fn all() -> crate::Result {
+        let fx = Fixture::new()?;
+        for graph in fx.graphs()? {
+            let graph = graph.as_ref();
+            let bases = |first: &str, others: &[&str]| merge_base::all(fx.id(first), &fx.ids(others), graph, fx.find());
+            assert_eq!(bases("a1", &["b1"])?, fx.ids(&["c1"]));
+            assert_eq!(bases("a1", &["m1"])?, fx.ids(&["c1"]));
+            assert_eq!(
+                bases("c1", &["a1"])?,
+                fx.ids(&["c1"]),
+                "an ancestor is its own merge-base"
+            );
+            assert_eq!(bases("a1", &["a1"])?, fx.ids(&["a1"]));
+            assert_eq!(
+                bases("a2", &["b2"])?,
+                fx.ids(&["b1", "a1"]),
+                "criss-cross merges have two merge-bases, newest first"
+            );
+            assert_eq!(
+                bases("a2", &["b1", "m1"])?,
+                fx.ids(&["b1"]),
+                "the merge-base with a hypothetical merge of all others"
+            );
+            assert_eq!(bases("a1", &["b1", "m1"])?, fx.ids(&["c1"]));
+            assert_eq!(bases("a1", &["o1"])?, Vec::<ObjectId>::new(), "unrelated histories");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn is_ancestor() -> crate::Result {
+        let fx = Fixture::new()?;
+        for graph in fx.graphs()? {
+            let graph = graph.as_ref();
+            let is_ancestor = |ancestor: &str, descendant: &str| {
+                merge_base::is_ancestor(fx.id(ancestor), fx.id(descendant), graph, fx.find())
+            };
+            assert!(is_ancestor("c0", "a2")?);
+            assert!(is_ancestor("a1", "b2")?);
+            assert!(is_ancestor("m1", "m1")?, "each commit is its own ancestor");
+            assert!(!is_ancestor("a2", "b2")?);
+            assert!(!is_ancestor("a2", "a1")?);
+            assert!(!is_ancestor("m1", "a1")?);
+            assert!(!is_ancestor("o1", "a2")?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn independent() -> crate::Result {
+        let fx = Fixture::new()?;
+        for graph in fx.graphs()? {
+            let graph = graph.as_ref();
+            let independent = |names: &[&str]| merge_base::independent(fx.ids(names), graph, fx.find());
+            assert_eq!(
+                independent(&["c1", "a1", "b1", "m1", "a1"])?,
+                fx.ids(&["a1", "b1", "m1"]),
+                "ancestors and duplicates are removed, and the order is retained"
+            );
+            assert_eq!(independent(&["a1", "a2", "b2", "o1"])?, fx.ids(&["a2", "b2", "o1"]));
+            assert_eq!(independent(&["c0"])?, fx.ids(&["c0"]));
+        }
+        Ok(())
+    }
+}
+
+mod commit_graph {
+    use std::cell::RefCell;
+
+    use git_odb::pack::FindExt;
+    use git_traverse::commit;
+
+    use crate::hex_to_id;
+
+    #[test]
+    fn commits_in_the_graph_are_not_decoded() -> crate::Result {
+        let dir = git_testtools::scripted_fixture_read_only_standalone("make_merge_base_repo.sh")?;
+        let store = git_odb::at(dir.join(".git").join("objects"))?;
+        let tip = std::fs::read_to_string(dir.join(".git").join("refs").join("tags").join("a2"))?;
+        let decoded = RefCell::new(Vec::new());
+        let traverse = |graph: Option<git_commitgraph::Graph>, sorting: commit::Sorting| -> crate::Result<Vec<_>> {
+            decoded.borrow_mut().clear();
+            Ok(commit::Ancestors::new(
+                Some(hex_to_id(tip.trim())),
+                commit::ancestors::State::default(),
+                |oid, buf| {
+                    decoded.borrow_mut().push(oid.to_owned());
+                    store.find_commit_iter(oid, buf).map(|t| t.0)
+                },
+            )
+            .commit_graph(graph)
+            .sorting(sorting)?
+            .collect::<Result<Vec<_>, _>>()?)
+        };
+
+        for sorting in [commit::Sorting::Topological, commit::Sorting::ByCommitTimeNewestFirst] {
+            let expected = traverse(None, sorting)?;
+            assert_eq!(expected.len(), 5);
+            let graph = git_commitgraph::Graph::from_info_dir(dir.join(".git").join("objects").join("info"))?;
+            assert_eq!(traverse(Some(graph), sorting)?, expected);
+            assert_eq!(
+                decoded.borrow().iter().collect::<std::collections::HashSet<_>>().len(),
+                1,
+                "only the tip isn't in the commit-graph"
+            );
+        }
+        Ok(())
+    }
+}
+
+mod sorting {
+    use std::path::{Path, PathBuf};
+
+    use git_hash::ObjectId;
+    use git_odb::pack::FindExt;
+    use git_traverse::commit;
+
+    use crate::hex_to_id;
+
+    fn fixture() -> crate::Result<PathBuf> {
+        git_testtools::scripted_fixture_read_only_standalone("make_sorted_traversal_repo.sh")
+    }
+
+    /// Return the ids `git rev-list` produced in the fixture.
+    fn expected(dir: &Path, name: &str) -> crate::Result<Vec<ObjectId>> {
+        Ok(std::fs::read_to_string(dir.join(format!("{name}.list")))?
+            .lines()
+            .map(hex_to_id)
+            .collect())
+    }
+
+    /// Return the id of the loose branch or tag with `name`.
+    fn id(dir: &Path, name: &str) -> ObjectId {
+        let refs = dir.join(".git").join("refs");
+        let hex = std::fs::read_to_string(refs.join("heads").join(name))
+            .or_else(|_| std::fs::read_to_string(refs.join("tags").join(name)))
+            .expect("loose branch or tag");
+        hex_to_id(hex.trim())
+    }
+
+    fn traverse(
+        dir: &Path,
+        tips: &[&str],
+        sorting: commit::Sorting,
+        parents: commit::Parents,
+        use_commit_graph: bool,
+    ) -> crate::Result<Vec<ObjectId>> {
+        traverse_with_hidden(dir, tips, &[], sorting, parents, use_commit_graph)
+    }
+
+    fn traverse_with_hidden(
+        dir: &Path,
+        tips: &[&str],
+        hidden: &[&str],
+        sorting: commit::Sorting,
+        parents: commit::Parents,
+        use_commit_graph: bool,
+    ) -> crate::Result<Vec<ObjectId>> {
+        let objects_dir = dir.join(".git").join("objects");
+        let graph = use_commit_graph
+            .then(|| git_commitgraph::Graph::from_info_dir(objects_dir.join("info")))
+            .transpose()?;
+        let store = git_odb::at(objects_dir)?;
+        let mut ancestors = commit::Ancestors::new(
+            tips.iter().map(|name| id(dir, name)),
+            commit::ancestors::State::default(),
+            move |oid, buf| store.find_commit_iter(oid, buf).map(|t| t.0),
+        )
+        .commit_graph(graph)
+        .parents(parents);
+        if !hidden.is_empty() {
+            ancestors = ancestors.hide(hidden.iter().map(|name| id(dir, name)))?;
+        }
+        Ok(ancestors.sorting(sorting)?.collect::<Result<_, _>>()?)
+    }
+
+    #[test]
+    fn parents_come_after_all_their_children_like_in_git() -> crate::Result {
+        let dir = fixture()?;
+        for use_commit_graph in [false, true] {
+            for (sorting, name) in [
+                (commit::Sorting::TopoOrder, "topo-order"),
+                (commit::Sorting::DateOrder, "date-order"),
+                (commit::Sorting::AuthorDateOrder, "author-date-order"),
+            ] {
+                assert_eq!(
+                    traverse(&dir, &["main"], sorting, commit::Parents::All, use_commit_graph)?,
+                    expected(&dir, name)?,
+                    "{name}, with commit-graph: {use_commit_graph}"
+                );
+                assert_eq!(
+                    traverse(&dir, &["a", "b"], sorting, commit::Parents::All, use_commit_graph)?,
+                    expected(&dir, &format!("{name}-multiple-tips"))?,
+                    "{name} with multiple tips, with commit-graph: {use_commit_graph}"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn first_parent_only() -> crate::Result {
+        let dir = fixture()?;
+        assert_eq!(
+            traverse(
+                &dir,
+                &["main"],
+                commit::Sorting::TopoOrder,
+                commit::Parents::First,
+                false
+            )?,
+            expected(&dir, "topo-order-first-parent")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn hidden_commits_and_their_ancestors_are_not_returned() -> crate::Result {
+        let dir = fixture()?;
+        for use_commit_graph in [false, true] {
+            for (sorting, name) in [
+                (commit::Sorting::TopoOrder, "topo-order"),
+                (commit::Sorting::DateOrder, "date-order"),
+            ] {
+                let traverse = |tips: &[&str], hidden: &[&str], parents: commit::Parents| {
+                    traverse_with_hidden(&dir, tips, hidden, sorting, parents, use_commit_graph)
+                };
+                assert_eq!(
+                    traverse(&["main"], &["a"], commit::Parents::All)?,
+                    expected(&dir, &format!("{name}-hidden"))?,
+                    "{name}, with commit-graph: {use_commit_graph}"
+                );
+                assert_eq!(
+                    traverse(&["main", "b"], &["c2", "b1"], commit::Parents::All)?,
+                    expected(&dir, &format!("{name}-multiple-hidden"))?,
+                    "hidden tips are not returned, {name}, with commit-graph: {use_commit_graph}"
+                );
+                assert_eq!(
+                    traverse(&["main"], &["a2"], commit::Parents::First)?,
+                    expected(&dir, &format!("{name}-hidden-first-parent"))?,
+                    "only first parents are hidden, {name}, with commit-graph: {use_commit_graph}"
+                );
+                assert_eq!(
+                    traverse(&["a"], &["main"], commit::Parents::All)?,
+                    Vec::<ObjectId>::new(),
+                    "everything is hidden"
+                );
+            }
+            assert_eq!(
+                traverse_with_hidden(
+                    &dir,
+                    &["main"],
+                    &["c0"],
+                    commit::Sorting::TopoOrder,
+                    commit::Parents::All,
+                    use_commit_graph
+                )?,
+                expected(&dir, "topo-order-hidden-root")?
+            );
+
+            let mut unsorted = traverse_with_hidden(
+                &dir,
+                &["main"],
+                &["a"],
+                commit::Sorting::Topological,
+                commit::Parents::All,
+                use_commit_graph,
+            )?;
+            unsorted.sort();
+            let mut expected = expected(&dir, "topo-order-hidden")?;
+            expected.sort();
+            assert_eq!(unsorted, expected, "the same commits are returned in any sort order");
+        }
+        Ok(())
+    }
+}
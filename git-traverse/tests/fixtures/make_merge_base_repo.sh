@@ -0,0 +1,43 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+git config merge.ff false
+
+t=1000000000
+function commit() {
+  local message=${1:?message}
+  t=$((t + 60))
+  GIT_COMMITTER_DATE="$t +0000" git commit -q --allow-empty -m "$message"
+  git tag "$message"
+}
+function merge() {
+  local message=${1:?message} other=${2:?other}
+  t=$((t + 60))
+  GIT_COMMITTER_DATE="$t +0000" git merge -q "$other" -m "$message"
+  git tag "$message"
+}
+
+git checkout -q -b main
+commit c0
+commit c1
+
+git checkout -q -b a c1
+commit a1
+git checkout -q -b b c1
+commit b1
+git checkout -q main
+commit m1
+
+git checkout -q --orphan other
+commit o1
+
+git -c commitGraph.generationVersion=1 commit-graph write --no-progress --reachable
+mv .git/objects/info/commit-graph v1.graph
+git -c commitGraph.generationVersion=2 commit-graph write --no-progress --reachable
+
+# criss-cross merges that aren't in the commit-graph
+git checkout -q a
+merge a2 b1
+git checkout -q b
+merge b2 a1
@@ -0,0 +1,53 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+git config merge.ff false
+
+t=1000000000
+function commit() {
+  local message=${1:?message} author_offset=${2:-0}
+  t=$((t + 60))
+  GIT_AUTHOR_DATE="$((t + author_offset)) +0000" GIT_COMMITTER_DATE="$t +0000" git commit -q --allow-empty -m "$message"
+  git tag "$message"
+}
+function merge() {
+  local message=${1:?message} other=${2:?other}
+  t=$((t + 60))
+  GIT_AUTHOR_DATE="$t +0000" GIT_COMMITTER_DATE="$t +0000" git merge -q "$other" -m "$message"
+  git tag "$message"
+}
+
+git checkout -q -b main
+commit c0
+commit c1
+
+# two branches whose commits interleave in time with the ones on main, with author dates in a different order
+git checkout -q -b a c1
+commit a1 1000
+git checkout -q main
+commit c2 -1000
+git checkout -q a
+commit a2
+git checkout -q -b b c1
+commit b1 500
+git checkout -q main
+commit c3
+merge m1 a
+commit c4 -2000
+merge m2 b
+
+for order in topo-order date-order author-date-order; do
+  git rev-list --$order main > $order.list
+  git rev-list --$order a b > $order-multiple-tips.list
+done
+git rev-list --topo-order --first-parent main > topo-order-first-parent.list
+
+for order in topo-order date-order; do
+  git rev-list --$order a..main > $order-hidden.list
+  git rev-list --$order ^c2 ^b1 main b > $order-multiple-hidden.list
+  git rev-list --$order --first-parent ^a2 main > $order-hidden-first-parent.list
+done
+git rev-list --topo-order main ^c0 > topo-order-hidden-root.list
+
+git commit-graph write --no-progress --reachable
@@ -0,0 +1,24 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q
+
+mkdir dir
+echo a > a
+echo b > dir/b
+git add . && git commit -q -m c1
+
+echo a2 > a
+git commit -q -am c2
+
+echo b2 > dir/b
+git commit -q -am c3
+
+echo c > c
+git add c && git commit -q -m c4
+
+git commit-graph write --no-progress --reachable --changed-paths
+
+# not in the commit-graph
+echo b3 > dir/b
+git commit -q -am c5
@@ -0,0 +1,323 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use bstr::{BStr, BString, ByteSlice, ByteVec};
+use git_hash::oid;
+use git_index::entry;
+
+use crate::fs;
+
+/// The way an index entry differs from its counterpart in the worktree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Change {
+    /// The file was removed from the worktree.
+    Removed,
+    /// The type of the entry changed, e.g. a file turned into a symbolic link or a directory.
+    Type,
+    /// The file was modified.
+    Modification {
+        /// If true, the executable bit of the file changed.
+        executable_bit_changed: bool,
+        /// If true, the content of the file changed.
+        content_change: bool,
+    },
+}
+
+/// Options for use in [`compare_to_index()`].
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Options {
+    /// Capabilities of the file system which affect how the worktree is compared to the index.
+    pub fs: fs::Capabilities,
+    /// How to compare stat information of files on disk with the one stored in the index.
+    pub stat: entry::stat::Options,
+}
+
+/// The outcome of [`compare_to_index()`].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    /// The amount of entries that were compared to their worktree counterpart.
+    pub entries_processed: usize,
+    /// The amount of files which had to be hashed as their stat information didn't suffice to determine if they changed.
+    pub files_hashed: usize,
+    /// The amount of racily clean entries which were hashed and found to be unchanged.
+    pub racy_clean: usize,
+}
+
+/// The error returned by [`compare_to_index()`] and [`untracked()`].
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("Could not obtain information about '{}'", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("The modification time of '{}' is before the unix epoch", path.display())]
+    Time {
+        path: PathBuf,
+        source: std::time::SystemTimeError,
+    },
+    #[error(transparent)]
+    Hash(#[from] git_features::hash::Error),
+}
+
+/// Compare all entries of `index` with the files in the `worktree` directory and call `record` with each entry path and entry that changed.
+///
+/// Racily clean entries, i.e. the ones whose stat information matches but which were modified around the time the index was written,
+/// are hashed to find out if their content really is unchanged.
+/// Conflicting entries, entries flagged to be skipped in the worktree as well as sparse directories are ignored.
+///
+/// Note that interruption via `should_interrupt` still produces an `Ok(…)` value, so the caller should look at it to communicate the outcome.
+pub fn compare_to_index<'index>(
+    index: &'index git_index::State,
+    worktree: &Path,
+    should_interrupt: &AtomicBool,
+    options: Options,
+    mut record: impl FnMut(&'index BStr, &'index git_index::Entry, Change),
+) -> Result<Outcome, Error> {
+    let mut outcome = Outcome::default();
+    let mut buf = Vec::new();
+    for entry in index.entries() {
+        if should_interrupt.load(Ordering::Relaxed) {
+            break;
+        }
+        if entry.stage() != 0 || entry.flags.contains(entry::Flags::SKIP_WORKTREE) || entry.mode.is_sparse() {
+            continue;
+        }
+        outcome.entries_processed += 1;
+        let rela_path = entry.path(index);
+        let path = worktree.join(git_path::from_bstr(rela_path));
+        if let Some(change) = compare_entry(entry, index, &path, options, &mut buf, &mut outcome)? {
+            record(rela_path, entry, change);
+        }
+    }
+    Ok(outcome)
+}
+
+fn compare_entry(
+    entry: &git_index::Entry,
+    index: &git_index::State,
+    path: &Path,
+    options: Options,
+    buf: &mut Vec<u8>,
+    outcome: &mut Outcome,
+) -> Result<Option<Change>, Error> {
+    let io_err = |source: io::Error| Error::Io {
+        path: path.to_owned(),
+        source,
+    };
+    let meta = match std::fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(err) if matches!(err.kind(), io::ErrorKind::NotFound) || is_not_a_directory(&err) => {
+            return Ok(Some(Change::Removed))
+        }
+        Err(err) => return Err(io_err(err)),
+    };
+
+    let file_type = meta.file_type();
+    if entry.mode == entry::Mode::COMMIT {
+        return Ok((!file_type.is_dir()).then_some(Change::Type));
+    }
+    let is_symlink_entry = entry.mode == entry::Mode::SYMLINK;
+    let type_matches = if file_type.is_symlink() {
+        is_symlink_entry
+    } else if file_type.is_file() {
+        !is_symlink_entry || !options.fs.symlink
+    } else {
+        false
+    };
+    if !type_matches {
+        return Ok(Some(Change::Type));
+    }
+
+    let executable_bit_changed = !is_symlink_entry
+        && options.fs.executable_bit
+        && (entry.mode == entry::Mode::FILE_EXECUTABLE) != is_executable(&meta);
+
+    let stat = entry::Stat::from_fs(&meta).map_err(|source| Error::Time {
+        path: path.to_owned(),
+        source,
+    })?;
+    let content_change = if entry.stat.size != 0 && entry.stat.size != stat.size {
+        true
+    } else if !entry.stat.matches(&stat, options.stat) || entry.stat.is_racy(index.timestamp(), options.stat) {
+        outcome.files_hashed += 1;
+        buf.clear();
+        if file_type.is_symlink() {
+            let target = std::fs::read_link(path).map_err(io_err)?;
+            buf.extend_from_slice(&git_path::into_bstr(target));
+        } else {
+            buf.extend_from_slice(&std::fs::read(path).map_err(io_err)?);
+        }
+        let changed = blob_id(buf, index.object_hash())? != entry.id;
+        if !changed && entry.stat.matches(&stat, options.stat) {
+            outcome.racy_clean += 1;
+        }
+        changed
+    } else {
+        false
+    };
+
+    Ok(
+        (executable_bit_changed || content_change).then_some(Change::Modification {
+            executable_bit_changed,
+            content_change,
+        }),
+    )
+}
+
+fn blob_id(data: &[u8], object_hash: git_hash::Kind) -> Result<git_hash::ObjectId, git_features::hash::Error> {
+    let mut hasher = git_features::hash::hasher(object_hash);
+    hasher.update(&git_object::encode::loose_header(git_object::Kind::Blob, data.len()));
+    hasher.update(data);
+    hasher.try_digest()
+}
+
+#[cfg(unix)]
+fn is_executable(meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    meta.mode() & 0o100 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_meta: &std::fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn is_not_a_directory(err: &io::Error) -> bool {
+    // A leading path component was turned into a file.
+    err.raw_os_error() == Some(libc::ENOTDIR)
+}
+
+#[cfg(not(unix))]
+fn is_not_a_directory(_err: &io::Error) -> bool {
+    false
+}
+
+/// Find all files in the worktree which aren't tracked by `index` and aren't excluded according to `cache`, which must be configured
+/// with an ignore stack, and call `record` with their path relative to the worktree root.
+///
+/// If a directory doesn't contain any tracked files, it is recorded as a whole instead of the untracked files within it, with
+/// its path ending in a slash, similar to what `git status` does.
+/// `find` is used to lookup exclude files stored in the index.
+///
+/// Note that interruption via `should_interrupt` still produces an `Ok(…)` value, so the caller should look at it to communicate the outcome.
+pub fn untracked<Find, E>(
+    index: &git_index::State,
+    cache: &mut fs::Cache,
+    mut find: Find,
+    should_interrupt: &AtomicBool,
+    mut record: impl FnMut(&BStr),
+) -> Result<(), Error>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<git_object::BlobRef<'a>, E>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let mut rela_path = BString::default();
+    let record: &mut dyn FnMut(&BStr) = &mut record;
+    walk(
+        index,
+        cache,
+        &mut find,
+        should_interrupt,
+        &mut rela_path,
+        &mut Some(record),
+    )?;
+    Ok(())
+}
+
+/// Walk the directory at `rela_path` and call `record` for untracked files if set.
+/// Return true if there is at least one untracked file.
+fn walk<Find, E>(
+    index: &git_index::State,
+    cache: &mut fs::Cache,
+    find: &mut Find,
+    should_interrupt: &AtomicBool,
+    rela_path: &mut BString,
+    record: &mut Option<&mut dyn FnMut(&BStr)>,
+) -> Result<bool, Error>
+where
+    Find: for<'a> FnMut(&oid, &'a mut Vec<u8>) -> Result<git_object::BlobRef<'a>, E>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let dir = cache.base().join(git_path::from_bstr(rela_path.as_bstr()));
+    let io_err = |source: io::Error| Error::Io {
+        path: dir.clone(),
+        source,
+    };
+    let mut entries = std::fs::read_dir(&dir)
+        .map_err(io_err)?
+        .map(|entry| entry.and_then(|entry| Ok((entry.file_name(), entry.file_type()?))))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io_err)?;
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut found_untracked = false;
+    let prefix_len = rela_path.len();
+    for (name, file_type) in entries {
+        if should_interrupt.load(Ordering::Relaxed) {
+            break;
+        }
+        if name == ".git" {
+            continue;
+        }
+        rela_path.truncate(prefix_len);
+        if !rela_path.is_empty() {
+            rela_path.push_byte(b'/');
+        }
+        rela_path.extend_from_slice(&git_path::into_bstr(PathBuf::from(name)));
+
+        let is_dir = file_type.is_dir();
+        let is_excluded = cache
+            .at_entry(rela_path.as_bstr(), Some(is_dir), &mut *find)
+            .map_err(|source| Error::Io {
+                path: dir.clone(),
+                source,
+            })?
+            .is_excluded();
+        if is_dir {
+            // Submodules are tracked as a single gitlink entry at the directory path itself, and their contents belong to them.
+            if is_excluded || is_tracked(index, rela_path.as_bstr()) {
+                continue;
+            }
+            rela_path.push_byte(b'/');
+            let has_tracked_files = has_entries_with_prefix(index, rela_path.as_bstr());
+            rela_path.pop();
+            if has_tracked_files {
+                found_untracked |= walk(index, cache, find, should_interrupt, rela_path, record)?;
+            } else if walk(index, cache, find, should_interrupt, rela_path, &mut None)? {
+                found_untracked = true;
+                match record {
+                    Some(record) => {
+                        rela_path.push_byte(b'/');
+                        record(rela_path.as_bstr());
+                    }
+                    None => break,
+                }
+            }
+        } else if !is_excluded && !is_tracked(index, rela_path.as_bstr()) {
+            found_untracked = true;
+            match record {
+                Some(record) => record(rela_path.as_bstr()),
+                None => break,
+            }
+        }
+    }
+    rela_path.truncate(prefix_len);
+    Ok(found_untracked)
+}
+
+fn is_tracked(index: &git_index::State, rela_path: &BStr) -> bool {
+    let entries = index.entries();
+    let pos = entries.partition_point(|entry| entry.path(index) < rela_path);
+    entries.get(pos).map_or(false, |entry| entry.path(index) == rela_path)
+}
+
+fn has_entries_with_prefix(index: &git_index::State, prefix: &BStr) -> bool {
+    let entries = index.entries();
+    let pos = entries.partition_point(|entry| entry.path(index) < prefix);
+    entries
+        .get(pos)
+        .map_or(false, |entry| entry.path(index).starts_with(prefix))
+}
@@ -0,0 +1,22 @@
+#!/bin/bash
+set -eu -o pipefail
+
+git init -q module1
+(cd module1
+  touch this
+  git add this
+  git commit -q -m c1
+)
+
+git init -q with-submodule
+(cd with-submodule
+  touch file
+  mkdir dir
+  touch dir/file
+  git add file dir
+  git commit -q -m "init"
+
+  git submodule add ../module1 sub
+  git submodule add ../module1 dir/sub
+  git commit -q -m "add submodules"
+)
@@ -1 +1,2 @@
 mod checkout;
+mod status;
@@ -0,0 +1,144 @@
+use std::{path::Path, sync::atomic::AtomicBool};
+
+use bstr::{BString, ByteSlice};
+use git_odb::FindExt;
+use git_worktree::index::status::{self, Change};
+
+fn writable_fixture() -> crate::Result<tempfile::TempDir> {
+    git_testtools::scripted_fixture_writable_with_args(
+        "make_mixed_without_submodules.sh",
+        None::<String>,
+        git_testtools::Creation::ExecuteScript,
+    )
+}
+
+fn index_at(worktree: &Path) -> git_index::File {
+    git_index::File::at(worktree.join(".git/index"), git_hash::Kind::Sha1, Default::default()).unwrap()
+}
+
+fn changes(worktree: &Path, index: &git_index::State) -> (Vec<(BString, Change)>, status::Outcome) {
+    let mut changes = Vec::new();
+    let outcome = status::compare_to_index(
+        index,
+        worktree,
+        &AtomicBool::default(),
+        status::Options {
+            fs: git_worktree::fs::Capabilities::probe(worktree.join(".git")),
+            ..Default::default()
+        },
+        |rela_path, _entry, change| changes.push((rela_path.to_owned(), change)),
+    )
+    .unwrap();
+    (changes, outcome)
+}
+
+fn untracked(root: &Path, index: &git_index::State) -> crate::Result<Vec<String>> {
+    let odb = git_odb::at(root.join(".git/objects"))?;
+    let mut buf = Vec::new();
+    let case = git_glob::pattern::Case::Sensitive;
+    let state = git_worktree::fs::cache::State::IgnoreStack(git_worktree::fs::cache::state::Ignore::new(
+        Default::default(),
+        git_attributes::MatchGroup::from_git_dir(root.join(".git"), None, &mut buf)?,
+        None,
+        case,
+    ));
+    let attribute_list = state.build_attribute_list(index, index.path_backing(), case);
+    let mut cache = git_worktree::fs::Cache::new(root, state, case, buf, attribute_list);
+
+    let mut untracked = Vec::new();
+    status::untracked(
+        index,
+        &mut cache,
+        |id, buf| odb.find_blob(id, buf),
+        &AtomicBool::default(),
+        |rela_path| untracked.push(rela_path.to_str_lossy().into_owned()),
+    )?;
+    Ok(untracked)
+}
+
+#[test]
+fn unchanged_worktree_has_no_changes() -> crate::Result {
+    let tmp = writable_fixture()?;
+    let index = index_at(tmp.path());
+    let (changes, outcome) = changes(tmp.path(), &index);
+    assert_eq!(changes, vec![]);
+    assert_eq!(outcome.entries_processed, 4);
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn modifications_deletions_and_type_changes_are_detected() -> crate::Result {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = writable_fixture()?;
+    let root = tmp.path();
+    let index = index_at(root);
+
+    std::fs::remove_file(root.join("empty"))?;
+    std::fs::write(root.join("dir/content"), "changed content")?;
+    std::fs::set_permissions(root.join("executable"), std::fs::Permissions::from_mode(0o644))?;
+    std::fs::remove_file(root.join("dir/sub-dir/symlink"))?;
+    std::fs::write(root.join("dir/sub-dir/symlink"), "../content")?;
+
+    let (changes, outcome) = changes(root, &index);
+    assert_eq!(
+        changes,
+        vec![
+            (
+                "dir/content".into(),
+                Change::Modification {
+                    executable_bit_changed: false,
+                    content_change: true
+                }
+            ),
+            ("dir/sub-dir/symlink".into(), Change::Type),
+            ("empty".into(), Change::Removed),
+            (
+                "executable".into(),
+                Change::Modification {
+                    executable_bit_changed: true,
+                    content_change: false
+                }
+            ),
+        ]
+    );
+    assert_eq!(outcome.entries_processed, 4);
+    Ok(())
+}
+
+#[test]
+fn untracked_files_honor_excludes_and_collapse_untracked_directories() -> crate::Result {
+    let tmp = writable_fixture()?;
+    let root = tmp.path();
+    let index = index_at(root);
+
+    std::fs::write(root.join(".gitignore"), "*.ignored\nignored-dir/\n")?;
+    std::fs::write(root.join("new"), "")?;
+    std::fs::write(root.join("dir/new"), "")?;
+    std::fs::write(root.join("dir/file.ignored"), "")?;
+    std::fs::create_dir_all(root.join("new-dir/sub"))?;
+    std::fs::write(root.join("new-dir/sub/file"), "")?;
+    std::fs::create_dir(root.join("ignored-dir"))?;
+    std::fs::write(root.join("ignored-dir/file"), "")?;
+    std::fs::create_dir(root.join("only-ignored-files"))?;
+    std::fs::write(root.join("only-ignored-files/file.ignored"), "")?;
+
+    assert_eq!(untracked(root, &index)?, [".gitignore", "dir/new", "new", "new-dir/"]);
+    Ok(())
+}
+
+#[test]
+fn submodules_are_not_untracked() -> crate::Result {
+    let tmp = git_testtools::scripted_fixture_writable("make_mixed_with_submodule.sh")?;
+    let root = tmp.path().join("with-submodule");
+    let index = index_at(&root);
+
+    std::fs::write(root.join("dir/new"), "")?;
+    assert_eq!(
+        untracked(&root, &index)?,
+        ["dir/new"],
+        "the checkouts of submodules are represented by a gitlink entry at their path, without entries within them"
+    );
+    Ok(())
+}
@@ -0,0 +1,343 @@
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail};
+use git_repository as git;
+use git_repository::{
+    bstr::{BString, ByteSlice},
+    hash::ObjectId,
+    interrupt,
+    objs::{tree::EntryMode, ObjectRef},
+    odb::pack,
+    prelude::{Find, FindExt},
+    protocol::{
+        handshake::Ref,
+        push::{response::RefStatus, Command},
+        receive_pack::{self, DelegateError, Request},
+    },
+    refs::{
+        transaction::{Change, LogChange, PreviousValue, RefEdit, RefLog},
+        Target,
+    },
+};
+
+pub struct Context {
+    /// If set, handle only a single request as needed by HTTP servers.
+    pub stateless_rpc: bool,
+    /// If set, only advertise references and capabilities.
+    pub advertise_refs: bool,
+    /// If set, don't use more than this amount of threads when indexing the received pack.
+    pub thread_limit: Option<usize>,
+}
+
+/// Receive objects and reference updates from a client speaking the pack protocol via `input` and `output`, and apply them
+/// to the repository at `repository_path`, similar to `git-receive-pack`.
+pub fn accept(
+    repository_path: impl AsRef<Path>,
+    input: impl io::Read,
+    output: impl io::Write,
+    Context {
+        stateless_rpc,
+        advertise_refs,
+        thread_limit,
+    }: Context,
+) -> anyhow::Result<()> {
+    let repo = git::open(repository_path.as_ref())?;
+    let options = receive_pack::Options {
+        object_hash: repo.object_hash(),
+        stateless_rpc,
+        advertise_refs,
+        ..Default::default()
+    };
+    let mut server = Server {
+        repo,
+        thread_limit,
+        quarantine: None,
+    };
+    let response = git::protocol::receive_pack(input, output, &mut server, &options)?;
+    if let Some(err) = response.unpack_error {
+        bail!("Could not receive the pack: {}", err)
+    }
+    Ok(())
+}
+
+struct Server {
+    repo: git::Repository,
+    thread_limit: Option<usize>,
+    /// The location of the objects received from the client until they are known to be complete.
+    quarantine: Option<Quarantine>,
+}
+
+/// A directory within the object database that holds the received pack until it is moved into place, and which is removed
+/// when dropped.
+struct Quarantine {
+    dir: PathBuf,
+    bundle: Option<pack::Bundle>,
+    outcome: pack::bundle::write::Outcome,
+}
+
+impl Drop for Quarantine {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.dir).ok();
+    }
+}
+
+impl receive_pack::Delegate for Server {
+    fn refs(&mut self) -> Result<Vec<Ref>, DelegateError> {
+        Ok(self.advertised_refs()?)
+    }
+
+    fn store_pack(&mut self, pack: &mut dyn io::BufRead, progress: &mut dyn io::Write) -> Result<(), DelegateError> {
+        Ok(self.receive_into_quarantine(pack, progress)?)
+    }
+
+    fn update_refs(&mut self, request: &Request, progress: &mut dyn io::Write) -> Vec<RefStatus> {
+        let mut errors: Vec<Option<String>> = request
+            .commands
+            .iter()
+            .map(|command| self.check(command).err())
+            .collect();
+        if request.atomic && errors.iter().any(Option::is_some) {
+            for error in errors.iter_mut().filter(|e| e.is_none()) {
+                *error = Some("atomic push failure".into());
+            }
+        }
+
+        let mut keep_path = None;
+        if errors.iter().any(Option::is_none) {
+            match self.migrate_quarantine() {
+                Ok(path) => keep_path = path,
+                Err(err) => {
+                    for error in errors.iter_mut().filter(|e| e.is_none()) {
+                        *error = Some(format!("failed to move objects into place: {}", err));
+                    }
+                }
+            }
+        }
+
+        let mut edits = Vec::new();
+        for (command, error) in request.commands.iter().zip(errors.iter_mut()) {
+            if error.is_some() {
+                continue;
+            }
+            match edit(command) {
+                Ok(edit) if request.atomic => edits.push(edit),
+                Ok(edit) => *error = self.repo.edit_reference(edit).err().map(|err| err.to_string()),
+                Err(err) => *error = Some(err.to_string()),
+            }
+        }
+        if !edits.is_empty() {
+            if let Err(err) = self.repo.edit_references(edits) {
+                for error in errors.iter_mut() {
+                    *error = Some(err.to_string());
+                }
+            }
+        }
+        if let Some(keep_path) = keep_path {
+            std::fs::remove_file(keep_path).ok();
+        }
+
+        let num_updated = errors.iter().filter(|e| e.is_none()).count();
+        writeln!(progress, "Updated {} of {} references", num_updated, errors.len()).ok();
+        request
+            .commands
+            .iter()
+            .zip(errors)
+            .map(|(command, error)| {
+                let error = error.map(|err| BString::from(err.replace('\n', " ")));
+                RefStatus::new(command.ref_name.as_bstr(), error.as_ref().map(|err| err.as_bstr()))
+            })
+            .collect()
+    }
+}
+
+impl Server {
+    /// Like `git`, we only advertise references, but not `HEAD`.
+    fn advertised_refs(&self) -> anyhow::Result<Vec<Ref>> {
+        let mut out = Vec::new();
+        for r in self.repo.references()?.all()? {
+            let mut r = r.map_err(|err| anyhow!(err))?;
+            let full_ref_name: BString = r.name().as_bstr().into();
+            match r.target() {
+                git::refs::TargetRef::Symbolic(target) => {
+                    let target = target.as_bstr().into();
+                    if let Ok(id) = r.peel_to_id_in_place() {
+                        out.push(Ref::Symbolic {
+                            full_ref_name,
+                            target,
+                            object: id.detach(),
+                        })
+                    }
+                }
+                git::refs::TargetRef::Peeled(id) => out.push(Ref::Direct {
+                    full_ref_name,
+                    object: id.to_owned(),
+                }),
+            }
+        }
+        Ok(out)
+    }
+
+    fn receive_into_quarantine(
+        &mut self,
+        pack: &mut dyn io::BufRead,
+        progress: &mut dyn io::Write,
+    ) -> anyhow::Result<()> {
+        let dir = self
+            .repo
+            .objects
+            .store_ref()
+            .path()
+            .join(format!("incoming-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("pack"))?;
+        let mut quarantine = Quarantine {
+            bundle: None,
+            outcome: pack::Bundle::write_to_directory(
+                pack,
+                Some(dir.join("pack")),
+                git::progress::Discard,
+                &interrupt::IS_INTERRUPTED,
+                Some(Box::new({
+                    let repo = self.repo.clone();
+                    move |oid, buf| repo.objects.find(oid, buf).ok()
+                })),
+                pack::bundle::write::Options {
+                    thread_limit: self.thread_limit,
+                    index_version: pack::index::Version::V2,
+                    iteration_mode: pack::data::input::Mode::Verify,
+                    object_hash: self.repo.object_hash(),
+                },
+            )
+            .map_err(|err| {
+                std::fs::remove_dir_all(&dir).ok();
+                err
+            })?,
+            dir,
+        };
+        quarantine.bundle = quarantine.outcome.to_bundle().transpose()?;
+        writeln!(progress, "Received {} objects", quarantine.outcome.index.num_objects)?;
+        self.quarantine = Some(quarantine);
+        Ok(())
+    }
+
+    /// Return an error message as `git` would produce it if `command` can't be performed.
+    fn check(&self, command: &Command) -> Result<(), String> {
+        let name = command.ref_name.as_bstr();
+        if !name.starts_with(b"refs/") || git::refs::FullName::try_from(name).is_err() {
+            return Err("funny refname".into());
+        }
+        if !self.repo.is_bare() {
+            if let Ok(Some(head)) = self.repo.head_name() {
+                if head.as_bstr() == name {
+                    return Err(if command.is_delete() {
+                        "deletion of the current branch prohibited"
+                    } else {
+                        "branch is currently checked out"
+                    }
+                    .into());
+                }
+            }
+        }
+        if !command.is_delete() && !self.is_connected(command.new_id).map_err(|err| err.to_string())? {
+            return Err("missing necessary objects".into());
+        }
+        Ok(())
+    }
+
+    /// Return true if all objects reachable from `tip` are present, assuming that objects in the repository are complete
+    /// and that only objects in the quarantine need to be traversed.
+    fn is_connected(&self, tip: ObjectId) -> anyhow::Result<bool> {
+        let bundle = self.quarantine.as_ref().and_then(|q| q.bundle.as_ref());
+        let mut buf = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = vec![tip];
+        while let Some(id) = queue.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            let data = match bundle {
+                Some(bundle) => bundle.find(id, &mut buf, &mut pack::cache::Never)?,
+                None => None,
+            };
+            match data {
+                Some((data, _location)) => match data.decode()? {
+                    ObjectRef::Commit(commit) => {
+                        queue.push(commit.tree());
+                        queue.extend(commit.parents());
+                    }
+                    ObjectRef::Tree(tree) => queue.extend(
+                        tree.entries
+                            .iter()
+                            .filter(|entry| entry.mode != EntryMode::Commit)
+                            .map(|entry| entry.oid.to_owned()),
+                    ),
+                    ObjectRef::Tag(tag) => queue.push(tag.target()),
+                    ObjectRef::Blob(_) => {}
+                },
+                None => {
+                    if !self.repo.objects.contains(id) {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Move the received pack into the object database, returning the path to its `.keep` file which prevents it from
+    /// being collected until references point to its objects.
+    fn migrate_quarantine(&mut self) -> io::Result<Option<PathBuf>> {
+        let mut quarantine = match self.quarantine.take() {
+            Some(quarantine) => quarantine,
+            None => return Ok(None),
+        };
+        quarantine.bundle = None;
+        let pack_dir = self.repo.objects.store_ref().path().join("pack");
+        let destination = |path: &Path| pack_dir.join(path.file_name().expect("pack files have names"));
+        let outcome = &quarantine.outcome;
+        // The index goes last so that readers who see it can also find the pack.
+        for path in [&outcome.keep_path, &outcome.data_path, &outcome.index_path]
+            .into_iter()
+            .flatten()
+        {
+            std::fs::rename(path, destination(path))?;
+        }
+        Ok(outcome.keep_path.as_deref().map(destination))
+    }
+}
+
+fn edit(command: &Command) -> anyhow::Result<RefEdit> {
+    let name = git::refs::FullName::try_from(command.ref_name.as_bstr())?;
+    let expected = if command.old_id.is_null() {
+        PreviousValue::MustNotExist
+    } else {
+        PreviousValue::MustExistAndMatch(Target::Peeled(command.old_id))
+    };
+    Ok(RefEdit {
+        change: if command.is_delete() {
+            Change::Delete {
+                expected: if command.old_id.is_null() {
+                    PreviousValue::MustExist
+                } else {
+                    expected
+                },
+                log: RefLog::AndReference,
+            }
+        } else {
+            Change::Update {
+                log: LogChange {
+                    mode: RefLog::AndReference,
+                    force_create_reflog: false,
+                    message: "push".into(),
+                },
+                expected,
+                new: Target::Peeled(command.new_id),
+            }
+        },
+        name,
+        deref: false,
+    })
+}
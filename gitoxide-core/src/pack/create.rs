@@ -3,10 +3,11 @@ use std::{ffi::OsStr, io, path::Path, str::FromStr, time::Instant};
 use anyhow::anyhow;
 use git_repository as git;
 use git_repository::{
+    config::tree::Pack,
     hash,
     hash::ObjectId,
     interrupt,
-    objs::bstr::ByteVec,
+    objs::bstr::{BStr, ByteSlice, ByteVec},
     odb::{pack, pack::FindExt},
     parallel::InOrderIter,
     prelude::Finalize,
@@ -92,6 +93,17 @@ pub struct Context<W> {
     /// This is a total, shared among all threads if `thread_limit` permits.
     /// Only used when known to be effective, namely when `expansion == ObjectExpansion::TreeDiff`.
     pub object_cache_size_in_bytes: usize,
+    /// The size of the sliding window of objects to try as base when delta-compressing objects, with values smaller than 2
+    /// disabling delta compression.
+    ///
+    /// If `None`, `pack.window` is used, or 10 if it is unset.
+    pub window: Option<usize>,
+    /// The maximum length of the delta chains created during delta compression.
+    ///
+    /// If `None`, `pack.depth` is used, or 50 if it is unset.
+    pub depth: Option<usize>,
+    /// If true, a reverse index is written along with the pack and its index, which only happens if there is an output directory.
+    pub reverse_index: bool,
     /// The output stream for use of additional information
     pub out: W,
 }
@@ -110,6 +122,9 @@ pub fn create<W, P>(
         statistics,
         pack_cache_size_in_bytes,
         object_cache_size_in_bytes,
+        window,
+        depth,
+        reverse_index,
         mut out,
     }: Context<W>,
 ) -> anyhow::Result<()>
@@ -119,9 +134,14 @@ where
     P::SubProgress: 'static,
 {
     let repo = git::discover(repository_path)?.into_sync();
+    let (mode, attributes) = {
+        let repo = repo.to_thread_local();
+        (delta_compression_mode(&repo, window, depth)?, attributes(&repo)?)
+    };
     progress.init(Some(2), progress::steps());
     let tips = tips.into_iter();
     let make_cancellation_err = || anyhow!("Cancelled by user");
+    let input_contains_ancestry = input.is_none();
     let (mut handle, input): (
         _,
         Box<dyn Iterator<Item = Result<ObjectId, input_iteration::Error>> + Send>,
@@ -206,7 +226,12 @@ where
                 });
             }
         }
-        let input_object_expansion = expansion.into();
+        let input_object_expansion = match expansion {
+            ObjectExpansion::TreeTraversal if input_contains_ancestry => {
+                pack::data::output::count::objects::ObjectExpansion::TreeContentsOfAncestry
+            }
+            expansion => expansion.into(),
+        };
         handle.prevent_pack_unload();
         handle.ignore_replacements = true;
         let (mut counts, count_stats) = if may_use_multiple_threads {
@@ -215,6 +240,7 @@ where
                 input,
                 progress,
                 &interrupt::IS_INTERRUPTED,
+                |path| try_delta(&attributes, path),
                 pack::data::output::count::objects::Options {
                     thread_limit,
                     chunk_size,
@@ -227,6 +253,7 @@ where
                 input,
                 progress,
                 &interrupt::IS_INTERRUPTED,
+                |path| try_delta(&attributes, path),
                 input_object_expansion,
             )?
         };
@@ -245,7 +272,7 @@ where
             progress,
             pack::data::output::entry::iter_from_counts::Options {
                 thread_limit,
-                mode: pack::data::output::entry::iter_from_counts::Mode::PackCopyAndBaseObjects,
+                mode,
                 allow_thin_pack: thin,
                 chunk_size,
                 version: Default::default(),
@@ -271,18 +298,23 @@ where
             (&mut sink_store, None)
         }
     };
+    let output_iter = pack::data::output::bytes::FromEntriesIter::new(
+        in_order_entries.by_ref().inspect(|e| {
+            if let Ok(entries) = e {
+                entries_progress.inc_by(entries.len())
+            }
+        }),
+        &mut pack_file,
+        num_objects as u32,
+        pack::data::Version::default(),
+        hash::Kind::default(),
+    );
     let mut interruptible_output_iter = interrupt::Iter::new(
-        pack::data::output::bytes::FromEntriesIter::new(
-            in_order_entries.by_ref().inspect(|e| {
-                if let Ok(entries) = e {
-                    entries_progress.inc_by(entries.len())
-                }
-            }),
-            &mut pack_file,
-            num_objects as u32,
-            pack::data::Version::default(),
-            hash::Kind::default(),
-        ),
+        if output_directory.is_some() {
+            output_iter.with_index_entries()
+        } else {
+            output_iter
+        },
         make_cancellation_err,
     );
     for io_res in interruptible_output_iter.by_ref() {
@@ -290,13 +322,37 @@ where
         write_progress.inc_by(written as usize);
     }
 
-    let hash = interruptible_output_iter
-        .into_inner()
-        .digest()
-        .expect("iteration is done");
+    let mut output_iter = interruptible_output_iter.into_inner();
+    let hash = output_iter.digest().expect("iteration is done");
+    let index_entries = output_iter.take_index_entries();
+    drop(output_iter);
     let pack_name = format!("{}.pack", hash);
-    if let (Some(pack_file), Some(dir)) = (named_tempfile_store.take(), output_directory) {
-        pack_file.persist(dir.as_ref().join(pack_name))?;
+    if let (Some(pack_file), Some(dir), Some(mut index_entries)) =
+        (named_tempfile_store.take(), output_directory, index_entries)
+    {
+        let dir = dir.as_ref();
+        pack_file.persist(dir.join(pack_name))?;
+        if reverse_index {
+            let mut reverse_index_file = tempfile::NamedTempFile::new_in(dir)?;
+            pack::index::File::write_reverse_index_to_stream(
+                &mut index_entries,
+                &hash,
+                io::BufWriter::new(&mut reverse_index_file),
+                hash::Kind::default(),
+            )?;
+            reverse_index_file.persist(dir.join(format!("{}.rev", hash)))?;
+        }
+        // The index is written last as its presence makes the pack visible to readers.
+        let mut index_file = tempfile::NamedTempFile::new_in(dir)?;
+        pack::index::File::write_entries_to_stream(
+            pack::index::Version::default(),
+            &mut index_entries,
+            &hash,
+            io::BufWriter::new(&mut index_file),
+            hash::Kind::default(),
+            progress.add_child("writing index"),
+        )?;
+        index_file.persist(dir.join(format!("{}.idx", hash)))?;
     } else {
         writeln!(out, "{}", pack_name)?;
     }
@@ -312,6 +368,82 @@ where
     Ok(())
 }
 
+/// Return the mode for creating pack entries, with delta compression being configured by `window` and `depth`,
+/// or `pack.window` and `pack.depth` respectively if these are `None`.
+pub(crate) fn delta_compression_mode(
+    repo: &git::Repository,
+    window: Option<usize>,
+    depth: Option<usize>,
+) -> anyhow::Result<pack::data::output::entry::iter_from_counts::Mode> {
+    let config = repo.config_snapshot();
+    let window = match window {
+        Some(window) => window,
+        None => config
+            .try_integer("pack.window")
+            .map(|value| Pack::WINDOW.try_into_usize(value))
+            .transpose()?
+            .unwrap_or(10),
+    };
+    let depth = match depth {
+        Some(depth) => depth,
+        None => config
+            .try_integer("pack.depth")
+            .map(|value| Pack::DEPTH.try_into_usize(value))
+            .transpose()?
+            .unwrap_or(50),
+    };
+    Ok(if window > 1 && depth > 0 {
+        pack::data::output::entry::iter_from_counts::Mode::PackCopyAndDeltaCompression { window, depth }
+    } else {
+        pack::data::output::entry::iter_from_counts::Mode::PackCopyAndBaseObjects
+    })
+}
+
+/// Load the attributes which may prevent objects from being delta-compressed from `info/attributes` and
+/// from the `.gitattributes` file at the root of the work tree, if there is one.
+pub(crate) fn attributes(repo: &git::Repository) -> std::io::Result<git::attrs::MatchGroup> {
+    let mut attributes = git::attrs::MatchGroup::default();
+    let mut buf = Vec::new();
+    for path in repo
+        .work_dir()
+        .map(|dir| dir.join(".gitattributes"))
+        .into_iter()
+        .chain(Some(repo.common_dir().join("info").join("attributes")))
+    {
+        attributes
+            .patterns
+            .extend(git::attrs::PatternList::from_file(path, None, true, &mut buf)?);
+    }
+    Ok(attributes)
+}
+
+/// Return `false` if the `delta` attribute is unset for `path`.
+pub(crate) fn try_delta(attributes: &git::attrs::MatchGroup, path: &BStr) -> bool {
+    let basename_pos = path.rfind_byte(b'/').map(|pos| pos + 1);
+    attributes
+        .patterns
+        .iter()
+        .rev()
+        .flat_map(|list| list.patterns.iter().rev())
+        .filter(|mapping| {
+            mapping.pattern.matches_repo_relative_path(
+                path,
+                basename_pos,
+                Some(false),
+                git::glob::pattern::Case::Sensitive,
+            )
+        })
+        .find_map(|mapping| match &mapping.value {
+            git::attrs::Value::Assignments(assignments) => assignments
+                .iter()
+                .rev()
+                .find(|assignment| assignment.name.as_str() == "delta")
+                .map(|assignment| !matches!(assignment.state, git::attrs::State::Unset)),
+            git::attrs::Value::MacroAttributes(_) => None,
+        })
+        .unwrap_or(true)
+}
+
 fn print(stats: Statistics, format: OutputFormat, out: impl std::io::Write) -> anyhow::Result<()> {
     match format {
         OutputFormat::Human => human_output(stats, out).map_err(Into::into),
@@ -335,6 +467,7 @@ fn human_output(
                 missing_objects,
                 objects_copied_from_pack,
                 ref_delta_objects,
+                delta_compressed_objects,
             },
     }: Statistics,
     mut out: impl std::io::Write,
@@ -355,10 +488,11 @@ fn human_output(
     #[rustfmt::skip]
     writeln!(
         out,
-        "\t{:<width$} {}\n\t{:<width$} {}\n\t{:<width$} {}\n\t{:<width$} {}",
+        "\t{:<width$} {}\n\t{:<width$} {}\n\t{:<width$} {}\n\t{:<width$} {}\n\t{:<width$} {}",
         "decoded and recompressed", decoded_and_recompressed_objects,
         "pack-to-pack copies", objects_copied_from_pack,
         "ref-delta-objects", ref_delta_objects,
+        "delta-compressed objects", delta_compressed_objects,
         "missing objects", missing_objects,
         width = width
     )?;
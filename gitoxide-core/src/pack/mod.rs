@@ -8,5 +8,15 @@ pub mod receive;
 #[cfg(any(feature = "async-client", feature = "blocking-client"))]
 pub use receive::receive;
 
+#[cfg(feature = "blocking-client")]
+pub mod send;
+#[cfg(feature = "blocking-client")]
+pub use send::send;
+
+#[cfg(feature = "blocking-client")]
+pub mod accept;
+#[cfg(feature = "blocking-client")]
+pub use accept::accept;
+
 pub mod create;
 pub use create::create;
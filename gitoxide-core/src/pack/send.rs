@@ -0,0 +1,598 @@
+use std::{
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+    io,
+    path::Path,
+};
+
+use anyhow::{anyhow, bail};
+use git_repository as git;
+use git_repository::{
+    bstr::{BString, ByteSlice},
+    hash::ObjectId,
+    interrupt,
+    objs::Kind,
+    odb::pack::{self, data::output},
+    parallel::InOrderIter,
+    prelude::{Find, HeaderExt},
+    protocol::{
+        handshake::Ref,
+        transport,
+        upload_pack::{self, DelegateError, Request, Shallow},
+    },
+};
+
+pub struct Context {
+    /// The protocol version to speak, or `None` to derive it from the `GIT_PROTOCOL` environment variable like `git` does.
+    pub protocol: Option<crate::net::Protocol>,
+    /// If set, handle only a single request as needed by HTTP servers.
+    pub stateless_rpc: bool,
+    /// If set, only advertise references and capabilities.
+    pub advertise_refs: bool,
+    /// If set, don't use more than this amount of threads when creating the pack.
+    pub thread_limit: Option<usize>,
+}
+
+/// Serve objects of the repository at `repository_path` to a client speaking the pack protocol via `input` and `output`,
+/// similar to `git-upload-pack`.
+pub fn send(
+    repository_path: impl AsRef<Path>,
+    input: impl io::Read,
+    output: impl io::Write,
+    Context {
+        protocol,
+        stateless_rpc,
+        advertise_refs,
+        thread_limit,
+    }: Context,
+) -> anyhow::Result<()> {
+    let repo = git::open(repository_path.as_ref())?;
+    let options = upload_pack::Options {
+        version: protocol.map_or_else(protocol_from_env, Into::into),
+        object_hash: repo.object_hash(),
+        stateless_rpc,
+        advertise_refs,
+        ..Default::default()
+    };
+    let mut server = Server {
+        repo,
+        thread_limit,
+        ready: HashSet::new(),
+        num_common_checked: None,
+    };
+    git::protocol::upload_pack(input, output, &mut server, &options)?;
+    Ok(())
+}
+
+/// Transports tell the server which protocol version to use with values like `version=2:other-key`.
+fn protocol_from_env() -> transport::Protocol {
+    match std::env::var("GIT_PROTOCOL") {
+        Ok(value) if value.split(':').any(|kv| kv == "version=2") => transport::Protocol::V2,
+        _ => transport::Protocol::V1,
+    }
+}
+
+struct Server {
+    repo: git::Repository,
+    thread_limit: Option<usize>,
+    /// Wants which are known to be reachable from a common commit.
+    ready: HashSet<ObjectId>,
+    /// The amount of common commits when readiness was last checked, as it can only change once more commits are common.
+    num_common_checked: Option<usize>,
+}
+
+impl upload_pack::Delegate for Server {
+    fn refs(&mut self) -> Result<Vec<Ref>, DelegateError> {
+        Ok(self.advertised_refs()?)
+    }
+
+    fn contains(&mut self, id: &git::hash::oid) -> bool {
+        self.repo.objects.contains(id)
+    }
+
+    fn is_ready(&mut self, wants: &[ObjectId], common: &[ObjectId]) -> bool {
+        if self.num_common_checked == Some(common.len()) {
+            return wants.iter().all(|want| self.ready.contains(want));
+        }
+        self.num_common_checked = Some(common.len());
+        let wants: Vec<_> = wants
+            .iter()
+            .filter(|want| !self.ready.contains(*want))
+            .copied()
+            .collect();
+        match self.reachable_from_common(&wants, common) {
+            Ok(ready) => {
+                let is_ready = ready.len() == wants.len();
+                self.ready.extend(ready);
+                is_ready
+            }
+            Err(_) => false,
+        }
+    }
+
+    fn shallow(&mut self, request: &Request) -> Result<Shallow, DelegateError> {
+        Ok(self.compute_shallow(request)?)
+    }
+
+    fn write_pack(
+        &mut self,
+        request: &Request,
+        common: &[ObjectId],
+        shallow: &[ObjectId],
+        progress: &mut dyn io::Write,
+        out: &mut dyn io::Write,
+    ) -> Result<(), DelegateError> {
+        Ok(self.create_pack(request, common, shallow, progress, out)?)
+    }
+}
+
+impl Server {
+    fn advertised_refs(&self) -> anyhow::Result<Vec<Ref>> {
+        let mut out = Vec::new();
+        let head = self.repo.head()?;
+        match &head.kind {
+            git::head::Kind::Symbolic(r) => {
+                if let Some(id) = r.target.try_id() {
+                    out.push(Ref::Symbolic {
+                        full_ref_name: "HEAD".into(),
+                        target: r.name.as_bstr().into(),
+                        object: id.to_owned(),
+                    })
+                }
+            }
+            git::head::Kind::Unborn(name) => out.push(Ref::Unborn {
+                full_ref_name: "HEAD".into(),
+                target: name.as_bstr().into(),
+            }),
+            git::head::Kind::Detached { target, .. } => out.push(Ref::Direct {
+                full_ref_name: "HEAD".into(),
+                object: *target,
+            }),
+        }
+
+        for r in self.repo.references()?.all()? {
+            let mut r = r.map_err(|err| anyhow!(err))?;
+            let full_ref_name: BString = r.name().as_bstr().into();
+            match r.target() {
+                git::refs::TargetRef::Symbolic(target) => {
+                    let target = target.as_bstr().into();
+                    if let Ok(id) = r.peel_to_id_in_place() {
+                        out.push(Ref::Symbolic {
+                            full_ref_name,
+                            target,
+                            object: id.detach(),
+                        })
+                    }
+                }
+                git::refs::TargetRef::Peeled(id) => {
+                    let id = id.to_owned();
+                    if self.repo.objects.header(id)?.kind() == Kind::Tag {
+                        out.push(Ref::Peeled {
+                            full_ref_name,
+                            tag: id,
+                            object: self.repo.find_object(id)?.peel_tags_to_end()?.id,
+                        })
+                    } else {
+                        out.push(Ref::Direct {
+                            full_ref_name,
+                            object: id,
+                        })
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Return those of `wants` whose commit has an ancestor in `common`.
+    ///
+    /// Like `git`, commits older than the oldest common commit aren't traversed as they can't lead to one.
+    fn reachable_from_common(&self, wants: &[ObjectId], common: &[ObjectId]) -> anyhow::Result<Vec<ObjectId>> {
+        let mut commits = Commits::new(&self.repo);
+        let mut common_commits = HashSet::new();
+        let mut cutoff = u32::MAX;
+        for id in common {
+            if self.repo.objects.header(id)?.kind() == Kind::Commit {
+                cutoff = cutoff.min(commits.info(*id)?.time);
+                common_commits.insert(*id);
+            }
+        }
+        // Commits which can't reach a common commit, shared by all wants.
+        let mut unreachable = HashSet::new();
+        let mut out = Vec::new();
+        'wants: for want in wants {
+            let commit = match self.peel_to_commit(*want)? {
+                Some(id) => id,
+                None => continue,
+            };
+            let mut visited = HashSet::new();
+            let mut stack = vec![commit];
+            while let Some(id) = stack.pop() {
+                if common_commits.contains(&id) {
+                    out.push(*want);
+                    continue 'wants;
+                }
+                if unreachable.contains(&id) || !visited.insert(id) {
+                    continue;
+                }
+                let info = commits.info(id)?;
+                if info.time >= cutoff {
+                    stack.extend(info.parents.iter().copied());
+                }
+            }
+            unreachable.extend(visited);
+        }
+        Ok(out)
+    }
+
+    fn peel_to_commit(&self, id: ObjectId) -> anyhow::Result<Option<ObjectId>> {
+        let object = self.repo.find_object(id)?.peel_tags_to_end()?;
+        Ok((object.kind == Kind::Commit).then_some(object.id))
+    }
+
+    fn parents(&self, id: ObjectId) -> anyhow::Result<Vec<ObjectId>> {
+        let commit = self.repo.find_object(id)?.try_into_commit()?;
+        let parents = commit.parent_ids().map(|id| id.detach()).collect();
+        Ok(parents)
+    }
+
+    fn compute_shallow(&self, request: &Request) -> anyhow::Result<Shallow> {
+        let mut out = Shallow::default();
+        if !request.is_deepening() {
+            return Ok(out);
+        }
+        let client_shallow: HashSet<_> = request.shallow.iter().copied().collect();
+        let mut excluded = HashSet::new();
+        for name in &request.deepen_not {
+            let id = match self.repo.try_find_reference(name.as_bstr())? {
+                Some(r) => r.into_fully_peeled_id()?.detach(),
+                None => bail!("deepen-not: {name:?} is not a reference"),
+            };
+            for info in self.repo.rev_walk(self.peel_to_commit(id)?).all()? {
+                excluded.insert(info?.id.detach());
+            }
+        }
+        let is_excluded = |id: &ObjectId| -> anyhow::Result<bool> {
+            Ok(excluded.contains(id)
+                || match request.deepen_since {
+                    Some(since) => {
+                        self.repo
+                            .find_object(*id)?
+                            .try_into_commit()?
+                            .time()?
+                            .seconds_since_unix_epoch
+                            < since
+                    }
+                    None => false,
+                })
+        };
+
+        let mut queue = VecDeque::new();
+        if request.deepen_relative && request.depth.is_some() {
+            queue.extend(request.shallow.iter().map(|id| (*id, 0)));
+        } else {
+            for want in &request.wants {
+                if let Some(id) = self.peel_to_commit(*want)? {
+                    if !is_excluded(&id)? {
+                        queue.push_back((id, 1));
+                    }
+                }
+            }
+        }
+        let mut seen = HashSet::new();
+        while let Some((id, depth)) = queue.pop_front() {
+            if !seen.insert(id) {
+                continue;
+            }
+            let parents = self.parents(id)?;
+            let is_boundary = match request.depth {
+                Some(max_depth) => depth >= max_depth,
+                None => {
+                    let mut any_excluded = false;
+                    for parent in &parents {
+                        any_excluded |= is_excluded(parent)?;
+                    }
+                    any_excluded
+                }
+            };
+            if is_boundary {
+                if !parents.is_empty() && !client_shallow.contains(&id) {
+                    out.shallow.push(id);
+                }
+                continue;
+            }
+            if client_shallow.contains(&id) && !parents.is_empty() {
+                out.unshallow.push(id);
+            }
+            queue.extend(parents.into_iter().map(|id| (id, depth + 1)));
+        }
+        Ok(out)
+    }
+
+    /// Find the commits reachable from `tips` that the client doesn't have, assuming it has all commits reachable from `common`
+    /// down to its `client_shallow` commits, similar to `git rev-list <tips> --not <common>`.
+    ///
+    /// Commits are traversed by commit time and the traversal stops once only commits the client has are left, so only
+    /// the history between both sides is seen. Commits in `shallow` are returned without traversing their parents.
+    fn select_commits(
+        &self,
+        tips: Vec<ObjectId>,
+        common: &[ObjectId],
+        client_shallow: &HashSet<ObjectId>,
+        shallow: &HashSet<ObjectId>,
+    ) -> anyhow::Result<Selection> {
+        let mut commits = Commits::new(&self.repo);
+        // Whether a seen commit is known to the client.
+        let mut known = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        fn enqueue(
+            commits: &mut Commits<'_>,
+            known: &mut HashMap<ObjectId, bool>,
+            queue: &mut BinaryHeap<(u32, ObjectId)>,
+            id: ObjectId,
+            is_known: bool,
+        ) -> anyhow::Result<()> {
+            match known.get_mut(&id) {
+                Some(previous) => *previous |= is_known,
+                None => {
+                    known.insert(id, is_known);
+                    queue.push((commits.info(id)?.time, id));
+                }
+            }
+            Ok(())
+        }
+        for id in common {
+            if self.repo.objects.header(id)?.kind() == Kind::Commit {
+                enqueue(&mut commits, &mut known, &mut queue, *id, true)?;
+            }
+        }
+        for id in tips {
+            enqueue(&mut commits, &mut known, &mut queue, id, false)?;
+        }
+
+        let mut out = Selection::default();
+        while let Some((_time, id)) = queue.pop() {
+            let is_known = known[&id];
+            if is_known {
+                // The client doesn't have the parents of its shallow commits.
+                if !client_shallow.contains(&id) {
+                    for parent in commits.info(id)?.parents.clone() {
+                        enqueue(&mut commits, &mut known, &mut queue, parent, true)?;
+                    }
+                }
+            } else if shallow.contains(&id) {
+                out.shallow_commits.push(id);
+            } else {
+                out.commits.push(id);
+                for parent in commits.info(id)?.parents.clone() {
+                    enqueue(&mut commits, &mut known, &mut queue, parent, false)?;
+                }
+            }
+            if queue.iter().all(|(_time, id)| known[id]) {
+                break;
+            }
+        }
+        // Clock skew may cause commits to be selected before they are found to be known.
+        out.commits.retain(|id| !known[id]);
+        out.shallow_commits.retain(|id| !known[id]);
+        for id in &out.commits {
+            for parent in &commits.info(*id)?.parents {
+                if known[parent] && !out.boundary.contains(parent) {
+                    out.boundary.push(*parent);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn create_pack(
+        &self,
+        request: &Request,
+        common: &[ObjectId],
+        shallow: &[ObjectId],
+        progress: &mut dyn io::Write,
+        out: &mut dyn io::Write,
+    ) -> anyhow::Result<()> {
+        let shallow: HashSet<_> = shallow.iter().copied().collect();
+        let client_shallow: HashSet<_> = request.shallow.iter().copied().collect();
+
+        let mut as_is = Vec::new();
+        let mut contents = Vec::new();
+        let mut tips = Vec::new();
+        for want in &request.wants {
+            let mut object = self.repo.find_object(*want)?;
+            loop {
+                match object.kind {
+                    Kind::Tag => {
+                        as_is.push(object.id);
+                        let target = object.to_tag_ref().target();
+                        object = self.repo.find_object(target)?;
+                    }
+                    Kind::Commit => {
+                        tips.push(object.id);
+                        break;
+                    }
+                    Kind::Tree | Kind::Blob => {
+                        contents.push(object.id);
+                        break;
+                    }
+                }
+            }
+        }
+
+        // Commits that aren't shallow on the client anymore need their parents, even though the client has them already.
+        for id in client_shallow.iter().filter(|id| !shallow.contains(*id)) {
+            tips.extend(self.parents(*id)?);
+        }
+
+        let Selection {
+            commits,
+            shallow_commits,
+            boundary,
+        } = self.select_commits(tips, common, &client_shallow, &shallow)?;
+        let mut exclude = HashSet::new();
+        for id in &boundary {
+            exclude.insert(*id);
+            exclude.insert(self.repo.find_object(*id)?.try_into_commit()?.tree_id()?.detach());
+        }
+        let sent: HashSet<_> = commits.iter().chain(&shallow_commits).copied().collect();
+        contents.extend(shallow_commits);
+        if request.include_tag {
+            for r in self.advertised_refs()? {
+                if let Ref::Peeled { tag, object, .. } = r {
+                    if sent.contains(&object) && !as_is.contains(&tag) {
+                        as_is.push(tag);
+                    }
+                }
+            }
+        }
+
+        let mut db = self.repo.objects.clone().into_arc()?;
+        db.prevent_pack_unload();
+        db.ignore_replacements = true;
+        let mut counts = Vec::new();
+        {
+            use output::count::objects::ObjectExpansion::*;
+            let mut ids = HashSet::new();
+            for (input, expansion) in [
+                (as_is, AsIs),
+                (contents, TreeContents),
+                (commits, TreeAdditionsComparedToAncestor),
+            ] {
+                let (batch, _) = output::count::objects_unthreaded(
+                    db.clone(),
+                    input.into_iter().map(Ok::<_, std::convert::Infallible>),
+                    git::progress::Discard,
+                    &interrupt::IS_INTERRUPTED,
+                    |_path| true,
+                    expansion,
+                )?;
+                counts.extend(
+                    batch
+                        .into_iter()
+                        .filter(|count| !exclude.contains(&count.id) && ids.insert(count.id)),
+                );
+            }
+        }
+        if let Some(filter) = &request.filter {
+            let filter = Filter::from_spec(filter.as_bstr())?;
+            let mut filtered = Vec::with_capacity(counts.len());
+            for count in counts {
+                let header = self.repo.objects.header(count.id)?;
+                if filter.allows(header.kind(), header.size()) || request.wants.contains(&count.id) {
+                    filtered.push(count);
+                }
+            }
+            counts = filtered;
+        }
+        let num_objects = counts.len();
+        writeln!(progress, "Counting objects: {num_objects}, done.")?;
+
+        let entries = InOrderIter::from(output::entry::iter_from_counts(
+            counts,
+            db,
+            git::progress::Discard,
+            output::entry::iter_from_counts::Options {
+                thread_limit: self.thread_limit,
+                mode: output::entry::iter_from_counts::Mode::PackCopyAndBaseObjects,
+                allow_thin_pack: false,
+                chunk_size: 50,
+                version: Default::default(),
+            },
+        ));
+        for res in output::bytes::FromEntriesIter::new(
+            entries,
+            out,
+            num_objects as u32,
+            pack::data::Version::V2,
+            self.repo.object_hash(),
+        ) {
+            res?;
+        }
+        writeln!(progress, "Total {num_objects} (delta 0), reused 0 (delta 0)")?;
+        Ok(())
+    }
+}
+
+/// The commits to send, as found by [`Server::select_commits()`].
+#[derive(Default)]
+struct Selection {
+    /// Commits to send along with the changes compared to their parents.
+    commits: Vec<ObjectId>,
+    /// Commits at the new shallow boundary of the client, which are sent with their entire tree.
+    shallow_commits: Vec<ObjectId>,
+    /// Parents of `commits` that the client has.
+    boundary: Vec<ObjectId>,
+}
+
+/// What's needed to traverse a commit.
+struct CommitInfo {
+    /// The time at which the commit was committed, in seconds since the unix epoch.
+    time: u32,
+    parents: Vec<ObjectId>,
+}
+
+/// A cache to decode each commit only once during a traversal.
+struct Commits<'repo> {
+    repo: &'repo git::Repository,
+    infos: HashMap<ObjectId, CommitInfo>,
+}
+
+impl<'repo> Commits<'repo> {
+    fn new(repo: &'repo git::Repository) -> Self {
+        Commits {
+            repo,
+            infos: HashMap::new(),
+        }
+    }
+
+    fn info(&mut self, id: ObjectId) -> anyhow::Result<&CommitInfo> {
+        if !self.infos.contains_key(&id) {
+            let commit = self.repo.find_object(id)?.try_into_commit()?;
+            let info = CommitInfo {
+                time: commit.time()?.seconds_since_unix_epoch,
+                parents: commit.parent_ids().map(|id| id.detach()).collect(),
+            };
+            self.infos.insert(id, info);
+        }
+        Ok(&self.infos[&id])
+    }
+}
+
+/// The object filters for partial clones we support.
+enum Filter {
+    /// `blob:none`
+    NoBlobs,
+    /// `blob:limit=<n>[kmg]`
+    BlobLimit(u64),
+    /// `tree:0`
+    NoTrees,
+}
+
+impl Filter {
+    fn from_spec(spec: &git::bstr::BStr) -> anyhow::Result<Self> {
+        Ok(match spec.as_bytes() {
+            b"blob:none" => Filter::NoBlobs,
+            b"tree:0" => Filter::NoTrees,
+            _ => match spec.strip_prefix(b"blob:limit=").and_then(|limit| limit.to_str().ok()) {
+                Some(limit) => {
+                    let (number, factor) = match limit.char_indices().last() {
+                        Some((pos, 'k')) => (&limit[..pos], 1024),
+                        Some((pos, 'm')) => (&limit[..pos], 1024 * 1024),
+                        Some((pos, 'g')) => (&limit[..pos], 1024 * 1024 * 1024),
+                        _ => (limit, 1),
+                    };
+                    Filter::BlobLimit(number.parse::<u64>()? * factor)
+                }
+                None => bail!("Unsupported filter: {spec:?}"),
+            },
+        })
+    }
+
+    fn allows(&self, kind: Kind, size: u64) -> bool {
+        match self {
+            Filter::NoBlobs => kind != Kind::Blob,
+            Filter::BlobLimit(limit) => kind != Kind::Blob || size <= *limit,
+            Filter::NoTrees => matches!(kind, Kind::Commit | Kind::Tag),
+        }
+    }
+}
@@ -30,5 +30,6 @@ pub mod mailmap;
 pub mod odb;
 pub mod remote;
 pub mod revision;
+pub mod status;
 pub mod tree;
 pub mod verify;
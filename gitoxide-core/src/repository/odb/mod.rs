@@ -216,3 +216,11 @@ pub fn entries(repo: git::Repository, format: OutputFormat, mut out: impl io::Wr
 
     Ok(())
 }
+
+mod reachable;
+
+pub mod prune;
+pub use prune::function::prune;
+
+pub mod repack;
+pub use repack::function::repack;
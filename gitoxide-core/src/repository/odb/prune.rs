@@ -0,0 +1,104 @@
+use crate::OutputFormat;
+
+pub struct Options {
+    pub format: OutputFormat,
+    /// If set, don't use more than this amount of threads.
+    /// Otherwise, usually use as many threads as there are logical cores.
+    pub thread_limit: Option<usize>,
+    /// The date before which unreachable objects expire and are deleted, like `2.weeks.ago`, `now` or `never`.
+    /// If `None`, `gc.pruneExpire` is used, which defaults to two weeks ago.
+    pub expire: Option<String>,
+    /// If true, don't delete anything but print the objects that would be deleted.
+    pub dry_run: bool,
+}
+
+pub const PROGRESS_RANGE: std::ops::RangeInclusive<u8> = 1..=2;
+
+pub(crate) mod function {
+    use std::{collections::HashSet, io};
+
+    use anyhow::bail;
+    use git_repository as git;
+    use git_repository::{interrupt, odb::HeaderExt, progress, Progress};
+
+    use super::Options;
+    use crate::{repository::odb::reachable, OutputFormat};
+
+    /// Delete all loose objects which aren't reachable from references, their reference logs, the index and worktrees of
+    /// `repo` and which expired according to `options`. Loose objects which are also stored in a pack are deleted as well,
+    /// no matter if they are reachable or not.
+    ///
+    /// The ids of unreachable objects that were deleted, or would be deleted in dry-run mode, are written to `out`.
+    pub fn prune(
+        repo: git::Repository,
+        mut progress: impl Progress,
+        mut out: impl io::Write,
+        mut err: impl io::Write,
+        Options {
+            format,
+            thread_limit,
+            expire,
+            dry_run,
+        }: Options,
+    ) -> anyhow::Result<()> {
+        if format != OutputFormat::Human {
+            bail!("JSON output isn't yet supported for pruning.");
+        }
+        let expire = reachable::expiry_date(&repo, expire)?;
+        let handle = reachable::handle(&repo)?;
+
+        progress.init(Some(2), progress::steps());
+        let tips = reachable::Tips::of(&repo)?;
+        let reachable: HashSet<_> = reachable::count(
+            &repo,
+            &handle,
+            &tips,
+            thread_limit,
+            |_path| true,
+            progress.add_child("counting"),
+        )?
+        .into_iter()
+        .map(|count| count.id)
+        .collect();
+        progress.inc();
+
+        let loose = git::odb::loose::Store::at(repo.objects.store_ref().path(), repo.object_hash());
+        let mut outcome = loose.prune(
+            |id| reachable.contains(&id.to_owned()),
+            progress.add_child("pruning"),
+            &interrupt::IS_INTERRUPTED,
+            git::odb::loose::prune::Options { expire, dry_run },
+        )?;
+        outcome.pruned.sort();
+
+        let mut packed = Vec::new();
+        for id in loose.iter() {
+            let id = id?;
+            if outcome.pruned.binary_search(&id).is_err()
+                && matches!(handle.header(id)?, git::odb::find::Header::Packed(_))
+            {
+                packed.push(id);
+            }
+        }
+        let mut num_packed = 0;
+        for id in packed {
+            if dry_run || loose.remove(id)? {
+                num_packed += 1;
+            }
+        }
+        progress.inc();
+
+        for id in &outcome.pruned {
+            writeln!(out, "{}", id)?;
+        }
+        if num_packed > 0 {
+            writeln!(
+                err,
+                "{} {} loose objects which are packed already",
+                if dry_run { "Would remove" } else { "Removed" },
+                num_packed
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use std::{convert::Infallible, sync::Arc, time::SystemTime};
+
+use anyhow::anyhow;
+use git_repository as git;
+use git_repository::{
+    bstr::{BStr, ByteSlice},
+    config::tree::Gc,
+    hash::ObjectId,
+    interrupt,
+    odb::pack,
+    prelude::Find,
+    progress, Progress,
+};
+
+pub(crate) type Handle = git::odb::Cache<git::odb::store::Handle<Arc<git::odb::Store>>>;
+
+/// Return a handle to the object database of `repo` suitable for counting and reading objects as they are stored.
+pub(crate) fn handle(repo: &git::Repository) -> std::io::Result<Handle> {
+    let mut handle = repo.objects.clone().into_arc()?;
+    handle.prevent_pack_unload();
+    handle.ignore_replacements = true;
+    Ok(handle)
+}
+
+/// Parse `expire` as expiry date for unreachable objects, or use `gc.pruneExpire` if it's `None`.
+pub(crate) fn expiry_date(repo: &git::Repository, expire: Option<String>) -> anyhow::Result<Option<u32>> {
+    Ok(match expire {
+        Some(date) => Gc::PRUNE_EXPIRE.try_into_expiry_date(date.as_bytes().as_bstr().into(), SystemTime::now())?,
+        None => repo.prune_expire()?,
+    })
+}
+
+/// The objects from which all reachable objects can be found.
+pub(crate) struct Tips {
+    /// All objects which are directly referenced by a repository and all of its worktrees, namely the targets of references
+    /// and `HEAD`, the entries of their reference logs, and the entries and cached trees of the index.
+    pub objects: Vec<ObjectId>,
+    /// The commits among `objects`, with annotated tags peeled.
+    pub commits: Vec<ObjectId>,
+}
+
+impl Tips {
+    /// Collect all tips of `repo`, ignoring objects which don't exist.
+    pub fn of(repo: &git::Repository) -> anyhow::Result<Self> {
+        let mut objects = Vec::new();
+        for reference in repo.references()?.all()? {
+            let reference = reference.map_err(|err| anyhow!(err))?;
+            objects.extend(reference.target().try_id().map(ToOwned::to_owned));
+            extend_with_log(&mut objects, reference.log_iter())?;
+        }
+        let mut worktrees = Vec::new();
+        for proxy in repo.worktrees()? {
+            worktrees.push(proxy.into_repo_with_possibly_inaccessible_worktree()?);
+        }
+        for repo in std::iter::once(repo).chain(worktrees.iter()) {
+            objects.extend(repo.head_id().ok().map(|id| id.detach()));
+            extend_with_log(&mut objects, repo.head()?.log_iter())?;
+            if repo.index_path().is_file() {
+                let index = repo.open_index()?;
+                objects.extend(
+                    index
+                        .entries()
+                        .iter()
+                        .filter(|entry| entry.mode != git::index::entry::Mode::COMMIT)
+                        .map(|entry| entry.id),
+                );
+                if let Some(tree) = index.tree() {
+                    extend_with_valid_trees(&mut objects, tree);
+                }
+            }
+        }
+        objects.sort();
+        objects.dedup();
+        objects.retain(|id| !id.is_null() && repo.objects.contains(id));
+
+        let mut commits = Vec::new();
+        for id in &objects {
+            let object = repo.find_object(*id)?.peel_tags_to_end()?;
+            if object.kind == git::object::Kind::Commit {
+                commits.push(object.id);
+            }
+        }
+        Ok(Tips { objects, commits })
+    }
+}
+
+/// Count all objects reachable from `tips` in `repo` using `handle`, with `try_delta(path)` determining if objects seen at `path`
+/// may be delta-compressed.
+pub(crate) fn count(
+    repo: &git::Repository,
+    handle: &Handle,
+    tips: &Tips,
+    thread_limit: Option<usize>,
+    try_delta: impl Fn(&BStr) -> bool + Sync,
+    mut progress: impl Progress,
+) -> anyhow::Result<Vec<pack::data::output::Count>> {
+    let commits = repo
+        .rev_walk(tips.commits.iter().cloned())
+        .all()?
+        .map(|info| info.map(|info| info.id.detach()))
+        .collect::<Result<Vec<_>, _>>()?;
+    progress.init(None, progress::count("objects"));
+    let (counts, _) = pack::data::output::count::objects(
+        handle.clone(),
+        tips.objects.iter().cloned().chain(commits).map(Ok::<_, Infallible>),
+        progress,
+        &interrupt::IS_INTERRUPTED,
+        try_delta,
+        pack::data::output::count::objects::Options {
+            thread_limit,
+            chunk_size: 50,
+            input_object_expansion: pack::data::output::count::objects::ObjectExpansion::TreeContentsOfAncestry,
+        },
+    )?;
+    Ok(counts)
+}
+
+fn extend_with_log(
+    objects: &mut Vec<ObjectId>,
+    mut log: git::reference::log::iter::Platform<'_, '_>,
+) -> anyhow::Result<()> {
+    if let Some(lines) = log.all()? {
+        for line in lines {
+            let line = line?;
+            objects.push(line.previous_oid());
+            objects.push(line.new_oid());
+        }
+    }
+    Ok(())
+}
+
+fn extend_with_valid_trees(objects: &mut Vec<ObjectId>, tree: &git::index::extension::Tree) {
+    if tree.num_entries.is_some() {
+        objects.push(tree.id);
+    }
+    for child in &tree.children {
+        extend_with_valid_trees(objects, child);
+    }
+}
@@ -0,0 +1,482 @@
+use crate::OutputFormat;
+
+pub struct Options {
+    pub format: OutputFormat,
+    /// If set, don't use more than this amount of threads.
+    /// Otherwise, usually use as many threads as there are logical cores.
+    pub thread_limit: Option<usize>,
+    /// If `Some(true)`, unreachable objects that didn't expire are written into a cruft pack along with their modification
+    /// times. If `Some(false)`, those of them which are packed are written as loose objects instead, with the modification
+    /// time of the pack they were stored in.
+    /// If `None`, `gc.cruftPacks` decides, which defaults to `false`.
+    pub cruft: Option<bool>,
+    /// The date before which unreachable objects expire and are dropped, like `2.weeks.ago`, `now` or `never`.
+    /// If `None`, `gc.pruneExpire` is used, which defaults to two weeks ago.
+    pub expire: Option<String>,
+    /// If true, write a reachability bitmap for the pack of reachable objects.
+    pub write_bitmap_index: bool,
+    /// If true, write a multi-pack index over all packs after repacking. It's always updated if one exists already.
+    pub write_multi_index: bool,
+}
+
+pub const PROGRESS_RANGE: std::ops::RangeInclusive<u8> = 1..=3;
+
+pub(crate) mod function {
+    use std::{
+        collections::{HashMap, HashSet},
+        convert::Infallible,
+        io,
+        path::{Path, PathBuf},
+        time::UNIX_EPOCH,
+    };
+
+    use anyhow::{anyhow, bail};
+    use git_repository as git;
+    use git_repository::{
+        config::tree::{Gc, Key},
+        hash::ObjectId,
+        interrupt,
+        odb::{pack, pack::FindExt},
+        parallel::InOrderIter,
+        prelude::{Finalize, Write},
+        progress, Progress,
+    };
+
+    use super::Options;
+    use crate::{
+        pack::create::{attributes, delta_compression_mode, try_delta},
+        repository::odb::reachable::{self, Handle},
+        OutputFormat,
+    };
+
+    /// Write all objects reachable from references, their reference logs, the index and worktrees of `repo` into a single
+    /// new pack, and handle unreachable objects according to `options`. Objects in packs marked with `.keep` or `.promisor`
+    /// files are left untouched.
+    ///
+    /// Afterwards, all packs that were superseded by the new ones are deleted, starting with their index to hide them from
+    /// new readers. Processes which have them mapped already can still read them as long as the platform allows to delete
+    /// files in use, otherwise these packs are reported to `err` and left in place.
+    ///
+    /// Finally, loose objects which are contained in the new packs or in kept packs are deleted. Unreachable loose objects
+    /// which aren't packed are left to pruning.
+    pub fn repack<P>(
+        repo: git::Repository,
+        mut progress: P,
+        mut out: impl io::Write,
+        mut err: impl io::Write,
+        Options {
+            format,
+            thread_limit,
+            cruft,
+            expire,
+            write_bitmap_index,
+            write_multi_index,
+        }: Options,
+    ) -> anyhow::Result<()>
+    where
+        P: Progress,
+        P::SubProgress: 'static,
+    {
+        if format != OutputFormat::Human {
+            bail!("JSON output isn't yet supported for repacking.");
+        }
+        let expire = reachable::expiry_date(&repo, expire)?;
+        let cruft = match cruft {
+            Some(cruft) => cruft,
+            None => repo
+                .config_snapshot()
+                .try_boolean(Gc::CRUFT_PACKS.logical_name().as_str())
+                .map(|value| Gc::CRUFT_PACKS.enrich_error(value))
+                .transpose()?
+                .unwrap_or(false),
+        };
+        let object_hash = repo.object_hash();
+        let objects_dir = repo.objects.store_ref().path().to_owned();
+        let pack_dir = objects_dir.join("pack");
+        let existing_packs = existing_packs(&pack_dir)?;
+        let kept_objects = {
+            let mut objects = HashSet::new();
+            for pack in existing_packs.iter().filter(|pack| pack.kept) {
+                let index = pack::index::File::at(&pack.index_path, object_hash)?;
+                objects.extend(index.iter().map(|entry| entry.oid));
+            }
+            objects
+        };
+        let mode = delta_compression_mode(&repo, None, None)?;
+        let attributes = attributes(&repo)?;
+
+        let handle = reachable::handle(&repo)?;
+        progress.init(Some(3), progress::steps());
+        let tips = reachable::Tips::of(&repo)?;
+        let mut counts = reachable::count(
+            &repo,
+            &handle,
+            &tips,
+            thread_limit,
+            |path| try_delta(&attributes, path),
+            progress.add_child("counting"),
+        )?;
+        let reachable: HashSet<_> = counts.iter().map(|count| count.id).collect();
+        counts.retain(|count| !kept_objects.contains(&count.id));
+        counts.sort_by_key(|count| count.id);
+        let num_reachable = counts.len();
+        let mut bitmap_progress = write_bitmap_index.then(|| progress.add_child("writing bitmap"));
+        let reachable_pack = write_pack(
+            &handle,
+            counts,
+            &pack_dir,
+            mode,
+            thread_limit,
+            object_hash,
+            &mut progress,
+            |hash, _entries, index_path| {
+                let bitmap_progress = match bitmap_progress.take() {
+                    Some(progress) => progress,
+                    None => return Ok(()),
+                };
+                let index = pack::index::File::at(index_path, object_hash)?;
+                let mut bitmap_file = tempfile::NamedTempFile::new_in(&pack_dir)?;
+                pack::bitmap::File::write_from_index(
+                    &index,
+                    handle.clone(),
+                    tips.commits.iter().cloned(),
+                    io::BufWriter::new(&mut bitmap_file),
+                    bitmap_progress,
+                    &interrupt::IS_INTERRUPTED,
+                    pack::bitmap::write::Options { object_hash },
+                )?;
+                bitmap_file.persist(pack_path(&pack_dir, hash, "bitmap"))?;
+                Ok(())
+            },
+        )?;
+        progress.inc();
+
+        let mut unreachable = HashMap::<ObjectId, u32>::new();
+        {
+            let mut add = |id: ObjectId, mtime: u32| {
+                if !reachable.contains(&id) && !kept_objects.contains(&id) {
+                    let entry = unreachable.entry(id).or_insert(mtime);
+                    *entry = (*entry).max(mtime);
+                }
+            };
+            for pack in existing_packs.iter().filter(|pack| !pack.kept) {
+                let index = pack::index::File::at(&pack.index_path, object_hash)?;
+                let pack_mtime = mtime(&pack.index_path.with_extension("pack"))?;
+                let mtimes_path = pack.index_path.with_extension("mtimes");
+                let mtimes = mtimes_path
+                    .is_file()
+                    .then(|| pack::mtimes::File::at(mtimes_path, object_hash))
+                    .transpose()?;
+                for index_position in 0..index.num_objects() {
+                    add(
+                        index.oid_at_index(index_position).to_owned(),
+                        mtimes
+                            .as_ref()
+                            .and_then(|mtimes| mtimes.mtime_at_index(index_position))
+                            .unwrap_or(pack_mtime),
+                    );
+                }
+            }
+            let loose = git::odb::loose::Store::at(&objects_dir, object_hash);
+            for id in loose.iter() {
+                let id = id?;
+                add(id, mtime(&loose.object_path(id))?);
+            }
+        }
+        let num_unreachable = unreachable.len();
+        unreachable.retain(|_, mtime| expire.map_or(true, |expire| *mtime >= expire));
+        let num_expired = num_unreachable - unreachable.len();
+        let mut unreachable: Vec<_> = unreachable.into_iter().collect();
+        unreachable.sort_by_key(|(id, _)| *id);
+
+        let (cruft_pack, num_loosened) = if cruft {
+            let (counts, _) = pack::data::output::count::objects_unthreaded(
+                handle.clone(),
+                unreachable.iter().map(|(id, _)| Ok::<_, Infallible>(*id)),
+                progress.add_child("counting unreachable"),
+                &interrupt::IS_INTERRUPTED,
+                |_path| true,
+                pack::data::output::count::objects::ObjectExpansion::AsIs,
+            )?;
+            let pack = write_pack(
+                &handle,
+                counts,
+                &pack_dir,
+                mode,
+                thread_limit,
+                object_hash,
+                &mut progress,
+                |hash, entries, _index_path| {
+                    let mut mtimes_file = tempfile::NamedTempFile::new_in(&pack_dir)?;
+                    pack::mtimes::File::write_to_stream(
+                        entries,
+                        |id| {
+                            unreachable
+                                .binary_search_by_key(&id, |(id, _)| id.as_ref())
+                                .map_or(0, |pos| unreachable[pos].1)
+                        },
+                        hash,
+                        io::BufWriter::new(&mut mtimes_file),
+                        object_hash,
+                    )?;
+                    mtimes_file.persist(pack_path(&pack_dir, hash, "mtimes"))?;
+                    Ok(())
+                },
+            )?;
+            (pack, 0)
+        } else {
+            let loose = git::odb::loose::Store::at(&objects_dir, object_hash);
+            let mut buf = Vec::new();
+            let mut num_loosened = 0;
+            for (id, mtime) in &unreachable {
+                if loose.contains(id) {
+                    continue;
+                }
+                let (object, _location) = handle.find(id, &mut buf)?;
+                let id = loose.write_buf(object.kind, object.data)?;
+                // Like git, keep the time of the pack so the object doesn't appear younger than it is and expires in time.
+                filetime::set_file_mtime(
+                    loose.object_path(id),
+                    filetime::FileTime::from_unix_time(*mtime as i64, 0),
+                )?;
+                num_loosened += 1;
+            }
+            (None, num_loosened)
+        };
+        progress.inc();
+
+        let new_packs: Vec<_> = reachable_pack.into_iter().chain(cruft_pack).collect();
+        let multi_index_path = pack_dir.join("multi-pack-index");
+        let update_multi_index = write_multi_index || multi_index_path.is_file();
+        if update_multi_index {
+            let index_paths: Vec<_> = new_packs
+                .iter()
+                .map(|hash| pack_path(&pack_dir, hash, "idx"))
+                .chain(
+                    existing_packs
+                        .iter()
+                        .filter(|pack| pack.kept)
+                        .map(|pack| pack.index_path.clone()),
+                )
+                .collect();
+            if index_paths.is_empty() {
+                if multi_index_path.is_file() {
+                    std::fs::remove_file(&multi_index_path)?;
+                }
+            } else {
+                crate::pack::multi_index::create(
+                    index_paths,
+                    multi_index_path,
+                    progress.add_child("writing multi-index"),
+                    &interrupt::IS_INTERRUPTED,
+                    object_hash,
+                )?;
+            }
+        }
+
+        // Release our own mappings of the packs we are about to delete.
+        drop(handle);
+        let mut removed_packs = Vec::new();
+        for pack in existing_packs.iter().filter(|pack| {
+            !pack.kept
+                && !new_packs
+                    .iter()
+                    .any(|hash| pack.index_path == pack_path(&pack_dir, hash, "idx"))
+        }) {
+            // The index goes first as without it, the pack won't be picked up anymore.
+            let mut removed = true;
+            for path in std::iter::once(pack.index_path.clone()).chain(
+                ["pack", "rev", "bitmap", "mtimes"]
+                    .iter()
+                    .map(|extension| pack.index_path.with_extension(extension))
+                    .filter(|path| path.is_file()),
+            ) {
+                if let Err(source) = std::fs::remove_file(&path) {
+                    writeln!(err, "Could not remove '{}': {}", path.display(), source)?;
+                    removed = false;
+                }
+            }
+            if removed {
+                removed_packs.push(pack.index_path.with_extension("pack"));
+            }
+        }
+
+        let packed_objects = {
+            let mut objects = kept_objects;
+            for hash in &new_packs {
+                let index = pack::index::File::at(pack_path(&pack_dir, hash, "idx"), object_hash)?;
+                objects.extend(index.iter().map(|entry| entry.oid));
+            }
+            objects
+        };
+        let loose = git::odb::loose::Store::at(&objects_dir, object_hash);
+        let mut packed_loose_objects = Vec::new();
+        for id in loose.iter() {
+            let id = id?;
+            if packed_objects.contains(&id) {
+                packed_loose_objects.push(id);
+            }
+        }
+        let mut num_removed_loose = 0;
+        for id in packed_loose_objects {
+            if loose.remove(id)? {
+                num_removed_loose += 1;
+            }
+        }
+        progress.inc();
+
+        let file_name = |hash: &ObjectId| {
+            pack_path(&pack_dir, hash, "pack")
+                .file_name()
+                .expect("present")
+                .to_string_lossy()
+                .into_owned()
+        };
+        match reachable_pack {
+            Some(hash) => writeln!(out, "wrote {} with {} objects", file_name(&hash), num_reachable)?,
+            None => writeln!(out, "no reachable objects to pack")?,
+        }
+        match cruft_pack {
+            Some(hash) => writeln!(
+                out,
+                "wrote cruft pack {} with {} unreachable objects",
+                file_name(&hash),
+                unreachable.len()
+            )?,
+            None if num_loosened > 0 => writeln!(out, "loosened {} unreachable objects", num_loosened)?,
+            None => {}
+        }
+        if num_expired > 0 {
+            writeln!(out, "{} unreachable objects expired", num_expired)?;
+        }
+        if update_multi_index {
+            writeln!(out, "updated multi-pack-index")?;
+        }
+        for path in removed_packs {
+            writeln!(out, "removed {}", path.display())?;
+        }
+        if num_removed_loose > 0 {
+            writeln!(out, "removed {} loose objects which are packed now", num_removed_loose)?;
+        }
+        Ok(())
+    }
+
+    struct ExistingPack {
+        index_path: PathBuf,
+        /// If true, the pack must not be touched.
+        kept: bool,
+    }
+
+    fn existing_packs(pack_dir: &Path) -> anyhow::Result<Vec<ExistingPack>> {
+        let mut packs = Vec::new();
+        if !pack_dir.is_dir() {
+            return Ok(packs);
+        }
+        for entry in std::fs::read_dir(pack_dir)? {
+            let index_path = entry?.path();
+            if index_path.extension().and_then(|ext| ext.to_str()) != Some("idx")
+                || !index_path.with_extension("pack").is_file()
+            {
+                continue;
+            }
+            packs.push(ExistingPack {
+                kept: index_path.with_extension("keep").is_file() || index_path.with_extension("promisor").is_file(),
+                index_path,
+            });
+        }
+        packs.sort_by(|a, b| a.index_path.cmp(&b.index_path));
+        Ok(packs)
+    }
+
+    fn pack_path(pack_dir: &Path, hash: &ObjectId, extension: &str) -> PathBuf {
+        pack_dir.join(format!("pack-{}.{}", hash, extension))
+    }
+
+    /// Return the modification time of the file at `path` in seconds since unix epoch.
+    fn mtime(path: &Path) -> io::Result<u32> {
+        Ok(std::fs::metadata(path)?
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs().try_into().unwrap_or(u32::MAX))
+            .unwrap_or_default())
+    }
+
+    /// Write a pack with all objects in `counts` into `pack_dir` and return its hash, or `None` if there are no objects.
+    ///
+    /// `before_index(hash, index_entries, index_path)` is called before the index at `index_path` is moved into place,
+    /// as the pack becomes visible to readers with it. It's not called if the pack existed already.
+    #[allow(clippy::too_many_arguments)]
+    fn write_pack<P>(
+        handle: &Handle,
+        counts: Vec<pack::data::output::Count>,
+        pack_dir: &Path,
+        mode: pack::data::output::entry::iter_from_counts::Mode,
+        thread_limit: Option<usize>,
+        object_hash: git::hash::Kind,
+        progress: &mut P,
+        before_index: impl FnOnce(&ObjectId, &mut [pack::index::write::Entry], &Path) -> anyhow::Result<()>,
+    ) -> anyhow::Result<Option<ObjectId>>
+    where
+        P: Progress,
+        P::SubProgress: 'static,
+    {
+        if counts.is_empty() {
+            return Ok(None);
+        }
+        let num_objects = counts.len();
+        let mut in_order_entries = InOrderIter::from(pack::data::output::entry::iter_from_counts(
+            counts,
+            handle.clone(),
+            progress.add_child("creating entries"),
+            pack::data::output::entry::iter_from_counts::Options {
+                thread_limit,
+                mode,
+                allow_thin_pack: false,
+                chunk_size: 1000,
+                version: Default::default(),
+            },
+        ));
+        let mut write_progress = progress.add_child("writing");
+        write_progress.init(None, progress::bytes());
+
+        let mut pack_file = tempfile::NamedTempFile::new_in(pack_dir)?;
+        let mut output_iter = interrupt::Iter::new(
+            pack::data::output::bytes::FromEntriesIter::new(
+                in_order_entries.by_ref(),
+                &mut pack_file,
+                num_objects as u32,
+                pack::data::Version::default(),
+                object_hash,
+            )
+            .with_index_entries(),
+            || anyhow!("Cancelled by user"),
+        );
+        for written in output_iter.by_ref() {
+            write_progress.inc_by(written?? as usize);
+        }
+        let mut output_iter = output_iter.into_inner();
+        let hash = output_iter.digest().expect("iteration is done");
+        let mut index_entries = output_iter.take_index_entries().expect("index entries were requested");
+        drop(output_iter);
+        in_order_entries.inner.finalize()?;
+
+        let index_path = pack_path(pack_dir, &hash, "idx");
+        if index_path.is_file() {
+            // The very same pack exists already, so there is nothing to do.
+            return Ok(Some(hash));
+        }
+        pack_file.persist(pack_path(pack_dir, &hash, "pack"))?;
+        let mut index_file = tempfile::NamedTempFile::new_in(pack_dir)?;
+        pack::index::File::write_entries_to_stream(
+            pack::index::Version::default(),
+            &mut index_entries,
+            &hash,
+            io::BufWriter::new(&mut index_file),
+            object_hash,
+            progress.add_child("writing index"),
+        )?;
+        before_index(&hash, &mut index_entries, index_file.path())?;
+        index_file.persist(index_path)?;
+        Ok(Some(hash))
+    }
+}
@@ -1,34 +1,75 @@
-use std::ffi::OsString;
-
-use anyhow::{bail, Context};
 use git_repository as git;
-use git_repository::prelude::ObjectIdExt;
 
 use crate::OutputFormat;
 
-pub fn list(
-    mut repo: git::Repository,
-    spec: OsString,
-    mut out: impl std::io::Write,
-    format: OutputFormat,
-) -> anyhow::Result<()> {
-    if format != OutputFormat::Human {
-        bail!("Only human output is currently supported");
-    }
-    repo.object_cache_size_if_unset(4 * 1024 * 1024);
+pub struct Options {
+    pub format: OutputFormat,
+    /// The order in which to list commits, or the order in which they are traversed if unset.
+    pub sorting: Option<git::traverse::commit::Sorting>,
+    pub reverse: bool,
+    /// Also list hidden parents of listed commits, prefixed with `-`.
+    pub boundary: bool,
+    /// Prefix commits with `<` if they are reachable from the left side of a symmetric difference, and with `>` otherwise.
+    pub left_right: bool,
+}
+
+pub(crate) mod function {
+    use std::ffi::OsString;
+
+    use anyhow::bail;
+    use git_repository as git;
+
+    use super::Options;
+    use crate::OutputFormat;
+
+    pub fn list(
+        mut repo: git::Repository,
+        specs: Vec<OsString>,
+        mut out: impl std::io::Write,
+        Options {
+            format,
+            sorting,
+            reverse,
+            boundary,
+            left_right,
+        }: Options,
+    ) -> anyhow::Result<()> {
+        if format != OutputFormat::Human {
+            bail!("Only human output is currently supported");
+        }
+        repo.object_cache_size_if_unset(4 * 1024 * 1024);
 
-    let spec = git::path::os_str_into_bstr(&spec)?;
-    let id = repo
-        .rev_parse_single(spec)
-        .context("Only single revisions are currently supported")?;
-    let commit_id = id
-        .object()?
-        .peel_to_kind(git::object::Kind::Commit)
-        .context("Need commitish as starting point")?
-        .id
-        .attach(&repo);
-    for commit in commit_id.ancestors().all()? {
-        writeln!(out, "{}", commit?.to_hex())?;
+        let specs = specs
+            .iter()
+            .map(|spec| -> anyhow::Result<_> {
+                let spec = git::path::os_str_into_bstr(spec)?;
+                Ok(repo.rev_parse(spec)?.detach())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut walk = repo.rev_walk_specs(specs)?;
+        if let Some(sorting) = sorting {
+            walk = walk.sorting(sorting);
+        }
+        if reverse {
+            walk = walk.reverse();
+        }
+        if boundary {
+            walk = walk.boundary();
+        }
+        for info in walk.all()? {
+            let info = info?;
+            let mark = if info.is_boundary {
+                "-"
+            } else if left_right {
+                match info.side {
+                    Some(git::revision::walk::Side::Left) => "<",
+                    Some(git::revision::walk::Side::Right) | None => ">",
+                }
+            } else {
+                ""
+            };
+            writeln!(out, "{mark}{}", info.id.to_hex())?;
+        }
+        Ok(())
     }
-    Ok(())
 }
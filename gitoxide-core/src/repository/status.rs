@@ -0,0 +1,55 @@
+use std::{io, sync::atomic::AtomicBool};
+
+use anyhow::{bail, Context};
+use git_repository as git;
+use git_repository::worktree::status::{Change, Item};
+
+use crate::OutputFormat;
+
+pub struct Options {
+    pub format: OutputFormat,
+    pub untracked: bool,
+}
+
+pub fn show(
+    repo: git::Repository,
+    mut out: impl io::Write,
+    mut err: impl io::Write,
+    should_interrupt: &AtomicBool,
+    Options { format, untracked }: Options,
+) -> anyhow::Result<()> {
+    if format != OutputFormat::Human {
+        bail!("JSON output isn't implemented yet");
+    }
+    let worktree = repo
+        .worktree()
+        .with_context(|| "Cannot show the status without a current worktree")?;
+
+    let mut write_err = None;
+    let outcome = worktree.status(untracked, should_interrupt, |item| {
+        if write_err.is_some() {
+            return;
+        }
+        let res = match item {
+            Item::Modification { rela_path, change, .. } => {
+                let status = match change {
+                    Change::Removed => "D",
+                    Change::Type => "T",
+                    Change::Modification { .. } => "M",
+                };
+                writeln!(out, " {} {}", status, rela_path)
+            }
+            Item::Untracked { rela_path } => writeln!(out, "?? {}", rela_path),
+        };
+        write_err = res.err();
+    })?;
+    if let Some(err) = write_err {
+        return Err(err.into());
+    }
+    writeln!(
+        err,
+        "{} entries processed, {} files hashed, {} racily clean",
+        outcome.entries_processed, outcome.files_hashed, outcome.racy_clean
+    )?;
+    Ok(())
+}
@@ -15,7 +15,9 @@ use gitoxide_core::pack::verify;
 
 use crate::{
     plumbing::{
-        options::{commit, config, credential, exclude, free, index, mailmap, odb, revision, tree, Args, Subcommands},
+        options::{
+            commit, config, credential, exclude, free, index, mailmap, odb, revision, status, tree, Args, Subcommands,
+        },
         show_progress,
     },
     shared::pretty::prepare_and_run,
@@ -374,6 +376,9 @@ pub fn main() -> Result<()> {
                     pack_cache_size_mb,
                     counting_threads,
                     object_cache_size_mb,
+                    window,
+                    depth,
+                    reverse_index,
                     output_directory,
                 } => {
                     let has_tips = !tips.is_empty();
@@ -392,6 +397,9 @@ pub fn main() -> Result<()> {
                                 nondeterministic_thread_count: nondeterministic_count.then_some(counting_threads),
                                 pack_cache_size_in_bytes: pack_cache_size_mb.unwrap_or(0) * 1_000_000,
                                 object_cache_size_in_bytes: object_cache_size_mb.unwrap_or(0) * 1_000_000,
+                                window,
+                                depth,
+                                reverse_index,
                                 statistics: if statistics { Some(format) } else { None },
                                 out,
                                 expansion: expansion.unwrap_or(if has_tips {
@@ -462,6 +470,38 @@ pub fn main() -> Result<()> {
                         )
                     },
                 ),
+                #[cfg(feature = "gitoxide-core-blocking-client")]
+                free::pack::Subcommands::Send {
+                    protocol,
+                    stateless_rpc,
+                    advertise_refs,
+                    directory,
+                } => core::pack::send(
+                    directory,
+                    std::io::stdin().lock(),
+                    std::io::stdout().lock(),
+                    core::pack::send::Context {
+                        protocol,
+                        stateless_rpc,
+                        advertise_refs,
+                        thread_limit,
+                    },
+                ),
+                #[cfg(feature = "gitoxide-core-blocking-client")]
+                free::pack::Subcommands::Accept {
+                    stateless_rpc,
+                    advertise_refs,
+                    directory,
+                } => core::pack::accept(
+                    directory,
+                    std::io::stdin().lock(),
+                    std::io::stdout().lock(),
+                    core::pack::accept::Context {
+                        stateless_rpc,
+                        advertise_refs,
+                        thread_limit,
+                    },
+                ),
                 free::pack::Subcommands::Explode {
                     check,
                     sink_compress,
@@ -647,14 +687,42 @@ pub fn main() -> Result<()> {
             },
         ),
         Subcommands::Revision(cmd) => match cmd {
-            revision::Subcommands::List { spec } => prepare_and_run(
+            revision::Subcommands::List {
+                topo_order,
+                date_order,
+                author_date_order,
+                reverse,
+                boundary,
+                left_right,
+                specs,
+            } => prepare_and_run(
                 "revision-list",
                 verbose,
                 progress,
                 progress_keep_open,
                 None,
                 move |_progress, out, _err| {
-                    core::repository::revision::list(repository(Mode::Lenient)?, spec, out, format)
+                    use git::traverse::commit::Sorting;
+                    core::repository::revision::list(
+                        repository(Mode::Lenient)?,
+                        specs,
+                        out,
+                        core::repository::revision::list::Options {
+                            format,
+                            sorting: if topo_order {
+                                Some(Sorting::TopoOrder)
+                            } else if date_order {
+                                Some(Sorting::DateOrder)
+                            } else if author_date_order {
+                                Some(Sorting::AuthorDateOrder)
+                            } else {
+                                None
+                            },
+                            reverse,
+                            boundary,
+                            left_right,
+                        },
+                    )
                 },
             ),
             revision::Subcommands::PreviousBranches => prepare_and_run(
@@ -807,6 +875,56 @@ pub fn main() -> Result<()> {
                 None,
                 move |_progress, out, err| core::repository::odb::info(repository(Mode::Strict)?, format, out, err),
             ),
+            odb::Subcommands::Prune { dry_run, expire } => prepare_and_run(
+                "odb-prune",
+                auto_verbose,
+                progress,
+                progress_keep_open,
+                core::repository::odb::prune::PROGRESS_RANGE,
+                move |progress, out, err| {
+                    core::repository::odb::prune(
+                        repository(Mode::Strict)?,
+                        progress,
+                        out,
+                        err,
+                        core::repository::odb::prune::Options {
+                            format,
+                            thread_limit,
+                            expire,
+                            dry_run,
+                        },
+                    )
+                },
+            ),
+            odb::Subcommands::Repack {
+                cruft,
+                no_cruft,
+                expire,
+                write_bitmap_index,
+                write_multi_index,
+            } => prepare_and_run(
+                "odb-repack",
+                auto_verbose,
+                progress,
+                progress_keep_open,
+                core::repository::odb::repack::PROGRESS_RANGE,
+                move |progress, out, err| {
+                    core::repository::odb::repack(
+                        repository(Mode::Strict)?,
+                        progress,
+                        out,
+                        err,
+                        core::repository::odb::repack::Options {
+                            format,
+                            thread_limit,
+                            cruft: (cruft || no_cruft).then_some(cruft),
+                            expire,
+                            write_bitmap_index,
+                            write_multi_index,
+                        },
+                    )
+                },
+            ),
         },
         Subcommands::Mailmap(cmd) => match cmd {
             mailmap::Subcommands::Entries => prepare_and_run(
@@ -855,6 +973,25 @@ pub fn main() -> Result<()> {
                 },
             ),
         },
+        Subcommands::Status(status::Platform { no_untracked }) => prepare_and_run(
+            "status",
+            verbose,
+            progress,
+            progress_keep_open,
+            None,
+            move |_progress, out, err| {
+                core::repository::status::show(
+                    repository(Mode::Lenient)?,
+                    out,
+                    err,
+                    &should_interrupt,
+                    core::repository::status::Options {
+                        format,
+                        untracked: !no_untracked,
+                    },
+                )
+            },
+        ),
         Subcommands::Index(cmd) => match cmd {
             index::Subcommands::FromTree {
                 force,
@@ -151,7 +151,24 @@ pub mod pack {
             /// Packs produced with this option enabled are only valid in transit, but not at rest.
             thin: bool,
 
-            /// The directory into which to write the pack file.
+            #[clap(long)]
+            /// The amount of objects to consider as base when delta-compressing an object, with values smaller than 2
+            /// disabling delta compression.
+            ///
+            /// If unset, `pack.window` is used, or 10 if it is not configured.
+            window: Option<usize>,
+
+            #[clap(long)]
+            /// The maximum length of the chains of newly created delta objects.
+            ///
+            /// If unset, `pack.depth` is used, or 50 if it is not configured.
+            depth: Option<usize>,
+
+            #[clap(long, requires = "output_directory")]
+            /// if set, a reverse index ('.rev' file) is written along with the pack and its index.
+            reverse_index: bool,
+
+            /// The directory into which to write the pack file along with its index.
             #[clap(long, short = 'o')]
             output_directory: Option<PathBuf>,
 
@@ -191,6 +208,44 @@ pub mod pack {
             /// If unset, they will be discarded.
             directory: Option<PathBuf>,
         },
+        /// Serve the objects of a repository to a client speaking the pack protocol on stdin and stdout, similar to `git-upload-pack`.
+        ///
+        /// Use it like `git clone --upload-pack='gix free pack send' <url>` to clone a repository through it.
+        #[cfg(feature = "gitoxide-core-blocking-client")]
+        Send {
+            /// The protocol version to speak. Valid values are 1 and 2.
+            ///
+            /// If unset, it's derived from the `GIT_PROTOCOL` environment variable like `git` does, defaulting to 1.
+            #[clap(long, short = 'p')]
+            protocol: Option<core::net::Protocol>,
+
+            /// Handle only a single request and don't advertise references, as needed by HTTP servers.
+            #[clap(long)]
+            stateless_rpc: bool,
+
+            /// Only advertise references and capabilities, then exit.
+            #[clap(long)]
+            advertise_refs: bool,
+
+            /// The repository whose objects to serve.
+            directory: PathBuf,
+        },
+        /// Receive objects and reference updates from a client speaking the pack protocol on stdin and stdout, similar to `git-receive-pack`.
+        ///
+        /// Use it like `git push --receive-pack='gix free pack accept' <url>` to push to a repository through it.
+        #[cfg(feature = "gitoxide-core-blocking-client")]
+        Accept {
+            /// Handle only a single request and don't advertise references, as needed by HTTP servers.
+            #[clap(long)]
+            stateless_rpc: bool,
+
+            /// Only advertise references and capabilities, then exit.
+            #[clap(long)]
+            advertise_refs: bool,
+
+            /// The repository to update.
+            directory: PathBuf,
+        },
         /// Dissolve a pack into its loose objects.
         ///
         /// Note that this effectively removes delta compression for an average compression of 2x, creating one file per object in the process.
@@ -108,6 +108,8 @@ pub enum Subcommands {
     Exclude(exclude::Subcommands),
     #[clap(subcommand)]
     Index(index::Subcommands),
+    /// Show the differences between the index and the worktree, similar to `git status --short`.
+    Status(status::Platform),
     /// Show which git configuration values are used or planned.
     ConfigTree,
     Config(config::Platform),
@@ -237,6 +239,39 @@ pub mod odb {
         /// Count and obtain information on all, possibly duplicate, objects in the database.
         #[clap(visible_alias = "statistics")]
         Stats,
+        /// Delete unreachable loose objects which expired, along with loose objects which are packed already.
+        Prune {
+            /// Don't delete anything, but print the unreachable objects which would be deleted.
+            #[clap(long, short = 'n')]
+            dry_run: bool,
+            /// The date before which unreachable objects expire and are deleted, like `2.weeks.ago`, `now` or `never`.
+            ///
+            /// If unset, `gc.pruneExpire` is used.
+            #[clap(long)]
+            expire: Option<String>,
+        },
+        /// Write all reachable objects into a single pack and remove the packs it supersedes.
+        Repack {
+            /// Write unreachable objects which didn't expire into a cruft pack instead of loose objects.
+            ///
+            /// If unset, `gc.cruftPacks` decides.
+            #[clap(long, conflicts_with = "no_cruft")]
+            cruft: bool,
+            /// Write unreachable objects which didn't expire as loose objects, even if `gc.cruftPacks` is set.
+            #[clap(long)]
+            no_cruft: bool,
+            /// The date before which unreachable objects expire and are dropped, like `2.weeks.ago`, `now` or `never`.
+            ///
+            /// If unset, `gc.pruneExpire` is used.
+            #[clap(long)]
+            expire: Option<String>,
+            /// Write a reachability bitmap for the pack of reachable objects.
+            #[clap(long, short = 'b')]
+            write_bitmap_index: bool,
+            /// Write a multi-pack index over all packs, which is always updated if it exists already.
+            #[clap(long, short = 'm')]
+            write_multi_index: bool,
+        },
     }
 }
 
@@ -325,9 +360,31 @@ pub mod revision {
     #[derive(Debug, clap::Subcommand)]
     #[clap(visible_alias = "rev", visible_alias = "r")]
     pub enum Subcommands {
-        /// List all commits reachable from the given rev-spec.
+        /// List all commits described by the given rev-specs, like `HEAD`, `main..feature`, `^main feature` or `a...b`.
         #[clap(visible_alias = "l")]
-        List { spec: std::ffi::OsString },
+        List {
+            /// Show no parent before all of its children, and avoid mixing commits of different lines of history.
+            #[clap(long, conflicts_with_all = ["date_order", "author_date_order"])]
+            topo_order: bool,
+            /// Show no parent before all of its children, and otherwise show commits by their commit date, newest first.
+            #[clap(long, conflicts_with = "author_date_order")]
+            date_order: bool,
+            /// Show no parent before all of its children, and otherwise show commits by their author date, newest first.
+            #[clap(long)]
+            author_date_order: bool,
+            /// Show the commits in reverse order.
+            #[clap(long)]
+            reverse: bool,
+            /// Also show hidden parents of shown commits, prefixed with `-`.
+            #[clap(long)]
+            boundary: bool,
+            /// Prefix commits reachable from the left side of a symmetric difference like `a...b` with `<`, and all others with `>`.
+            #[clap(long)]
+            left_right: bool,
+            /// rev-specs like `@`, `main..@`, `^main` or `main...@`.
+            #[clap(required = true)]
+            specs: Vec<std::ffi::OsString>,
+        },
         /// Provide the revision specification like `@~1` to explain.
         #[clap(visible_alias = "e")]
         Explain { spec: std::ffi::OsString },
@@ -352,6 +409,15 @@ pub mod revision {
     }
 }
 
+pub mod status {
+    #[derive(Debug, clap::Parser)]
+    pub struct Platform {
+        /// Don't show untracked files.
+        #[clap(long, short = 'n')]
+        pub no_untracked: bool,
+    }
+}
+
 pub mod exclude {
     use std::ffi::OsString;
 